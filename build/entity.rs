@@ -30,6 +30,7 @@ struct Field {
     bits: Vec<Bit>,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Clone, Debug)]
 #[serde(tag = "type", content = "default_value", rename_all = "snake_case")]
 enum Value {
@@ -269,12 +270,9 @@ pub fn build() -> anyhow::Result<TokenStream> {
         }
     });
 
-    let concrete_entity_names = concrete_entities
-        .iter()
-        .map(|(k, _)| ident(k))
-        .collect::<Vec<_>>();
+    let concrete_entity_names = concrete_entities.keys().map(ident).collect::<Vec<_>>();
 
-    let concrete_entity_structs = concrete_entities.iter().map(|(struct_name, _)| {
+    let concrete_entity_structs = concrete_entities.keys().map(|struct_name| {
         let fields = collect_all_fields(struct_name, &entities);
         let struct_name = ident(struct_name);
 
@@ -431,6 +429,62 @@ pub fn build() -> anyhow::Result<TokenStream> {
         }
     });
 
+    let name_arms = concrete_entities.iter().map(|(k, v)| {
+        let name = ident(k);
+        let typ = v.typ.as_ref().unwrap();
+
+        quote! {
+            Self::#name => #typ,
+        }
+    });
+
+    let from_name_arms = concrete_entities.iter().map(|(k, v)| {
+        let name = ident(k);
+        let typ = v.typ.as_ref().unwrap();
+
+        quote! {
+            #typ => Some(Self::#name),
+        }
+    });
+
+    // Setters for the tracked data shared by all entities.
+    let base_setters = entities["Entity"].fields.iter().map(|field| {
+        if field.bits.is_empty() {
+            let field_name = ident(&field.name);
+            let field_type = field.default_value.field_type();
+            let setter_name = ident(format!("set_{}", &field.name));
+            let doc = format!("Sets the `{}` field of any kind of entity.", field.name);
+
+            quote! {
+                #[doc = #doc]
+                pub fn #setter_name(&mut self, #field_name: impl Into<#field_type>) {
+                    match self {
+                        #(Self::#concrete_entity_names(e) => e.#setter_name(#field_name),)*
+                    }
+                }
+            }
+        } else {
+            field
+                .bits
+                .iter()
+                .map(|bit| {
+                    let bit_name = ident(&bit.name);
+                    let setter_name = ident(format!("set_{}", &bit.name));
+                    let doc = format!("Sets the `{}` flag of any kind of entity.", bit.name);
+
+                    quote! {
+                        #[doc = #doc]
+                        pub fn #setter_name(&mut self, #bit_name: bool) {
+                            match self {
+                                #(Self::#concrete_entity_names(e) => e.#setter_name(#bit_name),)*
+                            }
+                        }
+                    }
+                })
+                .collect()
+        }
+    });
+
     Ok(quote! {
         /// Contains a variant for each concrete entity type.
         #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -444,6 +498,23 @@ pub fn build() -> anyhow::Result<TokenStream> {
                     #(#translation_key_arms)*
                 }
             }
+
+            /// Returns the name of this entity kind without the `minecraft`
+            /// namespace, such as `"armor_stand"`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    #(#name_arms)*
+                }
+            }
+
+            /// Gets the entity kind with the given name, as returned by
+            /// [`Self::name`].
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    #(#from_name_arms)*
+                    _ => None,
+                }
+            }
         }
 
         pub enum TrackedData {
@@ -493,6 +564,23 @@ pub fn build() -> anyhow::Result<TokenStream> {
                 }
             }
 
+            /// Returns the flags shared by all entities, which are the tracked
+            /// data at index 0.
+            pub(crate) fn base_flags(&self) -> u8 {
+                match self {
+                    #(Self::#concrete_entity_names(e) => e.flags,)*
+                }
+            }
+
+            /// Returns whether the flags shared by all entities were modified.
+            pub(crate) fn base_flags_modified(&self) -> bool {
+                match self {
+                    #(Self::#concrete_entity_names(e) => e.__modified_flags & 1 != 0,)*
+                }
+            }
+
+            #(#base_setters)*
+
             pub(super) fn clear_modifications(&mut self) {
                 match self {
                     #(Self::#concrete_entity_names(e) => e.__modified_flags = 0,)*
@@ -8,6 +8,7 @@ use proc_macro2::{Ident, Span};
 mod block;
 mod entity;
 mod entity_event;
+mod version;
 
 pub fn main() -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed=extracted/");
@@ -16,6 +17,7 @@ pub fn main() -> anyhow::Result<()> {
         (entity::build as fn() -> _, "entity.rs"),
         (entity_event::build, "entity_event.rs"),
         (block::build, "block.rs"),
+        (version::build, "version.rs"),
     ];
 
     let out_dir = env::var_os("OUT_DIR").context("can't get OUT_DIR env var")?;
@@ -0,0 +1,40 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Debug)]
+struct Version {
+    game_version: String,
+    protocol_version: i32,
+    data_version: i32,
+}
+
+pub fn build() -> anyhow::Result<TokenStream> {
+    let version: Version = serde_json::from_str(include_str!("../extracted/version.json"))?;
+
+    let game_version = &version.game_version;
+    let protocol_version = version.protocol_version;
+    let data_version = version.data_version;
+
+    let assert_msg = format!(
+        "the extracted data is for protocol version {protocol_version} (Minecraft \
+         {game_version}), but valence_protocol targets a different version"
+    );
+
+    Ok(quote! {
+        /// The name of the Minecraft version the extracted entity and block
+        /// data was generated from.
+        ///
+        /// This is always the same version as [`VERSION_NAME`], which
+        /// `valence_protocol` targets.
+        pub const GAME_VERSION: &str = #game_version;
+
+        /// The [data version] of [`GAME_VERSION`], which is stored in saved
+        /// worlds to tell which version they were saved by.
+        ///
+        /// [data version]: https://minecraft.fandom.com/wiki/Data_version
+        pub const DATA_VERSION: i32 = #data_version;
+
+        const _: () = assert!(PROTOCOL_VERSION == #protocol_version, #assert_msg);
+    })
+}
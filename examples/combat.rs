@@ -3,15 +3,18 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use log::LevelFilter;
 use valence::block::{BlockPos, BlockState};
+use valence::chunk::UnloadedChunk;
 use valence::client::{
     default_client_event, ClientEvent, ClientId, GameMode, InteractWithEntityKind,
 };
 use valence::config::{Config, ServerListPing};
 use valence::dimension::DimensionId;
 use valence::entity::{EntityEvent, EntityId, EntityKind};
+use valence::favicon::Favicon;
 use valence::player_list::PlayerListId;
 use valence::server::{Server, SharedServer, ShutdownResult};
 use valence::text::{Color, TextFormat};
+use valence::transport::ListenAddress;
 use valence::{async_trait, Ticks};
 use vek::{Vec2, Vec3};
 
@@ -24,6 +27,7 @@ pub fn main() -> ShutdownResult {
     valence::start_server(
         Game {
             player_count: AtomicUsize::new(0),
+            favicon: Favicon::from_png(include_bytes!("../assets/favicon.png").as_slice()).unwrap(),
         },
         None,
     )
@@ -31,6 +35,7 @@ pub fn main() -> ShutdownResult {
 
 struct Game {
     player_count: AtomicUsize,
+    favicon: Favicon,
 }
 
 #[derive(Default)]
@@ -72,13 +77,15 @@ impl Config for Game {
         &self,
         _server: &SharedServer<Self>,
         _remote_addr: SocketAddr,
+        _local_addr: &ListenAddress,
         _protocol_version: i32,
     ) -> ServerListPing {
         ServerListPing::Respond {
             online_players: self.player_count.load(Ordering::SeqCst) as i32,
             max_players: MAX_PLAYERS as i32,
+            player_sample: Vec::new(),
             description: "Hello Valence!".color(Color::AQUA),
-            favicon_png: Some(include_bytes!("../assets/favicon.png")),
+            favicon: Some(&self.favicon),
         }
     }
 
@@ -90,22 +97,28 @@ impl Config for Game {
 
         // Create circular arena.
         let size = 2;
+        let section_count = server.shared.dimension(DimensionId::default()).height as usize / 16;
+        let floor_height = (SPAWN_POS.y - min_y + 1) as usize;
+        let empty = UnloadedChunk::filled([]);
+
         for chunk_z in -size - 2..size + 2 {
             for chunk_x in -size - 2..size + 2 {
-                let chunk = world.chunks.insert([chunk_x, chunk_z], ());
                 let r = -size..size;
                 if r.contains(&chunk_x) && r.contains(&chunk_z) {
-                    for z in 0..16 {
-                        for x in 0..16 {
-                            let block_x = chunk_x * 16 + x as i32;
-                            let block_z = chunk_z * 16 + z as i32;
-                            if f64::hypot(block_x as f64, block_z as f64) <= size as f64 * 16.0 {
-                                for y in 0..(SPAWN_POS.y - min_y + 1) as usize {
-                                    chunk.set_block_state(x, y, z, BlockState::STONE);
-                                }
-                            }
+                    let arena = UnloadedChunk::from_fn(section_count, |x, y, z| {
+                        let block_x = chunk_x * 16 + x as i32;
+                        let block_z = chunk_z * 16 + z as i32;
+                        if y < floor_height
+                            && f64::hypot(block_x as f64, block_z as f64) <= size as f64 * 16.0
+                        {
+                            BlockState::STONE
+                        } else {
+                            BlockState::AIR
                         }
-                    }
+                    });
+                    world.chunks.insert_with([chunk_x, chunk_z], &arena, ());
+                } else {
+                    world.chunks.insert_with([chunk_x, chunk_z], &empty, ());
                 }
             }
         }
@@ -148,10 +161,8 @@ impl Config for Game {
                 client.state.player = player_id;
                 client.state.extra_knockback = true;
 
-                client.spawn(world_id);
-                client.set_flat(true);
-                client.set_game_mode(GameMode::Survival);
-                client.teleport(
+                client.respawn(
+                    world_id,
                     [
                         SPAWN_POS.x as f64 + 0.5,
                         SPAWN_POS.y as f64 + 1.0,
@@ -160,6 +171,8 @@ impl Config for Game {
                     0.0,
                     0.0,
                 );
+                client.set_flat(true);
+                client.set_game_mode(GameMode::Survival);
                 client.set_player_list(server.state.clone());
 
                 if let Some(id) = &server.state {
@@ -12,10 +12,12 @@ use valence::config::{Config, ServerListPing};
 use valence::dimension::{Dimension, DimensionId};
 use valence::entity::types::Pose;
 use valence::entity::{EntityId, EntityKind, TrackedData};
+use valence::favicon::Favicon;
 use valence::player_list::PlayerListId;
 use valence::protocol::packets::s2c::play::SoundCategory;
 use valence::server::{Server, SharedServer, ShutdownResult};
 use valence::text::{Color, TextFormat};
+use valence::transport::ListenAddress;
 use valence::{async_trait, ident};
 use vek::Vec3;
 
@@ -28,6 +30,7 @@ pub fn main() -> ShutdownResult {
     valence::start_server(
         Game {
             player_count: AtomicUsize::new(0),
+            favicon: Favicon::from_png(include_bytes!("../assets/favicon.png").as_slice()).unwrap(),
         },
         ServerState {
             player_list: None,
@@ -40,6 +43,7 @@ pub fn main() -> ShutdownResult {
 
 struct Game {
     player_count: AtomicUsize,
+    favicon: Favicon,
 }
 
 struct ServerState {
@@ -93,13 +97,15 @@ impl Config for Game {
         &self,
         _server: &SharedServer<Self>,
         _remote_addr: SocketAddr,
+        _local_addr: &ListenAddress,
         _protocol_version: i32,
     ) -> ServerListPing {
         ServerListPing::Respond {
             online_players: self.player_count.load(Ordering::SeqCst) as i32,
             max_players: MAX_PLAYERS as i32,
+            player_sample: Vec::new(),
             description: "Hello Valence!".color(Color::AQUA),
-            favicon_png: Some(include_bytes!("../assets/favicon.png")),
+            favicon: Some(&self.favicon),
         }
     }
 
@@ -109,7 +115,7 @@ impl Config for Game {
 
         for chunk_z in -2..Integer::div_ceil(&(SIZE_Z as i32), &16) + 2 {
             for chunk_x in -2..Integer::div_ceil(&(SIZE_X as i32), &16) + 2 {
-                world.chunks.insert((chunk_x as i32, chunk_z as i32), ());
+                world.chunks.insert((chunk_x, chunk_z), ());
             }
         }
     }
@@ -147,9 +153,8 @@ impl Config for Game {
                     }
                 }
 
-                client.spawn(world_id);
+                client.respawn(world_id, spawn_pos, 0.0, 0.0);
                 client.set_flat(true);
-                client.teleport(spawn_pos, 0.0, 0.0);
                 client.set_player_list(server.state.player_list.clone());
 
                 if let Some(id) = &server.state.player_list {
@@ -187,30 +192,29 @@ impl Config for Game {
 
             while let Some(event) = default_client_event(client, player) {
                 match event {
-                    ClientEvent::Digging { position, .. } => {
+                    ClientEvent::Digging { position, .. }
                         if (0..SIZE_X as i32).contains(&position.x)
                             && (0..SIZE_Z as i32).contains(&position.z)
-                            && position.y == BOARD_Y
-                        {
-                            let index = position.x as usize + position.z as usize * SIZE_X;
-
-                            if !server.state.board[index] {
-                                client.play_sound(
-                                    ident!("minecraft:block.note_block.banjo"),
-                                    SoundCategory::Block,
-                                    Vec3::<i32>::from(position).as_(),
-                                    0.5f32,
-                                    1f32,
-                                );
-                            }
-
-                            server.state.board[index] = true;
+                            && position.y == BOARD_Y =>
+                    {
+                        let index = position.x as usize + position.z as usize * SIZE_X;
+
+                        if !server.state.board[index] {
+                            client.play_sound(
+                                ident!("minecraft:block.note_block.banjo"),
+                                SoundCategory::Block,
+                                Vec3::<i32>::from(position).as_(),
+                                0.5f32,
+                                1f32,
+                            );
                         }
+
+                        server.state.board[index] = true;
                     }
-                    ClientEvent::InteractWithBlock { hand, .. } => {
-                        if hand == Hand::Main {
-                            client.send_message("I said left click, not right click!".italic());
-                        }
+                    ClientEvent::InteractWithBlock {
+                        hand: Hand::Main, ..
+                    } => {
+                        client.send_message("I said left click, not right click!".italic());
                     }
                     _ => {}
                 }
@@ -223,7 +227,7 @@ impl Config for Game {
                     client.play_sound(
                         ident!("minecraft:block.note_block.pling"),
                         SoundCategory::Block,
-                        client.position().into(),
+                        client.position(),
                         0.5f32,
                         if sneaking { 0.5f32 } else { 1f32 },
                     );
@@ -9,9 +9,11 @@ use valence::client::{default_client_event, GameMode};
 use valence::config::{Config, ServerListPing};
 use valence::dimension::DimensionId;
 use valence::entity::{EntityId, EntityKind};
+use valence::favicon::Favicon;
 use valence::player_list::PlayerListId;
 use valence::server::{Server, SharedServer, ShutdownResult};
 use valence::text::{Color, TextFormat};
+use valence::transport::ListenAddress;
 use valence::util::to_yaw_and_pitch;
 use vek::{Mat3, Vec3};
 
@@ -24,6 +26,7 @@ pub fn main() -> ShutdownResult {
     valence::start_server(
         Game {
             player_count: AtomicUsize::new(0),
+            favicon: Favicon::from_png(include_bytes!("../assets/favicon.png").as_slice()).unwrap(),
         },
         ServerState {
             player_list: None,
@@ -34,6 +37,7 @@ pub fn main() -> ShutdownResult {
 
 struct Game {
     player_count: AtomicUsize,
+    favicon: Favicon,
 }
 
 struct ServerState {
@@ -63,13 +67,15 @@ impl Config for Game {
         &self,
         _server: &SharedServer<Self>,
         _remote_addr: SocketAddr,
+        _local_addr: &ListenAddress,
         _protocol_version: i32,
     ) -> ServerListPing {
         ServerListPing::Respond {
             online_players: self.player_count.load(Ordering::SeqCst) as i32,
             max_players: MAX_PLAYERS as i32,
+            player_sample: Vec::new(),
             description: "Hello Valence!".color(Color::AQUA),
-            favicon_png: Some(include_bytes!("../assets/favicon.png")),
+            favicon: Some(&self.favicon),
         }
     }
 
@@ -120,10 +126,8 @@ impl Config for Game {
                     }
                 }
 
-                client.spawn(world_id);
-                client.set_flat(true);
-                client.set_game_mode(GameMode::Creative);
-                client.teleport(
+                client.respawn(
+                    world_id,
                     [
                         SPAWN_POS.x as f64 + 0.5,
                         SPAWN_POS.y as f64 + 1.0,
@@ -132,6 +136,8 @@ impl Config for Game {
                     0.0,
                     0.0,
                 );
+                client.set_flat(true);
+                client.set_game_mode(GameMode::Creative);
                 client.set_player_list(server.state.player_list.clone());
 
                 if let Some(id) = &server.state.player_list {
@@ -8,10 +8,12 @@ use valence::client::{default_client_event, GameMode};
 use valence::config::{Config, ServerListPing};
 use valence::dimension::DimensionId;
 use valence::entity::{EntityId, EntityKind, TrackedData};
+use valence::favicon::Favicon;
 use valence::player_list::PlayerListId;
 use valence::server::{Server, SharedServer, ShutdownResult};
 use valence::spatial_index::RaycastHit;
 use valence::text::{Color, TextFormat};
+use valence::transport::ListenAddress;
 use valence::util::from_yaw_and_pitch;
 use vek::Vec3;
 
@@ -24,6 +26,7 @@ pub fn main() -> ShutdownResult {
     valence::start_server(
         Game {
             player_count: AtomicUsize::new(0),
+            favicon: Favicon::from_png(include_bytes!("../assets/favicon.png").as_slice()).unwrap(),
         },
         None,
     )
@@ -31,6 +34,7 @@ pub fn main() -> ShutdownResult {
 
 struct Game {
     player_count: AtomicUsize,
+    favicon: Favicon,
 }
 
 const MAX_PLAYERS: usize = 10;
@@ -61,13 +65,15 @@ impl Config for Game {
         &self,
         _server: &SharedServer<Self>,
         _remote_addr: SocketAddr,
+        _local_addr: &ListenAddress,
         _protocol_version: i32,
     ) -> ServerListPing {
         ServerListPing::Respond {
             online_players: self.player_count.load(Ordering::SeqCst) as i32,
             max_players: MAX_PLAYERS as i32,
+            player_sample: Vec::new(),
             description: "Hello Valence!".color(Color::AQUA),
-            favicon_png: Some(include_bytes!("../assets/favicon.png")),
+            favicon: Some(&self.favicon),
         }
     }
 
@@ -123,10 +129,8 @@ impl Config for Game {
                     }
                 }
 
-                client.spawn(world_id);
-                client.set_flat(true);
-                client.set_game_mode(GameMode::Creative);
-                client.teleport(
+                client.respawn(
+                    world_id,
                     [
                         SPAWN_POS.x as f64 + 0.5,
                         SPAWN_POS.y as f64 + 1.0,
@@ -135,6 +139,8 @@ impl Config for Game {
                     0.0,
                     0.0,
                 );
+                client.set_flat(true);
+                client.set_game_mode(GameMode::Creative);
                 client.set_player_list(server.state.clone());
 
                 if let Some(id) = &server.state {
@@ -173,7 +179,7 @@ impl Config for Game {
                 server
                     .entities
                     .get(hit.entity)
-                    .map_or(false, |e| e.kind() == EntityKind::Sheep)
+                    .is_some_and(|e| e.kind() == EntityKind::Sheep)
             };
 
             if let Some(hit) = world.spatial_index.raycast(origin, direction, only_sheep) {
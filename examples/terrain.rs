@@ -10,10 +10,11 @@ use valence::chunk::ChunkPos;
 use valence::client::{default_client_event, GameMode};
 use valence::config::{Config, ServerListPing};
 use valence::dimension::DimensionId;
-use valence::entity::{EntityId, EntityKind};
+use valence::favicon::Favicon;
 use valence::player_list::PlayerListId;
 use valence::server::{Server, SharedServer, ShutdownResult};
 use valence::text::{Color, TextFormat};
+use valence::transport::ListenAddress;
 use valence::util::chunks_in_view_distance;
 use vek::Lerp;
 
@@ -28,6 +29,7 @@ pub fn main() -> ShutdownResult {
     valence::start_server(
         Game {
             player_count: AtomicUsize::new(0),
+            favicon: Favicon::from_png(include_bytes!("../assets/favicon.png").as_slice()).unwrap(),
             density_noise: SuperSimplex::new().set_seed(seed),
             hilly_noise: SuperSimplex::new().set_seed(seed.wrapping_add(1)),
             stone_noise: SuperSimplex::new().set_seed(seed.wrapping_add(2)),
@@ -40,6 +42,7 @@ pub fn main() -> ShutdownResult {
 
 struct Game {
     player_count: AtomicUsize,
+    favicon: Favicon,
     density_noise: SuperSimplex,
     hilly_noise: SuperSimplex,
     stone_noise: SuperSimplex,
@@ -52,7 +55,7 @@ const MAX_PLAYERS: usize = 10;
 #[async_trait]
 impl Config for Game {
     type ServerState = Option<PlayerListId>;
-    type ClientState = EntityId;
+    type ClientState = ();
     type EntityState = ();
     type WorldState = ();
     /// If the chunk should stay loaded at the end of the tick.
@@ -68,13 +71,15 @@ impl Config for Game {
         &self,
         _server: &SharedServer<Self>,
         _remote_addr: SocketAddr,
+        _local_addr: &ListenAddress,
         _protocol_version: i32,
     ) -> ServerListPing {
         ServerListPing::Respond {
             online_players: self.player_count.load(Ordering::SeqCst) as i32,
             max_players: MAX_PLAYERS as i32,
+            player_sample: Vec::new(),
             description: "Hello Valence!".color(Color::AQUA),
-            favicon_png: Some(include_bytes!("../assets/favicon.png")),
+            favicon: Some(&self.favicon),
         }
     }
 
@@ -99,21 +104,10 @@ impl Config for Game {
                     return false;
                 }
 
-                match server
-                    .entities
-                    .insert_with_uuid(EntityKind::Player, client.uuid(), ())
-                {
-                    Some((id, _)) => client.state = id,
-                    None => {
-                        client.disconnect("Conflicting UUID");
-                        return false;
-                    }
-                }
-
-                client.spawn(world_id);
+                client.spawn_player_entity(());
+                client.respawn(world_id, [0.0, 200.0, 0.0], 0.0, 0.0);
                 client.set_flat(true);
                 client.set_game_mode(GameMode::Creative);
-                client.teleport([0.0, 200.0, 0.0], 0.0, 0.0);
                 client.set_player_list(server.state.clone());
 
                 if let Some(id) = &server.state {
@@ -135,12 +129,14 @@ impl Config for Game {
                 if let Some(id) = &server.state {
                     server.player_lists.get_mut(id).remove(client.uuid());
                 }
-                server.entities.remove(client.state);
 
                 return false;
             }
 
-            if let Some(entity) = server.entities.get_mut(client.state) {
+            if let Some(entity) = client
+                .player_entity()
+                .and_then(|id| server.entities.get_mut(id))
+            {
                 while default_client_event(client, entity).is_some() {}
             }
 
@@ -0,0 +1,22 @@
+package dev._00a.valence_extractor.extractors;
+
+import com.google.gson.JsonElement;
+import com.google.gson.JsonObject;
+import dev._00a.valence_extractor.Main;
+import net.minecraft.SharedConstants;
+
+public class Version implements Main.Extractor {
+    @Override
+    public String fileName() {
+        return "version.json";
+    }
+
+    @Override
+    public JsonElement extract() {
+        var versionJson = new JsonObject();
+        versionJson.addProperty("game_version", SharedConstants.getGameVersion().getName());
+        versionJson.addProperty("protocol_version", SharedConstants.getProtocolVersion());
+        versionJson.addProperty("data_version", SharedConstants.getGameVersion().getSaveVersion().getId());
+        return versionJson;
+    }
+}
@@ -12,16 +12,16 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::Semaphore;
-use valence::protocol::codec::Decoder;
-use valence::protocol::packets::c2s::handshake::{Handshake, HandshakeNextState};
-use valence::protocol::packets::c2s::login::{EncryptionResponse, LoginStart};
-use valence::protocol::packets::c2s::play::C2sPlayPacket;
-use valence::protocol::packets::c2s::status::{QueryPing, QueryRequest};
-use valence::protocol::packets::s2c::login::{LoginSuccess, S2cLoginPacket};
-use valence::protocol::packets::s2c::play::S2cPlayPacket;
-use valence::protocol::packets::s2c::status::{QueryPong, QueryResponse};
-use valence::protocol::packets::{DecodePacket, EncodePacket};
-use valence::protocol::{Encode, VarInt};
+use valence_protocol::codec::Decoder;
+use valence_protocol::packets::c2s::handshake::{Handshake, HandshakeNextState};
+use valence_protocol::packets::c2s::login::{EncryptionResponse, LoginStart};
+use valence_protocol::packets::c2s::play::C2sPlayPacket;
+use valence_protocol::packets::c2s::status::{QueryPing, QueryRequest};
+use valence_protocol::packets::s2c::login::{LoginSuccess, S2cLoginPacket};
+use valence_protocol::packets::s2c::play::S2cPlayPacket;
+use valence_protocol::packets::s2c::status::{QueryPong, QueryResponse};
+use valence_protocol::packets::{DecodePacket, EncodePacket};
+use valence_protocol::{Encode, VarInt};
 
 #[derive(Parser, Clone, Debug)]
 #[clap(author, version, about)]
@@ -0,0 +1,418 @@
+//! Keyframe animations for server-controlled entities.
+//!
+//! An [`Animation`] is a list of [`Keyframe`]s, each giving the position and
+//! rotation of an entity at a tick of the animation. Between keyframes, the
+//! position and rotation are interpolated with the [`Easing`] of the next
+//! keyframe. An [`Animator`] plays an animation on an entity by moving it
+//! every time [`Animator::update`] is called, which is meant to be done once
+//! per tick.
+//!
+//! ```
+//! use valence::animation::{Animation, Easing, Keyframe};
+//!
+//! // A platform moving up 5 blocks over 2 seconds and back.
+//! let platform = Animation::new()
+//!     .keyframe(Keyframe::new(0, [0.0, 64.0, 0.0]))
+//!     .keyframe(Keyframe::new(40, [0.0, 69.0, 0.0]).easing(Easing::EaseInOut))
+//!     .keyframe(Keyframe::new(80, [0.0, 64.0, 0.0]).easing(Easing::EaseInOut))
+//!     .looping(true);
+//!
+//! assert_eq!(platform.sample(20).unwrap().position.y, 66.5);
+//! assert_eq!(platform.sample(120).unwrap().position.y, 69.0);
+//! ```
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use vek::Vec3;
+
+use crate::config::Config;
+use crate::entity::{Entities, EntityId};
+use crate::Ticks;
+
+/// How the values between two keyframes are interpolated.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum Easing {
+    /// Moves at a constant speed.
+    #[default]
+    Linear,
+    /// Starts slowly and speeds up.
+    EaseIn,
+    /// Starts quickly and slows down.
+    EaseOut,
+    /// Starts and ends slowly.
+    EaseInOut,
+    /// Stays at the previous keyframe until the next keyframe is reached.
+    Step,
+}
+
+impl Easing {
+    /// Maps the progress `t` between two keyframes, in `0.0..=1.0`, to the
+    /// fraction of the distance covered.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::EaseOut => (t * PI / 2.0).sin(),
+            Easing::EaseInOut => (1.0 - (t * PI).cos()) / 2.0,
+            Easing::Step => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+/// The position and rotation of an entity at a tick of an [`Animation`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Keyframe {
+    tick: Ticks,
+    frame: Frame,
+    easing: Easing,
+}
+
+/// A sampled position and rotation of an [`Animation`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Frame {
+    pub position: Vec3<f64>,
+    /// The yaw in degrees.
+    pub yaw: f32,
+    /// The pitch in degrees.
+    pub pitch: f32,
+}
+
+impl Keyframe {
+    /// Creates a keyframe at the given tick of the animation with no rotation
+    /// and [linear](Easing::Linear) easing.
+    pub fn new(tick: Ticks, position: impl Into<Vec3<f64>>) -> Self {
+        Self {
+            tick,
+            frame: Frame {
+                position: position.into(),
+                yaw: 0.0,
+                pitch: 0.0,
+            },
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Sets the rotation at this keyframe in degrees.
+    #[must_use]
+    pub fn rotation(mut self, yaw: f32, pitch: f32) -> Self {
+        self.frame.yaw = yaw;
+        self.frame.pitch = pitch;
+        self
+    }
+
+    /// Sets how the values are interpolated from the previous keyframe to
+    /// this one.
+    #[must_use]
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Gets the tick of the animation this keyframe is at.
+    pub fn tick(&self) -> Ticks {
+        self.tick
+    }
+
+    /// Gets the position and rotation at this keyframe.
+    pub fn frame(&self) -> Frame {
+        self.frame
+    }
+}
+
+/// A path of an entity over time, described by [`Keyframe`]s.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Animation {
+    /// Sorted by tick.
+    keyframes: Vec<Keyframe>,
+    looping: bool,
+}
+
+impl Animation {
+    /// Creates an animation without keyframes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a keyframe. A keyframe at the same tick as an existing keyframe
+    /// replaces it.
+    #[must_use]
+    pub fn keyframe(mut self, keyframe: Keyframe) -> Self {
+        match self
+            .keyframes
+            .binary_search_by_key(&keyframe.tick, |k| k.tick)
+        {
+            Ok(idx) => self.keyframes[idx] = keyframe,
+            Err(idx) => self.keyframes.insert(idx, keyframe),
+        }
+        self
+    }
+
+    /// Sets whether the animation starts over after the last keyframe. A
+    /// looping animation should end where it starts.
+    #[must_use]
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Gets the keyframes sorted by tick.
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Returns `true` if the animation starts over after the last keyframe.
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Gets the tick of the last keyframe, or zero if there are no keyframes.
+    pub fn duration(&self) -> Ticks {
+        self.keyframes.last().map_or(0, |k| k.tick)
+    }
+
+    /// Gets the position and rotation at the given tick of the animation.
+    ///
+    /// Before the first keyframe, the first keyframe is returned. After the
+    /// last keyframe, the last keyframe is returned unless the animation is
+    /// looping. Returns `None` if there are no keyframes.
+    pub fn sample(&self, tick: Ticks) -> Option<Frame> {
+        let first = self.keyframes.first()?;
+        let duration = self.duration();
+
+        let tick = if self.looping && duration > 0 && tick > duration {
+            tick.rem_euclid(duration)
+        } else {
+            tick
+        };
+
+        let idx = self.keyframes.partition_point(|k| k.tick <= tick);
+        let (Some(prev), Some(next)) = (
+            idx.checked_sub(1).map(|i| &self.keyframes[i]),
+            self.keyframes.get(idx),
+        ) else {
+            return Some(if idx == 0 {
+                first.frame
+            } else {
+                self.keyframes[idx - 1].frame
+            });
+        };
+
+        let t = next
+            .easing
+            .apply((tick - prev.tick) as f64 / (next.tick - prev.tick) as f64);
+        let (a, b) = (prev.frame, next.frame);
+
+        Some(Frame {
+            position: a.position + (b.position - a.position) * t,
+            yaw: a.yaw + wrap_degrees(b.yaw - a.yaw) * t as f32,
+            pitch: a.pitch + (b.pitch - a.pitch) * t as f32,
+        })
+    }
+}
+
+/// Wraps an angle difference into `-180.0..180.0` so that yaws are
+/// interpolated the short way around.
+fn wrap_degrees(angle: f32) -> f32 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Plays an [`Animation`] on an entity.
+///
+/// The animator does not own the entity. Once the animation is over, the
+/// entity stays at the last keyframe.
+#[derive(Clone, Debug)]
+pub struct Animator {
+    animation: Arc<Animation>,
+    entity: EntityId,
+    tick: Ticks,
+    paused: bool,
+}
+
+impl Animator {
+    /// Creates an animator playing `animation` on `entity` from the start. The
+    /// animation can be shared between animators.
+    pub fn new(animation: impl Into<Arc<Animation>>, entity: EntityId) -> Self {
+        Self {
+            animation: animation.into(),
+            entity,
+            tick: 0,
+            paused: false,
+        }
+    }
+
+    /// Gets the animation being played.
+    pub fn animation(&self) -> &Arc<Animation> {
+        &self.animation
+    }
+
+    /// Gets the entity being animated.
+    pub fn entity(&self) -> EntityId {
+        self.entity
+    }
+
+    /// Gets the tick of the animation which is applied by the next
+    /// [update](Self::update).
+    pub fn tick(&self) -> Ticks {
+        self.tick
+    }
+
+    /// Jumps to the given tick of the animation.
+    pub fn seek(&mut self, tick: Ticks) {
+        self.tick = tick;
+    }
+
+    /// Returns `true` if the animation is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or resumes the animation. While paused, the entity keeps being
+    /// moved to the current tick of the animation.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Returns `true` if the last keyframe has been applied and the animation
+    /// is not looping.
+    pub fn is_finished(&self) -> bool {
+        !self.animation.is_looping() && self.tick > self.animation.duration()
+    }
+
+    /// Moves the entity to the current tick of the animation and advances
+    /// the animation by one tick unless it is paused.
+    ///
+    /// Returns `false` if the animation [is finished](Self::is_finished) or
+    /// the entity no longer exists, in which case the entity is not moved.
+    pub fn update<C: Config>(&mut self, entities: &mut Entities<C>) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+
+        let (Some(entity), Some(frame)) = (
+            entities.get_mut(self.entity),
+            self.animation.sample(self.tick),
+        ) else {
+            return false;
+        };
+
+        entity.set_position(frame.position);
+        entity.set_yaw(frame.yaw);
+        entity.set_head_yaw(frame.yaw);
+        entity.set_pitch(frame.pitch);
+
+        if !self.paused {
+            self.tick += 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::EntityKind;
+    use crate::testing::test_server;
+
+    #[test]
+    fn easing_endpoints() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::Step,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-9);
+        }
+
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+        assert_eq!(Easing::Step.apply(0.99), 0.0);
+    }
+
+    #[test]
+    fn sample_keyframes() {
+        let anim = Animation::new()
+            .keyframe(Keyframe::new(10, [10.0, 0.0, 0.0]).rotation(-170.0, 90.0))
+            .keyframe(Keyframe::new(0, [0.0, 0.0, 0.0]).rotation(170.0, 0.0))
+            .keyframe(Keyframe::new(20, [10.0, 10.0, 0.0]).easing(Easing::Step));
+
+        assert_eq!(anim.duration(), 20);
+        assert_eq!(anim.sample(-5).unwrap().position, Vec3::zero());
+
+        let mid = anim.sample(5).unwrap();
+        assert_eq!(mid.position, Vec3::new(5.0, 0.0, 0.0));
+        // The yaw wraps around instead of going through zero.
+        assert_eq!(mid.yaw, 180.0);
+        assert_eq!(mid.pitch, 45.0);
+
+        assert_eq!(anim.sample(19).unwrap().position, Vec3::new(10.0, 0.0, 0.0));
+        assert_eq!(
+            anim.sample(25).unwrap().position,
+            Vec3::new(10.0, 10.0, 0.0)
+        );
+
+        let anim = anim.looping(true);
+        assert_eq!(anim.sample(25), anim.sample(5));
+        assert_eq!(Animation::new().sample(0), None);
+    }
+
+    #[test]
+    fn animator_moves_entity() {
+        let mut server = test_server();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let (pig, entity) = s.entities.insert(EntityKind::Pig, ());
+        entity.set_world(world_id);
+
+        let animation = Animation::new()
+            .keyframe(Keyframe::new(0, [0.0, 100.0, 0.0]))
+            .keyframe(Keyframe::new(4, [4.0, 100.0, 0.0]).rotation(90.0, 0.0))
+            .keyframe(
+                Keyframe::new(6, [4.0, 102.0, 0.0])
+                    .rotation(90.0, 0.0)
+                    .easing(Easing::Step),
+            );
+        let mut animator = Animator::new(animation, pig);
+
+        let mut positions = Vec::new();
+        while animator.update(&mut server.server_mut().entities) {
+            server.tick();
+            let entity = server.server().entities.get(pig).unwrap();
+            positions.push((entity.position(), entity.yaw()));
+        }
+
+        assert!(animator.is_finished());
+        assert_eq!(
+            positions,
+            [
+                (Vec3::new(0.0, 100.0, 0.0), 0.0),
+                (Vec3::new(1.0, 100.0, 0.0), 22.5),
+                (Vec3::new(2.0, 100.0, 0.0), 45.0),
+                (Vec3::new(3.0, 100.0, 0.0), 67.5),
+                (Vec3::new(4.0, 100.0, 0.0), 90.0),
+                (Vec3::new(4.0, 100.0, 0.0), 90.0),
+                (Vec3::new(4.0, 102.0, 0.0), 90.0),
+            ]
+        );
+
+        // Paused animators keep the entity at the current tick.
+        animator.seek(2);
+        animator.set_paused(true);
+        assert!(animator.update(&mut server.server_mut().entities));
+        assert!(animator.update(&mut server.server_mut().entities));
+        assert_eq!(animator.tick(), 2);
+        let entity = server.server().entities.get(pig).unwrap();
+        assert_eq!(entity.position(), Vec3::new(2.0, 100.0, 0.0));
+    }
+}
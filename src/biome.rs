@@ -1,5 +1,6 @@
 //! Biome configuration and identification.
 
+use crate::entity::types::Particle;
 use crate::ident;
 use crate::ident::Ident;
 use crate::protocol::packets::s2c::play::Biome as BiomeRegistryBiome;
@@ -31,10 +32,16 @@ pub struct Biome {
     pub foliage_color: Option<u32>,
     pub grass_color: Option<u32>,
     pub grass_color_modifier: BiomeGrassColorModifier,
+    /// The music played while in this biome.
     pub music: Option<BiomeMusic>,
+    /// The sound event looped while in this biome, such as
+    /// `ambient.crimson_forest.loop`.
     pub ambient_sound: Option<Ident>,
+    /// A sound played at random while in this biome.
     pub additions_sound: Option<BiomeAdditionsSound>,
+    /// A sound played near players in dark places, like the cave ambience.
     pub mood_sound: Option<BiomeMoodSound>,
+    /// The particles floating around players in this biome.
     pub particle: Option<BiomeParticle>,
     // TODO: The following fields should be added if they can affect the appearance of the biome to
     // clients.
@@ -50,7 +57,7 @@ impl Biome {
     pub(crate) fn to_biome_registry_item(&self, id: i32) -> BiomeRegistryBiome {
         use crate::protocol::packets::s2c::play::{
             BiomeAdditionsSound, BiomeEffects, BiomeMoodSound, BiomeMusic, BiomeParticle,
-            BiomeParticleOptions, BiomeProperty,
+            BiomeProperty,
         };
 
         BiomeRegistryBiome {
@@ -98,13 +105,11 @@ impl Biome {
                         offset: m.offset,
                         block_search_extent: m.block_search_extent,
                     }),
+                    particle: self.particle.as_ref().map(|p| BiomeParticle {
+                        probability: p.probability,
+                        options: p.kind.to_nbt(),
+                    }),
                 },
-                particle: self.particle.as_ref().map(|p| BiomeParticle {
-                    probability: p.probability,
-                    options: BiomeParticleOptions {
-                        kind: p.kind.clone(),
-                    },
-                }),
             },
         }
     }
@@ -125,7 +130,13 @@ impl Default for Biome {
             music: None,
             ambient_sound: None,
             additions_sound: None,
-            mood_sound: None,
+            // The cave ambience heard in dark places in vanilla biomes.
+            mood_sound: Some(BiomeMoodSound {
+                sound: ident!("ambient.cave"),
+                tick_delay: 6000,
+                offset: 2.0,
+                block_search_extent: 8,
+            }),
             particle: None,
         }
     }
@@ -149,30 +160,110 @@ pub enum BiomeGrassColorModifier {
     None,
 }
 
-#[derive(Clone, Debug)]
+/// The music of a [`Biome`].
+#[derive(Clone, PartialEq, Debug)]
 pub struct BiomeMusic {
+    /// Whether the music stops the music that is already playing.
     pub replace_current_music: bool,
+    /// The sound event of the music, such as `music.nether.basalt_deltas`.
     pub sound: Ident,
+    /// The minimum number of ticks between two songs.
     pub min_delay: i32,
+    /// The maximum number of ticks between two songs.
     pub max_delay: i32,
 }
 
-#[derive(Clone, Debug)]
+/// A sound played at random in a [`Biome`].
+#[derive(Clone, PartialEq, Debug)]
 pub struct BiomeAdditionsSound {
+    /// The sound event, such as `ambient.basalt_deltas.additions`.
     pub sound: Ident,
+    /// The chance of the sound playing each tick, in `0.0..=1.0`.
     pub tick_chance: f64,
 }
 
-#[derive(Clone, Debug)]
+/// A sound played at a dark position near the player in a [`Biome`].
+#[derive(Clone, PartialEq, Debug)]
 pub struct BiomeMoodSound {
+    /// The sound event, such as `ambient.cave`.
     pub sound: Ident,
+    /// The number of ticks in the dark until the sound plays.
     pub tick_delay: i32,
+    /// The distance the sound is moved away from the player's eyes.
     pub offset: f64,
+    /// The radius of the cube around the player in which the dark position
+    /// is searched for.
     pub block_search_extent: i32,
 }
 
-#[derive(Clone, Debug)]
+/// The ambient particles of a [`Biome`], like the ash in basalt deltas.
+#[derive(Clone, PartialEq, Debug)]
 pub struct BiomeParticle {
+    /// The chance of a particle spawning at each block near the player each
+    /// tick, in `0.0..=1.0`. Vanilla biomes use values below `0.2`.
     pub probability: f32,
-    pub kind: Ident,
+    /// The particle to spawn. Particles with data, such as dust, keep their
+    /// color and size.
+    pub kind: Particle,
+}
+
+#[cfg(test)]
+mod tests {
+    use vek::Vec3;
+
+    use super::*;
+    use crate::nbt::{self, List, Value};
+
+    #[test]
+    fn biome_effects_encoded() {
+        let biome = Biome {
+            music: Some(BiomeMusic {
+                replace_current_music: true,
+                sound: ident!("music.nether.basalt_deltas"),
+                min_delay: 12000,
+                max_delay: 24000,
+            }),
+            additions_sound: Some(BiomeAdditionsSound {
+                sound: ident!("ambient.basalt_deltas.additions"),
+                tick_chance: 0.0111,
+            }),
+            particle: Some(BiomeParticle {
+                probability: 0.1,
+                kind: Particle::Dust {
+                    rgb: Vec3::new(1.0, 0.5, 0.0),
+                    scale: 2.0,
+                },
+            }),
+            ..Biome::default()
+        };
+
+        let nbt = nbt::to_compound(&biome.to_biome_registry_item(0)).unwrap();
+        let Value::Compound(element) = &nbt["element"] else {
+            panic!("biome element should be a compound");
+        };
+        let Value::Compound(effects) = &element["effects"] else {
+            panic!("biome effects should be a compound");
+        };
+
+        let Value::Compound(music) = &effects["music"] else {
+            panic!("music should be a compound");
+        };
+        assert_eq!(music["max_delay"], Value::Int(24000));
+
+        assert!(matches!(&effects["additions_sound"], Value::Compound(_)));
+        assert!(matches!(&effects["mood_sound"], Value::Compound(_)));
+
+        let Value::Compound(particle) = &effects["particle"] else {
+            panic!("the particle should be part of the effects");
+        };
+        let Value::Compound(options) = &particle["options"] else {
+            panic!("particle options should be a compound");
+        };
+        assert_eq!(options["type"], Value::String("minecraft:dust".into()));
+        assert_eq!(
+            options["color"],
+            Value::List(List::Float(vec![1.0, 0.5, 0.0]))
+        );
+        assert_eq!(options["scale"], Value::Float(2.0));
+    }
 }
@@ -8,7 +8,9 @@ use std::iter::FusedIterator;
 
 use anyhow::Context;
 
+pub use crate::block_placement::{BlockPlacement, PlacementContext};
 pub use crate::block_pos::BlockPos;
+pub use crate::block_schema::{BlockSchema, BlockSchemas, PaletteEntry};
 use crate::protocol::{Decode, Encode, VarInt};
 
 include!(concat!(env!("OUT_DIR"), "/block.rs"));
@@ -0,0 +1,472 @@
+use std::collections::{BTreeMap, HashSet};
+
+use rand::Rng;
+
+use crate::block::{BlockKind, BlockPos, BlockState, PropName, PropValue};
+use crate::block_placement::is_replaceable;
+use crate::chunk::Chunks;
+use crate::config::Config;
+use crate::server::SharedServer;
+use crate::world::WorldId;
+use crate::Ticks;
+
+/// The number of ticks before a block affected by gravity falls by one block.
+const FALL_DELAY: Ticks = 2;
+/// The number of ticks before water spreads by one block.
+const WATER_DELAY: Ticks = 5;
+
+/// Vanilla-like block behavior of a world, obtained with
+/// [`Chunks::physics_mut`]. Everything is disabled by default.
+///
+/// Blocks are updated through a queue of scheduled block ticks. Changing a
+/// block with [`Chunks::set_block_state`] or [`Chunks::set_block_state_by`]
+/// schedules a tick for the block and its six neighbors if they are affected
+/// by an enabled behavior. Changes made directly to a [`Chunk`] do not
+/// schedule ticks, but a tick can be scheduled with [`Self::schedule`].
+///
+/// In addition, every tick a number of random blocks in every chunk section
+/// are given to [`Config::random_tick`] if the
+/// [random tick speed](Self::set_random_tick_speed) is not zero.
+///
+/// The random and scheduled ticks of a world are run after [`Config::update`]
+/// and before clients are updated.
+///
+/// [`Chunk`]: crate::chunk::Chunk
+/// [`Config::update`]: crate::config::Config::update
+#[derive(Clone, Default, Debug)]
+pub struct BlockPhysics {
+    gravity: bool,
+    fluids: bool,
+    random_tick_speed: u32,
+    /// The number of times the scheduled ticks have been run.
+    tick: Ticks,
+    /// The positions to tick by the tick they are due.
+    queue: BTreeMap<Ticks, Vec<BlockPos>>,
+    scheduled: HashSet<BlockPos>,
+}
+
+impl BlockPhysics {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if sand, red sand, gravel, and concrete powder fall
+    /// when there is air, a liquid, or a replaceable block such as grass
+    /// below them.
+    pub fn gravity(&self) -> bool {
+        self.gravity
+    }
+
+    /// Sets whether sand, red sand, gravel, and concrete powder fall. Falling
+    /// blocks move down by one block every two ticks and replace the block
+    /// they fall into.
+    pub fn set_gravity(&mut self, gravity: bool) {
+        self.gravity = gravity;
+    }
+
+    /// Returns `true` if water spreads.
+    pub fn fluids(&self) -> bool {
+        self.fluids
+    }
+
+    /// Sets whether water spreads. Water flows down and spreads up to seven
+    /// blocks horizontally from a source or falling water, one block every
+    /// five ticks. Flowing water which is no longer fed is removed. Lava does
+    /// not flow.
+    pub fn set_fluids(&mut self, fluids: bool) {
+        self.fluids = fluids;
+    }
+
+    /// Gets the number of random ticks per chunk section per tick.
+    pub fn random_tick_speed(&self) -> u32 {
+        self.random_tick_speed
+    }
+
+    /// Sets the number of blocks picked at random in every 16x16x16 section of
+    /// every chunk each tick, which are passed to [`Config::random_tick`]. The
+    /// default is zero, which disables random ticks. The default of vanilla
+    /// is 3.
+    pub fn set_random_tick_speed(&mut self, speed: u32) {
+        self.random_tick_speed = speed;
+    }
+
+    /// Gets the next growth stage of crops such as wheat, carrots, or nether
+    /// wart, or `None` if `block` is not a crop or is fully grown.
+    ///
+    /// This is the default implementation of [`Config::random_tick`].
+    pub fn grow_crop(block: BlockState) -> Option<BlockState> {
+        if !matches!(
+            block.to_kind(),
+            BlockKind::Wheat
+                | BlockKind::Carrots
+                | BlockKind::Potatoes
+                | BlockKind::Beetroots
+                | BlockKind::MelonStem
+                | BlockKind::PumpkinStem
+                | BlockKind::NetherWart
+                | BlockKind::SweetBerryBush
+                | BlockKind::Cocoa
+        ) {
+            return None;
+        }
+
+        let age = block.get(PropName::Age)?.to_u16()?;
+        let grown = block.set(PropName::Age, PropValue::from_u16(age + 1)?);
+        (grown != block).then_some(grown)
+    }
+
+    /// Schedules the block at `pos` to be ticked after `delay` ticks, or the
+    /// next time scheduled ticks are run if `delay` is zero or less. Nothing
+    /// happens if the block is already scheduled.
+    ///
+    /// A tick for a block with no enabled behavior does nothing.
+    pub fn schedule(&mut self, pos: impl Into<BlockPos>, delay: Ticks) {
+        let pos = pos.into();
+        if self.scheduled.insert(pos) {
+            self.queue
+                .entry(self.tick + delay.max(1))
+                .or_default()
+                .push(pos);
+        }
+    }
+
+    /// Returns the number of scheduled block ticks.
+    pub fn scheduled(&self) -> usize {
+        self.scheduled.len()
+    }
+
+    /// Removes all scheduled block ticks.
+    pub fn clear_scheduled(&mut self) {
+        self.queue.clear();
+        self.scheduled.clear();
+    }
+
+    /// Gets the delay of a tick for `block`, or `None` if it has no enabled
+    /// behavior.
+    fn delay(&self, block: BlockState) -> Option<Ticks> {
+        if self.gravity && falls(block) {
+            Some(FALL_DELAY)
+        } else if self.fluids && block.to_kind() == BlockKind::Water {
+            Some(WATER_DELAY)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the tick and returns the positions which are due.
+    fn take_due(&mut self) -> Vec<BlockPos> {
+        self.tick += 1;
+
+        let later = self.queue.split_off(&(self.tick + 1));
+        let due: Vec<_> = std::mem::replace(&mut self.queue, later)
+            .into_values()
+            .flatten()
+            .collect();
+
+        for pos in &due {
+            self.scheduled.remove(pos);
+        }
+        due
+    }
+}
+
+impl<C: Config> Chunks<C> {
+    /// Schedules ticks for a changed block and its neighbors.
+    pub(crate) fn schedule_neighbor_ticks(&mut self, pos: BlockPos) {
+        if !self.physics().gravity && !self.physics().fluids {
+            return;
+        }
+
+        for pos in [
+            pos,
+            BlockPos::new(pos.x, pos.y - 1, pos.z),
+            BlockPos::new(pos.x, pos.y + 1, pos.z),
+            BlockPos::new(pos.x - 1, pos.y, pos.z),
+            BlockPos::new(pos.x + 1, pos.y, pos.z),
+            BlockPos::new(pos.x, pos.y, pos.z - 1),
+            BlockPos::new(pos.x, pos.y, pos.z + 1),
+        ] {
+            if let Some(delay) = self
+                .get_block_state(pos)
+                .and_then(|block| self.physics().delay(block))
+            {
+                self.physics_mut().schedule(pos, delay);
+            }
+        }
+    }
+
+    /// Passes random blocks of every chunk section to [`Config::random_tick`]
+    /// and sets the blocks it returns.
+    pub(crate) fn update_random_ticks(&mut self, shared: &SharedServer<C>, world: WorldId) {
+        let speed = self.physics().random_tick_speed;
+        if speed == 0 {
+            return;
+        }
+
+        let min_y = self.min_y();
+        let mut rng = rand::thread_rng();
+        let mut changes = Vec::new();
+
+        for (chunk_pos, chunk) in self.iter() {
+            for section in 0..chunk.height() / 16 {
+                for _ in 0..speed {
+                    let x = rng.gen_range(0..16);
+                    let y = section * 16 + rng.gen_range(0..16);
+                    let z = rng.gen_range(0..16);
+
+                    let block = chunk.get_block_state(x, y, z);
+                    if block.is_air() {
+                        continue;
+                    }
+
+                    let pos = BlockPos::new(
+                        chunk_pos.x * 16 + x as i32,
+                        min_y + y as i32,
+                        chunk_pos.z * 16 + z as i32,
+                    );
+
+                    if let Some(block) = shared.config().random_tick(world, pos, block) {
+                        changes.push((pos, block));
+                    }
+                }
+            }
+        }
+
+        for (pos, block) in changes {
+            self.set_block_state(pos, block);
+        }
+    }
+
+    /// Runs the block ticks which are due.
+    pub(crate) fn update_physics(&mut self) {
+        if self.physics().scheduled.is_empty() {
+            return;
+        }
+
+        for pos in self.physics_mut().take_due() {
+            let Some(block) = self.get_block_state(pos) else {
+                continue;
+            };
+
+            if self.physics().gravity && falls(block) {
+                let below = BlockPos::new(pos.x, pos.y - 1, pos.z);
+                if self.get_block_state(below).is_some_and(is_replaceable) {
+                    self.set_block_state(pos, BlockState::AIR);
+                    self.set_block_state(below, block);
+                }
+            } else if self.physics().fluids && block.to_kind() == BlockKind::Water {
+                self.tick_water(pos, water_level(block));
+            }
+        }
+    }
+
+    fn tick_water(&mut self, pos: BlockPos, level: u16) {
+        let water_at = |chunks: &Self, pos| {
+            chunks
+                .get_block_state(pos)
+                .filter(|b| b.to_kind() == BlockKind::Water)
+                .map(water_level)
+        };
+
+        let above = BlockPos::new(pos.x, pos.y + 1, pos.z);
+        let horizontal = [
+            BlockPos::new(pos.x - 1, pos.y, pos.z),
+            BlockPos::new(pos.x + 1, pos.y, pos.z),
+            BlockPos::new(pos.x, pos.y, pos.z - 1),
+            BlockPos::new(pos.x, pos.y, pos.z + 1),
+        ];
+
+        // Flowing water is fed by water above it, or by water next to it which
+        // is closer to a source. Falling water counts as a source.
+        if level != 0 {
+            let fed = water_at(self, above).is_some()
+                || (level < 8
+                    && horizontal
+                        .iter()
+                        .any(|&n| water_at(self, n).is_some_and(|l| l < level || l >= 8)));
+
+            if !fed {
+                self.set_block_state(pos, BlockState::AIR);
+                return;
+            }
+        }
+
+        let below = BlockPos::new(pos.x, pos.y - 1, pos.z);
+        let can_fall = self.get_block_state(below).is_some_and(|b| {
+            (is_replaceable(b) && !b.is_liquid())
+                || water_at(self, below).is_some_and(|l| l < 8 && l != 0)
+        });
+
+        if can_fall {
+            self.set_block_state(below, water(8));
+        }
+
+        // Like in vanilla, only sources spread sideways over water or a drop.
+        if level != 0 && (can_fall || water_at(self, below).is_some()) {
+            return;
+        }
+
+        let next = if level >= 8 { 1 } else { level + 1 };
+        if next > 7 {
+            return;
+        }
+
+        for n in horizontal {
+            let spreads = match self.get_block_state(n) {
+                Some(b) if b.to_kind() == BlockKind::Water => {
+                    let l = water_level(b);
+                    l != 0 && l < 8 && l > next
+                }
+                Some(b) => is_replaceable(b) && !b.is_liquid(),
+                None => false,
+            };
+
+            if spreads {
+                self.set_block_state(n, water(next));
+            }
+        }
+    }
+}
+
+/// Returns `true` if `block` is affected by gravity.
+fn falls(block: BlockState) -> bool {
+    matches!(
+        block.to_kind(),
+        BlockKind::Sand
+            | BlockKind::RedSand
+            | BlockKind::Gravel
+            | BlockKind::WhiteConcretePowder
+            | BlockKind::OrangeConcretePowder
+            | BlockKind::MagentaConcretePowder
+            | BlockKind::LightBlueConcretePowder
+            | BlockKind::YellowConcretePowder
+            | BlockKind::LimeConcretePowder
+            | BlockKind::PinkConcretePowder
+            | BlockKind::GrayConcretePowder
+            | BlockKind::LightGrayConcretePowder
+            | BlockKind::CyanConcretePowder
+            | BlockKind::PurpleConcretePowder
+            | BlockKind::BlueConcretePowder
+            | BlockKind::BrownConcretePowder
+            | BlockKind::GreenConcretePowder
+            | BlockKind::RedConcretePowder
+            | BlockKind::BlackConcretePowder
+    )
+}
+
+/// Gets the level of a water block. Zero is a source, `1..8` is flowing water
+/// further away from its source, and `8..16` is falling water.
+fn water_level(block: BlockState) -> u16 {
+    block
+        .get(PropName::Level)
+        .and_then(PropValue::to_u16)
+        .unwrap_or(0)
+}
+
+fn water(level: u16) -> BlockState {
+    BlockState::WATER.set(PropName::Level, PropValue::from_u16(level).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_pos::BlockPos;
+    use crate::testing::{test_server, Game, TestServer};
+
+    #[test]
+    fn block_physics_gravity_and_water() {
+        fn chunks(server: &mut TestServer<Game>) -> &mut Chunks<Game> {
+            &mut server
+                .server_mut()
+                .worlds
+                .iter_mut()
+                .next()
+                .unwrap()
+                .1
+                .chunks
+        }
+
+        fn water_level(server: &mut TestServer<Game>, pos: [i32; 3]) -> Option<u16> {
+            let block = chunks(server).get_block_state(pos).unwrap();
+            (block.to_kind() == BlockKind::Water).then(|| {
+                block
+                    .get(PropName::Level)
+                    .and_then(PropValue::to_u16)
+                    .unwrap()
+            })
+        }
+
+        let mut server = test_server();
+        server.tick();
+
+        for pos in BlockPos::iter_between([-9, 64, -9].into(), [9, 64, 9].into()) {
+            chunks(&mut server).set_block_state(pos, BlockState::STONE);
+        }
+        chunks(&mut server).set_block_state([0, 68, 0], BlockState::SAND);
+        // Nothing is scheduled while the physics are disabled.
+        assert_eq!(chunks(&mut server).physics().scheduled(), 0);
+
+        let physics = chunks(&mut server).physics_mut();
+        physics.set_gravity(true);
+        physics.set_fluids(true);
+        physics.schedule([0, 68, 0], 0);
+        for _ in 0..10 {
+            server.tick();
+        }
+        let c = chunks(&mut server);
+        assert_eq!(c.get_block_state([0, 68, 0]), Some(BlockState::AIR));
+        assert_eq!(c.get_block_state([0, 65, 0]), Some(BlockState::SAND));
+
+        c.set_block_state([0, 66, 0], BlockState::WATER);
+        for _ in 0..60 {
+            server.tick();
+        }
+
+        assert_eq!(water_level(&mut server, [0, 66, 0]), Some(0));
+        // The water falls next to the sand and spreads from there.
+        assert_eq!(water_level(&mut server, [1, 66, 0]), Some(1));
+        assert_eq!(water_level(&mut server, [1, 65, 0]), Some(8));
+        assert_eq!(water_level(&mut server, [2, 65, 0]), Some(1));
+        assert_eq!(water_level(&mut server, [2, 65, 1]), Some(2));
+        assert_eq!(water_level(&mut server, [8, 65, 0]), Some(7));
+        assert_eq!(water_level(&mut server, [9, 65, 0]), None);
+        assert_eq!(chunks(&mut server).physics().scheduled(), 0);
+
+        chunks(&mut server).set_block_state([0, 66, 0], BlockState::AIR);
+        for _ in 0..100 {
+            server.tick();
+        }
+        for pos in BlockPos::iter_between([-9, 65, -9].into(), [9, 66, 9].into()) {
+            assert_eq!(water_level(&mut server, pos.into()), None, "{pos:?}");
+        }
+    }
+
+    #[test]
+    fn random_ticks_grow_crops() {
+        let mut server = test_server();
+        server.tick();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        // Fill a whole chunk section with wheat so that every random tick in
+        // it hits a crop. All other sections are air and are skipped.
+        for pos in BlockPos::iter_between([0, 96, 0].into(), [15, 111, 15].into()) {
+            world.chunks.set_block_state(pos, BlockState::WHEAT);
+        }
+        world.chunks.physics_mut().set_random_tick_speed(3);
+        server.tick();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        let grown = BlockPos::iter_between([0, 96, 0].into(), [15, 111, 15].into())
+            .filter(|&pos| world.chunks.get_block_state(pos) != Some(BlockState::WHEAT))
+            .count();
+        assert!((1..=3).contains(&grown), "{grown}");
+
+        let ripe = BlockState::WHEAT.set(PropName::Age, PropValue::_7);
+        assert_eq!(BlockPhysics::grow_crop(ripe), None);
+        assert_eq!(BlockPhysics::grow_crop(BlockState::STONE), None);
+        assert_eq!(
+            BlockPhysics::grow_crop(BlockState::BEETROOTS),
+            Some(BlockState::BEETROOTS.set(PropName::Age, PropValue::_1))
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use vek::Vec3;
+
+use crate::block::{BlockKind, BlockPos, BlockState, PropName, PropValue};
+use crate::protocol::packets::c2s::play::BlockFace;
+
+/// Describes a client using a block on the face of another block, usually
+/// taken from [`ClientEvent::InteractWithBlock`].
+///
+/// [`ClientEvent::InteractWithBlock`]: crate::client::ClientEvent::InteractWithBlock
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlacementContext {
+    /// The position of the block that was clicked.
+    pub location: BlockPos,
+    /// The face of the block that was clicked.
+    pub face: BlockFace,
+    /// The position on the clicked face, relative to the block's minimum
+    /// corner. Each component is in `0.0..=1.0`.
+    pub cursor_pos: Vec3<f32>,
+    /// The yaw of the client placing the block, in degrees.
+    pub yaw: f32,
+    /// The pitch of the client placing the block, in degrees.
+    pub pitch: f32,
+}
+
+/// The block to place as the result of a client using a block, computed by
+/// [`BlockPlacement::new`].
+///
+/// Blocks held by clients with [`Client::set_held_block`] are placed
+/// automatically. Otherwise, nothing is placed until the placement is applied,
+/// so it can be inspected and discarded to cancel it:
+///
+/// ```ignore
+/// if let Some(placement) = world.chunks.block_placement(BlockKind::OakStairs, &ctx) {
+///     world.chunks.set_block_state(placement.position, placement.state);
+/// }
+/// ```
+///
+/// [`Client::set_held_block`]: crate::client::Client::set_held_block
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BlockPlacement {
+    /// The position the block is placed at.
+    pub position: BlockPos,
+    /// The block state to place, with properties such as the axis, facing
+    /// direction, slab type and waterlogging set.
+    pub state: BlockState,
+}
+
+impl BlockPlacement {
+    /// Computes where and in which state a block of the given kind is placed
+    /// like the vanilla server would.
+    ///
+    /// `get_block` returns the block currently at a position, or `None` if
+    /// the position is not loaded. `None` is returned if the block cannot be
+    /// placed because the position is occupied or not loaded.
+    ///
+    /// Blocks taller than one block such as doors are placed as their lower
+    /// half only. Connections to neighboring blocks such as the shape of
+    /// stairs are not computed.
+    pub fn new(
+        kind: BlockKind,
+        ctx: &PlacementContext,
+        get_block: impl Fn(BlockPos) -> Option<BlockState>,
+    ) -> Option<Self> {
+        let clicked = get_block(ctx.location)?;
+        let is_slab = kind.to_str().ends_with("_slab");
+
+        // Clicking the open half of a slab turns it into a double slab.
+        if is_slab && clicked.to_kind() == kind && can_merge_slab(clicked, ctx.face) {
+            return Some(Self {
+                position: ctx.location,
+                state: clicked.set(PropName::Type, PropValue::Double),
+            });
+        }
+
+        let (position, replaced) = if is_replaceable(clicked) {
+            (ctx.location, clicked)
+        } else {
+            let position = ctx.location.get_in_direction(ctx.face);
+            (position, get_block(position)?)
+        };
+
+        if is_slab && replaced.to_kind() == kind {
+            if replaced.get(PropName::Type) == Some(PropValue::Double) {
+                return None;
+            }
+
+            return Some(Self {
+                position,
+                state: replaced.set(PropName::Type, PropValue::Double),
+            });
+        }
+
+        if !is_replaceable(replaced) {
+            return None;
+        }
+
+        Some(Self {
+            position,
+            state: placement_state(kind, ctx, replaced),
+        })
+    }
+}
+
+fn placement_state(kind: BlockKind, ctx: &PlacementContext, replaced: BlockState) -> BlockState {
+    let mut state = kind.to_state();
+    let name = kind.to_str();
+
+    let top_half = match ctx.face {
+        BlockFace::Bottom => true,
+        BlockFace::Top => false,
+        _ => ctx.cursor_pos.y > 0.5,
+    };
+
+    for &prop in kind.props() {
+        let value = match prop {
+            PropName::Axis => Some(match ctx.face {
+                BlockFace::West | BlockFace::East => PropValue::X,
+                BlockFace::Bottom | BlockFace::Top => PropValue::Y,
+                BlockFace::North | BlockFace::South => PropValue::Z,
+            }),
+            PropName::Facing => Some(facing(name, ctx, kind.to_state())),
+            PropName::Half => Some(if kind.to_state().get(prop) == Some(PropValue::Lower) {
+                PropValue::Lower
+            } else if top_half {
+                PropValue::Top
+            } else {
+                PropValue::Bottom
+            }),
+            PropName::Type if name.ends_with("_slab") => Some(if top_half {
+                PropValue::Top
+            } else {
+                PropValue::Bottom
+            }),
+            PropName::Waterlogged => Some(PropValue::from_bool(is_water_source(replaced))),
+            _ => None,
+        };
+
+        if let Some(value) = value {
+            state = state.set(prop, value);
+        }
+    }
+
+    state
+}
+
+/// Computes the facing direction of a block being placed.
+fn facing(name: &str, ctx: &PlacementContext, default: BlockState) -> PropValue {
+    let horizontal = horizontal_facing(ctx.yaw);
+
+    // Blocks which can face up and down, like pistons, face the player.
+    let vertical = matches!(
+        default
+            .set(PropName::Facing, PropValue::Up)
+            .get(PropName::Facing),
+        Some(PropValue::Up)
+    );
+
+    if vertical {
+        let looking = if ctx.pitch > 45.0 {
+            PropValue::Down
+        } else if ctx.pitch < -45.0 {
+            PropValue::Up
+        } else {
+            horizontal
+        };
+
+        return if name == "observer" {
+            looking
+        } else {
+            opposite(looking)
+        };
+    }
+
+    if name.ends_with("_stairs") || name.ends_with("_door") || name.ends_with("_fence_gate") {
+        horizontal
+    } else if name.ends_with("_trapdoor") {
+        match ctx.face {
+            BlockFace::North => PropValue::North,
+            BlockFace::South => PropValue::South,
+            BlockFace::West => PropValue::West,
+            BlockFace::East => PropValue::East,
+            BlockFace::Bottom | BlockFace::Top => opposite(horizontal),
+        }
+    } else {
+        opposite(horizontal)
+    }
+}
+
+/// Gets the horizontal direction a player with the given yaw is looking in.
+fn horizontal_facing(yaw: f32) -> PropValue {
+    match (yaw / 90.0 + 0.5).floor().rem_euclid(4.0) as u8 {
+        0 => PropValue::South,
+        1 => PropValue::West,
+        2 => PropValue::North,
+        _ => PropValue::East,
+    }
+}
+
+fn opposite(dir: PropValue) -> PropValue {
+    match dir {
+        PropValue::North => PropValue::South,
+        PropValue::South => PropValue::North,
+        PropValue::West => PropValue::East,
+        PropValue::East => PropValue::West,
+        PropValue::Up => PropValue::Down,
+        PropValue::Down => PropValue::Up,
+        other => other,
+    }
+}
+
+fn can_merge_slab(slab: BlockState, face: BlockFace) -> bool {
+    matches!(
+        (slab.get(PropName::Type), face),
+        (Some(PropValue::Bottom), BlockFace::Top) | (Some(PropValue::Top), BlockFace::Bottom)
+    )
+}
+
+/// Returns `true` if a block placed at the position of `state` replaces it.
+pub(crate) fn is_replaceable(state: BlockState) -> bool {
+    state.is_air()
+        || state.is_liquid()
+        || matches!(
+            state.to_kind(),
+            BlockKind::Grass
+                | BlockKind::TallGrass
+                | BlockKind::Fern
+                | BlockKind::LargeFern
+                | BlockKind::DeadBush
+                | BlockKind::Seagrass
+                | BlockKind::TallSeagrass
+                | BlockKind::Vine
+                | BlockKind::Fire
+                | BlockKind::SoulFire
+        )
+        || (state.to_kind() == BlockKind::Snow
+            && state.get(PropName::Layers) == Some(PropValue::_1))
+}
+
+fn is_water_source(state: BlockState) -> bool {
+    state.to_kind() == BlockKind::Water && state.get(PropName::Level) == Some(PropValue::_0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(face: BlockFace, cursor_y: f32, yaw: f32) -> PlacementContext {
+        PlacementContext {
+            location: BlockPos::new(0, 0, 0),
+            face,
+            cursor_pos: Vec3::new(0.5, cursor_y, 0.5),
+            yaw,
+            pitch: 0.0,
+        }
+    }
+
+    fn place(
+        kind: BlockKind,
+        ctx: &PlacementContext,
+        clicked: BlockState,
+    ) -> Option<BlockPlacement> {
+        BlockPlacement::new(kind, ctx, |pos| {
+            Some(if pos == ctx.location {
+                clicked
+            } else {
+                BlockState::AIR
+            })
+        })
+    }
+
+    #[test]
+    fn axis_and_facing() {
+        let log = place(
+            BlockKind::OakLog,
+            &ctx(BlockFace::East, 0.5, 0.0),
+            BlockState::STONE,
+        )
+        .unwrap();
+        assert_eq!(log.position, BlockPos::new(1, 0, 0));
+        assert_eq!(log.state.get(PropName::Axis), Some(PropValue::X));
+
+        // A player looking south places a furnace facing north, towards them.
+        let furnace = place(
+            BlockKind::Furnace,
+            &ctx(BlockFace::Top, 1.0, 0.0),
+            BlockState::STONE,
+        )
+        .unwrap();
+        assert_eq!(furnace.state.get(PropName::Facing), Some(PropValue::North));
+
+        let stairs = place(
+            BlockKind::OakStairs,
+            &ctx(BlockFace::North, 0.75, 90.0),
+            BlockState::STONE,
+        )
+        .unwrap();
+        assert_eq!(stairs.state.get(PropName::Facing), Some(PropValue::West));
+        assert_eq!(stairs.state.get(PropName::Half), Some(PropValue::Top));
+    }
+
+    #[test]
+    fn slabs_and_water() {
+        let bottom = BlockState::OAK_SLAB.set(PropName::Type, PropValue::Bottom);
+        let double = place(BlockKind::OakSlab, &ctx(BlockFace::Top, 1.0, 0.0), bottom).unwrap();
+        assert_eq!(double.position, BlockPos::new(0, 0, 0));
+        assert_eq!(double.state.get(PropName::Type), Some(PropValue::Double));
+
+        let water = place(
+            BlockKind::OakSlab,
+            &ctx(BlockFace::Top, 1.0, 0.0),
+            BlockState::WATER,
+        )
+        .unwrap();
+        assert_eq!(water.position, BlockPos::new(0, 0, 0));
+        assert_eq!(
+            water.state.get(PropName::Waterlogged),
+            Some(PropValue::True)
+        );
+
+        let full = double.state;
+        let above = place(BlockKind::OakSlab, &ctx(BlockFace::Top, 1.0, 0.0), full).unwrap();
+        assert_eq!(above.position, BlockPos::new(0, 1, 0));
+        assert_eq!(above.state.get(PropName::Type), Some(PropValue::Bottom));
+    }
+}
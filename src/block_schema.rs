@@ -0,0 +1,567 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+
+use crate::block::{BlockKind, BlockState, PropName, PropValue};
+use crate::nbt::{Compound, Value};
+use crate::DATA_VERSION;
+
+/// The data version of Minecraft 1.13, which replaced numeric block IDs with
+/// named block states.
+const FLATTENING_DATA_VERSION: i32 = 1451;
+
+/// A block state as it is stored in the block palettes of Anvil chunk
+/// sections, with a namespaced block name and a string for every property.
+///
+/// Unlike [`BlockState`], a palette entry can hold blocks and properties from
+/// any version, which makes it the input and output of [`BlockSchemas`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PaletteEntry {
+    /// The namespaced name of the block, such as `minecraft:oak_sign`.
+    pub name: String,
+    /// The properties of the block state by name.
+    pub props: BTreeMap<String, String>,
+}
+
+impl PaletteEntry {
+    /// Creates an entry without properties. Names without a namespace are put
+    /// in the `minecraft` namespace.
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            name: if name.contains(':') {
+                name
+            } else {
+                format!("minecraft:{name}")
+            },
+            props: BTreeMap::new(),
+        }
+    }
+
+    /// Sets a property of the entry.
+    #[must_use]
+    pub fn prop(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.props.insert(name.into(), value.into());
+        self
+    }
+
+    /// Reads an entry from the `Name` and `Properties` tags of a palette
+    /// compound.
+    pub fn from_nbt(nbt: &Compound) -> anyhow::Result<Self> {
+        let Some(Value::String(name)) = nbt.get("Name") else {
+            bail!("missing block name in palette entry");
+        };
+
+        let mut entry = Self::new(name.as_str());
+
+        match nbt.get("Properties") {
+            Some(Value::Compound(props)) => {
+                for (prop, value) in props {
+                    let Value::String(value) = value else {
+                        bail!("block property \"{prop}\" of \"{name}\" is not a string");
+                    };
+                    entry.props.insert(prop.clone(), value.clone());
+                }
+            }
+            Some(_) => bail!("block properties of \"{name}\" are not a compound"),
+            None => {}
+        }
+
+        Ok(entry)
+    }
+
+    /// Writes the entry into a palette compound. The `Properties` tag is left
+    /// out if the entry has no properties.
+    pub fn to_nbt(&self) -> Compound {
+        let mut nbt = Compound::from([("Name".into(), Value::String(self.name.clone()))]);
+
+        if !self.props.is_empty() {
+            nbt.insert(
+                "Properties".into(),
+                Value::Compound(
+                    self.props
+                        .iter()
+                        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                        .collect(),
+                ),
+            );
+        }
+
+        nbt
+    }
+
+    /// Creates the entry for a block state of the current version.
+    pub fn from_block_state(state: BlockState) -> Self {
+        let mut entry = Self::new(state.to_kind().to_str());
+
+        for &prop in state.to_kind().props() {
+            if let Some(value) = state.get(prop) {
+                entry
+                    .props
+                    .insert(prop.to_str().to_owned(), value.to_str().to_owned());
+            }
+        }
+
+        entry
+    }
+
+    /// Converts the entry to a block state of the current version.
+    ///
+    /// Properties the block doesn't have are ignored and missing properties
+    /// keep their default value. `None` is returned if there is no block with
+    /// this name in the current version.
+    pub fn to_block_state(&self) -> Option<BlockState> {
+        let kind = BlockKind::from_str(self.name.strip_prefix("minecraft:")?)?;
+        let mut state = kind.to_state();
+
+        for (prop, value) in &self.props {
+            if let (Some(prop), Some(value)) =
+                (PropName::from_str(prop), PropValue::from_str(value))
+            {
+                if kind.props().contains(&prop) {
+                    state = state.set(prop, value);
+                }
+            }
+        }
+
+        Some(state)
+    }
+
+    /// Returns the block name without the `minecraft` namespace, or `None` if
+    /// the block is in another namespace.
+    fn vanilla_name(&self) -> Option<&str> {
+        self.name.strip_prefix("minecraft:")
+    }
+}
+
+/// Matches vanilla block names in a [`BlockSchema`]. A leading `*` matches
+/// any prefix, so `*_wall` matches every wall.
+fn matches(pattern: &str, entry: &PaletteEntry) -> bool {
+    let Some(name) = entry.vanilla_name() else {
+        return false;
+    };
+
+    match pattern.strip_prefix('*') {
+        Some(suffix) => name.ends_with(suffix),
+        None => name == pattern,
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum BlockFix {
+    RenameBlock {
+        from: String,
+        to: String,
+        /// Only rename blocks with this property value.
+        condition: Option<(String, String)>,
+    },
+    RenameProp {
+        block: String,
+        from: String,
+        to: String,
+    },
+    MapValue {
+        block: String,
+        prop: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl BlockFix {
+    fn upgrade(&self, entry: &mut PaletteEntry) {
+        match self {
+            BlockFix::RenameBlock {
+                from,
+                to,
+                condition,
+            } => {
+                let condition_holds = condition
+                    .as_ref()
+                    .is_none_or(|(prop, value)| entry.props.get(prop) == Some(value));
+
+                if matches(from, entry) && condition_holds {
+                    entry.name = format!("minecraft:{to}");
+                }
+            }
+            BlockFix::RenameProp { block, from, to } => {
+                if matches(block, entry) {
+                    if let Some(value) = entry.props.remove(from) {
+                        entry.props.insert(to.clone(), value);
+                    }
+                }
+            }
+            BlockFix::MapValue {
+                block,
+                prop,
+                from,
+                to,
+            } => {
+                if matches(block, entry) {
+                    if let Some(value) = entry.props.get_mut(prop) {
+                        if value == from {
+                            *value = to.clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn downgrade(&self, entry: &mut PaletteEntry) {
+        match self {
+            BlockFix::RenameBlock {
+                from,
+                to,
+                condition,
+            } => {
+                let condition_holds = condition
+                    .as_ref()
+                    .is_none_or(|(prop, value)| entry.props.get(prop) == Some(value));
+
+                if matches(to, entry) && condition_holds {
+                    entry.name = format!("minecraft:{from}");
+                }
+            }
+            BlockFix::RenameProp { block, from, to } => {
+                if matches(block, entry) {
+                    if let Some(value) = entry.props.remove(to) {
+                        entry.props.insert(from.clone(), value);
+                    }
+                }
+            }
+            BlockFix::MapValue {
+                block,
+                prop,
+                from,
+                to,
+            } => {
+                if matches(block, entry) {
+                    if let Some(value) = entry.props.get_mut(prop) {
+                        if value == to {
+                            *value = from.clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The changes made to block names and properties by a Minecraft version,
+/// like the schemas of the vanilla data fixer.
+///
+/// Block names in a schema are vanilla names without the namespace. Renamed
+/// blocks are matched by their old name and other changes are matched by
+/// the name the block has after this schema, where a leading `*` matches any
+/// prefix.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BlockSchema {
+    data_version: i32,
+    fixes: Vec<BlockFix>,
+}
+
+impl BlockSchema {
+    /// Creates an empty schema for the version with the given [data
+    /// version].
+    ///
+    /// [data version]: crate::DATA_VERSION
+    pub fn new(data_version: i32) -> Self {
+        Self {
+            data_version,
+            fixes: Vec::new(),
+        }
+    }
+
+    /// Renames the block `from` to `to`.
+    #[must_use]
+    pub fn rename_block(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.fixes.push(BlockFix::RenameBlock {
+            from: from.into(),
+            to: to.into(),
+            condition: None,
+        });
+        self
+    }
+
+    /// Renames the block `from` to `to` if the property `prop` is set to
+    /// `value`. Used for blocks that were split in two.
+    #[must_use]
+    pub fn rename_block_if(
+        mut self,
+        from: impl Into<String>,
+        to: impl Into<String>,
+        prop: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.fixes.push(BlockFix::RenameBlock {
+            from: from.into(),
+            to: to.into(),
+            condition: Some((prop.into(), value.into())),
+        });
+        self
+    }
+
+    /// Renames the property `from` of the blocks matching `block` to `to`.
+    #[must_use]
+    pub fn rename_prop(
+        mut self,
+        block: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> Self {
+        self.fixes.push(BlockFix::RenameProp {
+            block: block.into(),
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Replaces the value `from` of the property `prop` of the blocks matching
+    /// `block` with `to`.
+    #[must_use]
+    pub fn map_value(
+        mut self,
+        block: impl Into<String>,
+        prop: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> Self {
+        self.fixes.push(BlockFix::MapValue {
+            block: block.into(),
+            prop: prop.into(),
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Gets the data version this schema upgrades to.
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    /// Applies the changes of this schema to an entry from the previous
+    /// version.
+    pub fn upgrade(&self, entry: &mut PaletteEntry) {
+        for fix in &self.fixes {
+            fix.upgrade(entry);
+        }
+    }
+
+    /// Reverts the changes of this schema on an entry from this version.
+    ///
+    /// Blocks and values that did not exist in the previous version are left
+    /// as they are.
+    pub fn downgrade(&self, entry: &mut PaletteEntry) {
+        for fix in self.fixes.iter().rev() {
+            fix.downgrade(entry);
+        }
+    }
+}
+
+/// An ordered set of [`BlockSchema`]s used to convert block states of worlds
+/// saved by other Minecraft versions.
+///
+/// # Examples
+///
+/// ```
+/// use valence::block::{BlockKind, BlockSchemas, PaletteEntry};
+///
+/// let schemas = BlockSchemas::vanilla();
+///
+/// // A sign saved by Minecraft 1.13.2.
+/// let mut entry = PaletteEntry::new("sign").prop("rotation", "4");
+/// schemas.upgrade(&mut entry, 1631).unwrap();
+///
+/// let state = entry.to_block_state().unwrap();
+/// assert_eq!(state.to_kind(), BlockKind::OakSign);
+/// ```
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct BlockSchemas {
+    schemas: Vec<BlockSchema>,
+}
+
+impl BlockSchemas {
+    /// Creates an empty set of schemas.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the schemas for the changes to vanilla blocks since Minecraft
+    /// 1.13.
+    pub fn vanilla() -> Self {
+        const DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+        let mut walls = BlockSchema::new(2566);
+        for dir in DIRECTIONS {
+            walls = walls
+                .map_value("*_wall", dir, "true", "low")
+                .map_value("*_wall", dir, "false", "none");
+        }
+
+        Self::new()
+            // 1.14
+            .schema(
+                BlockSchema::new(1952)
+                    .rename_block("stone_slab", "smooth_stone_slab")
+                    .rename_block("sign", "oak_sign")
+                    .rename_block("wall_sign", "oak_wall_sign"),
+            )
+            // 1.16
+            .schema(
+                walls
+                    .rename_prop("jigsaw", "facing", "orientation")
+                    .map_value("jigsaw", "orientation", "down", "down_south")
+                    .map_value("jigsaw", "orientation", "up", "up_north")
+                    .map_value("jigsaw", "orientation", "north", "north_up")
+                    .map_value("jigsaw", "orientation", "east", "east_up")
+                    .map_value("jigsaw", "orientation", "south", "south_up")
+                    .map_value("jigsaw", "orientation", "west", "west_up"),
+            )
+            // 1.17
+            .schema(
+                BlockSchema::new(2724)
+                    .rename_block("grass_path", "dirt_path")
+                    .rename_block_if("cauldron", "water_cauldron", "level", "1")
+                    .rename_block_if("cauldron", "water_cauldron", "level", "2")
+                    .rename_block_if("cauldron", "water_cauldron", "level", "3"),
+            )
+    }
+
+    /// Adds a schema. Schemas are applied in the order of their data
+    /// versions.
+    #[must_use]
+    pub fn schema(mut self, schema: BlockSchema) -> Self {
+        let idx = self
+            .schemas
+            .partition_point(|s| s.data_version <= schema.data_version);
+        self.schemas.insert(idx, schema);
+        self
+    }
+
+    /// Upgrades an entry saved with `data_version` to the current
+    /// [`DATA_VERSION`].
+    ///
+    /// An error is returned for worlds saved before Minecraft 1.13, which
+    /// used numeric block IDs, and for worlds saved by newer versions.
+    ///
+    /// [`DATA_VERSION`]: crate::DATA_VERSION
+    pub fn upgrade(&self, entry: &mut PaletteEntry, data_version: i32) -> anyhow::Result<()> {
+        if data_version < FLATTENING_DATA_VERSION {
+            bail!("data version {data_version} is from before block states were named");
+        }
+
+        if data_version > DATA_VERSION {
+            bail!("data version {data_version} is newer than the current version {DATA_VERSION}");
+        }
+
+        for schema in &self.schemas {
+            if schema.data_version > data_version && schema.data_version <= DATA_VERSION {
+                schema.upgrade(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Downgrades an entry of the current [`DATA_VERSION`] so that it can be
+    /// saved for the version with `data_version`.
+    ///
+    /// This is lossy where a newer version added blocks or property values,
+    /// which are left as they are.
+    ///
+    /// [`DATA_VERSION`]: crate::DATA_VERSION
+    pub fn downgrade(&self, entry: &mut PaletteEntry, data_version: i32) -> anyhow::Result<()> {
+        if data_version < FLATTENING_DATA_VERSION {
+            bail!("data version {data_version} is from before block states were named");
+        }
+
+        for schema in self.schemas.iter().rev() {
+            if schema.data_version > data_version && schema.data_version <= DATA_VERSION {
+                schema.downgrade(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a palette compound saved with `data_version` and converts it to
+    /// a block state of the current version.
+    pub fn upgrade_nbt(&self, nbt: &Compound, data_version: i32) -> anyhow::Result<BlockState> {
+        let mut entry = PaletteEntry::from_nbt(nbt)?;
+        self.upgrade(&mut entry, data_version)?;
+
+        entry
+            .to_block_state()
+            .with_context(|| format!("unknown block \"{}\"", entry.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::PropValue;
+
+    #[test]
+    fn palette_entry_round_trip() {
+        let state = BlockState::OAK_STAIRS
+            .set(PropName::Facing, PropValue::East)
+            .set(PropName::Waterlogged, PropValue::True);
+
+        let entry = PaletteEntry::from_block_state(state);
+        assert_eq!(entry.name, "minecraft:oak_stairs");
+        assert_eq!(entry.props["facing"], "east");
+
+        let entry = PaletteEntry::from_nbt(&entry.to_nbt()).unwrap();
+        assert_eq!(entry.to_block_state(), Some(state));
+    }
+
+    #[test]
+    fn vanilla_upgrades() {
+        let schemas = BlockSchemas::vanilla();
+
+        let wall = PaletteEntry::new("cobblestone_wall")
+            .prop("north", "true")
+            .prop("south", "false")
+            .prop("up", "true");
+        let nbt = wall.to_nbt();
+        let state = schemas.upgrade_nbt(&nbt, 2230).unwrap();
+        assert_eq!(state.to_kind(), BlockKind::CobblestoneWall);
+        assert_eq!(state.get(PropName::North), Some(PropValue::Low));
+        assert_eq!(state.get(PropName::South), Some(PropValue::None));
+
+        let mut cauldron = PaletteEntry::new("cauldron").prop("level", "2");
+        schemas.upgrade(&mut cauldron, 2586).unwrap();
+        assert_eq!(cauldron.name, "minecraft:water_cauldron");
+
+        let mut empty = PaletteEntry::new("cauldron").prop("level", "0");
+        schemas.upgrade(&mut empty, 2586).unwrap();
+        assert_eq!(empty.to_block_state(), Some(BlockState::CAULDRON));
+
+        // Blocks saved by the current version are not changed.
+        let mut slab = PaletteEntry::new("stone_slab");
+        schemas.upgrade(&mut slab, DATA_VERSION).unwrap();
+        assert_eq!(slab.name, "minecraft:stone_slab");
+
+        assert!(schemas.upgrade(&mut slab, 1343).is_err());
+    }
+
+    #[test]
+    fn vanilla_downgrades() {
+        let schemas = BlockSchemas::vanilla();
+
+        let mut entry = PaletteEntry::from_block_state(BlockState::DIRT_PATH);
+        schemas.downgrade(&mut entry, 2586).unwrap();
+        assert_eq!(entry.name, "minecraft:grass_path");
+
+        let mut entry = PaletteEntry::new("jigsaw").prop("orientation", "up_north");
+        schemas.downgrade(&mut entry, 1631).unwrap();
+        assert_eq!(entry, PaletteEntry::new("jigsaw").prop("facing", "up"));
+
+        let mut entry = PaletteEntry::new("oak_wall_sign");
+        schemas.downgrade(&mut entry, 1631).unwrap();
+        assert_eq!(entry.name, "minecraft:wall_sign");
+    }
+}
@@ -94,7 +94,7 @@ impl<T: Send + Sync> Bvh<T> {
         debug_assert_eq!(self.internal_nodes.len(), self.leaf_nodes.len() - 1);
     }
 
-    pub fn traverse(&self) -> Option<Node<T>> {
+    pub fn traverse(&self) -> Option<Node<'_, T>> {
         if !self.leaf_nodes.is_empty() {
             Some(Node::from_idx(self, self.root))
         } else {
@@ -3,31 +3,62 @@
 // TODO: https://github.com/rust-lang/rust/issues/88581 for div_ceil
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::Write;
 use std::iter::FusedIterator;
+use std::mem;
 
 use bitvec::vec::BitVec;
 use num::Integer;
 use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use uuid::Uuid;
 
 use crate::biome::BiomeId;
-use crate::block::BlockState;
+use crate::block::{BlockKind, BlockPlacement, BlockState, PlacementContext};
+pub use crate::block_physics::BlockPhysics;
 use crate::block_pos::BlockPos;
 pub use crate::chunk_pos::ChunkPos;
 use crate::config::Config;
 use crate::dimension::DimensionId;
+use crate::generator::Generator;
 use crate::protocol::packets::s2c::play::{
     BlockUpdate, ChunkData, ChunkDataHeightmaps, ChunkSectionUpdate, S2cPlayPacket,
 };
 use crate::protocol::{Encode, NbtBridge, VarInt, VarLong};
 use crate::server::SharedServer;
+use crate::util::chunks_in_view_distance;
+use crate::Ticks;
 
 /// A container for all [`Chunk`]s in a [`World`](crate::world::World).
 pub struct Chunks<C: Config> {
     chunks: HashMap<ChunkPos, Chunk<C>>,
     shared: SharedServer<C>,
     dimension: DimensionId,
+    generator: Option<Generator>,
+    /// The chunk produced by the generator at every position, if any.
+    prototype: Option<UnloadedChunk>,
+    tickets: HashMap<ChunkTicketId, ChunkTicket>,
+    next_ticket_id: u64,
+    player_tickets: bool,
+    /// The chunks which were loaded because of a ticket and are unloaded once
+    /// no ticket covers them.
+    ticket_loaded: HashSet<ChunkPos>,
+    block_log: Option<BlockChangeLog>,
+    /// The chunks which were changed during the previous tick.
+    changed_last_tick: Vec<ChunkPos>,
+    physics: BlockPhysics,
+}
+
+/// Identifies a chunk ticket added with
+/// [`World::add_ticket`](crate::world::World::add_ticket).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ChunkTicketId(u64);
+
+struct ChunkTicket {
+    center: ChunkPos,
+    radius: u32,
+    /// The tick at which the ticket is removed, if any.
+    expires_at: Option<Ticks>,
 }
 
 impl<C: Config> Chunks<C> {
@@ -36,12 +67,37 @@ impl<C: Config> Chunks<C> {
             chunks: HashMap::new(),
             shared,
             dimension,
+            generator: None,
+            prototype: None,
+            tickets: HashMap::new(),
+            next_ticket_id: 0,
+            player_tickets: false,
+            ticket_loaded: HashSet::new(),
+            block_log: None,
+            changed_last_tick: Vec::new(),
+            physics: BlockPhysics::new(),
         }
     }
 
-    /// Creates an empty chunk at the provided position and returns a mutable
+    /// Gets the generator used to fill new chunks, set with
+    /// [`World::set_generator`](crate::world::World::set_generator).
+    pub fn generator(&self) -> Option<&Generator> {
+        self.generator.as_ref()
+    }
+
+    pub(crate) fn set_generator(&mut self, generator: Option<Generator>) {
+        let section_count = self.shared.dimension(self.dimension).section_count();
+
+        self.prototype = generator.as_ref().and_then(|g| g.prototype(section_count));
+        self.generator = generator;
+    }
+
+    /// Creates a chunk at the provided position and returns a mutable
     /// reference to it.
     ///
+    /// The chunk is filled by the world's [`Generator`] if it has one.
+    /// Otherwise, the chunk is empty.
+    ///
     /// If a chunk at the position already exists, then the old chunk
     /// is overwritten.
     ///
@@ -50,11 +106,58 @@ impl<C: Config> Chunks<C> {
     /// are not spawned within unloaded chunks via
     /// [`spawn`](crate::client::Client::spawn).
     pub fn insert(&mut self, pos: impl Into<ChunkPos>, state: C::ChunkState) -> &mut Chunk<C> {
-        let section_count = (self.shared.dimension(self.dimension).height / 16) as u32;
+        let pos = pos.into();
+        let dim = self.shared.dimension(self.dimension);
+        let section_count = dim.section_count();
         let biome_registry_len = self.shared.biomes().len();
-        let chunk = Chunk::new(section_count, biome_registry_len, state);
 
-        match self.chunks.entry(pos.into()) {
+        let chunk = match (&self.prototype, &self.generator) {
+            (Some(prototype), _) => {
+                Chunk::from_prototype(prototype, section_count, biome_registry_len, state)
+            }
+            (None, Some(generator)) => Chunk::from_prototype(
+                &generator.generate(pos, dim.min_y, section_count),
+                section_count,
+                biome_registry_len,
+                state,
+            ),
+            (None, None) => Chunk::new(section_count as u32, biome_registry_len, state),
+        };
+
+        self.ticket_loaded.remove(&pos);
+
+        match self.chunks.entry(pos) {
+            Entry::Occupied(mut oe) => {
+                oe.insert(chunk);
+                oe.into_mut()
+            }
+            Entry::Vacant(ve) => ve.insert(chunk),
+        }
+    }
+
+    /// Creates a chunk at the provided position with the blocks of an
+    /// [`UnloadedChunk`] and returns a mutable reference to it.
+    ///
+    /// The prototype is not consumed, so the same blocks can be inserted at
+    /// many positions cheaply. Sections above the height of the world's
+    /// dimension are ignored and missing sections are filled with air.
+    ///
+    /// If a chunk at the position already exists, then the old chunk
+    /// is overwritten.
+    pub fn insert_with(
+        &mut self,
+        pos: impl Into<ChunkPos>,
+        prototype: &UnloadedChunk,
+        state: C::ChunkState,
+    ) -> &mut Chunk<C> {
+        let section_count = self.shared.dimension(self.dimension).section_count();
+        let biome_registry_len = self.shared.biomes().len();
+        let chunk = Chunk::from_prototype(prototype, section_count, biome_registry_len, state);
+        let pos = pos.into();
+
+        self.ticket_loaded.remove(&pos);
+
+        match self.chunks.entry(pos) {
             Entry::Occupied(mut oe) => {
                 oe.insert(chunk);
                 oe.into_mut()
@@ -68,7 +171,9 @@ impl<C: Config> Chunks<C> {
     /// If a chunk exists at the position, then it is deleted and its
     /// `ChunkState` is returned. Otherwise, `None` is returned.
     pub fn remove(&mut self, pos: impl Into<ChunkPos>) -> Option<C::ChunkState> {
-        self.chunks.remove(&pos.into()).map(|c| c.state)
+        let pos = pos.into();
+        self.ticket_loaded.remove(&pos);
+        self.chunks.remove(&pos).map(|c| c.state)
     }
 
     /// Returns the number of loaded chunks.
@@ -81,6 +186,41 @@ impl<C: Config> Chunks<C> {
         self.chunks.len() == 0
     }
 
+    /// Returns an estimate of the memory used by the chunks in bytes.
+    ///
+    /// This counts the block and biome data of every section along with its
+    /// encoded palettes, the heightmaps, the generator's prototype chunk, the
+    /// block change log and the space reserved by the collections holding
+    /// them. Custom chunk state
+    /// is counted only by its size, not by what it allocates.
+    pub fn memory_usage(&self) -> usize {
+        let map = self.chunks.capacity() * mem::size_of::<(ChunkPos, Chunk<C>)>();
+
+        let chunks: usize = self
+            .chunks
+            .par_iter()
+            .map(|(_, chunk)| chunk.heap_memory_usage())
+            .sum();
+
+        let prototype = self.prototype.as_ref().map_or(0, |proto| {
+            proto.sections.capacity() * mem::size_of::<UnloadedSection>()
+                + proto
+                    .sections
+                    .iter()
+                    .map(|sect| sect.block_data.capacity())
+                    .sum::<usize>()
+        });
+
+        let tickets = self.tickets.capacity() * mem::size_of::<(ChunkTicketId, ChunkTicket)>()
+            + self.ticket_loaded.capacity() * mem::size_of::<ChunkPos>();
+
+        let block_log = self.block_log.as_ref().map_or(0, |log| {
+            log.changes.capacity() * mem::size_of::<BlockChange>()
+        });
+
+        mem::size_of::<Self>() + map + chunks + prototype + tickets + block_log
+    }
+
     /// Gets a shared reference to the chunk at the provided position.
     ///
     /// If there is no chunk at the position, then `None` is returned.
@@ -135,6 +275,21 @@ impl<C: Config> Chunks<C> {
         self.chunks.par_iter_mut().map(|(&pos, chunk)| (pos, chunk))
     }
 
+    /// Returns the positions of the chunks which were
+    /// [changed](Chunk::changed_this_tick) during the previous tick, in an
+    /// unspecified order. Chunks which were removed since may be included.
+    ///
+    /// Systems which scan the blocks of a world can use this to only rescan
+    /// the chunks which changed.
+    pub fn changed_last_tick(&self) -> &[ChunkPos] {
+        &self.changed_last_tick
+    }
+
+    /// Gets the lowest y coordinate of the chunks.
+    pub(crate) fn min_y(&self) -> i32 {
+        self.shared.dimension(self.dimension).min_y
+    }
+
     /// Gets the block state at a position.
     ///
     /// If the position is not inside of a chunk, then `None` is returned.
@@ -171,7 +326,26 @@ impl<C: Config> Chunks<C> {
     /// efficient write to the chunks directly with
     /// [`Chunk::set_block_state`].
     pub fn set_block_state(&mut self, pos: impl Into<BlockPos>, block: BlockState) -> bool {
-        let pos = pos.into();
+        self.set_block_state_with_actor(pos.into(), block, None)
+    }
+
+    /// Like [`Self::set_block_state`], but records `actor` as the player who
+    /// made the change in the [block change log](Self::block_log).
+    pub fn set_block_state_by(
+        &mut self,
+        pos: impl Into<BlockPos>,
+        block: BlockState,
+        actor: Uuid,
+    ) -> bool {
+        self.set_block_state_with_actor(pos.into(), block, Some(actor))
+    }
+
+    fn set_block_state_with_actor(
+        &mut self,
+        pos: BlockPos,
+        block: BlockState,
+        actor: Option<Uuid>,
+    ) -> bool {
         let chunk_pos = ChunkPos::from(pos);
 
         if let Some(chunk) = self.chunks.get_mut(&chunk_pos) {
@@ -179,12 +353,25 @@ impl<C: Config> Chunks<C> {
 
             if let Some(y) = pos.y.checked_sub(min_y).and_then(|y| y.try_into().ok()) {
                 if y < chunk.height() {
-                    chunk.set_block_state(
-                        pos.x.rem_euclid(16) as usize,
-                        y,
-                        pos.z.rem_euclid(16) as usize,
-                        block,
-                    );
+                    let (x, z) = (pos.x.rem_euclid(16) as usize, pos.z.rem_euclid(16) as usize);
+
+                    let old = chunk.get_block_state(x, y, z);
+                    if old == block {
+                        return true;
+                    }
+
+                    if let Some(log) = &mut self.block_log {
+                        log.push(BlockChange {
+                            pos,
+                            tick: self.shared.current_tick(),
+                            actor,
+                            old,
+                            new: block,
+                        });
+                    }
+
+                    chunk.set_block_state(x, y, z, block);
+                    self.schedule_neighbor_ticks(pos);
                     return true;
                 }
             }
@@ -193,28 +380,431 @@ impl<C: Config> Chunks<C> {
         false
     }
 
+    /// Gets the [block physics](BlockPhysics) of this world.
+    pub fn physics(&self) -> &BlockPhysics {
+        &self.physics
+    }
+
+    /// Gets a mutable reference to the [block physics](BlockPhysics) of this
+    /// world, which can be used to enable them.
+    pub fn physics_mut(&mut self) -> &mut BlockPhysics {
+        &mut self.physics
+    }
+
+    /// Gets the log of recent block changes in this world, if enabled with
+    /// [`Self::set_block_log`].
+    pub fn block_log(&self) -> Option<&BlockChangeLog> {
+        self.block_log.as_ref()
+    }
+
+    /// Gets a mutable reference to the log of recent block changes in this
+    /// world, if enabled with [`Self::set_block_log`].
+    pub fn block_log_mut(&mut self) -> Option<&mut BlockChangeLog> {
+        self.block_log.as_mut()
+    }
+
+    /// Sets the log recording block changes in this world. Pass `None` to stop
+    /// recording. The log is disabled by default.
+    pub fn set_block_log(&mut self, log: Option<BlockChangeLog>) {
+        self.block_log = log;
+    }
+
+    /// Computes where and in which state a block of the given kind is placed
+    /// when a client uses it as described by `ctx`. See
+    /// [`BlockPlacement::new`].
+    ///
+    /// The block is not placed. Apply the returned placement with
+    /// [`Self::set_block_state`] unless it should be cancelled.
+    pub fn block_placement(
+        &self,
+        kind: BlockKind,
+        ctx: &PlacementContext,
+    ) -> Option<BlockPlacement> {
+        BlockPlacement::new(kind, ctx, |pos| self.get_block_state(pos))
+    }
+
+    /// Takes a snapshot of the blocks in the box between the corners `a` and
+    /// `b`, inclusive. Restore it with [`Self::restore`].
+    ///
+    /// Blocks outside of loaded chunks are not part of the snapshot.
+    pub fn snapshot(&self, a: impl Into<BlockPos>, b: impl Into<BlockPos>) -> RegionSnapshot {
+        let (a, b) = (a.into(), b.into());
+        let min = BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let max = BlockPos::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+
+        let mut snapshot = RegionSnapshot {
+            min,
+            max,
+            blocks: vec![None; RegionSnapshot::volume(min, max)],
+        };
+
+        let min_y = self.shared.dimension(self.dimension).min_y;
+
+        for (chunk_pos, columns) in region_columns(min, max) {
+            if let Some(chunk) = self.chunks.get(&chunk_pos) {
+                let ys = min.y.max(min_y)..=max.y.min(min_y + chunk.height() as i32 - 1);
+
+                for (x, z) in columns.clone() {
+                    for y in ys.clone() {
+                        let idx = snapshot.index(BlockPos::new(x, y, z));
+                        snapshot.blocks[idx] = Some(chunk.get_block_state(
+                            x.rem_euclid(16) as usize,
+                            (y - min_y) as usize,
+                            z.rem_euclid(16) as usize,
+                        ));
+                    }
+                }
+            }
+        }
+
+        snapshot
+    }
+
+    /// Sets the blocks in the region of a snapshot taken with
+    /// [`Self::snapshot`] back to their state in the snapshot.
+    ///
+    /// Only blocks which were part of the snapshot and are inside of a loaded
+    /// chunk are restored. Blocks which are unchanged since the snapshot
+    /// are not sent to clients again.
+    pub fn restore(&mut self, snapshot: &RegionSnapshot) {
+        let min_y = self.shared.dimension(self.dimension).min_y;
+
+        for (chunk_pos, columns) in region_columns(snapshot.min, snapshot.max) {
+            if let Some(chunk) = self.chunks.get_mut(&chunk_pos) {
+                let ys = snapshot.min.y.max(min_y)
+                    ..=snapshot.max.y.min(min_y + chunk.height() as i32 - 1);
+
+                for (x, z) in columns.clone() {
+                    for y in ys.clone() {
+                        if let Some(block) = snapshot.blocks[snapshot.index(BlockPos::new(x, y, z))]
+                        {
+                            chunk.set_block_state(
+                                x.rem_euclid(16) as usize,
+                                (y - min_y) as usize,
+                                z.rem_euclid(16) as usize,
+                                block,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn add_ticket(
+        &mut self,
+        center: ChunkPos,
+        radius: u32,
+        ttl: Option<Ticks>,
+    ) -> ChunkTicketId {
+        let id = ChunkTicketId(self.next_ticket_id);
+        self.next_ticket_id += 1;
+
+        let expires_at = ttl.map(|ttl| self.shared.current_tick() + ttl);
+        self.tickets.insert(
+            id,
+            ChunkTicket {
+                center,
+                radius,
+                expires_at,
+            },
+        );
+
+        id
+    }
+
+    pub(crate) fn remove_ticket(&mut self, id: ChunkTicketId) -> bool {
+        self.tickets.remove(&id).is_some()
+    }
+
+    pub(crate) fn player_tickets(&self) -> bool {
+        self.player_tickets
+    }
+
+    pub(crate) fn set_player_tickets(&mut self, enabled: bool) {
+        self.player_tickets = enabled;
+    }
+
+    /// Removes expired tickets, then loads the chunks covered by a ticket and
+    /// unloads the chunks loaded by tickets which are no longer covered.
+    ///
+    /// `viewers` are the chunk positions and view distances of the clients in
+    /// this world, which hold tickets if player tickets are enabled.
+    pub(crate) fn update_tickets(&mut self, viewers: impl Iterator<Item = (ChunkPos, u8)>) {
+        let current_tick = self.shared.current_tick();
+        self.tickets
+            .retain(|_, t| t.expires_at.is_none_or(|tick| tick > current_tick));
+
+        if self.tickets.is_empty() && !self.player_tickets && self.ticket_loaded.is_empty() {
+            return;
+        }
+
+        let mut covered = HashSet::new();
+
+        for t in self.tickets.values() {
+            let r = i64::from(t.radius);
+            for z in -r..=r {
+                for x in -r..=r {
+                    if x * x + z * z <= r * r {
+                        covered.insert(ChunkPos::new(t.center.x + x as i32, t.center.z + z as i32));
+                    }
+                }
+            }
+        }
+
+        if self.player_tickets {
+            for (center, dist) in viewers {
+                covered.extend(chunks_in_view_distance(center, dist));
+            }
+        }
+
+        let chunks = &mut self.chunks;
+        self.ticket_loaded.retain(|pos| {
+            let keep = covered.contains(pos);
+            if !keep {
+                chunks.remove(pos);
+            }
+            keep
+        });
+
+        for pos in covered {
+            if !self.chunks.contains_key(&pos) {
+                self.insert(pos, C::ChunkState::default());
+                self.ticket_loaded.insert(pos);
+            }
+        }
+    }
+
+    /// Marks all dirty chunks as saved and returns their positions along with
+    /// a copy of each.
+    pub(crate) fn take_dirty(&mut self) -> Vec<(ChunkPos, UnloadedChunk)> {
+        self.chunks
+            .par_iter_mut()
+            .filter(|(_, chunk)| chunk.dirty)
+            .map(|(&pos, chunk)| {
+                chunk.dirty = false;
+                (pos, chunk.to_unloaded())
+            })
+            .collect()
+    }
+
     /// Apply chunk modifications to only the chunks that were created this
-    /// tick.
+    /// tick or which have a section with more block changes than the
+    /// [resend threshold]. The latter are marked to be resent to clients.
+    ///
+    /// [resend threshold]: crate::config::Config::chunk_resend_threshold
     pub(crate) fn update_created_this_tick(&mut self) {
         let biome_registry_len = self.shared.biomes().len();
+        let resend_threshold = self.shared.chunk_resend_threshold();
         self.chunks.par_iter_mut().for_each(|(_, chunk)| {
             if chunk.created_this_tick() {
                 chunk.apply_modifications(biome_registry_len);
+            } else if chunk
+                .sections
+                .iter()
+                .any(|sect| sect.modified_count as usize > resend_threshold)
+            {
+                chunk.apply_modifications(biome_registry_len);
+                chunk.resent_this_tick = true;
             }
         });
     }
 
-    /// Apply chunk modifications to all chunks and clear the created_this_tick
-    /// flag.
+    /// Apply chunk modifications to all chunks, clear the created_this_tick
+    /// and resent_this_tick flags, and move the chunks changed this tick to
+    /// `changed_last_tick`.
     pub(crate) fn update(&mut self) {
         let biome_registry_len = self.shared.biomes().len();
-        self.chunks.par_iter_mut().for_each(|(_, chunk)| {
-            chunk.apply_modifications(biome_registry_len);
-            chunk.created_this_tick = false;
-        });
+        self.changed_last_tick = self
+            .chunks
+            .par_iter_mut()
+            .filter_map(|(&pos, chunk)| {
+                chunk.apply_modifications(biome_registry_len);
+                chunk.created_this_tick = false;
+                chunk.resent_this_tick = false;
+                mem::take(&mut chunk.changed_this_tick).then_some(pos)
+            })
+            .collect();
+    }
+}
+
+/// A ring buffer of the most recent block changes in a world, enabled with
+/// [`Chunks::set_block_log`].
+///
+/// Only changes made with [`Chunks::set_block_state`] and
+/// [`Chunks::set_block_state_by`] which actually change the block are
+/// recorded. Changes made directly to a [`Chunk`] are not. Once the log is
+/// full, the oldest change is dropped to make room for a new one.
+#[derive(Clone, Debug)]
+pub struct BlockChangeLog {
+    changes: VecDeque<BlockChange>,
+    capacity: usize,
+}
+
+/// A block change recorded by a [`BlockChangeLog`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BlockChange {
+    /// The position of the changed block.
+    pub pos: BlockPos,
+    /// The tick in which the block was changed.
+    pub tick: Ticks,
+    /// The UUID of the player who changed the block, if known.
+    pub actor: Option<Uuid>,
+    /// The block state before the change.
+    pub old: BlockState,
+    /// The block state after the change.
+    pub new: BlockState,
+}
+
+impl BlockChangeLog {
+    /// Creates an empty log holding up to `capacity` changes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            changes: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of changes held by the log.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of changes in the log.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Returns `true` if the log is empty.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Removes all changes from the log.
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Returns an iterator over the changes in the log from oldest to newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &BlockChange> + ExactSizeIterator + '_ {
+        self.changes.iter()
+    }
+
+    /// Returns an iterator over the changes to the block at `pos` from oldest
+    /// to newest.
+    pub fn changes_at(
+        &self,
+        pos: impl Into<BlockPos>,
+    ) -> impl DoubleEndedIterator<Item = &BlockChange> + '_ {
+        let pos = pos.into();
+        self.changes.iter().filter(move |c| c.pos == pos)
+    }
+
+    /// Returns an iterator over the changes to the blocks in the box between
+    /// the corners `a` and `b`, inclusive, from oldest to newest.
+    pub fn changes_in(
+        &self,
+        a: impl Into<BlockPos>,
+        b: impl Into<BlockPos>,
+    ) -> impl DoubleEndedIterator<Item = &BlockChange> + '_ {
+        let (a, b) = (a.into(), b.into());
+        let xs = a.x.min(b.x)..=a.x.max(b.x);
+        let ys = a.y.min(b.y)..=a.y.max(b.y);
+        let zs = a.z.min(b.z)..=a.z.max(b.z);
+
+        self.changes.iter().filter(move |c| {
+            xs.contains(&c.pos.x) && ys.contains(&c.pos.y) && zs.contains(&c.pos.z)
+        })
+    }
+
+    fn push(&mut self, change: BlockChange) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.changes.len() == self.capacity {
+            self.changes.pop_front();
+        }
+
+        self.changes.push_back(change);
+    }
+}
+
+/// The blocks in a box-shaped region of a world, taken with
+/// [`Chunks::snapshot`] and restored with [`Chunks::restore`].
+///
+/// This is useful to reset part of a world, such as a minigame arena, without
+/// regenerating or reloading its chunks.
+#[derive(Clone, Debug)]
+pub struct RegionSnapshot {
+    min: BlockPos,
+    max: BlockPos,
+    /// The blocks in the region stored in x, z, y order, or `None` for blocks
+    /// which were not in a loaded chunk.
+    blocks: Vec<Option<BlockState>>,
+}
+
+impl RegionSnapshot {
+    /// Returns the corner of the region with the lowest coordinates.
+    pub fn min(&self) -> BlockPos {
+        self.min
+    }
+
+    /// Returns the corner of the region with the highest coordinates.
+    pub fn max(&self) -> BlockPos {
+        self.max
+    }
+
+    /// Gets the block state at a position in the snapshot.
+    ///
+    /// If the position is outside of the region or was not in a loaded chunk
+    /// when the snapshot was taken, then `None` is returned.
+    pub fn get_block_state(&self, pos: impl Into<BlockPos>) -> Option<BlockState> {
+        let pos = pos.into();
+
+        if (self.min.x..=self.max.x).contains(&pos.x)
+            && (self.min.y..=self.max.y).contains(&pos.y)
+            && (self.min.z..=self.max.z).contains(&pos.z)
+        {
+            self.blocks[self.index(pos)]
+        } else {
+            None
+        }
+    }
+
+    fn volume(min: BlockPos, max: BlockPos) -> usize {
+        (max.x - min.x + 1) as usize * (max.y - min.y + 1) as usize * (max.z - min.z + 1) as usize
+    }
+
+    fn index(&self, pos: BlockPos) -> usize {
+        let size_x = (self.max.x - self.min.x + 1) as usize;
+        let size_z = (self.max.z - self.min.z + 1) as usize;
+
+        (pos.x - self.min.x) as usize
+            + (pos.z - self.min.z) as usize * size_x
+            + (pos.y - self.min.y) as usize * size_x * size_z
     }
 }
 
+/// Returns the chunks intersecting the box between `min` and `max` along with
+/// the block columns of each chunk inside of the box.
+fn region_columns(
+    min: BlockPos,
+    max: BlockPos,
+) -> impl Iterator<Item = (ChunkPos, impl Iterator<Item = (i32, i32)> + Clone)> {
+    (min.z.div_euclid(16)..=max.z.div_euclid(16)).flat_map(move |cz| {
+        (min.x.div_euclid(16)..=max.x.div_euclid(16)).map(move |cx| {
+            let xs = min.x.max(cx * 16)..=max.x.min(cx * 16 + 15);
+            let zs = min.z.max(cz * 16)..=max.z.min(cz * 16 + 15);
+
+            (
+                ChunkPos::new(cx, cz),
+                zs.flat_map(move |z| xs.clone().map(move |x| (x, z))),
+            )
+        })
+    })
+}
+
 /// A chunk is a 16x16-block segment of a world with a height determined by the
 /// [`Dimension`](crate::dimension::Dimension) of the world.
 ///
@@ -228,6 +818,12 @@ pub struct Chunk<C: Config> {
     /// The MOTION_BLOCKING heightmap
     heightmap: Vec<i64>,
     created_this_tick: bool,
+    /// If this chunk has too many changes this tick to send as block changes,
+    /// so the whole chunk is sent to clients instead.
+    resent_this_tick: bool,
+    /// If this chunk was modified since it was last saved.
+    dirty: bool,
+    changed_this_tick: bool,
 }
 
 impl<C: Config> Chunk<C> {
@@ -244,17 +840,116 @@ impl<C: Config> Chunk<C> {
             sections: vec![sect; section_count as usize].into(),
             heightmap: Vec::new(),
             created_this_tick: true,
+            resent_this_tick: false,
+            dirty: false,
+            changed_this_tick: false,
         };
 
         chunk.apply_modifications(biome_registry_len);
         chunk
     }
 
+    fn from_prototype(
+        prototype: &UnloadedChunk,
+        section_count: usize,
+        biome_registry_len: usize,
+        data: C::ChunkState,
+    ) -> Self {
+        let air = UnloadedSection::filled(BlockState::AIR);
+
+        let sections = (0..section_count)
+            .map(|i| {
+                let proto = prototype.sections.get(i).unwrap_or(&air);
+
+                let mut sect = ChunkSection {
+                    blocks: proto.blocks,
+                    modified_count: 0,
+                    biomes: proto.biomes,
+                    compact_data: proto.block_data.clone(),
+                };
+
+                encode_biomes(&sect.biomes, biome_registry_len, &mut sect.compact_data);
+                sect
+            })
+            .collect::<Box<[_]>>();
+
+        let mut heightmap = Vec::new();
+        build_heightmap(&sections, &mut heightmap);
+
+        Self {
+            state: data,
+            sections,
+            heightmap,
+            created_this_tick: true,
+            resent_this_tick: false,
+            dirty: false,
+            changed_this_tick: false,
+        }
+    }
+
     /// Returns `true` if this chunk was created during the current tick.
     pub fn created_this_tick(&self) -> bool {
         self.created_this_tick
     }
 
+    /// Returns `true` if the blocks or biomes of this chunk were modified since
+    /// it was created or last saved by [`Config::save_chunk`].
+    ///
+    /// [`Config::save_chunk`]: crate::config::Config::save_chunk
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Sets whether this chunk is [dirty](Self::is_dirty). Set it to `false`
+    /// after saving the chunk by other means.
+    pub fn set_dirty(&mut self, dirty: bool) {
+        self.dirty = dirty;
+    }
+
+    /// Returns `true` if the blocks or biomes of this chunk were modified
+    /// during the current tick. Unlike the [dirty](Self::is_dirty) flag, this
+    /// is cleared at the end of every tick.
+    ///
+    /// See also [`Chunks::changed_last_tick`].
+    pub fn changed_this_tick(&self) -> bool {
+        self.changed_this_tick
+    }
+
+    /// Copies the blocks and biomes of this chunk into an [`UnloadedChunk`],
+    /// which can be saved or inserted into another world.
+    pub fn to_unloaded(&self) -> UnloadedChunk {
+        UnloadedChunk {
+            sections: self
+                .sections
+                .iter()
+                .map(|sect| {
+                    let mut unloaded =
+                        UnloadedSection::new(sect.blocks.map(|b| b & BLOCK_STATE_MASK));
+                    unloaded.biomes = sect.biomes;
+                    unloaded
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns an estimate of the memory allocated by this chunk in bytes, not
+    /// including the size of the chunk itself.
+    fn heap_memory_usage(&self) -> usize {
+        self.sections.len() * mem::size_of::<ChunkSection>()
+            + self
+                .sections
+                .iter()
+                .map(|sect| sect.compact_data.capacity())
+                .sum::<usize>()
+            + self.heightmap.capacity() * mem::size_of::<i64>()
+    }
+
+    /// Returns `true` if this chunk is sent to clients in full this tick
+    /// because it has too many block changes.
+    pub(crate) fn resent_this_tick(&self) -> bool {
+        self.resent_this_tick
+    }
+
     /// Returns the height of this chunk in blocks.
     pub fn height(&self) -> usize {
         self.sections.len() * 16
@@ -276,6 +971,21 @@ impl<C: Config> Chunk<C> {
         )
     }
 
+    /// Returns whether the block at the provided offsets was changed this
+    /// tick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offsets are outside the bounds of the chunk.
+    pub(crate) fn is_block_modified(&self, x: usize, y: usize, z: usize) -> bool {
+        assert!(
+            x < 16 && y < self.height() && z < 16,
+            "chunk block offsets must be within bounds"
+        );
+
+        self.sections[y / 16].blocks[x + z * 16 + y % 16 * 16 * 16] & !BLOCK_STATE_MASK != 0
+    }
+
     /// Sets the block state at the provided offsets in the chunk.
     ///
     /// # Panics
@@ -295,6 +1005,8 @@ impl<C: Config> Chunk<C> {
                 sect.modified_count += 1;
             }
             sect.blocks[idx] = block.to_raw() | !BLOCK_STATE_MASK;
+            self.dirty = true;
+            self.changed_this_tick = true;
 
             // TODO: if the block type was modified and the old block type
             // could be a block entity, then the block entity at this
@@ -334,6 +1046,8 @@ impl<C: Config> Chunk<C> {
         );
 
         self.sections[y / 4].biomes[x + z * 4 + y % 4 * 4 * 4] = b;
+        self.dirty = true;
+        self.changed_this_tick = true;
     }
 
     /// Gets the chunk data packet for this chunk with the given position. This
@@ -367,6 +1081,10 @@ impl<C: Config> Chunk<C> {
 
     /// Returns unapplied changes to this chunk as block change packets through
     /// the provided closure.
+    ///
+    /// Sections with a single changed block produce a [`BlockUpdate`]. All
+    /// changes to a section with more than one changed block are batched into
+    /// a single [`ChunkSectionUpdate`].
     pub(crate) fn block_change_packets(
         &self,
         pos: ChunkPos,
@@ -391,22 +1109,17 @@ impl<C: Config> Chunk<C> {
                     block_id: VarInt((block & BLOCK_STATE_MASK).into()),
                 }));
             } else if sect.modified_count > 1 {
-                let mut blocks = Vec::new();
-                for y in 0..16 {
-                    for z in 0..16 {
-                        for x in 0..16 {
-                            let block =
-                                sect.blocks[x as usize + z as usize * 16 + y as usize * 16 * 16];
-
-                            if block & !BLOCK_STATE_MASK != 0 {
-                                blocks.push(VarLong(
-                                    ((block & BLOCK_STATE_MASK) as i64) << 12
-                                        | (x << 8 | z << 4 | y),
-                                ))
-                            }
-                        }
-                    }
-                }
+                let blocks: Vec<_> = sect
+                    .blocks
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &b)| b & !BLOCK_STATE_MASK != 0)
+                    .take(sect.modified_count as usize)
+                    .map(|(idx, &block)| {
+                        let (x, z, y) = (idx as i64 % 16, idx as i64 / 16 % 16, idx as i64 / 256);
+                        VarLong(((block & BLOCK_STATE_MASK) as i64) << 12 | (x << 8 | z << 4 | y))
+                    })
+                    .collect();
 
                 let chunk_section_position = (pos.x as i64) << 42
                     | (pos.z as i64 & 0x3fffff) << 20
@@ -431,34 +1144,12 @@ impl<C: Config> Chunk<C> {
 
                 sect.compact_data.clear();
 
-                let mut non_air_block_count: i16 = 0;
-
                 for b in &mut sect.blocks {
                     *b &= BLOCK_STATE_MASK;
-                    if !BlockState::from_raw_unchecked(*b).is_air() {
-                        non_air_block_count += 1;
-                    }
                 }
 
-                non_air_block_count.encode(&mut sect.compact_data).unwrap();
-
-                encode_paletted_container(
-                    sect.blocks.iter().cloned(),
-                    4,
-                    9,
-                    log2_ceil(BlockState::max_raw() as usize),
-                    &mut sect.compact_data,
-                )
-                .unwrap();
-
-                encode_paletted_container(
-                    sect.biomes.iter().map(|b| b.0),
-                    0,
-                    4,
-                    log2_ceil(biome_registry_len),
-                    &mut sect.compact_data,
-                )
-                .unwrap();
+                encode_blocks(&sect.blocks, &mut sect.compact_data);
+                encode_biomes(&sect.biomes, biome_registry_len, &mut sect.compact_data);
             }
         }
 
@@ -483,6 +1174,143 @@ impl From<BlockChangePacket> for S2cPlayPacket {
     }
 }
 
+/// A chunk of blocks which is not part of a world.
+///
+/// Unloaded chunks are built in bulk with [`UnloadedChunk::from_fn`] or
+/// [`UnloadedChunk::filled`] and added to a world with
+/// [`Chunks::insert_with`]. The encoded form of every section is computed
+/// once when the unloaded chunk is created, which makes them useful as
+/// prototypes for worlds made of many identical chunks such as arenas.
+///
+/// Biomes are the default biome unless they are changed with
+/// [`UnloadedChunk::set_biome`].
+#[derive(Clone)]
+pub struct UnloadedChunk {
+    sections: Vec<UnloadedSection>,
+}
+
+impl UnloadedChunk {
+    /// Creates an unloaded chunk with `section_count` sections of 16 blocks
+    /// in height. The block at each offset in the chunk is determined by
+    /// `f`, which is called with the `x`, `y` and `z` offsets.
+    pub fn from_fn(
+        section_count: usize,
+        mut f: impl FnMut(usize, usize, usize) -> BlockState,
+    ) -> Self {
+        let sections = (0..section_count)
+            .map(|sect_y| {
+                let mut blocks = [0; 4096];
+
+                for (idx, block) in blocks.iter_mut().enumerate() {
+                    let x = idx % 16;
+                    let z = idx / 16 % 16;
+                    let y = sect_y * 16 + idx / (16 * 16);
+
+                    *block = f(x, y, z).to_raw();
+                }
+
+                UnloadedSection::new(blocks)
+            })
+            .collect();
+
+        Self { sections }
+    }
+
+    /// Creates an unloaded chunk where every section is filled with a single
+    /// block state, from the bottom of the chunk upwards.
+    ///
+    /// ```
+    /// # use valence::block::BlockState;
+    /// # use valence::chunk::UnloadedChunk;
+    /// let floor = UnloadedChunk::filled([BlockState::BEDROCK, BlockState::STONE]);
+    /// assert_eq!(floor.height(), 32);
+    /// ```
+    pub fn filled(sections: impl IntoIterator<Item = BlockState>) -> Self {
+        Self {
+            sections: sections.into_iter().map(UnloadedSection::filled).collect(),
+        }
+    }
+
+    /// Returns the height of this chunk in blocks.
+    pub fn height(&self) -> usize {
+        self.sections.len() * 16
+    }
+
+    /// Gets the block state at the provided offsets in the chunk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offsets are outside the bounds of the chunk.
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> BlockState {
+        assert!(
+            x < 16 && y < self.height() && z < 16,
+            "chunk block offsets must be within bounds"
+        );
+
+        BlockState::from_raw_unchecked(self.sections[y / 16].blocks[x + z * 16 + y % 16 * 16 * 16])
+    }
+
+    /// Gets the biome at the provided biome offsets in the chunk.
+    ///
+    /// Note: the arguments are **not** block positions. Biomes are 4x4x4
+    /// segments of a chunk, so `x` and `z` are in `0..=4`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offsets are outside the bounds of the chunk.
+    pub fn get_biome(&self, x: usize, y: usize, z: usize) -> BiomeId {
+        assert!(
+            x < 4 && y < self.height() / 4 && z < 4,
+            "chunk biome offsets must be within bounds"
+        );
+
+        self.sections[y / 4].biomes[x + z * 4 + y % 4 * 4 * 4]
+    }
+
+    /// Sets the biome at the provided biome offsets in the chunk.
+    ///
+    /// Note: the arguments are **not** block positions. Biomes are 4x4x4
+    /// segments of a chunk, so `x` and `z` are in `0..=4`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offsets are outside the bounds of the chunk.
+    pub fn set_biome(&mut self, x: usize, y: usize, z: usize, b: BiomeId) {
+        assert!(
+            x < 4 && y < self.height() / 4 && z < 4,
+            "chunk biome offsets must be within bounds"
+        );
+
+        self.sections[y / 4].biomes[x + z * 4 + y % 4 * 4 * 4] = b;
+    }
+}
+
+#[derive(Clone)]
+struct UnloadedSection {
+    /// The block states in this section, stored in x, z, y order.
+    blocks: [u16; 4096],
+    /// The non-air block count and paletted block states of this section.
+    block_data: Vec<u8>,
+    biomes: [BiomeId; 64],
+}
+
+impl UnloadedSection {
+    fn new(blocks: [u16; 4096]) -> Self {
+        let mut block_data = Vec::new();
+        encode_blocks(&blocks, &mut block_data);
+
+        Self {
+            blocks,
+            block_data,
+            biomes: [BiomeId::default(); 64],
+        }
+    }
+
+    fn filled(block: BlockState) -> Self {
+        Self::new([block.to_raw(); 4096])
+    }
+}
+
 /// A 16x16x16 section of blocks, biomes, and light in a chunk.
 #[derive(Clone)]
 struct ChunkSection {
@@ -506,9 +1334,13 @@ const _: () = assert!(
 );
 
 /// Builds the MOTION_BLOCKING heightmap.
+///
+/// Each column stores the height of the first free block above the highest
+/// non-air block relative to the bottom of the chunk, so values range from 0
+/// to the height of the chunk inclusive.
 fn build_heightmap(sections: &[ChunkSection], heightmap: &mut Vec<i64>) {
     let height = sections.len() * 16;
-    let bits_per_val = log2_ceil(height);
+    let bits_per_val = log2_ceil(height + 1);
     let vals_per_u64 = 64 / bits_per_val;
     let u64_count = Integer::div_ceil(&256, &vals_per_u64);
 
@@ -524,9 +1356,9 @@ fn build_heightmap(sections: &[ChunkSection], heightmap: &mut Vec<i64>) {
 
                 // TODO: is_solid || is_fluid heuristic for motion blocking.
                 if !block.is_air() {
-                    let column_height = y as u64;
+                    let column_height = y as u64 + 1;
 
-                    let i = x * 16 + z; // TODO: X or Z major?
+                    let i = x + z * 16;
                     heightmap[i / vals_per_u64] |=
                         (column_height << (i % vals_per_u64 * bits_per_val)) as i64;
 
@@ -537,6 +1369,37 @@ fn build_heightmap(sections: &[ChunkSection], heightmap: &mut Vec<i64>) {
     }
 }
 
+/// Encodes the non-air block count and block states of a chunk section. The
+/// blocks must not have the modified bit set.
+fn encode_blocks(blocks: &[u16; 4096], out: &mut Vec<u8>) {
+    let non_air_block_count = blocks
+        .iter()
+        .filter(|&&b| !BlockState::from_raw_unchecked(b).is_air())
+        .count() as i16;
+
+    non_air_block_count.encode(&mut *out).unwrap();
+
+    encode_paletted_container(
+        blocks.iter().cloned(),
+        4,
+        9,
+        log2_ceil(BlockState::max_raw() as usize),
+        out,
+    )
+    .unwrap();
+}
+
+fn encode_biomes(biomes: &[BiomeId; 64], biome_registry_len: usize, out: &mut Vec<u8>) {
+    encode_paletted_container(
+        biomes.iter().map(|b| b.0),
+        0,
+        4,
+        log2_ceil(biome_registry_len),
+        out,
+    )
+    .unwrap();
+}
+
 fn encode_paletted_container(
     mut entries: impl ExactSizeIterator<Item = u16> + Clone,
     min_bits_per_idx: usize,
@@ -616,3 +1479,251 @@ fn log2_ceil(n: usize) -> usize {
     debug_assert_ne!(n, 0);
     n.next_power_of_two().trailing_zeros() as usize
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::block::BlockState;
+    use crate::event_bus::{ChunkChanged, EventResult};
+    use crate::testing::{connected_client, test_server};
+
+    #[test]
+    fn unloaded_chunk_constructors() {
+        let chunk = UnloadedChunk::from_fn(2, |x, y, z| {
+            if y < 16 || x == z {
+                BlockState::STONE
+            } else {
+                BlockState::AIR
+            }
+        });
+
+        assert_eq!(chunk.height(), 32);
+        assert_eq!(chunk.get_block_state(3, 4, 5), BlockState::STONE);
+        assert_eq!(chunk.get_block_state(7, 20, 7), BlockState::STONE);
+        assert_eq!(chunk.get_block_state(3, 20, 5), BlockState::AIR);
+
+        let filled = UnloadedChunk::filled([BlockState::STONE, BlockState::AIR]);
+        assert_eq!(filled.sections[0].block_data, chunk.sections[0].block_data);
+        assert_eq!(
+            filled.sections[1].block_data,
+            UnloadedSection::filled(BlockState::AIR).block_data
+        );
+        assert_ne!(filled.sections[1].block_data, chunk.sections[1].block_data);
+    }
+
+    #[test]
+    fn heightmap_fits_chunk_height() {
+        let section = ChunkSection {
+            blocks: [BlockState::AIR.to_raw(); 4096],
+            modified_count: 0,
+            biomes: [BiomeId::default(); 64],
+            compact_data: Vec::new(),
+        };
+
+        // 256 blocks tall, so heights from 0 to 256 need 9 bits.
+        let mut sections = vec![section; 16];
+        let (x, y, z) = (1, 100, 2);
+        sections[y / 16].blocks[x + z * 16 + y % 16 * 16 * 16] = BlockState::STONE.to_raw();
+
+        let mut heightmap = Vec::new();
+        build_heightmap(&sections, &mut heightmap);
+
+        let vals_per_u64 = 64 / 9;
+        assert_eq!(heightmap.len(), Integer::div_ceil(&256, &vals_per_u64));
+
+        let i = x + z * 16;
+        let val = heightmap[i / vals_per_u64] as u64 >> (i % vals_per_u64 * 9) & 0x1ff;
+        assert_eq!(val, y as u64 + 1);
+    }
+
+    #[test]
+    fn block_changes_batched_per_section() {
+        let (mut server, client) = connected_client();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        for x in 0..3 {
+            world.chunks.set_block_state([x, 64, 0], BlockState::STONE);
+        }
+        world.chunks.set_block_state([0, 80, 0], BlockState::STONE);
+        server.tick();
+
+        let pkts = client.take_packets();
+        let multi: Vec<_> = pkts
+            .iter()
+            .filter_map(|pkt| match pkt {
+                S2cPlayPacket::ChunkSectionUpdate(p) => Some(p.blocks.len()),
+                _ => None,
+            })
+            .collect();
+        let single = pkts
+            .iter()
+            .filter(|pkt| matches!(pkt, S2cPlayPacket::BlockUpdate(_)))
+            .count();
+
+        assert_eq!(multi, [3]);
+        assert_eq!(single, 1);
+    }
+
+    #[test]
+    fn heavily_edited_chunk_resent() {
+        let (mut server, client) = connected_client();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        for x in 0..9 {
+            world.chunks.set_block_state([x, 64, 0], BlockState::STONE);
+        }
+        server.tick();
+
+        let pkts = client.take_packets();
+        assert!(!pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::ChunkSectionUpdate(_) | S2cPlayPacket::BlockUpdate(_)
+        )));
+        assert!(pkts.iter().any(
+            |pkt| matches!(pkt, S2cPlayPacket::ChunkData(p) if p.chunk_x == 0 && p.chunk_z == 0)
+        ));
+
+        let (_, world) = server.server().worlds.iter().next().unwrap();
+        assert_eq!(
+            world.chunks.get_block_state([8, 64, 0]),
+            Some(BlockState::STONE)
+        );
+    }
+
+    #[test]
+    fn region_snapshot_restored() {
+        let mut server = test_server();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state([5, 64, 5], BlockState::STONE);
+
+        let snapshot = world.chunks.snapshot([40, 70, 10], [-10, 60, -10]);
+        assert_eq!(snapshot.min(), BlockPos::new(-10, 60, -10));
+        assert_eq!(snapshot.max(), BlockPos::new(40, 70, 10));
+        assert_eq!(
+            snapshot.get_block_state([5, 64, 5]),
+            Some(BlockState::STONE)
+        );
+        assert_eq!(snapshot.get_block_state([35, 64, 5]), None);
+        assert_eq!(snapshot.get_block_state([5, 71, 5]), None);
+
+        world.chunks.set_block_state([5, 64, 5], BlockState::AIR);
+        world
+            .chunks
+            .set_block_state([-10, 60, -10], BlockState::STONE);
+        world.chunks.set_block_state([0, 80, 0], BlockState::STONE);
+        world.chunks.insert([2, 0], ());
+        world.chunks.set_block_state([35, 64, 5], BlockState::STONE);
+
+        world.chunks.restore(&snapshot);
+
+        assert_eq!(
+            world.chunks.get_block_state([5, 64, 5]),
+            Some(BlockState::STONE)
+        );
+        assert_eq!(
+            world.chunks.get_block_state([-10, 60, -10]),
+            Some(BlockState::AIR)
+        );
+        assert_eq!(
+            world.chunks.get_block_state([0, 80, 0]),
+            Some(BlockState::STONE)
+        );
+        assert_eq!(
+            world.chunks.get_block_state([35, 64, 5]),
+            Some(BlockState::STONE)
+        );
+    }
+
+    #[test]
+    fn block_changes_logged() {
+        let mut server = test_server();
+        server.tick();
+
+        let actor = Uuid::from_u128(1);
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state([0, 64, 0], BlockState::DIRT);
+        assert!(world.chunks.block_log().is_none());
+
+        world.chunks.set_block_log(Some(BlockChangeLog::new(3)));
+        world.chunks.set_block_state([0, 64, 0], BlockState::STONE);
+        world.chunks.set_block_state([0, 64, 0], BlockState::STONE);
+        world
+            .chunks
+            .set_block_state_by([1, 64, 0], BlockState::STONE, actor);
+        world
+            .chunks
+            .set_block_state_by([0, 64, 0], BlockState::AIR, actor);
+
+        let log = world.chunks.block_log().unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(
+            log.changes_at([0, 64, 0])
+                .map(|c| (c.old, c.new, c.actor))
+                .collect::<Vec<_>>(),
+            [
+                (BlockState::DIRT, BlockState::STONE, None),
+                (BlockState::STONE, BlockState::AIR, Some(actor)),
+            ]
+        );
+        assert_eq!(log.changes_in([1, 0, 1], [5, 100, -1]).count(), 1);
+        assert!(log.iter().all(|c| c.tick == 1));
+
+        world.chunks.set_block_state([2, 64, 0], BlockState::STONE);
+        let log = world.chunks.block_log().unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.iter().next().unwrap().pos, BlockPos::new(1, 64, 0));
+    }
+
+    #[test]
+    fn chunk_changes_reported() {
+        let mut server = test_server();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        server.server_mut().event_bus.subscribe({
+            let log = log.clone();
+            move |_, event: &mut ChunkChanged| {
+                log.lock().unwrap().push(event.pos);
+                EventResult::Continue
+            }
+        });
+        server.tick();
+        assert!(log.lock().unwrap().is_empty());
+
+        let (world_id, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        assert!(world.chunks.set_block_state([1, 64, 1], BlockState::STONE));
+        assert!(world.chunks.set_block_state([-3, 64, 1], BlockState::STONE));
+        // Setting the same block is not a change.
+        let chunk = world.chunks.get_mut(ChunkPos::new(1, 0)).unwrap();
+        chunk.set_block_state(0, 0, 0, BlockState::AIR);
+        assert!(!chunk.changed_this_tick());
+        assert!(world
+            .chunks
+            .get(ChunkPos::new(0, 0))
+            .unwrap()
+            .changed_this_tick());
+        server.tick();
+
+        let world = server.server().worlds.get(world_id).unwrap();
+        assert!(!world
+            .chunks
+            .get(ChunkPos::new(0, 0))
+            .unwrap()
+            .changed_this_tick());
+        let mut changed = world.chunks.changed_last_tick().to_vec();
+        changed.sort_by_key(|pos| (pos.x, pos.z));
+        assert_eq!(changed, [ChunkPos::new(-1, 0), ChunkPos::new(0, 0)]);
+
+        // The events are posted at the beginning of the next tick.
+        assert!(log.lock().unwrap().is_empty());
+        server.tick();
+        let mut events = mem::take(&mut *log.lock().unwrap());
+        events.sort_by_key(|pos| (pos.x, pos.z));
+        assert_eq!(events, changed);
+
+        server.tick();
+        assert!(log.lock().unwrap().is_empty());
+    }
+}
@@ -20,6 +20,37 @@ impl ChunkPos {
     pub fn at(x: f64, z: f64) -> Self {
         Self::new((x / 16.0).floor() as i32, (z / 16.0).floor() as i32)
     }
+
+    /// Returns the absolute position of a block in this chunk from its
+    /// offsets. `x` and `z` are in `0..16`.
+    pub fn block_pos(self, x: usize, y: i32, z: usize) -> BlockPos {
+        debug_assert!(
+            x < 16 && z < 16,
+            "chunk block offsets must be within bounds"
+        );
+        BlockPos::new(self.x * 16 + x as i32, y, self.z * 16 + z as i32)
+    }
+
+    /// Returns the sum of the absolute differences between the components of
+    /// two chunk positions.
+    pub fn manhattan_distance(self, other: Self) -> u32 {
+        self.x.abs_diff(other.x) + self.z.abs_diff(other.z)
+    }
+
+    /// Returns the largest absolute difference between the components of two
+    /// chunk positions. This is the distance used for view distances.
+    pub fn chebyshev_distance(self, other: Self) -> u32 {
+        self.x.abs_diff(other.x).max(self.z.abs_diff(other.z))
+    }
+
+    /// Returns an iterator over all chunk positions in the rectangle with
+    /// corners `a` and `b`, inclusive. The corners can be given in any order.
+    pub fn iter_between(a: Self, b: Self) -> impl Iterator<Item = Self> + Clone {
+        let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+        let (min_z, max_z) = (a.z.min(b.z), a.z.max(b.z));
+
+        (min_z..=max_z).flat_map(move |z| (min_x..=max_x).map(move |x| Self::new(x, z)))
+    }
 }
 
 impl From<(i32, i32)> for ChunkPos {
@@ -51,3 +82,20 @@ impl From<BlockPos> for ChunkPos {
         Self::new(pos.x.div_euclid(16), pos.z.div_euclid(16))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn math() {
+        let a = ChunkPos::new(-3, 2);
+        let b = ChunkPos::new(1, 1);
+
+        assert_eq!(a.manhattan_distance(b), 5);
+        assert_eq!(a.chebyshev_distance(b), 4);
+        assert_eq!(ChunkPos::iter_between(a, b).count(), 5 * 2);
+        assert_eq!(a.block_pos(15, -64, 0), BlockPos::new(-33, -64, 32));
+        assert_eq!(ChunkPos::from(a.block_pos(15, 0, 0)), a);
+    }
+}
@@ -3,51 +3,77 @@
 use std::collections::{HashSet, VecDeque};
 use std::iter::FusedIterator;
 use std::mem;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub use bitfield_struct::bitfield;
 pub use event::*;
 use flume::{Receiver, Sender, TrySendError};
+use glow::ViewerGlow;
+pub(crate) use network_stats::NetworkCounters;
+use network_stats::RateMeter;
+pub use network_stats::{FloodLimit, NetworkStats};
+use packet_limits::{PacketKind, RateCounter};
+pub use packet_limits::{PacketLimits, RateLimit, RateLimitAction};
 use rayon::iter::ParallelIterator;
+pub use titles::Titles;
 use uuid::Uuid;
 use vek::Vec3;
+pub use view_snapshot::ViewSnapshot;
 
 use crate::biome::Biome;
+use crate::block::BlockKind;
 use crate::block_pos::BlockPos;
 use crate::chunk_pos::ChunkPos;
+use crate::command::Commands;
+use crate::component::Components;
 use crate::config::Config;
 use crate::dimension::DimensionId;
 use crate::entity::data::Player;
+use crate::entity::types::Pose;
 use crate::entity::{
-    velocity_to_packet_units, Entities, EntityEvent, EntityId, EntityKind, StatusOrAnimation,
+    velocity_to_packet_units, Entities, Entity, EntityEvent, EntityId, EntityKind, MovementUpdate,
+    StatusOrAnimation, TrackedData,
 };
+use crate::firework::explosion_tracked_data;
 use crate::ident::Ident;
-use crate::player_list::{PlayerListId, PlayerLists};
+use crate::permission::Permissions;
+use crate::player_list::{PlayerList, PlayerListId, PlayerLists};
 use crate::player_textures::SignedPlayerTextures;
+use crate::protection::{ProtectedAction, Protection};
 use crate::protocol::packets::c2s::play::{
-    C2sPlayPacket, DiggingStatus, InteractKind, PlayerCommandId,
+    C2sPlayPacket, ClientStatus, DiggingStatus as PacketDiggingStatus, InteractKind,
+    PlayerCommandId,
 };
-pub use crate::protocol::packets::s2c::play::TitleFade;
 use crate::protocol::packets::s2c::play::{
-    BiomeRegistry, ChatTypeRegistry, ChunkLoadDistance, ChunkRenderDistanceCenter, ClearTitles,
-    DimensionTypeRegistry, DimensionTypeRegistryEntry, Disconnect, EntitiesDestroy,
-    EntityAnimation, EntityAttributes, EntityAttributesProperty, EntityPosition, EntitySetHeadYaw,
-    EntityStatus, EntityTrackerUpdate, EntityVelocityUpdate, GameJoin, GameMessage,
-    GameStateChange, GameStateChangeReason, KeepAlive, MoveRelative, OverlayMessage, PlaySoundId,
-    PlayerActionResponse, PlayerPositionLook, PlayerPositionLookFlags, PlayerRespawn,
-    PlayerSpawnPosition, RegistryCodec, Rotate, RotateAndMoveRelative, S2cPlayPacket,
-    SoundCategory, UnloadChunk, UpdateSubtitle, UpdateTitle,
+    BiomeRegistry, BlockUpdate, ChatTypeRegistry, ChunkLoadDistance, ChunkRenderDistanceCenter,
+    ClearTitles, DimensionTypeRegistry, DimensionTypeRegistryEntry, Disconnect, EntitiesDestroy,
+    EntityAnchor, EntityAnimation, EntityAttributes, EntityAttributesProperty, EntityPosition,
+    EntitySetHeadYaw, EntitySpawn, EntityStatus, EntityTrackerUpdate, EntityVelocityUpdate,
+    GameJoin, GameMessage, GameStateChange, GameStateChangeReason, KeepAlive, LookAt, MoveRelative,
+    OverlayMessage, PlaySoundId, PlayerActionResponse, PlayerListHeaderFooter, PlayerPositionLook,
+    PlayerPositionLookFlags, PlayerRespawn, PlayerSpawnPosition, RegistryCodec, Rotate,
+    RotateAndMoveRelative, S2cPlayPacket, SoundCategory, UnloadChunk, UpdatePlayerList,
+    UpdateSubtitle, UpdateTitle, WorldEvent,
 };
-use crate::protocol::{BoundedInt, ByteAngle, NbtBridge, RawBytes, VarInt};
-use crate::server::{C2sPacketChannels, NewClientData, S2cPlayMessage, SharedServer};
+pub use crate::protocol::packets::s2c::play::{TeamColor, TitleFade};
+use crate::protocol::{BoundedInt, ByteAngle, Decode, NbtBridge, RawBytes, VarInt};
+use crate::scoreboard::{update_sidebar_packets, Sidebar};
+use crate::server::{C2sPacketChannels, NewClientData, PacketLane, S2cPlayMessage, SharedServer};
 use crate::slab_versioned::{Key, VersionedSlab};
 use crate::text::Text;
 use crate::util::{chunks_in_view_distance, is_chunk_in_view_distance};
-use crate::world::{WorldId, Worlds};
-use crate::{ident, LIBRARY_NAMESPACE};
+use crate::world::{LevelEvent, WorldId, WorldMeta, Worlds};
+use crate::{ident, Ticks, LIBRARY_NAMESPACE};
 
 /// Contains the [`ClientEvent`] enum and related data types.
 mod event;
+mod glow;
+mod network_stats;
+mod packet_limits;
+mod titles;
+mod view_snapshot;
 
 /// A container for all [`Client`]s on a [`Server`](crate::server::Server).
 ///
@@ -56,12 +82,16 @@ mod event;
 /// they disconnect. This can be checked with [`Client::is_disconnected`].
 pub struct Clients<C: Config> {
     slab: VersionedSlab<Client<C>>,
+    /// The UUIDs and states of the clients removed with [`Self::retain`] which
+    /// have not been passed to [`Config::save_player_data`] yet.
+    removed: Vec<(Uuid, C::ClientState)>,
 }
 
 impl<C: Config> Clients<C> {
     pub(crate) fn new() -> Self {
         Self {
             slab: VersionedSlab::new(),
+            removed: Vec::new(),
         }
     }
 
@@ -79,11 +109,27 @@ impl<C: Config> Clients<C> {
         self.slab.remove(client.0).map(|c| c.state)
     }
 
-    /// Deletes all clients from the server for which `f` returns `true`.
+    /// Deletes all clients from the server for which `f` returns `false`.
+    /// The states of the deleted clients are passed to
+    /// [`Config::save_player_data`].
     ///
     /// All clients are visited in an unspecified order.
     pub fn retain(&mut self, mut f: impl FnMut(ClientId, &mut Client<C>) -> bool) {
-        self.slab.retain(|k, v| f(ClientId(k), v))
+        let removed = &mut self.removed;
+
+        self.slab.retain(|k, v| {
+            let keep = f(ClientId(k), v);
+            if !keep {
+                removed.push((v.uuid, mem::take(&mut v.state)));
+            }
+            keep
+        })
+    }
+
+    /// Takes the UUIDs and states of the clients removed with
+    /// [`Self::retain`] since the last call.
+    pub(crate) fn take_removed(&mut self) -> Vec<(Uuid, C::ClientState)> {
+        mem::take(&mut self.removed)
     }
 
     /// Returns the number of clients on the server. This includes clients for
@@ -139,6 +185,46 @@ impl<C: Config> Clients<C> {
     ) -> impl ParallelIterator<Item = (ClientId, &mut Client<C>)> + '_ {
         self.slab.par_iter_mut().map(|(k, v)| (ClientId(k), v))
     }
+
+    /// Returns the ID of the client with the given username, ignoring case.
+    /// If no such client exists, then `None` is returned.
+    ///
+    /// This is useful for finding the recipient of a private message.
+    pub fn find_by_username(&self, username: &str) -> Option<ClientId> {
+        self.iter()
+            .find(|(_, client)| client.username().eq_ignore_ascii_case(username))
+            .map(|(id, _)| id)
+    }
+
+    /// Sends a chat message to every client on the server with
+    /// [`Client::send_message`].
+    pub fn broadcast(&mut self, msg: impl Into<Text>) {
+        self.broadcast_filter(msg, |_, _| true);
+    }
+
+    /// Sends a chat message to every client in `world` with
+    /// [`Client::send_message`].
+    pub fn broadcast_to_world(&mut self, world: WorldId, msg: impl Into<Text>) {
+        self.broadcast_filter(msg, |_, client| client.world() == world);
+    }
+
+    /// Sends a chat message with [`Client::send_message`] to every client for
+    /// which `f` returns `true`.
+    ///
+    /// All clients are visited in an unspecified order.
+    pub fn broadcast_filter(
+        &mut self,
+        msg: impl Into<Text>,
+        mut f: impl FnMut(ClientId, &Client<C>) -> bool,
+    ) {
+        let msg = msg.into();
+
+        for (id, client) in self.iter_mut() {
+            if f(id, client) {
+                client.send_message(msg.clone());
+            }
+        }
+    }
 }
 
 /// An identifier for a [`Client`] on the server.
@@ -184,15 +270,35 @@ impl ClientId {
 pub struct Client<C: Config> {
     /// Custom state.
     pub state: C::ClientState,
+    /// State attached by independent modules. See [`component`](crate::component).
+    pub components: Components,
     /// Setting this to `None` disconnects the client.
     send: SendOpt,
     recv: Receiver<C2sPlayPacket>,
     uuid: Uuid,
     username: String,
     textures: Option<SignedPlayerTextures>,
+    remote_addr: SocketAddr,
+    server_address: String,
+    forge_marker: Option<String>,
+    protocol_version: i32,
+    brand: Option<String>,
     world: WorldId,
-    old_player_list: Option<PlayerListId>,
-    new_player_list: Option<PlayerListId>,
+    old_player_lists: Vec<PlayerListId>,
+    new_player_lists: Vec<PlayerListId>,
+    /// Unlisted player list entries that were added to the client and the tick
+    /// at which they should be removed from its tab list.
+    pending_unlisted: Vec<(Uuid, i64)>,
+    /// Packets waiting for the tick they should be sent at, ordered by that
+    /// tick.
+    scheduled_packets: Vec<(Ticks, S2cPlayPacket)>,
+    /// The player entity the skin settings were last copied to.
+    skin_synced_entity: Option<EntityId>,
+    /// The state of the player entity to create for this client at the next
+    /// sync.
+    new_player_entity: Option<C::EntityState>,
+    /// The player entity owned by this client.
+    player_entity: Option<EntityId>,
     position: Vec3<f64>,
     old_position: Vec3<f64>,
     /// Measured in m/s.
@@ -202,12 +308,18 @@ pub struct Client<C: Config> {
     /// Measured in degrees
     pitch: f32,
     view_distance: u8,
+    /// The view distance limit set by the server while it is overloaded.
+    view_distance_limit: u8,
     /// Counts up as teleports are made.
     teleport_id_counter: u32,
     /// The number of pending client teleports that have yet to receive a
     /// confirmation. Inbound client position packets are ignored while this
     /// is nonzero.
     pending_teleports: u32,
+    /// The components of the teleport made this tick which are relative.
+    teleport_flags: TeleportFlags,
+    /// The relative components of the teleport made this tick.
+    teleport_offset: (Vec3<f64>, f32, f32),
     spawn_position: BlockPos,
     spawn_position_yaw: f32,
     death_location: Option<(DimensionId, BlockPos)>,
@@ -218,14 +330,37 @@ pub struct Client<C: Config> {
     /// This is used to determine what entity create/destroy packets should be
     /// sent.
     loaded_entities: HashSet<EntityId>,
+    /// Loaded entities which were spawned as a different kind by their spawn
+    /// hook, so their tracked data is not sent.
+    disguised_entities: HashSet<EntityId>,
     loaded_chunks: HashSet<ChunkPos>,
     new_game_mode: GameMode,
     old_game_mode: GameMode,
     settings: Option<Settings>,
     dug_blocks: Vec<i32>,
-    /// Should be sent after login packet.
-    msgs_to_send: Vec<Text>,
+    /// Blocks the client has broken this tick, which are restored on the client
+    /// if they are left unchanged. Blocks are only added if the
+    /// `restore_dug_blocks` bit is set.
+    predicted_dug_blocks: Vec<BlockPos>,
+    held_block: Option<BlockKind>,
+    /// Commands received this tick which have yet to be executed.
+    pending_commands: Vec<String>,
+    /// Packets received in the current window of each [`RateLimit`], indexed
+    /// by [`PacketKind`].
+    rate_counters: [RateCounter; 3],
+    /// The traffic of the connection, counted by its packet tasks.
+    network: Arc<NetworkCounters>,
+    rate_meter: RateMeter,
+    op_level: u8,
+    /// Should be sent after login packet. The `bool` is `true` for critical
+    /// messages, which ignore the chat settings.
+    msgs_to_send: Vec<(Text, bool)>,
     bar_to_send: Option<Text>,
+    titles: Titles,
+    glow: ViewerGlow,
+    sidebar: Option<Sidebar>,
+    /// The sidebar the client is currently displaying.
+    old_sidebar: Option<Sidebar>,
     attack_speed: f64,
     movement_speed: f64,
     bits: ClientBits,
@@ -234,7 +369,7 @@ pub struct Client<C: Config> {
     entity_events: Vec<EntityEvent>,
 }
 
-#[bitfield(u16)]
+#[bitfield(u32)]
 struct ClientBits {
     spawn: bool,
     flat: bool,
@@ -249,55 +384,199 @@ struct ClientBits {
     velocity_modified: bool,
     created_this_tick: bool,
     view_distance_modified: bool,
-    #[bits(5)]
+    op_level_modified: bool,
+    /// If the skin parts or main hand in the settings changed this tick.
+    skin_settings_modified: bool,
+    restore_dug_blocks: bool,
+    copy_metadata: bool,
+    /// If the portal travel sound should be played after respawning.
+    portal_travel: bool,
+    on_ground: bool,
+    sneaking: bool,
+    sprinting: bool,
+    forward_arm_swings: bool,
+    /// If the main hand was swung since the last sync of the player entity.
+    swung_main_hand: bool,
+    /// If the off hand was swung since the last sync of the player entity.
+    swung_off_hand: bool,
+    vanished: bool,
+    vanished_modified: bool,
+    /// If the death screen is shown instead of respawning immediately.
+    respawn_screen: bool,
+    respawn_screen_modified: bool,
+    #[bits(6)]
     _pad: u8,
 }
 
+/// Why a client was disconnected. See [`Client::disconnect_reason`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum DisconnectReason {
+    /// The connection was closed by the client or lost, or the packets sent by
+    /// the client could not be decoded.
+    ConnectionClosed,
+    /// The client did not respond to a keepalive in time.
+    KeepaliveTimeout,
+    /// The client did not receive packets fast enough, filling the buffer of
+    /// packets waiting to be sent.
+    ///
+    /// The size of the buffer is set with
+    /// [`Config::outgoing_packet_capacity`].
+    BufferFull,
+    /// The client sent packets it should not have, such as teleport
+    /// confirmations or keepalive responses with the wrong ID.
+    ProtocolError,
+    /// The client was in a world which does not exist.
+    InvalidWorld,
+    /// The client was disconnected with [`Client::disconnect`] or
+    /// [`Client::disconnect_no_reason`], or for exceeding a [`RateLimit`]. The
+    /// message shown to the client is included if there was one.
+    Kicked(Option<Text>),
+}
+
+/// Selects which components of a teleport made with
+/// [`Client::teleport_relative`] are relative to the client's current position
+/// and rotation.
+///
+/// Relative components are applied by the client on top of its own position
+/// and rotation, so they preserve its velocity along those axes. For instance,
+/// a teleport which is relative in the position and zero in all of its
+/// position offsets only changes the client's rotation.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct TeleportFlags {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+    pub yaw: bool,
+    pub pitch: bool,
+}
+
+impl TeleportFlags {
+    /// All components are absolute. This is what [`Client::teleport`] uses.
+    pub const NONE: Self = Self {
+        x: false,
+        y: false,
+        z: false,
+        yaw: false,
+        pitch: false,
+    };
+
+    /// The position is relative and the rotation is absolute.
+    pub const POSITION: Self = Self {
+        x: true,
+        y: true,
+        z: true,
+        yaw: false,
+        pitch: false,
+    };
+
+    /// The rotation is relative and the position is absolute.
+    pub const ROTATION: Self = Self {
+        x: false,
+        y: false,
+        z: false,
+        yaw: true,
+        pitch: true,
+    };
+
+    /// All components are relative.
+    pub const ALL: Self = Self {
+        x: true,
+        y: true,
+        z: true,
+        yaw: true,
+        pitch: true,
+    };
+}
+
+/// A screen or message of the demo mode shown with
+/// [`Client::show_demo_message`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DemoMessage {
+    /// The demo welcome screen.
+    Welcome = 0,
+    /// Tells the player how to move.
+    MovementControls = 101,
+    /// Tells the player how to jump.
+    JumpControl = 102,
+    /// Tells the player how to open the inventory.
+    InventoryControl = 103,
+    /// Tells the player that the demo is over and how to take a screenshot.
+    Over = 104,
+}
+
 impl<C: Config> Client<C> {
     pub(crate) fn new(
         packet_channels: C2sPacketChannels,
         ncd: NewClientData,
         state: C::ClientState,
     ) -> Self {
-        let (send, recv) = packet_channels;
+        let (send, recv, network) = packet_channels;
 
         Self {
             state,
-            send: Some(send),
+            components: Components::new(),
+            send: Ok(send),
             recv,
             uuid: ncd.uuid,
             username: ncd.username,
             textures: ncd.textures,
+            remote_addr: ncd.remote_addr,
+            server_address: ncd.server_address,
+            forge_marker: ncd.forge_marker,
+            protocol_version: ncd.protocol_version,
+            brand: None,
             world: WorldId::default(),
-            old_player_list: None,
-            new_player_list: None,
+            old_player_lists: Vec::new(),
+            new_player_lists: Vec::new(),
+            pending_unlisted: Vec::new(),
+            scheduled_packets: Vec::new(),
+            skin_synced_entity: None,
+            new_player_entity: None,
+            player_entity: None,
             position: Vec3::default(),
             old_position: Vec3::default(),
             velocity: Vec3::default(),
             yaw: 0.0,
             pitch: 0.0,
             view_distance: 2,
+            view_distance_limit: 32,
             teleport_id_counter: 0,
             pending_teleports: 0,
+            teleport_flags: TeleportFlags::NONE,
+            teleport_offset: (Vec3::zero(), 0.0, 0.0),
             spawn_position: BlockPos::default(),
             spawn_position_yaw: 0.0,
             death_location: None,
             events: VecDeque::new(),
             last_keepalive_id: 0,
             loaded_entities: HashSet::new(),
+            disguised_entities: HashSet::new(),
             loaded_chunks: HashSet::new(),
             new_game_mode: GameMode::Survival,
             old_game_mode: GameMode::Survival,
             settings: None,
             dug_blocks: Vec::new(),
+            predicted_dug_blocks: Vec::new(),
+            held_block: None,
+            pending_commands: Vec::new(),
+            rate_counters: Default::default(),
+            network,
+            rate_meter: RateMeter::default(),
+            op_level: 0,
             msgs_to_send: Vec::new(),
             bar_to_send: None,
+            titles: Titles::new(),
+            glow: ViewerGlow::default(),
+            sidebar: None,
+            old_sidebar: None,
             attack_speed: 4.0,
             movement_speed: 0.7,
             bits: ClientBits::new()
                 .with_modified_spawn_position(true)
                 .with_got_keepalive(true)
-                .with_created_this_tick(true),
+                .with_created_this_tick(true)
+                .with_copy_metadata(true),
             player_data: Player::new(),
             entity_events: Vec::new(),
         }
@@ -324,26 +603,145 @@ impl<C: Config> Client<C> {
         self.textures.as_ref()
     }
 
+    /// Gets the address of the remote end of this client's connection. See
+    /// [`NewClientData::remote_addr`].
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// Gets the number of bytes and packets sent to and received from this
+    /// client. The stats are updated at the start of every tick.
+    pub fn network_stats(&self) -> &NetworkStats {
+        self.rate_meter.stats()
+    }
+
+    /// Gets the hostname this client used to connect to the server. See
+    /// [`NewClientData::server_address`].
+    pub fn server_address(&self) -> &str {
+        &self.server_address
+    }
+
+    /// Gets the marker Forge clients append to the server address, or `None`
+    /// for vanilla clients. See [`NewClientData::forge_marker`].
+    pub fn forge_marker(&self) -> Option<&str> {
+        self.forge_marker.as_deref()
+    }
+
+    /// Gets the protocol version this client sent in its handshake. See
+    /// [`NewClientData::protocol_version`].
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    /// Gets the client brand sent on the `minecraft:brand` channel, such as
+    /// `vanilla` or `fabric`. Returns `None` if the client has not sent its
+    /// brand yet.
+    ///
+    /// The brand is chosen by the client and should not be trusted.
+    pub fn brand(&self) -> Option<&str> {
+        self.brand.as_deref()
+    }
+
+    /// Returns `true` if this client has the permission `node` according to
+    /// `permissions`.
+    ///
+    /// This is shorthand for [`Permissions::check`] with the client's UUID.
+    pub fn has_permission(&self, permissions: &Permissions, node: &str) -> bool {
+        permissions.check(self.uuid, node)
+    }
+
+    /// Gets the operator level of this client. This is in `0..=4`.
+    ///
+    /// The operator level decides which [commands](crate::command) the client
+    /// can execute. The client also uses it to enable features such as the F3
+    /// game mode switcher.
+    pub fn op_level(&self) -> u8 {
+        self.op_level
+    }
+
+    /// Sets the operator level of this client. The value is clamped to
+    /// `0..=4`.
+    pub fn set_op_level(&mut self, op_level: u8) {
+        let op_level = op_level.min(4);
+        if self.op_level != op_level {
+            self.op_level = op_level;
+            self.bits.set_op_level_modified(true);
+        }
+    }
+
     /// Gets the world this client is located in.
     pub fn world(&self) -> WorldId {
         self.world
     }
 
-    /// Gets the player list this client sees.
+    /// Gets the primary player list this client sees. This is the first of the
+    /// client's [player lists](Self::player_lists).
     pub fn player_list(&self) -> Option<&PlayerListId> {
-        self.new_player_list.as_ref()
+        self.new_player_lists.first()
     }
 
-    /// Sets the player list this client sees.
+    /// Sets the player list this client sees, replacing all of the client's
+    /// current player lists.
     ///
-    /// The previous player list ID is returned.
+    /// The previous primary player list ID is returned.
     pub fn set_player_list(&mut self, id: impl Into<Option<PlayerListId>>) -> Option<PlayerListId> {
-        mem::replace(&mut self.new_player_list, id.into())
+        let old = mem::replace(&mut self.new_player_lists, id.into().into_iter().collect());
+        old.into_iter().next()
+    }
+
+    /// Gets all of the player lists this client sees.
+    ///
+    /// The entries of every list are shown together in the client's tab list.
+    /// The header and footer are taken from the first list.
+    pub fn player_lists(&self) -> &[PlayerListId] {
+        &self.new_player_lists
+    }
+
+    /// Sets all of the player lists this client sees. Duplicate IDs are
+    /// ignored.
+    ///
+    /// The previous player list IDs are returned.
+    pub fn set_player_lists(
+        &mut self,
+        ids: impl IntoIterator<Item = PlayerListId>,
+    ) -> Vec<PlayerListId> {
+        let mut new = Vec::new();
+        for id in ids {
+            if !new.contains(&id) {
+                new.push(id);
+            }
+        }
+
+        mem::replace(&mut self.new_player_lists, new)
+    }
+
+    /// Adds a player list to the end of the player lists this client sees.
+    ///
+    /// Returns `true` if the player list was added. If the client already sees
+    /// the player list, `false` is returned and the function has no effect.
+    pub fn add_player_list(&mut self, id: PlayerListId) -> bool {
+        if self.new_player_lists.contains(&id) {
+            false
+        } else {
+            self.new_player_lists.push(id);
+            true
+        }
+    }
+
+    /// Removes a player list from the player lists this client sees. Returns
+    /// `true` if the client was seeing the player list.
+    pub fn remove_player_list(&mut self, id: &PlayerListId) -> bool {
+        let len = self.new_player_lists.len();
+        self.new_player_lists.retain(|pl| pl != id);
+        self.new_player_lists.len() != len
     }
 
     /// Sets if this client sees the world as superflat. Superflat worlds have
     /// a horizon line lower than normal worlds.
     ///
+    /// Worlds which are [flat](crate::world::WorldMeta::is_flat) are shown as
+    /// superflat regardless of this setting.
+    ///
     /// The player must be (re)spawned for changes to take effect.
     pub fn set_flat(&mut self, flat: bool) {
         self.bits.set_flat(flat);
@@ -355,6 +753,22 @@ impl<C: Config> Client<C> {
         self.bits.flat()
     }
 
+    /// Sets if the client keeps the metadata and attributes of its own player
+    /// entity when it is respawned. This is `true` by default.
+    ///
+    /// Vanilla servers keep them when changing dimensions and reset them when
+    /// respawning after death. Either way, the values set on this client and
+    /// its [player data](Self::player) are sent again if they are lost.
+    pub fn set_copy_metadata(&mut self, copy: bool) {
+        self.bits.set_copy_metadata(copy);
+    }
+
+    /// Gets if the client keeps the metadata and attributes of its own player
+    /// entity when it is respawned.
+    pub fn copies_metadata(&self) -> bool {
+        self.bits.copy_metadata()
+    }
+
     /// Changes the world this client is located in and respawns the client.
     /// This can be used to respawn the client after death.
     ///
@@ -365,12 +779,60 @@ impl<C: Config> Client<C> {
         self.bits.set_spawn(true);
     }
 
+    /// Respawns the client in `world` at the given position and rotation.
+    ///
+    /// Besides doing what [`Self::spawn`] and [`Self::teleport`] do, this
+    /// resets the client's velocity, discards the titles and action bar
+    /// messages queued for the client, and clears those on its screen. The
+    /// client's game mode, attributes, and other configured data are kept.
+    ///
+    /// The given [`WorldId`] must be valid. Otherwise, the client is
+    /// disconnected.
+    pub fn respawn(&mut self, world: WorldId, pos: impl Into<Vec3<f64>>, yaw: f32, pitch: f32) {
+        self.spawn(world);
+        self.teleport(pos, yaw, pitch);
+
+        self.velocity = Vec3::zero();
+        self.bits.set_velocity_modified(false);
+        self.titles.clear();
+        // Blocks dug in the old world must not be restored in the new one.
+        self.predicted_dug_blocks.clear();
+    }
+
+    /// Moves the client to `world` like travelling through a portal.
+    ///
+    /// This is [`Self::respawn`] followed by the portal travel sound, which
+    /// vanilla clients play after the new dimension has loaded. To send the
+    /// client through the end portal, show it the [credits](Self::show_credits)
+    /// first and call this once [`ClientEvent::RespawnRequest`] is received.
+    pub fn change_dimension(
+        &mut self,
+        world: WorldId,
+        pos: impl Into<Vec3<f64>>,
+        yaw: f32,
+        pitch: f32,
+    ) {
+        self.respawn(world, pos, yaw, pitch);
+        self.bits.set_portal_travel(true);
+    }
+
     /// Sends a system message to the player which is visible in the chat. The
     /// message is only visible to this client.
+    ///
+    /// The client's chat [settings](Self::settings) are respected: the message
+    /// is dropped if the client has hidden the chat, and its colors are
+    /// removed if the client has disabled chat colors. Use
+    /// [`Self::send_critical_message`] for messages which must always be shown.
     pub fn send_message(&mut self, msg: impl Into<Text>) {
         // We buffer messages because weird things happen if we send them before the
         // login packet.
-        self.msgs_to_send.push(msg.into());
+        self.msgs_to_send.push((msg.into(), false));
+    }
+
+    /// Like [`Self::send_message`], but the message is shown unchanged
+    /// regardless of the client's chat settings.
+    pub fn send_critical_message(&mut self, msg: impl Into<Text>) {
+        self.msgs_to_send.push((msg.into(), true));
     }
 
     /// Gets the absolute position of this client in the world it is located
@@ -384,13 +846,93 @@ impl<C: Config> Client<C> {
     ///
     /// If you want to change the client's world, use [`Self::spawn`].
     pub fn teleport(&mut self, pos: impl Into<Vec3<f64>>, yaw: f32, pitch: f32) {
-        self.position = pos.into();
-        self.yaw = yaw;
-        self.pitch = pitch;
+        self.teleport_relative(pos, yaw, pitch, TeleportFlags::NONE);
+    }
+
+    /// Like [`Self::teleport`], but the components selected by `flags` are
+    /// added to the client's current position and rotation instead of
+    /// replacing them.
+    ///
+    /// Unlike absolute components, relative components do not reset the
+    /// client's velocity along them. If a component is made absolute by any
+    /// teleport in a tick, it is sent as absolute.
+    pub fn teleport_relative(
+        &mut self,
+        pos: impl Into<Vec3<f64>>,
+        yaw: f32,
+        pitch: f32,
+        flags: TeleportFlags,
+    ) {
+        fn apply<T: Copy + std::ops::Add<Output = T>>(
+            value: &mut T,
+            offset: &mut T,
+            pending_relative: &mut bool,
+            relative: bool,
+            new: T,
+        ) {
+            if relative {
+                *value = *value + new;
+                *offset = *offset + new;
+            } else {
+                *value = new;
+                *pending_relative = false;
+            }
+        }
+
+        if !self.bits.teleported_this_tick() {
+            self.teleport_flags = TeleportFlags::ALL;
+            self.teleport_offset = (Vec3::zero(), 0.0, 0.0);
+        }
+
+        let pos = pos.into();
+        let f = &mut self.teleport_flags;
+        let (offset, yaw_offset, pitch_offset) = &mut self.teleport_offset;
+
+        apply(
+            &mut self.position.x,
+            &mut offset.x,
+            &mut f.x,
+            flags.x,
+            pos.x,
+        );
+        apply(
+            &mut self.position.y,
+            &mut offset.y,
+            &mut f.y,
+            flags.y,
+            pos.y,
+        );
+        apply(
+            &mut self.position.z,
+            &mut offset.z,
+            &mut f.z,
+            flags.z,
+            pos.z,
+        );
+        apply(&mut self.yaw, yaw_offset, &mut f.yaw, flags.yaw, yaw);
+        apply(
+            &mut self.pitch,
+            pitch_offset,
+            &mut f.pitch,
+            flags.pitch,
+            pitch,
+        );
 
         self.bits.set_teleported_this_tick(true);
     }
 
+    /// Rotates the client so that its eyes look at the point `target`.
+    ///
+    /// The client's new rotation is reported back to the server with the
+    /// usual movement events.
+    pub fn look_at(&mut self, target: impl Into<Vec3<f64>>) {
+        self.send_packet(LookAt {
+            anchor: EntityAnchor::Eyes,
+            target: target.into(),
+            entity: None,
+        });
+    }
+
     /// Gets the most recently set velocity of this client in m/s.
     pub fn velocity(&self) -> Vec3<f32> {
         self.velocity
@@ -412,6 +954,21 @@ impl<C: Config> Client<C> {
         self.pitch
     }
 
+    /// Gets if the client was on the ground when it last moved.
+    pub fn on_ground(&self) -> bool {
+        self.bits.on_ground()
+    }
+
+    /// Gets if the client is sneaking.
+    pub fn is_sneaking(&self) -> bool {
+        self.bits.sneaking()
+    }
+
+    /// Gets if the client is sprinting.
+    pub fn is_sprinting(&self) -> bool {
+        self.bits.sprinting()
+    }
+
     /// Gets the spawn position. The client will see `minecraft:compass` items
     /// point at the returned position.
     pub fn spawn_position(&self) -> BlockPos {
@@ -513,6 +1070,50 @@ impl<C: Config> Client<C> {
         self.bar_to_send = Some(text.into());
     }
 
+    /// Gets the color of the outline shown around an entity for this client
+    /// only, as set by [`Self::set_glowing`].
+    pub fn glow_color(&self, entity: EntityId) -> Option<TeamColor> {
+        self.glow.get(entity)
+    }
+
+    /// Makes an entity glow with an outline of the given color for this client
+    /// only, or stops it from glowing if `color` is `None`.
+    ///
+    /// This does not affect other clients, or entities which are glowing on
+    /// their own. The outline color is set by adding the entity to a team on
+    /// the client, which replaces any other team the entity is a member of on
+    /// this client.
+    pub fn set_glowing(&mut self, entity: EntityId, color: Option<TeamColor>) {
+        self.glow.set(entity, color);
+    }
+
+    /// Gets the queue of titles and action bar messages shown to this client.
+    pub fn titles(&self) -> &Titles {
+        &self.titles
+    }
+
+    /// Gets the queue of titles and action bar messages shown to this client,
+    /// which can be used to show messages one after another.
+    pub fn titles_mut(&mut self) -> &mut Titles {
+        &mut self.titles
+    }
+
+    /// Gets the sidebar displayed to this client.
+    pub fn sidebar(&self) -> Option<&Sidebar> {
+        self.sidebar.as_ref()
+    }
+
+    /// Gets an exclusive reference to the sidebar displayed to this client.
+    /// Changes are sent to the client when it is updated.
+    pub fn sidebar_mut(&mut self) -> Option<&mut Sidebar> {
+        self.sidebar.as_mut()
+    }
+
+    /// Sets the sidebar displayed to this client. `None` hides the sidebar.
+    pub fn set_sidebar(&mut self, sidebar: impl Into<Option<Sidebar>>) {
+        self.sidebar = sidebar.into();
+    }
+
     /// Gets the attack cooldown speed.
     pub fn attack_speed(&self) -> f64 {
         self.attack_speed
@@ -544,13 +1145,120 @@ impl<C: Config> Client<C> {
         self.send_packet(ClearTitles { reset: true });
     }
 
+    /// Shows the end poem and credits to this client, like after entering the
+    /// end portal. The client asks to respawn once the credits are closed.
+    pub fn show_credits(&mut self) {
+        self.send_game_state_change(GameStateChangeReason::WinGame, 1.0);
+    }
+
+    /// Shows one of the screens or messages of the demo mode to this client.
+    pub fn show_demo_message(&mut self, msg: DemoMessage) {
+        self.send_game_state_change(GameStateChangeReason::DemoEvent, msg as u8 as f32);
+    }
+
+    /// Shows the elder guardian apparition and plays its sound.
+    pub fn play_elder_guardian_effect(&mut self) {
+        self.send_game_state_change(GameStateChangeReason::PlayElderGuardianMobAppearance, 0.0);
+    }
+
+    /// Plays the sound of an arrow shot by this client hitting a player.
+    pub fn play_arrow_hit_sound(&mut self) {
+        self.send_game_state_change(GameStateChangeReason::ArrowHitPlayer, 0.0);
+    }
+
+    /// Plays the sound of a pufferfish stinging this client.
+    pub fn play_pufferfish_sting_sound(&mut self) {
+        self.send_game_state_change(GameStateChangeReason::PlayPufferfishStingSound, 0.0);
+    }
+
+    fn send_game_state_change(&mut self, reason: GameStateChangeReason, value: f32) {
+        self.send_packet(GameStateChange { reason, value });
+    }
+
+    /// Sends the rain and thunder levels of the client's world. If `entered`
+    /// is `true`, the client has just entered the world and is sent the levels
+    /// which are not zero. Otherwise, only the levels modified this tick are
+    /// sent.
+    fn send_weather(&mut self, meta: &WorldMeta, entered: bool) {
+        let rain = meta.rain_level();
+
+        if entered && rain != 0.0 || !entered && meta.rain_level_modified() {
+            self.send_game_state_change(
+                if rain != 0.0 {
+                    GameStateChangeReason::BeginRaining
+                } else {
+                    GameStateChangeReason::EndRaining
+                },
+                0.0,
+            );
+            self.send_game_state_change(GameStateChangeReason::RainLevelChange, rain);
+        }
+
+        let thunder = meta.thunder_level();
+
+        if entered && thunder != 0.0 || !entered && meta.thunder_level_modified() {
+            self.send_game_state_change(GameStateChangeReason::ThunderLevelChange, thunder);
+        }
+    }
+
+    /// Gets whether blocks this client breaks are restored on the client
+    /// unless they are changed in the same tick.
+    ///
+    /// This is `false` by default.
+    pub fn restores_dug_blocks(&self) -> bool {
+        self.bits.restore_dug_blocks()
+    }
+
+    /// Sets whether blocks this client breaks are restored on the client
+    /// unless they are changed in the same tick.
+    ///
+    /// Clients remove the blocks they break without waiting for the server.
+    /// Once the server acknowledges the action, the client undoes the removal
+    /// by itself if the block was not changed, so this is only needed for
+    /// actions without a sequence number, which are not acknowledged. When
+    /// enabled, blocks broken by finished [`ClientEvent::Digging`] events that
+    /// are not handled by changing the block in the world are sent back to
+    /// the client, as are breaks and placements denied by a protected region
+    /// or cancelled by the event bus.
+    pub fn set_restore_dug_blocks(&mut self, restore: bool) {
+        self.bits.set_restore_dug_blocks(restore);
+    }
+
+    /// Gets the block this client places when it uses the item in its hand on
+    /// a block.
+    ///
+    /// This is `None` by default.
+    pub fn held_block(&self) -> Option<BlockKind> {
+        self.held_block
+    }
+
+    /// Sets the block this client places when it uses the item in its hand on
+    /// a block. The server does not keep track of inventories, so this is up
+    /// to the application.
+    ///
+    /// While this is `Some`, every [`ClientEvent::InteractWithBlock`] places
+    /// the block like the vanilla server would before [`Config::update`] is
+    /// called, unless the [`BlockPlace`] event posted for it is cancelled.
+    /// Pass `None` to handle interactions yourself.
+    ///
+    /// [`BlockPlace`]: crate::event_bus::BlockPlace
+    pub fn set_held_block(&mut self, block: Option<BlockKind>) {
+        self.held_block = block;
+    }
+
     /// Gets whether or not the client is connected to the server.
     ///
     /// A disconnected client object will never become reconnected. It is your
     /// responsibility to remove disconnected clients from the [`Clients`]
     /// container.
     pub fn is_disconnected(&self) -> bool {
-        self.send.is_none()
+        self.send.is_err()
+    }
+
+    /// Returns why this client was disconnected, or `None` if it is still
+    /// connected.
+    pub fn disconnect_reason(&self) -> Option<&DisconnectReason> {
+        self.send.as_ref().err()
     }
 
     /// Returns an iterator over all pending client events in the order they
@@ -572,6 +1280,25 @@ impl<C: Config> Client<C> {
         self.events.pop_front()
     }
 
+    pub(crate) fn take_events(&mut self) -> VecDeque<ClientEvent> {
+        mem::take(&mut self.events)
+    }
+
+    /// Puts `events` in front of the events in the event queue.
+    pub(crate) fn prepend_events(&mut self, mut events: VecDeque<ClientEvent>) {
+        events.append(&mut self.events);
+        self.events = events;
+    }
+
+    /// Restores a block this client broke on the client, unless it is changed
+    /// in the same tick. Does nothing if the client does not
+    /// [restore dug blocks](Self::restores_dug_blocks).
+    pub(crate) fn restore_dug_block(&mut self, pos: BlockPos) {
+        if self.bits.restore_dug_blocks() && !self.predicted_dug_blocks.contains(&pos) {
+            self.predicted_dug_blocks.push(pos);
+        }
+    }
+
     /// Pushes an entity event to the queue.
     pub fn push_entity_event(&mut self, event: EntityEvent) {
         self.entity_events.push(event);
@@ -580,11 +1307,29 @@ impl<C: Config> Client<C> {
     /// The current view distance of this client measured in chunks. The client
     /// will not be able to see chunks and entities past this distance.
     ///
-    /// The result is in `2..=32`.
+    /// The result is in `2..=32`. While the server is overloaded, the client
+    /// may see less, see [`Self::effective_view_distance`].
     pub fn view_distance(&self) -> u8 {
         self.view_distance
     }
 
+    /// The view distance the client actually uses, which is the
+    /// [view distance](Self::view_distance) lowered to the
+    /// [limit](crate::server::SharedServer::view_distance_limit) set while the
+    /// server is overloaded.
+    pub fn effective_view_distance(&self) -> u8 {
+        self.view_distance.min(self.view_distance_limit)
+    }
+
+    pub(crate) fn set_view_distance_limit(&mut self, limit: u8) {
+        let old = self.effective_view_distance();
+        self.view_distance_limit = limit;
+
+        if self.effective_view_distance() != old {
+            self.bits.set_view_distance_modified(true);
+        }
+    }
+
     /// Sets the view distance. The client will not be able to see chunks and
     /// entities past this distance.
     ///
@@ -598,19 +1343,46 @@ impl<C: Config> Client<C> {
         }
     }
 
-    /// Enables hardcore mode. This changes the design of the client's hearts.
+    /// Enables hardcore mode for this client, even if the world it joins is
+    /// not [hardcore](WorldMeta::is_hardcore). This changes the design of the
+    /// client's hearts, and its death screen only offers to spectate the
+    /// world.
     ///
-    /// To have any visible effect, this function must be called on the same
-    /// tick the client joins the server.
+    /// Clients keep the mode they joined with across respawns, so this must
+    /// be called on the same tick the client joins the server to have any
+    /// effect.
     pub fn set_hardcore(&mut self, hardcore: bool) {
-        self.bits.set_hardcore(hardcore);
+        if self.created_this_tick() {
+            self.bits.set_hardcore(hardcore);
+        }
     }
 
-    /// Gets if hardcore mode is enabled.
+    /// Gets if hardcore mode is enabled. After the client has joined, this is
+    /// the mode it shows, which includes the hardcore mode of the world it
+    /// joined in.
     pub fn is_hardcore(&self) -> bool {
         self.bits.hardcore()
     }
 
+    /// Sets if the client shows the death screen when it dies. Otherwise, the
+    /// client asks to respawn immediately with
+    /// [`ClientEvent::RespawnRequest`]. The default is `false`.
+    ///
+    /// Unlike the hardcore mode, this can be changed at any time and is kept
+    /// across respawns.
+    pub fn set_respawn_screen(&mut self, enabled: bool) {
+        if self.bits.respawn_screen() != enabled {
+            self.bits.set_respawn_screen(enabled);
+            self.bits.set_respawn_screen_modified(true);
+        }
+    }
+
+    /// Gets if the client shows the death screen when it dies. See
+    /// [`Self::set_respawn_screen`].
+    pub fn has_respawn_screen(&self) -> bool {
+        self.bits.respawn_screen()
+    }
+
     /// Gets the client's current settings.
     pub fn settings(&self) -> Option<&Settings> {
         self.settings.as_ref()
@@ -621,22 +1393,30 @@ impl<C: Config> Client<C> {
     ///
     /// All future calls to [`Self::is_disconnected`] will return `true`.
     pub fn disconnect(&mut self, reason: impl Into<Text>) {
-        if self.send.is_some() {
+        if self.send.is_ok() {
             let txt = reason.into();
-            log::info!("disconnecting client '{}': \"{txt}\"", self.username);
+            tracing::info!("disconnecting client '{}': \"{txt}\"", self.username);
 
-            self.send_packet(Disconnect { reason: txt });
+            self.send_packet(Disconnect {
+                reason: txt.clone(),
+            });
 
-            self.send = None;
+            self.send = Err(DisconnectReason::Kicked(Some(txt)));
         }
     }
 
     /// Like [`Self::disconnect`], but no reason for the disconnect is
     /// displayed.
     pub fn disconnect_no_reason(&mut self) {
-        if self.send.is_some() {
-            log::info!("disconnecting client '{}'", self.username);
-            self.send = None;
+        self.disconnect_with(DisconnectReason::Kicked(None));
+    }
+
+    /// Disconnects this client without showing it a reason, recording
+    /// `reason` as the cause.
+    fn disconnect_with(&mut self, reason: DisconnectReason) {
+        if self.send.is_ok() {
+            tracing::info!("disconnecting client '{}'", self.username);
+            self.send = Err(reason);
         }
     }
 
@@ -652,25 +1432,374 @@ impl<C: Config> Client<C> {
         &mut self.player_data
     }
 
+    /// Stores new settings and generates an event for every setting that
+    /// differs from the previous settings. All events are generated for the
+    /// first settings received.
+    fn handle_settings(&mut self, new: Settings) {
+        let old = self.settings.replace(new.clone());
+        let old = old.as_ref();
+
+        if old.is_none_or(|old| old.locale != new.locale) {
+            self.events.push_back(ClientEvent::LocaleChanged {
+                locale: new.locale.clone(),
+            });
+        }
+
+        if old.is_none_or(|old| old.view_distance != new.view_distance) {
+            self.events.push_back(ClientEvent::ViewDistanceChanged {
+                view_distance: new.view_distance,
+            });
+        }
+
+        if old
+            .is_none_or(|old| old.chat_mode != new.chat_mode || old.chat_colors != new.chat_colors)
+        {
+            self.events.push_back(ClientEvent::ChatSettingsChanged {
+                chat_mode: new.chat_mode,
+                chat_colors: new.chat_colors,
+            });
+        }
+
+        if old.is_none_or(|old| old.main_hand != new.main_hand) {
+            self.events.push_back(ClientEvent::MainHandChanged {
+                main_hand: new.main_hand,
+            });
+        }
+
+        if old.is_none_or(|old| old.displayed_skin_parts != new.displayed_skin_parts) {
+            self.events.push_back(ClientEvent::SkinPartsChanged {
+                displayed_skin_parts: new.displayed_skin_parts,
+            });
+        }
+
+        if old.is_none_or(|old| old.allow_server_listings != new.allow_server_listings) {
+            self.events
+                .push_back(ClientEvent::AllowServerListingsChanged {
+                    allow_server_listings: new.allow_server_listings,
+                });
+        }
+
+        if old.is_none_or(|old| {
+            old.main_hand != new.main_hand || old.displayed_skin_parts != new.displayed_skin_parts
+        }) {
+            apply_skin_settings(&mut self.player_data, &new);
+            self.bits.set_skin_settings_modified(true);
+        }
+    }
+
+    /// Makes this client own a player entity with the client's UUID, so that
+    /// other clients can see it without any further work.
+    ///
+    /// The entity is created with the given state before clients are updated
+    /// in the current tick, or the next tick if that has passed. From then on,
+    /// its world, position, rotation, and pose are copied from this client
+    /// every tick, and it is removed once this client disconnects. If an entity
+    /// with the client's UUID already exists, the client is disconnected
+    /// instead.
+    ///
+    /// Removing the entity yourself ends the ownership.
+    pub fn spawn_player_entity(&mut self, state: C::EntityState) {
+        self.new_player_entity = Some(state);
+    }
+
+    /// Gets the player entity owned by this client, if any. See
+    /// [`Self::spawn_player_entity`].
+    pub fn player_entity(&self) -> Option<EntityId> {
+        self.player_entity
+    }
+
+    /// Hides this client from other clients while keeping it connected, or
+    /// shows it again.
+    ///
+    /// While vanished, the entity with the client's UUID is not
+    /// [visible](crate::entity::Entity::is_visible_to) to any client and the
+    /// client's entries in all player lists are hidden from the tab list.
+    /// Since player lists are shared, the client does not see itself in the
+    /// tab list either. The [visibility](crate::entity::Entity::visibility)
+    /// and [listing](crate::player_list::PlayerListEntry::is_listed) set on
+    /// the entity and entries are left untouched and apply again once the
+    /// client is unvanished.
+    pub fn set_vanished(&mut self, vanished: bool) {
+        if self.bits.vanished() != vanished {
+            self.bits.set_vanished(vanished);
+            self.bits.set_vanished_modified(true);
+        }
+    }
+
+    /// Returns `true` if this client is [vanished](Self::set_vanished).
+    pub fn is_vanished(&self) -> bool {
+        self.bits.vanished()
+    }
+
+    /// Sets if the arm swings of this client are played on its
+    /// [player entity](Self::spawn_player_entity) automatically. This is
+    /// `false` by default.
+    ///
+    /// Leave this disabled if the swings are already forwarded by other means,
+    /// such as [`default_client_event`], to avoid playing them twice.
+    pub fn set_forward_arm_swings(&mut self, forward: bool) {
+        self.bits.set_forward_arm_swings(forward);
+    }
+
+    /// Gets if the arm swings of this client are played on its player entity
+    /// automatically.
+    pub fn forwards_arm_swings(&self) -> bool {
+        self.bits.forward_arm_swings()
+    }
+
+    /// Creates and updates the player entity owned by this client and applies
+    /// the vanish state to the client's entity and player list entries. Then,
+    /// copies the skin parts and main hand from the client's settings to the
+    /// player entity with the same UUID as the client, so that other clients
+    /// see the changes.
+    ///
+    /// The skin settings are copied when they change and whenever a different
+    /// entity with the client's UUID appears, such as when the player entity
+    /// is created or replaced.
+    pub(crate) fn sync_player_entity(
+        &mut self,
+        entities: &mut Entities<C>,
+        player_lists: &mut PlayerLists<C>,
+    ) {
+        if let Some(state) = self.new_player_entity.take() {
+            if self.player_entity.is_none() && !self.is_disconnected() {
+                match entities.insert_with_uuid(EntityKind::Player, self.uuid, state) {
+                    Some((id, _)) => self.player_entity = Some(id),
+                    None => self.disconnect("Conflicting UUID"),
+                }
+            }
+        }
+
+        let swung_main_hand = self.bits.swung_main_hand();
+        self.bits.set_swung_main_hand(false);
+        let swung_off_hand = self.bits.swung_off_hand();
+        self.bits.set_swung_off_hand(false);
+
+        if let Some(id) = self.player_entity {
+            match entities.get_mut(id) {
+                Some(entity) => {
+                    entity.set_world(self.world);
+                    entity.set_position(self.position);
+                    entity.set_yaw(self.yaw);
+                    entity.set_pitch(self.pitch);
+                    entity.set_head_yaw(self.yaw);
+                    entity.set_on_ground(self.bits.on_ground());
+
+                    if self.bits.forward_arm_swings() {
+                        if swung_main_hand {
+                            entity.push_event(EntityEvent::SwingMainHand);
+                        }
+                        if swung_off_hand {
+                            entity.push_event(EntityEvent::SwingOffHand);
+                        }
+                    }
+
+                    if let TrackedData::Player(player) = entity.data_mut() {
+                        if self.bits.sneaking() && player.get_pose() == Pose::Standing {
+                            player.set_pose(Pose::Sneaking);
+                        } else if !self.bits.sneaking() && player.get_pose() == Pose::Sneaking {
+                            player.set_pose(Pose::Standing);
+                        }
+                        player.set_sprinting(self.bits.sprinting());
+                    }
+                }
+                None => self.player_entity = None,
+            }
+        }
+
+        let id = entities.get_with_uuid(self.uuid);
+
+        // Entities and entries are vanished every tick so that new ones are hidden
+        // as well.
+        if self.bits.vanished() || self.bits.vanished_modified() {
+            self.bits.set_vanished_modified(false);
+
+            if let Some(entity) = id.and_then(|id| entities.get_mut(id)) {
+                entity.set_vanished(self.bits.vanished());
+            }
+            player_lists.set_vanished(self.uuid, self.bits.vanished());
+        }
+
+        let settings = match &self.settings {
+            Some(settings) => settings,
+            None => return,
+        };
+
+        if !self.bits.skin_settings_modified() && id == self.skin_synced_entity {
+            return;
+        }
+
+        self.bits.set_skin_settings_modified(false);
+        self.skin_synced_entity = id;
+
+        if let Some(entity) = id.and_then(|id| entities.get_mut(id)) {
+            if let TrackedData::Player(player) = entity.data_mut() {
+                apply_skin_settings(player, settings);
+            }
+        }
+    }
+
+    /// Removes the player entity owned by this client if the client has
+    /// disconnected.
+    pub(crate) fn remove_disconnected_player_entity(&mut self, entities: &mut Entities<C>) {
+        if self.is_disconnected() {
+            if let Some(id) = self.player_entity.take() {
+                entities.remove(id);
+            }
+        }
+    }
+
     /// Attempts to enqueue a play packet to be sent to this client. The client
     /// is disconnected if the clientbound packet buffer is full.
     pub fn send_packet(&mut self, packet: impl Into<S2cPlayPacket>) {
         send_packet(&mut self.send, packet);
     }
 
-    pub(crate) fn handle_serverbound_packets(&mut self, entities: &Entities<C>) {
+    /// Schedules a play packet to be sent to this client when the client is
+    /// updated at the given tick of [`SharedServer::current_tick`]. Packets
+    /// scheduled for the same tick are sent in the order they were scheduled,
+    /// and packets scheduled for a past tick are sent at the next update.
+    ///
+    /// This is useful for sequences of effects such as countdowns or
+    /// cutscenes.
+    pub fn send_packet_at(&mut self, tick: Ticks, packet: impl Into<S2cPlayPacket>) {
+        let idx = self.scheduled_packets.partition_point(|&(t, _)| t <= tick);
+        self.scheduled_packets.insert(idx, (tick, packet.into()));
+    }
+
+    /// Discards all packets scheduled with [`Self::send_packet_at`] which have
+    /// not been sent yet.
+    pub fn clear_scheduled_packets(&mut self) {
+        self.scheduled_packets.clear();
+    }
+
+    /// Gets the entities this client has loaded.
+    pub(crate) fn loaded_entities(&self) -> &HashSet<EntityId> {
+        &self.loaded_entities
+    }
+
+    /// Takes the commands sent by this client since the last call.
+    pub(crate) fn take_commands(&mut self) -> Vec<String> {
+        mem::take(&mut self.pending_commands)
+    }
+
+    pub(crate) fn handle_serverbound_packets(
+        &mut self,
+        shared: &SharedServer<C>,
+        entities: &Entities<C>,
+        worlds: &Worlds<C>,
+    ) {
         self.events.clear();
+        self.check_flood_limit(shared);
+        let protection = worlds.get(self.world).map(|w| &w.protection);
+
         for _ in 0..self.recv.len() {
-            self.handle_serverbound_packet(entities, self.recv.try_recv().unwrap());
+            let pkt = self.recv.try_recv().unwrap();
+            if self.check_rate_limit(shared, &pkt) {
+                self.handle_serverbound_packet(entities, protection, pkt);
+            }
+        }
+    }
+
+    /// Returns the name of the region in `protection` denying this client
+    /// `action` at `pos`, if any.
+    fn denying_region<'a>(
+        &self,
+        protection: Option<&'a Protection>,
+        action: ProtectedAction,
+        pos: BlockPos,
+    ) -> Option<&'a str> {
+        protection?.check(action, pos, self.uuid).err()
+    }
+
+    fn push_denied_event(&mut self, action: ProtectedAction, region: &str) {
+        self.events.push_back(ClientEvent::ProtectionDenied {
+            action,
+            region: region.to_owned(),
+        });
+    }
+
+    /// Returns `true` if this client may move to `pos`. Otherwise, the client
+    /// is teleported back to its current position.
+    fn check_entry(&mut self, protection: Option<&Protection>, pos: Vec3<f64>) -> bool {
+        let (old, new) = (BlockPos::at(self.position), BlockPos::at(pos));
+        let action = ProtectedAction::Entry;
+
+        // Clients inside a region they may not enter are allowed to leave it.
+        if old == new || self.denying_region(protection, action, old).is_some() {
+            return true;
+        }
+
+        match self.denying_region(protection, action, new) {
+            Some(region) => {
+                self.push_denied_event(action, region);
+                self.teleport(self.position, self.yaw, self.pitch);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Updates the network stats and disconnects the client if it exceeds the
+    /// [`FloodLimit`].
+    fn check_flood_limit(&mut self, shared: &SharedServer<C>) {
+        let updated =
+            self.rate_meter
+                .update(&self.network, shared.current_tick(), shared.tick_rate());
+
+        if !updated || self.is_disconnected() {
+            return;
+        }
+
+        if let Some(limit) = &shared.packet_limits().flood {
+            if limit.is_exceeded_by(self.rate_meter.stats()) {
+                tracing::warn!("{} exceeded the flood limit", self.username);
+                self.disconnect(limit.reason.clone());
+            }
+        }
+    }
+
+    /// Counts the packet towards its rate limit, if any. Returns `false` if the
+    /// packet should be ignored.
+    fn check_rate_limit(&mut self, shared: &SharedServer<C>, pkt: &C2sPlayPacket) -> bool {
+        if self.is_disconnected() {
+            return false;
+        }
+
+        let (kind, limit) = match PacketKind::of(pkt) {
+            Some(kind) => match kind.limit(shared.packet_limits()) {
+                Some(limit) => (kind, limit),
+                None => return true,
+            },
+            None => return true,
+        };
+
+        if self.rate_counters[kind as usize].allow(limit, shared.current_tick()) {
+            return true;
         }
+
+        match &limit.action {
+            RateLimitAction::Drop => {}
+            RateLimitAction::Disconnect(reason) => {
+                tracing::warn!("{} exceeded the {kind:?} packet rate limit", self.username);
+                self.disconnect(reason.clone());
+            }
+        }
+
+        false
     }
 
-    fn handle_serverbound_packet(&mut self, entities: &Entities<C>, pkt: C2sPlayPacket) {
+    fn handle_serverbound_packet(
+        &mut self,
+        entities: &Entities<C>,
+        protection: Option<&Protection>,
+        pkt: C2sPlayPacket,
+    ) {
         match pkt {
             C2sPlayPacket::TeleportConfirm(p) => {
                 if self.pending_teleports == 0 {
-                    log::warn!("unexpected teleport confirmation from {}", self.username());
-                    self.disconnect_no_reason();
+                    tracing::warn!("unexpected teleport confirmation from {}", self.username());
+                    self.disconnect_with(DisconnectReason::ProtocolError);
                     return;
                 }
 
@@ -682,25 +1811,28 @@ impl<C: Config> Client<C> {
                 if got == expected {
                     self.pending_teleports -= 1;
                 } else {
-                    log::warn!(
+                    tracing::warn!(
                         "unexpected teleport ID from {} (expected {expected}, got {got})",
                         self.username()
                     );
-                    self.disconnect_no_reason();
+                    self.disconnect_with(DisconnectReason::ProtocolError);
                 }
             }
             C2sPlayPacket::QueryBlockNbt(_) => {}
             C2sPlayPacket::UpdateDifficulty(_) => {}
             C2sPlayPacket::MessageAcknowledgment(_) => {}
-            C2sPlayPacket::CommandExecution(_) => {}
+            C2sPlayPacket::CommandExecution(p) => self.pending_commands.push(p.command.0),
             C2sPlayPacket::ChatMessage(p) => self.events.push_back(ClientEvent::ChatMessage {
                 message: p.message.0,
                 timestamp: Duration::from_millis(p.timestamp),
             }),
             C2sPlayPacket::RequestChatPreview(_) => {}
-            C2sPlayPacket::ClientStatus(_) => {}
+            C2sPlayPacket::ClientStatus(p) => match p {
+                ClientStatus::PerformRespawn => self.events.push_back(ClientEvent::RespawnRequest),
+                ClientStatus::RequestStatus => {}
+            },
             C2sPlayPacket::ClientSettings(p) => {
-                self.events.push_back(ClientEvent::SettingsChanged {
+                let new = Settings {
                     locale: p.locale.0,
                     view_distance: p.view_distance.0,
                     chat_mode: p.chat_mode,
@@ -708,13 +1840,22 @@ impl<C: Config> Client<C> {
                     main_hand: p.main_hand,
                     displayed_skin_parts: p.displayed_skin_parts,
                     allow_server_listings: p.allow_server_listings,
-                })
+                };
+
+                self.handle_settings(new);
             }
             C2sPlayPacket::RequestCommandCompletion(_) => {}
             C2sPlayPacket::ButtonClick(_) => {}
             C2sPlayPacket::ClickSlot(_) => {}
             C2sPlayPacket::CloseHandledScreen(_) => {}
-            C2sPlayPacket::CustomPayload(_) => {}
+            C2sPlayPacket::CustomPayload(p) => {
+                if p.channel == ident!("brand") {
+                    match String::decode(&mut p.data.0.as_slice()) {
+                        Ok(brand) => self.brand = Some(brand),
+                        Err(e) => tracing::debug!("invalid brand from {}: {e:#}", self.username),
+                    }
+                }
+            }
             C2sPlayPacket::BookUpdate(_) => {}
             C2sPlayPacket::QueryEntityNbt(_) => {}
             C2sPlayPacket::PlayerInteractEntity(p) => {
@@ -722,6 +1863,26 @@ impl<C: Config> Client<C> {
                     // TODO: verify that the client has line of sight to the targeted entity and
                     // that the distance is <=4 blocks.
 
+                    if let (InteractKind::Attack, Some(target)) = (&p.kind, entities.get(id)) {
+                        if target.kind() == EntityKind::Player {
+                            let action = ProtectedAction::Pvp;
+                            let region = self
+                                .denying_region(protection, action, BlockPos::at(self.position))
+                                .or_else(|| {
+                                    self.denying_region(
+                                        protection,
+                                        action,
+                                        BlockPos::at(target.position()),
+                                    )
+                                });
+
+                            if let Some(region) = region {
+                                self.push_denied_event(action, region);
+                                return;
+                            }
+                        }
+                    }
+
                     self.events.push_back(ClientEvent::InteractWithEntity {
                         id,
                         sneaking: p.sneaking,
@@ -739,24 +1900,25 @@ impl<C: Config> Client<C> {
             C2sPlayPacket::KeepAlive(p) => {
                 let last_keepalive_id = self.last_keepalive_id;
                 if self.bits.got_keepalive() {
-                    log::warn!("unexpected keepalive from player {}", self.username());
-                    self.disconnect_no_reason();
+                    tracing::warn!("unexpected keepalive from player {}", self.username());
+                    self.disconnect_with(DisconnectReason::ProtocolError);
                 } else if p.id != last_keepalive_id {
-                    log::warn!(
+                    tracing::warn!(
                         "keepalive ids for player {} don't match (expected {}, got {})",
                         self.username(),
                         last_keepalive_id,
                         p.id
                     );
-                    self.disconnect_no_reason();
+                    self.disconnect_with(DisconnectReason::ProtocolError);
                 } else {
                     self.bits.set_got_keepalive(true);
                 }
             }
             C2sPlayPacket::UpdateDifficultyLock(_) => {}
             C2sPlayPacket::MovePlayerPosition(p) => {
-                if self.pending_teleports == 0 {
+                if self.pending_teleports == 0 && self.check_entry(protection, p.position) {
                     self.position = p.position;
+                    self.bits.set_on_ground(p.on_ground);
 
                     self.events.push_back(ClientEvent::MovePosition {
                         position: p.position,
@@ -765,10 +1927,11 @@ impl<C: Config> Client<C> {
                 }
             }
             C2sPlayPacket::MovePlayerPositionAndRotation(p) => {
-                if self.pending_teleports == 0 {
+                if self.pending_teleports == 0 && self.check_entry(protection, p.position) {
                     self.position = p.position;
                     self.yaw = p.yaw;
                     self.pitch = p.pitch;
+                    self.bits.set_on_ground(p.on_ground);
 
                     self.events.push_back(ClientEvent::MovePositionAndRotation {
                         position: p.position,
@@ -782,6 +1945,7 @@ impl<C: Config> Client<C> {
                 if self.pending_teleports == 0 {
                     self.yaw = p.yaw;
                     self.pitch = p.pitch;
+                    self.bits.set_on_ground(p.on_ground);
 
                     self.events.push_back(ClientEvent::MoveRotation {
                         yaw: p.yaw,
@@ -792,13 +1956,15 @@ impl<C: Config> Client<C> {
             }
             C2sPlayPacket::MovePlayerOnGround(p) => {
                 if self.pending_teleports == 0 {
+                    self.bits.set_on_ground(p.on_ground);
+
                     self.events.push_back(ClientEvent::MoveOnGround {
                         on_ground: p.on_ground,
                     });
                 }
             }
             C2sPlayPacket::MoveVehicle(p) => {
-                if self.pending_teleports == 0 {
+                if self.pending_teleports == 0 && self.check_entry(protection, p.position) {
                     self.position = p.position;
                     self.yaw = p.yaw;
                     self.pitch = p.pitch;
@@ -827,29 +1993,75 @@ impl<C: Config> Client<C> {
                     self.dug_blocks.push(p.sequence.0);
                 }
 
+                let digging = matches!(
+                    p.status,
+                    PacketDiggingStatus::StartedDigging
+                        | PacketDiggingStatus::CancelledDigging
+                        | PacketDiggingStatus::FinishedDigging
+                );
+
+                if digging {
+                    let action = ProtectedAction::BlockBreak;
+                    if let Some(region) = self.denying_region(protection, action, p.location) {
+                        // Some blocks are broken as soon as digging starts, so the block is
+                        // restored in either case. Only the start of digging is reported to
+                        // avoid reporting every attempt twice.
+                        match p.status {
+                            PacketDiggingStatus::StartedDigging => {
+                                self.restore_dug_block(p.location);
+                                self.push_denied_event(action, region);
+                            }
+                            PacketDiggingStatus::FinishedDigging => {
+                                self.restore_dug_block(p.location);
+                            }
+                            _ => {}
+                        }
+                        return;
+                    }
+                }
+
+                // Players in creative mode break blocks as soon as they start digging.
+                let broken = match p.status {
+                    PacketDiggingStatus::FinishedDigging => true,
+                    PacketDiggingStatus::StartedDigging => self.game_mode() == GameMode::Creative,
+                    _ => false,
+                };
+
+                if broken {
+                    self.restore_dug_block(p.location);
+                }
+
                 self.events.push_back(match p.status {
-                    DiggingStatus::StartedDigging => ClientEvent::Digging {
-                        status: event::DiggingStatus::Start,
+                    PacketDiggingStatus::StartedDigging => ClientEvent::Digging {
+                        status: DiggingStatus::Start,
                         position: p.location,
                         face: p.face,
                     },
-                    DiggingStatus::CancelledDigging => ClientEvent::Digging {
-                        status: event::DiggingStatus::Cancel,
+                    PacketDiggingStatus::CancelledDigging => ClientEvent::Digging {
+                        status: DiggingStatus::Cancel,
                         position: p.location,
                         face: p.face,
                     },
-                    DiggingStatus::FinishedDigging => ClientEvent::Digging {
-                        status: event::DiggingStatus::Finish,
+                    PacketDiggingStatus::FinishedDigging => ClientEvent::Digging {
+                        status: DiggingStatus::Finish,
                         position: p.location,
                         face: p.face,
                     },
-                    DiggingStatus::DropItemStack => return,
-                    DiggingStatus::DropItem => return,
-                    DiggingStatus::ShootArrowOrFinishEating => return,
-                    DiggingStatus::SwapItemInHand => return,
+                    PacketDiggingStatus::DropItemStack => return,
+                    PacketDiggingStatus::DropItem => return,
+                    PacketDiggingStatus::ShootArrowOrFinishEating => return,
+                    PacketDiggingStatus::SwapItemInHand => return,
                 });
             }
             C2sPlayPacket::PlayerCommand(c) => {
+                match c.action_id {
+                    PlayerCommandId::StartSneaking => self.bits.set_sneaking(true),
+                    PlayerCommandId::StopSneaking => self.bits.set_sneaking(false),
+                    PlayerCommandId::StartSprinting => self.bits.set_sprinting(true),
+                    PlayerCommandId::StopSprinting => self.bits.set_sprinting(false),
+                    _ => {}
+                }
+
                 self.events.push_back(match c.action_id {
                     PlayerCommandId::StartSneaking => ClientEvent::StartSneaking,
                     PlayerCommandId::StopSneaking => ClientEvent::StopSneaking,
@@ -880,9 +2092,34 @@ impl<C: Config> Client<C> {
             C2sPlayPacket::UpdateJigsaw(_) => {}
             C2sPlayPacket::UpdateStructureBlock(_) => {}
             C2sPlayPacket::UpdateSign(_) => {}
-            C2sPlayPacket::HandSwing(p) => self.events.push_back(ClientEvent::ArmSwing(p.hand)),
+            C2sPlayPacket::HandSwing(p) => {
+                match p.hand {
+                    Hand::Main => self.bits.set_swung_main_hand(true),
+                    Hand::Off => self.bits.set_swung_off_hand(true),
+                }
+
+                self.events.push_back(ClientEvent::ArmSwing(p.hand))
+            }
             C2sPlayPacket::SpectatorTeleport(_) => {}
             C2sPlayPacket::PlayerInteractBlock(p) => {
+                // The block is placed either at the clicked block or next to it.
+                let placed = p.location.get_in_direction(p.face);
+                let action = ProtectedAction::BlockPlace;
+                let region = self
+                    .denying_region(protection, action, p.location)
+                    .or_else(|| self.denying_region(protection, action, placed));
+
+                if let Some(region) = region {
+                    // Acknowledging the interaction makes the client undo its prediction.
+                    if p.sequence.0 != 0 {
+                        self.dug_blocks.push(p.sequence.0);
+                    }
+                    self.restore_dug_block(p.location);
+                    self.restore_dug_block(placed);
+                    self.push_denied_event(action, region);
+                    return;
+                }
+
                 self.events.push_back(ClientEvent::InteractWithBlock {
                     hand: p.hand,
                     location: p.location,
@@ -902,37 +2139,47 @@ impl<C: Config> Client<C> {
         entities: &Entities<C>,
         worlds: &Worlds<C>,
         player_lists: &PlayerLists<C>,
+        commands: &Commands<C>,
+        permissions: &Permissions,
     ) {
         // Mark the client as disconnected when appropriate.
-        if self.recv.is_disconnected() || self.send.as_ref().map_or(true, |s| s.is_disconnected()) {
-            self.send = None;
-            return;
+        match &self.send {
+            Ok(lanes) if self.recv.is_disconnected() || lanes[0].is_disconnected() => {
+                self.send = Err(DisconnectReason::ConnectionClosed);
+                return;
+            }
+            Ok(_) => {}
+            Err(_) => return,
         }
 
         let world = match worlds.get(self.world) {
             Some(world) => world,
             None => {
-                log::warn!(
+                tracing::warn!(
                     "client {} is in an invalid world and must be disconnected",
                     self.username()
                 );
-                self.disconnect_no_reason();
+                self.disconnect_with(DisconnectReason::InvalidWorld);
                 return;
             }
         };
 
         let current_tick = shared.current_tick();
 
+        let lists: Vec<&PlayerList<C>> = self
+            .new_player_lists
+            .iter()
+            .map(|id| player_lists.get(id))
+            .collect();
+
         // Send the join game packet and other initial packets. We defer this until now
         // so that the user can set the client's initial location, game mode, etc.
         if self.created_this_tick() {
             self.bits.set_spawn(false);
+            self.bits.set_portal_travel(false);
 
-            if let Some(id) = &self.new_player_list {
-                player_lists
-                    .get(id)
-                    .initial_packets(|p| send_packet(&mut self.send, p));
-            }
+            self.send_initial_player_lists(&lists, current_tick);
+            self.old_player_lists = self.new_player_lists.clone();
 
             let mut dimension_names: Vec<_> = shared
                 .dimensions()
@@ -941,6 +2188,12 @@ impl<C: Config> Client<C> {
 
             dimension_names.push(ident!("{LIBRARY_NAMESPACE}:dummy_dimension"));
 
+            // The client keeps the hardcore mode it joined with until it
+            // reconnects.
+            self.bits
+                .set_hardcore(self.bits.hardcore() || world.meta.is_hardcore());
+            self.bits.set_respawn_screen_modified(false);
+
             self.send_packet(GameJoin {
                 entity_id: 0, // EntityId 0 is reserved for clients.
                 is_hardcore: self.bits.hardcore(),
@@ -958,22 +2211,26 @@ impl<C: Config> Client<C> {
                 ),
                 hashed_seed: 0,
                 max_players: VarInt(0),
-                view_distance: BoundedInt(VarInt(self.view_distance() as i32)),
+                view_distance: BoundedInt(VarInt(self.effective_view_distance() as i32)),
                 simulation_distance: VarInt(16),
                 reduced_debug_info: false,
-                enable_respawn_screen: false,
+                enable_respawn_screen: self.bits.respawn_screen(),
                 is_debug: false,
-                is_flat: self.bits.flat(),
+                is_flat: self.bits.flat() || world.meta.is_flat(),
                 last_death_location: self
                     .death_location
                     .map(|(id, pos)| (ident!("{LIBRARY_NAMESPACE}:dimension_{}", id.0), pos)),
             });
 
             self.teleport(self.position(), self.yaw(), self.pitch());
+            self.bits.set_op_level_modified(true);
+
+            self.send_weather(&world.meta, true);
         } else {
             if self.bits.spawn() {
                 self.bits.set_spawn(false);
                 self.loaded_entities.clear();
+                self.disguised_entities.clear();
                 self.loaded_chunks.clear();
 
                 // Client bug workaround: send the client to a dummy dimension first.
@@ -1003,14 +2260,48 @@ impl<C: Config> Client<C> {
                     game_mode: self.game_mode(),
                     previous_game_mode: self.game_mode(),
                     is_debug: false,
-                    is_flat: self.bits.flat(),
-                    copy_metadata: true,
+                    is_flat: self.bits.flat() || world.meta.is_flat(),
+                    copy_metadata: self.bits.copy_metadata(),
                     last_death_location: self
                         .death_location
                         .map(|(id, pos)| (ident!("{LIBRARY_NAMESPACE}:dimension_{}", id.0), pos)),
                 });
 
                 self.teleport(self.position(), self.yaw(), self.pitch());
+                // The client forgets its operator level when respawning.
+                self.bits.set_op_level_modified(true);
+
+                if !self.bits.copy_metadata() {
+                    self.bits.set_attack_speed_modified(true);
+                    self.bits.set_movement_speed_modified(true);
+
+                    let mut data = Vec::new();
+                    self.player_data.initial_tracked_data(&mut data);
+
+                    if !data.is_empty() {
+                        data.push(0xff);
+
+                        self.send_packet(EntityTrackerUpdate {
+                            entity_id: VarInt(0),
+                            metadata: RawBytes(data),
+                        });
+                    }
+                }
+
+                self.send_weather(&world.meta, true);
+
+                if self.bits.portal_travel() {
+                    self.bits.set_portal_travel(false);
+
+                    self.send_packet(WorldEvent {
+                        event: LevelEvent::PortalTravel.id(),
+                        location: BlockPos::at(self.position()),
+                        data: 0,
+                        disable_relative_volume: false,
+                    });
+                }
+            } else {
+                self.send_weather(&world.meta, false);
             }
 
             // Update game mode
@@ -1022,29 +2313,89 @@ impl<C: Config> Client<C> {
                 });
             }
 
-            // If the player list was changed...
-            if self.old_player_list != self.new_player_list {
-                // Delete existing entries from old player list.
-                if let Some(id) = &self.old_player_list {
-                    player_lists
-                        .get(id)
-                        .clear_packets(|p| send_packet(&mut self.send, p));
+            if self.bits.respawn_screen_modified() {
+                self.bits.set_respawn_screen_modified(false);
+                // A value of 1 makes the client respawn immediately.
+                let immediate_respawn = !self.bits.respawn_screen();
+                self.send_game_state_change(
+                    GameStateChangeReason::EnableRespawnScreen,
+                    immediate_respawn as u8 as f32,
+                );
+            }
+
+            // If the player lists were changed...
+            if self.old_player_lists != self.new_player_lists {
+                // Delete all existing entries of the old player lists in one packet before
+                // adding the entries of the new lists, so the client never sees a mix of
+                // both.
+                let mut removed = HashSet::new();
+                for id in &self.old_player_lists {
+                    removed.extend(player_lists.get(id).sent_uuids());
                 }
 
-                // Get initial packets for new player list.
-                if let Some(id) = &self.new_player_list {
-                    player_lists
-                        .get(id)
-                        .initial_packets(|p| send_packet(&mut self.send, p));
+                if !removed.is_empty() {
+                    self.send_packet(UpdatePlayerList::RemovePlayer(
+                        removed.into_iter().collect(),
+                    ));
                 }
 
-                self.old_player_list = self.new_player_list.clone();
-            } else if let Some(id) = &self.new_player_list {
-                // Update current player list.
-                player_lists
-                    .get(id)
-                    .update_packets(|p| send_packet(&mut self.send, p));
+                self.pending_unlisted.clear();
+                self.send_initial_player_lists(&lists, current_tick);
+
+                // Always replace the header and footer so the old ones do not linger.
+                let header_footer = match lists.first() {
+                    Some(pl) => pl.header_footer_packet(),
+                    None => PlayerListHeaderFooter {
+                        header: Text::default(),
+                        footer: Text::default(),
+                    },
+                };
+                self.send_packet(header_footer);
+
+                self.old_player_lists = self.new_player_lists.clone();
+            } else {
+                // Update current player lists.
+                for (i, pl) in lists.iter().enumerate() {
+                    pl.update_packets(
+                        |p| send_packet(&mut self.send, p),
+                        |uuid| {
+                            lists.iter().enumerate().any(|(j, other)| {
+                                j != i && other.get(uuid).is_some_and(|e| e.is_shown())
+                            })
+                        },
+                    );
+                    self.pending_unlisted.extend(
+                        pl.added_unlisted(false)
+                            .map(|uuid| (uuid, current_tick + UNLIST_DELAY_TICKS)),
+                    );
+                }
+
+                if let Some(pl) = lists.first() {
+                    if pl.modified_header_or_footer() {
+                        self.send_packet(pl.header_footer_packet());
+                    }
+                }
+            }
+        }
+
+        // Update the operator level and the commands the client can see.
+        if self.bits.op_level_modified() || commands.modified() || permissions.modified() {
+            if self.bits.op_level_modified() {
+                self.send_packet(EntityStatus {
+                    entity_id: 0,
+                    entity_status: 24 + self.op_level,
+                });
             }
+
+            self.bits.set_op_level_modified(false);
+
+            let op_level = self.op_level;
+            let uuid = self.uuid;
+            self.send_packet(
+                commands.declare_packet(|cmd| {
+                    cmd.allows(op_level, |node| permissions.check(uuid, node))
+                }),
+            );
         }
 
         // Set player attributes
@@ -1090,7 +2441,7 @@ impl<C: Config> Client<C> {
 
             if !self.created_this_tick() {
                 self.send_packet(ChunkLoadDistance {
-                    view_distance: BoundedInt(VarInt(self.view_distance() as i32)),
+                    view_distance: BoundedInt(VarInt(self.effective_view_distance() as i32)),
                 });
             }
         }
@@ -1103,15 +2454,16 @@ impl<C: Config> Client<C> {
                 self.last_keepalive_id = id;
                 self.bits.set_got_keepalive(false);
             } else {
-                log::warn!(
+                tracing::warn!(
                     "player {} timed out (no keepalive response)",
                     self.username()
                 );
-                self.disconnect_no_reason();
+                self.disconnect_with(DisconnectReason::KeepaliveTimeout);
             }
         }
 
         let center = ChunkPos::at(self.position.x, self.position.z);
+        let view_distance = self.effective_view_distance();
 
         // Send the update view position packet if the client changes the chunk section
         // they're in.
@@ -1137,12 +2489,16 @@ impl<C: Config> Client<C> {
             let cache = 2;
 
             if let Some(chunk) = world.chunks.get(pos) {
-                if is_chunk_in_view_distance(center, pos, self.view_distance + cache)
+                if is_chunk_in_view_distance(center, pos, view_distance + cache)
                     && !chunk.created_this_tick()
                 {
-                    chunk.block_change_packets(pos, dimension.min_y, |pkt| {
-                        send_packet(&mut self.send, pkt)
-                    });
+                    if chunk.resent_this_tick() {
+                        send_packet(&mut self.send, chunk.chunk_data_packet(pos));
+                    } else {
+                        chunk.block_change_packets(pos, dimension.min_y, |pkt| {
+                            send_packet(&mut self.send, pkt)
+                        });
+                    }
                     return true;
                 }
             }
@@ -1158,7 +2514,7 @@ impl<C: Config> Client<C> {
         });
 
         // Load new chunks within the view distance
-        for pos in chunks_in_view_distance(center, self.view_distance) {
+        for pos in chunks_in_view_distance(center, view_distance) {
             if let Some(chunk) = world.chunks.get(pos) {
                 if self.loaded_chunks.insert(pos) {
                     self.send_packet(chunk.chunk_data_packet(pos));
@@ -1167,6 +2523,31 @@ impl<C: Config> Client<C> {
             }
         }
 
+        // Restore blocks the client broke unless they were changed this tick.
+        for pos in self.predicted_dug_blocks.drain(..) {
+            let chunk_pos = ChunkPos::from(pos);
+            if !self.loaded_chunks.contains(&chunk_pos) {
+                continue;
+            }
+
+            if let (Some(chunk), Ok(y)) = (
+                world.chunks.get(chunk_pos),
+                usize::try_from(pos.y - dimension.min_y),
+            ) {
+                let (x, z) = (pos.x.rem_euclid(16) as usize, pos.z.rem_euclid(16) as usize);
+
+                if y < chunk.height() && !chunk.is_block_modified(x, y, z) {
+                    send_packet(
+                        &mut self.send,
+                        BlockUpdate {
+                            location: pos,
+                            block_id: VarInt(chunk.get_block_state(x, y, z).to_raw() as i32),
+                        },
+                    );
+                }
+            }
+        }
+
         // Acknowledge broken blocks.
         for seq in self.dug_blocks.drain(..) {
             send_packet(
@@ -1184,11 +2565,18 @@ impl<C: Config> Client<C> {
         if self.bits.teleported_this_tick() {
             self.bits.set_teleported_this_tick(false);
 
+            let f = self.teleport_flags;
+            let (offset, yaw_offset, pitch_offset) = self.teleport_offset;
+
             self.send_packet(PlayerPositionLook {
-                position: self.position,
-                yaw: self.yaw,
-                pitch: self.pitch,
-                flags: PlayerPositionLookFlags::new(false, false, false, false, false),
+                position: Vec3::new(
+                    if f.x { offset.x } else { self.position.x },
+                    if f.y { offset.y } else { self.position.y },
+                    if f.z { offset.z } else { self.position.z },
+                ),
+                yaw: if f.yaw { yaw_offset } else { self.yaw },
+                pitch: if f.pitch { pitch_offset } else { self.pitch },
+                flags: PlayerPositionLookFlags::new(f.x, f.y, f.z, f.yaw, f.pitch),
                 teleport_id: VarInt(self.teleport_id_counter as i32),
                 dismount_vehicle: false,
             });
@@ -1196,8 +2584,8 @@ impl<C: Config> Client<C> {
             self.pending_teleports = self.pending_teleports.wrapping_add(1);
 
             if self.pending_teleports == 0 {
-                log::warn!("too many pending teleports for {}", self.username());
-                self.disconnect_no_reason();
+                tracing::warn!("too many pending teleports for {}", self.username());
+                self.disconnect_with(DisconnectReason::ProtocolError);
                 return;
             }
 
@@ -1216,7 +2604,22 @@ impl<C: Config> Client<C> {
         }
 
         // Send chat messages.
-        for msg in self.msgs_to_send.drain(..) {
+        let (chat_hidden, chat_colors) = match &self.settings {
+            Some(settings) => (settings.chat_mode == ChatMode::Hidden, settings.chat_colors),
+            None => (false, true),
+        };
+
+        for (mut msg, critical) in self.msgs_to_send.drain(..) {
+            if !critical {
+                if chat_hidden {
+                    continue;
+                }
+
+                if !chat_colors {
+                    msg.strip_colors();
+                }
+            }
+
             send_packet(
                 &mut self.send,
                 GameMessage {
@@ -1230,6 +2633,25 @@ impl<C: Config> Client<C> {
             send_packet(&mut self.send, OverlayMessage { text: bar });
         }
 
+        self.titles.update(|pkt| send_packet(&mut self.send, pkt));
+
+        // Send the scheduled packets which are due.
+        let due = self
+            .scheduled_packets
+            .partition_point(|&(tick, _)| tick <= current_tick);
+        for (_, pkt) in self.scheduled_packets.drain(..due) {
+            send_packet(&mut self.send, pkt);
+        }
+
+        if self.sidebar != self.old_sidebar {
+            update_sidebar_packets(self.old_sidebar.as_ref(), self.sidebar.as_ref(), |pkt| {
+                send_packet(&mut self.send, pkt)
+            });
+            self.old_sidebar = self.sidebar.clone();
+        }
+
+        self.glow.retain_existing(|id| entities.get(id).is_some());
+
         let mut entities_to_unload = Vec::new();
 
         // Update all entities that are visible and unload entities that are no
@@ -1237,42 +2659,59 @@ impl<C: Config> Client<C> {
         self.loaded_entities.retain(|&id| {
             if let Some(entity) = entities.get(id) {
                 debug_assert!(entity.kind() != EntityKind::Marker);
-                if self.position.distance(entity.position()) <= self.view_distance as f64 * 16.0 {
-                    if let Some(meta) = entity.updated_tracked_data_packet(id) {
-                        send_packet(&mut self.send, meta);
+                if entity.world() == self.world
+                    && self.position.distance(entity.position()) <= view_distance as f64 * 16.0
+                    && entity.is_visible_to(self.uuid)
+                {
+                    if !self.disguised_entities.contains(&id) {
+                        if let Some(meta) = entity.updated_tracked_data_packet(id) {
+                            send_packet(&mut self.send, meta);
+                        }
                     }
 
-                    let position_delta = entity.position() - entity.old_position();
-                    let needs_teleport = position_delta.map(f64::abs).reduce_partial_max() >= 8.0;
+                    // Send the fewest packets needed to bring the entity up to date.
+                    // Teleports include the rotation, and changes to angles too small to
+                    // be seen by clients are not sent.
+                    let movement = entity.movement_update();
+                    let rotated = entity.rotation_changed();
                     let flags = entity.bits();
 
-                    if entity.position() != entity.old_position()
-                        && !needs_teleport
-                        && flags.yaw_or_pitch_modified()
-                    {
-                        send_packet(
-                            &mut self.send,
-                            RotateAndMoveRelative {
-                                entity_id: VarInt(id.to_network_id()),
-                                delta: (position_delta * 4096.0).as_(),
-                                yaw: ByteAngle::from_degrees(entity.yaw()),
-                                pitch: ByteAngle::from_degrees(entity.pitch()),
-                                on_ground: entity.on_ground(),
-                            },
-                        );
-                    } else {
-                        if entity.position() != entity.old_position() && !needs_teleport {
+                    match movement {
+                        MovementUpdate::Relative(delta) if rotated => {
+                            send_packet(
+                                &mut self.send,
+                                RotateAndMoveRelative {
+                                    entity_id: VarInt(id.to_network_id()),
+                                    delta: (delta * 4096.0).as_(),
+                                    yaw: ByteAngle::from_degrees(entity.yaw()),
+                                    pitch: ByteAngle::from_degrees(entity.pitch()),
+                                    on_ground: entity.on_ground(),
+                                },
+                            );
+                        }
+                        MovementUpdate::Relative(delta) => {
                             send_packet(
                                 &mut self.send,
                                 MoveRelative {
                                     entity_id: VarInt(id.to_network_id()),
-                                    delta: (position_delta * 4096.0).as_(),
+                                    delta: (delta * 4096.0).as_(),
                                     on_ground: entity.on_ground(),
                                 },
                             );
                         }
-
-                        if flags.yaw_or_pitch_modified() {
+                        MovementUpdate::Teleport => {
+                            send_packet(
+                                &mut self.send,
+                                EntityPosition {
+                                    entity_id: VarInt(id.to_network_id()),
+                                    position: entity.position(),
+                                    yaw: ByteAngle::from_degrees(entity.yaw()),
+                                    pitch: ByteAngle::from_degrees(entity.pitch()),
+                                    on_ground: entity.on_ground(),
+                                },
+                            );
+                        }
+                        MovementUpdate::None if rotated => {
                             send_packet(
                                 &mut self.send,
                                 Rotate {
@@ -1283,19 +2722,7 @@ impl<C: Config> Client<C> {
                                 },
                             );
                         }
-                    }
-
-                    if needs_teleport {
-                        send_packet(
-                            &mut self.send,
-                            EntityPosition {
-                                entity_id: VarInt(id.to_network_id()),
-                                position: entity.position(),
-                                yaw: ByteAngle::from_degrees(entity.yaw()),
-                                pitch: ByteAngle::from_degrees(entity.pitch()),
-                                on_ground: entity.on_ground(),
-                            },
-                        );
+                        MovementUpdate::None => {}
                     }
 
                     if flags.velocity_modified() {
@@ -1308,7 +2735,7 @@ impl<C: Config> Client<C> {
                         );
                     }
 
-                    if flags.head_yaw_modified() {
+                    if entity.head_yaw_changed() {
                         send_packet(
                             &mut self.send,
                             EntitySetHeadYaw {
@@ -1318,12 +2745,21 @@ impl<C: Config> Client<C> {
                         )
                     }
 
+                    self.glow.update_entity(
+                        id,
+                        entity.data().base_flags(),
+                        entity.data().base_flags_modified(),
+                        || team_entry(entity, &lists),
+                        |pkt| send_packet(&mut self.send, pkt),
+                    );
+
                     send_entity_events(&mut self.send, id.to_network_id(), entity.events());
 
                     return true;
                 }
             }
 
+            self.disguised_entities.remove(&id);
             entities_to_unload.push(VarInt(id.to_network_id()));
             false
         });
@@ -1349,55 +2785,237 @@ impl<C: Config> Client<C> {
 
         // Spawn new entities within the view distance.
         let pos = self.position();
-        let view_dist = self.view_distance;
         world.spatial_index.query::<_, _, ()>(
-            |bb| bb.projected_point(pos).distance(pos) <= view_dist as f64 * 16.0,
+            |bb| bb.projected_point(pos).distance(pos) <= view_distance as f64 * 16.0,
             |id, _| {
                 let entity = entities
                     .get(id)
                     .expect("entity IDs in spatial index should be valid at this point");
                 if entity.kind() != EntityKind::Marker
                     && entity.uuid() != self.uuid
-                    && self.loaded_entities.insert(id)
+                    && entity.is_visible_to(self.uuid)
+                    && !self.loaded_entities.contains(&id)
                 {
-                    self.send_packet(
-                        entity
-                            .spawn_packet(id)
-                            .expect("should not be a marker entity"),
-                    );
+                    // Entities vetoed by their spawn hook are skipped, which continues the
+                    // query.
+                    let (pkt, appearance) = entity.spawn_packet(id, self.uuid)?;
+                    self.loaded_entities.insert(id);
+
+                    // The client will not spawn a player without a player list entry, so
+                    // unlisted entries must be added again for a moment.
+                    if appearance.kind == EntityKind::Player && is_unlisted(&lists, appearance.uuid)
+                    {
+                        if let Some(pkt) = lists
+                            .iter()
+                            .find_map(|pl| pl.add_player_packet(appearance.uuid))
+                        {
+                            self.send_packet(pkt);
+                            self.pending_unlisted
+                                .push((appearance.uuid, current_tick + UNLIST_DELAY_TICKS));
+                        }
+                    }
+
+                    self.send_packet(pkt);
 
-                    if let Some(meta) = entity.initial_tracked_data_packet(id) {
+                    if appearance.kind != entity.kind() {
+                        self.disguised_entities.insert(id);
+                    } else if let Some(meta) = entity.initial_tracked_data_packet(id) {
                         self.send_packet(meta);
                     }
 
+                    self.glow.update_entity(
+                        id,
+                        entity.data().base_flags(),
+                        true,
+                        || team_entry(entity, &lists),
+                        |pkt| send_packet(&mut self.send, pkt),
+                    );
+
                     send_entity_events(&mut self.send, id.to_network_id(), entity.events());
                 }
                 None
             },
         );
 
+        // Lightning bolts are visible regardless of distance.
+        for strike in world.meta.lightning_strikes() {
+            self.send_packet(EntitySpawn {
+                entity_id: VarInt(strike.network_id),
+                object_uuid: strike.uuid,
+                kind: VarInt(EntityKind::Lightning as i32),
+                position: strike.position,
+                pitch: ByteAngle(0),
+                yaw: ByteAngle(0),
+                head_yaw: ByteAngle(0),
+                data: VarInt(0),
+                velocity: Vec3::zero(),
+            });
+        }
+
+        // Firework rockets are visible regardless of distance too. Their explosion is
+        // only attached right before it goes off, see `explosion_tracked_data`.
+        let mut detonated_fireworks = Vec::new();
+        for fw in world.meta.fireworks() {
+            if fw.launched {
+                self.send_packet(EntitySpawn {
+                    entity_id: VarInt(fw.network_id),
+                    object_uuid: fw.uuid,
+                    kind: VarInt(EntityKind::FireworkRocket as i32),
+                    position: fw.position,
+                    pitch: ByteAngle(0),
+                    yaw: ByteAngle(0),
+                    head_yaw: ByteAngle(0),
+                    data: VarInt(0),
+                    velocity: velocity_to_packet_units(FIREWORK_LAUNCH_VELOCITY),
+                });
+            }
+
+            if fw.ticks_left == 0 {
+                self.send_packet(EntityTrackerUpdate {
+                    entity_id: VarInt(fw.network_id),
+                    metadata: RawBytes(explosion_tracked_data(&fw.nbt)),
+                });
+                send_entity_events(
+                    &mut self.send,
+                    fw.network_id,
+                    &[EntityEvent::ExplodeFireworkClient],
+                );
+                detonated_fireworks.push(VarInt(fw.network_id));
+            }
+        }
+
+        if !detonated_fireworks.is_empty() {
+            self.send_packet(EntitiesDestroy {
+                entities: detonated_fireworks,
+            });
+        }
+
+        for event in world.meta.level_events() {
+            let global = event.event.is_global();
+
+            if global || is_chunk_in_view_distance(center, event.position.into(), view_distance) {
+                self.send_packet(WorldEvent {
+                    event: event.event.id(),
+                    location: event.position,
+                    data: event.data,
+                    disable_relative_volume: global,
+                });
+            }
+        }
+
         send_entity_events(&mut self.send, 0, &self.entity_events);
         self.entity_events.clear();
 
+        // Remove unlisted entries from the tab list once the client has had time to
+        // spawn their player entities.
+        let mut unlisted = Vec::new();
+        self.pending_unlisted.retain(|&(uuid, deadline)| {
+            if deadline > current_tick {
+                return true;
+            }
+
+            if is_unlisted(&lists, uuid) {
+                unlisted.push(uuid);
+            }
+            false
+        });
+
+        if !unlisted.is_empty() {
+            self.send_packet(UpdatePlayerList::RemovePlayer(unlisted));
+        }
+
         self.player_data.clear_modifications();
         self.old_position = self.position;
         self.bits.set_created_this_tick(false);
+    }
+
+    /// Sends the entries and header/footer of the given player lists to a
+    /// client that does not know about any of them yet.
+    fn send_initial_player_lists(&mut self, lists: &[&PlayerList<C>], current_tick: i64) {
+        for pl in lists {
+            pl.initial_packets(|p| send_packet(&mut self.send, p));
+            self.pending_unlisted.extend(
+                pl.added_unlisted(true)
+                    .map(|uuid| (uuid, current_tick + UNLIST_DELAY_TICKS)),
+            );
+        }
+
+        if let Some(pl) = lists.first() {
+            if pl.header() != &Text::default() || pl.footer() != &Text::default() {
+                self.send_packet(pl.header_footer_packet());
+            }
+        }
+    }
+}
+
+/// Returns `true` if the given player lists contain an entry with the UUID
+/// but none of the lists have it listed.
+fn is_unlisted<C: Config>(lists: &[&PlayerList<C>], uuid: Uuid) -> bool {
+    let mut entries = lists.iter().filter_map(|pl| pl.get(uuid)).peekable();
+    entries.peek().is_some() && entries.all(|e| !e.is_shown())
+}
 
-        send_packet(&mut self.send, S2cPlayMessage::Flush);
+/// Returns the name of an entity in teams, which is the username of players and
+/// the UUID of other entities.
+fn team_entry<C: Config>(entity: &Entity<C>, lists: &[&PlayerList<C>]) -> String {
+    if entity.kind() == EntityKind::Player {
+        if let Some(entry) = lists.iter().find_map(|pl| pl.get(entity.uuid())) {
+            return entry.username().to_owned();
+        }
     }
+
+    entity.uuid().hyphenated().to_string()
+}
+
+/// The number of ticks unlisted player list entries stay in the client's tab
+/// list. The client needs the entry for a moment after a player entity is
+/// spawned to load its skin.
+const UNLIST_DELAY_TICKS: i64 = 20;
+
+/// The velocity of firework rockets when they are launched, in meters per
+/// second. Clients accelerate the rockets on their own.
+const FIREWORK_LAUNCH_VELOCITY: Vec3<f32> = Vec3::new(0.0, 1.0, 0.0);
+
+/// The channels for packets to the client, one for each [`PacketLane`], or the
+/// reason the client was disconnected.
+type SendOpt = Result<[Sender<S2cPlayMessage>; 3], DisconnectReason>;
+
+fn apply_skin_settings(player: &mut Player, settings: &Settings) {
+    let parts = settings.displayed_skin_parts;
+
+    player.set_cape(parts.cape());
+    player.set_jacket(parts.jacket());
+    player.set_left_sleeve(parts.left_sleeve());
+    player.set_right_sleeve(parts.right_sleeve());
+    player.set_left_pants_leg(parts.left_pants_leg());
+    player.set_right_pants_leg(parts.right_pants_leg());
+    player.set_hat(parts.hat());
+    player.set_main_arm(settings.main_hand as u8);
 }
 
-type SendOpt = Option<Sender<S2cPlayMessage>>;
+// `try_send` hands the packet back in its error.
+#[allow(clippy::result_large_err)]
+fn send_packet(send_opt: &mut SendOpt, pkt: impl Into<S2cPlayPacket>) {
+    if let Ok(lanes) = send_opt {
+        let pkt = pkt.into();
+
+        let res = match PacketLane::of(&pkt) {
+            Some(lane) => lanes[lane as usize].try_send(S2cPlayMessage::Packet(pkt)),
+            None => lanes
+                .iter()
+                .try_for_each(|lane| lane.try_send(S2cPlayMessage::Barrier))
+                .and_then(|_| {
+                    lanes[PacketLane::Urgent as usize].try_send(S2cPlayMessage::Packet(pkt))
+                }),
+        };
 
-fn send_packet(send_opt: &mut SendOpt, pkt: impl Into<S2cPlayMessage>) {
-    if let Some(send) = send_opt {
-        match send.try_send(pkt.into()) {
+        match res {
             Err(TrySendError::Full(_)) => {
-                log::warn!("max outbound packet capacity reached for client");
-                *send_opt = None;
+                tracing::warn!("max outbound packet capacity reached for client");
+                *send_opt = Err(DisconnectReason::BufferFull);
             }
             Err(TrySendError::Disconnected(_)) => {
-                *send_opt = None;
+                *send_opt = Err(DisconnectReason::ConnectionClosed);
             }
             Ok(_) => {}
         }
@@ -1466,3 +3084,609 @@ fn make_registry_codec<C: Config>(shared: &SharedServer<C>) -> RegistryCodec {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockState;
+    use crate::protocol::packets::c2s::play::{
+        BlockFace, ChatMode, ClientSettings, DiggingStatus, DisplayedSkinParts, Hand, HandSwing,
+        MainHand, MovePlayerPositionAndRotation, PlayerAction, PlayerCommand, TeleportConfirm,
+    };
+    use crate::protocol::packets::s2c;
+    use crate::protocol::BoundedString;
+    use crate::testing::{connected_client, messages, test_server, Game, MockClient, TestServer};
+    use crate::text::{Color, TextFormat};
+
+    #[test]
+    fn relative_teleport() {
+        let (mut server, client) = connected_client();
+
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        c.teleport_relative([1.0, 0.0, 0.0], 90.0, 0.0, TeleportFlags::POSITION);
+        c.teleport_relative([2.0, 50.0, 0.0], 0.0, 0.0, TeleportFlags::ALL);
+        c.teleport_relative(
+            [0.0, 64.0, 0.0],
+            0.0,
+            0.0,
+            TeleportFlags {
+                y: false,
+                ..TeleportFlags::ALL
+            },
+        );
+        assert_eq!(c.position(), Vec3::new(3.5, 64.0, 0.5));
+        assert_eq!(c.yaw(), 90.0);
+        server.tick();
+
+        let teleports: Vec<_> = client
+            .take_packets()
+            .into_iter()
+            .filter_map(|pkt| match pkt {
+                S2cPlayPacket::PlayerPositionLook(p) => Some(p),
+                _ => None,
+            })
+            .collect();
+
+        let [p] = teleports.as_slice() else {
+            panic!("expected one teleport, got {teleports:?}");
+        };
+        assert_eq!(p.position, Vec3::new(3.0, 64.0, 0.0));
+        assert_eq!(p.yaw, 90.0);
+        assert_eq!(
+            (
+                p.flags.x(),
+                p.flags.y(),
+                p.flags.z(),
+                p.flags.y_rot(),
+                p.flags.x_rot()
+            ),
+            (true, false, true, false, false)
+        );
+    }
+
+    #[test]
+    fn respawn_resets_client() {
+        let (mut server, client) = connected_client();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let c = s.clients.get_mut(client.id()).unwrap();
+        c.titles_mut().push_title(
+            "title",
+            "",
+            TitleFade {
+                fade_in: 10,
+                stay: 70,
+                fade_out: 20,
+            },
+        );
+        c.set_velocity([0.0, 5.0, 0.0]);
+        c.respawn(world_id, [1.5, 80.0, 1.5], 45.0, 0.0);
+        assert_eq!(c.position(), Vec3::new(1.5, 80.0, 1.5));
+        assert_eq!(c.velocity(), Vec3::zero());
+        assert!(c.titles().is_empty());
+        server.tick();
+
+        let pkts = client.take_packets();
+        let respawns = pkts
+            .iter()
+            .filter(|pkt| matches!(pkt, S2cPlayPacket::PlayerRespawn(_)))
+            .count();
+        assert_eq!(respawns, 2);
+        assert!(pkts
+            .iter()
+            .any(|pkt| matches!(pkt, S2cPlayPacket::ClearTitles(_))));
+        assert!(!pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::UpdateTitle(_) | S2cPlayPacket::EntityVelocityUpdate(_)
+        )));
+        assert!(pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::PlayerPositionLook(p) if p.position == Vec3::new(1.5, 80.0, 1.5)
+        )));
+    }
+
+    #[test]
+    fn urgent_packets_overtake_chunks() {
+        let (mut server, client) = connected_client();
+
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        c.send_packet(UnloadChunk {
+            chunk_x: 5,
+            chunk_z: 5,
+        });
+        c.send_packet(s2c::play::KeepAlive { id: 7 });
+
+        assert!(matches!(
+            client.take_packets().as_slice(),
+            [S2cPlayPacket::KeepAlive(_), S2cPlayPacket::UnloadChunk(_)]
+        ));
+
+        // Nothing overtakes a respawn or is overtaken by it.
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let c = s.clients.get_mut(client.id()).unwrap();
+        c.send_packet(UnloadChunk {
+            chunk_x: 5,
+            chunk_z: 5,
+        });
+        c.respawn(world_id, [1.5, 80.0, 1.5], 45.0, 0.0);
+        server.tick();
+
+        let pkts = client.take_packets();
+        let index_of = |f: fn(&S2cPlayPacket) -> bool| pkts.iter().position(f).unwrap();
+        let unload = index_of(|pkt| matches!(pkt, S2cPlayPacket::UnloadChunk(_)));
+        let respawn = index_of(|pkt| matches!(pkt, S2cPlayPacket::PlayerRespawn(_)));
+        let teleport = index_of(|pkt| matches!(pkt, S2cPlayPacket::PlayerPositionLook(_)));
+        assert!(unload < respawn && respawn < teleport);
+    }
+
+    #[test]
+    fn dimension_change_after_respawn_request() {
+        let (mut server, client) = connected_client();
+
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        c.set_copy_metadata(false);
+        c.show_credits();
+        assert!(client.send(ClientStatus::PerformRespawn));
+        server.tick();
+
+        let pkts = client.take_packets();
+        let respawn = pkts.iter().rev().find_map(|pkt| match pkt {
+            S2cPlayPacket::PlayerRespawn(p) => Some(p),
+            _ => None,
+        });
+        assert!(!respawn.expect("no respawn packet").copy_metadata);
+        assert!(pkts
+            .iter()
+            .any(|pkt| matches!(pkt, S2cPlayPacket::EntityAttributes(_))));
+        assert!(pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::WorldEvent(p) if p.event == LevelEvent::PortalTravel.id()
+        )));
+    }
+
+    #[test]
+    fn scheduled_packets_sent_in_order() {
+        let (mut server, client) = connected_client();
+
+        let tick = server.server().shared.current_tick();
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        c.send_packet_at(tick + 2, UpdateTitle { text: "3".into() });
+        c.send_packet_at(tick + 1, UpdateTitle { text: "1".into() });
+        c.send_packet_at(tick + 1, UpdateTitle { text: "2".into() });
+        c.send_packet_at(tick + 5, UpdateTitle { text: "4".into() });
+
+        let titles = |client: &MockClient| -> Vec<_> {
+            client
+                .take_packets()
+                .into_iter()
+                .filter_map(|pkt| match pkt {
+                    S2cPlayPacket::UpdateTitle(p) => Some(p.text),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        server.tick();
+        assert!(titles(&client).is_empty());
+        server.tick();
+        assert_eq!(titles(&client), ["1".into(), "2".into()]);
+        server.tick();
+        assert_eq!(titles(&client), ["3".into()]);
+
+        server
+            .server_mut()
+            .clients
+            .get_mut(client.id())
+            .unwrap()
+            .clear_scheduled_packets();
+        for _ in 0..3 {
+            server.tick();
+        }
+        assert!(titles(&client).is_empty());
+    }
+
+    #[test]
+    fn owned_player_entity_synced() {
+        let mut server = test_server();
+
+        let client = server.connect("tester");
+        server
+            .server_mut()
+            .clients
+            .get_mut(client.id())
+            .unwrap()
+            .spawn_player_entity(());
+        server.tick();
+
+        let s = server.server();
+        let entity_id = s.clients.get(client.id()).unwrap().player_entity().unwrap();
+        let entity = s.entities.get(entity_id).unwrap();
+        assert_eq!(entity.uuid(), s.clients.get(client.id()).unwrap().uuid());
+        assert_eq!(entity.position(), Vec3::new(0.5, 100.0, 0.5));
+
+        for pkt in client.take_packets() {
+            if let S2cPlayPacket::PlayerPositionLook(p) = pkt {
+                assert!(client.send(TeleportConfirm {
+                    teleport_id: p.teleport_id,
+                }));
+            }
+        }
+        assert!(client.send(MovePlayerPositionAndRotation {
+            position: Vec3::new(2.0, 99.0, 3.0),
+            yaw: 90.0,
+            pitch: 10.0,
+            on_ground: true,
+        }));
+        assert!(client.send(PlayerCommand {
+            entity_id: VarInt(0),
+            action_id: PlayerCommandId::StartSneaking,
+            jump_boost: VarInt(0).into(),
+        }));
+        server.tick();
+
+        let entity = server.server().entities.get(entity_id).unwrap();
+        assert_eq!(entity.position(), Vec3::new(2.0, 99.0, 3.0));
+        assert_eq!((entity.yaw(), entity.pitch()), (90.0, 10.0));
+        assert!(entity.on_ground());
+        let TrackedData::Player(player) = entity.data() else {
+            panic!("not a player");
+        };
+        assert_eq!(player.get_pose(), Pose::Sneaking);
+
+        drop(client);
+        server.tick();
+        assert!(server.server().entities.get(entity_id).is_none());
+    }
+
+    #[test]
+    fn arm_swings_forwarded_to_viewers() {
+        let mut server = test_server();
+
+        let swinger = server.connect("swinger");
+        let viewer = server.connect("viewer");
+        let c = server.server_mut().clients.get_mut(swinger.id()).unwrap();
+        c.spawn_player_entity(());
+        c.set_forward_arm_swings(true);
+        server.tick();
+        server.tick();
+        viewer.take_packets();
+
+        let entity_id = server
+            .server()
+            .clients
+            .get(swinger.id())
+            .unwrap()
+            .player_entity()
+            .unwrap();
+
+        assert!(swinger.send(HandSwing { hand: Hand::Off }));
+        server.tick();
+
+        let animations: Vec<_> = viewer
+            .take_packets()
+            .into_iter()
+            .filter_map(|pkt| match pkt {
+                S2cPlayPacket::EntityAnimation(p) => Some((p.entity_id.0, p.animation)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(animations, [(entity_id.to_network_id(), 3)]);
+    }
+
+    #[test]
+    fn disconnect_reasons_recorded() {
+        let mut server = test_server();
+
+        let kicked = server.connect("kicked");
+        let leaving = server.connect("leaving");
+        let cheater = server.connect("cheater");
+        server.tick();
+
+        let reason = |server: &TestServer<Game>, client: &MockClient| {
+            server
+                .server()
+                .clients
+                .get(client.id())
+                .unwrap()
+                .disconnect_reason()
+                .cloned()
+        };
+        assert_eq!(reason(&server, &kicked), None);
+
+        server
+            .server_mut()
+            .clients
+            .get_mut(kicked.id())
+            .unwrap()
+            .disconnect("bye");
+        assert!(cheater.send(TeleportConfirm {
+            teleport_id: VarInt(1000),
+        }));
+        let leaving_id = leaving.id();
+        drop(leaving);
+        server.tick();
+
+        assert_eq!(
+            reason(&server, &kicked),
+            Some(DisconnectReason::Kicked(Some("bye".into())))
+        );
+        assert_eq!(
+            reason(&server, &cheater),
+            Some(DisconnectReason::ProtocolError)
+        );
+        assert_eq!(
+            server
+                .server()
+                .clients
+                .get(leaving_id)
+                .unwrap()
+                .disconnect_reason(),
+            Some(&DisconnectReason::ConnectionClosed)
+        );
+    }
+
+    #[test]
+    fn vanished_client_hidden() {
+        let mut server = test_server();
+
+        let vanisher = server.connect("vanisher");
+        let viewer = server.connect("viewer");
+
+        let s = server.server_mut();
+        let (list_id, list) = s.player_lists.insert(());
+        for (_, c) in s.clients.iter_mut() {
+            list.insert(c.uuid(), c.username(), None, c.game_mode(), 0, None);
+            c.set_player_list(list_id.clone());
+        }
+        let c = s.clients.get_mut(vanisher.id()).unwrap();
+        let uuid = c.uuid();
+        c.spawn_player_entity(());
+        server.tick();
+        server.tick();
+        viewer.take_packets();
+
+        let set_vanished = |server: &mut TestServer<Game>, vanished| {
+            server
+                .server_mut()
+                .clients
+                .get_mut(vanisher.id())
+                .unwrap()
+                .set_vanished(vanished);
+            server.tick();
+            viewer.take_packets()
+        };
+
+        let pkts = set_vanished(&mut server, true);
+        assert!(pkts
+            .iter()
+            .any(|pkt| matches!(pkt, S2cPlayPacket::EntitiesDestroy(_))));
+        assert!(pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::UpdatePlayerList(UpdatePlayerList::RemovePlayer(uuids)) if uuids == &[uuid]
+        )));
+        assert!(server
+            .server()
+            .player_lists
+            .get(&list_id)
+            .get(uuid)
+            .unwrap()
+            .is_listed());
+
+        let pkts = set_vanished(&mut server, false);
+        assert!(pkts
+            .iter()
+            .any(|pkt| matches!(pkt, S2cPlayPacket::PlayerSpawn(p) if p.player_uuid == uuid)));
+        assert!(pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::UpdatePlayerList(UpdatePlayerList::AddPlayer(entries))
+                if entries.iter().any(|e| e.uuid == uuid)
+        )));
+    }
+
+    #[test]
+    fn unlisted_entry_shown_by_other_list() {
+        let (mut server, client) = connected_client();
+
+        let uuid = Uuid::from_u128(7);
+        let s = server.server_mut();
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let (id, list) = s.player_lists.insert(());
+            list.insert(uuid, "steve", None, GameMode::Survival, 0, None);
+            ids.push(id);
+        }
+        let c = s.clients.get_mut(client.id()).unwrap();
+        c.set_player_lists(ids.clone());
+        server.tick();
+        client.take_packets();
+
+        let removed = |server: &mut TestServer<Game>, id: &PlayerListId| {
+            let list = server.server_mut().player_lists.get_mut(id);
+            list.get_mut(uuid).unwrap().set_listed(false);
+            server.tick();
+            client.take_packets().iter().any(|pkt| {
+                matches!(
+                    pkt,
+                    S2cPlayPacket::UpdatePlayerList(UpdatePlayerList::RemovePlayer(uuids))
+                        if uuids == &[uuid]
+                )
+            })
+        };
+
+        // The entry is still listed by the second list.
+        assert!(!removed(&mut server, &ids[0]));
+        assert!(removed(&mut server, &ids[1]));
+    }
+
+    #[test]
+    fn chat_settings_filter_messages() {
+        let (mut server, client) = connected_client();
+
+        let settings = |chat_mode, chat_colors| ClientSettings {
+            locale: BoundedString("en_us".into()),
+            view_distance: BoundedInt(8),
+            chat_mode,
+            chat_colors,
+            displayed_skin_parts: DisplayedSkinParts::new(true, true, true, true, true, true, true),
+            main_hand: MainHand::Right,
+            enable_text_filtering: false,
+            allow_server_listings: true,
+        };
+        let messages = |client: &MockClient| -> Vec<_> {
+            client
+                .take_packets()
+                .into_iter()
+                .filter_map(|pkt| match pkt {
+                    S2cPlayPacket::GameMessage(p) => Some(p.chat),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        client.send(settings(ChatMode::Hidden, true));
+        server.tick();
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        c.send_message("hidden");
+        c.send_critical_message("critical");
+        server.tick();
+        assert_eq!(messages(&client), ["critical".into_text()]);
+
+        client.send(settings(ChatMode::CommandsOnly, false));
+        server.tick();
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        c.send_message("plain".color(Color::RED) + "text".color(Color::BLUE));
+        c.send_critical_message("red".color(Color::RED));
+        server.tick();
+        assert_eq!(
+            messages(&client),
+            ["plain".into_text() + "text", "red".color(Color::RED)]
+        );
+    }
+
+    #[test]
+    fn broadcast_messages() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        let carol = server.connect("carol");
+        server.tick();
+
+        let s = server.server_mut();
+        let (lobby, _) = s.worlds.iter().next().unwrap();
+        let (arena, _) = s.worlds.insert(DimensionId::default(), ());
+        for client in [&bob, &carol] {
+            let c = s.clients.get_mut(client.id()).unwrap();
+            c.respawn(arena, [0.5, 100.0, 0.5], 0.0, 0.0);
+        }
+        server.tick();
+        for client in [&alice, &bob, &carol] {
+            client.take_packets();
+        }
+
+        let s = server.server_mut();
+        assert_eq!(s.clients.find_by_username("BOB"), Some(bob.id()));
+        assert_eq!(s.clients.find_by_username("dave"), None);
+
+        s.clients.broadcast("everyone");
+        s.clients.broadcast_to_world(lobby, "lobby");
+        s.clients.broadcast_to_world(arena, "arena");
+        s.clients
+            .broadcast_filter("not bob", |id, _| id != bob.id());
+        server.tick();
+
+        assert_eq!(messages(&alice), ["everyone", "lobby", "not bob"]);
+        assert_eq!(messages(&bob), ["everyone", "arena"]);
+        assert_eq!(messages(&carol), ["everyone", "arena", "not bob"]);
+    }
+
+    #[test]
+    fn look_at_and_face_towards() {
+        let (mut server, client) = connected_client();
+
+        let s = server.server_mut();
+        s.clients
+            .get_mut(client.id())
+            .unwrap()
+            .look_at([5.0, 100.0, 0.5]);
+        assert!(matches!(
+            client.take_packets().as_slice(),
+            [S2cPlayPacket::LookAt(p)] if p.target == Vec3::new(5.0, 100.0, 0.5)
+        ));
+
+        let (_, entity) = s.entities.insert(EntityKind::Zombie, ());
+        entity.set_position([0.0, 0.0, 0.0]);
+
+        // Straight along +x from the eyes of the zombie.
+        entity.face_towards([10.0, 1.95 * 0.85, 0.0]);
+        assert!((entity.yaw() - -90.0).abs() < 1e-4);
+        assert!(entity.pitch().abs() < 1e-4);
+        assert_eq!(entity.head_yaw(), entity.yaw());
+
+        entity.face_towards([0.0, 100.0, 0.0]);
+        assert!((entity.pitch() - -90.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn dug_blocks_restored() {
+        let mut server = test_server();
+
+        let client = server.connect("tester");
+        let pos = BlockPos::new(1, 64, 1);
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state(pos, BlockState::STONE);
+        server.tick();
+        client.take_packets();
+
+        let dig = |sequence| PlayerAction {
+            status: DiggingStatus::FinishedDigging,
+            location: pos,
+            face: BlockFace::Top,
+            sequence: VarInt(sequence),
+        };
+
+        let block_updates = |client: &MockClient| -> Vec<_> {
+            client
+                .take_packets()
+                .into_iter()
+                .filter_map(|pkt| match pkt {
+                    S2cPlayPacket::BlockUpdate(p) => Some((p.location, p.block_id.0)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // By default, only the acknowledgement undoes the break on the client.
+        assert!(client.send(dig(1)));
+        server.tick();
+        assert_eq!(block_updates(&client), []);
+
+        server
+            .server_mut()
+            .clients
+            .get_mut(client.id())
+            .unwrap()
+            .set_restore_dug_blocks(true);
+
+        // The break is not handled, so the block is restored.
+        assert!(client.send(dig(0)));
+        server.tick();
+        assert_eq!(
+            block_updates(&client),
+            [(pos, BlockState::STONE.to_raw() as i32)]
+        );
+
+        // The break is confirmed by changing the block in the same tick.
+        assert!(client.send(dig(0)));
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state(pos, BlockState::AIR);
+        server.tick();
+        assert_eq!(
+            block_updates(&client),
+            [(pos, BlockState::AIR.to_raw() as i32)]
+        );
+    }
+}
@@ -7,6 +7,7 @@ use crate::block_pos::BlockPos;
 use crate::config::Config;
 use crate::entity::types::Pose;
 use crate::entity::{Entity, EntityEvent, EntityId, TrackedData};
+use crate::protection::ProtectedAction;
 pub use crate::protocol::packets::c2s::play::{
     BlockFace, ChatMode, DisplayedSkinParts, Hand, MainHand,
 };
@@ -26,20 +27,42 @@ pub enum ClientEvent {
         /// The time the message was sent.
         timestamp: Duration,
     },
-    /// Settings were changed. This is always sent once after joining by the
-    /// vanilla client.
-    SettingsChanged {
+    /// The client's locale changed.
+    ///
+    /// This and the other settings events are all sent when the client sends
+    /// its settings for the first time, which the vanilla client does once
+    /// after joining. Afterwards, only the events for settings that changed
+    /// are sent. The complete settings are available from
+    /// [`Client::settings`].
+    LocaleChanged {
         /// e.g. en_US
         locale: String,
+    },
+    /// The client side render distance changed.
+    ViewDistanceChanged {
         /// The client side render distance, in chunks.
         ///
         /// The value is always in `2..=32`.
         view_distance: u8,
+    },
+    /// The chat mode or chat colors setting changed.
+    ChatSettingsChanged {
         chat_mode: ChatMode,
         /// `true` if the client has chat colors enabled, `false` otherwise.
         chat_colors: bool,
+    },
+    /// The client's main hand changed. This is applied to the client's player
+    /// entity automatically.
+    MainHandChanged {
         main_hand: MainHand,
+    },
+    /// The displayed skin parts changed. These are applied to the client's
+    /// player entity automatically.
+    SkinPartsChanged {
         displayed_skin_parts: DisplayedSkinParts,
+    },
+    /// The setting allowing the client to be shown in server listings changed.
+    AllowServerListingsChanged {
         allow_server_listings: bool,
     },
     MovePosition {
@@ -82,6 +105,16 @@ pub enum ClientEvent {
     OpenHorseInventory,
     StartFlyingWithElytra,
     ArmSwing(Hand),
+    /// The client asked to be respawned, either from the death screen or after
+    /// closing the credits shown with [`Client::show_credits`].
+    ///
+    /// The client is not respawned automatically. Use [`Client::respawn`] or
+    /// [`Client::change_dimension`] to do so. [Hardcore] clients send this
+    /// when choosing to spectate the world, so they are usually respawned in
+    /// [`GameMode::Spectator`].
+    ///
+    /// [Hardcore]: Client::is_hardcore
+    RespawnRequest,
     /// Left or right click interaction with an entity's hitbox.
     InteractWithEntity {
         /// The ID of the entity being interacted with.
@@ -103,6 +136,8 @@ pub enum ClientEvent {
         /// The face of the block being broken.
         face: BlockFace,
     },
+    /// The client used the item in its hand on a block. If the client has a
+    /// [held block](Client::held_block), the block has already been placed.
     InteractWithBlock {
         /// The hand that was used
         hand: Hand,
@@ -117,6 +152,15 @@ pub enum ClientEvent {
         /// Sequence number
         sequence: VarInt,
     },
+    /// An action of the client was denied by a protected region of its world,
+    /// and the event for the action was not created. See the
+    /// [`protection`](crate::protection) module.
+    ProtectionDenied {
+        /// The action which was denied.
+        action: ProtectedAction,
+        /// The name of the region denying the action.
+        region: String,
+    },
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -162,36 +206,14 @@ pub fn default_client_event<C: Config>(
 
     match &event {
         ClientEvent::ChatMessage { .. } => {}
-        ClientEvent::SettingsChanged {
-            view_distance,
-            main_hand,
-            displayed_skin_parts,
-            ..
-        } => {
+        ClientEvent::LocaleChanged { .. } => {}
+        ClientEvent::ViewDistanceChanged { view_distance } => {
             client.set_view_distance(*view_distance);
-
-            let player = client.player_mut();
-
-            player.set_cape(displayed_skin_parts.cape());
-            player.set_jacket(displayed_skin_parts.jacket());
-            player.set_left_sleeve(displayed_skin_parts.left_sleeve());
-            player.set_right_sleeve(displayed_skin_parts.right_sleeve());
-            player.set_left_pants_leg(displayed_skin_parts.left_pants_leg());
-            player.set_right_pants_leg(displayed_skin_parts.right_pants_leg());
-            player.set_hat(displayed_skin_parts.hat());
-            player.set_main_arm(*main_hand as u8);
-
-            if let TrackedData::Player(player) = entity.data_mut() {
-                player.set_cape(displayed_skin_parts.cape());
-                player.set_jacket(displayed_skin_parts.jacket());
-                player.set_left_sleeve(displayed_skin_parts.left_sleeve());
-                player.set_right_sleeve(displayed_skin_parts.right_sleeve());
-                player.set_left_pants_leg(displayed_skin_parts.left_pants_leg());
-                player.set_right_pants_leg(displayed_skin_parts.right_pants_leg());
-                player.set_hat(displayed_skin_parts.hat());
-                player.set_main_arm(*main_hand as u8);
-            }
         }
+        ClientEvent::ChatSettingsChanged { .. } => {}
+        ClientEvent::MainHandChanged { .. } => {}
+        ClientEvent::SkinPartsChanged { .. } => {}
+        ClientEvent::AllowServerListingsChanged { .. } => {}
         ClientEvent::MovePosition {
             position,
             on_ground,
@@ -260,10 +282,12 @@ pub fn default_client_event<C: Config>(
                 Hand::Off => EntityEvent::SwingOffHand,
             });
         }
+        ClientEvent::RespawnRequest => {}
         ClientEvent::InteractWithEntity { .. } => {}
         ClientEvent::SteerBoat { .. } => {}
         ClientEvent::Digging { .. } => {}
         ClientEvent::InteractWithBlock { .. } => {}
+        ClientEvent::ProtectionDenied { .. } => {}
     }
 
     entity.set_world(client.world());
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::entity::EntityId;
+use crate::protocol::packets::s2c::play::{
+    EntityTrackerUpdate, S2cPlayPacket, Team, TeamCollisionRule, TeamColor, TeamCreate, TeamFlags,
+    TeamInfo, TeamMode, TeamVisibility,
+};
+use crate::protocol::{BoundedString, RawBytes, VarInt};
+use crate::text::Text;
+
+/// The bit of the entity flags which makes an entity glow.
+const GLOWING_BIT: u8 = 0x40;
+
+/// The entities glowing for a single client and the state of the glow teams
+/// on that client.
+///
+/// The color of an entity's outline is the color of its team, so each color
+/// has its own team which is created on the client the first time it is
+/// needed.
+#[derive(Clone, Default, Debug)]
+pub(super) struct ViewerGlow {
+    colors: HashMap<EntityId, TeamColor>,
+    /// The colors of the entities the client has been told are glowing.
+    sent: HashMap<EntityId, TeamColor>,
+    /// Bitset of the teams created on the client, indexed by color.
+    created_teams: u32,
+}
+
+impl ViewerGlow {
+    pub(super) fn get(&self, entity: EntityId) -> Option<TeamColor> {
+        self.colors.get(&entity).copied()
+    }
+
+    pub(super) fn set(&mut self, entity: EntityId, color: Option<TeamColor>) {
+        match color {
+            Some(color) => self.colors.insert(entity, color),
+            None => self.colors.remove(&entity),
+        };
+    }
+
+    /// Forgets the glow of entities for which `exists` returns `false`.
+    pub(super) fn retain_existing(&mut self, mut exists: impl FnMut(EntityId) -> bool) {
+        self.colors.retain(|&id, _| exists(id));
+        self.sent.retain(|&id, _| exists(id));
+    }
+
+    /// Creates the packets needed to bring the glow of a loaded entity up to
+    /// date for the client.
+    ///
+    /// `flags` are the entity's own flags, and `flags_sent` is whether they
+    /// were sent to the client this tick, overwriting the glow. `team_entry`
+    /// is the name of the entity in teams.
+    pub(super) fn update_entity(
+        &mut self,
+        entity: EntityId,
+        flags: u8,
+        flags_sent: bool,
+        team_entry: impl FnOnce() -> String,
+        mut push: impl FnMut(S2cPlayPacket),
+    ) {
+        let color = self.get(entity);
+        let sent = self.sent.get(&entity).copied();
+
+        if color != sent {
+            let entry = BoundedString(team_entry());
+
+            match color {
+                Some(color) => {
+                    let bit = 1 << color as u32;
+                    let mode = if self.created_teams & bit == 0 {
+                        self.created_teams |= bit;
+                        TeamMode::Create(Box::new(TeamCreate {
+                            info: team_info(color),
+                            entities: vec![entry],
+                        }))
+                    } else {
+                        TeamMode::AddEntities(vec![entry])
+                    };
+
+                    push(
+                        Team {
+                            team_name: team_name(color),
+                            mode,
+                        }
+                        .into(),
+                    );
+                    self.sent.insert(entity, color);
+                }
+                None => {
+                    if let Some(sent) = sent {
+                        push(
+                            Team {
+                                team_name: team_name(sent),
+                                mode: TeamMode::RemoveEntities(vec![entry]),
+                            }
+                            .into(),
+                        );
+                    }
+                    self.sent.remove(&entity);
+                }
+            }
+        }
+
+        if color.is_some() && (flags_sent || sent.is_none()) || color.is_none() && sent.is_some() {
+            let flags = if color.is_some() {
+                flags | GLOWING_BIT
+            } else {
+                flags
+            };
+
+            // The flags are tracked data at index 0 with the byte type.
+            push(
+                EntityTrackerUpdate {
+                    entity_id: VarInt(entity.to_network_id()),
+                    metadata: RawBytes(vec![0, 0, flags, 0xff]),
+                }
+                .into(),
+            );
+        }
+    }
+}
+
+fn team_name(color: TeamColor) -> BoundedString<0, 16> {
+    BoundedString(format!("valence_glow_{}", color as u32))
+}
+
+fn team_info(color: TeamColor) -> TeamInfo {
+    TeamInfo {
+        display_name: Text::default(),
+        flags: TeamFlags::new(false, false),
+        name_tag_visibility: TeamVisibility::Always,
+        collision_rule: TeamCollisionRule::Always,
+        color,
+        prefix: Text::default(),
+        suffix: Text::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::TeamColor;
+    use crate::entity::{EntityKind, TrackedData};
+    use crate::testing::{test_server, MockClient};
+
+    #[test]
+    fn per_client_glow() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let (pig_id, pig) = s.entities.insert(EntityKind::Pig, ());
+        pig.set_world(world_id);
+        pig.set_position([0.5, 100.0, 4.5]);
+        let pig_uuid = pig.uuid().hyphenated().to_string();
+        server.tick();
+        alice.take_packets();
+        bob.take_packets();
+
+        let glow_packets = |client: &MockClient| {
+            let mut flags = Vec::new();
+            let mut teams = Vec::new();
+            for pkt in client.take_packets() {
+                match pkt {
+                    S2cPlayPacket::EntityTrackerUpdate(p) => flags.push(p.metadata.0),
+                    S2cPlayPacket::Team(p) => teams.push(p),
+                    _ => {}
+                }
+            }
+            (flags, teams)
+        };
+
+        server
+            .server_mut()
+            .clients
+            .get_mut(alice.id())
+            .unwrap()
+            .set_glowing(pig_id, Some(TeamColor::Red));
+        server.tick();
+
+        let (flags, teams) = glow_packets(&alice);
+        assert_eq!(flags, [vec![0, 0, 0x40, 0xff]]);
+        assert!(matches!(
+            teams.as_slice(),
+            [Team { mode: TeamMode::Create(c), .. }]
+                if c.info.color == TeamColor::Red && c.entities[0].0 == pig_uuid
+        ));
+        let (flags, teams) = glow_packets(&bob);
+        assert!(flags.is_empty() && teams.is_empty());
+
+        // Changes to the flags keep the glow.
+        let pig = server.server_mut().entities.get_mut(pig_id).unwrap();
+        if let TrackedData::Pig(pig) = pig.data_mut() {
+            pig.set_on_fire(true);
+        }
+        server.tick();
+        assert_eq!(glow_packets(&alice).0.last().unwrap(), &[0, 0, 0x41, 0xff]);
+        assert_eq!(glow_packets(&bob).0, [vec![0, 0, 0x01, 0xff]]);
+
+        server
+            .server_mut()
+            .clients
+            .get_mut(alice.id())
+            .unwrap()
+            .set_glowing(pig_id, None);
+        server.tick();
+
+        let (flags, teams) = glow_packets(&alice);
+        assert_eq!(flags, [vec![0, 0, 0x01, 0xff]]);
+        assert!(matches!(
+            teams.as_slice(),
+            [Team {
+                mode: TeamMode::RemoveEntities(_),
+                ..
+            }]
+        ));
+    }
+}
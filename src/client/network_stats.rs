@@ -0,0 +1,227 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::text::Text;
+use crate::Ticks;
+
+/// The traffic of a client's connection, obtained with
+/// [`Client::network_stats`].
+///
+/// Totals are counted from when the client joined. Rates are measured over
+/// the last full second of game ticks, so they are zero during the client's
+/// first second.
+///
+/// Bytes are counted as sent over the connection, after compression. Clients
+/// added with [`TestServer::connect`] only count packets, and packets sent to
+/// them are counted once taken with [`MockClient::take_packets`].
+///
+/// [`Client::network_stats`]: crate::client::Client::network_stats
+/// [`TestServer::connect`]: crate::testing::TestServer::connect
+/// [`MockClient::take_packets`]: crate::testing::MockClient::take_packets
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct NetworkStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent_per_second: u64,
+    pub bytes_received_per_second: u64,
+    pub packets_sent_per_second: u64,
+    pub packets_received_per_second: u64,
+}
+
+/// Disconnects clients which send more than `max_packets_per_second` packets
+/// or `max_bytes_per_second` bytes in a second, configured with
+/// [`PacketLimits::flood`].
+///
+/// [`PacketLimits::flood`]: crate::client::PacketLimits::flood
+#[derive(Clone, PartialEq, Debug)]
+pub struct FloodLimit {
+    pub max_packets_per_second: u64,
+    pub max_bytes_per_second: u64,
+    /// The message shown to disconnected clients.
+    pub reason: Text,
+}
+
+impl FloodLimit {
+    /// Returns `true` if the rates in `stats` exceed this limit.
+    pub fn is_exceeded_by(&self, stats: &NetworkStats) -> bool {
+        stats.packets_received_per_second > self.max_packets_per_second
+            || stats.bytes_received_per_second > self.max_bytes_per_second
+    }
+}
+
+/// The totals of a connection, shared between the client and the tasks
+/// reading and writing its packets.
+#[derive(Default, Debug)]
+pub(crate) struct NetworkCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    packets_sent: AtomicU64,
+    packets_received: AtomicU64,
+}
+
+impl NetworkCounters {
+    pub(crate) fn record_sent(&self, bytes: usize) {
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_received(&self, bytes: usize) {
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn totals(&self) -> [u64; 4] {
+        [
+            self.bytes_sent.load(Ordering::Relaxed),
+            self.bytes_received.load(Ordering::Relaxed),
+            self.packets_sent.load(Ordering::Relaxed),
+            self.packets_received.load(Ordering::Relaxed),
+        ]
+    }
+}
+
+/// Turns the totals of a connection into [`NetworkStats`].
+#[derive(Clone, Copy, Default, Debug)]
+pub(super) struct RateMeter {
+    /// The tick the current window started at, or `None` before the first
+    /// update.
+    window_start: Option<Ticks>,
+    /// The totals at the start of the current window.
+    start_totals: [u64; 4],
+    stats: NetworkStats,
+}
+
+impl RateMeter {
+    /// Updates the totals, and the rates once a second has passed. Returns
+    /// `true` if the rates were updated.
+    pub(super) fn update(
+        &mut self,
+        counters: &NetworkCounters,
+        current_tick: Ticks,
+        tick_rate: Ticks,
+    ) -> bool {
+        let totals = counters.totals();
+        let [bytes_sent, bytes_received, packets_sent, packets_received] = totals;
+
+        self.stats.bytes_sent = bytes_sent;
+        self.stats.bytes_received = bytes_received;
+        self.stats.packets_sent = packets_sent;
+        self.stats.packets_received = packets_received;
+
+        match self.window_start {
+            Some(start) if current_tick - start >= tick_rate => {}
+            Some(_) => return false,
+            None => {
+                self.window_start = Some(current_tick);
+                self.start_totals = totals;
+                return false;
+            }
+        }
+
+        let [bytes_sent, bytes_received, packets_sent, packets_received] =
+            [0, 1, 2, 3].map(|i| totals[i] - self.start_totals[i]);
+
+        self.stats.bytes_sent_per_second = bytes_sent;
+        self.stats.bytes_received_per_second = bytes_received;
+        self.stats.packets_sent_per_second = packets_sent;
+        self.stats.packets_received_per_second = packets_received;
+
+        self.window_start = Some(current_tick);
+        self.start_totals = totals;
+        true
+    }
+
+    pub(super) fn stats(&self) -> &NetworkStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::PacketLimits;
+    use crate::protocol::packets::c2s::play::{Hand, HandSwing};
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::testing::{Game, TestServer};
+
+    #[test]
+    fn rate_meter_windows() {
+        let counters = NetworkCounters::default();
+        let mut meter = RateMeter::default();
+
+        assert!(!meter.update(&counters, 0, 20));
+        counters.record_received(10);
+        counters.record_sent(100);
+        assert!(!meter.update(&counters, 5, 20));
+        assert_eq!(meter.stats().packets_received, 1);
+        assert_eq!(meter.stats().bytes_received_per_second, 0);
+
+        counters.record_received(10);
+        assert!(meter.update(&counters, 20, 20));
+        assert_eq!(meter.stats().bytes_received_per_second, 20);
+        assert_eq!(meter.stats().packets_received_per_second, 2);
+        assert_eq!(meter.stats().bytes_sent_per_second, 100);
+
+        counters.record_received(5);
+        assert!(!meter.update(&counters, 39, 20));
+        assert!(meter.update(&counters, 40, 20));
+        assert_eq!(meter.stats().bytes_received, 25);
+        assert_eq!(meter.stats().bytes_received_per_second, 5);
+        assert_eq!(meter.stats().packets_sent_per_second, 0);
+    }
+
+    #[test]
+    fn flood_limit_kicks() {
+        let game = Game {
+            packet_limits: PacketLimits {
+                flood: Some(FloodLimit {
+                    max_packets_per_second: 100,
+                    max_bytes_per_second: 1 << 20,
+                    reason: "flood".into(),
+                }),
+                ..PacketLimits::default()
+            },
+            ..Game::default()
+        };
+        let mut server = TestServer::new(game, ()).unwrap();
+
+        let client = server.connect("tester");
+        server.tick();
+        let welcome = client.take_packets().len() as u64;
+
+        for _ in 0..20 {
+            assert!(client.send(HandSwing { hand: Hand::Main }));
+            server.tick();
+        }
+
+        let stats = *server
+            .server()
+            .clients
+            .get(client.id())
+            .unwrap()
+            .network_stats();
+        assert_eq!(stats.packets_received, 20);
+        assert_eq!(stats.packets_received_per_second, 20);
+        assert_eq!(stats.packets_sent, welcome);
+        assert_eq!(stats.bytes_received, 0);
+        assert!(!client.is_disconnected());
+
+        for _ in 0..3 {
+            for _ in 0..40 {
+                assert!(client.send(HandSwing { hand: Hand::Main }));
+            }
+            server.tick();
+        }
+        for _ in 0..20 {
+            server.tick();
+        }
+
+        let pkts = client.take_packets();
+        assert!(pkts.iter().any(
+            |pkt| matches!(pkt, S2cPlayPacket::Disconnect(p) if p.reason.to_plain() == "flood")
+        ));
+        assert!(client.is_disconnected());
+    }
+}
@@ -0,0 +1,197 @@
+use super::FloodLimit;
+use crate::protocol::packets::c2s::play::C2sPlayPacket;
+use crate::protocol::DecodeLimits;
+use crate::text::Text;
+use crate::{Ticks, STANDARD_TPS};
+
+/// Limits on the packets a client may send, configured with
+/// [`Config::packet_limits`].
+///
+/// Rate limits are counted per client in game ticks. A limit of `None` lets a
+/// client send any number of packets of that kind.
+///
+/// [`Config::packet_limits`]: crate::config::Config::packet_limits
+#[derive(Clone, PartialEq, Debug)]
+pub struct PacketLimits {
+    /// The maximum sizes accepted while decoding packets from clients.
+    pub decode: DecodeLimits,
+    /// The rate limit for chat messages, commands and command suggestion
+    /// requests.
+    pub chat: Option<RateLimit>,
+    /// The rate limit for interacting with blocks, items and entities,
+    /// digging, and swinging arms.
+    pub interaction: Option<RateLimit>,
+    /// The rate limit for player and vehicle movement.
+    pub movement: Option<RateLimit>,
+    /// The limit on all packets and bytes received from a client in a
+    /// second, checked once per second. Unlike the other limits, packets are
+    /// not dropped before a client exceeding it is disconnected.
+    pub flood: Option<FloodLimit>,
+}
+
+impl Default for PacketLimits {
+    fn default() -> Self {
+        Self {
+            decode: DecodeLimits::DEFAULT,
+            chat: Some(RateLimit {
+                max_packets: 20,
+                window: STANDARD_TPS * 5,
+                action: RateLimitAction::Disconnect(Text::translate("disconnect.spam")),
+            }),
+            interaction: Some(RateLimit {
+                max_packets: 200,
+                window: STANDARD_TPS,
+                action: RateLimitAction::Drop,
+            }),
+            movement: Some(RateLimit {
+                max_packets: 100,
+                window: STANDARD_TPS,
+                action: RateLimitAction::Drop,
+            }),
+            flood: Some(FloodLimit {
+                max_packets_per_second: 1000,
+                max_bytes_per_second: 1 << 20,
+                reason: Text::translate("disconnect.exceeded_packet_rate"),
+            }),
+        }
+    }
+}
+
+/// Allows up to `max_packets` packets of a kind within every window of
+/// `window` ticks.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RateLimit {
+    /// The number of packets allowed per window.
+    pub max_packets: u32,
+    /// The length of a window in ticks. Must be greater than zero.
+    pub window: Ticks,
+    /// What happens to a client exceeding the limit.
+    pub action: RateLimitAction,
+}
+
+/// What happens to a client exceeding a [`RateLimit`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum RateLimitAction {
+    /// Packets over the limit are ignored until the next window begins.
+    Drop,
+    /// The client is disconnected with the given reason.
+    Disconnect(Text),
+}
+
+/// The kinds of packets which have their own [`RateLimit`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum PacketKind {
+    Chat,
+    Interaction,
+    Movement,
+}
+
+impl PacketKind {
+    pub(super) fn of(pkt: &C2sPlayPacket) -> Option<Self> {
+        match pkt {
+            C2sPlayPacket::ChatMessage(_)
+            | C2sPlayPacket::CommandExecution(_)
+            | C2sPlayPacket::RequestCommandCompletion(_) => Some(Self::Chat),
+            C2sPlayPacket::PlayerInteractEntity(_)
+            | C2sPlayPacket::PlayerInteractBlock(_)
+            | C2sPlayPacket::PlayerInteractItem(_)
+            | C2sPlayPacket::PlayerAction(_)
+            | C2sPlayPacket::HandSwing(_) => Some(Self::Interaction),
+            C2sPlayPacket::MovePlayerPosition(_)
+            | C2sPlayPacket::MovePlayerPositionAndRotation(_)
+            | C2sPlayPacket::MovePlayerRotation(_)
+            | C2sPlayPacket::MovePlayerOnGround(_)
+            | C2sPlayPacket::MoveVehicle(_) => Some(Self::Movement),
+            _ => None,
+        }
+    }
+
+    pub(super) fn limit(self, limits: &PacketLimits) -> Option<&RateLimit> {
+        match self {
+            Self::Chat => limits.chat.as_ref(),
+            Self::Interaction => limits.interaction.as_ref(),
+            Self::Movement => limits.movement.as_ref(),
+        }
+    }
+}
+
+/// Counts the packets of one kind a client sent in the current window.
+#[derive(Clone, Copy, Default, Debug)]
+pub(super) struct RateCounter {
+    window_start: Ticks,
+    count: u32,
+}
+
+impl RateCounter {
+    /// Counts a packet received at `current_tick` and returns whether it is
+    /// within the limit.
+    pub(super) fn allow(&mut self, limit: &RateLimit, current_tick: Ticks) -> bool {
+        if current_tick - self.window_start >= limit.window {
+            self.window_start = current_tick;
+            self.count = 0;
+        }
+
+        self.count = self.count.saturating_add(1);
+        self.count <= limit.max_packets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::testing::{chat_message, Game, TestServer};
+
+    #[test]
+    fn rate_counter_windows() {
+        let limit = RateLimit {
+            max_packets: 2,
+            window: 10,
+            action: RateLimitAction::Drop,
+        };
+
+        let mut counter = RateCounter::default();
+
+        assert!(counter.allow(&limit, 0));
+        assert!(counter.allow(&limit, 3));
+        assert!(!counter.allow(&limit, 9));
+        assert!(counter.allow(&limit, 10));
+        assert!(counter.allow(&limit, 10));
+        assert!(!counter.allow(&limit, 19));
+        assert!(counter.allow(&limit, 25));
+    }
+
+    #[test]
+    fn chat_rate_limit() {
+        let game = Game {
+            packet_limits: PacketLimits {
+                chat: Some(RateLimit {
+                    max_packets: 2,
+                    window: 20,
+                    action: RateLimitAction::Disconnect("spam".into()),
+                }),
+                ..PacketLimits::default()
+            },
+            ..Game::default()
+        };
+        let mut server = TestServer::new(game, ()).unwrap();
+
+        let client = server.connect("tester");
+        server.tick();
+        client.take_packets();
+
+        for msg in ["a", "b", "c"] {
+            assert!(client.send(chat_message(msg)));
+        }
+        server.tick();
+
+        // The client is disconnected before the messages are echoed.
+        let pkts = client.take_packets();
+        assert!(matches!(
+            pkts.as_slice(),
+            [S2cPlayPacket::Disconnect(p)] if p.reason.to_plain() == "spam"
+        ));
+        assert!(client.is_disconnected());
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+
+use crate::protocol::packets::s2c::play::{
+    ClearTitles, OverlayMessage, S2cPlayPacket, TitleFade, UpdateSubtitle, UpdateTitle,
+};
+use crate::text::Text;
+
+/// The vanilla client hides the action bar a few seconds after it was last
+/// sent, so long messages are resent at this interval.
+const ACTION_BAR_RESEND_TICKS: u32 = 40;
+
+/// A queue of titles and action bar messages shown to a client one after
+/// another.
+///
+/// Titles and action bar messages are queued independently, so an action bar
+/// message can be shown while a title is displayed. The queue of a client is
+/// obtained with [`Client::titles_mut`] and advances by one tick every time
+/// the client is updated.
+///
+/// [`Client::titles_mut`]: crate::client::Client::titles_mut
+#[derive(Clone, Default, Debug)]
+pub struct Titles {
+    titles: VecDeque<QueuedTitle>,
+    /// Ticks left until the current title is finished.
+    title_ticks_left: u32,
+    action_bars: VecDeque<QueuedActionBar>,
+    /// Ticks the current action bar message has been shown for.
+    action_bar_ticks: u32,
+    clear: bool,
+}
+
+#[derive(Clone, Debug)]
+struct QueuedTitle {
+    title: Text,
+    subtitle: Text,
+    fade: TitleFade,
+}
+
+#[derive(Clone, Debug)]
+struct QueuedActionBar {
+    text: Text,
+    duration: u32,
+}
+
+impl Titles {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a title with a subtitle. The title is shown once the previous
+    /// title has faded out and occupies the screen for the sum of the times
+    /// in `fade`.
+    ///
+    /// An empty subtitle shows the title on its own.
+    pub fn push_title(
+        &mut self,
+        title: impl Into<Text>,
+        subtitle: impl Into<Text>,
+        fade: TitleFade,
+    ) -> &mut Self {
+        self.titles.push_back(QueuedTitle {
+            title: title.into(),
+            subtitle: subtitle.into(),
+            fade,
+        });
+        self
+    }
+
+    /// Queues an action bar message which is shown for at least `duration`
+    /// ticks once the previous action bar message has finished.
+    pub fn push_action_bar(&mut self, text: impl Into<Text>, duration: u32) -> &mut Self {
+        self.action_bars.push_back(QueuedActionBar {
+            text: text.into(),
+            duration: duration.max(1),
+        });
+        self
+    }
+
+    /// Queues a countdown from `seconds` down to 1, showing the title
+    /// returned by `title` for each remaining second.
+    ///
+    /// ```
+    /// # use valence::client::Titles;
+    /// let mut titles = Titles::new();
+    /// titles.push_countdown(3, |n| format!("Starting in {n}").into());
+    /// ```
+    pub fn push_countdown(
+        &mut self,
+        seconds: u32,
+        mut title: impl FnMut(u32) -> Text,
+    ) -> &mut Self {
+        for n in (1..=seconds).rev() {
+            self.push_title(
+                title(n),
+                "",
+                TitleFade {
+                    fade_in: 0,
+                    stay: 20,
+                    fade_out: 0,
+                },
+            );
+        }
+        self
+    }
+
+    /// Removes all queued messages and clears the title and action bar
+    /// currently on the client's screen.
+    pub fn clear(&mut self) {
+        self.titles.clear();
+        self.title_ticks_left = 0;
+        self.action_bars.clear();
+        self.action_bar_ticks = 0;
+        self.clear = true;
+    }
+
+    /// Returns `true` if nothing is queued or being shown.
+    pub fn is_empty(&self) -> bool {
+        self.titles.is_empty() && self.title_ticks_left == 0 && self.action_bars.is_empty()
+    }
+
+    /// Advances the queue by one tick, sending the packets needed to show the
+    /// next messages.
+    pub(crate) fn update(&mut self, mut send: impl FnMut(S2cPlayPacket)) {
+        if self.clear {
+            self.clear = false;
+            send(ClearTitles { reset: true }.into());
+            send(
+                OverlayMessage {
+                    text: Text::default(),
+                }
+                .into(),
+            );
+        }
+
+        self.title_ticks_left = self.title_ticks_left.saturating_sub(1);
+
+        if self.title_ticks_left == 0 {
+            if let Some(next) = self.titles.pop_front() {
+                let fade = next.fade;
+                self.title_ticks_left = (fade.fade_in + fade.stay + fade.fade_out).max(1);
+
+                send(fade.into());
+                send(
+                    UpdateSubtitle {
+                        subtitle_text: next.subtitle,
+                    }
+                    .into(),
+                );
+                send(UpdateTitle { text: next.title }.into());
+            }
+        }
+
+        if let Some(bar) = self.action_bars.front() {
+            if self
+                .action_bar_ticks
+                .is_multiple_of(ACTION_BAR_RESEND_TICKS)
+            {
+                send(
+                    OverlayMessage {
+                        text: bar.text.clone(),
+                    }
+                    .into(),
+                );
+            }
+
+            self.action_bar_ticks += 1;
+
+            if self.action_bar_ticks >= bar.duration {
+                self.action_bars.pop_front();
+                self.action_bar_ticks = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(titles: &mut Titles) -> Vec<S2cPlayPacket> {
+        let mut packets = Vec::new();
+        titles.update(|pkt| packets.push(pkt));
+        packets
+    }
+
+    #[test]
+    fn titles_are_sequenced() {
+        let mut titles = Titles::new();
+        titles.push_countdown(2, |n| n.to_string().into());
+
+        let first = tick(&mut titles);
+        assert!(matches!(first.last(), Some(S2cPlayPacket::UpdateTitle(_))));
+
+        for _ in 1..20 {
+            assert!(tick(&mut titles).is_empty());
+        }
+
+        assert_eq!(tick(&mut titles).len(), 3);
+
+        for _ in 0..20 {
+            tick(&mut titles);
+        }
+
+        assert!(titles.is_empty());
+    }
+
+    #[test]
+    fn action_bar_is_resent() {
+        let mut titles = Titles::new();
+        titles.push_action_bar("hello", 100);
+
+        let sent = (0..100)
+            .map(|_| tick(&mut titles).len())
+            .filter(|&n| n > 0)
+            .count();
+
+        assert_eq!(sent, 3);
+        assert!(titles.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Client, GameMode};
+use crate::config::Config;
+use crate::world::WorldId;
+
+/// A copy of what a client sees, which can be moved to another server
+/// instance to transfer the client without it noticing.
+///
+/// A snapshot is taken with [`Client::view_snapshot`] and turned into bytes
+/// with [`Self::to_bytes`]. A proxy moving the client to another instance
+/// passes the bytes along, and the other instance restores the client with
+/// [`Self::from_bytes`] and [`Client::restore_view`] once the client has
+/// joined it.
+///
+/// [`WorldId`]s are not the same between instances, so the world of the
+/// client is identified by a name chosen by the application. `T` is custom
+/// data carried along with the snapshot, such as parts of the client's
+/// [state](Client::state).
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ViewSnapshot<T = ()> {
+    /// The UUID of the client the snapshot was taken of.
+    pub uuid: Uuid,
+    /// The name of the world the client is in.
+    pub world: String,
+    pub position: [f64; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    /// The velocity in m/s.
+    pub velocity: [f32; 3],
+    pub game_mode: GameMode,
+    pub data: T,
+}
+
+impl<T: Serialize> ViewSnapshot<T> {
+    /// Encodes the snapshot as JSON, so that proxies can read it as well.
+    /// Fails if the custom data cannot be serialized.
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        serde_json::to_vec(self).context("failed to serialize view snapshot")
+    }
+}
+
+impl<T: DeserializeOwned> ViewSnapshot<T> {
+    /// Decodes a snapshot encoded with [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        serde_json::from_slice(bytes).context("failed to parse view snapshot")
+    }
+}
+
+impl<C: Config> Client<C> {
+    /// Takes a [`ViewSnapshot`] of this client, which is in the world named
+    /// `world`, carrying `data` along.
+    pub fn view_snapshot<T>(&self, world: impl Into<String>, data: T) -> ViewSnapshot<T> {
+        ViewSnapshot {
+            uuid: self.uuid,
+            world: world.into(),
+            position: self.position.into_array(),
+            yaw: self.yaw,
+            pitch: self.pitch,
+            velocity: self.velocity.into_array(),
+            game_mode: self.new_game_mode,
+            data,
+        }
+    }
+
+    /// Moves this client into `world` at the position of a [`ViewSnapshot`]
+    /// and restores its game mode and velocity. `world` should be the world
+    /// named by the snapshot. The custom data of the snapshot is left for the
+    /// caller to restore.
+    ///
+    /// Returns `false` without changing anything if the snapshot was taken of
+    /// a client with another UUID.
+    pub fn restore_view<T>(&mut self, snapshot: &ViewSnapshot<T>, world: WorldId) -> bool {
+        if snapshot.uuid != self.uuid {
+            return false;
+        }
+
+        if world != self.world {
+            self.spawn(world);
+        }
+        self.teleport(snapshot.position, snapshot.yaw, snapshot.pitch);
+        self.set_velocity(snapshot.velocity);
+        self.set_game_mode(snapshot.game_mode);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vek::Vec3;
+
+    use super::*;
+    use crate::client::GameMode;
+    use crate::testing::test_server;
+
+    #[test]
+    fn view_snapshot_transfers_client() {
+        let mut old = test_server();
+        let mut new = test_server();
+
+        let client = old.connect("tester");
+        old.tick();
+
+        let c = old.server_mut().clients.get_mut(client.id()).unwrap();
+        c.teleport([3.5, 80.0, -2.5], 90.0, 10.0);
+        c.set_game_mode(GameMode::Creative);
+        let bytes = c.view_snapshot("lobby", vec![1, 2, 3]).to_bytes().unwrap();
+
+        let snapshot = ViewSnapshot::<Vec<u8>>::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.world, "lobby");
+        assert_eq!(snapshot.data, [1, 2, 3]);
+        assert!(ViewSnapshot::<String>::from_bytes(&bytes).is_err());
+
+        let transferred = new.connect("tester");
+        let other = new.connect("other");
+        new.tick();
+
+        let s = new.server_mut();
+        let (world, _) = s.worlds.iter().next().unwrap();
+        assert!(!s
+            .clients
+            .get_mut(other.id())
+            .unwrap()
+            .restore_view(&snapshot, world));
+
+        let c = s.clients.get_mut(transferred.id()).unwrap();
+        assert!(c.restore_view(&snapshot, world));
+        assert_eq!(c.position(), Vec3::new(3.5, 80.0, -2.5));
+        assert_eq!((c.yaw(), c.pitch()), (90.0, 10.0));
+        assert_eq!(c.game_mode(), GameMode::Creative);
+        assert_eq!(c.world(), world);
+    }
+}
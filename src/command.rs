@@ -0,0 +1,1047 @@
+//! Commands executed by clients and the console.
+//!
+//! Commands are registered in [`Server::commands`] and executed with
+//! [`execute`]. Commands sent by clients and entered in the console are
+//! executed automatically at the start of each tick, before [`Config::update`]
+//! is called. Console input is enabled by [`Config::console_input`].
+//!
+//! By default, a command receives everything after its name as a string in
+//! [`CommandContext::args`]. Typed arguments such as block positions and
+//! player selectors can be declared with [`Command::arg`] instead. They are
+//! parsed before the command is executed, and clients are told about them so
+//! they can highlight and complete them.
+//!
+//! The built-in commands are `stop`, which shuts down the server, and
+//! `save-all`, which starts saving the game with [`Server::save_all`]. Both
+//! require operator level 4.
+//!
+//! The built-in moderation commands require operator level 3 and keep their
+//! records in [`SharedServer::storage`]:
+//!
+//! - `kick <player> [reason]` disconnects a player and adds the kick to the
+//!   player's kick history.
+//! - `kicks <player>` lists the kick history of a player.
+//! - `ban <player> [reason]` and `ban-ip <address|player> [reason]` ban a
+//!   player or IP address and disconnect the affected players.
+//! - `pardon <player>` and `pardon-ip <address>` lift bans.
+//! - `banlist` lists the bans in effect.
+//!
+//! Players who are offline can be given by UUID, or by username if online
+//! mode is disabled.
+//!
+//! The built-in commands for [functions](crate::function) require operator
+//! level 2:
+//!
+//! - `function <name>` runs a function.
+//! - `schedule function <name> <time>` runs a function after some time, given
+//!   in ticks or with a unit like `5s` or `1d`.
+//! - `schedule clear <name>` cancels the schedule of a function.
+//!
+//! [`Config::update`]: crate::config::Config::update
+//! [`Config::console_input`]: crate::config::Config::console_input
+//! [`SharedServer::storage`]: crate::server::SharedServer::storage
+
+// Command results carry the feedback shown to the sender, which is a `Text`
+// either way.
+#![allow(clippy::result_large_err)]
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use self::arg::Arg;
+pub use self::arg::{ArgKind, ArgValue};
+use crate::client::ClientId;
+use crate::config::Config;
+use crate::protocol::packets::s2c::play::{
+    CommandNode, CommandNodeData, CommandParser, Commands as CommandsPacket, StringArg,
+};
+use crate::protocol::VarInt;
+use crate::server::{self, Server};
+use crate::storage::{Ban, BanTarget, Kick};
+use crate::text::{Color, Text, TextFormat};
+use crate::util::valid_username;
+use crate::{function, Ticks, STANDARD_TPS};
+
+mod arg;
+
+/// The result of executing a command.
+///
+/// `Ok` contains an optional message that is sent to the
+/// [sender](CommandSender) on success. `Err` contains an error message that is
+/// sent to the sender in red.
+pub type CommandResult = Result<Option<Text>, Text>;
+
+type Handler<C> = Arc<dyn Fn(&mut Server<C>, &CommandContext) -> CommandResult + Send + Sync>;
+
+/// A container for all [`Command`]s on a [`Server`].
+pub struct Commands<C: Config> {
+    commands: BTreeMap<String, Command<C>>,
+    modified: bool,
+}
+
+/// A command that can be executed by clients and the console.
+pub struct Command<C: Config> {
+    op_level: u8,
+    permission: Option<String>,
+    args: Vec<Arg>,
+    handler: Handler<C>,
+}
+
+/// Identifies who executed a command.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CommandSender {
+    /// The command was sent by a client.
+    Client(ClientId),
+    /// The command was entered in the server console. The console is allowed
+    /// to execute every command.
+    Console,
+}
+
+/// Information about a command being executed.
+#[derive(Clone, Debug)]
+pub struct CommandContext {
+    /// Who is executing the command.
+    pub sender: CommandSender,
+    /// The name of the command without the leading slash.
+    pub name: String,
+    /// Everything after the command name with leading whitespace removed.
+    pub args: String,
+    values: Vec<(String, ArgValue)>,
+}
+
+impl CommandContext {
+    /// Gets the value of the argument with the given name, as declared with
+    /// [`Command::arg`] or [`Command::optional_arg`]. Returns `None` if the
+    /// command has no such argument or an optional argument was omitted.
+    pub fn arg(&self, name: &str) -> Option<&ArgValue> {
+        self.values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl<C: Config> Commands<C> {
+    /// Creates the container with the built-in commands registered.
+    pub(crate) fn new() -> Self {
+        let mut commands = Self {
+            commands: BTreeMap::new(),
+            modified: false,
+        };
+
+        commands
+            .register("stop", |server, _| {
+                server
+                    .shared
+                    .shutdown::<_, Box<dyn Error + Send + Sync>>(Ok(()));
+                Ok(Some("Stopping the server".into()))
+            })
+            .set_op_level(4);
+
+        commands
+            .register("save-all", |server, _| {
+                server.save_all();
+                Ok(Some("Saving the game".into()))
+            })
+            .set_op_level(4);
+
+        commands.register("kick", kick).set_op_level(3);
+        commands.register("kicks", kicks).set_op_level(3);
+        commands.register("ban", ban).set_op_level(3);
+        commands.register("ban-ip", ban_ip).set_op_level(3);
+        commands.register("pardon", pardon).set_op_level(3);
+        commands.register("pardon-ip", pardon_ip).set_op_level(3);
+        commands.register("banlist", banlist).set_op_level(3);
+
+        commands
+            .register("function", run_function)
+            .arg("name", ArgKind::Word)
+            .set_op_level(2);
+        commands
+            .register("schedule", schedule)
+            .arg("action", ArgKind::Word)
+            .arg("name", ArgKind::Word)
+            .optional_arg("time", ArgKind::Word)
+            .set_op_level(2);
+
+        commands
+    }
+
+    /// Registers a command with the given name, replacing any existing command
+    /// with the same name. A reference to the command is returned so that its
+    /// requirements can be configured.
+    ///
+    /// `handler` is called every time the command is executed by a sender
+    /// allowed to execute it.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        handler: impl Fn(&mut Server<C>, &CommandContext) -> CommandResult + Send + Sync + 'static,
+    ) -> &mut Command<C> {
+        let name = name.into();
+        debug_assert!(
+            !name.is_empty() && !name.contains(char::is_whitespace),
+            "invalid command name \"{name}\""
+        );
+
+        self.modified = true;
+        self.commands.insert(
+            name.clone(),
+            Command {
+                op_level: 0,
+                permission: None,
+                args: Vec::new(),
+                handler: Arc::new(handler),
+            },
+        );
+
+        self.commands.get_mut(&name).unwrap()
+    }
+
+    /// Removes the command with the given name. Returns `true` if the command
+    /// existed.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        if self.commands.remove(name).is_some() {
+            self.modified = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets a shared reference to the command with the given name.
+    pub fn get(&self, name: &str) -> Option<&Command<C>> {
+        self.commands.get(name)
+    }
+
+    /// Gets an exclusive reference to the command with the given name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Command<C>> {
+        let cmd = self.commands.get_mut(name)?;
+        // The requirements of the command might change.
+        self.modified = true;
+        Some(cmd)
+    }
+
+    /// Returns an iterator over all commands and their names in alphabetical
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Command<C>)> + '_ {
+        self.commands.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Returns the number of registered commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns `true` if there are no registered commands.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub(crate) fn modified(&self) -> bool {
+        self.modified
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.modified = false;
+    }
+
+    /// Creates the packet declaring the commands for which `visible` returns
+    /// `true` to a client. This lets the client suggest command names.
+    pub(crate) fn declare_packet(
+        &self,
+        mut visible: impl FnMut(&Command<C>) -> bool,
+    ) -> CommandsPacket {
+        let mut nodes = vec![CommandNode {
+            children: Vec::new(),
+            redirect_node: None,
+            executable: false,
+            data: CommandNodeData::Root,
+        }];
+
+        for (name, cmd) in &self.commands {
+            if !visible(cmd) {
+                continue;
+            }
+
+            let literal = nodes.len();
+            nodes[0].children.push(VarInt(literal as i32));
+
+            nodes.push(CommandNode {
+                children: Vec::new(),
+                redirect_node: None,
+                executable: cmd.args.first().is_none_or(|arg| arg.optional),
+                data: CommandNodeData::Literal { name: name.clone() },
+            });
+
+            if cmd.args.is_empty() {
+                // Commands without declared arguments take any arguments.
+                let greedy = nodes.len() as i32;
+                nodes[literal].children.push(VarInt(greedy));
+                nodes.push(CommandNode {
+                    children: Vec::new(),
+                    redirect_node: None,
+                    executable: true,
+                    data: CommandNodeData::Argument {
+                        name: "args".into(),
+                        parser: CommandParser::String(StringArg::GreedyPhrase),
+                        suggestion: None,
+                    },
+                });
+                continue;
+            }
+
+            // Every node of an argument leads to all nodes of the next argument.
+            let mut parents = vec![literal];
+            for (i, arg) in cmd.args.iter().enumerate() {
+                let executable = cmd.args.get(i + 1).is_none_or(|next| next.optional);
+                let first = nodes.len();
+
+                nodes.extend(
+                    arg.kind
+                        .nodes(&arg.name)
+                        .into_iter()
+                        .map(|data| CommandNode {
+                            children: Vec::new(),
+                            redirect_node: None,
+                            executable,
+                            data,
+                        }),
+                );
+
+                let children: Vec<_> = (first..nodes.len()).map(|n| VarInt(n as i32)).collect();
+                for parent in parents {
+                    nodes[parent].children = children.clone();
+                }
+                parents = (first..nodes.len()).collect();
+            }
+        }
+
+        CommandsPacket {
+            nodes,
+            root_index: VarInt(0),
+        }
+    }
+}
+
+impl<C: Config> Command<C> {
+    /// Gets the minimum [operator level] needed to execute this command.
+    ///
+    /// [operator level]: crate::client::Client::op_level
+    pub fn op_level(&self) -> u8 {
+        self.op_level
+    }
+
+    /// Sets the minimum [operator level] needed to execute this command. This
+    /// is 0 by default.
+    ///
+    /// [operator level]: crate::client::Client::op_level
+    pub fn set_op_level(&mut self, op_level: u8) -> &mut Self {
+        self.op_level = op_level;
+        self
+    }
+
+    /// Gets the permission node needed to execute this command.
+    pub fn permission(&self) -> Option<&str> {
+        self.permission.as_deref()
+    }
+
+    /// Sets the permission node needed to execute this command. By default, no
+    /// permission is needed.
+    ///
+    /// The node is checked against [`Server::permissions`].
+    pub fn set_permission(&mut self, permission: impl Into<String>) -> &mut Self {
+        self.permission = Some(permission.into());
+        self
+    }
+
+    /// Removes the permission node needed to execute this command.
+    pub fn clear_permission(&mut self) -> &mut Self {
+        self.permission = None;
+        self
+    }
+
+    /// Adds a required argument to this command after the arguments added
+    /// before. Its value is available from [`CommandContext::arg`].
+    ///
+    /// Commands without arguments accept any arguments, which are only
+    /// available from [`CommandContext::args`]. Once an argument is added,
+    /// the arguments are parsed before the handler is called. If they fail to
+    /// parse, the error is sent to the sender and the handler is not called.
+    pub fn arg(&mut self, name: impl Into<String>, kind: ArgKind) -> &mut Self {
+        debug_assert!(
+            self.args.last().is_none_or(|arg| !arg.optional),
+            "required arguments cannot follow optional arguments"
+        );
+        self.push_arg(name.into(), kind, false)
+    }
+
+    /// Like [`Self::arg`], but the argument and all arguments after it can
+    /// be omitted.
+    pub fn optional_arg(&mut self, name: impl Into<String>, kind: ArgKind) -> &mut Self {
+        self.push_arg(name.into(), kind, true)
+    }
+
+    fn push_arg(&mut self, name: String, kind: ArgKind, optional: bool) -> &mut Self {
+        debug_assert!(
+            self.args
+                .last()
+                .is_none_or(|arg| arg.kind != ArgKind::Greedy),
+            "greedy arguments must be the last argument"
+        );
+        self.args.push(Arg {
+            name,
+            kind,
+            optional,
+        });
+        self
+    }
+
+    /// Returns `true` if a sender with the given operator level and
+    /// permissions can execute this command.
+    pub(crate) fn allows(
+        &self,
+        op_level: u8,
+        mut has_permission: impl FnMut(&str) -> bool,
+    ) -> bool {
+        op_level >= self.op_level && self.permission.as_deref().is_none_or(&mut has_permission)
+    }
+}
+
+impl<C: Config> fmt::Debug for Command<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Command")
+            .field("op_level", &self.op_level)
+            .field("permission", &self.permission)
+            .field("args", &self.args)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CommandSender {
+    /// Sends a message to this sender. Messages sent to the console are
+    /// logged.
+    ///
+    /// Nothing happens if the sender is a client which no longer exists.
+    pub fn send_message<C: Config>(self, server: &mut Server<C>, msg: impl Into<Text>) {
+        match self {
+            CommandSender::Client(id) => {
+                if let Some(client) = server.clients.get_mut(id) {
+                    client.send_message(msg);
+                }
+            }
+            CommandSender::Console => tracing::info!("{}", msg.into().to_plain()),
+        }
+    }
+
+    /// Gets the operator level of this sender. The console always has the
+    /// highest level of 4.
+    pub fn op_level<C: Config>(self, server: &Server<C>) -> u8 {
+        match self {
+            CommandSender::Client(id) => server.clients.get(id).map_or(0, |c| c.op_level()),
+            CommandSender::Console => 4,
+        }
+    }
+
+    /// Returns `true` if this sender has the permission `node` according to
+    /// [`Server::permissions`]. The console has every permission.
+    pub fn has_permission<C: Config>(self, server: &Server<C>, node: &str) -> bool {
+        match self {
+            CommandSender::Client(id) => server
+                .clients
+                .get(id)
+                .is_some_and(|c| c.has_permission(&server.permissions, node)),
+            CommandSender::Console => true,
+        }
+    }
+}
+
+/// Executes a command line on behalf of `sender`. A leading slash is optional.
+///
+/// Feedback from the command is sent to the sender. An error message is sent
+/// if the command does not exist or the sender is not allowed to execute it.
+///
+/// Returns `true` if the command's handler was called.
+pub fn execute<C: Config>(server: &mut Server<C>, sender: CommandSender, line: &str) -> bool {
+    let line = line.trim();
+    let line = line.strip_prefix('/').unwrap_or(line);
+
+    let (name, args) = match line.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim_start()),
+        None => (line, ""),
+    };
+
+    if name.is_empty() {
+        return false;
+    }
+
+    let op_level = sender.op_level(server);
+
+    let (handler, values) = match server.commands.get(name) {
+        Some(cmd) => {
+            if !cmd.allows(op_level, |node| sender.has_permission(server, node)) {
+                sender.send_message(
+                    server,
+                    "You do not have permission to use this command".color(Color::RED),
+                );
+                return false;
+            }
+            let values = arg::parse(server, sender, &cmd.args, args);
+            (cmd.handler.clone(), values)
+        }
+        None => {
+            sender.send_message(
+                server,
+                format!("Unknown command \"{name}\"").color(Color::RED),
+            );
+            return false;
+        }
+    };
+
+    let values = match values {
+        Ok(values) => values,
+        Err(msg) => {
+            sender.send_message(server, msg.color(Color::RED));
+            return false;
+        }
+    };
+
+    let ctx = CommandContext {
+        sender,
+        name: name.to_owned(),
+        args: args.to_owned(),
+        values,
+    };
+
+    match handler(server, &ctx) {
+        Ok(Some(msg)) => sender.send_message(server, msg),
+        Ok(None) => {}
+        Err(msg) => sender.send_message(server, msg.color(Color::RED)),
+    }
+
+    true
+}
+
+fn kick<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let (name, reason) = split_first_arg(&ctx.args);
+    let reason = non_empty_or(reason, "Kicked by an operator");
+    let source = source_name(server, ctx.sender);
+
+    let client = server
+        .clients
+        .iter_mut()
+        .map(|(_, c)| c)
+        .find(|c| c.username().eq_ignore_ascii_case(name))
+        .ok_or_else(|| player_not_found(name))?;
+
+    let (uuid, name) = (client.uuid(), client.username().to_owned());
+    client.disconnect(reason.clone());
+
+    let kick = Kick {
+        reason: reason.clone(),
+        source,
+        time: SystemTime::now(),
+    };
+
+    server
+        .shared
+        .storage()
+        .record_kick(uuid, &kick)
+        .map_err(storage_error)?;
+
+    Ok(Some(format!("Kicked {name}: {reason}").into()))
+}
+
+fn kicks<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let (name, _) = split_first_arg(&ctx.args);
+    let (uuid, name) = find_player(server, name).ok_or_else(|| player_not_found(name))?;
+
+    let kicks = server.shared.storage().kicks(uuid).map_err(storage_error)?;
+
+    if kicks.is_empty() {
+        return Ok(Some(format!("{name} has never been kicked").into()));
+    }
+
+    let mut msg = format!("{name} has been kicked {} time(s):", kicks.len());
+    for kick in &kicks {
+        msg += &format!("\nBy {}: {}", kick.source, kick.reason);
+    }
+
+    Ok(Some(msg.into()))
+}
+
+fn ban<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let (name, reason) = split_first_arg(&ctx.args);
+    let (uuid, name) = find_player(server, name).ok_or_else(|| player_not_found(name))?;
+    let reason = non_empty_or(reason, "Banned by an operator.");
+
+    let ban = Ban {
+        name: Some(name.clone()),
+        reason: reason.clone(),
+        source: source_name(server, ctx.sender),
+        created_at: SystemTime::now(),
+        expires_at: None,
+    };
+
+    server
+        .shared
+        .storage()
+        .ban(uuid, &ban)
+        .map_err(storage_error)?;
+
+    let msg = server::ban_message(BanTarget::Player(uuid), &ban);
+    for (_, client) in server.clients.iter_mut() {
+        if client.uuid() == uuid {
+            client.disconnect(msg.clone());
+        }
+    }
+
+    Ok(Some(format!("Banned {name}: {reason}").into()))
+}
+
+fn ban_ip<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let (target, reason) = split_first_arg(&ctx.args);
+    let reason = non_empty_or(reason, "Banned by an operator.");
+
+    let ip = match target.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        Err(_) => server
+            .clients
+            .iter()
+            .find(|(_, c)| c.username().eq_ignore_ascii_case(target))
+            .map(|(_, c)| c.remote_addr().ip())
+            .filter(|ip| !ip.is_unspecified())
+            .ok_or_else(|| Text::from("Invalid IP address or unknown player"))?,
+    };
+
+    let ban = Ban {
+        name: None,
+        reason: reason.clone(),
+        source: source_name(server, ctx.sender),
+        created_at: SystemTime::now(),
+        expires_at: None,
+    };
+
+    server
+        .shared
+        .storage()
+        .ban(ip, &ban)
+        .map_err(storage_error)?;
+
+    let msg = server::ban_message(BanTarget::Ip(ip), &ban);
+    for (_, client) in server.clients.iter_mut() {
+        if client.remote_addr().ip() == ip {
+            client.disconnect(msg.clone());
+        }
+    }
+
+    Ok(Some(format!("Banned IP {ip}: {reason}").into()))
+}
+
+fn pardon<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let (name, _) = split_first_arg(&ctx.args);
+    let storage = server.shared.storage();
+
+    // Banned players are usually offline, so look for the name in the bans
+    // first.
+    let banned = storage
+        .bans()
+        .map_err(storage_error)?
+        .into_iter()
+        .find_map(|(target, ban)| match target {
+            BanTarget::Player(uuid)
+                if ban
+                    .name
+                    .as_deref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name)) =>
+            {
+                Some((uuid, ban.name.unwrap()))
+            }
+            _ => None,
+        });
+
+    let (uuid, name) = banned
+        .or_else(|| find_player(server, name))
+        .ok_or_else(|| player_not_found(name))?;
+
+    if server.shared.storage().unban(uuid).map_err(storage_error)? {
+        Ok(Some(format!("Unbanned {name}").into()))
+    } else {
+        Err("Nothing changed. The player isn't banned".into())
+    }
+}
+
+fn pardon_ip<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let (target, _) = split_first_arg(&ctx.args);
+    let ip = target
+        .parse::<IpAddr>()
+        .map_err(|_| Text::from("Invalid IP address"))?;
+
+    if server.shared.storage().unban(ip).map_err(storage_error)? {
+        Ok(Some(format!("Unbanned IP {ip}").into()))
+    } else {
+        Err("Nothing changed. That IP isn't banned".into())
+    }
+}
+
+fn banlist<C: Config>(server: &mut Server<C>, _ctx: &CommandContext) -> CommandResult {
+    let now = SystemTime::now();
+    let bans: Vec<_> = server
+        .shared
+        .storage()
+        .bans()
+        .map_err(storage_error)?
+        .into_iter()
+        .filter(|(_, ban)| !ban.is_expired_at(now))
+        .collect();
+
+    if bans.is_empty() {
+        return Ok(Some("There are no bans".into()));
+    }
+
+    let mut msg = format!("There are {} ban(s):", bans.len());
+    for (target, ban) in &bans {
+        let banned = match (target, &ban.name) {
+            (BanTarget::Player(_), Some(name)) => name.clone(),
+            _ => target.to_string(),
+        };
+
+        msg += &format!("\n{banned} was banned by {}: {}", ban.source, ban.reason);
+    }
+
+    Ok(Some(msg.into()))
+}
+
+fn run_function<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let name = string_arg(ctx, "name");
+
+    match function::run(server, name, ctx.sender) {
+        Some(count) => Ok(Some(
+            format!("Executed {count} command(s) from function {name}").into(),
+        )),
+        None if server.functions.get(name).is_some() => {
+            Err(format!("Function {name} is nested too deeply").into())
+        }
+        None => Err(format!("Unknown function {name}").into()),
+    }
+}
+
+fn schedule<C: Config>(server: &mut Server<C>, ctx: &CommandContext) -> CommandResult {
+    let name = string_arg(ctx, "name");
+
+    match string_arg(ctx, "action") {
+        "function" => {
+            if server.functions.get(name).is_none() {
+                return Err(format!("Unknown function {name}").into());
+            }
+
+            let time = string_arg(ctx, "time");
+            let ticks = parse_time(time)
+                .filter(|&ticks| ticks > 0)
+                .ok_or_else(|| Text::from(format!("Invalid time \"{time}\"")))?;
+
+            server.functions.schedule(name, ticks);
+            Ok(Some(
+                format!("Scheduled function {name} in {ticks} tick(s)").into(),
+            ))
+        }
+        "clear" => {
+            if server.functions.cancel(name) {
+                Ok(Some(
+                    format!("Removed the schedule of function {name}").into(),
+                ))
+            } else {
+                Err(format!("Function {name} is not scheduled").into())
+            }
+        }
+        action => Err(format!("Unknown action \"{action}\", expected function or clear").into()),
+    }
+}
+
+/// Parses a duration such as `20`, `20t`, `5s` or `1d` into ticks.
+fn parse_time(time: &str) -> Option<Ticks> {
+    let (amount, unit) = match time.char_indices().last()? {
+        (i, 't') => (&time[..i], 1.0),
+        (i, 's') => (&time[..i], STANDARD_TPS as f64),
+        (i, 'd') => (&time[..i], 24000.0),
+        _ => (time, 1.0),
+    };
+
+    let amount: f64 = amount.parse().ok()?;
+    Some((amount * unit).round() as Ticks)
+}
+
+/// Gets the value of a string argument, or an empty string if it was
+/// omitted.
+fn string_arg<'a>(ctx: &'a CommandContext, name: &str) -> &'a str {
+    match ctx.arg(name) {
+        Some(ArgValue::String(s)) => s,
+        _ => "",
+    }
+}
+
+/// Splits command arguments into the first word and the remaining
+/// arguments.
+fn split_first_arg(args: &str) -> (&str, &str) {
+    match args.split_once(char::is_whitespace) {
+        Some((first, rest)) => (first, rest.trim()),
+        None => (args, ""),
+    }
+}
+
+fn non_empty_or(s: &str, default: &str) -> String {
+    if s.is_empty() { default } else { s }.to_owned()
+}
+
+/// The name bans and kicks issued by `sender` are recorded with.
+fn source_name<C: Config>(server: &Server<C>, sender: CommandSender) -> String {
+    match sender {
+        CommandSender::Client(id) => server
+            .clients
+            .get(id)
+            .map_or("Server", |c| c.username())
+            .to_owned(),
+        CommandSender::Console => "Server".to_owned(),
+    }
+}
+
+/// Finds the UUID and username of a player. Online players are found by
+/// username. Other players can be given by UUID, or by username if online
+/// mode is disabled.
+fn find_player<C: Config>(server: &Server<C>, name: &str) -> Option<(Uuid, String)> {
+    if let Some((_, client)) = server
+        .clients
+        .iter()
+        .find(|(_, c)| c.username().eq_ignore_ascii_case(name))
+    {
+        return Some((client.uuid(), client.username().to_owned()));
+    }
+
+    if let Ok(uuid) = Uuid::parse_str(name) {
+        return Some((uuid, name.to_owned()));
+    }
+
+    if !server.shared.online_mode() && valid_username(name) {
+        return Some((server::offline_uuid(name), name.to_owned()));
+    }
+
+    None
+}
+
+fn player_not_found(name: &str) -> Text {
+    if name.is_empty() {
+        "Expected a player".into()
+    } else {
+        format!("No player named \"{name}\" was found").into()
+    }
+}
+
+fn storage_error(e: anyhow::Error) -> Text {
+    tracing::error!("failed to access storage: {e:#}");
+    "Failed to access the ban and kick records".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_pos::BlockPos;
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::server;
+    use crate::testing::{messages, test_server, MockClient};
+
+    #[test]
+    fn moderation_commands_recorded() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        let s = server.server_mut();
+
+        assert!(execute(s, CommandSender::Console, "kick alice spamming"));
+        assert!(s.clients.get(alice.id()).unwrap().is_disconnected());
+
+        let kicks = s
+            .shared
+            .storage()
+            .kicks(server::offline_uuid("alice"))
+            .unwrap();
+        assert!(matches!(
+            kicks.as_slice(),
+            [Kick { reason, source, .. }] if reason == "spamming" && source == "Server"
+        ));
+
+        let bob_uuid = server::offline_uuid("bob");
+        assert!(execute(s, CommandSender::Console, "ban bob"));
+        assert!(s.clients.get(bob.id()).unwrap().is_disconnected());
+
+        let ban = s.shared.storage().active_ban(bob_uuid).unwrap().unwrap();
+        assert_eq!(ban.name.as_deref(), Some("bob"));
+        assert_eq!(ban.reason, "Banned by an operator.");
+
+        assert!(execute(s, CommandSender::Console, "pardon Bob"));
+        assert_eq!(s.shared.storage().ban_of(bob_uuid).unwrap(), None);
+
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        assert!(execute(s, CommandSender::Console, "ban-ip 10.0.0.1"));
+        assert!(s.shared.storage().active_ban(ip).unwrap().is_some());
+        assert!(execute(s, CommandSender::Console, "pardon-ip 10.0.0.1"));
+        assert_eq!(s.shared.storage().ban_of(ip).unwrap(), None);
+    }
+
+    #[test]
+    fn typed_command_arguments() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        server.tick();
+
+        server
+            .server_mut()
+            .commands
+            .register("paint", |_, ctx| {
+                let Some(ArgValue::BlockPos(pos)) = ctx.arg("pos") else {
+                    return Err("no pos".into());
+                };
+                let Some(ArgValue::Players(players)) = ctx.arg("targets") else {
+                    return Err("no targets".into());
+                };
+                let Some(ArgValue::GameMode(mode)) = ctx.arg("mode") else {
+                    return Err("no mode".into());
+                };
+                let color = match ctx.arg("color") {
+                    Some(ArgValue::Color(color)) => Some(*color),
+                    _ => None,
+                };
+                Ok(Some(
+                    format!("{pos:?} {} {mode:?} {color:?}", players.len()).into(),
+                ))
+            })
+            .arg("pos", ArgKind::BlockPos)
+            .arg("targets", ArgKind::Players { single: false })
+            .arg("mode", ArgKind::GameMode)
+            .optional_arg("color", ArgKind::Color);
+        server.tick();
+        bob.take_packets();
+
+        let nodes = alice
+            .take_packets()
+            .into_iter()
+            .rev()
+            .find_map(|pkt| match pkt {
+                S2cPlayPacket::Commands(p) => Some(p.nodes),
+                _ => None,
+            })
+            .unwrap();
+        let children = |node: &CommandNode| -> Vec<_> {
+            node.children.iter().map(|c| &nodes[c.0 as usize]).collect()
+        };
+        let paint = nodes
+            .iter()
+            .find(|n| matches!(&n.data, CommandNodeData::Literal { name } if name == "paint"))
+            .unwrap();
+        assert!(!paint.executable);
+        let [pos] = children(paint)[..] else { panic!() };
+        assert!(matches!(
+            pos.data,
+            CommandNodeData::Argument {
+                parser: CommandParser::BlockPos,
+                ..
+            }
+        ));
+        let [targets] = children(pos)[..] else {
+            panic!()
+        };
+        assert!(matches!(
+            &targets.data,
+            CommandNodeData::Argument { parser: CommandParser::Entity(flags), .. }
+                if !flags.single() && flags.players_only()
+        ));
+        let modes = children(targets);
+        assert_eq!(modes.len(), 4);
+        for mode in modes {
+            assert!(mode.executable);
+            assert!(matches!(
+                children(mode)[..],
+                [CommandNode {
+                    data: CommandNodeData::Argument {
+                        parser: CommandParser::Color,
+                        ..
+                    },
+                    executable: true,
+                    ..
+                }]
+            ));
+        }
+
+        let s = server.server_mut();
+        let sender = CommandSender::Client(alice.id());
+        assert!(execute(s, sender, "paint ~1 ~ ~-1 @a creative red"));
+        assert!(execute(s, sender, "paint 4 5 6 Bob survival"));
+        assert!(!execute(s, sender, "paint 4 5"));
+        assert!(!execute(s, sender, "paint 4 5 6 @a flying"));
+        assert!(!execute(s, sender, "paint 4 5 6 dave survival"));
+        assert!(!execute(
+            s,
+            CommandSender::Console,
+            "paint ~ ~ ~ @s survival"
+        ));
+        server.tick();
+
+        assert_eq!(
+            messages(&alice),
+            [
+                format!(
+                    "{:?} 2 Creative {:?}",
+                    BlockPos::new(1, 100, -1),
+                    Some(Color::RED)
+                ),
+                format!("{:?} 1 Survival None", BlockPos::new(4, 5, 6)),
+                "Incomplete block position".into(),
+                "Unknown game mode \"flying\"".into(),
+                "No player was found".into(),
+            ]
+        );
+        assert!(messages(&bob).is_empty());
+    }
+
+    #[test]
+    fn commands_resent_when_permissions_change() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        server
+            .server_mut()
+            .commands
+            .register("secret", |_, _| Ok(None))
+            .set_permission("test.secret");
+        server.tick();
+
+        let declared = |client: &MockClient| {
+            client.take_packets().into_iter().find_map(|pkt| match pkt {
+                S2cPlayPacket::Commands(p) => Some(p.nodes.iter().any(
+                    |n| matches!(&n.data, CommandNodeData::Literal { name } if name == "secret"),
+                )),
+                _ => None,
+            })
+        };
+        assert_eq!(declared(&alice), Some(false));
+
+        server.tick();
+        assert_eq!(declared(&alice), None);
+
+        server
+            .server_mut()
+            .permissions
+            .user_mut(server::offline_uuid("alice"))
+            .set_node("test.secret", true);
+        server.tick();
+        assert_eq!(declared(&alice), Some(true));
+    }
+}
@@ -0,0 +1,312 @@
+use vek::Vec3;
+
+use super::CommandSender;
+use crate::block_pos::BlockPos;
+use crate::client::{ClientId, GameMode};
+use crate::config::Config;
+use crate::ident::Ident;
+use crate::protocol::packets::s2c::play::{
+    CommandNodeData, CommandParser, EntityArgFlags, StringArg,
+};
+use crate::selector::{Selector, Target};
+use crate::server::Server;
+use crate::text::{Color, Text};
+use crate::world::WorldId;
+
+/// The type of a command argument, added to a command with
+/// [`Command::arg`](super::Command::arg).
+///
+/// Every kind is declared to clients with the matching vanilla argument
+/// parser, so clients highlight and complete arguments like they do for
+/// vanilla commands.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArgKind {
+    /// A single word without whitespace, parsed as [`ArgValue::String`].
+    Word,
+    /// The rest of the command line, parsed as [`ArgValue::String`]. This
+    /// must be the last argument of a command.
+    Greedy,
+    /// Three block coordinates such as `10 ~ ~-2`, parsed as
+    /// [`ArgValue::BlockPos`]. Coordinates starting with `~` are relative to
+    /// the position of the client executing the command.
+    BlockPos,
+    /// A [selector](crate::selector) which only selects players, parsed as
+    /// [`ArgValue::Players`].
+    Players {
+        /// If at most one player may be selected.
+        single: bool,
+    },
+    /// A [selector](crate::selector), parsed as [`ArgValue::Targets`].
+    Entities {
+        /// If at most one target may be selected.
+        single: bool,
+    },
+    /// One of `survival`, `creative`, `adventure` or `spectator`, parsed as
+    /// [`ArgValue::GameMode`].
+    GameMode,
+    /// An item ID such as `minecraft:diamond`, parsed as [`ArgValue::Item`].
+    /// Item NBT is not supported.
+    Item,
+    /// A named text color such as `dark_red`, parsed as
+    /// [`ArgValue::Color`].
+    Color,
+}
+
+/// The value of a parsed command argument, obtained with
+/// [`CommandContext::arg`](super::CommandContext::arg).
+#[derive(Clone, PartialEq, Debug)]
+pub enum ArgValue {
+    /// The value of an [`ArgKind::Word`] or [`ArgKind::Greedy`] argument.
+    String(String),
+    /// The resolved block position. Relative coordinates are rounded down.
+    BlockPos(BlockPos),
+    /// The selected players. This is never empty.
+    Players(Vec<ClientId>),
+    /// The selected players and entities. This is never empty.
+    Targets(Vec<Target>),
+    GameMode(GameMode),
+    /// The item ID as written. The namespace is optional.
+    Item(Ident),
+    Color(Color),
+}
+
+/// An argument declared by a command.
+#[derive(Clone, Debug)]
+pub(super) struct Arg {
+    pub name: String,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+const GAME_MODES: [(&str, GameMode); 4] = [
+    ("survival", GameMode::Survival),
+    ("creative", GameMode::Creative),
+    ("adventure", GameMode::Adventure),
+    ("spectator", GameMode::Spectator),
+];
+
+impl ArgKind {
+    /// Gets the command nodes declaring an argument of this kind to clients.
+    /// Game modes are declared as one literal per game mode, since 1.19.2
+    /// clients have no parser for them.
+    pub(super) fn nodes(self, name: &str) -> Vec<CommandNodeData> {
+        let parser = match self {
+            ArgKind::Word => CommandParser::String(StringArg::SingleWord),
+            ArgKind::Greedy => CommandParser::String(StringArg::GreedyPhrase),
+            ArgKind::BlockPos => CommandParser::BlockPos,
+            ArgKind::Players { single } => CommandParser::Entity(EntityArgFlags::new(single, true)),
+            ArgKind::Entities { single } => {
+                CommandParser::Entity(EntityArgFlags::new(single, false))
+            }
+            ArgKind::GameMode => {
+                return GAME_MODES
+                    .iter()
+                    .map(|&(name, _)| CommandNodeData::Literal { name: name.into() })
+                    .collect()
+            }
+            ArgKind::Item => CommandParser::ItemStack,
+            ArgKind::Color => CommandParser::Color,
+        };
+
+        vec![CommandNodeData::Argument {
+            name: name.into(),
+            parser,
+            suggestion: None,
+        }]
+    }
+}
+
+/// Parses the arguments of a command from `input`. Parsing stops at the first
+/// missing optional argument. Commands without declared arguments accept any
+/// input.
+pub(super) fn parse<C: Config>(
+    server: &Server<C>,
+    sender: CommandSender,
+    args: &[Arg],
+    input: &str,
+) -> Result<Vec<(String, ArgValue)>, Text> {
+    if args.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut input = input.trim();
+    let mut values = Vec::with_capacity(args.len());
+
+    for arg in args {
+        if input.is_empty() {
+            if arg.optional {
+                break;
+            }
+            return Err(format!("Expected {}", arg.name).into());
+        }
+
+        let value = match arg.kind {
+            ArgKind::Word => ArgValue::String(next_word(&mut input).to_owned()),
+            ArgKind::Greedy => ArgValue::String(std::mem::take(&mut input).to_owned()),
+            ArgKind::BlockPos => {
+                let origin = sender_position(server, sender);
+                let mut coord = |origin: Option<f64>| match next_word(&mut input) {
+                    "" => Err(Text::from("Incomplete block position")),
+                    word => parse_coordinate(word, origin),
+                };
+
+                ArgValue::BlockPos(BlockPos::new(
+                    coord(origin.map(|(_, pos)| pos.x))?,
+                    coord(origin.map(|(_, pos)| pos.y))?,
+                    coord(origin.map(|(_, pos)| pos.z))?,
+                ))
+            }
+            ArgKind::Players { single } => {
+                let selector = parse_selector(next_word(&mut input), single)?;
+                if selector.selects_entities() {
+                    return Err("Only players can be selected".into());
+                }
+
+                let players: Vec<_> = selector
+                    .select(server, sender)
+                    .into_iter()
+                    .filter_map(|target| match target {
+                        Target::Player(id) => Some(id),
+                        // Selected by `@e[type=player]`.
+                        Target::Entity(id) => server
+                            .clients
+                            .iter()
+                            .find(|(_, client)| client.player_entity() == Some(id))
+                            .map(|(client, _)| client),
+                    })
+                    .collect();
+
+                if players.is_empty() {
+                    return Err("No player was found".into());
+                }
+                ArgValue::Players(players)
+            }
+            ArgKind::Entities { single } => {
+                let targets = parse_selector(next_word(&mut input), single)?.select(server, sender);
+                if targets.is_empty() {
+                    return Err("No entity was found".into());
+                }
+                ArgValue::Targets(targets)
+            }
+            ArgKind::GameMode => {
+                let word = next_word(&mut input);
+                let (_, mode) = GAME_MODES
+                    .iter()
+                    .find(|(name, _)| *name == word)
+                    .ok_or_else(|| Text::from(format!("Unknown game mode \"{word}\"")))?;
+                ArgValue::GameMode(*mode)
+            }
+            ArgKind::Item => {
+                let word = next_word(&mut input);
+                if word.contains('{') {
+                    return Err("Item NBT is not supported".into());
+                }
+                let id = Ident::new(word.to_owned())
+                    .map_err(|_| Text::from(format!("Invalid item ID \"{word}\"")))?;
+                ArgValue::Item(id)
+            }
+            ArgKind::Color => {
+                let word = next_word(&mut input);
+                let color = Color::from_name(word)
+                    .ok_or_else(|| Text::from(format!("Unknown color \"{word}\"")))?;
+                ArgValue::Color(color)
+            }
+        };
+
+        values.push((arg.name.clone(), value));
+    }
+
+    if !input.is_empty() {
+        return Err(format!("Unexpected argument \"{input}\"").into());
+    }
+
+    Ok(values)
+}
+
+/// Removes the first word from `input` and returns it.
+fn next_word<'a>(input: &mut &'a str) -> &'a str {
+    let (word, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    *input = rest.trim_start();
+    word
+}
+
+/// Parses a block coordinate. Relative coordinates are resolved against
+/// `origin`, which is `None` if the sender has no position.
+fn parse_coordinate(word: &str, origin: Option<f64>) -> Result<i32, Text> {
+    if word.starts_with('^') {
+        return Err("Local coordinates are not supported".into());
+    }
+
+    match word.strip_prefix('~') {
+        Some(offset) => {
+            let origin =
+                origin.ok_or_else(|| Text::from("Relative coordinates require a player"))?;
+            let offset = match offset {
+                "" => 0.0,
+                offset => offset
+                    .parse::<f64>()
+                    .map_err(|_| Text::from(format!("Invalid coordinate \"{word}\"")))?,
+            };
+            Ok((origin + offset).floor() as i32)
+        }
+        None => word
+            .parse()
+            .map_err(|_| format!("Invalid coordinate \"{word}\"").into()),
+    }
+}
+
+fn sender_position<C: Config>(
+    server: &Server<C>,
+    sender: CommandSender,
+) -> Option<(WorldId, Vec3<f64>)> {
+    match sender {
+        CommandSender::Client(id) => server
+            .clients
+            .get(id)
+            .map(|client| (client.world(), client.position())),
+        CommandSender::Console => None,
+    }
+}
+
+/// Parses a selector for an argument which may only select one target if
+/// `single` is `true`.
+fn parse_selector(word: &str, single: bool) -> Result<Selector, Text> {
+    let selector =
+        Selector::parse(word).map_err(|e| Text::from(format!("Invalid selector: {e}")))?;
+
+    if single && !selector.is_single() {
+        return Err("Only one target is allowed, but the selector allows more".into());
+    }
+
+    Ok(selector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(parse_coordinate("-7", None), Ok(-7));
+        assert_eq!(parse_coordinate("~", Some(2.5)), Ok(2));
+        assert_eq!(parse_coordinate("~-3", Some(2.5)), Ok(-1));
+        assert_eq!(parse_coordinate("~0.5", Some(2.5)), Ok(3));
+        assert!(parse_coordinate("~1", None).is_err());
+        assert!(parse_coordinate("^1", Some(0.0)).is_err());
+        assert!(parse_coordinate("1.5", None).is_err());
+    }
+
+    #[test]
+    fn game_mode_declared_as_literals() {
+        let nodes = ArgKind::GameMode.nodes("mode");
+        let names: Vec<_> = nodes
+            .iter()
+            .map(|node| match node {
+                CommandNodeData::Literal { name } => name.as_str(),
+                _ => panic!("expected a literal node"),
+            })
+            .collect();
+
+        assert_eq!(names, ["survival", "creative", "adventure", "spectator"]);
+    }
+}
@@ -0,0 +1,130 @@
+//! Typed state attached to clients and entities.
+//!
+//! Every [`Client`] and [`Entity`] has a [`Components`] map alongside its
+//! custom state. The map stores at most one value of each type, so
+//! independent modules can attach their own state by defining their own type
+//! instead of adding fields to a shared [`Config::ClientState`] or
+//! [`Config::EntityState`].
+//!
+//! ```
+//! use valence::component::Components;
+//!
+//! #[derive(Default)]
+//! struct Kills(u32);
+//!
+//! let mut components = Components::new();
+//! components.get_or_default::<Kills>().0 += 1;
+//!
+//! assert_eq!(components.get::<Kills>().map(|k| k.0), Some(1));
+//! ```
+//!
+//! [`Client`]: crate::client::Client
+//! [`Entity`]: crate::entity::Entity
+//! [`Config::ClientState`]: crate::config::Config::ClientState
+//! [`Config::EntityState`]: crate::config::Config::EntityState
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A map containing at most one value of every type.
+#[derive(Default)]
+pub struct Components {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Components {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, replacing and returning the previous value of the
+    /// same type if there was one.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|old| *old.downcast().unwrap())
+    }
+
+    /// Gets the value of type `T`.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .map(|value| value.downcast_ref().unwrap())
+    }
+
+    /// Gets the value of type `T` mutably.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .map(|value| value.downcast_mut().unwrap())
+    }
+
+    /// Gets the value of type `T` mutably, inserting the default value first
+    /// if there is none.
+    pub fn get_or_default<T: Default + Send + Sync + 'static>(&mut self) -> &mut T {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<T>::default())
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Removes and returns the value of type `T`.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .map(|value| *value.downcast().unwrap())
+    }
+
+    /// Returns `true` if there is a value of type `T`.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes all values.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl fmt::Debug for Components {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Components")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_value_per_type() {
+        let mut components = Components::new();
+        assert_eq!(components.insert(5_u32), None);
+        assert_eq!(components.insert("team"), None);
+        assert_eq!(components.insert(7_u32), Some(5));
+
+        *components.get_mut::<u32>().unwrap() += 1;
+        assert_eq!(components.get::<u32>(), Some(&8));
+        assert_eq!(components.get::<&str>(), Some(&"team"));
+        assert_eq!(components.len(), 2);
+
+        assert_eq!(components.remove::<u32>(), Some(8));
+        assert!(!components.contains::<u32>());
+        assert_eq!(*components.get_or_default::<u32>(), 0);
+    }
+}
@@ -2,14 +2,26 @@
 
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use tokio::runtime::Handle as TokioHandle;
+use uuid::Uuid;
 
 use crate::biome::Biome;
+use crate::block::{BlockPos, BlockState};
+use crate::chunk::{BlockPhysics, ChunkPos, UnloadedChunk};
+use crate::client::{Client, ClientId, PacketLimits};
 use crate::dimension::Dimension;
-use crate::server::{NewClientData, Server, SharedServer};
+use crate::entity::Entities;
+use crate::favicon::Favicon;
+use crate::mojang::{GameProfile, HttpMojangApi, MojangApi};
+use crate::packet_log::PacketLogFilter;
+use crate::server::{ConnectionInfo, NewClientData, Server, SharedServer};
+use crate::storage::Storage;
 use crate::text::Text;
+use crate::transport::ListenAddress;
+use crate::world::{World, WorldId};
 use crate::{Ticks, STANDARD_TPS};
 
 /// A trait for the configuration of a server.
@@ -24,14 +36,17 @@ use crate::{Ticks, STANDARD_TPS};
 pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
     /// Custom state to store with the [`Server`].
     type ServerState: Send + Sync;
-    /// Custom state to store with every [`Client`](crate::client::Client).
+    /// Custom state to store with every [`Client`].
     type ClientState: Default + Send + Sync;
     /// Custom state to store with every [`Entity`](crate::entity::Entity).
     type EntityState: Send + Sync;
-    /// Custom state to store with every [`World`](crate::world::World).
+    /// Custom state to store with every [`World`].
     type WorldState: Send + Sync;
     /// Custom state to store with every [`Chunk`](crate::chunk::Chunk).
-    type ChunkState: Send + Sync;
+    ///
+    /// Chunks loaded by [tickets](crate::world::World::add_ticket) start
+    /// with the default state.
+    type ChunkState: Default + Send + Sync;
     /// Custom state to store with every
     /// [`PlayerList`](crate::player_list::PlayerList).
     type PlayerListState: Send + Sync;
@@ -45,16 +60,37 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
     /// full.
     fn max_connections(&self) -> usize;
 
-    /// Called once at startup to get the socket address the server will
-    /// be bound to.
+    /// Called once at startup to get the address the server will listen on,
+    /// unless [`Self::addresses`] is overridden.
+    ///
+    /// This is either a TCP socket address or, on Unix, the path of a Unix
+    /// domain socket. Listening on a Unix domain socket is useful when the
+    /// server is only reached through a proxy running alongside it.
     ///
     /// # Default Implementation
     ///
     /// Returns `127.0.0.1:25565`.
-    fn address(&self) -> SocketAddr {
+    fn address(&self) -> ListenAddress {
         SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 25565).into()
     }
 
+    /// Called once at startup to get all addresses the server will listen
+    /// on, such as an IPv4 and an IPv6 address, the addresses of several
+    /// network interfaces, or a TCP address alongside a Unix domain socket.
+    ///
+    /// Connections accepted on any of the addresses join the same server.
+    /// [`Self::server_list_ping`] is given the address a query was received
+    /// on, so each address can have its own status response.
+    ///
+    /// The returned `Vec` must not be empty.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `vec![self.address()]`.
+    fn addresses(&self) -> Vec<ListenAddress> {
+        vec![self.address()]
+    }
+
     /// Called once at startup to get the tick rate, which is the number of game
     /// updates that should occur in one second.
     ///
@@ -74,6 +110,16 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
         STANDARD_TPS
     }
 
+    /// Called once at startup to get what the server does when ticks take
+    /// longer than the [tick rate](Self::tick_rate) allows.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns [`TickCatchUp::Stretch`].
+    fn tick_catch_up(&self) -> TickCatchUp {
+        TickCatchUp::Stretch
+    }
+
     /// Called once at startup to get the "online mode" option, which determines
     /// if client authentication and encryption should take place.
     ///
@@ -91,6 +137,147 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
         true
     }
 
+    /// Called once at startup to get the client used for the Mojang API and
+    /// session server.
+    ///
+    /// This is used by the default implementation of [`Self::authenticate`]
+    /// and is available through [`SharedServer::mojang_api`]. Return a custom
+    /// [`MojangApi`] to authenticate players against another session server,
+    /// for instance behind a proxy or in a test environment.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns an [`HttpMojangApi`] for Mojang's servers.
+    fn mojang_api(&self) -> Box<dyn MojangApi> {
+        Box::new(HttpMojangApi::new())
+    }
+
+    /// Called once at startup to get the [`Storage`] holding bans and kick
+    /// records.
+    ///
+    /// Players and IP addresses with an active ban in this storage are
+    /// disconnected during login, before [`Self::login`] is called. The
+    /// storage is available through [`SharedServer::storage`] and can hold
+    /// player data and statistics as well.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns [`Storage::in_memory`], so bans are lost when the server stops.
+    fn storage(&self) -> Storage {
+        Storage::in_memory()
+    }
+
+    /// Called once at startup to determine if lines entered on the standard
+    /// input should be executed as [commands](crate::command) from the
+    /// [console](crate::command::CommandSender::Console).
+    ///
+    /// Lines are read on a separate thread and executed at the start of the
+    /// next tick. The built-in `stop` command shuts down the server.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `false`.
+    fn console_input(&self) -> bool {
+        false
+    }
+
+    /// Called once at startup to get the address to serve
+    /// [metrics](crate::metrics) on. Prometheus can scrape the metrics from
+    /// this address over HTTP.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `None`, so metrics are not served.
+    fn metrics_address(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Called once at startup to get the message of the day announced to
+    /// clients on the local network.
+    ///
+    /// If this returns `Some`, the server is announced over UDP multicast the
+    /// same way vanilla announces worlds opened to LAN, so that it is listed
+    /// under the games on the local network in the multiplayer menu. Since
+    /// LAN worlds do not authenticate players, this is usually combined with
+    /// disabling [online mode](Self::online_mode).
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `None`, so the server is not announced.
+    fn lan_motd(&self) -> Option<String> {
+        None
+    }
+
+    /// Called once at startup to get the limits on packets sent by clients.
+    ///
+    /// This includes the maximum sizes of decoded packets and per-client rate
+    /// limits for chat, interaction and movement packets. Clients exceeding a
+    /// rate limit have their packets dropped or are disconnected.
+    ///
+    /// The window of every rate limit must be greater than zero.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns [`PacketLimits::default()`], which is lenient enough for
+    /// vanilla clients.
+    fn packet_limits(&self) -> PacketLimits {
+        PacketLimits::default()
+    }
+
+    /// Called once at startup to get the packets which are
+    /// [logged](crate::packet_log) with their names and sizes. This is meant
+    /// for debugging the order of packets sent and received by clients.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `None`, so no packets are logged.
+    fn packet_log(&self) -> Option<PacketLogFilter> {
+        None
+    }
+
+    /// Called once at startup to get the number of block changes in a single
+    /// chunk section per tick above which the whole chunk is sent to clients
+    /// again instead of the individual block changes.
+    ///
+    /// Sending a chunk is cheaper than sending thousands of block changes, but
+    /// more expensive than sending a few. A chunk section contains 4096 blocks,
+    /// so any threshold of 4096 or more disables resending.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `2048`.
+    fn chunk_resend_threshold(&self) -> usize {
+        2048
+    }
+
+    /// Called once at startup to get the number of ticks between autosaves,
+    /// or `None` to disable autosaving.
+    ///
+    /// An autosave does the same as [`Server::save_all`]. It is skipped if
+    /// saves started earlier are still running. The interval must be greater
+    /// than zero.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `None`.
+    fn autosave_interval(&self) -> Option<Ticks> {
+        None
+    }
+
+    /// Called once at startup to get the settings for lowering the view
+    /// distance of clients while the server is overloaded, or `None` to
+    /// always use the view distance set with [`Client::set_view_distance`].
+    ///
+    /// Loading fewer chunks and entities for each client makes ticks faster,
+    /// which keeps a slow tick from causing even slower ticks.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `None`.
+    fn view_distance_scaling(&self) -> Option<ViewDistanceScaling> {
+        None
+    }
+
     /// Called once at startup to get the capacity of the buffer used to
     /// hold incoming packets.
     ///
@@ -105,9 +292,15 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
         64
     }
 
-    /// Called once at startup to get the capacity of the buffer used to
+    /// Called once at startup to get the capacity of the buffers used to
     /// hold outgoing packets.
     ///
+    /// Each client has separate buffers for urgent packets such as keepalives
+    /// and teleports, for chunk data, and for all other packets. Urgent
+    /// packets are written before the chunk data still waiting to be written,
+    /// so that clients on slow connections are not timed out while loading
+    /// chunks. The capacity applies to each buffer.
+    ///
     /// A larger capacity reduces the chance that a client needs to be
     /// disconnected due to a full buffer, but increases potential memory usage.
     ///
@@ -169,6 +362,12 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
     /// Called when the server receives a Server List Ping query.
     /// Data for the response can be provided or the query can be ignored.
     ///
+    /// `local_addr` is the address from [`Self::addresses`] the query was
+    /// received on.
+    ///
+    /// This method is not called while a status set with
+    /// [`SharedServer::set_status`] is in effect.
+    ///
     /// This method is called from within a tokio runtime.
     ///
     /// # Default Implementation
@@ -178,11 +377,56 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
         &self,
         shared: &SharedServer<Self>,
         remote_addr: SocketAddr,
+        local_addr: &ListenAddress,
         protocol_version: i32,
     ) -> ServerListPing {
         ServerListPing::Ignore
     }
 
+    /// Called asynchronously in [online mode](Self::online_mode) to
+    /// authenticate a client after encryption has been enabled, returning the
+    /// client's profile. If this method returns with `Err(reason)`, then the
+    /// client is immediately disconnected with the given reason.
+    ///
+    /// `server_hash` is the server ID hash the client sent to its session
+    /// server when joining. Override this method to authenticate clients by
+    /// other means, such as a private session server or access tokens. The
+    /// username and UUID of the returned profile are used for the client, and
+    /// its `textures` property for the client's skin if present. The username
+    /// must match the one the client logged in with.
+    ///
+    /// This method is called from within a tokio runtime.
+    ///
+    /// # Default Implementation
+    ///
+    /// Asks [`SharedServer::mojang_api`] if the client has joined.
+    async fn authenticate(
+        &self,
+        shared: &SharedServer<Self>,
+        username: &str,
+        server_hash: &str,
+        remote_addr: SocketAddr,
+    ) -> Result<GameProfile, Text> {
+        match shared
+            .mojang_api()
+            .has_joined(
+                username,
+                server_hash,
+                Some(remote_addr.ip()).filter(|ip| !ip.is_unspecified()),
+            )
+            .await
+        {
+            Ok(Some(profile)) => Ok(profile),
+            Ok(None) => Err(Text::translate(
+                "multiplayer.disconnect.unverified_username",
+            )),
+            Err(e) => {
+                tracing::error!("failed to authenticate {username}: {e:#}");
+                Err(Text::translate("multiplayer.disconnect.authservers_down"))
+            }
+        }
+    }
+
     /// Called asynchronously for each client after successful authentication
     /// (if online mode is enabled) to determine if they can join
     /// the server. On success, the new client is added to the server's
@@ -204,6 +448,123 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
         Ok(())
     }
 
+    /// Called for every connection attempting to log in, directly after the
+    /// handshake.
+    ///
+    /// This is useful for logging and metrics. To reject clients, use
+    /// [`Self::login`].
+    ///
+    /// This method is called from within a tokio runtime.
+    fn on_connect(&self, shared: &SharedServer<Self>, info: &ConnectionInfo) {}
+
+    /// Called after a client has logged in successfully and [`Self::login`]
+    /// has allowed it to join. The client is added to the server's
+    /// [`Clients`] at the start of the next tick.
+    ///
+    /// This method is called from within a tokio runtime.
+    ///
+    /// [`Clients`]: crate::client::Clients
+    fn on_login_success(
+        &self,
+        shared: &SharedServer<Self>,
+        info: &ConnectionInfo,
+        ncd: &NewClientData,
+    ) {
+    }
+
+    /// Called when a connection passed to [`Self::on_connect`] is closed for
+    /// any reason.
+    ///
+    /// `ncd` is `Some` if the client logged in successfully before
+    /// disconnecting.
+    ///
+    /// This method is called from within a tokio runtime.
+    fn on_disconnect(
+        &self,
+        shared: &SharedServer<Self>,
+        info: &ConnectionInfo,
+        ncd: Option<&NewClientData>,
+    ) {
+    }
+
+    /// Called asynchronously after a client has logged in to load the data
+    /// stored for the player with the given UUID. The returned state becomes
+    /// the client's [`ClientState`](Self::ClientState) when it joins.
+    ///
+    /// This method is called from within a tokio runtime.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `Self::ClientState::default()`. [`PlayerDataStore`] can be used
+    /// to load state saved to flat files by [`Self::save_player_data`].
+    ///
+    /// [`PlayerDataStore`]: crate::player_data::PlayerDataStore
+    async fn load_player_data(&self, shared: &SharedServer<Self>, uuid: Uuid) -> Self::ClientState {
+        Self::ClientState::default()
+    }
+
+    /// Called asynchronously with the state of a client after it was removed
+    /// from the server's [`Clients`] with [`Clients::retain`], and for the
+    /// clients still on the server when it shuts down. The server waits for
+    /// the calls to finish before shutting down.
+    ///
+    /// The state of clients removed with [`Clients::remove`] is returned from
+    /// that method instead.
+    ///
+    /// This method is called from within a tokio runtime.
+    ///
+    /// # Default Implementation
+    ///
+    /// The state is dropped. [`PlayerDataStore`] can be used to save state to
+    /// flat files.
+    ///
+    /// [`Clients`]: crate::client::Clients
+    /// [`Clients::retain`]: crate::client::Clients::retain
+    /// [`Clients::remove`]: crate::client::Clients::remove
+    /// [`PlayerDataStore`]: crate::player_data::PlayerDataStore
+    async fn save_player_data(
+        &self,
+        shared: &SharedServer<Self>,
+        uuid: Uuid,
+        state: Self::ClientState,
+    ) {
+    }
+
+    /// Called by [`Server::save_all`] for each client to copy the state
+    /// passed to [`Self::save_player_data`] while the client is still on the
+    /// server. Returning `None` skips saving the client.
+    ///
+    /// # Default Implementation
+    ///
+    /// Returns `None`, since the client state may not be cloneable.
+    fn snapshot_player_data(&self, client: &Client<Self>) -> Option<Self::ClientState> {
+        None
+    }
+
+    /// Called asynchronously by [`Server::save_all`] with a copy of each chunk
+    /// which is [dirty](crate::chunk::Chunk::is_dirty), and for the dirty chunks left when
+    /// the server shuts down. The server waits for the calls to finish before
+    /// shutting down.
+    ///
+    /// The chunk can be loaded again by inserting it with
+    /// [`Chunks::insert_with`].
+    ///
+    /// This method is called from within a tokio runtime.
+    ///
+    /// # Default Implementation
+    ///
+    /// The chunk is dropped.
+    ///
+    /// [`Chunks::insert_with`]: crate::chunk::Chunks::insert_with
+    async fn save_chunk(
+        &self,
+        shared: &SharedServer<Self>,
+        world: WorldId,
+        pos: ChunkPos,
+        chunk: UnloadedChunk,
+    ) {
+    }
+
     /// Called after the server is created, but prior to accepting connections
     /// and entering the update loop.
     ///
@@ -224,6 +585,120 @@ pub trait Config: Sized + Send + Sync + UnwindSafe + RefUnwindSafe + 'static {
     ///
     /// The default implementation does nothing.
     fn update(&self, server: &mut Server<Self>);
+
+    /// Called once per world every tick, right after [`Self::update`].
+    ///
+    /// `clients` contains every client whose [`Client::world`] is `world`.
+    /// Worlds are updated in parallel on separate threads, so code which
+    /// only touches a single world and the clients inside it can be moved
+    /// here from [`Self::update`] to spread the work across cores.
+    ///
+    /// Entities cannot be spawned or despawned from here. Use
+    /// [`Self::update`] for that instead.
+    ///
+    /// # Default Implementation
+    ///
+    /// The default implementation does nothing.
+    fn update_world(
+        &self,
+        shared: &SharedServer<Self>,
+        id: WorldId,
+        world: &mut World<Self>,
+        clients: &mut [(ClientId, &mut Client<Self>)],
+        entities: &Entities<Self>,
+    ) {
+    }
+
+    /// Called for every block which is picked at random in a world with a
+    /// non-zero [random tick speed]. This can be used for farming and
+    /// ambient block behavior such as spreading grass or melting ice.
+    ///
+    /// `block` is the block at `pos`, which is never air. If a block is
+    /// returned, it replaces the block at `pos` once all random ticks of
+    /// the world are done.
+    ///
+    /// Worlds are updated in parallel, so this can be called from several
+    /// threads at once.
+    ///
+    /// # Default Implementation
+    ///
+    /// Crops grow by one stage with [`BlockPhysics::grow_crop`].
+    ///
+    /// [random tick speed]: BlockPhysics::set_random_tick_speed
+    fn random_tick(&self, world: WorldId, pos: BlockPos, block: BlockState) -> Option<BlockState> {
+        BlockPhysics::grow_crop(block)
+    }
+
+    /// Called once after [`SharedServer::shutdown`] or
+    /// [`SharedServer::shutdown_with_reason`], before clients are disconnected
+    /// and the update loop exits.
+    ///
+    /// This is useful for saving persistent data while the clients are still
+    /// available.
+    ///
+    /// This method is called from within a tokio runtime.
+    fn shutdown(&self, server: &mut Server<Self>) {}
+}
+
+/// What the server does when ticks fall behind schedule, configured with
+/// [`Config::tick_catch_up`].
+///
+/// The number of ticks the server is behind is available from
+/// [`SharedServer::tick_debt`].
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum TickCatchUp {
+    /// The next tick starts right after a slow tick, and the schedule
+    /// continues from there. Time in the game passes slower while ticks are
+    /// slow, but is never sped up.
+    #[default]
+    Stretch,
+    /// The ticks missed during a slow tick are skipped, and the next tick
+    /// starts at the next time on the original schedule. The skipped ticks
+    /// never run.
+    Skip,
+    /// The ticks missed during a slow tick are run back to back without
+    /// sleeping until the server is back on schedule. At most `max_ticks`
+    /// ticks are owed at a time, and the rest are skipped.
+    CatchUp {
+        /// The largest number of ticks run to catch up.
+        max_ticks: Ticks,
+    },
+}
+
+/// Settings for lowering the view distance of clients while ticks take too
+/// long, configured with [`Config::view_distance_scaling`].
+///
+/// Every `interval` ticks, the average duration of the ticks in the interval
+/// is compared to the thresholds. If it is above `slow_tick`, the view
+/// distance of all clients is limited to one chunk less than the largest view
+/// distance in use, down to `min_view_distance`. If it is below `fast_tick`,
+/// the limit is raised by one chunk until it no longer applies.
+///
+/// See [`SharedServer::view_distance_limit`] for the current limit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ViewDistanceScaling {
+    /// The average tick duration above which view distances are lowered.
+    pub slow_tick: Duration,
+    /// The average tick duration below which view distances are restored.
+    /// Must be less than `slow_tick`.
+    pub fast_tick: Duration,
+    /// The lowest view distance clients are limited to. Must be in `2..=32`.
+    pub min_view_distance: u8,
+    /// The number of ticks between adjustments. Must be greater than zero.
+    pub interval: Ticks,
+}
+
+impl Default for ViewDistanceScaling {
+    /// Lowers view distances down to 4 chunks when ticks take more than 90%
+    /// of the 50 ms available at 20 TPS, and restores them below 60%.
+    fn default() -> Self {
+        Self {
+            slow_tick: Duration::from_millis(45),
+            fast_tick: Duration::from_millis(30),
+            min_view_distance: 4,
+            interval: STANDARD_TPS,
+        }
+    }
 }
 
 /// The result of the [`server_list_ping`](Config::server_list_ping) callback.
@@ -237,14 +712,45 @@ pub enum ServerListPing<'a> {
         /// Displayed as the maximum number of players allowed on the server at
         /// a time.
         max_players: i32,
+        /// The players shown when hovering over the player count.
+        player_sample: Vec<PlayerSampleEntry>,
         /// A description of the server.
         description: Text,
-        /// The server's icon as the bytes of a PNG image.
-        /// The image must be 64x64 pixels.
+        /// The server's icon.
         ///
         /// No icon is used if the value is `None`.
-        favicon_png: Option<&'a [u8]>,
+        favicon: Option<&'a Favicon>,
     },
     /// Ignores the query and disconnects from the client.
     Ignore,
 }
+
+/// An entry in the player sample of a server list ping response.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PlayerSampleEntry {
+    /// The name displayed for the player. This does not have to be a real
+    /// username.
+    pub name: String,
+    /// The UUID of the player.
+    pub id: Uuid,
+}
+
+/// The server list ping response set with [`SharedServer::set_status`].
+///
+/// This is the owned equivalent of [`ServerListPing::Respond`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct ServerStatus {
+    /// Displayed as the number of players on the server.
+    pub online_players: i32,
+    /// Displayed as the maximum number of players allowed on the server at a
+    /// time.
+    pub max_players: i32,
+    /// The players shown when hovering over the player count.
+    pub player_sample: Vec<PlayerSampleEntry>,
+    /// A description of the server.
+    pub description: Text,
+    /// The server's icon.
+    ///
+    /// No icon is used if the value is `None`.
+    pub favicon: Option<Favicon>,
+}
@@ -0,0 +1,303 @@
+//! Groups of blocks which move as a unit, such as moving platforms.
+
+use vek::Vec3;
+
+use crate::block::{BlockPos, BlockState};
+use crate::chunk::Chunks;
+use crate::config::Config;
+use crate::entity::{Entities, EntityId, EntityKind, TrackedData};
+use crate::world::WorldId;
+
+/// A group of blocks which can be moved and rotated as a unit.
+///
+/// While moving, every block is shown by a [`EntityKind::FallingBlock`]
+/// entity without gravity. The entities are created, moved, and removed by
+/// [`Self::update`], which must be called after the contraption is modified
+/// for the changes to become visible. Clients do not collide with the
+/// entities, so players standing on a contraption do not move with it.
+///
+/// A contraption is created from the blocks of a world with
+/// [`Self::capture`], and turned back into real blocks with
+/// [`Self::settle`].
+///
+/// Rotating a contraption moves its blocks, but does not rotate the block
+/// states. For example, stairs keep facing the same direction.
+#[derive(Debug)]
+pub struct Contraption {
+    world: WorldId,
+    position: Vec3<f64>,
+    yaw: f32,
+    /// The blocks by their offset from the origin of the contraption.
+    blocks: Vec<(BlockPos, BlockState)>,
+    /// The falling block showing each block.
+    entities: Vec<EntityId>,
+}
+
+impl Contraption {
+    /// Creates a contraption without blocks whose origin is at `position`.
+    pub fn new(world: WorldId, position: impl Into<Vec3<f64>>) -> Self {
+        Self {
+            world,
+            position: position.into(),
+            yaw: 0.0,
+            blocks: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    /// Creates a contraption from the blocks between `a` and `b` inclusive in
+    /// `chunks`, which belong to `world`. The blocks are replaced with air.
+    /// Air and blocks outside of loaded chunks are not included.
+    ///
+    /// The origin of the contraption is the corner of the region with the
+    /// lowest coordinates.
+    pub fn capture<C: Config>(
+        world: WorldId,
+        chunks: &mut Chunks<C>,
+        a: impl Into<BlockPos>,
+        b: impl Into<BlockPos>,
+    ) -> Self {
+        let (a, b) = (a.into(), b.into());
+        let min = BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let mut contraption = Self::new(world, [min.x as f64, min.y as f64, min.z as f64]);
+
+        for pos in BlockPos::iter_between(a, b) {
+            if let Some(block) = chunks.get_block_state(pos) {
+                if !block.is_air() {
+                    let offset = BlockPos::new(pos.x - min.x, pos.y - min.y, pos.z - min.z);
+                    contraption.blocks.push((offset, block));
+                    chunks.set_block_state(pos, BlockState::AIR);
+                }
+            }
+        }
+
+        contraption
+    }
+
+    /// Gets the world the contraption is located in.
+    pub fn world(&self) -> WorldId {
+        self.world
+    }
+
+    /// Sets the world the contraption is located in.
+    pub fn set_world(&mut self, world: WorldId) {
+        self.world = world;
+    }
+
+    /// Gets the position of the origin of the contraption. The block at
+    /// offset `(0, 0, 0)` has its lowest corner here.
+    pub fn position(&self) -> Vec3<f64> {
+        self.position
+    }
+
+    /// Sets the position of the origin of the contraption.
+    pub fn set_position(&mut self, position: impl Into<Vec3<f64>>) {
+        self.position = position.into();
+    }
+
+    /// Gets the rotation of the contraption around the vertical axis through
+    /// its origin, in degrees.
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    /// Sets the rotation of the contraption around the vertical axis through
+    /// its origin, in degrees. Like the yaw of an entity, a positive yaw turns
+    /// the contraption clockwise when viewed from above.
+    pub fn set_yaw(&mut self, yaw: f32) {
+        self.yaw = yaw;
+    }
+
+    /// Gets the blocks of the contraption by their offset from its origin.
+    pub fn blocks(&self) -> &[(BlockPos, BlockState)] {
+        &self.blocks
+    }
+
+    /// Sets the block at an offset from the origin. Setting a block to air
+    /// removes it from the contraption.
+    pub fn set_block(&mut self, offset: impl Into<BlockPos>, block: BlockState) {
+        let offset = offset.into();
+        match self.blocks.iter().position(|&(o, _)| o == offset) {
+            Some(idx) if block.is_air() => {
+                self.blocks.remove(idx);
+            }
+            Some(idx) => self.blocks[idx].1 = block,
+            None if block.is_air() => {}
+            None => self.blocks.push((offset, block)),
+        }
+    }
+
+    /// Gets the IDs of the falling blocks showing the blocks of the
+    /// contraption, in the same order as [`Self::blocks`].
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    /// Spawns, moves, and despawns the falling blocks of the contraption so
+    /// that they match its current state. Falling blocks which were removed
+    /// by other means or show the wrong block are spawned again.
+    pub fn update<C: Config>(&mut self, entities: &mut Entities<C>)
+    where
+        C::EntityState: Default,
+    {
+        for id in self
+            .entities
+            .drain(self.blocks.len().min(self.entities.len())..)
+        {
+            entities.remove(id);
+        }
+
+        let (sin, cos) = (self.yaw as f64).to_radians().sin_cos();
+
+        for (i, &(offset, block)) in self.blocks.iter().enumerate() {
+            let data = block.to_raw() as i32;
+
+            let entity = match self.entities.get(i).and_then(|&id| entities.get_mut(id)) {
+                Some(entity) if entity.object_data() == data => entity,
+                _ => {
+                    if let Some(&id) = self.entities.get(i) {
+                        entities.remove(id);
+                    }
+
+                    let (id, entity) =
+                        entities.insert(EntityKind::FallingBlock, C::EntityState::default());
+
+                    entity.set_object_data(data);
+                    if let TrackedData::FallingBlock(falling) = entity.data_mut() {
+                        falling.set_no_gravity(true);
+                    }
+
+                    if i < self.entities.len() {
+                        self.entities[i] = id;
+                    } else {
+                        self.entities.push(id);
+                    }
+
+                    entities.get_mut(id).unwrap()
+                }
+            };
+
+            // Falling blocks are positioned at the center of their bottom face.
+            let x = offset.x as f64 + 0.5;
+            let z = offset.z as f64 + 0.5;
+            let rotated = Vec3::new(x * cos - z * sin, offset.y as f64, x * sin + z * cos);
+
+            entity.set_world(self.world);
+            entity.set_position(self.position + rotated);
+        }
+    }
+
+    /// Despawns the falling blocks and places the blocks of the contraption in
+    /// `chunks`, which must belong to the world of the contraption.
+    ///
+    /// The origin is rounded to the nearest block corner and the yaw to the
+    /// nearest quarter turn. Blocks outside of loaded chunks are lost. Returns
+    /// the number of blocks placed.
+    pub fn settle<C: Config>(self, entities: &mut Entities<C>, chunks: &mut Chunks<C>) -> usize {
+        let origin = self.position.round().as_::<i32>();
+        let turns = (self.yaw / 90.0).round().rem_euclid(4.0) as u8;
+        let blocks = self.blocks.clone();
+        self.remove(entities);
+
+        blocks
+            .into_iter()
+            .filter(|&(offset, block)| {
+                let (mut x, mut z) = (offset.x, offset.z);
+                for _ in 0..turns {
+                    (x, z) = (-z - 1, x);
+                }
+
+                let pos = BlockPos::new(origin.x + x, origin.y + offset.y, origin.z + z);
+                chunks.set_block_state(pos, block)
+            })
+            .count()
+    }
+
+    /// Despawns the falling blocks of the contraption without placing its
+    /// blocks.
+    pub fn remove<C: Config>(self, entities: &mut Entities<C>) {
+        for id in self.entities {
+            entities.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_pos::BlockPos;
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::testing::connected_client;
+
+    #[test]
+    fn contraption_moves_and_settles() {
+        let (mut server, client) = connected_client();
+
+        let s = server.server_mut();
+        let (world_id, world) = s.worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state([2, 99, 2], BlockState::STONE);
+        world.chunks.set_block_state([3, 99, 2], BlockState::GLASS);
+
+        let mut contraption =
+            Contraption::capture(world_id, &mut world.chunks, [2, 99, 2], [3, 100, 3]);
+        assert_eq!(
+            contraption.blocks(),
+            [
+                (BlockPos::new(0, 0, 0), BlockState::STONE),
+                (BlockPos::new(1, 0, 0), BlockState::GLASS)
+            ]
+        );
+        assert_eq!(
+            world.chunks.get_block_state([2, 99, 2]),
+            Some(BlockState::AIR)
+        );
+
+        contraption.update(&mut s.entities);
+        server.tick();
+
+        let spawned: Vec<_> = client
+            .take_packets()
+            .into_iter()
+            .filter_map(|pkt| match pkt {
+                S2cPlayPacket::EntitySpawn(p) => Some((p.kind.0, p.data.0, p.position)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            spawned,
+            [
+                (
+                    EntityKind::FallingBlock as i32,
+                    BlockState::STONE.to_raw() as i32,
+                    Vec3::new(2.5, 99.0, 2.5)
+                ),
+                (
+                    EntityKind::FallingBlock as i32,
+                    BlockState::GLASS.to_raw() as i32,
+                    Vec3::new(3.5, 99.0, 2.5)
+                ),
+            ]
+        );
+
+        // A quarter turn clockwise points the contraption towards -X.
+        let s = server.server_mut();
+        contraption.set_position([5.1, 101.0, 5.0]);
+        contraption.set_yaw(91.0);
+        contraption.update(&mut s.entities);
+        let glass = s.entities.get(contraption.entities()[1]).unwrap();
+        assert!(glass.position().distance(Vec3::new(4.6, 101.0, 6.5)) < 0.05);
+
+        let entities = contraption.entities().to_vec();
+        let (_, world) = s.worlds.iter_mut().next().unwrap();
+        assert_eq!(contraption.settle(&mut s.entities, &mut world.chunks), 2);
+        assert_eq!(
+            world.chunks.get_block_state([4, 101, 5]),
+            Some(BlockState::STONE)
+        );
+        assert_eq!(
+            world.chunks.get_block_state([4, 101, 6]),
+            Some(BlockState::GLASS)
+        );
+        assert!(entities.iter().all(|&id| s.entities.get(id).is_none()));
+    }
+}
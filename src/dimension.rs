@@ -1,5 +1,9 @@
 //! Dimension configuration and identification.
 
+use std::ops::Range;
+
+use anyhow::ensure;
+
 use crate::ident;
 use crate::protocol::packets::s2c::play::DimensionType;
 
@@ -49,11 +53,12 @@ pub struct Dimension {
     /// * `min_y % 16 == 0`
     /// * `-2032 <= min_y <= 2016`
     pub min_y: i32,
-    /// The total height in which blocks can exist in this dimension.
+    /// The total height in which blocks can exist in this dimension. Chunks
+    /// in worlds of this dimension have `height / 16` sections.
     ///
     /// `height` must meet the following conditions:
     /// * `height % 16 == 0`
-    /// * `0 <= height <= 4064`
+    /// * `16 <= height <= 4064`
     /// * `min_y + height <= 2032`
     pub height: i32,
     // TODO: The following fields should be added if they can affect the
@@ -72,6 +77,49 @@ pub struct Dimension {
 }
 
 impl Dimension {
+    /// Returns the number of chunk sections in chunks of this dimension.
+    pub fn section_count(&self) -> usize {
+        (self.height / 16) as usize
+    }
+
+    /// Returns the range of Y coordinates in which blocks can exist in this
+    /// dimension.
+    pub fn y_range(&self) -> Range<i32> {
+        self.min_y..self.min_y + self.height
+    }
+
+    /// Checks the conditions documented on the fields of this dimension.
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.min_y % 16 == 0 && (-2032..=2016).contains(&self.min_y),
+            "invalid min_y of {}",
+            self.min_y
+        );
+
+        ensure!(
+            self.height % 16 == 0
+                && (16..=4064).contains(&self.height)
+                && self.min_y.saturating_add(self.height) <= 2032,
+            "invalid height of {} with a min_y of {}",
+            self.height,
+            self.min_y
+        );
+
+        ensure!(
+            (0.0..=1.0).contains(&self.ambient_light),
+            "ambient_light is out of range"
+        );
+
+        if let Some(fixed_time) = self.fixed_time {
+            ensure!(
+                (0..=24_000).contains(&fixed_time),
+                "fixed_time is out of range"
+            );
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn to_dimension_registry_item(&self) -> DimensionType {
         DimensionType {
             piglin_safe: true,
@@ -121,3 +169,33 @@ pub enum DimensionEffects {
     TheNether,
     TheEnd,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimension_heights() {
+        let dim = Dimension::default();
+        assert!(dim.validate().is_ok());
+        assert_eq!(dim.section_count(), 24);
+        assert_eq!(dim.y_range(), -64..320);
+
+        let tall = Dimension {
+            min_y: -2032,
+            height: 4064,
+            ..Dimension::default()
+        };
+        assert!(tall.validate().is_ok());
+        assert_eq!(tall.section_count(), 254);
+
+        for (min_y, height) in [(0, 0), (8, 256), (0, 264), (1024, 1024), (-2048, 256)] {
+            let dim = Dimension {
+                min_y,
+                height,
+                ..Dimension::default()
+            };
+            assert!(dim.validate().is_err(), "{min_y} {height}");
+        }
+    }
+}
@@ -1,25 +1,29 @@
 //! Entities in a world.
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::iter::FusedIterator;
 use std::num::NonZeroU32;
 
+use anyhow::{bail, Context};
 use bitfield_struct::bitfield;
 pub use data::{EntityKind, TrackedData};
 use rayon::iter::ParallelIterator;
 use uuid::Uuid;
 use vek::{Aabb, Vec3};
 
+use crate::component::Components;
 use crate::config::Config;
+use crate::nbt::{Compound, List, Value};
 use crate::protocol::packets::s2c::play::{
     EntitySpawn, EntityTrackerUpdate, ExperienceOrbSpawn, PlayerSpawn, S2cPlayPacket,
 };
-use crate::protocol::{ByteAngle, RawBytes, VarInt};
+use crate::protocol::{ByteAngle, Encode, RawBytes, VarInt};
 use crate::slab_versioned::{Key, VersionedSlab};
-use crate::util::aabb_from_bottom_and_size;
-use crate::world::WorldId;
-use crate::STANDARD_TPS;
+use crate::text::Text;
+use crate::util::{aabb_from_bottom_and_size, to_yaw_and_pitch};
+use crate::world::{WorldId, Worlds};
+use crate::{Ticks, STANDARD_TPS};
 
 pub mod data;
 pub mod types;
@@ -36,10 +40,34 @@ include!(concat!(env!("OUT_DIR"), "/entity_event.rs"));
 ///
 /// [`Player`]: crate::entity::data::Player
 /// [`PlayerList`]: crate::player_list::PlayerList
+///
+/// # Despawning
+///
+/// Entities can be removed automatically, which is useful for item drops and
+/// short-lived effects. An entity is removed after [`Config::update`] once the
+/// time set with [`Entity::set_despawn_after`] has passed, or once no client
+/// has it loaded if [`Entity::set_despawn_when_unviewed`] is enabled.
+///
+/// # Kinds
+///
+/// The entities of each [`EntityKind`] are indexed, so [`Self::iter_kind`]
+/// and [`Self::count_kind`] only visit entities of the requested kind.
+///
+/// # Tags
+///
+/// Entities can be given string tags such as `"arena:mob"` with
+/// [`Self::add_tag`]. The entities with a tag are indexed, so
+/// [`Self::with_tag`] finds them without visiting every entity.
+///
+/// [`Config::update`]: crate::config::Config::update
 pub struct Entities<C: Config> {
     slab: VersionedSlab<Entity<C>>,
     uuid_to_entity: HashMap<Uuid, EntityId>,
     network_id_to_entity: HashMap<NonZeroU32, u32>,
+    /// The entities of each kind. Kinds without entities are removed.
+    by_kind: HashMap<EntityKind, BTreeSet<EntityId>>,
+    /// The entities with each tag. Tags without entities are removed.
+    tagged: HashMap<String, BTreeSet<EntityId>>,
 }
 
 impl<C: Config> Entities<C> {
@@ -48,6 +76,8 @@ impl<C: Config> Entities<C> {
             slab: VersionedSlab::new(),
             uuid_to_entity: HashMap::new(),
             network_id_to_entity: HashMap::new(),
+            by_kind: HashMap::new(),
+            tagged: HashMap::new(),
         }
     }
 
@@ -78,21 +108,33 @@ impl<C: Config> Entities<C> {
             Entry::Vacant(ve) => {
                 let (k, e) = self.slab.insert(Entity {
                     state: data,
+                    components: Components::new(),
                     variants: TrackedData::new(kind),
                     events: Vec::new(),
-                    bits: EntityBits::new(),
+                    // New entities count as viewed until clients have had a chance to load
+                    // them.
+                    bits: EntityBits::new().with_teleported(true).with_viewed(true),
                     world: WorldId::NULL,
                     new_position: Vec3::default(),
                     old_position: Vec3::default(),
+                    movement_sync: MovementSync::default(),
                     yaw: 0.0,
                     pitch: 0.0,
                     head_yaw: 0.0,
+                    sent_angles: [ByteAngle(0); 3],
                     velocity: Vec3::default(),
                     uuid,
+                    visibility: Visibility::All,
+                    despawn_timer: None,
+                    tags: Vec::new(),
+                    spawn_hook: None,
+                    raw_tracked_data: BTreeMap::new(),
+                    object_data: 1,
                 });
 
                 // TODO check for overflowing version?
                 self.network_id_to_entity.insert(k.version(), k.index());
+                self.by_kind.entry(kind).or_default().insert(EntityId(k));
 
                 ve.insert(EntityId(k));
 
@@ -101,6 +143,161 @@ impl<C: Config> Entities<C> {
         }
     }
 
+    /// Spawns an entity from its NBT in the vanilla format, as found in the
+    /// entity lists of structure and region files. A reference to the entity
+    /// along with its ID is returned.
+    ///
+    /// The kind is read from the `id` tag, which is required. Missing tags
+    /// keep their defaults, and a random UUID is used if there is no `UUID`
+    /// tag. The following tags are read:
+    ///
+    /// - `UUID`, `Pos`, `Motion`, `Rotation` and `OnGround`.
+    /// - `CustomName`, `CustomNameVisible`, `Silent`, `NoGravity`, `Glowing`,
+    ///   `Air`, `TicksFrozen`, `Fire` and `HasVisualFire`.
+    /// - `Invisible`, `Small`, `ShowArms`, `NoBasePlate` and `Marker` of armor
+    ///   stands, and `Size` of slimes and magma cubes.
+    ///
+    /// Other tags are ignored. The entity is not in any world until
+    /// [`Entity::set_world`] is called.
+    ///
+    /// An error is returned if a tag has the wrong type, the kind is unknown,
+    /// or the UUID is already used by another entity.
+    pub fn create_from_nbt(
+        &mut self,
+        nbt: &Compound,
+        state: C::EntityState,
+    ) -> anyhow::Result<(EntityId, &mut Entity<C>)> {
+        let id = match nbt.get("id") {
+            Some(Value::String(id)) => id,
+            Some(_) => bail!("entity ID is not a string"),
+            None => bail!("missing entity ID"),
+        };
+        let name = id.strip_prefix("minecraft:").unwrap_or(id);
+        let kind = EntityKind::from_name(name).with_context(|| format!("unknown entity `{id}`"))?;
+
+        let uuid = match nbt.get("UUID") {
+            Some(Value::IntArray(ints)) if ints.len() == 4 => {
+                let mut bytes = [0; 16];
+                for (chunk, int) in bytes.chunks_exact_mut(4).zip(ints) {
+                    chunk.copy_from_slice(&int.to_be_bytes());
+                }
+                Uuid::from_bytes(bytes)
+            }
+            Some(_) => bail!("entity UUID is not an array of 4 ints"),
+            None => Uuid::from_bytes(rand::random()),
+        };
+
+        // Check all tags before the entity is inserted, so nothing is left
+        // behind on errors.
+        let pos = nbt_list::<3>(nbt, "Pos")?;
+        let motion = nbt_list::<3>(nbt, "Motion")?;
+        let rotation = nbt_list::<2>(nbt, "Rotation")?;
+        let custom_name = match nbt.get("CustomName") {
+            Some(Value::String(json)) => {
+                Some(serde_json::from_str::<Text>(json).context("invalid entity custom name")?)
+            }
+            Some(_) => bail!("entity custom name is not a string"),
+            None => None,
+        };
+        let flag = |name| nbt_int(nbt, name).map(|n| n.map(|n| n != 0));
+        let on_ground = flag("OnGround")?;
+        let name_visible = flag("CustomNameVisible")?;
+        let silent = flag("Silent")?;
+        let no_gravity = flag("NoGravity")?;
+        let glowing = flag("Glowing")?;
+        let visual_fire = flag("HasVisualFire")?;
+        let air = nbt_int(nbt, "Air")?;
+        let frozen_ticks = nbt_int(nbt, "TicksFrozen")?;
+        let fire = nbt_int(nbt, "Fire")?;
+        let invisible = flag("Invisible")?;
+        let small = flag("Small")?;
+        let show_arms = flag("ShowArms")?;
+        let no_base_plate = flag("NoBasePlate")?;
+        let marker = flag("Marker")?;
+        let size = nbt_int(nbt, "Size")?;
+
+        let (id, e) = self
+            .insert_with_uuid(kind, uuid, state)
+            .with_context(|| format!("entity UUID {uuid} is already in use"))?;
+
+        if let Some(pos) = pos {
+            e.set_position(pos);
+            e.old_position = e.new_position;
+        }
+        if let Some(motion) = motion {
+            // Motion is stored in blocks per tick.
+            e.set_velocity(Vec3::<f64>::from(motion).as_::<f32>() * STANDARD_TPS as f32);
+        }
+        if let Some([yaw, pitch]) = rotation {
+            e.set_yaw(yaw as f32);
+            e.set_pitch(pitch as f32);
+            e.set_head_yaw(yaw as f32);
+        }
+        if let Some(on_ground) = on_ground {
+            e.set_on_ground(on_ground);
+        }
+
+        let data = &mut e.variants;
+        if custom_name.is_some() {
+            data.set_custom_name(custom_name);
+        }
+        if let Some(name_visible) = name_visible {
+            data.set_name_visible(name_visible);
+        }
+        if let Some(silent) = silent {
+            data.set_silent(silent);
+        }
+        if let Some(no_gravity) = no_gravity {
+            data.set_no_gravity(no_gravity);
+        }
+        if let Some(glowing) = glowing {
+            data.set_glowing(glowing);
+        }
+        if let Some(air) = air {
+            data.set_air(air);
+        }
+        if let Some(frozen_ticks) = frozen_ticks {
+            data.set_frozen_ticks(frozen_ticks);
+        }
+        if fire.is_some_and(|f| f > 0) || visual_fire == Some(true) {
+            data.set_on_fire(true);
+        }
+
+        match data {
+            TrackedData::ArmorStand(stand) => {
+                if let Some(invisible) = invisible {
+                    stand.set_invisible(invisible);
+                }
+                if let Some(small) = small {
+                    stand.set_small(small);
+                }
+                if let Some(show_arms) = show_arms {
+                    stand.set_show_arms(show_arms);
+                }
+                if let Some(no_base_plate) = no_base_plate {
+                    stand.set_hide_base_plate(no_base_plate);
+                }
+                if let Some(marker) = marker {
+                    stand.set_marker(marker);
+                }
+            }
+            // The size is stored one less than the tracked size.
+            TrackedData::Slime(slime) => {
+                if let Some(size) = size {
+                    slime.set_slime_size(size + 1);
+                }
+            }
+            TrackedData::MagmaCube(cube) => {
+                if let Some(size) = size {
+                    cube.set_slime_size(size + 1);
+                }
+            }
+            _ => {}
+        }
+
+        Ok((id, e))
+    }
+
     /// Removes an entity from the server.
     ///
     /// If the given entity ID is valid, the entity's `EntityState` is returned
@@ -116,6 +313,9 @@ impl<C: Config> Entities<C> {
                 .remove(&entity.0.version())
                 .expect("network ID should have been in the network ID map");
 
+            remove_from_kind(&mut self.by_kind, entity, e.kind());
+            untag(&mut self.tagged, entity, &e.tags);
+
             e.state
         })
     }
@@ -136,6 +336,9 @@ impl<C: Config> Entities<C> {
                     .remove(&k.version())
                     .expect("network ID should have been in the network ID map");
 
+                remove_from_kind(&mut self.by_kind, EntityId(k), v.kind());
+                untag(&mut self.tagged, EntityId(k), &v.tags);
+
                 false
             }
         });
@@ -173,6 +376,81 @@ impl<C: Config> Entities<C> {
         self.slab.get_mut(entity.0)
     }
 
+    /// Returns an iterator over the entities of the given kind, ordered by
+    /// [`EntityId`].
+    ///
+    /// To modify the entities, collect their IDs and use [`Self::get_mut`].
+    pub fn iter_kind(
+        &self,
+        kind: EntityKind,
+    ) -> impl FusedIterator<Item = (EntityId, &Entity<C>)> + Clone + '_ {
+        self.by_kind.get(&kind).into_iter().flatten().map(|&id| {
+            (
+                id,
+                self.slab.get(id.0).expect("indexed entity should exist"),
+            )
+        })
+    }
+
+    /// Returns the number of entities of the given kind.
+    pub fn count_kind(&self, kind: EntityKind) -> usize {
+        self.by_kind.get(&kind).map_or(0, BTreeSet::len)
+    }
+
+    /// Adds a tag to the entity with the given [`EntityId`]. Returns `true`
+    /// if the entity exists and did not have the tag already.
+    pub fn add_tag(&mut self, entity: EntityId, tag: impl Into<String>) -> bool {
+        let Some(e) = self.slab.get_mut(entity.0) else {
+            return false;
+        };
+
+        let tag = tag.into();
+        if e.has_tag(&tag) {
+            return false;
+        }
+
+        e.tags.push(tag.clone());
+        self.tagged.entry(tag).or_default().insert(entity);
+        true
+    }
+
+    /// Removes a tag from the entity with the given [`EntityId`]. Returns
+    /// `true` if the entity had the tag.
+    pub fn remove_tag(&mut self, entity: EntityId, tag: &str) -> bool {
+        let Some(e) = self.slab.get_mut(entity.0) else {
+            return false;
+        };
+
+        let Some(idx) = e.tags.iter().position(|t| t == tag) else {
+            return false;
+        };
+
+        let tag = e.tags.swap_remove(idx);
+        untag(&mut self.tagged, entity, &[tag]);
+        true
+    }
+
+    /// Returns an iterator over the entities with the given tag, ordered by
+    /// [`EntityId`].
+    ///
+    /// To modify the entities, collect their IDs and use [`Self::get_mut`].
+    pub fn with_tag<'a>(
+        &'a self,
+        tag: &str,
+    ) -> impl FusedIterator<Item = (EntityId, &'a Entity<C>)> + Clone + 'a {
+        self.tagged.get(tag).into_iter().flatten().map(|&id| {
+            (
+                id,
+                self.slab.get(id.0).expect("indexed entity should exist"),
+            )
+        })
+    }
+
+    /// Returns the number of entities with the given tag.
+    pub fn count_with_tag(&self, tag: &str) -> usize {
+        self.tagged.get(tag).map_or(0, BTreeSet::len)
+    }
+
     pub(crate) fn get_with_network_id(&self, network_id: i32) -> Option<EntityId> {
         let version = NonZeroU32::new(network_id as u32)?;
         let index = *self.network_id_to_entity.get(&version)?;
@@ -209,15 +487,112 @@ impl<C: Config> Entities<C> {
         self.slab.par_iter_mut().map(|(k, v)| (EntityId(k), v))
     }
 
-    pub(crate) fn update(&mut self) {
-        for (_, e) in self.iter_mut() {
-            e.old_position = e.new_position;
+    /// Removes the entities whose despawn rules say they should be removed.
+    pub(crate) fn despawn(&mut self) {
+        self.retain(|_, e| {
+            e.despawn_timer.is_none_or(|t| t > 0)
+                && (e.bits.viewed() || !e.bits.despawn_when_unviewed())
+        });
+    }
+
+    /// Rebuilds the set of entities in each world. Entities in worlds that do
+    /// not exist are moved to [`WorldId::NULL`].
+    pub(crate) fn update_worlds(&mut self, worlds: &mut Worlds<C>) {
+        for (_, world) in worlds.iter_mut() {
+            world.entities.clear();
+        }
+
+        for (id, e) in self.iter_mut() {
+            match worlds.get_mut(e.world) {
+                Some(world) => {
+                    world.entities.insert(id);
+                }
+                None => e.set_world(WorldId::NULL),
+            }
+        }
+    }
+
+    /// `viewed` contains the entities loaded by any client this tick.
+    pub(crate) fn update(&mut self, viewed: &HashSet<EntityId>) {
+        for (id, e) in self.iter_mut() {
+            e.bits.set_viewed(viewed.contains(&id));
+
+            if let Some(timer) = &mut e.despawn_timer {
+                *timer = (*timer - 1).max(0);
+            }
+
+            e.old_position = e.synced_position();
             e.variants.clear_modifications();
+            for raw in e.raw_tracked_data.values_mut() {
+                raw.modified = false;
+            }
             e.events.clear();
 
+            if e.bits.yaw_or_pitch_modified() {
+                e.sent_angles[0] = ByteAngle::from_degrees(e.yaw);
+                e.sent_angles[1] = ByteAngle::from_degrees(e.pitch);
+            }
+            if e.bits.head_yaw_modified() {
+                e.sent_angles[2] = ByteAngle::from_degrees(e.head_yaw);
+            }
+
             e.bits.set_yaw_or_pitch_modified(false);
             e.bits.set_head_yaw_modified(false);
             e.bits.set_velocity_modified(false);
+            e.bits.set_teleported(false);
+        }
+    }
+}
+
+/// Reads the list of `N` doubles or floats called `name` from `nbt`.
+fn nbt_list<const N: usize>(nbt: &Compound, name: &str) -> anyhow::Result<Option<[f64; N]>> {
+    let items: Vec<f64> = match nbt.get(name) {
+        Some(Value::List(List::Double(l))) => l.clone(),
+        Some(Value::List(List::Float(l))) => l.iter().map(|&v| v.into()).collect(),
+        None => return Ok(None),
+        Some(_) => bail!("entity tag `{name}` is not a list of doubles or floats"),
+    };
+
+    items
+        .try_into()
+        .map(Some)
+        .map_err(|_| anyhow::anyhow!("entity tag `{name}` does not have {N} elements"))
+}
+
+/// Reads the integer called `name` from `nbt`, which may be of any integer
+/// type.
+fn nbt_int(nbt: &Compound, name: &str) -> anyhow::Result<Option<i32>> {
+    Ok(match nbt.get(name) {
+        Some(Value::Byte(n)) => Some((*n).into()),
+        Some(Value::Short(n)) => Some((*n).into()),
+        Some(Value::Int(n)) => Some(*n),
+        None => None,
+        Some(_) => bail!("entity tag `{name}` is not an integer"),
+    })
+}
+
+/// Removes `entity` from the index of its kind.
+fn remove_from_kind(
+    by_kind: &mut HashMap<EntityKind, BTreeSet<EntityId>>,
+    entity: EntityId,
+    kind: EntityKind,
+) {
+    if let Some(entities) = by_kind.get_mut(&kind) {
+        entities.remove(&entity);
+        if entities.is_empty() {
+            by_kind.remove(&kind);
+        }
+    }
+}
+
+/// Removes `entity` from the index of each of `tags`.
+fn untag(tagged: &mut HashMap<String, BTreeSet<EntityId>>, entity: EntityId, tags: &[String]) {
+    for tag in tags {
+        if let Some(entities) = tagged.get_mut(tag) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                tagged.remove(tag);
+            }
         }
     }
 }
@@ -230,7 +605,7 @@ impl<C: Config> Entities<C> {
 ///
 /// The [`Ord`] instance on this type is correct but otherwise unspecified. This
 /// is useful for storing IDs in containers such as
-/// [`BTreeMap`](std::collections::BTreeMap).
+/// [`BTreeMap`].
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
 pub struct EntityId(Key);
 
@@ -255,17 +630,68 @@ impl EntityId {
 pub struct Entity<C: Config> {
     /// Custom data.
     pub state: C::EntityState,
+    /// State attached by independent modules. See [`component`](crate::component).
+    pub components: Components,
     variants: TrackedData,
     bits: EntityBits,
     events: Vec<EntityEvent>,
     world: WorldId,
     new_position: Vec3<f64>,
+    /// The position of this entity as it was last sent to clients.
     old_position: Vec3<f64>,
+    movement_sync: MovementSync,
     yaw: f32,
     pitch: f32,
     head_yaw: f32,
+    /// The yaw, pitch, and head yaw of this entity as they were last sent to
+    /// clients. Every client viewing the entity has been sent the same angles,
+    /// so this is tracked once per entity rather than per client.
+    sent_angles: [ByteAngle; 3],
     velocity: Vec3<f32>,
     uuid: Uuid,
+    visibility: Visibility,
+    /// The number of ticks until this entity is removed.
+    despawn_timer: Option<Ticks>,
+    /// The tags of this entity, which are indexed in [`Entities`].
+    tags: Vec<String>,
+    spawn_hook: Option<Box<SpawnHook>>,
+    /// Tracked data set with [`Entity::set_raw_tracked_data`], by index.
+    raw_tracked_data: BTreeMap<u8, RawTrackedData>,
+    object_data: i32,
+}
+
+/// A tracked data value set with [`Entity::set_raw_tracked_data`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RawTrackedData {
+    /// The ID of the type of the value.
+    pub type_id: i32,
+    /// The value encoded in the protocol format of its type.
+    pub data: Vec<u8>,
+    /// If the value was set this tick.
+    modified: bool,
+}
+
+type SpawnHook = dyn Fn(Uuid, &mut SpawnAppearance) -> bool + Send + Sync;
+
+/// How an entity is shown to a client when it is spawned for that client.
+/// This can be changed per client with [`Entity::set_spawn_hook`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpawnAppearance {
+    /// The kind of entity the client spawns.
+    pub kind: EntityKind,
+    /// The UUID of the spawned entity. The skin of a player entity is taken
+    /// from the player list entry with this UUID.
+    pub uuid: Uuid,
+    /// The position the entity is spawned at.
+    pub position: Vec3<f64>,
+    /// The yaw of the entity in degrees.
+    pub yaw: f32,
+    /// The pitch of the entity in degrees.
+    pub pitch: f32,
+    /// The head yaw of the entity in degrees.
+    pub head_yaw: f32,
+    /// The velocity of the entity in meters per second.
+    pub velocity: Vec3<f32>,
 }
 
 #[bitfield(u8)]
@@ -274,8 +700,92 @@ pub(crate) struct EntityBits {
     pub head_yaw_modified: bool,
     pub velocity_modified: bool,
     pub on_ground: bool,
-    #[bits(4)]
-    _pad: u8,
+    /// If the entity was created or changed worlds this tick, so its
+    /// movement should not be interpolated.
+    pub teleported: bool,
+    /// If the entity belongs to a vanished client.
+    pub vanished: bool,
+    pub despawn_when_unviewed: bool,
+    /// If a client had the entity loaded at the end of the last tick, or the
+    /// entity was created since.
+    pub viewed: bool,
+}
+
+/// Controls how the movement of an entity is sent to clients, obtained with
+/// [`Entity::movement_sync`].
+///
+/// Clients are sent the change in position of an entity every tick as a
+/// relative movement, which is limited to just under 8 blocks along each axis.
+/// Movements that are too large are sent as a teleport instead, which makes
+/// the entity jump to its new position on clients.
+///
+/// The default settings teleport the entity whenever it moves 8 or more blocks
+/// along any axis. To make fast entities move smoothly, the teleport threshold
+/// can be raised along with a lower `max_step`. Large movements are then
+/// interpolated over multiple ticks, at the cost of clients seeing the entity
+/// behind its actual position until they catch up.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MovementSync {
+    /// Movements of at least this many blocks along any axis are sent as a
+    /// teleport.
+    pub teleport_threshold: f64,
+    /// The largest distance in blocks along any axis clients are moved by in
+    /// a single tick. Movements below the teleport threshold which exceed
+    /// this are spread over multiple ticks. Values above
+    /// [`MAX_RELATIVE_MOVE`] are treated as [`MAX_RELATIVE_MOVE`].
+    pub max_step: f64,
+}
+
+/// The largest change in position along any axis that can be sent to clients
+/// as a relative movement in a single tick.
+pub const MAX_RELATIVE_MOVE: f64 = i16::MAX as f64 / 4096.0;
+
+impl Default for MovementSync {
+    fn default() -> Self {
+        Self {
+            teleport_threshold: 8.0,
+            max_step: MAX_RELATIVE_MOVE,
+        }
+    }
+}
+
+/// Selects the clients an entity is visible to, obtained with
+/// [`Entity::visibility`].
+///
+/// Clients are identified by their UUID. An entity is only ever visible to
+/// clients in the same world within their view distance.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub enum Visibility {
+    /// The entity is visible to all clients.
+    #[default]
+    All,
+    /// The entity is only visible to the clients with the given UUIDs.
+    Only(HashSet<Uuid>),
+    /// The entity is visible to all clients except those with the given
+    /// UUIDs.
+    Except(HashSet<Uuid>),
+}
+
+impl Visibility {
+    /// Returns whether the client with the given UUID can see the entity.
+    pub fn includes(&self, client: Uuid) -> bool {
+        match self {
+            Visibility::All => true,
+            Visibility::Only(uuids) => uuids.contains(&client),
+            Visibility::Except(uuids) => !uuids.contains(&client),
+        }
+    }
+}
+
+/// How the movement of an entity in the current tick is sent to clients.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum MovementUpdate {
+    /// The position clients see does not change.
+    None,
+    /// Clients are sent a relative movement by the given delta.
+    Relative(Vec3<f64>),
+    /// Clients are sent the current position of the entity.
+    Teleport,
 }
 
 impl<C: Config> Entity<C> {
@@ -289,6 +799,9 @@ impl<C: Config> Entity<C> {
     }
 
     /// Returns an exclusive reference to this entity's tracked data.
+    ///
+    /// The data must not be replaced with the data of a different
+    /// [`EntityKind`].
     pub fn data_mut(&mut self) -> &mut TrackedData {
         &mut self.variants
     }
@@ -317,8 +830,30 @@ impl<C: Config> Entity<C> {
     }
 
     /// Sets the world this entity is located in.
+    ///
+    /// If the world does not exist when the entity is added to the entities of
+    /// its world, which happens once per tick as described in
+    /// [`World::entities`], the entity is moved to
+    /// [`WorldId::NULL`](crate::world::WorldId::NULL) instead and is not part
+    /// of any world.
+    ///
+    /// [`World::entities`]: crate::world::World::entities
     pub fn set_world(&mut self, world: WorldId) {
-        self.world = world;
+        if self.world != world {
+            self.world = world;
+            self.bits.set_teleported(true);
+        }
+    }
+
+    /// Moves this entity to the position `pos` in another world.
+    ///
+    /// Clients in the old world which have loaded the entity destroy it, and
+    /// clients in the new world within range spawn it at `pos`, both during
+    /// the same tick. The entity is never shown at its old position in the new
+    /// world or the other way around.
+    pub fn move_to_world(&mut self, world: WorldId, pos: impl Into<Vec3<f64>>) {
+        self.set_world(world);
+        self.set_position(pos);
     }
 
     /// Gets the position of this entity in the world it inhabits.
@@ -332,15 +867,120 @@ impl<C: Config> Entity<C> {
     /// Sets the position of this entity in the world it inhabits.
     ///
     /// The position of an entity is located on the botton of its
-    /// hitbox and not the center.
+    /// hitbox and not the center. How the movement is shown to clients depends
+    /// on the entity's [`MovementSync`].
     pub fn set_position(&mut self, pos: impl Into<Vec3<f64>>) {
         self.new_position = pos.into();
     }
 
-    /// Returns the position of this entity as it existed at the end of the
-    /// previous tick.
-    pub(crate) fn old_position(&self) -> Vec3<f64> {
-        self.old_position
+    /// Sets the yaw, pitch, and head yaw of this entity so that it faces the
+    /// point `target`.
+    ///
+    /// The direction is measured from the entity's eyes, which are assumed to
+    /// be at 85% of the height of its [hitbox](Self::hitbox). Nothing happens
+    /// if `target` is at the eyes.
+    pub fn face_towards(&mut self, target: impl Into<Vec3<f64>>) {
+        let hitbox = self.hitbox();
+        let eyes = Vec3::new(
+            self.new_position.x,
+            self.new_position.y + (hitbox.max.y - hitbox.min.y) * 0.85,
+            self.new_position.z,
+        );
+
+        let dir = target.into() - eyes;
+        if dir == Vec3::zero() {
+            return;
+        }
+
+        let (yaw, pitch) = to_yaw_and_pitch(dir.normalized());
+        self.set_yaw(yaw as f32);
+        self.set_pitch(pitch as f32);
+        self.set_head_yaw(yaw as f32);
+    }
+
+    /// Gets the clients this entity is visible to.
+    pub fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+
+    /// Sets the clients this entity is visible to. Clients which can no longer
+    /// see the entity have it despawned on the next tick.
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    /// Returns whether the client with the given UUID can see this entity if
+    /// it is in range. Entities of [vanished] clients are not visible to
+    /// anyone.
+    ///
+    /// [vanished]: crate::client::Client::set_vanished
+    pub fn is_visible_to(&self, client: Uuid) -> bool {
+        !self.bits.vanished() && self.visibility.includes(client)
+    }
+
+    pub(crate) fn set_vanished(&mut self, vanished: bool) {
+        self.bits.set_vanished(vanished);
+    }
+
+    /// Gets the settings controlling how the movement of this entity is sent
+    /// to clients.
+    pub fn movement_sync(&self) -> MovementSync {
+        self.movement_sync
+    }
+
+    /// Sets how the movement of this entity is sent to clients.
+    pub fn set_movement_sync(&mut self, sync: MovementSync) {
+        self.movement_sync = sync;
+    }
+
+    /// Returns whether the yaw or pitch of this entity changed enough this tick
+    /// to be visible to clients, which only see angles in steps of 1/256 of a
+    /// turn.
+    pub(crate) fn rotation_changed(&self) -> bool {
+        self.bits.yaw_or_pitch_modified()
+            && (ByteAngle::from_degrees(self.yaw) != self.sent_angles[0]
+                || ByteAngle::from_degrees(self.pitch) != self.sent_angles[1])
+    }
+
+    /// Like [`Self::rotation_changed`], but for the head yaw.
+    pub(crate) fn head_yaw_changed(&self) -> bool {
+        self.bits.head_yaw_modified()
+            && ByteAngle::from_degrees(self.head_yaw) != self.sent_angles[2]
+    }
+
+    /// Returns how the change in position of this entity since clients were
+    /// last updated should be sent to clients this tick.
+    pub(crate) fn movement_update(&self) -> MovementUpdate {
+        let delta = self.new_position - self.old_position;
+
+        if delta == Vec3::zero() {
+            return MovementUpdate::None;
+        }
+
+        let max_delta = delta.map(f64::abs).reduce_partial_max();
+
+        if self.bits.teleported() || max_delta >= self.movement_sync.teleport_threshold {
+            return MovementUpdate::Teleport;
+        }
+
+        let max_step = self.movement_sync.max_step.min(MAX_RELATIVE_MOVE);
+
+        if max_delta <= max_step {
+            MovementUpdate::Relative(delta)
+        } else if max_step > 0.0 {
+            MovementUpdate::Relative(delta * (max_step / max_delta))
+        } else {
+            MovementUpdate::Teleport
+        }
+    }
+
+    /// Returns the position clients see this entity at once they are updated
+    /// this tick.
+    pub(crate) fn synced_position(&self) -> Vec3<f64> {
+        match self.movement_update() {
+            MovementUpdate::None | MovementUpdate::Teleport => self.new_position,
+            MovementUpdate::Relative(delta) => self.old_position + delta,
+        }
     }
 
     /// Gets the yaw of this entity in degrees.
@@ -412,6 +1052,165 @@ impl<C: Config> Entity<C> {
         self.uuid
     }
 
+    /// Returns an iterator over the tags of this entity in an unspecified
+    /// order. Tags are changed with [`Entities::add_tag`] and
+    /// [`Entities::remove_tag`].
+    pub fn tags(&self) -> impl ExactSizeIterator<Item = &str> + Clone + '_ {
+        self.tags.iter().map(String::as_str)
+    }
+
+    /// Returns `true` if this entity has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Sets a hook which is called whenever this entity is about to be spawned
+    /// for a client, replacing any previous hook.
+    ///
+    /// The hook is given the UUID of the client and the appearance the entity
+    /// is spawned with, which it can modify for that client. If the hook
+    /// returns `false`, the entity is not spawned for the client. The hook is
+    /// then called again on the following ticks for as long as the entity is
+    /// in range of the client.
+    ///
+    /// If the hook changes the [`EntityKind`], the client is not sent any
+    /// tracked data of the entity, since it belongs to the actual kind. Player
+    /// entities are only shown by clients if their UUID is in the player list.
+    pub fn set_spawn_hook(
+        &mut self,
+        hook: impl Fn(Uuid, &mut SpawnAppearance) -> bool + Send + Sync + 'static,
+    ) {
+        self.spawn_hook = Some(Box::new(hook));
+    }
+
+    /// Removes the hook set with [`Self::set_spawn_hook`]. Clients which
+    /// already spawned the entity keep its current appearance.
+    pub fn clear_spawn_hook(&mut self) {
+        self.spawn_hook = None;
+    }
+
+    /// Returns `true` if this entity has a hook set with
+    /// [`Self::set_spawn_hook`].
+    pub fn has_spawn_hook(&self) -> bool {
+        self.spawn_hook.is_some()
+    }
+
+    /// Gets the data sent to clients when this entity is spawned. See
+    /// [`Self::set_object_data`].
+    pub fn object_data(&self) -> i32 {
+        self.object_data
+    }
+
+    /// Sets the data sent to clients when this entity is spawned, whose
+    /// meaning depends on the kind of entity. For example, it is the
+    /// [block state](crate::block::BlockState::to_raw) shown by a
+    /// [`EntityKind::FallingBlock`]. The default is `1`.
+    ///
+    /// Clients which already spawned the entity do not see the change.
+    pub fn set_object_data(&mut self, data: i32) {
+        self.object_data = data;
+    }
+
+    /// Gets the number of ticks left until this entity is removed, or `None`
+    /// if it is not removed after a set time.
+    pub fn despawn_after(&self) -> Option<Ticks> {
+        self.despawn_timer
+    }
+
+    /// Removes this entity once the given number of ticks have passed, counted
+    /// from the current tick. With 0 ticks, the entity is removed at the end of
+    /// the current [`Config::update`]. `None` keeps the entity until it is
+    /// removed by other means.
+    ///
+    /// [`Config::update`]: crate::config::Config::update
+    pub fn set_despawn_after(&mut self, ticks: impl Into<Option<Ticks>>) {
+        self.despawn_timer = ticks.into().map(|t| t.max(0));
+    }
+
+    /// Gets whether this entity is removed once no client has it loaded.
+    pub fn despawns_when_unviewed(&self) -> bool {
+        self.bits.despawn_when_unviewed()
+    }
+
+    /// Sets whether this entity is removed once no client has it loaded,
+    /// because it is out of view of every client or hidden from them. This is
+    /// `false` by default.
+    ///
+    /// Entities are checked after every [`Config::update`]. Entities created
+    /// since the clients were last updated are kept until the next check.
+    ///
+    /// [`Config::update`]: crate::config::Config::update
+    pub fn set_despawn_when_unviewed(&mut self, despawn: bool) {
+        self.bits.set_despawn_when_unviewed(despawn);
+    }
+
+    /// Sets the tracked data at `index` to a raw value, for tracked data which
+    /// has no typed accessor in [`TrackedData`] yet.
+    ///
+    /// `type_id` is the protocol ID of the tracked data type and `data` is
+    /// the value encoded in the format of that type. The value is sent to
+    /// clients after the typed tracked data, so it overrides a typed field
+    /// with the same index. Sending a value of the wrong type for an index
+    /// disconnects clients.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is `0xff`, which terminates tracked data.
+    pub fn set_raw_tracked_data(&mut self, index: u8, type_id: i32, data: impl Into<Vec<u8>>) {
+        assert_ne!(index, 0xff, "tracked data index 0xff is reserved");
+
+        self.raw_tracked_data.insert(
+            index,
+            RawTrackedData {
+                type_id,
+                data: data.into(),
+                modified: true,
+            },
+        );
+    }
+
+    /// Gets the raw tracked data at `index` set with
+    /// [`Self::set_raw_tracked_data`].
+    pub fn raw_tracked_data(&self, index: u8) -> Option<&RawTrackedData> {
+        self.raw_tracked_data.get(&index)
+    }
+
+    /// Removes the raw tracked data at `index` and returns it. Clients which
+    /// have spawned the entity keep the last value they were sent.
+    pub fn remove_raw_tracked_data(&mut self, index: u8) -> Option<RawTrackedData> {
+        self.raw_tracked_data.remove(&index)
+    }
+
+    /// Appends the raw tracked data selected by `include` to typed tracked
+    /// data terminated by `0xff`.
+    fn with_raw_tracked_data(
+        &self,
+        typed: Option<Vec<u8>>,
+        include: impl Fn(&RawTrackedData) -> bool,
+    ) -> Option<Vec<u8>> {
+        let mut raw = self
+            .raw_tracked_data
+            .iter()
+            .filter(|(_, v)| include(v))
+            .peekable();
+
+        if raw.peek().is_none() {
+            return typed;
+        }
+
+        let mut data = typed.unwrap_or_default();
+        data.pop();
+        for (&index, value) in raw {
+            data.push(index);
+            VarInt(value.type_id)
+                .encode(&mut data)
+                .expect("encoding into a vec should not fail");
+            data.extend_from_slice(&value.data);
+        }
+        data.push(0xff);
+        Some(data)
+    }
+
     /// Returns the hitbox of this entity.
     ///
     /// The hitbox describes the space that an entity occupies. Clients interact
@@ -572,8 +1371,7 @@ impl<C: Config> Entity<C> {
         &self,
         this_id: EntityId,
     ) -> Option<EntityTrackerUpdate> {
-        self.variants
-            .initial_tracked_data()
+        self.with_raw_tracked_data(self.variants.initial_tracked_data(), |_| true)
             .map(|meta| EntityTrackerUpdate {
                 entity_id: VarInt(this_id.to_network_id()),
                 metadata: RawBytes(meta),
@@ -588,43 +1386,66 @@ impl<C: Config> Entity<C> {
         &self,
         this_id: EntityId,
     ) -> Option<EntityTrackerUpdate> {
-        self.variants
-            .updated_tracked_data()
+        self.with_raw_tracked_data(self.variants.updated_tracked_data(), |v| v.modified)
             .map(|meta| EntityTrackerUpdate {
                 entity_id: VarInt(this_id.to_network_id()),
                 metadata: RawBytes(meta),
             })
     }
 
-    pub(crate) fn spawn_packet(&self, this_id: EntityId) -> Option<EntitySpawnPacket> {
-        match &self.variants {
-            TrackedData::Marker(_) => None,
-            TrackedData::ExperienceOrb(_) => {
-                Some(EntitySpawnPacket::ExperienceOrb(ExperienceOrbSpawn {
-                    entity_id: VarInt(this_id.to_network_id()),
-                    position: self.new_position,
-                    count: 0, // TODO
-                }))
+    /// Returns the packet spawning this entity for the client with the UUID
+    /// `viewer` along with the appearance it was built from, or `None` if the
+    /// entity should not be spawned for the client.
+    pub(crate) fn spawn_packet(
+        &self,
+        this_id: EntityId,
+        viewer: Uuid,
+    ) -> Option<(EntitySpawnPacket, SpawnAppearance)> {
+        let mut appearance = SpawnAppearance {
+            kind: self.kind(),
+            uuid: self.uuid,
+            position: self.synced_position(),
+            yaw: self.yaw,
+            pitch: self.pitch,
+            head_yaw: self.head_yaw,
+            velocity: self.velocity,
+        };
+
+        if let Some(hook) = &self.spawn_hook {
+            if !hook(viewer, &mut appearance) {
+                return None;
             }
-            TrackedData::Player(_) => Some(EntitySpawnPacket::Player(PlayerSpawn {
+        }
+
+        let a = &appearance;
+        let pkt = match a.kind {
+            EntityKind::Marker => return None,
+            EntityKind::ExperienceOrb => EntitySpawnPacket::ExperienceOrb(ExperienceOrbSpawn {
                 entity_id: VarInt(this_id.to_network_id()),
-                player_uuid: self.uuid,
-                position: self.new_position,
-                yaw: ByteAngle::from_degrees(self.yaw),
-                pitch: ByteAngle::from_degrees(self.pitch),
-            })),
-            _ => Some(EntitySpawnPacket::Entity(EntitySpawn {
+                position: a.position,
+                count: 0, // TODO
+            }),
+            EntityKind::Player => EntitySpawnPacket::Player(PlayerSpawn {
                 entity_id: VarInt(this_id.to_network_id()),
-                object_uuid: self.uuid,
-                kind: VarInt(self.kind() as i32),
-                position: self.new_position,
-                pitch: ByteAngle::from_degrees(self.pitch),
-                yaw: ByteAngle::from_degrees(self.yaw),
-                head_yaw: ByteAngle::from_degrees(self.head_yaw),
-                data: VarInt(1), // TODO
-                velocity: velocity_to_packet_units(self.velocity),
-            })),
-        }
+                player_uuid: a.uuid,
+                position: a.position,
+                yaw: ByteAngle::from_degrees(a.yaw),
+                pitch: ByteAngle::from_degrees(a.pitch),
+            }),
+            kind => EntitySpawnPacket::Entity(EntitySpawn {
+                entity_id: VarInt(this_id.to_network_id()),
+                object_uuid: a.uuid,
+                kind: VarInt(kind as i32),
+                position: a.position,
+                pitch: ByteAngle::from_degrees(a.pitch),
+                yaw: ByteAngle::from_degrees(a.yaw),
+                head_yaw: ByteAngle::from_degrees(a.head_yaw),
+                data: VarInt(self.object_data),
+                velocity: velocity_to_packet_units(a.velocity),
+            }),
+        };
+
+        Some((pkt, appearance))
     }
 }
 
@@ -648,3 +1469,432 @@ impl From<EntitySpawnPacket> for S2cPlayPacket {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dimension::DimensionId;
+    use crate::nbt::snbt;
+    use crate::testing::{connected_client, test_server, Game, MockClient, TestServer};
+
+    #[test]
+    fn entity_movement_interpolated() {
+        let mut server = test_server();
+
+        let client = server.connect("tester");
+        server.tick();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let (entity_id, entity) = s.entities.insert(EntityKind::Pig, ());
+        entity.set_world(world_id);
+        entity.set_position([0.5, 100.0, 4.5]);
+        entity.set_movement_sync(MovementSync {
+            teleport_threshold: 16.0,
+            max_step: 2.0,
+        });
+        server.tick();
+        client.take_packets();
+
+        server
+            .server_mut()
+            .entities
+            .get_mut(entity_id)
+            .unwrap()
+            .set_position([5.5, 100.0, 4.5]);
+
+        let mut deltas = Vec::new();
+        for _ in 0..4 {
+            server.tick();
+            for pkt in client.take_packets() {
+                match pkt {
+                    S2cPlayPacket::MoveRelative(p) => deltas.push(p.delta),
+                    S2cPlayPacket::EntityPosition(_) => panic!("entity was teleported"),
+                    _ => {}
+                }
+            }
+        }
+        assert_eq!(
+            deltas,
+            [
+                Vec3::new(2 * 4096, 0, 0),
+                Vec3::new(2 * 4096, 0, 0),
+                Vec3::new(4096, 0, 0)
+            ]
+        );
+
+        server
+            .server_mut()
+            .entities
+            .get_mut(entity_id)
+            .unwrap()
+            .set_position([25.5, 100.0, 4.5]);
+        server.tick();
+        assert!(client
+            .take_packets()
+            .iter()
+            .any(|pkt| matches!(pkt, S2cPlayPacket::EntityPosition(_))));
+    }
+
+    #[test]
+    fn entity_updates_coalesced() {
+        let mut server = test_server();
+
+        let client = server.connect("tester");
+        server.tick();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let (entity_id, entity) = s.entities.insert(EntityKind::Pig, ());
+        entity.set_world(world_id);
+        entity.set_position([0.5, 100.0, 4.5]);
+        server.tick();
+        client.take_packets();
+
+        let mut update = |f: &dyn Fn(&mut Entity<Game>)| {
+            f(server.server_mut().entities.get_mut(entity_id).unwrap());
+            server.tick();
+            client.take_packets()
+        };
+
+        // Changes smaller than a byte angle are not sent.
+        assert!(update(&|e| {
+            e.set_yaw(0.5);
+            e.set_head_yaw(0.5);
+        })
+        .is_empty());
+
+        let pkts = update(&|e| {
+            e.set_yaw(90.0);
+            e.set_head_yaw(90.0);
+        });
+        assert!(matches!(
+            pkts.as_slice(),
+            [S2cPlayPacket::Rotate(_), S2cPlayPacket::EntitySetHeadYaw(_)]
+        ));
+
+        // Teleports carry the rotation.
+        let pkts = update(&|e| {
+            e.set_yaw(180.0);
+            e.set_position([20.5, 100.0, 4.5]);
+        });
+        assert!(matches!(
+            pkts.as_slice(),
+            [S2cPlayPacket::EntityPosition(_)]
+        ));
+    }
+
+    #[test]
+    fn entities_despawned_by_rules() {
+        let mut server = test_server();
+
+        let _client = server.connect("tester");
+        server.tick();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let mut spawn = |pos: [f64; 3], f: fn(&mut Entity<Game>)| {
+            let (id, e) = s.entities.insert(EntityKind::Item, ());
+            e.set_world(world_id);
+            e.set_position(pos);
+            f(e);
+            id
+        };
+
+        let timed = spawn([0.5, 100.0, 0.5], |e| e.set_despawn_after(2));
+        let near = spawn([1.5, 100.0, 0.5], |e| e.set_despawn_when_unviewed(true));
+        let far = spawn([1000.0, 100.0, 0.5], |e| e.set_despawn_when_unviewed(true));
+
+        let exists = |server: &TestServer<Game>, id| server.server().entities.get(id).is_some();
+
+        // New entities are kept until clients have had a chance to load them.
+        server.tick();
+        assert!(exists(&server, far));
+
+        server.tick();
+        assert!(!exists(&server, far));
+        assert!(exists(&server, timed));
+        assert_eq!(
+            server.server().entities.get(timed).unwrap().despawn_after(),
+            Some(0)
+        );
+
+        server.tick();
+        assert!(!exists(&server, timed));
+        assert!(exists(&server, near));
+    }
+
+    #[test]
+    fn entity_tags_indexed() {
+        let mut server = test_server();
+
+        let entities = &mut server.server_mut().entities;
+        let a = entities.insert(EntityKind::Zombie, ()).0;
+        let b = entities.insert(EntityKind::Zombie, ()).0;
+        let c = entities.insert(EntityKind::Item, ()).0;
+
+        assert!(entities.add_tag(a, "arena:mob"));
+        assert!(entities.add_tag(b, "arena:mob"));
+        assert!(!entities.add_tag(b, "arena:mob"));
+        assert!(entities.add_tag(c, "arena:loot"));
+        assert!(entities.add_tag(b, "boss"));
+
+        let tagged = |entities: &Entities<Game>, tag| {
+            entities.with_tag(tag).map(|(id, _)| id).collect::<Vec<_>>()
+        };
+
+        assert_eq!(tagged(entities, "arena:mob"), [a, b]);
+        assert_eq!(entities.count_with_tag("arena:loot"), 1);
+        assert!(entities.get(b).unwrap().has_tag("boss"));
+        assert_eq!(entities.get(b).unwrap().tags().len(), 2);
+
+        assert!(entities.remove_tag(a, "arena:mob"));
+        assert!(!entities.remove_tag(a, "arena:mob"));
+        assert_eq!(tagged(entities, "arena:mob"), [b]);
+
+        entities.remove(b);
+        entities.retain(|_, e| e.kind() != EntityKind::Item);
+        assert!(tagged(entities, "arena:mob").is_empty());
+        assert_eq!(entities.count_with_tag("arena:loot"), 0);
+        assert_eq!(entities.count_with_tag("boss"), 0);
+    }
+
+    #[test]
+    fn entities_indexed_by_kind() {
+        let mut server = test_server();
+
+        let entities = &mut server.server_mut().entities;
+        let a = entities.insert(EntityKind::Zombie, ()).0;
+        let b = entities.insert(EntityKind::Item, ()).0;
+        let c = entities.insert(EntityKind::Zombie, ()).0;
+
+        let of_kind = |entities: &Entities<Game>, kind| {
+            entities
+                .iter_kind(kind)
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(of_kind(entities, EntityKind::Zombie), [a, c]);
+        assert_eq!(of_kind(entities, EntityKind::Item), [b]);
+        assert_eq!(entities.count_kind(EntityKind::Pig), 0);
+
+        entities.remove(a);
+        entities.retain(|_, e| e.kind() != EntityKind::Item);
+        assert_eq!(of_kind(entities, EntityKind::Zombie), [c]);
+        assert_eq!(entities.count_kind(EntityKind::Item), 0);
+    }
+
+    #[test]
+    fn entity_created_from_nbt() {
+        let mut server = test_server();
+        let entities = &mut server.server_mut().entities;
+
+        let nbt = snbt::compound_from_snbt(
+            r#"{id: "minecraft:armor_stand", UUID: [I; 1, 2, 3, 4], Pos: [1.5d, 64.0d, -2.5d],
+            Motion: [0.0d, 0.5d, 0.0d], Rotation: [90.0f, 10.0f], CustomName: '{"text":"Bob"}',
+            CustomNameVisible: 1b, NoGravity: 1b, Small: 1b, ShowArms: 1b, Tags: ["ignored"]}"#,
+        )
+        .unwrap();
+
+        let (_, e) = entities.create_from_nbt(&nbt, ()).unwrap();
+        assert_eq!(e.kind(), EntityKind::ArmorStand);
+        assert_eq!(
+            e.uuid(),
+            Uuid::from_u128(0x00000001_00000002_00000003_00000004)
+        );
+        assert_eq!(e.position(), Vec3::new(1.5, 64.0, -2.5));
+        assert_eq!(e.velocity(), Vec3::new(0.0, 10.0, 0.0));
+        assert_eq!((e.yaw(), e.pitch()), (90.0, 10.0));
+
+        let TrackedData::ArmorStand(stand) = e.data() else {
+            panic!("entity should be an armor stand");
+        };
+        assert_eq!(stand.get_custom_name(), Some(&Text::from("Bob")));
+        assert!(stand.get_name_visible() && stand.get_no_gravity());
+        assert!(stand.get_small() && stand.get_show_arms() && !stand.get_marker());
+
+        let slime = snbt::compound_from_snbt("{id: slime, Size: 3}").unwrap();
+        let (_, e) = entities.create_from_nbt(&slime, ()).unwrap();
+        assert!(matches!(e.data(), TrackedData::Slime(s) if s.get_slime_size() == 4));
+
+        for bad in [
+            "{Pos: [0.0d, 0.0d, 0.0d]}",
+            "{id: \"minecraft:dragon\"}",
+            "{id: pig, Pos: [0.0d, 0.0d]}",
+            "{id: pig, UUID: [I; 1, 2, 3, 4]}",
+        ] {
+            let len = entities.len();
+            let nbt = snbt::compound_from_snbt(bad).unwrap();
+            assert!(entities.create_from_nbt(&nbt, ()).is_err(), "{bad}");
+            assert_eq!(entities.len(), len);
+        }
+    }
+
+    #[test]
+    fn raw_tracked_data_sent() {
+        let (mut server, client) = connected_client();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let (pig, e) = s.entities.insert(EntityKind::Pig, ());
+        e.move_to_world(world_id, [2.5, 100.0, 0.5]);
+        // The saddle of pigs is a boolean (type 7) at index 17.
+        e.set_raw_tracked_data(17, 7, [1]);
+        server.tick();
+
+        let pig_id = VarInt(pig.to_network_id());
+        let tracked_data = |client: &MockClient| {
+            client
+                .take_packets()
+                .into_iter()
+                .filter_map(|pkt| match pkt {
+                    S2cPlayPacket::EntityTrackerUpdate(u) if u.entity_id == pig_id => {
+                        Some(u.metadata.0)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(tracked_data(&client), [vec![17, 7, 1, 0xff]]);
+
+        let e = server.server_mut().entities.get_mut(pig).unwrap();
+        if let TrackedData::Pig(pig) = e.data_mut() {
+            pig.set_boost_time(5);
+        }
+        e.set_raw_tracked_data(17, 7, [0]);
+        server.tick();
+        assert_eq!(tracked_data(&client), [vec![18, 1, 5, 17, 7, 0, 0xff]]);
+
+        server.tick();
+        assert!(tracked_data(&client).is_empty());
+        let e = server.server().entities.get(pig).unwrap();
+        assert_eq!(e.raw_tracked_data(17).unwrap().data, [0]);
+    }
+
+    #[test]
+    fn entities_owned_by_worlds() {
+        let mut server = test_server();
+
+        let s = server.server_mut();
+        let (a, _) = s.worlds.insert(DimensionId::default(), ());
+        let (b, _) = s.worlds.insert(DimensionId::default(), ());
+        let (pig, e) = s.entities.insert(EntityKind::Pig, ());
+        e.set_world(a);
+        s.entities.insert(EntityKind::Pig, ());
+
+        let in_world = |server: &TestServer<Game>, world| {
+            let world = server.server().worlds.get(world).unwrap();
+            let ids: Vec<_> = world.entities().collect();
+            let indexed: Vec<_> = world.spatial_index.iter().map(|(id, _)| id).collect();
+            assert_eq!(ids, indexed);
+            ids
+        };
+
+        server.tick();
+        assert_eq!(in_world(&server, a), [pig]);
+        assert!(in_world(&server, b).is_empty());
+
+        let s = server.server_mut();
+        s.entities.get_mut(pig).unwrap().set_world(b);
+        server.tick();
+        assert!(in_world(&server, a).is_empty());
+        assert_eq!(in_world(&server, b), [pig]);
+        assert!(server.server().worlds.get(b).unwrap().contains_entity(pig));
+
+        server.server_mut().worlds.remove(b);
+        server.tick();
+        let world = server.server().entities.get(pig).unwrap().world();
+        assert_eq!(world, WorldId::NULL);
+    }
+
+    #[test]
+    fn entity_moved_between_worlds() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        server.tick();
+
+        let s = server.server_mut();
+        let (a, _) = s.worlds.iter().next().unwrap();
+        let (b, _) = s.worlds.insert(DimensionId::default(), ());
+        s.clients
+            .get_mut(bob.id())
+            .unwrap()
+            .respawn(b, [0.5, 100.0, 0.5], 0.0, 0.0);
+        let (pig, e) = s.entities.insert(EntityKind::Pig, ());
+        e.move_to_world(a, [2.5, 100.0, 0.5]);
+        server.tick();
+        alice.take_packets();
+        bob.take_packets();
+
+        server
+            .server_mut()
+            .entities
+            .get_mut(pig)
+            .unwrap()
+            .move_to_world(b, [3.5, 100.0, 0.5]);
+        server.tick();
+
+        let pig_id = VarInt(pig.to_network_id());
+        assert!(alice.take_packets().iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::EntitiesDestroy(d) if d.entities.contains(&pig_id)
+        )));
+        assert!(bob.take_packets().iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::EntitySpawn(s) if s.entity_id == pig_id && s.position.x == 3.5
+        )));
+    }
+
+    #[test]
+    fn spawn_hook_changes_appearance() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        server.tick();
+        alice.take_packets();
+        bob.take_packets();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let alice_uuid = s.clients.get(alice.id()).unwrap().uuid();
+        let (pig, e) = s.entities.insert(EntityKind::Pig, ());
+        e.move_to_world(world_id, [2.5, 100.0, 0.5]);
+        e.set_spawn_hook(move |viewer, appearance| {
+            appearance.kind = EntityKind::Cow;
+            viewer != alice_uuid
+        });
+        server.tick();
+
+        let pig_id = VarInt(pig.to_network_id());
+        let is_pig_packet = |pkt: &S2cPlayPacket| match pkt {
+            S2cPlayPacket::EntitySpawn(s) => s.entity_id == pig_id,
+            S2cPlayPacket::EntityTrackerUpdate(u) => u.entity_id == pig_id,
+            _ => false,
+        };
+
+        assert!(!alice.take_packets().iter().any(is_pig_packet));
+        let pkts: Vec<_> = bob
+            .take_packets()
+            .into_iter()
+            .filter(is_pig_packet)
+            .collect();
+        assert!(matches!(
+            pkts.as_slice(),
+            [S2cPlayPacket::EntitySpawn(s)] if s.kind == VarInt(EntityKind::Cow as i32)
+        ));
+
+        let e = server.server_mut().entities.get_mut(pig).unwrap();
+        if let TrackedData::Pig(pig) = e.data_mut() {
+            pig.set_saddled(true);
+        }
+        e.clear_spawn_hook();
+        server.tick();
+        assert!(!bob.take_packets().iter().any(is_pig_packet));
+        assert!(alice.take_packets().iter().any(is_pig_packet));
+    }
+}
@@ -2,6 +2,10 @@
 
 use std::io::{Read, Write};
 
+use vek::Vec3;
+
+use crate::block::{BlockState, PaletteEntry};
+use crate::nbt::{Compound, List, Value};
 use crate::protocol::{Decode, Encode, VarInt};
 
 /// Represents an optional `u32` value excluding [`u32::MAX`].
@@ -243,14 +247,412 @@ impl Encode for PaintingKind {
     }
 }
 
-// TODO
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+/// A particle effect, used by area effect clouds among other things.
+///
+/// Particles are identified by their ID in the `minecraft:particle_type`
+/// registry. Some kinds of particles carry additional data.
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Particle {
-    EntityEffect = 21,
+    AmbientEntityEffect,
+    AngryVillager,
+    Block(BlockState),
+    BlockMarker(BlockState),
+    Bubble,
+    Cloud,
+    Crit,
+    DamageIndicator,
+    DragonBreath,
+    DrippingLava,
+    FallingLava,
+    LandingLava,
+    DrippingWater,
+    FallingWater,
+    Dust {
+        /// The color of the dust, with each component in `0.0..=1.0`.
+        rgb: Vec3<f32>,
+        /// The size of the dust, which is clamped to `0.01..=4.0` by clients.
+        scale: f32,
+    },
+    DustColorTransition {
+        from_rgb: Vec3<f32>,
+        scale: f32,
+        to_rgb: Vec3<f32>,
+    },
+    Effect,
+    ElderGuardian,
+    EnchantedHit,
+    Enchant,
+    EndRod,
+    EntityEffect,
+    ExplosionEmitter,
+    Explosion,
+    SonicBoom,
+    FallingDust(BlockState),
+    Firework,
+    Fishing,
+    Flame,
+    SculkSoul,
+    SculkCharge {
+        /// The rotation of the particle in radians.
+        roll: f32,
+    },
+    SculkChargePop,
+    SoulFireFlame,
+    Soul,
+    Flash,
+    HappyVillager,
+    Composter,
+    Heart,
+    InstantEffect,
+    // TODO: item particles need item stacks.
+    // TODO: vibration particles need position sources.
+    ItemSlime,
+    ItemSnowball,
+    LargeSmoke,
+    Lava,
+    Mycelium,
+    Note,
+    Poof,
+    Portal,
+    Rain,
+    Smoke,
+    Sneeze,
+    Spit,
+    SquidInk,
+    SweepAttack,
+    TotemOfUndying,
+    Underwater,
+    Splash,
+    Witch,
+    BubblePop,
+    CurrentDown,
+    BubbleColumnUp,
+    Nautilus,
+    Dolphin,
+    CampfireCosySmoke,
+    CampfireSignalSmoke,
+    DrippingHoney,
+    FallingHoney,
+    LandingHoney,
+    FallingNectar,
+    FallingSporeBlossom,
+    Ash,
+    CrimsonSpore,
+    WarpedSpore,
+    SporeBlossomAir,
+    DrippingObsidianTear,
+    FallingObsidianTear,
+    LandingObsidianTear,
+    ReversePortal,
+    WhiteAsh,
+    SmallFlame,
+    Snowflake,
+    DrippingDripstoneLava,
+    FallingDripstoneLava,
+    DrippingDripstoneWater,
+    FallingDripstoneWater,
+    GlowSquidInk,
+    Glow,
+    WaxOn,
+    WaxOff,
+    ElectricSpark,
+    Scrape,
+    Shriek {
+        /// The delay in ticks before the particle appears.
+        delay: i32,
+    },
+}
+
+impl Particle {
+    /// Returns the ID of this kind of particle in the
+    /// `minecraft:particle_type` registry.
+    pub fn id(self) -> i32 {
+        match self {
+            Particle::AmbientEntityEffect => 0,
+            Particle::AngryVillager => 1,
+            Particle::Block(_) => 2,
+            Particle::BlockMarker(_) => 3,
+            Particle::Bubble => 4,
+            Particle::Cloud => 5,
+            Particle::Crit => 6,
+            Particle::DamageIndicator => 7,
+            Particle::DragonBreath => 8,
+            Particle::DrippingLava => 9,
+            Particle::FallingLava => 10,
+            Particle::LandingLava => 11,
+            Particle::DrippingWater => 12,
+            Particle::FallingWater => 13,
+            Particle::Dust { .. } => 14,
+            Particle::DustColorTransition { .. } => 15,
+            Particle::Effect => 16,
+            Particle::ElderGuardian => 17,
+            Particle::EnchantedHit => 18,
+            Particle::Enchant => 19,
+            Particle::EndRod => 20,
+            Particle::EntityEffect => 21,
+            Particle::ExplosionEmitter => 22,
+            Particle::Explosion => 23,
+            Particle::SonicBoom => 24,
+            Particle::FallingDust(_) => 25,
+            Particle::Firework => 26,
+            Particle::Fishing => 27,
+            Particle::Flame => 28,
+            Particle::SculkSoul => 29,
+            Particle::SculkCharge { .. } => 30,
+            Particle::SculkChargePop => 31,
+            Particle::SoulFireFlame => 32,
+            Particle::Soul => 33,
+            Particle::Flash => 34,
+            Particle::HappyVillager => 35,
+            Particle::Composter => 36,
+            Particle::Heart => 37,
+            Particle::InstantEffect => 38,
+            Particle::ItemSlime => 41,
+            Particle::ItemSnowball => 42,
+            Particle::LargeSmoke => 43,
+            Particle::Lava => 44,
+            Particle::Mycelium => 45,
+            Particle::Note => 46,
+            Particle::Poof => 47,
+            Particle::Portal => 48,
+            Particle::Rain => 49,
+            Particle::Smoke => 50,
+            Particle::Sneeze => 51,
+            Particle::Spit => 52,
+            Particle::SquidInk => 53,
+            Particle::SweepAttack => 54,
+            Particle::TotemOfUndying => 55,
+            Particle::Underwater => 56,
+            Particle::Splash => 57,
+            Particle::Witch => 58,
+            Particle::BubblePop => 59,
+            Particle::CurrentDown => 60,
+            Particle::BubbleColumnUp => 61,
+            Particle::Nautilus => 62,
+            Particle::Dolphin => 63,
+            Particle::CampfireCosySmoke => 64,
+            Particle::CampfireSignalSmoke => 65,
+            Particle::DrippingHoney => 66,
+            Particle::FallingHoney => 67,
+            Particle::LandingHoney => 68,
+            Particle::FallingNectar => 69,
+            Particle::FallingSporeBlossom => 70,
+            Particle::Ash => 71,
+            Particle::CrimsonSpore => 72,
+            Particle::WarpedSpore => 73,
+            Particle::SporeBlossomAir => 74,
+            Particle::DrippingObsidianTear => 75,
+            Particle::FallingObsidianTear => 76,
+            Particle::LandingObsidianTear => 77,
+            Particle::ReversePortal => 78,
+            Particle::WhiteAsh => 79,
+            Particle::SmallFlame => 80,
+            Particle::Snowflake => 81,
+            Particle::DrippingDripstoneLava => 82,
+            Particle::FallingDripstoneLava => 83,
+            Particle::DrippingDripstoneWater => 84,
+            Particle::FallingDripstoneWater => 85,
+            Particle::GlowSquidInk => 86,
+            Particle::Glow => 87,
+            Particle::WaxOn => 88,
+            Particle::WaxOff => 89,
+            Particle::ElectricSpark => 90,
+            Particle::Scrape => 91,
+            Particle::Shriek { .. } => 92,
+        }
+    }
+
+    /// Returns the name of this kind of particle in the
+    /// `minecraft:particle_type` registry, without the namespace.
+    pub fn name(self) -> &'static str {
+        match self {
+            Particle::AmbientEntityEffect => "ambient_entity_effect",
+            Particle::AngryVillager => "angry_villager",
+            Particle::Block(_) => "block",
+            Particle::BlockMarker(_) => "block_marker",
+            Particle::Bubble => "bubble",
+            Particle::Cloud => "cloud",
+            Particle::Crit => "crit",
+            Particle::DamageIndicator => "damage_indicator",
+            Particle::DragonBreath => "dragon_breath",
+            Particle::DrippingLava => "dripping_lava",
+            Particle::FallingLava => "falling_lava",
+            Particle::LandingLava => "landing_lava",
+            Particle::DrippingWater => "dripping_water",
+            Particle::FallingWater => "falling_water",
+            Particle::Dust { .. } => "dust",
+            Particle::DustColorTransition { .. } => "dust_color_transition",
+            Particle::Effect => "effect",
+            Particle::ElderGuardian => "elder_guardian",
+            Particle::EnchantedHit => "enchanted_hit",
+            Particle::Enchant => "enchant",
+            Particle::EndRod => "end_rod",
+            Particle::EntityEffect => "entity_effect",
+            Particle::ExplosionEmitter => "explosion_emitter",
+            Particle::Explosion => "explosion",
+            Particle::SonicBoom => "sonic_boom",
+            Particle::FallingDust(_) => "falling_dust",
+            Particle::Firework => "firework",
+            Particle::Fishing => "fishing",
+            Particle::Flame => "flame",
+            Particle::SculkSoul => "sculk_soul",
+            Particle::SculkCharge { .. } => "sculk_charge",
+            Particle::SculkChargePop => "sculk_charge_pop",
+            Particle::SoulFireFlame => "soul_fire_flame",
+            Particle::Soul => "soul",
+            Particle::Flash => "flash",
+            Particle::HappyVillager => "happy_villager",
+            Particle::Composter => "composter",
+            Particle::Heart => "heart",
+            Particle::InstantEffect => "instant_effect",
+            Particle::ItemSlime => "item_slime",
+            Particle::ItemSnowball => "item_snowball",
+            Particle::LargeSmoke => "large_smoke",
+            Particle::Lava => "lava",
+            Particle::Mycelium => "mycelium",
+            Particle::Note => "note",
+            Particle::Poof => "poof",
+            Particle::Portal => "portal",
+            Particle::Rain => "rain",
+            Particle::Smoke => "smoke",
+            Particle::Sneeze => "sneeze",
+            Particle::Spit => "spit",
+            Particle::SquidInk => "squid_ink",
+            Particle::SweepAttack => "sweep_attack",
+            Particle::TotemOfUndying => "totem_of_undying",
+            Particle::Underwater => "underwater",
+            Particle::Splash => "splash",
+            Particle::Witch => "witch",
+            Particle::BubblePop => "bubble_pop",
+            Particle::CurrentDown => "current_down",
+            Particle::BubbleColumnUp => "bubble_column_up",
+            Particle::Nautilus => "nautilus",
+            Particle::Dolphin => "dolphin",
+            Particle::CampfireCosySmoke => "campfire_cosy_smoke",
+            Particle::CampfireSignalSmoke => "campfire_signal_smoke",
+            Particle::DrippingHoney => "dripping_honey",
+            Particle::FallingHoney => "falling_honey",
+            Particle::LandingHoney => "landing_honey",
+            Particle::FallingNectar => "falling_nectar",
+            Particle::FallingSporeBlossom => "falling_spore_blossom",
+            Particle::Ash => "ash",
+            Particle::CrimsonSpore => "crimson_spore",
+            Particle::WarpedSpore => "warped_spore",
+            Particle::SporeBlossomAir => "spore_blossom_air",
+            Particle::DrippingObsidianTear => "dripping_obsidian_tear",
+            Particle::FallingObsidianTear => "falling_obsidian_tear",
+            Particle::LandingObsidianTear => "landing_obsidian_tear",
+            Particle::ReversePortal => "reverse_portal",
+            Particle::WhiteAsh => "white_ash",
+            Particle::SmallFlame => "small_flame",
+            Particle::Snowflake => "snowflake",
+            Particle::DrippingDripstoneLava => "dripping_dripstone_lava",
+            Particle::FallingDripstoneLava => "falling_dripstone_lava",
+            Particle::DrippingDripstoneWater => "dripping_dripstone_water",
+            Particle::FallingDripstoneWater => "falling_dripstone_water",
+            Particle::GlowSquidInk => "glow_squid_ink",
+            Particle::Glow => "glow",
+            Particle::WaxOn => "wax_on",
+            Particle::WaxOff => "wax_off",
+            Particle::ElectricSpark => "electric_spark",
+            Particle::Scrape => "scrape",
+            Particle::Shriek { .. } => "shriek",
+        }
+    }
+
+    /// Serializes this particle the way particle options are stored in
+    /// NBT, with the namespaced name in the `type` tag.
+    pub fn to_nbt(self) -> Compound {
+        let vec3 = |v: Vec3<f32>| Value::List(List::Float(vec![v.x, v.y, v.z]));
+
+        let mut nbt = Compound::from([(
+            "type".into(),
+            Value::String(format!("minecraft:{}", self.name())),
+        )]);
+
+        match self {
+            Particle::Block(block)
+            | Particle::BlockMarker(block)
+            | Particle::FallingDust(block) => {
+                let state = PaletteEntry::from_block_state(block).to_nbt();
+                nbt.insert("value".into(), Value::Compound(state));
+            }
+            Particle::Dust { rgb, scale } => {
+                nbt.insert("color".into(), vec3(rgb));
+                nbt.insert("scale".into(), Value::Float(scale));
+            }
+            Particle::DustColorTransition {
+                from_rgb,
+                scale,
+                to_rgb,
+            } => {
+                nbt.insert("fromColor".into(), vec3(from_rgb));
+                nbt.insert("toColor".into(), vec3(to_rgb));
+                nbt.insert("scale".into(), Value::Float(scale));
+            }
+            Particle::SculkCharge { roll } => {
+                nbt.insert("roll".into(), Value::Float(roll));
+            }
+            Particle::Shriek { delay } => {
+                nbt.insert("delay".into(), Value::Int(delay));
+            }
+            _ => {}
+        }
+
+        nbt
+    }
 }
 
 impl Encode for Particle {
     fn encode(&self, w: &mut impl Write) -> anyhow::Result<()> {
-        VarInt(*self as i32).encode(w)
+        VarInt(self.id()).encode(w)?;
+
+        match *self {
+            Particle::Block(block)
+            | Particle::BlockMarker(block)
+            | Particle::FallingDust(block) => block.encode(w),
+            Particle::Dust { rgb, scale } => {
+                rgb.encode(w)?;
+                scale.encode(w)
+            }
+            Particle::DustColorTransition {
+                from_rgb,
+                scale,
+                to_rgb,
+            } => {
+                from_rgb.encode(w)?;
+                scale.encode(w)?;
+                to_rgb.encode(w)
+            }
+            Particle::SculkCharge { roll } => roll.encode(w),
+            Particle::Shriek { delay } => VarInt(delay).encode(w),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particle_encoding() {
+        let mut buf = Vec::new();
+        Particle::EntityEffect.encode(&mut buf).unwrap();
+        assert_eq!(buf, [21]);
+
+        buf.clear();
+        Particle::Dust {
+            rgb: Vec3::new(1.0, 0.0, 0.0),
+            scale: 1.0,
+        }
+        .encode(&mut buf)
+        .unwrap();
+        assert_eq!(buf[0], 14);
+        assert_eq!(buf.len(), 1 + 4 * 4);
+
+        buf.clear();
+        Particle::Shriek { delay: 10 }.encode(&mut buf).unwrap();
+        assert_eq!(buf, [92, 10]);
     }
 }
@@ -0,0 +1,521 @@
+//! A central place to subscribe to events by their type.
+//!
+//! Instead of every module taking [`ClientEvent`]s with
+//! [`Client::pop_event`], handlers can be subscribed to an [`Event`] type in
+//! [`Server::event_bus`]. When an event is [posted](post), the handlers of its
+//! type are called in the order they were subscribed. A handler can modify the
+//! event for the handlers after it, or [cancel](EventResult::Cancel) it so that
+//! the handlers after it are not called.
+//!
+//! At the beginning of every tick, before [`Config::update`], the server posts
+//! the following events:
+//! - [`PlayerJoin`] for every client created this tick.
+//! - [`ChatMessage`] for every [`ClientEvent::ChatMessage`]. The client event
+//!   is updated with the modified message, or removed from the client's event
+//!   queue if the message is cancelled.
+//! - [`BlockBreak`] for every [`ClientEvent::Digging`] event which breaks a
+//!   block. If the break is cancelled, the digging event is removed from the
+//!   client's event queue and the block is restored on the client.
+//! - [`BlockPlace`] for every [`ClientEvent::InteractWithBlock`] of a client
+//!   with a [held block](crate::client::Client::held_block) which can be
+//!   placed. The block is placed unless the event is cancelled, in which case
+//!   the interaction event is removed from the client's event queue.
+//! - [`ChunkChanged`] for every chunk which was
+//!   [changed](crate::chunk::Chunk::changed_this_tick) during the previous
+//!   tick and still exists. Cancelling it only stops the handlers after it.
+//!
+//! Other event types can be defined by implementing [`Event`] and posted with
+//! [`post`].
+//!
+//! [`Client::pop_event`]: crate::client::Client::pop_event
+//! [`Config::update`]: crate::config::Config::update
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use vek::Vec3;
+
+use crate::block::{BlockPlacement, PlacementContext};
+use crate::block_pos::BlockPos;
+use crate::chunk::ChunkPos;
+use crate::client::{BlockFace, ClientEvent, ClientId, DiggingStatus, GameMode};
+use crate::config::Config;
+use crate::server::Server;
+use crate::world::WorldId;
+
+/// A type which can be posted to the [`EventBus`].
+pub trait Event: Send + Sync + 'static {}
+
+/// Whether the handlers after a handler are called.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EventResult {
+    /// The event is passed to the next handler.
+    Continue,
+    /// The event is cancelled. No more handlers are called.
+    Cancel,
+}
+
+/// Identifies a handler subscribed to an [`EventBus`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SubscriptionId(u64);
+
+type Handler<C, E> = Arc<dyn Fn(&mut Server<C>, &mut E) -> EventResult + Send + Sync>;
+
+/// A [`Handler`] with its event type erased.
+type ErasedHandler = Box<dyn Any + Send + Sync>;
+
+/// Contains the event handlers of a server.
+pub struct EventBus<C: Config> {
+    /// The handlers of every event type.
+    handlers: HashMap<TypeId, Vec<(SubscriptionId, ErasedHandler)>>,
+    next_id: u64,
+    _marker: PhantomData<fn(&mut Server<C>)>,
+}
+
+/// Posted when a client joins the server, before [`Config::update`] is called
+/// with the client.
+///
+/// [`Config::update`]: crate::config::Config::update
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PlayerJoin {
+    pub client: ClientId,
+}
+
+/// Posted when a client sends a chat message.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ChatMessage {
+    pub client: ClientId,
+    /// The message, which can be modified by handlers.
+    pub message: String,
+}
+
+/// Posted when a client breaks a block.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BlockBreak {
+    pub client: ClientId,
+    /// The world the client is in.
+    pub world: WorldId,
+    pub position: BlockPos,
+}
+
+/// Posted when a client places its
+/// [held block](crate::client::Client::held_block), before the block is placed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BlockPlace {
+    pub client: ClientId,
+    /// The world the client is in.
+    pub world: WorldId,
+    /// The block to place, which can be modified by handlers.
+    pub placement: BlockPlacement,
+}
+
+/// Posted at the beginning of a tick for every chunk whose blocks or biomes
+/// were changed during the previous tick.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ChunkChanged {
+    pub world: WorldId,
+    pub pos: ChunkPos,
+}
+
+impl Event for PlayerJoin {}
+impl Event for ChatMessage {}
+impl Event for BlockBreak {}
+impl Event for BlockPlace {}
+impl Event for ChunkChanged {}
+
+impl<C: Config> EventBus<C> {
+    pub(crate) fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            next_id: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Subscribes a handler to events of type `E`. It is called after the
+    /// handlers subscribed before it.
+    pub fn subscribe<E: Event>(
+        &mut self,
+        handler: impl Fn(&mut Server<C>, &mut E) -> EventResult + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+
+        let handler: Handler<C, E> = Arc::new(handler);
+        self.handlers
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push((id, Box::new(handler)));
+
+        id
+    }
+
+    /// Removes a handler. Returns `true` if it was subscribed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        for handlers in self.handlers.values_mut() {
+            if let Some(idx) = handlers.iter().position(|(i, _)| *i == id) {
+                handlers.remove(idx);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns `true` if any handler is subscribed to events of type `E`.
+    pub fn has_handlers<E: Event>(&self) -> bool {
+        self.handlers
+            .get(&TypeId::of::<E>())
+            .is_some_and(|handlers| !handlers.is_empty())
+    }
+
+    fn handlers<E: Event>(&self) -> Vec<Handler<C, E>> {
+        self.handlers
+            .get(&TypeId::of::<E>())
+            .map(|handlers| {
+                handlers
+                    .iter()
+                    .filter_map(|(_, h)| h.downcast_ref::<Handler<C, E>>().cloned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Calls the handlers subscribed to events of type `E` in order.
+///
+/// Returns `false` if the event was cancelled. Handlers subscribed or
+/// unsubscribed by a handler take effect when the next event is posted.
+pub fn post<C: Config, E: Event>(server: &mut Server<C>, event: &mut E) -> bool {
+    for handler in server.event_bus.handlers::<E>() {
+        if handler(server, event) == EventResult::Cancel {
+            return false;
+        }
+    }
+    true
+}
+
+/// Posts the events of the clients created this tick and the events in the
+/// event queues of all clients.
+pub(crate) fn post_client_events<C: Config>(server: &mut Server<C>) {
+    let bus = &server.event_bus;
+    if !bus.has_handlers::<PlayerJoin>()
+        && !bus.has_handlers::<ChatMessage>()
+        && !bus.has_handlers::<BlockBreak>()
+        && !server
+            .clients
+            .iter()
+            .any(|(_, client)| client.held_block().is_some())
+    {
+        return;
+    }
+
+    let ids: Vec<_> = server.clients.iter().map(|(id, _)| id).collect();
+
+    for id in ids {
+        if server
+            .clients
+            .get(id)
+            .is_some_and(|client| client.created_this_tick())
+        {
+            post(server, &mut PlayerJoin { client: id });
+        }
+
+        let Some(client) = server.clients.get_mut(id) else {
+            continue;
+        };
+        let mut kept = VecDeque::new();
+
+        for event in client.take_events() {
+            match event {
+                ClientEvent::ChatMessage { message, timestamp } => {
+                    let mut event = ChatMessage {
+                        client: id,
+                        message,
+                    };
+                    if post(server, &mut event) {
+                        kept.push_back(ClientEvent::ChatMessage {
+                            message: event.message,
+                            timestamp,
+                        });
+                    }
+                }
+                ClientEvent::Digging {
+                    status,
+                    position,
+                    face,
+                } if breaks_block(server, id, status) => {
+                    let Some(world) = server.clients.get(id).map(|client| client.world()) else {
+                        break;
+                    };
+                    if post(
+                        server,
+                        &mut BlockBreak {
+                            client: id,
+                            world,
+                            position,
+                        },
+                    ) {
+                        kept.push_back(ClientEvent::Digging {
+                            status,
+                            position,
+                            face,
+                        });
+                    } else if let Some(client) = server.clients.get_mut(id) {
+                        client.restore_dug_block(position);
+                    }
+                }
+                event @ ClientEvent::InteractWithBlock {
+                    location,
+                    face,
+                    cursor_pos,
+                    ..
+                } => {
+                    if place_block(server, id, location, face, cursor_pos) {
+                        kept.push_back(event);
+                    }
+                }
+                event => kept.push_back(event),
+            }
+        }
+
+        if let Some(client) = server.clients.get_mut(id) {
+            client.prepend_events(kept);
+        }
+    }
+}
+
+/// Posts a [`ChunkChanged`] event for every chunk changed during the previous
+/// tick.
+pub(crate) fn post_chunk_events<C: Config>(server: &mut Server<C>) {
+    if !server.event_bus.has_handlers::<ChunkChanged>() {
+        return;
+    }
+
+    let changed: Vec<_> = server
+        .worlds
+        .iter()
+        .flat_map(|(world, w)| {
+            w.chunks
+                .changed_last_tick()
+                .iter()
+                .map(move |&pos| ChunkChanged { world, pos })
+        })
+        .collect();
+
+    for mut event in changed {
+        let exists = server
+            .worlds
+            .get(event.world)
+            .is_some_and(|w| w.chunks.get(event.pos).is_some());
+
+        if exists {
+            post(server, &mut event);
+        }
+    }
+}
+
+/// Posts a [`BlockPlace`] event for a client using its held block on the
+/// block at `location` and places the block unless the event is cancelled.
+///
+/// Returns `false` if the event was cancelled.
+fn place_block<C: Config>(
+    server: &mut Server<C>,
+    id: ClientId,
+    location: BlockPos,
+    face: BlockFace,
+    cursor_pos: Vec3<f32>,
+) -> bool {
+    let Some(client) = server.clients.get(id) else {
+        return true;
+    };
+    let Some(kind) = client.held_block() else {
+        return true;
+    };
+    let world = client.world();
+    let ctx = PlacementContext {
+        location,
+        face,
+        cursor_pos,
+        yaw: client.yaw(),
+        pitch: client.pitch(),
+    };
+    let Some(placement) = server
+        .worlds
+        .get(world)
+        .and_then(|w| w.chunks.block_placement(kind, &ctx))
+    else {
+        return true;
+    };
+
+    let mut event = BlockPlace {
+        client: id,
+        world,
+        placement,
+    };
+
+    if post(server, &mut event) {
+        if let Some(w) = server.worlds.get_mut(world) {
+            w.chunks
+                .set_block_state(event.placement.position, event.placement.state);
+        }
+        true
+    } else {
+        if let Some(client) = server.clients.get_mut(id) {
+            client.restore_dug_block(location);
+            client.restore_dug_block(placement.position);
+        }
+        false
+    }
+}
+
+/// Returns `true` if a digging event with the given status breaks a block.
+/// Players in creative mode break blocks as soon as they start digging.
+fn breaks_block<C: Config>(server: &Server<C>, id: ClientId, status: DiggingStatus) -> bool {
+    match status {
+        DiggingStatus::Finish => true,
+        DiggingStatus::Start => server
+            .clients
+            .get(id)
+            .is_some_and(|client| client.game_mode() == GameMode::Creative),
+        DiggingStatus::Cancel => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::block::{BlockKind, BlockState, PropName, PropValue};
+    use crate::client::Hand;
+    use crate::protocol::packets::c2s::play::{
+        BlockFace, DiggingStatus, PlayerAction, PlayerInteractBlock,
+    };
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::protocol::VarInt;
+    use crate::testing::{chat_message, messages, test_server, Game, TestServer};
+
+    #[test]
+    fn event_bus_handlers_run_in_order() {
+        let mut server = test_server();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let bus = &mut server.server_mut().event_bus;
+        bus.subscribe(|server, event: &mut PlayerJoin| {
+            let client = server.clients.get_mut(event.client).unwrap();
+            let msg = format!("hi {}", client.username());
+            client.send_message(msg);
+            EventResult::Continue
+        });
+        let filter = bus.subscribe(|_, event: &mut ChatMessage| {
+            if event.message == "spam" {
+                return EventResult::Cancel;
+            }
+            event.message = event.message.to_uppercase();
+            EventResult::Continue
+        });
+        bus.subscribe({
+            let log = log.clone();
+            move |_, event: &mut ChatMessage| {
+                log.lock().unwrap().push(event.message.clone());
+                EventResult::Continue
+            }
+        });
+        bus.subscribe(|_, _: &mut BlockBreak| EventResult::Cancel);
+
+        let client = server.connect("tester");
+        server.tick();
+        assert_eq!(messages(&client), ["hi tester", "welcome"]);
+
+        assert!(client.send(chat_message("spam")));
+        assert!(client.send(chat_message("hello")));
+        server.tick();
+        assert_eq!(messages(&client), ["HELLO at 0.5"]);
+        assert_eq!(*log.lock().unwrap(), ["HELLO"]);
+
+        let s = server.server_mut();
+        assert!(s.event_bus.unsubscribe(filter));
+        assert!(!s.event_bus.unsubscribe(filter));
+        assert!(client.send(chat_message("spam")));
+        server.tick();
+        assert_eq!(messages(&client), ["spam at 0.5"]);
+
+        // Cancelled breaks are restored if the client restores dug blocks.
+        let pos = BlockPos::new(1, 64, 1);
+        let s = server.server_mut();
+        s.clients
+            .get_mut(client.id())
+            .unwrap()
+            .set_restore_dug_blocks(true);
+        let (_, world) = s.worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state(pos, BlockState::STONE);
+        server.tick();
+        client.take_packets();
+
+        assert!(client.send(PlayerAction {
+            status: DiggingStatus::FinishedDigging,
+            location: pos,
+            face: BlockFace::Top,
+            sequence: VarInt(0),
+        }));
+        server.tick();
+        assert!(client.take_packets().iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::BlockUpdate(p)
+                if p.location == pos && p.block_id.0 == BlockState::STONE.to_raw() as i32
+        )));
+    }
+
+    #[test]
+    fn held_blocks_placed_unless_cancelled() {
+        let mut server = test_server();
+        let client = server.connect("tester");
+        server.tick();
+
+        let s = server.server_mut();
+        s.clients
+            .get_mut(client.id())
+            .unwrap()
+            .set_held_block(Some(BlockKind::OakLog));
+        let (_, world) = s.worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state([1, 64, 1], BlockState::STONE);
+
+        let place = |x| PlayerInteractBlock {
+            hand: Hand::Main,
+            location: BlockPos::new(x, 64, 1),
+            face: BlockFace::East,
+            cursor_pos: Vec3::new(1.0, 0.5, 0.5),
+            head_inside_block: false,
+            sequence: VarInt(0),
+        };
+        let block = |server: &TestServer<Game>, x| {
+            let (_, world) = server.server().worlds.iter().next().unwrap();
+            world.chunks.get_block_state([x, 64, 1]).unwrap()
+        };
+
+        assert!(client.send(place(1)));
+        server.tick();
+        let placed = block(&server, 2);
+        assert_eq!(placed.to_kind(), BlockKind::OakLog);
+        assert_eq!(placed.get(PropName::Axis), Some(PropValue::X));
+
+        let bus = &mut server.server_mut().event_bus;
+        bus.subscribe(|_, event: &mut BlockPlace| {
+            if event.placement.position.x == 4 {
+                return EventResult::Cancel;
+            }
+            event.placement.state = BlockState::GLASS;
+            EventResult::Continue
+        });
+
+        assert!(client.send(place(2)));
+        server.tick();
+        assert_eq!(block(&server, 3), BlockState::GLASS);
+
+        assert!(client.send(place(3)));
+        server.tick();
+        assert_eq!(block(&server, 4), BlockState::AIR);
+    }
+}
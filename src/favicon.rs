@@ -0,0 +1,134 @@
+//! Server icons shown in the server list.
+
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
+use thiserror::Error;
+
+/// A server icon, which is a 64x64 PNG image.
+///
+/// A favicon can only be created from a valid image, so server list pings
+/// never respond with an icon the client cannot display. It is cheap to
+/// clone.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Favicon {
+    png: Arc<[u8]>,
+}
+
+/// An error returned when creating a [`Favicon`].
+#[derive(Debug, Error)]
+pub enum FaviconError {
+    #[error("failed to read favicon: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("favicon is not a valid PNG image: {0}")]
+    InvalidImage(#[from] image::ImageError),
+    #[error("favicon must be 64x64 pixels, but is {width}x{height}")]
+    WrongSize { width: u32, height: u32 },
+}
+
+impl Favicon {
+    /// The width and height of a favicon in pixels.
+    pub const SIZE: u32 = 64;
+
+    /// Creates a favicon from the bytes of a PNG image, which must be 64x64
+    /// pixels. The bytes are used as they are.
+    pub fn from_png(png: impl Into<Arc<[u8]>>) -> Result<Self, FaviconError> {
+        let png = png.into();
+        let (width, height) = decode(&png)?.dimensions();
+
+        if (width, height) != (Self::SIZE, Self::SIZE) {
+            return Err(FaviconError::WrongSize { width, height });
+        }
+
+        Ok(Self { png })
+    }
+
+    /// Creates a favicon from the bytes of a PNG image of any size. Images
+    /// which are not 64x64 pixels are resized, and stretched if they are not
+    /// square.
+    pub fn from_png_resized(png: &[u8]) -> Result<Self, FaviconError> {
+        let image = decode(png)?;
+
+        if image.dimensions() == (Self::SIZE, Self::SIZE) {
+            return Ok(Self { png: png.into() });
+        }
+
+        Self::from_image(&image.resize_exact(Self::SIZE, Self::SIZE, FilterType::Lanczos3))
+    }
+
+    /// Loads a favicon from a PNG file of any size, resizing it like
+    /// [`Self::from_png_resized`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FaviconError> {
+        Self::from_png_resized(&std::fs::read(path)?)
+    }
+
+    /// Encodes a 64x64 image as a favicon.
+    fn from_image(image: &DynamicImage) -> Result<Self, FaviconError> {
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)?;
+        Ok(Self { png: png.into() })
+    }
+
+    /// Gets the bytes of the PNG image.
+    pub fn png(&self) -> &[u8] {
+        &self.png
+    }
+
+    /// Gets the favicon as a data URI, which is how it is sent to clients.
+    pub fn to_data_uri(&self) -> String {
+        let mut buf = "data:image/png;base64,".to_owned();
+        base64::encode_config_buf(&self.png, base64::STANDARD, &mut buf);
+        buf
+    }
+}
+
+fn decode(png: &[u8]) -> Result<DynamicImage, FaviconError> {
+    Ok(image::load_from_memory_with_format(png, ImageFormat::Png)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn favicon_validation() {
+        Favicon::from_png(include_bytes!("../assets/favicon.png").as_slice()).unwrap();
+
+        let icon = Favicon::from_png(png(64, 64)).unwrap();
+        assert!(icon
+            .to_data_uri()
+            .starts_with("data:image/png;base64,iVBOR"));
+
+        assert!(matches!(
+            Favicon::from_png(png(32, 64)),
+            Err(FaviconError::WrongSize {
+                width: 32,
+                height: 64
+            })
+        ));
+        assert!(matches!(
+            Favicon::from_png(b"GIF89a".as_slice()),
+            Err(FaviconError::InvalidImage(_))
+        ));
+
+        let resized = Favicon::from_png_resized(&png(128, 100)).unwrap();
+        assert_eq!(decode(resized.png()).unwrap().dimensions(), (64, 64));
+        assert_eq!(
+            Favicon::from_png_resized(icon.png()).unwrap(),
+            Favicon::from_png(icon.png()).unwrap()
+        );
+    }
+}
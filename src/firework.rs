@@ -0,0 +1,309 @@
+//! Firework rockets and their explosions.
+//!
+//! A [`Firework`] describes a rocket with any number of [`FireworkEffect`]s,
+//! which are serialized into the NBT of a firework rocket item. Rockets are
+//! launched with [`World::launch_firework`], which detonates them once their
+//! flight is over.
+//!
+//! [`World::launch_firework`]: crate::world::World::launch_firework
+
+use crate::nbt::{Compound, List, Value};
+use crate::protocol::{Encode, VarInt};
+use crate::text::Color;
+use crate::Ticks;
+
+/// The shape of a [`FireworkEffect`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum FireworkShape {
+    #[default]
+    SmallBall,
+    LargeBall,
+    Star,
+    Creeper,
+    Burst,
+}
+
+impl FireworkShape {
+    fn id(self) -> i8 {
+        match self {
+            FireworkShape::SmallBall => 0,
+            FireworkShape::LargeBall => 1,
+            FireworkShape::Star => 2,
+            FireworkShape::Creeper => 3,
+            FireworkShape::Burst => 4,
+        }
+    }
+}
+
+/// A single explosion of a [`Firework`].
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct FireworkEffect {
+    shape: FireworkShape,
+    colors: Vec<Color>,
+    fade_colors: Vec<Color>,
+    flicker: bool,
+    trail: bool,
+}
+
+impl FireworkEffect {
+    /// Creates an effect with the given shape and no colors. Clients show
+    /// effects without colors in black.
+    pub fn new(shape: FireworkShape) -> Self {
+        Self {
+            shape,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a color to the explosion. Each particle gets one of the colors.
+    #[must_use]
+    pub fn color(mut self, color: Color) -> Self {
+        self.colors.push(color);
+        self
+    }
+
+    /// Adds a color the particles fade to.
+    #[must_use]
+    pub fn fade_color(mut self, color: Color) -> Self {
+        self.fade_colors.push(color);
+        self
+    }
+
+    /// Sets if the particles twinkle. The default is `false`.
+    #[must_use]
+    pub fn flicker(mut self, flicker: bool) -> Self {
+        self.flicker = flicker;
+        self
+    }
+
+    /// Sets if the particles leave a trail. The default is `false`.
+    #[must_use]
+    pub fn trail(mut self, trail: bool) -> Self {
+        self.trail = trail;
+        self
+    }
+
+    /// Gets the shape of this effect.
+    pub fn shape(&self) -> FireworkShape {
+        self.shape
+    }
+
+    /// Serializes this effect into the compound stored in the `Explosions`
+    /// list of a firework rocket.
+    pub fn to_nbt(&self) -> Compound {
+        let rgb = |colors: &[Color]| {
+            Value::IntArray(
+                colors
+                    .iter()
+                    .map(|c| i32::from_be_bytes([0, c.r, c.g, c.b]))
+                    .collect(),
+            )
+        };
+
+        Compound::from([
+            ("Type".into(), Value::Byte(self.shape.id())),
+            ("Colors".into(), rgb(&self.colors)),
+            ("FadeColors".into(), rgb(&self.fade_colors)),
+            ("Flicker".into(), Value::Byte(self.flicker.into())),
+            ("Trail".into(), Value::Byte(self.trail.into())),
+        ])
+    }
+}
+
+/// A firework rocket launched with [`World::launch_firework`].
+///
+/// [`World::launch_firework`]: crate::world::World::launch_firework
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Firework {
+    flight: u8,
+    effects: Vec<FireworkEffect>,
+}
+
+impl Default for Firework {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Firework {
+    /// Creates a rocket with a flight duration of 1 and no effects. Rockets
+    /// without effects detonate with a puff of smoke.
+    pub fn new() -> Self {
+        Self {
+            flight: 1,
+            effects: Vec::new(),
+        }
+    }
+
+    /// Sets the flight duration, which is how much gunpowder the rocket was
+    /// crafted with. The value is clamped to `1..=3`.
+    #[must_use]
+    pub fn flight(mut self, flight: u8) -> Self {
+        self.flight = flight.clamp(1, 3);
+        self
+    }
+
+    /// Adds an explosion to the rocket. All explosions go off at the same
+    /// time.
+    #[must_use]
+    pub fn effect(mut self, effect: FireworkEffect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
+    /// Gets the flight duration of the rocket.
+    pub fn flight_duration(&self) -> u8 {
+        self.flight
+    }
+
+    /// Returns the explosions of the rocket.
+    pub fn effects(&self) -> &[FireworkEffect] {
+        &self.effects
+    }
+
+    /// Returns the number of ticks the rocket flies before detonating. Like
+    /// in vanilla, this is `10 * (flight + 1)` plus up to 11 random ticks.
+    pub fn lifetime(&self) -> Ticks {
+        10 * (self.flight as Ticks + 1)
+            + rand::random::<u8>() as Ticks % 6
+            + rand::random::<u8>() as Ticks % 7
+    }
+
+    /// Serializes this rocket into the `Fireworks` compound of a firework
+    /// rocket item.
+    pub fn to_nbt(&self) -> Compound {
+        Compound::from([
+            ("Flight".into(), Value::Byte(self.flight as i8)),
+            (
+                "Explosions".into(),
+                Value::List(List::Compound(
+                    self.effects.iter().map(FireworkEffect::to_nbt).collect(),
+                )),
+            ),
+        ])
+    }
+}
+
+/// Returns the tracked data giving a firework rocket entity an item with the
+/// `Fireworks` compound `nbt`, terminated by `0xff`.
+///
+/// There is no item registry to look up the ID of the firework rocket item,
+/// so the compound is carried by a stone item, whose ID is always 1. Clients
+/// read the explosions from any item, but render the rocket as the item, so
+/// this must only be sent right before the rocket detonates. Until then,
+/// clients render rockets without an item as a firework rocket.
+pub(crate) fn explosion_tracked_data(nbt: &Compound) -> Vec<u8> {
+    const ITEM_INDEX: u8 = 8;
+    const ITEM_STACK_TYPE: VarInt = VarInt(6);
+    const CARRIER_ITEM_ID: VarInt = VarInt(1);
+
+    let tag = Compound::from([("Fireworks".into(), Value::Compound(nbt.clone()))]);
+
+    let mut data = Vec::new();
+    let mut encode = || -> anyhow::Result<()> {
+        ITEM_INDEX.encode(&mut data)?;
+        ITEM_STACK_TYPE.encode(&mut data)?;
+        // The item stack is present, followed by its ID, count and NBT.
+        true.encode(&mut data)?;
+        CARRIER_ITEM_ID.encode(&mut data)?;
+        1_i8.encode(&mut data)?;
+        tag.encode(&mut data)?;
+        0xff_u8.encode(&mut data)
+    };
+    encode().expect("encoding tracked data into a vec should not fail");
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::EntityKind;
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::testing::connected_client;
+    use crate::world::LevelEvent;
+
+    #[test]
+    fn firework_nbt() {
+        let firework = Firework::new().flight(5).effect(
+            FireworkEffect::new(FireworkShape::Star)
+                .color(Color::new(0xff, 0x80, 0))
+                .fade_color(Color::WHITE)
+                .trail(true),
+        );
+
+        let nbt = firework.to_nbt();
+        assert_eq!(nbt["Flight"], Value::Byte(3));
+
+        let Value::List(List::Compound(explosions)) = &nbt["Explosions"] else {
+            panic!("explosions should be a list of compounds");
+        };
+        assert_eq!(explosions.len(), 1);
+        assert_eq!(explosions[0]["Type"], Value::Byte(2));
+        assert_eq!(explosions[0]["Colors"], Value::IntArray(vec![0xff8000]));
+        assert_eq!(explosions[0]["FadeColors"], Value::IntArray(vec![0xffffff]));
+        assert_eq!(explosions[0]["Flicker"], Value::Byte(0));
+        assert_eq!(explosions[0]["Trail"], Value::Byte(1));
+
+        let lifetime = firework.lifetime();
+        assert!((40..=51).contains(&lifetime));
+    }
+
+    #[test]
+    fn firework_launched_and_detonated() {
+        let (mut server, client) = connected_client();
+
+        let firework = Firework::new().effect(
+            FireworkEffect::new(FireworkShape::Creeper)
+                .color(Color::GREEN)
+                .flicker(true),
+        );
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.launch_firework_with_lifetime([0.5, 100.0, 3.5], &firework, 2);
+        server.tick();
+
+        let pkts = client.take_packets();
+        let rocket_id = pkts
+            .iter()
+            .find_map(|pkt| match pkt {
+                S2cPlayPacket::EntitySpawn(s)
+                    if s.kind == VarInt(EntityKind::FireworkRocket as i32) =>
+                {
+                    Some(s.entity_id)
+                }
+                _ => None,
+            })
+            .expect("rocket should have been spawned");
+        assert!(pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::WorldEvent(e) if e.event == LevelEvent::FireworkShot.id()
+        )));
+
+        let is_rocket_packet = |pkt: &S2cPlayPacket| match pkt {
+            S2cPlayPacket::EntityTrackerUpdate(u) => u.entity_id == rocket_id,
+            S2cPlayPacket::EntityStatus(s) => s.entity_id == rocket_id.0,
+            S2cPlayPacket::EntitiesDestroy(d) => d.entities.contains(&rocket_id),
+            _ => false,
+        };
+
+        server.tick();
+        assert!(!client.take_packets().iter().any(is_rocket_packet));
+
+        server.tick();
+        let pkts: Vec<_> = client
+            .take_packets()
+            .into_iter()
+            .filter(is_rocket_packet)
+            .collect();
+        assert!(matches!(
+            pkts.as_slice(),
+            [
+                S2cPlayPacket::EntityTrackerUpdate(_),
+                S2cPlayPacket::EntityStatus(s),
+                S2cPlayPacket::EntitiesDestroy(_),
+            ] if s.entity_status == 17
+        ));
+
+        server.tick();
+        assert!(!client.take_packets().iter().any(is_rocket_packet));
+    }
+}
@@ -0,0 +1,421 @@
+//! Functions: lists of commands executed together, like in data packs.
+//!
+//! A [`Function`] is parsed from the contents of an `.mcfunction` file, which
+//! has one command per line. Empty lines and lines starting with `#` are
+//! ignored. Functions are stored by name in [`Server::functions`] and can be
+//! executed with [`run`], with the built-in `function <name>` command, on a
+//! [schedule](Functions::schedule) or on [every tick](Functions::add_tick).
+//!
+//! Function names are resource locations such as `arena:reset`. Names without
+//! a namespace are in the `minecraft` namespace.
+//!
+//! The functions of a data pack can be loaded with
+//! [`Functions::load_data_pack`]. The functions in the
+//! `#minecraft:load` tag run at the beginning of the next tick and the
+//! functions in the `#minecraft:tick` tag run at the beginning of every tick.
+//!
+//! Functions run by the server itself are executed by
+//! [`CommandSender::Console`]. Every command is executed with
+//! [`command::execute`], so the sender must be allowed to execute it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::command::{self, CommandSender};
+use crate::config::Config;
+use crate::server::Server;
+use crate::Ticks;
+
+/// How deeply functions can call other functions. Calls beyond this depth are
+/// not executed, which stops functions calling themselves forever.
+pub const MAX_DEPTH: usize = 64;
+
+/// A list of commands.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Function {
+    commands: Vec<String>,
+}
+
+/// Contains the functions of a server and when to run them.
+#[derive(Clone, Default, Debug)]
+pub struct Functions {
+    functions: BTreeMap<String, Arc<Function>>,
+    /// The functions run at the beginning of every tick.
+    tick: Vec<String>,
+    /// The functions to run at the beginning of the next tick.
+    pending: Vec<String>,
+    /// Scheduled functions and the number of ticks until they run.
+    scheduled: Vec<(String, Ticks)>,
+    depth: usize,
+}
+
+#[derive(Deserialize)]
+struct FunctionTag {
+    values: Vec<String>,
+}
+
+impl Function {
+    /// Parses a function from the contents of an `.mcfunction` file.
+    pub fn parse(src: &str) -> Self {
+        Self {
+            commands: src
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    /// Loads a function from an `.mcfunction` file.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path)
+            .with_context(|| format!("failed to read function from {}", path.display()))?;
+        Ok(Self::parse(&src))
+    }
+
+    /// Gets the commands of this function in the order they are executed.
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+}
+
+impl FromIterator<String> for Function {
+    fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
+        Self {
+            commands: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Functions {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a function with the given name, replacing and returning the
+    /// function with the same name if there was one.
+    pub fn insert(&mut self, name: &str, function: Function) -> Option<Arc<Function>> {
+        self.functions.insert(full_name(name), Arc::new(function))
+    }
+
+    /// Removes the function with the given name. It is also removed from the
+    /// tick functions and its schedules are cancelled.
+    pub fn remove(&mut self, name: &str) -> Option<Arc<Function>> {
+        let name = full_name(name);
+        self.tick.retain(|n| *n != name);
+        self.pending.retain(|n| *n != name);
+        self.scheduled.retain(|(n, _)| *n != name);
+        self.functions.remove(&name)
+    }
+
+    /// Gets the function with the given name.
+    pub fn get(&self, name: &str) -> Option<&Arc<Function>> {
+        self.functions.get(&full_name(name))
+    }
+
+    /// Returns an iterator over all functions and their names in alphabetical
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Arc<Function>)> + '_ {
+        self.functions.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Returns the number of functions.
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Returns `true` if there are no functions.
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Runs the function with the given name at the beginning of every tick.
+    pub fn add_tick(&mut self, name: &str) {
+        let name = full_name(name);
+        if !self.tick.contains(&name) {
+            self.tick.push(name);
+        }
+    }
+
+    /// Stops running the function with the given name every tick. Returns
+    /// `true` if it was run every tick.
+    pub fn remove_tick(&mut self, name: &str) -> bool {
+        let name = full_name(name);
+        let len = self.tick.len();
+        self.tick.retain(|n| *n != name);
+        self.tick.len() != len
+    }
+
+    /// Schedules the function with the given name to run at the beginning of
+    /// the tick `delay` ticks after the current one. A delay of zero or less
+    /// runs it at the beginning of the next tick.
+    ///
+    /// Like the vanilla `schedule` command, this replaces the previous
+    /// schedule of the function.
+    pub fn schedule(&mut self, name: &str, delay: Ticks) {
+        let name = full_name(name);
+        self.scheduled.retain(|(n, _)| *n != name);
+        self.scheduled.push((name, delay.max(1)));
+    }
+
+    /// Cancels the schedule of the function with the given name. Returns
+    /// `true` if it was scheduled.
+    pub fn cancel(&mut self, name: &str) -> bool {
+        let name = full_name(name);
+        let len = self.scheduled.len();
+        self.scheduled.retain(|(n, _)| *n != name);
+        self.scheduled.len() != len
+    }
+
+    /// Loads the functions of the data pack in `dir`, which contains the
+    /// `data` directory of the data pack. Functions are read from
+    /// `data/<namespace>/functions`.
+    ///
+    /// The functions in the `minecraft:load` function tag are run at the
+    /// beginning of the next tick, and the functions in the `minecraft:tick`
+    /// function tag are [added to the tick functions](Self::add_tick).
+    /// Other tags are ignored.
+    ///
+    /// Returns the number of functions loaded.
+    pub fn load_data_pack(&mut self, dir: impl AsRef<Path>) -> anyhow::Result<usize> {
+        let data = dir.as_ref().join("data");
+        let mut count = 0;
+
+        for entry in fs::read_dir(&data)
+            .with_context(|| format!("failed to read data pack from {}", data.display()))?
+        {
+            let entry = entry?;
+            let namespace = entry.file_name().to_string_lossy().into_owned();
+            let functions = entry.path().join("functions");
+
+            if functions.is_dir() {
+                count += self.load_functions(&functions, &format!("{namespace}:"))?;
+            }
+        }
+
+        let tags = data.join("minecraft").join("tags").join("functions");
+        for (tag, tick) in [("load", false), ("tick", true)] {
+            let path = tags.join(tag).with_extension("json");
+            if !path.is_file() {
+                continue;
+            }
+
+            let json = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read function tag {}", path.display()))?;
+            let tag: FunctionTag = serde_json::from_str(&json)
+                .with_context(|| format!("invalid function tag {}", path.display()))?;
+
+            for name in tag.values {
+                if tick {
+                    self.add_tick(&name);
+                } else {
+                    self.pending.push(full_name(&name));
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Loads the `.mcfunction` files in `dir` and its subdirectories, naming
+    /// them by their path relative to `dir` after `prefix`.
+    fn load_functions(&mut self, dir: &Path, prefix: &str) -> anyhow::Result<usize> {
+        let mut count = 0;
+
+        for entry in fs::read_dir(dir)
+            .with_context(|| format!("failed to read functions from {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let Some(stem) = path.file_stem().map(|s| s.to_string_lossy()) else {
+                continue;
+            };
+
+            if path.is_dir() {
+                count += self.load_functions(&path, &format!("{prefix}{stem}/"))?;
+            } else if path.extension().is_some_and(|ext| ext == "mcfunction") {
+                self.insert(&format!("{prefix}{stem}"), Function::load(&path)?);
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+/// Executes the function with the given name on behalf of `sender`.
+///
+/// Returns the number of commands executed, or `None` if the function does
+/// not exist or functions are nested deeper than [`MAX_DEPTH`].
+pub fn run<C: Config>(server: &mut Server<C>, name: &str, sender: CommandSender) -> Option<usize> {
+    let function = server.functions.get(name)?.clone();
+
+    if server.functions.depth >= MAX_DEPTH {
+        tracing::warn!("not running function {name} because functions are nested too deeply");
+        return None;
+    }
+
+    server.functions.depth += 1;
+    let count = function
+        .commands
+        .iter()
+        .filter(|cmd| command::execute(server, sender, cmd))
+        .count();
+    server.functions.depth -= 1;
+
+    Some(count)
+}
+
+/// Runs the load functions, tick functions and scheduled functions which are
+/// due this tick.
+pub(crate) fn update<C: Config>(server: &mut Server<C>) {
+    let mut due = std::mem::take(&mut server.functions.pending);
+    due.extend(server.functions.tick.iter().cloned());
+    server.functions.scheduled.retain_mut(|(name, delay)| {
+        *delay -= 1;
+        if *delay <= 0 {
+            due.push(name.clone());
+        }
+        *delay > 0
+    });
+
+    for name in due {
+        if run(server, &name, CommandSender::Console).is_none() {
+            tracing::warn!("function {name} could not be run");
+        }
+    }
+}
+
+/// Adds the `minecraft` namespace to names without a namespace.
+fn full_name(name: &str) -> String {
+    if name.contains(':') {
+        name.to_owned()
+    } else {
+        format!("minecraft:{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{connected_client, messages};
+
+    #[test]
+    fn data_pack_loaded() {
+        let dir = std::env::temp_dir().join(format!("valence-data-pack-{}", std::process::id()));
+        let functions = dir.join("data/arena/functions");
+        let tags = dir.join("data/minecraft/tags/functions");
+        fs::create_dir_all(functions.join("round")).unwrap();
+        fs::create_dir_all(&tags).unwrap();
+
+        fs::write(
+            functions.join("setup.mcfunction"),
+            "# Prepares the arena.\n\nsay hello\n  kick @a  \n",
+        )
+        .unwrap();
+        fs::write(functions.join("round/end.mcfunction"), "say bye").unwrap();
+        fs::write(functions.join("notes.txt"), "not a function").unwrap();
+        fs::write(tags.join("tick.json"), r#"{"values": ["arena:round/end"]}"#).unwrap();
+        fs::write(tags.join("load.json"), r#"{"values": ["arena:setup"]}"#).unwrap();
+
+        let mut loaded = Functions::new();
+        assert_eq!(loaded.load_data_pack(&dir).unwrap(), 2);
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(
+            loaded.get("arena:setup").unwrap().commands(),
+            ["say hello", "kick @a"]
+        );
+        assert_eq!(
+            loaded.get("arena:round/end").unwrap().commands(),
+            ["say bye"]
+        );
+        assert_eq!(loaded.tick, ["arena:round/end"]);
+        assert_eq!(loaded.pending, ["arena:setup"]);
+    }
+
+    #[test]
+    fn names_default_to_minecraft() {
+        let mut functions = Functions::new();
+        functions.insert("reset", Function::parse("stop"));
+
+        assert!(functions.get("minecraft:reset").is_some());
+        assert_eq!(functions.iter().next().unwrap().0, "minecraft:reset");
+
+        functions.schedule("reset", 20);
+        functions.schedule("reset", 0);
+        assert_eq!(functions.scheduled, [("minecraft:reset".to_owned(), 1)]);
+        assert!(functions.cancel("minecraft:reset"));
+        assert!(functions.remove("reset").is_some());
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn functions_run_and_scheduled() {
+        let (mut server, client) = connected_client();
+
+        let s = server.server_mut();
+        s.commands.register("say", |server, ctx| {
+            server.clients.broadcast(ctx.args.clone());
+            Ok(None)
+        });
+        s.functions.insert(
+            "arena:greet",
+            Function::parse("say hello\nfunction arena:inner"),
+        );
+        s.functions
+            .insert("arena:inner", Function::parse("# Comment\nsay inner"));
+        s.functions
+            .insert("recurse", Function::parse("function recurse"));
+
+        assert!(command::execute(
+            s,
+            CommandSender::Console,
+            "function arena:greet"
+        ));
+        assert_eq!(run(s, "recurse", CommandSender::Console), Some(1));
+        assert!(!command::execute(
+            s,
+            CommandSender::Client(client.id()),
+            "function arena:greet"
+        ));
+        server.tick();
+        assert_eq!(
+            messages(&client),
+            [
+                "hello",
+                "inner",
+                "You do not have permission to use this command"
+            ]
+        );
+
+        let s = server.server_mut();
+        assert!(command::execute(
+            s,
+            CommandSender::Console,
+            "schedule function arena:greet 2t"
+        ));
+        s.functions.add_tick("arena:inner");
+        server.tick();
+        assert_eq!(messages(&client), ["inner"]);
+        server.tick();
+        assert_eq!(messages(&client), ["inner", "hello", "inner"]);
+
+        let s = server.server_mut();
+        assert!(s.functions.remove_tick("arena:inner"));
+        s.functions.schedule("greet", 1);
+        assert!(command::execute(
+            s,
+            CommandSender::Console,
+            "schedule clear greet"
+        ));
+        server.tick();
+        assert!(messages(&client).is_empty());
+    }
+}
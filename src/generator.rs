@@ -0,0 +1,164 @@
+//! Filling new chunks with blocks.
+//!
+//! A [`Generator`] set on a world with [`World::set_generator`] determines the
+//! blocks of every chunk created with [`Chunks::insert`].
+//!
+//! [`World::set_generator`]: crate::world::World::set_generator
+//! [`Chunks::insert`]: crate::chunk::Chunks::insert
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::block::BlockState;
+use crate::chunk::{ChunkPos, UnloadedChunk};
+
+/// Determines the blocks of new chunks in a world.
+///
+/// Generators also decide if worlds are shown as superflat, see
+/// [`Generator::is_flat`].
+#[derive(Clone)]
+pub struct Generator {
+    kind: GeneratorKind,
+    flat: bool,
+}
+
+#[derive(Clone)]
+enum GeneratorKind {
+    Layers(Vec<(BlockState, usize)>),
+    Fn(Arc<GeneratorFn>),
+}
+
+type GeneratorFn = dyn Fn(ChunkPos, i32, usize) -> UnloadedChunk + Send + Sync;
+
+impl Generator {
+    /// A generator which leaves all chunks empty, like the vanilla "The
+    /// Void" superflat preset.
+    pub fn void() -> Self {
+        Self::superflat([])
+    }
+
+    /// A generator which fills all chunks with horizontal layers of blocks,
+    /// like vanilla superflat worlds.
+    ///
+    /// Each layer is a block state and its thickness in blocks, listed from
+    /// the bottom of the world upwards. Layers above the top of the world are
+    /// ignored.
+    ///
+    /// ```
+    /// # use valence::block::BlockState;
+    /// # use valence::generator::Generator;
+    /// let classic = Generator::superflat([
+    ///     (BlockState::BEDROCK, 1),
+    ///     (BlockState::DIRT, 2),
+    ///     (BlockState::GRASS_BLOCK, 1),
+    /// ]);
+    /// assert!(classic.is_flat());
+    /// ```
+    pub fn superflat(layers: impl IntoIterator<Item = (BlockState, usize)>) -> Self {
+        Self {
+            kind: GeneratorKind::Layers(layers.into_iter().collect()),
+            flat: true,
+        }
+    }
+
+    /// A generator which calls `f` for every new chunk.
+    ///
+    /// `f` is called with the position of the chunk, the y coordinate of the
+    /// bottom of the world and the number of chunk sections in the world's
+    /// dimension. If the returned chunk does not have the height of the
+    /// world, it is padded with air or cut off at the top.
+    pub fn from_fn(
+        f: impl Fn(ChunkPos, i32, usize) -> UnloadedChunk + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            kind: GeneratorKind::Fn(Arc::new(f)),
+            flat: false,
+        }
+    }
+
+    /// Sets if worlds using this generator are shown as superflat.
+    #[must_use]
+    pub fn with_flat(mut self, flat: bool) -> Self {
+        self.flat = flat;
+        self
+    }
+
+    /// Gets if worlds using this generator are shown as superflat. Superflat
+    /// worlds have a horizon line lower than normal worlds and no void fog.
+    ///
+    /// This is `true` for [`Generator::superflat`] and [`Generator::void`]
+    /// and `false` for [`Generator::from_fn`] unless changed with
+    /// [`Generator::with_flat`].
+    pub fn is_flat(&self) -> bool {
+        self.flat
+    }
+
+    /// Returns the chunk this generator produces at every position, if it
+    /// does not depend on the position.
+    pub(crate) fn prototype(&self, section_count: usize) -> Option<UnloadedChunk> {
+        match &self.kind {
+            GeneratorKind::Layers(layers) => {
+                let mut column = Vec::with_capacity(section_count * 16);
+                for &(block, thickness) in layers {
+                    column.extend(std::iter::repeat_n(block, thickness));
+                }
+                column.resize(section_count * 16, BlockState::AIR);
+
+                Some(UnloadedChunk::from_fn(section_count, |_, y, _| column[y]))
+            }
+            GeneratorKind::Fn(_) => None,
+        }
+    }
+
+    /// Generates the chunk at `pos`.
+    pub(crate) fn generate(
+        &self,
+        pos: ChunkPos,
+        min_y: i32,
+        section_count: usize,
+    ) -> UnloadedChunk {
+        match &self.kind {
+            GeneratorKind::Layers(_) => self
+                .prototype(section_count)
+                .expect("layered generators have a prototype"),
+            GeneratorKind::Fn(f) => f(pos, min_y, section_count),
+        }
+    }
+}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Generator");
+
+        match &self.kind {
+            GeneratorKind::Layers(layers) => s.field("layers", layers),
+            GeneratorKind::Fn(_) => s.field("layers", &format_args!("<custom>")),
+        };
+
+        s.field("flat", &self.flat).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn superflat_layers() {
+        let generator = Generator::superflat([(BlockState::BEDROCK, 1), (BlockState::DIRT, 18)]);
+        let chunk = generator.prototype(2).unwrap();
+
+        assert_eq!(chunk.height(), 32);
+        assert_eq!(chunk.get_block_state(3, 0, 7), BlockState::BEDROCK);
+        assert_eq!(chunk.get_block_state(3, 18, 7), BlockState::DIRT);
+        assert_eq!(chunk.get_block_state(3, 19, 7), BlockState::AIR);
+
+        let void = Generator::void().prototype(1).unwrap();
+        assert!(void.get_block_state(0, 0, 0).is_air());
+        assert!(Generator::void().is_flat());
+        assert!(
+            !Generator::from_fn(|_, _, n| UnloadedChunk::filled(vec![BlockState::AIR; n]))
+                .is_flat()
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Invisible entities which can be clicked.
+
+use vek::Vec3;
+
+use crate::client::ClientEvent;
+use crate::config::Config;
+use crate::entity::{Entities, EntityId, EntityKind, TrackedData, Visibility};
+use crate::world::WorldId;
+
+/// An invisible cube in a world which clients can click, for buttons and
+/// clickable holograms.
+///
+/// The protocol has no dedicated entity for hitboxes, so the cube is an
+/// invisible, silent slime without AI or gravity. Left and right clicks on it
+/// are received as [`ClientEvent::InteractWithEntity`] with the ID of
+/// [`Self::entity`], which is most easily checked with [`Self::is_clicked`].
+/// Like other mobs, the slime pushes away players who walk into it.
+///
+/// The slime is created, updated, and removed by [`Self::update`], which must
+/// be called after the hitbox is modified for the changes to become visible.
+/// The hitbox does not own its entity. Use [`Self::remove`] to despawn it
+/// when the hitbox is no longer needed.
+#[derive(Debug)]
+pub struct Hitbox {
+    world: WorldId,
+    position: Vec3<f64>,
+    size: u8,
+    visibility: Visibility,
+    entity: Option<EntityId>,
+}
+
+impl Hitbox {
+    /// The width and height of a hitbox of size 1, in blocks.
+    pub const UNIT_WIDTH: f64 = 0.51000005;
+    /// The largest size clients accept for a hitbox.
+    pub const MAX_SIZE: u8 = 127;
+
+    /// Creates a hitbox with the center of its bottom face at `position`. The
+    /// size is clamped to `1..=`[`Self::MAX_SIZE`]. No entity is spawned until
+    /// [`Self::update`] is called.
+    pub fn new(world: WorldId, position: impl Into<Vec3<f64>>, size: u8) -> Self {
+        Self {
+            world,
+            position: position.into(),
+            size: size.clamp(1, Self::MAX_SIZE),
+            visibility: Visibility::All,
+            entity: None,
+        }
+    }
+
+    /// Gets the world the hitbox is located in.
+    pub fn world(&self) -> WorldId {
+        self.world
+    }
+
+    /// Sets the world the hitbox is located in.
+    pub fn set_world(&mut self, world: WorldId) {
+        self.world = world;
+    }
+
+    /// Gets the position of the center of the bottom face of the hitbox.
+    pub fn position(&self) -> Vec3<f64> {
+        self.position
+    }
+
+    /// Sets the position of the center of the bottom face of the hitbox.
+    pub fn set_position(&mut self, position: impl Into<Vec3<f64>>) {
+        self.position = position.into();
+    }
+
+    /// Gets the size of the hitbox. Its width and height are
+    /// [`Self::UNIT_WIDTH`] times the size.
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    /// Sets the size of the hitbox, clamped to `1..=`[`Self::MAX_SIZE`].
+    pub fn set_size(&mut self, size: u8) {
+        self.size = size.clamp(1, Self::MAX_SIZE);
+    }
+
+    /// Gets the width and height of the hitbox in blocks.
+    pub fn width(&self) -> f64 {
+        Self::UNIT_WIDTH * self.size as f64
+    }
+
+    /// Gets the clients which can click the hitbox.
+    pub fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+
+    /// Sets the clients which can click the hitbox.
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    /// Returns the ID of the entity currently making up the hitbox.
+    pub fn entity(&self) -> Option<EntityId> {
+        self.entity
+    }
+
+    /// Returns if `event` is a click on this hitbox.
+    pub fn is_clicked(&self, event: &ClientEvent) -> bool {
+        matches!(
+            event,
+            ClientEvent::InteractWithEntity { id, .. } if Some(*id) == self.entity
+        )
+    }
+
+    /// Spawns or updates the entity of the hitbox so that it matches its
+    /// current state. The entity is spawned again if it was removed by other
+    /// means.
+    pub fn update<C: Config>(&mut self, entities: &mut Entities<C>)
+    where
+        C::EntityState: Default,
+    {
+        let entity = match self.entity.and_then(|id| entities.get_mut(id)) {
+            Some(entity) => entity,
+            None => {
+                let (id, entity) = entities.insert(EntityKind::Slime, C::EntityState::default());
+
+                if let TrackedData::Slime(slime) = entity.data_mut() {
+                    slime.set_invisible(true);
+                    slime.set_silent(true);
+                    slime.set_no_gravity(true);
+                    slime.set_ai_disabled(true);
+                }
+
+                self.entity = Some(id);
+                entities.get_mut(id).unwrap()
+            }
+        };
+
+        entity.set_world(self.world);
+        entity.set_position(self.position);
+
+        if entity.visibility() != &self.visibility {
+            entity.set_visibility(self.visibility.clone());
+        }
+
+        if let TrackedData::Slime(slime) = entity.data_mut() {
+            if slime.get_slime_size() != self.size as i32 {
+                slime.set_slime_size(self.size);
+            }
+        }
+    }
+
+    /// Despawns the entity of the hitbox.
+    pub fn remove<C: Config>(self, entities: &mut Entities<C>) {
+        if let Some(id) = self.entity {
+            entities.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Hand, InteractWithEntityKind};
+    use crate::testing::test_server;
+
+    #[test]
+    fn hitbox_spawns_sized_invisible_slime() {
+        let mut server = test_server();
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+
+        let mut hitbox = Hitbox::new(world_id, [0.5, 100.0, 0.5], 0);
+        assert_eq!(hitbox.size(), 1);
+        hitbox.set_size(4);
+        hitbox.update(&mut s.entities);
+
+        let id = hitbox.entity().unwrap();
+        let entity = s.entities.get(id).unwrap();
+        let TrackedData::Slime(slime) = entity.data() else {
+            panic!("hitbox is not a slime");
+        };
+        assert!(slime.get_invisible());
+        assert_eq!(slime.get_slime_size(), 4);
+        assert_eq!(entity.hitbox().size().w, hitbox.width());
+
+        let click = ClientEvent::InteractWithEntity {
+            id,
+            sneaking: false,
+            kind: InteractWithEntityKind::Interact(Hand::Main),
+        };
+        assert!(hitbox.is_clicked(&click));
+
+        s.entities.remove(id);
+        hitbox.update(&mut s.entities);
+        assert_ne!(hitbox.entity(), Some(id));
+        assert!(!hitbox.is_clicked(&click));
+
+        hitbox.remove(&mut s.entities);
+        assert!(s.entities.is_empty());
+    }
+}
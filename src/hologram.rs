@@ -0,0 +1,228 @@
+//! Floating text in the world.
+
+use vek::Vec3;
+
+use crate::config::Config;
+use crate::entity::{Entities, EntityId, EntityKind, TrackedData, Visibility};
+use crate::text::Text;
+use crate::world::WorldId;
+
+/// Lines of text floating at a position in a world, visible to some or all
+/// clients.
+///
+/// Each line is displayed as the name of an invisible marker armor stand, since
+/// the protocol has no dedicated entity for text. The armor stands are
+/// created, updated, and removed by [`Self::update`], which must be called
+/// after the hologram is modified for the changes to become visible.
+///
+/// The hologram does not own its entities. Use [`Self::remove`] to despawn
+/// them when the hologram is no longer needed.
+#[derive(Debug)]
+pub struct Hologram {
+    world: WorldId,
+    position: Vec3<f64>,
+    lines: Vec<Text>,
+    line_spacing: f64,
+    visibility: Visibility,
+    /// The armor stand showing each line, from top to bottom.
+    entities: Vec<EntityId>,
+}
+
+impl Hologram {
+    /// The vertical distance between lines used by [`Self::new`], in blocks.
+    pub const DEFAULT_LINE_SPACING: f64 = 0.25;
+
+    /// Creates a hologram with its top line at `position`. No entities are
+    /// spawned until [`Self::update`] is called.
+    pub fn new(world: WorldId, position: impl Into<Vec3<f64>>, lines: Vec<Text>) -> Self {
+        Self {
+            world,
+            position: position.into(),
+            lines,
+            line_spacing: Self::DEFAULT_LINE_SPACING,
+            visibility: Visibility::All,
+            entities: Vec::new(),
+        }
+    }
+
+    /// Gets the world the hologram is located in.
+    pub fn world(&self) -> WorldId {
+        self.world
+    }
+
+    /// Sets the world the hologram is located in.
+    pub fn set_world(&mut self, world: WorldId) {
+        self.world = world;
+    }
+
+    /// Gets the position of the top line of the hologram.
+    pub fn position(&self) -> Vec3<f64> {
+        self.position
+    }
+
+    /// Sets the position of the top line of the hologram. The other lines are
+    /// placed below it.
+    pub fn set_position(&mut self, position: impl Into<Vec3<f64>>) {
+        self.position = position.into();
+    }
+
+    /// Gets the lines of the hologram from top to bottom.
+    pub fn lines(&self) -> &[Text] {
+        &self.lines
+    }
+
+    /// Sets the lines of the hologram from top to bottom.
+    pub fn set_lines(&mut self, lines: Vec<Text>) {
+        self.lines = lines;
+    }
+
+    /// Gets the vertical distance between lines in blocks.
+    pub fn line_spacing(&self) -> f64 {
+        self.line_spacing
+    }
+
+    /// Sets the vertical distance between lines in blocks.
+    pub fn set_line_spacing(&mut self, line_spacing: f64) {
+        self.line_spacing = line_spacing;
+    }
+
+    /// Gets the clients the hologram is visible to.
+    pub fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+
+    /// Sets the clients the hologram is visible to.
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    /// Returns the IDs of the entities currently displaying the lines of the
+    /// hologram, from top to bottom.
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    /// Spawns, updates, and despawns the entities of the hologram so that they
+    /// match its current state. Entities of the hologram which were removed
+    /// by other means are spawned again.
+    pub fn update<C: Config>(&mut self, entities: &mut Entities<C>)
+    where
+        C::EntityState: Default,
+    {
+        for id in self
+            .entities
+            .drain(self.lines.len().min(self.entities.len())..)
+        {
+            entities.remove(id);
+        }
+
+        for (i, line) in self.lines.iter().enumerate() {
+            let entity = match self.entities.get(i).and_then(|&id| entities.get_mut(id)) {
+                Some(entity) => entity,
+                None => {
+                    let (id, entity) =
+                        entities.insert(EntityKind::ArmorStand, C::EntityState::default());
+
+                    if let TrackedData::ArmorStand(stand) = entity.data_mut() {
+                        stand.set_invisible(true);
+                        stand.set_marker(true);
+                        stand.set_no_gravity(true);
+                        stand.set_name_visible(true);
+                    }
+
+                    if i < self.entities.len() {
+                        self.entities[i] = id;
+                    } else {
+                        self.entities.push(id);
+                    }
+
+                    entities.get_mut(id).unwrap()
+                }
+            };
+
+            entity.set_world(self.world);
+            entity.set_position(self.position - Vec3::unit_y() * (i as f64 * self.line_spacing));
+
+            if entity.visibility() != &self.visibility {
+                entity.set_visibility(self.visibility.clone());
+            }
+
+            if let TrackedData::ArmorStand(stand) = entity.data_mut() {
+                if stand.get_custom_name() != Some(line) {
+                    stand.set_custom_name(line.clone());
+                }
+            }
+        }
+    }
+
+    /// Despawns the entities of the hologram.
+    pub fn remove<C: Config>(self, entities: &mut Entities<C>) {
+        for id in self.entities {
+            entities.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::testing::{test_server, MockClient};
+
+    #[test]
+    fn hologram_per_client_visibility() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        server.tick();
+        alice.take_packets();
+        bob.take_packets();
+
+        let spawned_stands = |client: &MockClient| {
+            client
+                .take_packets()
+                .iter()
+                .filter(|pkt| matches!(pkt, S2cPlayPacket::EntitySpawn(_)))
+                .count()
+        };
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let alice_uuid = s.clients.get(alice.id()).unwrap().uuid();
+
+        let mut hologram = Hologram::new(
+            world_id,
+            [0.5, 102.0, 3.5],
+            vec!["first".into(), "second".into()],
+        );
+        hologram.set_visibility(Visibility::Only([alice_uuid].into()));
+        hologram.update(&mut s.entities);
+
+        let positions: Vec<_> = hologram
+            .entities()
+            .iter()
+            .map(|&id| s.entities.get(id).unwrap().position())
+            .collect();
+        assert_eq!(
+            positions,
+            [Vec3::new(0.5, 102.0, 3.5), Vec3::new(0.5, 101.75, 3.5)]
+        );
+
+        server.tick();
+        assert_eq!(spawned_stands(&alice), 2);
+        assert_eq!(spawned_stands(&bob), 0);
+
+        let s = server.server_mut();
+        hologram.set_lines(vec!["only".into()]);
+        hologram.set_visibility(Visibility::All);
+        hologram.update(&mut s.entities);
+        assert_eq!(hologram.entities().len(), 1);
+
+        server.tick();
+        assert_eq!(spawned_stands(&bob), 1);
+
+        hologram.remove(&mut server.server_mut().entities);
+        assert_eq!(server.server().entities.len(), 0);
+    }
+}
@@ -0,0 +1,73 @@
+//! Announcing the server to clients on the local network.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+
+use crate::config::Config;
+use crate::server::SharedServer;
+
+/// The multicast address the vanilla client listens on for LAN games.
+const LAN_MULTICAST_ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 2, 60), 4445);
+
+/// The time between announcements, the same as in vanilla.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Periodically announces the server listening on `port` with the given
+/// message of the day until the server shuts down.
+pub(crate) async fn announce_lan<C: Config>(shared: SharedServer<C>, port: u16, motd: String) {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::error!("failed to bind LAN announcement socket: {e}");
+            return;
+        }
+    };
+
+    let msg = announcement(&motd, port);
+    let mut interval = tokio::time::interval(ANNOUNCE_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shared.shutdown_signal() => return,
+        }
+
+        if let Err(e) = socket.send_to(msg.as_bytes(), LAN_MULTICAST_ADDR).await {
+            tracing::debug!("failed to send LAN announcement: {e}");
+        }
+    }
+}
+
+fn announcement(motd: &str, port: u16) -> String {
+    format!("[MOTD]{motd}[/MOTD][AD]{port}[/AD]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_server;
+
+    #[test]
+    fn announcement_format() {
+        assert_eq!(
+            announcement("A Valence Server", 25565),
+            "[MOTD]A Valence Server[/MOTD][AD]25565[/AD]"
+        );
+    }
+
+    #[test]
+    fn announcing_stops_on_shutdown() {
+        let server = test_server();
+        let shared = server.server().shared.clone();
+        let handle = shared.tokio_handle();
+
+        let task = handle.spawn(announce_lan(shared.clone(), 25565, "motd".into()));
+        shared.shutdown::<_, Box<dyn std::error::Error + Send + Sync>>(Ok(()));
+        handle
+            .block_on(async { tokio::time::timeout(Duration::from_secs(5), task).await })
+            .expect("LAN announcements did not stop")
+            .unwrap();
+    }
+}
@@ -86,39 +86,69 @@
 pub use async_trait::async_trait;
 #[doc(inline)]
 pub use server::start_server;
+#[cfg(feature = "protocol")]
 #[doc(inline)]
-pub use {serde_nbt as nbt, uuid, vek};
+pub use valence_protocol as protocol;
+#[cfg(not(feature = "protocol"))]
+pub(crate) use valence_protocol as protocol;
+pub(crate) use valence_protocol::block_pos;
+#[doc(inline)]
+pub use valence_protocol::{ident, nbt, text};
+#[doc(inline)]
+pub use {uuid, vek};
 
+pub mod animation;
 pub mod biome;
 pub mod block;
-mod block_pos;
+mod block_physics;
+mod block_placement;
+mod block_schema;
 mod bvh;
 pub mod chunk;
 mod chunk_pos;
 pub mod client;
+pub mod command;
+pub mod component;
 pub mod config;
+pub mod contraption;
 pub mod dimension;
 pub mod entity;
-pub mod ident;
+pub mod event_bus;
+pub mod favicon;
+pub mod firework;
+pub mod function;
+pub mod generator;
+pub mod hitbox;
+pub mod hologram;
+mod lan;
+pub mod metrics;
+pub mod mojang;
+pub mod packet_log;
+pub mod permission;
+pub mod player_data;
 pub mod player_list;
 pub mod player_textures;
-#[allow(dead_code)]
-#[doc(hidden)]
-pub mod protocol;
+pub mod protection;
+pub mod scoreboard;
+pub mod selector;
 pub mod server;
 mod slab;
 mod slab_rc;
 mod slab_versioned;
 pub mod spatial_index;
-pub mod text;
+pub mod storage;
+pub mod system;
+pub mod testing;
+pub mod transport;
 pub mod util;
 pub mod world;
+#[cfg(feature = "worldgen")]
+pub mod worldgen;
+
+#[doc(inline)]
+pub use valence_protocol::{PROTOCOL_VERSION, VERSION_NAME};
 
-/// The Minecraft protocol version this library currently targets.
-pub const PROTOCOL_VERSION: i32 = 760;
-/// The name of the Minecraft version this library currently targets, e.g.
-/// "1.8.2"
-pub const VERSION_NAME: &str = "1.19.2";
+include!(concat!(env!("OUT_DIR"), "/version.rs"));
 
 /// The namespace for this library used internally for
 /// [identifiers](crate::ident::Ident).
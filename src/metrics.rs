@@ -0,0 +1,307 @@
+//! Server metrics and an optional [Prometheus] exporter.
+//!
+//! Metrics are always collected and can be read with
+//! [`SharedServer::metrics`]. If [`Config::metrics_address`] returns an
+//! address, the metrics are also served over HTTP in the Prometheus text
+//! format.
+//!
+//! [Prometheus]: https://prometheus.io
+//! [`Config::metrics_address`]: crate::config::Config::metrics_address
+
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::Config;
+use crate::server::SharedServer;
+
+/// Counters and gauges describing the state of a running server.
+#[derive(Default, Debug)]
+pub struct Metrics {
+    ticks: AtomicU64,
+    tick_duration_sum_nanos: AtomicU64,
+    last_tick_duration_nanos: AtomicU64,
+    packets_sent: AtomicU64,
+    packets_received: AtomicU64,
+    clients: AtomicU64,
+    chunks: AtomicU64,
+    world_memory_usage: AtomicU64,
+    entities: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of ticks recorded so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed)
+    }
+
+    /// Returns the time the last tick took to complete, excluding the time
+    /// spent sleeping until the next tick.
+    pub fn last_tick_duration(&self) -> Duration {
+        Duration::from_nanos(self.last_tick_duration_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Returns the average time a tick took to complete since the server
+    /// started.
+    pub fn average_tick_duration(&self) -> Duration {
+        self.tick_duration_sum_nanos
+            .load(Ordering::Relaxed)
+            .checked_div(self.ticks())
+            .map_or(Duration::ZERO, Duration::from_nanos)
+    }
+
+    /// Returns the total number of play packets sent to clients.
+    pub fn packets_sent(&self) -> u64 {
+        self.packets_sent.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of play packets received from clients.
+    pub fn packets_received(&self) -> u64 {
+        self.packets_received.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of clients at the end of the last tick.
+    pub fn clients(&self) -> u64 {
+        self.clients.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of loaded chunks in all worlds at the end of the
+    /// last tick.
+    pub fn chunks(&self) -> u64 {
+        self.chunks.load(Ordering::Relaxed)
+    }
+
+    /// Returns the estimated memory used by all worlds in bytes at the end of
+    /// the last tick. See [`World::memory_usage`].
+    ///
+    /// [`World::memory_usage`]: crate::world::World::memory_usage
+    pub fn world_memory_usage(&self) -> u64 {
+        self.world_memory_usage.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of entities at the end of the last tick.
+    pub fn entities(&self) -> u64 {
+        self.entities.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_tick(
+        &self,
+        duration: Duration,
+        clients: usize,
+        chunks: usize,
+        world_memory_usage: usize,
+        entities: usize,
+    ) {
+        let nanos = duration.as_nanos() as u64;
+
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+        self.tick_duration_sum_nanos
+            .fetch_add(nanos, Ordering::Relaxed);
+        self.last_tick_duration_nanos
+            .store(nanos, Ordering::Relaxed);
+        self.clients.store(clients as u64, Ordering::Relaxed);
+        self.chunks.store(chunks as u64, Ordering::Relaxed);
+        self.world_memory_usage
+            .store(world_memory_usage as u64, Ordering::Relaxed);
+        self.entities.store(entities as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_packets_sent(&self) {
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_packets_received(&self) {
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Encodes the metrics in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            let _ = writeln!(out, "{name} {value}");
+        };
+
+        metric(
+            "valence_tick_duration_seconds",
+            "gauge",
+            "The time the last tick took to complete.",
+            self.last_tick_duration().as_secs_f64().to_string(),
+        );
+        metric(
+            "valence_tick_duration_seconds_sum",
+            "counter",
+            "The total time spent in ticks.",
+            (self.tick_duration_sum_nanos.load(Ordering::Relaxed) as f64 / 1e9).to_string(),
+        );
+        metric(
+            "valence_ticks_total",
+            "counter",
+            "The number of completed ticks.",
+            self.ticks().to_string(),
+        );
+        metric(
+            "valence_packets_sent_total",
+            "counter",
+            "The number of play packets sent to clients.",
+            self.packets_sent().to_string(),
+        );
+        metric(
+            "valence_packets_received_total",
+            "counter",
+            "The number of play packets received from clients.",
+            self.packets_received().to_string(),
+        );
+        metric(
+            "valence_clients",
+            "gauge",
+            "The number of connected clients.",
+            self.clients().to_string(),
+        );
+        metric(
+            "valence_chunks",
+            "gauge",
+            "The number of loaded chunks in all worlds.",
+            self.chunks().to_string(),
+        );
+        metric(
+            "valence_world_memory_bytes",
+            "gauge",
+            "The estimated memory used by all worlds.",
+            self.world_memory_usage().to_string(),
+        );
+        metric(
+            "valence_entities",
+            "gauge",
+            "The number of entities.",
+            self.entities().to_string(),
+        );
+
+        out
+    }
+}
+
+/// How long to wait after failing to accept a connection.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Serves the server's metrics over HTTP until the server shuts down.
+pub(crate) async fn serve_metrics<C: Config>(shared: SharedServer<C>, address: SocketAddr) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("failed to start metrics listener on {address}: {e}");
+            return;
+        }
+    };
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shared.shutdown_signal() => return,
+        };
+
+        match accepted {
+            Ok((stream, _)) => {
+                let shared = shared.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_metrics_request(&shared, stream).await {
+                        tracing::debug!("error while serving metrics: {e}");
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!("failed to accept metrics connection: {e}");
+                // Errors such as running out of file descriptors usually
+                // persist for a while, so don't retry right away.
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+            }
+        }
+    }
+}
+
+async fn handle_metrics_request<C: Config>(
+    shared: &SharedServer<C>,
+    mut stream: TcpStream,
+) -> std::io::Result<()> {
+    // The request is not inspected. Every request gets the metrics.
+    let mut buf = [0; 1024];
+    let _ = stream.read(&mut buf).await?;
+
+    let body = shared.metrics().to_prometheus();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: \
+         {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_server;
+
+    #[test]
+    fn prometheus_format() {
+        let metrics = Metrics::new();
+        metrics.record_tick(Duration::from_millis(10), 3, 100, 4096, 7);
+        metrics.record_tick(Duration::from_millis(30), 2, 90, 2048, 5);
+        metrics.inc_packets_sent();
+
+        assert_eq!(metrics.ticks(), 2);
+        assert_eq!(metrics.average_tick_duration(), Duration::from_millis(20));
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("valence_clients 2\n"));
+        assert!(text.contains("valence_chunks 90\n"));
+        assert!(text.contains("valence_world_memory_bytes 2048\n"));
+        assert!(text.contains("valence_entities 5\n"));
+        assert!(text.contains("valence_packets_sent_total 1\n"));
+        assert!(text.contains("# TYPE valence_ticks_total counter\n"));
+    }
+
+    #[test]
+    fn metrics_served_until_shutdown() {
+        let server = test_server();
+        let shared = server.server().shared.clone();
+        let handle = shared.tokio_handle();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let task = handle.spawn(serve_metrics(shared.clone(), address));
+        let body = handle.block_on(async {
+            loop {
+                match TcpStream::connect(address).await {
+                    Ok(mut stream) => {
+                        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+                        let mut body = String::new();
+                        stream.read_to_string(&mut body).await.unwrap();
+                        break body;
+                    }
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        });
+        assert!(body.contains("valence_ticks_total"));
+
+        shared.shutdown::<_, Box<dyn std::error::Error + Send + Sync>>(Ok(()));
+        handle
+            .block_on(async { tokio::time::timeout(Duration::from_secs(5), task).await })
+            .expect("metrics server did not stop")
+            .unwrap();
+    }
+}
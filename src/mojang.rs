@@ -0,0 +1,256 @@
+//! Access to the Mojang API and session servers.
+//!
+//! The [`MojangApi`] returned by [`Config::mojang_api`] is used to
+//! authenticate players in online mode, unless [`Config::authenticate`] is
+//! overridden. It is also available through [`SharedServer::mojang_api`] to
+//! look up the profiles of other players.
+//!
+//! [`Config::mojang_api`]: crate::config::Config::mojang_api
+//! [`Config::authenticate`]: crate::config::Config::authenticate
+//! [`SharedServer::mojang_api`]: crate::server::SharedServer::mojang_api
+
+use std::net::IpAddr;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use reqwest::{Client as HttpClient, StatusCode};
+use serde::Deserialize;
+use url::Url;
+use uuid::Uuid;
+
+use crate::player_textures::SignedPlayerTextures;
+use crate::protocol::packets::Property;
+
+/// The profile of a player as returned by the session server.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct GameProfile {
+    /// The UUID of the player.
+    pub id: Uuid,
+    /// The username of the player.
+    pub name: String,
+    /// The properties of the profile, such as the player's textures.
+    #[serde(default)]
+    pub properties: Vec<Property>,
+}
+
+impl GameProfile {
+    /// Gets the signed skin and cape of the player from the `textures`
+    /// property, if present.
+    pub fn textures(&self) -> anyhow::Result<Option<SignedPlayerTextures>> {
+        match self.properties.iter().find(|p| p.name == "textures") {
+            Some(p) => Ok(Some(SignedPlayerTextures::from_base64(
+                p.value.clone(),
+                p.signature
+                    .clone()
+                    .context("missing signature for textures")?,
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// An asynchronous client for the Mojang API and session server.
+///
+/// This trait uses the [async_trait](crate::async_trait) attribute macro.
+/// Implement it to point authentication and profile lookups at something other
+/// than Mojang's servers, such as a proxy or a mock in tests. See
+/// [`HttpMojangApi`] for an implementation talking to servers with the same
+/// API as Mojang's.
+#[async_trait]
+pub trait MojangApi: Send + Sync + 'static {
+    /// Checks if the player with the given username has joined the server
+    /// identified by `server_hash` through the session server, and returns
+    /// the player's profile if so.
+    ///
+    /// `ip` is the address the player connected from, which the session
+    /// server may check as well.
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+        ip: Option<IpAddr>,
+    ) -> anyhow::Result<Option<GameProfile>>;
+
+    /// Looks up the profile of the player with the given UUID, including
+    /// signed properties. Returns `None` if there is no such player.
+    async fn profile(&self, uuid: Uuid) -> anyhow::Result<Option<GameProfile>>;
+
+    /// Looks up the UUID of the player with the given username. Returns `None`
+    /// if there is no such player.
+    async fn uuid_of(&self, username: &str) -> anyhow::Result<Option<Uuid>>;
+}
+
+/// A [`MojangApi`] sending HTTP requests to Mojang's servers, or to other
+/// servers with the same API.
+#[derive(Clone, Debug)]
+pub struct HttpMojangApi {
+    http_client: HttpClient,
+    session_server: Url,
+    api_server: Url,
+}
+
+impl HttpMojangApi {
+    /// The address of Mojang's session server.
+    pub const MOJANG_SESSION_SERVER: &'static str = "https://sessionserver.mojang.com";
+    /// The address of Mojang's API server.
+    pub const MOJANG_API_SERVER: &'static str = "https://api.mojang.com";
+
+    /// Creates a client for Mojang's servers.
+    pub fn new() -> Self {
+        Self::with_servers(
+            Url::parse(Self::MOJANG_SESSION_SERVER).unwrap(),
+            Url::parse(Self::MOJANG_API_SERVER).unwrap(),
+        )
+    }
+
+    /// Creates a client for a custom session server and API server.
+    pub fn with_servers(session_server: Url, api_server: Url) -> Self {
+        Self {
+            http_client: HttpClient::new(),
+            session_server,
+            api_server,
+        }
+    }
+
+    /// Gets the address of the session server.
+    pub fn session_server(&self) -> &Url {
+        &self.session_server
+    }
+
+    /// Gets the address of the API server.
+    pub fn api_server(&self) -> &Url {
+        &self.api_server
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, url: Url) -> anyhow::Result<Option<T>> {
+        let resp = self.http_client.get(url).send().await?;
+
+        match resp.status() {
+            StatusCode::NO_CONTENT | StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(resp.json().await?)),
+            status => bail!("Mojang API GET request failed: {status}"),
+        }
+    }
+}
+
+impl Default for HttpMojangApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Appends `path` to the path of `base`.
+fn endpoint(base: &Url, path: &str) -> Url {
+    let mut url = base.clone();
+    url.set_path(&format!("{}/{path}", base.path().trim_end_matches('/')));
+    url
+}
+
+#[async_trait]
+impl MojangApi for HttpMojangApi {
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+        ip: Option<IpAddr>,
+    ) -> anyhow::Result<Option<GameProfile>> {
+        let mut url = endpoint(&self.session_server, "session/minecraft/hasJoined");
+
+        url.query_pairs_mut()
+            .append_pair("username", username)
+            .append_pair("serverId", server_hash);
+
+        if let Some(ip) = ip {
+            url.query_pairs_mut().append_pair("ip", &ip.to_string());
+        }
+
+        self.get(url).await
+    }
+
+    async fn profile(&self, uuid: Uuid) -> anyhow::Result<Option<GameProfile>> {
+        let mut url = endpoint(
+            &self.session_server,
+            &format!("session/minecraft/profile/{}", uuid.simple()),
+        );
+
+        url.query_pairs_mut().append_pair("unsigned", "false");
+
+        self.get(url).await
+    }
+
+    async fn uuid_of(&self, username: &str) -> anyhow::Result<Option<Uuid>> {
+        #[derive(Deserialize)]
+        struct Profile {
+            id: Uuid,
+        }
+
+        let url = endpoint(
+            &self.api_server,
+            &format!("users/profiles/minecraft/{username}"),
+        );
+
+        Ok(self.get::<Profile>(url).await?.map(|p| p.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::testing::test_server;
+    use crate::text::Text;
+
+    #[test]
+    fn endpoints_keep_base_path() {
+        let base = Url::parse("http://localhost:8080/auth/").unwrap();
+        assert_eq!(
+            endpoint(&base, "session/minecraft/hasJoined").as_str(),
+            "http://localhost:8080/auth/session/minecraft/hasJoined"
+        );
+
+        let base = Url::parse(HttpMojangApi::MOJANG_API_SERVER).unwrap();
+        assert_eq!(
+            endpoint(&base, "users/profiles/minecraft/jeb_").as_str(),
+            "https://api.mojang.com/users/profiles/minecraft/jeb_"
+        );
+    }
+
+    #[test]
+    fn profile_deserialized() {
+        let profile: GameProfile = serde_json::from_str(
+            r#"{"id":"853c80ef3c3749fdaa49938b674adae6","name":"jeb_","properties":[]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            profile.id,
+            Uuid::parse_str("853c80ef-3c37-49fd-aa49-938b674adae6").unwrap()
+        );
+        assert_eq!(profile.name, "jeb_");
+        assert_eq!(profile.textures().unwrap(), None);
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn default_authentication_uses_mojang_api() {
+        let address = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0).into();
+        let server = test_server();
+
+        let shared = &server.server().shared;
+        let authenticate = |username| {
+            shared.tokio_handle().block_on(
+                shared
+                    .config()
+                    .authenticate(shared, username, "hash", address),
+            )
+        };
+
+        assert_eq!(authenticate("tester").unwrap().id, Uuid::from_u128(1));
+        assert_eq!(
+            authenticate("intruder").unwrap_err(),
+            Text::translate("multiplayer.disconnect.unverified_username")
+        );
+    }
+}
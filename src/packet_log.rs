@@ -0,0 +1,157 @@
+//! Logging the packets of clients for debugging, enabled with
+//! [`Config::packet_log`].
+//!
+//! Every logged packet produces one line with the name of the client, the
+//! direction and name of the packet, and its size in bytes as sent over the
+//! connection. Packet contents are not logged. Lines are logged at the `INFO`
+//! level with the target `valence::packet_log`, so they can be enabled
+//! independently of other logs with a filter such as
+//! `RUST_LOG=valence::packet_log=info`.
+//!
+//! Only packets of the play state are logged. Packets of clients added with
+//! [`TestServer::connect`] are not logged, since they are never encoded.
+//!
+//! ```
+//! use valence::packet_log::PacketLogFilter;
+//!
+//! // Log everything except movement and keepalive packets.
+//! let filter = PacketLogFilter::new()
+//!     .exclude("MovePlayer*")
+//!     .exclude("KeepAlive");
+//!
+//! assert!(filter.matches("Steve", "ChatMessage"));
+//! assert!(!filter.matches("Steve", "MovePlayerPosition"));
+//! ```
+//!
+//! [`Config::packet_log`]: crate::config::Config::packet_log
+//! [`TestServer::connect`]: crate::testing::TestServer::connect
+
+/// Selects the packets which are logged.
+///
+/// Packets are selected by the name of their type, such as `"ChatMessage"` or
+/// `"ChunkData"`. A pattern ending in `*` matches every name starting with the
+/// rest of the pattern.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PacketLogFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    clients: Vec<String>,
+    serverbound: bool,
+    clientbound: bool,
+}
+
+impl Default for PacketLogFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PacketLogFilter {
+    /// Creates a filter which selects all packets in both directions of all
+    /// clients.
+    pub fn new() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            clients: Vec::new(),
+            serverbound: true,
+            clientbound: true,
+        }
+    }
+
+    /// Only selects packets matching `pattern` or another included pattern.
+    /// If no pattern is included, all packets are selected.
+    #[must_use]
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Does not select packets matching `pattern`, even if they match an
+    /// included pattern.
+    #[must_use]
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Only selects packets of the client with the given username or another
+    /// added client. If no client is added, the packets of all clients are
+    /// selected. Usernames are compared case-insensitively.
+    #[must_use]
+    pub fn client(mut self, username: impl Into<String>) -> Self {
+        self.clients.push(username.into());
+        self
+    }
+
+    /// Sets whether packets sent by clients are selected.
+    #[must_use]
+    pub fn serverbound(mut self, serverbound: bool) -> Self {
+        self.serverbound = serverbound;
+        self
+    }
+
+    /// Sets whether packets sent to clients are selected.
+    #[must_use]
+    pub fn clientbound(mut self, clientbound: bool) -> Self {
+        self.clientbound = clientbound;
+        self
+    }
+
+    /// Returns `true` if the packet named `packet` of the client `username` is
+    /// selected, regardless of its direction.
+    pub fn matches(&self, username: &str, packet: &str) -> bool {
+        (self.clients.is_empty()
+            || self
+                .clients
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(username)))
+            && (self.include.is_empty() || self.include.iter().any(|p| pattern_matches(p, packet)))
+            && !self.exclude.iter().any(|p| pattern_matches(p, packet))
+    }
+
+    /// Logs a packet sent by a client if it is selected.
+    pub(crate) fn log_serverbound(&self, username: &str, packet: &str, len: usize) {
+        if self.serverbound && self.matches(username, packet) {
+            tracing::info!(target: "valence::packet_log", "{username} -> {packet} ({len} bytes)");
+        }
+    }
+
+    /// Logs a packet sent to a client if it is selected.
+    pub(crate) fn log_clientbound(&self, username: &str, packet: &str, len: usize) {
+        if self.clientbound && self.matches(username, packet) {
+            tracing::info!(target: "valence::packet_log", "{username} <- {packet} ({len} bytes)");
+        }
+    }
+}
+
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_patterns() {
+        let all = PacketLogFilter::new();
+        assert!(all.matches("a", "KeepAlive"));
+        assert_eq!(PacketLogFilter::default(), all);
+
+        let filter = PacketLogFilter::new()
+            .include("Chunk*")
+            .include("KeepAlive")
+            .exclude("ChunkData")
+            .client("Steve");
+
+        assert!(filter.matches("steve", "ChunkLoadDistance"));
+        assert!(filter.matches("Steve", "KeepAlive"));
+        assert!(!filter.matches("Steve", "ChunkData"));
+        assert!(!filter.matches("Steve", "KeepAliveX"));
+        assert!(!filter.matches("Alex", "KeepAlive"));
+    }
+}
@@ -0,0 +1,465 @@
+//! Permission groups, per-node permission checks and the whitelist.
+//!
+//! A permission node is a dot-separated string such as
+//! `valence.command.stop`. Nodes can be granted or denied with wildcards:
+//! `valence.command.*` matches every node starting with `valence.command.`
+//! and `*` matches every node. When several patterns match a node, the most
+//! specific one wins.
+//!
+//! Permissions can be loaded from and saved to JSON files like this one:
+//!
+//! ```json
+//! {
+//!     "groups": {
+//!         "default": { "nodes": { "valence.command.help": true } },
+//!         "admin": { "inherits": ["default"], "nodes": { "*": true } }
+//!     },
+//!     "users": {
+//!         "069a79f4-44e9-4726-a5be-fca90e38aaf5": { "groups": ["admin"] }
+//!     }
+//! }
+//! ```
+//!
+//! A [`Whitelist`] restricts who can join the server. It is set with
+//! [`SharedServer::set_whitelist`] and stored as a JSON array of UUIDs.
+//!
+//! [`SharedServer::set_whitelist`]: crate::server::SharedServer::set_whitelist
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The name of the group every user is implicitly a member of.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Contains all permission groups and users of a server.
+#[derive(Clone, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Permissions {
+    groups: BTreeMap<String, Group>,
+    users: HashMap<Uuid, User>,
+    /// If groups or users were changed since the end of the last tick. New
+    /// permissions start out modified so that replacing the permissions of a
+    /// server is noticed as well.
+    #[serde(skip)]
+    modified: bool,
+}
+
+/// A named set of permission nodes that users can be a member of.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Group {
+    /// The names of the groups this group inherits nodes from. Nodes of this
+    /// group take precedence over inherited ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inherits: Vec<String>,
+    #[serde(default)]
+    nodes: NodeSet,
+}
+
+/// The permissions of a single user.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct User {
+    /// The names of the groups this user is a member of, in order of
+    /// precedence.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    #[serde(default, skip_serializing_if = "NodeSet::is_empty")]
+    nodes: NodeSet,
+}
+
+/// The players allowed to join the server while it is whitelisted.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Whitelist {
+    players: BTreeSet<Uuid>,
+}
+
+/// A set of granted and denied permission patterns.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+struct NodeSet(BTreeMap<String, bool>);
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self {
+            groups: BTreeMap::new(),
+            users: HashMap::new(),
+            modified: true,
+        }
+    }
+}
+
+impl PartialEq for Permissions {
+    fn eq(&self, other: &Self) -> bool {
+        self.groups == other.groups && self.users == other.users
+    }
+}
+
+impl Permissions {
+    /// Creates an empty set of permissions where every check fails.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses permissions from a JSON string.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("failed to parse permissions")
+    }
+
+    /// Serializes the permissions to a pretty-printed JSON string.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("permissions should serialize")
+    }
+
+    /// Loads permissions from the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read permissions from {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// Saves the permissions to the JSON file at `path`, replacing the file if
+    /// it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .with_context(|| format!("failed to write permissions to {}", path.display()))
+    }
+
+    /// Gets a shared reference to the group with the given name.
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.get(name)
+    }
+
+    /// Gets an exclusive reference to the group with the given name, creating
+    /// an empty group if it does not exist.
+    pub fn group_mut(&mut self, name: impl Into<String>) -> &mut Group {
+        self.modified = true;
+        self.groups.entry(name.into()).or_default()
+    }
+
+    /// Removes the group with the given name. Returns the removed group if it
+    /// existed.
+    ///
+    /// Users and groups referring to the removed group are left unchanged.
+    pub fn remove_group(&mut self, name: &str) -> Option<Group> {
+        self.modified = true;
+        self.groups.remove(name)
+    }
+
+    /// Returns an iterator over all groups and their names in alphabetical
+    /// order.
+    pub fn groups(&self) -> impl Iterator<Item = (&str, &Group)> + '_ {
+        self.groups.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Gets a shared reference to the permissions of the user with the given
+    /// UUID.
+    pub fn user(&self, uuid: Uuid) -> Option<&User> {
+        self.users.get(&uuid)
+    }
+
+    /// Gets an exclusive reference to the permissions of the user with the
+    /// given UUID, creating an empty entry if it does not exist.
+    pub fn user_mut(&mut self, uuid: Uuid) -> &mut User {
+        self.modified = true;
+        self.users.entry(uuid).or_default()
+    }
+
+    /// Removes the user with the given UUID. Returns the removed user if it
+    /// existed.
+    pub fn remove_user(&mut self, uuid: Uuid) -> Option<User> {
+        self.modified = true;
+        self.users.remove(&uuid)
+    }
+
+    /// Returns `true` if the user with the given UUID has the permission
+    /// `node`.
+    ///
+    /// The user's own nodes are checked first, then the user's groups in order
+    /// followed by the groups they inherit from, and finally the
+    /// [default group](DEFAULT_GROUP). The first match decides the result. If
+    /// nothing matches, `false` is returned.
+    pub fn check(&self, uuid: Uuid, node: &str) -> bool {
+        let mut visited = HashSet::new();
+
+        if let Some(user) = self.users.get(&uuid) {
+            if let Some(value) = user.nodes.get(node) {
+                return value;
+            }
+
+            for group in &user.groups {
+                if let Some(value) = self.check_group(group, node, &mut visited) {
+                    return value;
+                }
+            }
+        }
+
+        self.check_group(DEFAULT_GROUP, node, &mut visited)
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn modified(&self) -> bool {
+        self.modified
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.modified = false;
+    }
+
+    fn check_group<'a>(
+        &'a self,
+        name: &'a str,
+        node: &str,
+        visited: &mut HashSet<&'a str>,
+    ) -> Option<bool> {
+        // Guard against inheritance cycles.
+        if !visited.insert(name) {
+            return None;
+        }
+
+        let group = self.groups.get(name)?;
+
+        if let Some(value) = group.nodes.get(node) {
+            return Some(value);
+        }
+
+        group
+            .inherits
+            .iter()
+            .find_map(|parent| self.check_group(parent, node, visited))
+    }
+}
+
+impl Group {
+    /// Creates an empty group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the value of the given permission pattern in this group, if it is
+    /// set. This does not perform wildcard matching.
+    pub fn node(&self, pattern: &str) -> Option<bool> {
+        self.nodes.0.get(pattern).copied()
+    }
+
+    /// Grants (`true`) or denies (`false`) a permission pattern.
+    pub fn set_node(&mut self, pattern: impl Into<String>, value: bool) {
+        self.nodes.0.insert(pattern.into(), value);
+    }
+
+    /// Removes a permission pattern. Returns its previous value.
+    pub fn unset_node(&mut self, pattern: &str) -> Option<bool> {
+        self.nodes.0.remove(pattern)
+    }
+
+    /// Returns an iterator over all permission patterns in this group.
+    pub fn nodes(&self) -> impl Iterator<Item = (&str, bool)> + '_ {
+        self.nodes.0.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+impl User {
+    /// Creates a user with no groups or nodes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the value of the given permission pattern set directly on this
+    /// user, if it is set. This does not perform wildcard matching.
+    pub fn node(&self, pattern: &str) -> Option<bool> {
+        self.nodes.0.get(pattern).copied()
+    }
+
+    /// Grants (`true`) or denies (`false`) a permission pattern for this user.
+    pub fn set_node(&mut self, pattern: impl Into<String>, value: bool) {
+        self.nodes.0.insert(pattern.into(), value);
+    }
+
+    /// Removes a permission pattern. Returns its previous value.
+    pub fn unset_node(&mut self, pattern: &str) -> Option<bool> {
+        self.nodes.0.remove(pattern)
+    }
+
+    /// Returns an iterator over all permission patterns set on this user.
+    pub fn nodes(&self) -> impl Iterator<Item = (&str, bool)> + '_ {
+        self.nodes.0.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+impl Whitelist {
+    /// Creates an empty whitelist, which lets nobody join.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a whitelist from a JSON array of UUIDs.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("failed to parse whitelist")
+    }
+
+    /// Serializes the whitelist to a pretty-printed JSON array of UUIDs.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("whitelist should serialize")
+    }
+
+    /// Loads a whitelist from the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read whitelist from {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    /// Saves the whitelist to the JSON file at `path`, replacing the file if
+    /// it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .with_context(|| format!("failed to write whitelist to {}", path.display()))
+    }
+
+    /// Returns `true` if the player with the given UUID is on the whitelist.
+    pub fn contains(&self, uuid: Uuid) -> bool {
+        self.players.contains(&uuid)
+    }
+
+    /// Adds a player to the whitelist. Returns `true` if the player was not on
+    /// it yet.
+    pub fn add(&mut self, uuid: Uuid) -> bool {
+        self.players.insert(uuid)
+    }
+
+    /// Removes a player from the whitelist. Returns `true` if the player was
+    /// on it.
+    pub fn remove(&mut self, uuid: Uuid) -> bool {
+        self.players.remove(&uuid)
+    }
+
+    /// Returns an iterator over the UUIDs of all players on the whitelist.
+    pub fn players(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.players.iter().copied()
+    }
+}
+
+impl NodeSet {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Finds the value of the most specific pattern matching `node`.
+    fn get(&self, node: &str) -> Option<bool> {
+        if let Some(&value) = self.0.get(node) {
+            return Some(value);
+        }
+
+        // Try `a.b.*`, then `a.*`, then `*`.
+        let mut prefix = node;
+        while let Some(i) = prefix.rfind('.') {
+            prefix = &prefix[..i];
+            if let Some(&value) = self.0.get(&format!("{prefix}.*")) {
+                return Some(value);
+            }
+        }
+
+        self.0.get("*").copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        let mut perms = Permissions::new();
+        let uuid = Uuid::from_u128(1);
+
+        let user = perms.user_mut(uuid);
+        user.set_node("a.*", true);
+        user.set_node("a.b.*", false);
+        user.set_node("a.b.c", true);
+
+        assert!(perms.check(uuid, "a.x"));
+        assert!(!perms.check(uuid, "a.b.x"));
+        assert!(perms.check(uuid, "a.b.c"));
+        assert!(!perms.check(uuid, "a"));
+
+        perms.user_mut(uuid).set_node("*", true);
+        assert!(perms.check(uuid, "a"));
+        assert!(perms.check(uuid, "z.y"));
+    }
+
+    #[test]
+    fn groups() {
+        let mut perms = Permissions::new();
+        let admin = Uuid::from_u128(1);
+        let guest = Uuid::from_u128(2);
+
+        perms.group_mut(DEFAULT_GROUP).set_node("cmd.help", true);
+
+        let group = perms.group_mut("admin");
+        group.inherits.push(DEFAULT_GROUP.into());
+        group.inherits.push("admin".into()); // Cycles must not loop forever.
+        group.set_node("cmd.*", true);
+
+        perms.user_mut(admin).groups.push("admin".into());
+        perms.user_mut(admin).set_node("cmd.stop", false);
+
+        assert!(perms.check(guest, "cmd.help"));
+        assert!(!perms.check(guest, "cmd.kick"));
+
+        assert!(perms.check(admin, "cmd.help"));
+        assert!(perms.check(admin, "cmd.kick"));
+        assert!(!perms.check(admin, "cmd.stop"));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = r#"{
+            "groups": {
+                "default": { "nodes": { "cmd.help": true } },
+                "admin": { "inherits": ["default"], "nodes": { "*": true } }
+            },
+            "users": {
+                "069a79f4-44e9-4726-a5be-fca90e38aaf5": { "groups": ["admin"] }
+            }
+        }"#;
+
+        let perms = Permissions::from_json(json).unwrap();
+        let uuid = Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+
+        assert!(perms.check(uuid, "anything"));
+        assert!(!perms.check(Uuid::from_u128(1), "anything"));
+        assert!(perms.check(Uuid::from_u128(1), "cmd.help"));
+
+        assert_eq!(Permissions::from_json(&perms.to_json()).unwrap(), perms);
+    }
+
+    #[test]
+    fn whitelist() {
+        let mut whitelist = Whitelist::new();
+        let uuid = Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap();
+
+        assert!(!whitelist.contains(uuid));
+        assert!(whitelist.add(uuid));
+        assert!(!whitelist.add(uuid));
+        assert!(whitelist.contains(uuid));
+
+        let json = whitelist.to_json();
+        assert_eq!(
+            Whitelist::from_json(r#"["069a79f4-44e9-4726-a5be-fca90e38aaf5"]"#).unwrap(),
+            whitelist
+        );
+        assert_eq!(Whitelist::from_json(&json).unwrap(), whitelist);
+
+        assert!(whitelist.remove(uuid));
+        assert!(!whitelist.contains(uuid));
+        assert_eq!(whitelist.players().count(), 0);
+    }
+}
@@ -0,0 +1,308 @@
+//! Storing the data of players between sessions.
+//!
+//! The state of a client is loaded with [`Config::load_player_data`] when the
+//! client joins and handed to [`Config::save_player_data`] when the client is
+//! removed. [`PlayerDataStore`] keeps the data of each player in a JSON file
+//! named after the player's UUID. It can be used to implement both methods for
+//! client states which implement [`Serialize`] and [`Deserialize`].
+//!
+//! [`Config::load_player_data`]: crate::config::Config::load_player_data
+//! [`Config::save_player_data`]: crate::config::Config::save_player_data
+//! [`Deserialize`]: serde::Deserialize
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Flat-file storage for player data, with one JSON file per player.
+#[derive(Clone, Debug)]
+pub struct PlayerDataStore {
+    dir: PathBuf,
+}
+
+impl PlayerDataStore {
+    /// Creates a store keeping its files in the given directory. The
+    /// directory is created when the first file is saved.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Gets the directory the files are kept in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Gets the path of the file for the player with the given UUID.
+    pub fn path(&self, uuid: Uuid) -> PathBuf {
+        self.dir.join(format!("{uuid}.json"))
+    }
+
+    /// Loads the data of the player with the given UUID. Returns `None` if
+    /// nothing was saved for the player.
+    pub async fn load<T: DeserializeOwned>(&self, uuid: Uuid) -> anyhow::Result<Option<T>> {
+        let path = self.path(uuid);
+
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes).with_context(|| {
+                format!("failed to parse player data in {}", path.display())
+            })?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                Err(e).with_context(|| format!("failed to read player data in {}", path.display()))
+            }
+        }
+    }
+
+    /// Saves the data of the player with the given UUID, replacing what was
+    /// saved before.
+    ///
+    /// The data is written to a temporary file first, so the previous data is
+    /// kept intact if writing fails.
+    pub async fn save<T: Serialize>(&self, uuid: Uuid, data: &T) -> anyhow::Result<()> {
+        let path = self.path(uuid);
+        let tmp_path = path.with_extension("json.tmp");
+        let bytes = serde_json::to_vec(data)?;
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+        tokio::fs::write(&tmp_path, bytes)
+            .await
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .with_context(|| format!("failed to replace {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::block::BlockState;
+    use crate::chunk::{ChunkPos, UnloadedChunk};
+    use crate::client::Client;
+    use crate::config::Config;
+    use crate::dimension::DimensionId;
+    use crate::server::{self, Server, SharedServer};
+    use crate::testing::TestServer;
+    use crate::world::WorldId;
+    use crate::Ticks;
+
+    #[tokio::test]
+    async fn saved_data_loaded() {
+        let dir = std::env::temp_dir().join(format!("valence-player-data-{}", std::process::id()));
+        let store = PlayerDataStore::new(&dir);
+        let uuid = Uuid::from_u128(1);
+
+        assert_eq!(store.load::<Vec<i32>>(uuid).await.unwrap(), None);
+
+        store.save(uuid, &vec![1, 2, 3]).await.unwrap();
+        store.save(uuid, &vec![4, 5]).await.unwrap();
+        assert_eq!(
+            store.load::<Vec<i32>>(uuid).await.unwrap(),
+            Some(vec![4, 5])
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Stores the state of each player, a number, and saved chunks in memory.
+    #[derive(Default)]
+    struct PersistentGame {
+        saved: Mutex<HashMap<Uuid, i32>>,
+        saved_chunks: Mutex<Vec<(ChunkPos, UnloadedChunk)>>,
+        /// How long saving player data takes.
+        save_delay: Duration,
+        /// Whether saving player data and chunks panics.
+        panic_on_save: bool,
+    }
+
+    #[async_trait]
+    impl Config for PersistentGame {
+        type ServerState = ();
+        type ClientState = i32;
+        type EntityState = ();
+        type WorldState = ();
+        type ChunkState = ();
+        type PlayerListState = ();
+
+        fn max_connections(&self) -> usize {
+            10
+        }
+
+        fn autosave_interval(&self) -> Option<Ticks> {
+            Some(2)
+        }
+
+        async fn load_player_data(&self, _shared: &SharedServer<Self>, uuid: Uuid) -> i32 {
+            self.saved.lock().unwrap().get(&uuid).copied().unwrap_or(1)
+        }
+
+        async fn save_player_data(&self, _shared: &SharedServer<Self>, uuid: Uuid, state: i32) {
+            tokio::time::sleep(self.save_delay).await;
+            assert!(!self.panic_on_save, "failed to save player data");
+            self.saved.lock().unwrap().insert(uuid, state);
+        }
+
+        fn snapshot_player_data(&self, client: &Client<Self>) -> Option<i32> {
+            Some(client.state)
+        }
+
+        async fn save_chunk(
+            &self,
+            _shared: &SharedServer<Self>,
+            _world: WorldId,
+            pos: ChunkPos,
+            chunk: UnloadedChunk,
+        ) {
+            assert!(!self.panic_on_save, "failed to save chunk");
+            self.saved_chunks.lock().unwrap().push((pos, chunk));
+        }
+
+        fn init(&self, server: &mut Server<Self>) {
+            let (_, world) = server.worlds.insert(DimensionId::default(), ());
+            world.chunks.insert([0, 0], ());
+        }
+
+        fn update(&self, _server: &mut Server<Self>) {}
+    }
+
+    /// Waits up to five seconds for `f` to return `true`.
+    fn wait_until(mut f: impl FnMut() -> bool) -> bool {
+        let start = Instant::now();
+        while !f() {
+            if start.elapsed() >= Duration::from_secs(5) {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+
+    #[test]
+    fn player_data_saved_and_loaded() {
+        let mut server = TestServer::new(PersistentGame::default(), ()).unwrap();
+
+        let client = server.connect("tester");
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        assert_eq!(c.state, 1);
+        c.state = 5;
+
+        server.server_mut().clients.retain(|_, _| false);
+        server.tick();
+
+        let uuid = server::offline_uuid("tester");
+        let saved = || {
+            let saved = server.server().shared.config().saved.lock().unwrap();
+            saved.get(&uuid).copied()
+        };
+
+        assert!(wait_until(|| saved().is_some()));
+        assert_eq!(saved(), Some(5));
+
+        let client = server.connect("tester");
+        assert_eq!(server.server().clients.get(client.id()).unwrap().state, 5);
+    }
+
+    #[test]
+    fn reconnect_waits_for_pending_save() {
+        let mut server = TestServer::new(
+            PersistentGame {
+                save_delay: Duration::from_millis(200),
+                ..PersistentGame::default()
+            },
+            (),
+        )
+        .unwrap();
+
+        let client = server.connect("tester");
+        server
+            .server_mut()
+            .clients
+            .get_mut(client.id())
+            .unwrap()
+            .state = 5;
+        server.server_mut().clients.retain(|_, _| false);
+        server.tick();
+
+        let client = server.connect("tester");
+        assert_eq!(server.server().clients.get(client.id()).unwrap().state, 5);
+    }
+
+    #[test]
+    fn panicking_saves_do_not_hang_shutdown() {
+        let mut server = TestServer::new(
+            PersistentGame {
+                panic_on_save: true,
+                ..PersistentGame::default()
+            },
+            (),
+        )
+        .unwrap();
+
+        server.connect("tester");
+        server.tick();
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.chunks.set_block_state([1, 0, 1], BlockState::STONE);
+
+        let start = Instant::now();
+        server::shutdown_server(server.server_mut());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // The failed save does not keep the player from joining again.
+        let client = server.connect("tester");
+        assert_eq!(server.server().clients.get(client.id()).unwrap().state, 1);
+    }
+
+    #[test]
+    fn autosave_saves_dirty_chunks_and_players() {
+        let mut server = TestServer::new(PersistentGame::default(), ()).unwrap();
+
+        let client = server.connect("tester");
+        let s = server.server_mut();
+        s.clients.get_mut(client.id()).unwrap().state = 7;
+        let (_, world) = s.worlds.iter_mut().next().unwrap();
+        assert!(world.chunks.set_block_state([1, 0, 1], BlockState::STONE));
+        assert!(world.chunks.get([0, 0]).unwrap().is_dirty());
+
+        // The autosave happens on the tick with number 2.
+        for _ in 0..3 {
+            server.tick();
+        }
+
+        let cfg = server.server().shared.config();
+        let uuid = server::offline_uuid("tester");
+        assert!(wait_until(
+            || cfg.saved.lock().unwrap().get(&uuid) == Some(&7)
+        ));
+        assert!(wait_until(|| !cfg.saved_chunks.lock().unwrap().is_empty()));
+
+        let saved_chunks = cfg.saved_chunks.lock().unwrap();
+        assert_eq!(saved_chunks.len(), 1);
+        let (pos, chunk) = &saved_chunks[0];
+        assert_eq!(*pos, ChunkPos::new(0, 0));
+        let min_y = server
+            .server()
+            .shared
+            .dimension(DimensionId::default())
+            .min_y;
+        assert_eq!(
+            chunk.get_block_state(1, (-min_y) as usize, 1),
+            BlockState::STONE
+        );
+        drop(saved_chunks);
+
+        let (_, world) = server.server().worlds.iter().next().unwrap();
+        assert!(!world.chunks.get([0, 0]).unwrap().is_dirty());
+    }
+}
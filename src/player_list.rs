@@ -84,6 +84,16 @@ impl<C: Config> PlayerLists<C> {
         self.slab.get_mut(&id.0)
     }
 
+    /// Hides or shows the entries with the given UUID in every player list
+    /// for a client being vanished or unvanished.
+    pub(crate) fn set_vanished(&mut self, uuid: Uuid, vanished: bool) {
+        for (_, pl) in self.slab.iter_mut() {
+            if let Some(e) = pl.entries.get_mut(&uuid) {
+                e.set_vanished(vanished);
+            }
+        }
+    }
+
     pub(crate) fn update(&mut self) {
         self.slab.collect_garbage();
         for (_, pl) in self.slab.iter_mut() {
@@ -141,6 +151,8 @@ impl<C: Config> PlayerList<C> {
                         game_mode,
                         ping,
                         display_name: display_name.into(),
+                        listed: true,
+                        vanished: false,
                         bits: EntryBits::new().with_created_this_tick(true),
                     });
                 } else {
@@ -157,6 +169,8 @@ impl<C: Config> PlayerList<C> {
                     game_mode,
                     ping,
                     display_name: display_name.into(),
+                    listed: true,
+                    vanished: false,
                     bits: EntryBits::new().with_created_this_tick(true),
                 });
                 true
@@ -164,6 +178,18 @@ impl<C: Config> PlayerList<C> {
         }
     }
 
+    /// Gets a shared reference to the entry with the given UUID. Returns `None`
+    /// if the entry is not in the list.
+    pub fn get(&self, uuid: Uuid) -> Option<&PlayerListEntry> {
+        self.entries.get(&uuid)
+    }
+
+    /// Gets an exclusive reference to the entry with the given UUID. Returns
+    /// `None` if the entry is not in the list.
+    pub fn get_mut(&mut self, uuid: Uuid) -> Option<&mut PlayerListEntry> {
+        self.entries.get_mut(&uuid)
+    }
+
     /// Removes an entry from the player list with the given UUID. Returns
     /// whether the entry was present in the list.
     pub fn remove(&mut self, uuid: Uuid) -> bool {
@@ -227,87 +253,88 @@ impl<C: Config> PlayerList<C> {
         self.entries.iter().map(|(k, v)| (*k, v))
     }
 
+    /// Returns all listed entries in the order the vanilla client displays
+    /// them.
+    ///
+    /// The client decides the order of the tab list on its own. Entries in
+    /// spectator mode are placed last, and the rest are sorted by username
+    /// ignoring case. The UUID breaks ties so the order is stable.
+    pub fn sorted_entries(&self) -> Vec<(Uuid, &PlayerListEntry)> {
+        let mut entries: Vec<_> = self.entries().filter(|(_, e)| e.is_shown()).collect();
+
+        entries.sort_by_cached_key(|(uuid, e)| {
+            (
+                e.game_mode == GameMode::Spectator,
+                e.username.to_lowercase(),
+                *uuid,
+            )
+        });
+
+        entries
+    }
+
     /// Returns a mutable iterator over all entries in an unspecified order.
     pub fn entries_mut(&mut self) -> impl Iterator<Item = (Uuid, &mut PlayerListEntry)> + '_ {
         self.entries.iter_mut().map(|(k, v)| (*k, v))
     }
 
     pub(crate) fn initial_packets(&self, mut push_packet: impl FnMut(S2cPlayPacket)) {
+        // Unlisted entries are added too so that clients can spawn their player
+        // entities. The client removes them again once that is done.
         let add_player: Vec<_> = self
             .entries
             .iter()
-            .map(|(&uuid, e)| PlayerListAddPlayer {
-                uuid,
-                username: e.username.clone().into(),
-                properties: {
-                    let mut properties = Vec::new();
-                    if let Some(textures) = &e.textures {
-                        properties.push(Property {
-                            name: "textures".into(),
-                            value: base64::encode(textures.payload()),
-                            signature: Some(base64::encode(textures.signature())),
-                        });
-                    }
-                    properties
-                },
-                game_mode: e.game_mode,
-                ping: VarInt(e.ping),
-                display_name: e.display_name.clone(),
-                sig_data: None,
-            })
+            .map(|(&uuid, e)| e.add_player(uuid))
             .collect();
 
         if !add_player.is_empty() {
             push_packet(UpdatePlayerList::AddPlayer(add_player).into());
         }
-
-        if self.header != Text::default() || self.footer != Text::default() {
-            push_packet(
-                PlayerListHeaderFooter {
-                    header: self.header.clone(),
-                    footer: self.footer.clone(),
-                }
-                .into(),
-            );
-        }
     }
 
-    pub(crate) fn update_packets(&self, mut push_packet: impl FnMut(S2cPlayPacket)) {
-        if !self.removed.is_empty() {
-            push_packet(
-                UpdatePlayerList::RemovePlayer(self.removed.iter().cloned().collect()).into(),
-            );
+    /// Pushes the packets that bring a client up to date with the changes made
+    /// to this player list during the tick.
+    ///
+    /// Removed and newly unlisted entries for which `in_other_list` returns
+    /// `true` are not removed from the client, because another player list
+    /// seen by the client still shows them.
+    pub(crate) fn update_packets(
+        &self,
+        mut push_packet: impl FnMut(S2cPlayPacket),
+        mut in_other_list: impl FnMut(Uuid) -> bool,
+    ) {
+        let removed: Vec<_> = self
+            .removed
+            .iter()
+            .cloned()
+            .filter(|&uuid| !in_other_list(uuid))
+            .collect();
+
+        if !removed.is_empty() {
+            push_packet(UpdatePlayerList::RemovePlayer(removed).into());
         }
 
         let mut add_player = Vec::new();
         let mut game_mode = Vec::new();
         let mut ping = Vec::new();
         let mut display_name = Vec::new();
+        let mut unlisted = Vec::new();
 
         for (&uuid, e) in self.entries.iter() {
             if e.bits.created_this_tick() {
-                let mut properties = Vec::new();
-                if let Some(textures) = &e.textures {
-                    properties.push(Property {
-                        name: "textures".into(),
-                        value: base64::encode(textures.payload()),
-                        signature: Some(base64::encode(textures.signature())),
-                    });
-                }
-
-                add_player.push(PlayerListAddPlayer {
-                    uuid,
-                    username: e.username.clone().into(),
-                    properties,
-                    game_mode: e.game_mode,
-                    ping: VarInt(e.ping),
-                    display_name: e.display_name.clone(),
-                    sig_data: None,
-                });
-
+                add_player.push(e.add_player(uuid));
                 continue;
             }
 
+            if e.bits.modified_listed() {
+                if e.is_shown() {
+                    add_player.push(e.add_player(uuid));
+                    continue;
+                } else if !in_other_list(uuid) {
+                    unlisted.push(uuid);
+                }
+            }
+
             if e.bits.modified_game_mode() {
                 game_mode.push((uuid, e.game_mode));
             }
@@ -337,19 +364,44 @@ impl<C: Config> PlayerList<C> {
             push_packet(UpdatePlayerList::UpdateDisplayName(display_name).into());
         }
 
-        if self.modified_header_or_footer {
-            push_packet(
-                PlayerListHeaderFooter {
-                    header: self.header.clone(),
-                    footer: self.footer.clone(),
-                }
-                .into(),
-            );
+        if !unlisted.is_empty() {
+            push_packet(UpdatePlayerList::RemovePlayer(unlisted).into());
+        }
+    }
+
+    /// Returns the UUIDs of all entries a client seeing this player list could
+    /// know about, including the entries removed this tick.
+    pub(crate) fn sent_uuids(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.entries.keys().chain(self.removed.iter()).cloned()
+    }
+
+    pub(crate) fn modified_header_or_footer(&self) -> bool {
+        self.modified_header_or_footer
+    }
+
+    pub(crate) fn header_footer_packet(&self) -> PlayerListHeaderFooter {
+        PlayerListHeaderFooter {
+            header: self.header.clone(),
+            footer: self.footer.clone(),
         }
     }
 
-    pub(crate) fn clear_packets(&self, mut push_packet: impl FnMut(S2cPlayPacket)) {
-        push_packet(UpdatePlayerList::RemovePlayer(self.entries.keys().cloned().collect()).into());
+    /// Returns the unlisted entries that were added to the client by
+    /// [`Self::initial_packets`] (if `initial` is `true`) or
+    /// [`Self::update_packets`] (if `initial` is `false`) and need to be
+    /// removed from the client's tab list later.
+    pub(crate) fn added_unlisted(&self, initial: bool) -> impl Iterator<Item = Uuid> + '_ {
+        self.entries
+            .iter()
+            .filter(move |(_, e)| !e.is_shown() && (initial || e.bits.created_this_tick()))
+            .map(|(&uuid, _)| uuid)
+    }
+
+    /// Returns the packet adding the entry with the given UUID to a client's
+    /// tab list, if the entry exists.
+    pub(crate) fn add_player_packet(&self, uuid: Uuid) -> Option<S2cPlayPacket> {
+        let e = self.entries.get(&uuid)?;
+        Some(UpdatePlayerList::AddPlayer(vec![e.add_player(uuid)]).into())
     }
 }
 
@@ -360,6 +412,10 @@ pub struct PlayerListEntry {
     game_mode: GameMode,
     ping: i32,
     display_name: Option<Text>,
+    listed: bool,
+    /// If the entry belongs to a vanished client, which hides it from the tab
+    /// list regardless of `listed`.
+    vanished: bool,
     bits: EntryBits,
 }
 
@@ -369,7 +425,8 @@ struct EntryBits {
     modified_game_mode: bool,
     modified_ping: bool,
     modified_display_name: bool,
-    #[bits(4)]
+    modified_listed: bool,
+    #[bits(3)]
     _pad: u8,
 }
 
@@ -423,4 +480,57 @@ impl PlayerListEntry {
             self.bits.set_modified_display_name(true);
         }
     }
+
+    /// Returns `true` if this entry is shown in the tab list.
+    pub fn is_listed(&self) -> bool {
+        self.listed
+    }
+
+    /// Sets whether or not this entry is shown in the tab list. Entries are
+    /// listed by default.
+    ///
+    /// Unlisted entries are useful for NPCs. The client still needs an entry
+    /// to spawn a player entity with the entry's UUID and skin, so unlisted
+    /// entries are briefly sent to clients before being removed from their
+    /// tab list again.
+    pub fn set_listed(&mut self, listed: bool) {
+        if self.listed != listed {
+            self.listed = listed;
+            self.bits.set_modified_listed(true);
+        }
+    }
+
+    /// Returns `true` if this entry is listed and does not belong to a
+    /// [vanished](crate::client::Client::set_vanished) client.
+    pub(crate) fn is_shown(&self) -> bool {
+        self.listed && !self.vanished
+    }
+
+    fn set_vanished(&mut self, vanished: bool) {
+        if self.vanished != vanished {
+            self.vanished = vanished;
+            self.bits.set_modified_listed(true);
+        }
+    }
+
+    fn add_player(&self, uuid: Uuid) -> PlayerListAddPlayer {
+        let mut properties = Vec::new();
+        if let Some(textures) = &self.textures {
+            properties.push(Property {
+                name: "textures".into(),
+                value: base64::encode(textures.payload()),
+                signature: Some(base64::encode(textures.signature())),
+            });
+        }
+
+        PlayerListAddPlayer {
+            uuid,
+            username: self.username.clone().into(),
+            properties,
+            game_mode: self.game_mode,
+            ping: VarInt(self.ping),
+            display_name: self.display_name.clone(),
+            sig_data: None,
+        }
+    }
 }
@@ -0,0 +1,387 @@
+//! Regions of a world protected from players.
+//!
+//! Each [`World`] has a [`Protection`] containing named [`Region`]s. A region
+//! is a box of blocks with flags allowing or denying [`ProtectedAction`]s in
+//! it. Clients are checked against the regions of their world when their
+//! packets are handled, before any [`ClientEvent`] is created:
+//!
+//! - Blocks in regions denying [`ProtectedAction::BlockBreak`] cannot be
+//!   broken, and are sent back to clients which broke them.
+//! - Blocks cannot be placed or used in regions denying
+//!   [`ProtectedAction::BlockPlace`].
+//! - Players in regions denying [`ProtectedAction::Pvp`] cannot attack or be
+//!   attacked by other players.
+//! - Clients cannot move into regions denying [`ProtectedAction::Entry`] and
+//!   are teleported back instead. Clients already inside such a region can
+//!   move freely, so they can leave it.
+//!
+//! The event for a denied action is replaced by a
+//! [`ClientEvent::ProtectionDenied`], which can be used to tell the player why
+//! nothing happened.
+//!
+//! [`World`]: crate::world::World
+//! [`ClientEvent`]: crate::client::ClientEvent
+//! [`ClientEvent::ProtectionDenied`]: crate::client::ClientEvent::ProtectionDenied
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::block_pos::BlockPos;
+
+/// An action players can be allowed or denied in a [`Region`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ProtectedAction {
+    /// Breaking blocks.
+    BlockBreak,
+    /// Placing blocks and interacting with blocks.
+    BlockPlace,
+    /// Attacking other players.
+    Pvp,
+    /// Moving into the region.
+    Entry,
+}
+
+/// A box of blocks in which [`ProtectedAction`]s are allowed or denied.
+///
+/// Where regions overlap, the region with the highest priority which has a
+/// flag for an action decides whether it is allowed. Actions without any flag
+/// are allowed. Members of the deciding region are always allowed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Region {
+    min: BlockPos,
+    max: BlockPos,
+    priority: i32,
+    flags: HashMap<ProtectedAction, bool>,
+    members: HashSet<Uuid>,
+}
+
+impl Region {
+    /// Creates a region containing all blocks between the corners `a` and `b`
+    /// inclusive, with priority 0 and no flags or members.
+    pub fn new(a: impl Into<BlockPos>, b: impl Into<BlockPos>) -> Self {
+        let (a, b) = (a.into(), b.into());
+
+        Self {
+            min: BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: BlockPos::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+            priority: 0,
+            flags: HashMap::new(),
+            members: HashSet::new(),
+        }
+    }
+
+    /// Gets the corner of this region with the smallest coordinates.
+    pub fn min(&self) -> BlockPos {
+        self.min
+    }
+
+    /// Gets the corner of this region with the largest coordinates.
+    pub fn max(&self) -> BlockPos {
+        self.max
+    }
+
+    /// Returns `true` if the block at `pos` is inside this region.
+    pub fn contains(&self, pos: impl Into<BlockPos>) -> bool {
+        let pos = pos.into();
+
+        (self.min.x..=self.max.x).contains(&pos.x)
+            && (self.min.y..=self.max.y).contains(&pos.y)
+            && (self.min.z..=self.max.z).contains(&pos.z)
+    }
+
+    /// Gets the priority of this region.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Sets the priority of this region. Regions with a higher priority
+    /// override the flags of overlapping regions with a lower priority.
+    pub fn set_priority(&mut self, priority: i32) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Gets the flag of an action in this region. `Some(true)` allows the
+    /// action, `Some(false)` denies it and `None` leaves it to overlapping
+    /// regions.
+    pub fn flag(&self, action: ProtectedAction) -> Option<bool> {
+        self.flags.get(&action).copied()
+    }
+
+    /// Allows an action in this region.
+    pub fn allow(&mut self, action: ProtectedAction) -> &mut Self {
+        self.flags.insert(action, true);
+        self
+    }
+
+    /// Denies an action in this region.
+    pub fn deny(&mut self, action: ProtectedAction) -> &mut Self {
+        self.flags.insert(action, false);
+        self
+    }
+
+    /// Removes the flag of an action, leaving it to overlapping regions.
+    pub fn clear_flag(&mut self, action: ProtectedAction) -> &mut Self {
+        self.flags.remove(&action);
+        self
+    }
+
+    /// Returns `true` if the player with the given UUID is a member of this
+    /// region.
+    pub fn is_member(&self, uuid: Uuid) -> bool {
+        self.members.contains(&uuid)
+    }
+
+    /// Makes the player with the given UUID a member of this region. Members
+    /// are not affected by the flags of the region.
+    pub fn add_member(&mut self, uuid: Uuid) -> &mut Self {
+        self.members.insert(uuid);
+        self
+    }
+
+    /// Removes the player with the given UUID from the members of this
+    /// region. Returns `true` if the player was a member.
+    pub fn remove_member(&mut self, uuid: Uuid) -> bool {
+        self.members.remove(&uuid)
+    }
+
+    /// Returns an iterator over the UUIDs of the members of this region.
+    pub fn members(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.members.iter().copied()
+    }
+}
+
+/// The named [`Region`]s of a world.
+#[derive(Clone, Default, Debug)]
+pub struct Protection {
+    regions: BTreeMap<String, Region>,
+}
+
+impl Protection {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a region with the given name, replacing any existing region with
+    /// the same name. A reference to the region is returned so that its flags
+    /// can be configured.
+    pub fn insert(&mut self, name: impl Into<String>, region: Region) -> &mut Region {
+        let name = name.into();
+        self.regions.insert(name.clone(), region);
+        self.regions.get_mut(&name).unwrap()
+    }
+
+    /// Removes the region with the given name and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<Region> {
+        self.regions.remove(name)
+    }
+
+    /// Gets a shared reference to the region with the given name.
+    pub fn get(&self, name: &str) -> Option<&Region> {
+        self.regions.get(name)
+    }
+
+    /// Gets an exclusive reference to the region with the given name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Region> {
+        self.regions.get_mut(name)
+    }
+
+    /// Returns an iterator over all regions and their names in alphabetical
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Region)> + '_ {
+        self.regions.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Returns an iterator over all regions containing the block at `pos`
+    /// and their names, in alphabetical order.
+    pub fn regions_at(&self, pos: impl Into<BlockPos>) -> impl Iterator<Item = (&str, &Region)> {
+        let pos = pos.into();
+        self.iter().filter(move |(_, r)| r.contains(pos))
+    }
+
+    /// Returns the number of regions.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Returns `true` if there are no regions.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Checks if the player with the given UUID may perform `action` at the
+    /// block at `pos`. Returns the name of the region denying it otherwise.
+    pub fn check(
+        &self,
+        action: ProtectedAction,
+        pos: impl Into<BlockPos>,
+        player: Uuid,
+    ) -> Result<(), &str> {
+        let deciding = self
+            .regions_at(pos)
+            .filter_map(|(name, r)| Some((name, r, r.flag(action)?)))
+            // Ties are broken by name so that the result is deterministic.
+            .max_by(|(n0, r0, _), (n1, r1, _)| {
+                r0.priority.cmp(&r1.priority).then_with(|| n1.cmp(n0))
+            });
+
+        match deciding {
+            Some((name, region, false)) if !region.is_member(player) => Err(name),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `true` if the player with the given UUID may perform `action`
+    /// at the block at `pos`. See [`Self::check`].
+    pub fn allows(&self, action: ProtectedAction, pos: impl Into<BlockPos>, player: Uuid) -> bool {
+        self.check(action, pos, player).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vek::Vec3;
+
+    use super::*;
+    use crate::protocol::packets::c2s::play::{
+        BlockFace, DiggingStatus, Hand, InteractKind, MovePlayerPosition, PlayerAction,
+        PlayerInteractBlock, PlayerInteractEntity, TeleportConfirm,
+    };
+    use crate::protocol::packets::s2c::play::S2cPlayPacket;
+    use crate::protocol::VarInt;
+    use crate::server;
+    use crate::testing::{messages, test_server};
+
+    #[test]
+    fn highest_priority_flag_decides() {
+        let mut protection = Protection::new();
+        let member = Uuid::from_u128(1);
+        let other = Uuid::from_u128(2);
+
+        protection
+            .insert("spawn", Region::new([10, 0, 10], [-10, 20, -10]))
+            .deny(ProtectedAction::BlockBreak)
+            .add_member(member);
+        protection
+            .insert("garden", Region::new([0, 0, 0], [2, 2, 2]))
+            .set_priority(1)
+            .allow(ProtectedAction::BlockBreak);
+
+        let check =
+            |pos: [i32; 3], player| protection.check(ProtectedAction::BlockBreak, pos, player);
+
+        assert_eq!(check([5, 5, 5], other), Err("spawn"));
+        assert_eq!(check([5, 5, 5], member), Ok(()));
+        assert_eq!(check([1, 1, 1], other), Ok(()));
+        assert_eq!(check([11, 5, 5], other), Ok(()));
+        assert!(protection.allows(ProtectedAction::Pvp, [5, 5, 5], other));
+
+        assert_eq!(
+            protection
+                .regions_at([1, 1, 1])
+                .map(|(n, _)| n)
+                .collect::<Vec<_>>(),
+            ["garden", "spawn"]
+        );
+    }
+
+    #[test]
+    fn protected_regions_deny_actions() {
+        let mut server = test_server();
+
+        let client = server.connect("tester");
+        let victim = server.connect("victim");
+        for c in [&client, &victim] {
+            let c = server.server_mut().clients.get_mut(c.id()).unwrap();
+            c.spawn_player_entity(());
+        }
+
+        let s = server.server_mut();
+        let member = server::offline_uuid("member");
+        let (_, world) = s.worlds.iter_mut().next().unwrap();
+        world
+            .protection
+            .insert("spawn", Region::new([-8, 0, -8], [8, 120, 8]))
+            .deny(ProtectedAction::BlockBreak)
+            .deny(ProtectedAction::BlockPlace)
+            .deny(ProtectedAction::Pvp)
+            .add_member(member);
+        world
+            .protection
+            .insert("vault", Region::new([10, 90, -2], [12, 110, 2]))
+            .deny(ProtectedAction::Entry);
+        server.tick();
+
+        for pkt in client.take_packets() {
+            if let S2cPlayPacket::PlayerPositionLook(p) = pkt {
+                assert!(client.send(TeleportConfirm {
+                    teleport_id: p.teleport_id,
+                }));
+            }
+        }
+        victim.take_packets();
+
+        let pos = BlockPos::new(1, 99, 1);
+        assert!(client.send(PlayerAction {
+            status: DiggingStatus::StartedDigging,
+            location: pos,
+            face: BlockFace::Top,
+            sequence: VarInt(1),
+        }));
+        assert!(client.send(PlayerInteractBlock {
+            hand: Hand::Main,
+            location: pos,
+            face: BlockFace::Top,
+            cursor_pos: Vec3::new(0.5, 1.0, 0.5),
+            head_inside_block: false,
+            sequence: VarInt(2),
+        }));
+
+        let victim_entity = server
+            .server()
+            .clients
+            .get(victim.id())
+            .unwrap()
+            .player_entity()
+            .unwrap();
+        assert!(client.send(PlayerInteractEntity {
+            entity_id: VarInt(victim_entity.to_network_id()),
+            kind: InteractKind::Attack,
+            sneaking: false,
+        }));
+        server.tick();
+
+        assert_eq!(
+            messages(&client),
+            [
+                "BlockBreak denied by spawn",
+                "BlockPlace denied by spawn",
+                "Pvp denied by spawn"
+            ]
+        );
+
+        // Moving into the vault is undone.
+        assert!(client.send(MovePlayerPosition {
+            position: Vec3::new(10.5, 100.0, 0.5),
+            on_ground: true,
+        }));
+        server.tick();
+
+        let c = server.server().clients.get(client.id()).unwrap();
+        assert_eq!(c.position(), Vec3::new(0.5, 100.0, 0.5));
+        let pkts = client.take_packets();
+        assert!(pkts
+            .iter()
+            .any(|pkt| matches!(pkt, S2cPlayPacket::PlayerPositionLook(_))));
+        assert!(pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::GameMessage(p) if p.chat.to_plain() == "Entry denied by vault"
+        )));
+
+        let (_, world) = server.server().worlds.iter().next().unwrap();
+        assert!(world
+            .protection
+            .allows(ProtectedAction::BlockBreak, pos, member));
+    }
+}
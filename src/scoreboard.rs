@@ -0,0 +1,269 @@
+//! Scoreboard displays.
+//!
+//! Currently the only display is the [`Sidebar`], which is shown to a client
+//! with [`Client::set_sidebar`].
+//!
+//! [`Client::set_sidebar`]: crate::client::Client::set_sidebar
+
+use crate::protocol::packets::s2c::play::{
+    S2cPlayPacket, ScoreboardDisplay, ScoreboardObjective, ScoreboardObjectiveUpdate,
+    ScoreboardObjectiveUpdateMode, ScoreboardPlayerUpdate, ScoreboardPosition,
+    ScoreboardRenderType, Team, TeamCollisionRule, TeamColor, TeamCreate, TeamFlags, TeamInfo,
+    TeamMode, TeamVisibility,
+};
+use crate::protocol::{BoundedString, VarInt};
+use crate::text::Text;
+
+/// The maximum number of lines the vanilla client displays in the sidebar.
+/// Additional lines are not sent.
+pub const MAX_SIDEBAR_LINES: usize = 15;
+
+/// The name of the objective used to display the sidebar.
+const SIDEBAR_OBJECTIVE: &str = "valence_sidebar";
+
+/// A list of lines displayed on the right side of a client's screen.
+///
+/// Lines are arbitrary [`Text`] of any length, listed from top to bottom.
+/// Each line is displayed as the prefix of a team containing an invisible
+/// score holder, which avoids the length and uniqueness limits of score
+/// holder names. The score numbers are still shown by the vanilla client.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Sidebar {
+    title: Text,
+    lines: Vec<Text>,
+}
+
+impl Sidebar {
+    /// Creates a sidebar with the given title and lines.
+    ///
+    /// Only the first [`MAX_SIDEBAR_LINES`] lines are displayed.
+    pub fn new(title: impl Into<Text>, lines: Vec<Text>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+        }
+    }
+
+    /// Gets the title displayed above the lines.
+    pub fn title(&self) -> &Text {
+        &self.title
+    }
+
+    /// Sets the title displayed above the lines.
+    pub fn set_title(&mut self, title: impl Into<Text>) {
+        self.title = title.into();
+    }
+
+    /// Gets the lines of the sidebar from top to bottom.
+    pub fn lines(&self) -> &[Text] {
+        &self.lines
+    }
+
+    /// Replaces all lines of the sidebar.
+    pub fn set_lines(&mut self, lines: Vec<Text>) {
+        self.lines = lines;
+    }
+
+    /// Sets the line at `idx`, appending empty lines if `idx` is out of
+    /// bounds.
+    pub fn set_line(&mut self, idx: usize, line: impl Into<Text>) {
+        if idx >= self.lines.len() {
+            self.lines.resize(idx + 1, Text::default());
+        }
+        self.lines[idx] = line.into();
+    }
+
+    /// Adds a line to the bottom of the sidebar.
+    pub fn push_line(&mut self, line: impl Into<Text>) -> &mut Self {
+        self.lines.push(line.into());
+        self
+    }
+
+    fn visible_lines(&self) -> &[Text] {
+        &self.lines[..self.lines.len().min(MAX_SIDEBAR_LINES)]
+    }
+}
+
+/// Creates the packets needed to change the sidebar displayed by a client from
+/// `old` to `new`.
+pub(crate) fn update_sidebar_packets(
+    old: Option<&Sidebar>,
+    new: Option<&Sidebar>,
+    mut push: impl FnMut(S2cPlayPacket),
+) {
+    let objective = || BoundedString(SIDEBAR_OBJECTIVE.to_owned());
+
+    let new = match new {
+        Some(new) => new,
+        None => {
+            if let Some(old) = old {
+                push(
+                    ScoreboardObjectiveUpdate {
+                        objective_name: objective(),
+                        mode: ScoreboardObjectiveUpdateMode::Remove,
+                    }
+                    .into(),
+                );
+
+                for i in 0..old.visible_lines().len() {
+                    push(
+                        Team {
+                            team_name: team_name(i),
+                            mode: TeamMode::Remove,
+                        }
+                        .into(),
+                    );
+                }
+            }
+            return;
+        }
+    };
+
+    let display = ScoreboardObjective {
+        display_name: new.title.clone(),
+        render_type: ScoreboardRenderType::Integer,
+    };
+
+    match old {
+        None => {
+            push(
+                ScoreboardObjectiveUpdate {
+                    objective_name: objective(),
+                    mode: ScoreboardObjectiveUpdateMode::Create(display),
+                }
+                .into(),
+            );
+            push(
+                ScoreboardDisplay {
+                    position: ScoreboardPosition::Sidebar,
+                    score_name: objective(),
+                }
+                .into(),
+            );
+        }
+        Some(old) if old.title != new.title => push(
+            ScoreboardObjectiveUpdate {
+                objective_name: objective(),
+                mode: ScoreboardObjectiveUpdateMode::Update(display),
+            }
+            .into(),
+        ),
+        Some(_) => {}
+    }
+
+    let old_lines = old.map_or(&[][..], |old| old.visible_lines());
+    let new_lines = new.visible_lines();
+
+    for (i, line) in new_lines.iter().enumerate() {
+        let mode = match old_lines.get(i) {
+            None => TeamMode::Create(Box::new(TeamCreate {
+                info: team_info(line),
+                entities: vec![entry_name(i)],
+            })),
+            Some(old_line) if old_line != line => TeamMode::UpdateInfo(Box::new(team_info(line))),
+            Some(_) => continue,
+        };
+
+        push(
+            Team {
+                team_name: team_name(i),
+                mode,
+            }
+            .into(),
+        );
+    }
+
+    for i in new_lines.len()..old_lines.len() {
+        push(
+            ScoreboardPlayerUpdate {
+                entity_name: entry_name(i),
+                objective_name: objective(),
+                value: None,
+            }
+            .into(),
+        );
+        push(
+            Team {
+                team_name: team_name(i),
+                mode: TeamMode::Remove,
+            }
+            .into(),
+        );
+    }
+
+    // Scores are sorted in descending order, so the scores of all lines change
+    // when the number of lines changes.
+    if old_lines.len() != new_lines.len() {
+        for i in 0..new_lines.len() {
+            push(
+                ScoreboardPlayerUpdate {
+                    entity_name: entry_name(i),
+                    objective_name: objective(),
+                    value: Some(VarInt((new_lines.len() - 1 - i) as i32)),
+                }
+                .into(),
+            );
+        }
+    }
+}
+
+fn team_name(line: usize) -> BoundedString<0, 16> {
+    BoundedString(format!("valence_sb_{line}"))
+}
+
+/// Formatting codes are not displayed, so each line gets a unique invisible
+/// score holder name.
+fn entry_name(line: usize) -> BoundedString<0, 40> {
+    BoundedString(format!("§{line:x}§r"))
+}
+
+fn team_info(line: &Text) -> TeamInfo {
+    TeamInfo {
+        display_name: Text::default(),
+        flags: TeamFlags::new(false, false),
+        name_tag_visibility: TeamVisibility::Always,
+        collision_rule: TeamCollisionRule::Always,
+        color: TeamColor::Reset,
+        prefix: line.clone(),
+        suffix: Text::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packets(old: Option<&Sidebar>, new: Option<&Sidebar>) -> Vec<S2cPlayPacket> {
+        let mut packets = Vec::new();
+        update_sidebar_packets(old, new, |pkt| packets.push(pkt));
+        packets
+    }
+
+    #[test]
+    fn sidebar_diff() {
+        let a = Sidebar::new("Title", vec!["first".into(), "second".into()]);
+
+        // Objective, display, two teams and two scores.
+        assert_eq!(packets(None, Some(&a)).len(), 6);
+        assert!(packets(Some(&a), Some(&a)).is_empty());
+
+        let mut b = a.clone();
+        b.set_line(1, "changed");
+        assert!(matches!(
+            packets(Some(&a), Some(&b)).as_slice(),
+            [S2cPlayPacket::Team(Team {
+                mode: TeamMode::UpdateInfo(_),
+                ..
+            })]
+        ));
+
+        let mut c = a.clone();
+        c.set_lines(vec!["only".into()]);
+        // Update the first team, remove the second score and team, and
+        // renumber the remaining score.
+        assert_eq!(packets(Some(&a), Some(&c)).len(), 4);
+
+        // Remove the objective and both teams.
+        assert_eq!(packets(Some(&a), None).len(), 3);
+    }
+}
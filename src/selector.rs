@@ -0,0 +1,550 @@
+//! Target selectors such as `@a` and `@e[type=zombie,distance=..10]`.
+//!
+//! A [`Selector`] is parsed from a string with [`Selector::parse`] and
+//! evaluated against the clients and entities of a [`Server`] with
+//! [`Selector::select`]. Selectors are also accepted by the
+//! [player](crate::command::ArgKind::Players) and
+//! [entity](crate::command::ArgKind::Entities) command arguments.
+//!
+//! The following selectors are supported:
+//!
+//! - `@p` selects the nearest player.
+//! - `@r` selects a random player.
+//! - `@a` selects every player.
+//! - `@e` selects every entity, including player entities.
+//! - `@s` selects the player executing the command.
+//! - A username selects the player with that username.
+//!
+//! Selectors starting with `@` can be followed by arguments in brackets, such
+//! as `@e[type=!player,limit=3]`. The supported arguments are `type`, `name`,
+//! `tag`, `gamemode`, `distance`, `x`, `y`, `z`, `limit` and `sort`. Like in
+//! vanilla, `type`, `name`, `tag` and `gamemode` can be negated with `!` and
+//! given more than once.
+//!
+//! Only players have a name, which is their username. The tags of a player
+//! are the tags of its [player entity](crate::client::Client::player_entity).
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use thiserror::Error;
+use vek::Vec3;
+
+use crate::client::{Client, ClientId, GameMode};
+use crate::command::CommandSender;
+use crate::config::Config;
+use crate::entity::{Entity, EntityId, EntityKind};
+use crate::server::Server;
+use crate::util::valid_username;
+use crate::world::WorldId;
+
+/// A parsed target selector. See the [module documentation](self) for the
+/// syntax.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Selector {
+    kind: SelectorKind,
+    types: Vec<(EntityKind, bool)>,
+    names: Vec<(String, bool)>,
+    tags: Vec<(String, bool)>,
+    game_modes: Vec<(GameMode, bool)>,
+    distance: Option<Range>,
+    origin: Vec3<Option<f64>>,
+    limit: Option<usize>,
+    sort: Sort,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum SelectorKind {
+    Name(String),
+    Nearest,
+    Random,
+    AllPlayers,
+    Entities,
+    Executor,
+}
+
+/// An inclusive range of distances. Either end can be open.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Range {
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Sort {
+    Arbitrary,
+    Nearest,
+    Furthest,
+    Random,
+}
+
+/// Something selected by a [`Selector`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Target {
+    /// A client selected by a player selector.
+    Player(ClientId),
+    /// An entity selected by `@e`.
+    Entity(EntityId),
+}
+
+/// An error returned by [`Selector::parse`].
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum SelectorError {
+    #[error("unknown selector \"{0}\"")]
+    UnknownSelector(String),
+    #[error("invalid username \"{0}\"")]
+    InvalidName(String),
+    #[error("selector arguments are not closed with ']'")]
+    Unterminated,
+    #[error("unknown selector argument \"{0}\"")]
+    UnknownArgument(String),
+    #[error("invalid value \"{value}\" for selector argument \"{key}\"")]
+    InvalidValue { key: String, value: String },
+}
+
+/// A candidate for selection.
+struct Candidate<'a, C: Config> {
+    target: Target,
+    world: WorldId,
+    position: Vec3<f64>,
+    kind: EntityKind,
+    name: Option<&'a str>,
+    game_mode: Option<GameMode>,
+    /// The entity the tags are read from.
+    entity: Option<&'a Entity<C>>,
+}
+
+impl Selector {
+    /// Parses a selector from a string.
+    pub fn parse(s: &str) -> Result<Self, SelectorError> {
+        let (kind, args) = match s.strip_prefix('@') {
+            Some(rest) => {
+                let (name, args) = match rest.split_once('[') {
+                    Some((name, args)) => (
+                        name,
+                        Some(args.strip_suffix(']').ok_or(SelectorError::Unterminated)?),
+                    ),
+                    None => (rest, None),
+                };
+
+                let kind = match name {
+                    "p" => SelectorKind::Nearest,
+                    "r" => SelectorKind::Random,
+                    "a" => SelectorKind::AllPlayers,
+                    "e" => SelectorKind::Entities,
+                    "s" => SelectorKind::Executor,
+                    _ => return Err(SelectorError::UnknownSelector(s.into())),
+                };
+
+                (kind, args)
+            }
+            None if valid_username(s) => (SelectorKind::Name(s.into()), None),
+            None => return Err(SelectorError::InvalidName(s.into())),
+        };
+
+        let (limit, sort) = match kind {
+            SelectorKind::Nearest => (Some(1), Sort::Nearest),
+            SelectorKind::Random => (Some(1), Sort::Random),
+            _ => (None, Sort::Arbitrary),
+        };
+
+        let mut selector = Self {
+            kind,
+            types: Vec::new(),
+            names: Vec::new(),
+            tags: Vec::new(),
+            game_modes: Vec::new(),
+            distance: None,
+            origin: Vec3::new(None, None, None),
+            limit,
+            sort,
+        };
+
+        for arg in args.into_iter().flat_map(|args| args.split(',')) {
+            let arg = arg.trim();
+            if arg.is_empty() {
+                continue;
+            }
+
+            let (key, value) = arg
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| SelectorError::UnknownArgument(arg.into()))?;
+
+            let invalid = || SelectorError::InvalidValue {
+                key: key.into(),
+                value: value.into(),
+            };
+
+            let (negated, unnegated) = match value.strip_prefix('!') {
+                Some(v) => (true, v),
+                None => (false, value),
+            };
+
+            match key {
+                "type" => {
+                    let name = unnegated.strip_prefix("minecraft:").unwrap_or(unnegated);
+                    let kind = EntityKind::from_name(name).ok_or_else(invalid)?;
+                    selector.types.push((kind, negated));
+                }
+                "name" => selector.names.push((unnegated.into(), negated)),
+                "tag" => selector.tags.push((unnegated.into(), negated)),
+                "gamemode" => {
+                    let mode = match unnegated {
+                        "survival" => GameMode::Survival,
+                        "creative" => GameMode::Creative,
+                        "adventure" => GameMode::Adventure,
+                        "spectator" => GameMode::Spectator,
+                        _ => return Err(invalid()),
+                    };
+                    selector.game_modes.push((mode, negated));
+                }
+                "distance" => {
+                    let range = Range::parse(value).ok_or_else(invalid)?;
+                    if range.min.is_some_and(|min| min < 0.0) {
+                        return Err(invalid());
+                    }
+                    selector.distance = Some(range);
+                }
+                "x" => selector.origin.x = Some(value.parse().map_err(|_| invalid())?),
+                "y" => selector.origin.y = Some(value.parse().map_err(|_| invalid())?),
+                "z" => selector.origin.z = Some(value.parse().map_err(|_| invalid())?),
+                "limit" => {
+                    let limit = value.parse().map_err(|_| invalid())?;
+                    if limit == 0 {
+                        return Err(invalid());
+                    }
+                    selector.limit = Some(limit);
+                }
+                "sort" => {
+                    selector.sort = match value {
+                        "arbitrary" => Sort::Arbitrary,
+                        "nearest" => Sort::Nearest,
+                        "furthest" => Sort::Furthest,
+                        "random" => Sort::Random,
+                        _ => return Err(invalid()),
+                    };
+                }
+                _ => return Err(SelectorError::UnknownArgument(key.into())),
+            }
+        }
+
+        Ok(selector)
+    }
+
+    /// Returns `true` if this selector can select entities which are not
+    /// players. Only `@e` can, unless it is limited to `type=player`.
+    pub fn selects_entities(&self) -> bool {
+        self.kind == SelectorKind::Entities
+            && !self
+                .types
+                .iter()
+                .any(|&(kind, negated)| kind == EntityKind::Player && !negated)
+    }
+
+    /// Returns `true` if this selector selects at most one target.
+    pub fn is_single(&self) -> bool {
+        matches!(self.kind, SelectorKind::Name(_) | SelectorKind::Executor) || self.limit == Some(1)
+    }
+
+    /// Evaluates this selector on behalf of `sender`.
+    ///
+    /// Positions are relative to the client executing the command, or to the
+    /// origin for the console. Selectors sorting by distance or filtering by
+    /// position only select targets in the world of the client executing the
+    /// command. `@s` selects nothing when executed by the console.
+    pub fn select<C: Config>(&self, server: &Server<C>, sender: CommandSender) -> Vec<Target> {
+        let executor = match sender {
+            CommandSender::Client(id) => server.clients.get(id).map(|client| (id, client)),
+            CommandSender::Console => None,
+        };
+
+        let executor_pos = executor.map_or(Vec3::zero(), |(_, client)| client.position());
+        let origin = Vec3::new(
+            self.origin.x.unwrap_or(executor_pos.x),
+            self.origin.y.unwrap_or(executor_pos.y),
+            self.origin.z.unwrap_or(executor_pos.z),
+        );
+
+        let world_limited = self.distance.is_some()
+            || self.origin.iter().any(Option::is_some)
+            || matches!(self.sort, Sort::Nearest | Sort::Furthest);
+        let world = executor
+            .map(|(_, client)| client.world())
+            .filter(|_| world_limited);
+
+        let mut candidates: Vec<_> = match &self.kind {
+            SelectorKind::Name(name) => server
+                .clients
+                .find_by_username(name)
+                .and_then(|id| Some(Candidate::player(server, id, server.clients.get(id)?)))
+                .into_iter()
+                .collect(),
+            SelectorKind::Executor => executor
+                .map(|(id, client)| Candidate::player(server, id, client))
+                .into_iter()
+                .collect(),
+            SelectorKind::Nearest | SelectorKind::Random | SelectorKind::AllPlayers => server
+                .clients
+                .iter()
+                .filter(|(_, client)| !client.is_disconnected())
+                .map(|(id, client)| Candidate::player(server, id, client))
+                .collect(),
+            SelectorKind::Entities => {
+                let owners: HashMap<_, _> = server
+                    .clients
+                    .iter()
+                    .filter_map(|(_, client)| Some((client.player_entity()?, client)))
+                    .collect();
+
+                server
+                    .entities
+                    .iter()
+                    .map(|(id, entity)| {
+                        let owner = owners.get(&id).copied();
+
+                        Candidate {
+                            target: Target::Entity(id),
+                            world: entity.world(),
+                            position: entity.position(),
+                            kind: entity.kind(),
+                            name: owner.map(|client| client.username()),
+                            game_mode: owner.map(|client| client.game_mode()),
+                            entity: Some(entity),
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        candidates.retain(|c| {
+            world.is_none_or(|world| c.world == world)
+                && self
+                    .distance
+                    .is_none_or(|range| range.contains(c.position.distance(origin)))
+                && matches_all(&self.types, |&kind| c.kind == kind)
+                && matches_all(&self.names, |name| {
+                    c.name.is_some_and(|n| n.eq_ignore_ascii_case(name))
+                })
+                && matches_all(&self.tags, |tag| {
+                    c.entity.is_some_and(|entity| entity.has_tag(tag))
+                })
+                && matches_all(&self.game_modes, |&mode| c.game_mode == Some(mode))
+        });
+
+        let distance = |c: &Candidate<C>| c.position.distance_squared(origin);
+        match self.sort {
+            Sort::Arbitrary => {}
+            Sort::Nearest => candidates.sort_by(|a, b| distance(a).total_cmp(&distance(b))),
+            Sort::Furthest => candidates.sort_by(|a, b| distance(b).total_cmp(&distance(a))),
+            Sort::Random => candidates.shuffle(&mut rand::thread_rng()),
+        }
+
+        if let Some(limit) = self.limit {
+            candidates.truncate(limit);
+        }
+
+        candidates.into_iter().map(|c| c.target).collect()
+    }
+}
+
+impl<'a, C: Config> Candidate<'a, C> {
+    fn player(server: &'a Server<C>, id: ClientId, client: &'a Client<C>) -> Self {
+        Self {
+            target: Target::Player(id),
+            world: client.world(),
+            position: client.position(),
+            kind: EntityKind::Player,
+            name: Some(client.username()),
+            game_mode: Some(client.game_mode()),
+            entity: client
+                .player_entity()
+                .and_then(|entity| server.entities.get(entity)),
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Returns `true` if `matches` returns `true` for every value which is not
+/// negated and `false` for every value which is.
+fn matches_all<T>(values: &[(T, bool)], mut matches: impl FnMut(&T) -> bool) -> bool {
+    values
+        .iter()
+        .all(|(value, negated)| matches(value) != *negated)
+}
+
+impl Range {
+    /// Parses a range such as `5`, `..5`, `1..` or `1..5`.
+    fn parse(s: &str) -> Option<Self> {
+        let parse_end = |s: &str| match s {
+            "" => Some(None),
+            s => s.parse().ok().map(Some),
+        };
+
+        let range = match s.split_once("..") {
+            Some((min, max)) => Self {
+                min: parse_end(min)?,
+                max: parse_end(max)?,
+            },
+            None => {
+                let value = s.parse().ok()?;
+                Self {
+                    min: Some(value),
+                    max: Some(value),
+                }
+            }
+        };
+
+        (range.min.is_some() || range.max.is_some()).then_some(range)
+    }
+
+    fn contains(self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{self, ArgKind, ArgValue};
+    use crate::testing::test_server;
+
+    #[test]
+    fn parse_selectors() {
+        let selector = Selector::parse("@e[type=!player, tag=boss,distance=..10,limit=2]").unwrap();
+        assert_eq!(selector.kind, SelectorKind::Entities);
+        assert_eq!(selector.types, [(EntityKind::Player, true)]);
+        assert_eq!(selector.tags, [("boss".to_owned(), false)]);
+        assert_eq!(
+            selector.distance,
+            Some(Range {
+                min: None,
+                max: Some(10.0)
+            })
+        );
+        assert_eq!(selector.limit, Some(2));
+        assert!(selector.selects_entities());
+        assert!(!selector.is_single());
+
+        assert!(Selector::parse("@p").unwrap().is_single());
+        assert!(Selector::parse("Notch").unwrap().is_single());
+        assert!(!Selector::parse("@e[type=minecraft:player]")
+            .unwrap()
+            .selects_entities());
+
+        assert_eq!(
+            Selector::parse("@x"),
+            Err(SelectorError::UnknownSelector("@x".into()))
+        );
+        assert_eq!(
+            Selector::parse("@a[limit=1"),
+            Err(SelectorError::Unterminated)
+        );
+        assert_eq!(
+            Selector::parse("@a[color=red]"),
+            Err(SelectorError::UnknownArgument("color".into()))
+        );
+        assert_eq!(
+            Selector::parse("@a[limit=0]"),
+            Err(SelectorError::InvalidValue {
+                key: "limit".into(),
+                value: "0".into()
+            })
+        );
+        assert!(Selector::parse("not a name").is_err());
+    }
+
+    #[test]
+    fn ranges() {
+        let range = Range::parse("1..5").unwrap();
+        assert!(range.contains(1.0) && range.contains(5.0) && !range.contains(5.5));
+        assert!(Range::parse("3").unwrap().contains(3.0));
+        assert!(Range::parse("2..").unwrap().contains(100.0));
+        assert!(!Range::parse("..2").unwrap().contains(2.5));
+        assert_eq!(Range::parse(".."), None);
+        assert_eq!(Range::parse("a..b"), None);
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn selectors_select_targets() {
+        let mut server = test_server();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        let carol = server.connect("carol");
+        server.tick();
+
+        let s = server.server_mut();
+        let (world_id, _) = s.worlds.iter().next().unwrap();
+        let c = s.clients.get_mut(bob.id()).unwrap();
+        c.teleport([10.5, 100.0, 0.5], 0.0, 0.0);
+        let c = s.clients.get_mut(carol.id()).unwrap();
+        c.teleport([3.5, 100.0, 0.5], 0.0, 0.0);
+        c.set_game_mode(GameMode::Creative);
+
+        let (zombie, entity) = s.entities.insert(EntityKind::Zombie, ());
+        entity.set_world(world_id);
+        entity.set_position([2.0, 100.0, 0.0]);
+        s.entities.add_tag(zombie, "boss");
+        let (pig, entity) = s.entities.insert(EntityKind::Pig, ());
+        entity.set_world(world_id);
+        entity.set_position([20.0, 100.0, 0.0]);
+        server.tick();
+
+        let s = server.server();
+        let select =
+            |selector: &str, sender| selector.parse::<Selector>().unwrap().select(s, sender);
+        let sender = CommandSender::Client(alice.id());
+        let [alice, bob, carol] = [&alice, &bob, &carol].map(|c| Target::Player(c.id()));
+        let [zombie, pig] = [zombie, pig].map(Target::Entity);
+
+        assert_eq!(select("@p", sender), [alice]);
+        assert_eq!(select("@p[name=!alice]", sender), [carol]);
+        assert_eq!(select("@a[sort=furthest]", sender), [bob, carol, alice]);
+        assert_eq!(select("@a[gamemode=creative]", sender), [carol]);
+        assert_eq!(
+            select("@a[distance=..5,sort=nearest]", sender),
+            [alice, carol]
+        );
+        assert_eq!(
+            select("@e[type=!player,sort=nearest]", sender),
+            [zombie, pig]
+        );
+        assert_eq!(select("@e[tag=boss]", sender), [zombie]);
+        assert_eq!(select("@e[distance=10..]", sender), [pig]);
+        assert_eq!(select("@e[x=20,y=100,z=0,distance=..1]", sender), [pig]);
+        assert_eq!(select("@s", sender), [alice]);
+        assert_eq!(select("@s", CommandSender::Console), []);
+        assert_eq!(select("Bob", CommandSender::Console), [bob]);
+        assert_eq!(select("@r", CommandSender::Console).len(), 1);
+
+        let s = server.server_mut();
+        s.commands
+            .register("target", |_, ctx| match ctx.arg("target") {
+                Some(ArgValue::Targets(targets)) => Ok(Some(format!("{targets:?}").into())),
+                _ => Err("no target".into()),
+            })
+            .arg("target", ArgKind::Entities { single: true });
+
+        assert!(command::execute(
+            s,
+            CommandSender::Console,
+            "target @e[type=pig,limit=1]"
+        ));
+        assert!(!command::execute(s, CommandSender::Console, "target @e"));
+        assert!(!command::execute(
+            s,
+            CommandSender::Console,
+            "target @e[type=creeper,limit=1]"
+        ));
+    }
+}
@@ -1,37 +1,49 @@
 //! The heart of the server.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::iter::FusedIterator;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicI64, Ordering};
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicI64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 use std::{io, thread};
 
-use anyhow::{bail, ensure, Context};
+use anyhow::{ensure, Context};
 use flume::{Receiver, Sender};
+use futures::future::{self, Shared};
+use futures::FutureExt;
 use num::BigInt;
 use rand::rngs::OsRng;
-use rayon::iter::ParallelIterator;
-use reqwest::Client as HttpClient;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use rsa::{PaddingScheme, PublicKeyParts, RsaPrivateKey};
-use serde::Deserialize;
 use serde_json::{json, Value};
 use sha1::digest::Update;
 use sha1::Sha1;
 use sha2::{Digest, Sha256};
-use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
-use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::{Handle, Runtime};
-use tokio::sync::{oneshot, Semaphore};
+use tokio::sync::{oneshot, watch, Semaphore};
+use tracing::Instrument;
 use uuid::Uuid;
 
 use crate::biome::{Biome, BiomeId};
-use crate::client::{Client, Clients};
-use crate::config::{Config, ServerListPing};
+use crate::block::BlockKind;
+use crate::chunk::ChunkPos;
+use crate::client::{Client, ClientId, Clients, NetworkCounters, PacketLimits};
+use crate::command::{self, CommandSender, Commands};
+use crate::config::{
+    Config, PlayerSampleEntry, ServerListPing, ServerStatus, TickCatchUp, ViewDistanceScaling,
+};
 use crate::dimension::{Dimension, DimensionId};
 use crate::entity::Entities;
+use crate::event_bus::{self, EventBus};
+use crate::favicon::Favicon;
+use crate::function::{self, Functions};
+use crate::lan::announce_lan;
+use crate::metrics::{serve_metrics, Metrics};
+use crate::mojang::MojangApi;
+use crate::packet_log::PacketLogFilter;
+use crate::permission::{Permissions, Whitelist};
 use crate::player_list::PlayerLists;
 use crate::player_textures::SignedPlayerTextures;
 use crate::protocol::codec::{Decoder, Encoder};
@@ -44,11 +56,18 @@ use crate::protocol::packets::s2c::login::{
 };
 use crate::protocol::packets::s2c::play::S2cPlayPacket;
 use crate::protocol::packets::s2c::status::{QueryPong, QueryResponse};
-use crate::protocol::packets::Property;
 use crate::protocol::{BoundedArray, BoundedString, VarInt};
+use crate::storage::{Ban, BanTarget, Storage};
+use crate::system::{self, Systems};
+use crate::text::Text;
+#[cfg(unix)]
+use crate::transport::UnixTransport;
+use crate::transport::{
+    Connection, ConnectionReader, ConnectionWriter, ListenAddress, TcpTransport, Transport,
+};
 use crate::util::valid_username;
 use crate::world::Worlds;
-use crate::{Ticks, PROTOCOL_VERSION, VERSION_NAME};
+use crate::{ident, Ticks, PROTOCOL_VERSION, VERSION_NAME};
 
 /// Contains the entire state of a running Minecraft server, accessible from
 /// within the [update](crate::config::Config::update) loop.
@@ -65,6 +84,17 @@ pub struct Server<C: Config> {
     pub worlds: Worlds<C>,
     /// All of the player lists on the server.
     pub player_lists: PlayerLists<C>,
+    /// All of the commands on the server.
+    pub commands: Commands<C>,
+    /// All of the [functions](crate::function) on the server.
+    pub functions: Functions,
+    /// The permission groups and users of the server. This is empty by
+    /// default and can be loaded with [`Permissions::load`].
+    pub permissions: Permissions,
+    /// All of the [systems](crate::system) on the server.
+    pub systems: Systems<C>,
+    /// The [event handlers](crate::event_bus) of the server.
+    pub event_bus: EventBus<C>,
 }
 
 /// A handle to a Minecraft server containing the subset of functionality which
@@ -84,10 +114,24 @@ impl<C: Config> Clone for SharedServer<C> {
 
 struct SharedServerInner<C: Config> {
     cfg: C,
-    address: SocketAddr,
+    addresses: Vec<ListenAddress>,
     tick_rate: Ticks,
+    tick_catch_up: TickCatchUp,
+    /// The number of ticks the update loop is behind schedule.
+    tick_debt: AtomicI64,
     online_mode: bool,
     max_connections: usize,
+    packet_limits: PacketLimits,
+    packet_log: Option<PacketLogFilter>,
+    chunk_resend_threshold: usize,
+    autosave_interval: Option<Ticks>,
+    view_distance_scaling: Option<ViewDistanceScaling>,
+    /// The current limit on the view distance of clients. This is 32, the
+    /// largest view distance, while the limit does not apply.
+    view_distance_limit: AtomicU8,
+    /// The total duration and number of ticks in the current interval of
+    /// [`ViewDistanceScaling`].
+    tick_load: Mutex<(Duration, Ticks)>,
     incoming_packet_capacity: usize,
     outgoing_packet_capacity: usize,
     tokio_handle: Handle,
@@ -98,8 +142,10 @@ struct SharedServerInner<C: Config> {
     /// The instant the server was started.
     start_instant: Instant,
     /// Receiver for new clients past the login stage.
-    new_clients_rx: Receiver<NewClientMessage>,
-    new_clients_tx: Sender<NewClientMessage>,
+    new_clients_rx: Receiver<NewClientMessage<C>>,
+    new_clients_tx: Sender<NewClientMessage<C>>,
+    /// Receiver for lines entered in the console.
+    console_rx: Receiver<String>,
     /// Incremented on every game tick.
     tick_counter: AtomicI64,
     /// A semaphore used to limit the number of simultaneous connections to the
@@ -107,16 +153,41 @@ struct SharedServerInner<C: Config> {
     connection_sema: Arc<Semaphore>,
     /// The result that will be returned when the server is shut down.
     shutdown_result: Mutex<Option<ShutdownResult>>,
+    /// Set to `true` once shutdown is initiated. Background tasks wait on it
+    /// to stop.
+    shutting_down: watch::Sender<bool>,
+    /// The server list ping response set with [`SharedServer::set_status`].
+    status: RwLock<Option<ServerStatus>>,
+    /// The players allowed to join, set with [`SharedServer::set_whitelist`].
+    whitelist: RwLock<Option<Whitelist>>,
+    /// The message clients are disconnected with when the server shuts down.
+    shutdown_reason: Mutex<Option<Text>>,
+    /// Counters and gauges describing the server.
+    metrics: Metrics,
+    /// The number of tasks sending packets to clients. Used to wait for
+    /// outgoing packets to be written before shutting down.
+    encoder_tasks: AtomicUsize,
+    /// The number of tasks running [`Config::save_player_data`] or
+    /// [`Config::save_chunk`]. Used to wait for saves to finish before
+    /// shutting down.
+    save_tasks: AtomicUsize,
+    /// Completes when the latest save of the player data of each UUID
+    /// finishes. Player data is not saved or loaded while a previous save of
+    /// the same player is still running.
+    pending_saves: Mutex<HashMap<Uuid, Shared<oneshot::Receiver<()>>>>,
     /// The RSA keypair used for encryption with clients.
     rsa_key: RsaPrivateKey,
     /// The public part of `rsa_key` encoded in DER, which is an ASN.1 format.
     /// This is sent to clients during the authentication process.
     public_key_der: Box<[u8]>,
-    /// For session server requests.
-    http_client: HttpClient,
+    /// For authentication and profile lookups.
+    mojang_api: Box<dyn MojangApi>,
+    /// Holds bans and kick records.
+    storage: Storage,
 }
 
 /// Contains information about a new client.
+#[derive(Clone, Debug)]
 pub struct NewClientData {
     /// The UUID of the new client.
     pub uuid: Uuid,
@@ -127,32 +198,199 @@ pub struct NewClientData {
     pub textures: Option<SignedPlayerTextures>,
     /// The remote address of the new client.
     pub remote_addr: SocketAddr,
+    /// The hostname the client used to connect to the server, also known as
+    /// the virtual host. The hostname is in lowercase and Forge markers or a
+    /// trailing dot are removed.
+    ///
+    /// This can be used to send clients to different worlds depending on the
+    /// subdomain they joined with.
+    pub server_address: String,
+    /// The port the client used to connect to the server.
+    pub server_port: u16,
+    /// The marker Forge clients append to the server address, such as `FML3`.
+    /// `None` for vanilla clients.
+    pub forge_marker: Option<String>,
+    /// The protocol version the client sent in its handshake. This is
+    /// normally [`PROTOCOL_VERSION`], but can differ when the client connects
+    /// through a proxy which translates between versions.
+    pub protocol_version: i32,
+}
+
+/// Information about a connection obtained from its handshake.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ConnectionInfo {
+    /// The remote address of the connection.
+    pub remote_addr: SocketAddr,
+    /// The address from [`Config::addresses`] the connection was accepted on.
+    pub local_addr: ListenAddress,
+    /// The protocol version of the connecting client. This may differ from
+    /// [`PROTOCOL_VERSION`].
+    pub protocol_version: i32,
+    /// The address the client used to connect to the server, exactly as sent
+    /// in the handshake. This is usually the hostname typed into the client,
+    /// also known as the virtual host.
+    pub server_address: String,
+    /// The port the client used to connect to the server.
+    pub server_port: u16,
 }
 
-struct NewClientMessage {
+struct NewClientMessage<C: Config> {
     ncd: NewClientData,
+    state: C::ClientState,
     reply: oneshot::Sender<S2cPacketChannels>,
 }
 
 /// The result type returned from [`start_server`].
 pub type ShutdownResult = Result<(), Box<dyn Error + Send + Sync + 'static>>;
 
-pub(crate) type S2cPacketChannels = (Sender<C2sPlayPacket>, Receiver<S2cPlayMessage>);
-pub(crate) type C2sPacketChannels = (Sender<S2cPlayMessage>, Receiver<C2sPlayPacket>);
+pub(crate) type S2cPacketChannels = (Sender<C2sPlayPacket>, S2cPlayReceiver, Arc<NetworkCounters>);
+pub(crate) type C2sPacketChannels = (
+    [Sender<S2cPlayMessage>; 3],
+    Receiver<C2sPlayPacket>,
+    Arc<NetworkCounters>,
+);
 
 /// Messages sent to packet encoders.
 #[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug)]
 pub(crate) enum S2cPlayMessage {
-    /// Queue a play packet for sending.
-    Queue(S2cPlayPacket),
-    /// Instructs the encoder to flush all queued packets to the TCP stream.
-    Flush,
+    /// A play packet to write.
+    Packet(S2cPlayPacket),
+    /// Sent on every lane before a packet without a [`PacketLane`]. A lane
+    /// which reached a barrier is held until all lanes have reached it.
+    Barrier,
+}
+
+/// The lanes the packets sent to a client are queued in.
+///
+/// Packets in the same lane are written in the order they were sent. Packets
+/// in a more urgent lane are written before the packets of less urgent lanes
+/// which are still waiting, so that keepalives are not delayed by chunk data
+/// on a congested connection.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PacketLane {
+    /// Keepalives, teleports and disconnects.
+    Urgent,
+    /// Entity updates and all other packets without a more specific lane.
+    Normal,
+    /// Chunk data and the packets which need to be written after the chunk
+    /// data they refer to, such as block changes.
+    Chunks,
+}
+
+impl PacketLane {
+    const ALL: [Self; 3] = [Self::Urgent, Self::Normal, Self::Chunks];
+
+    /// Returns the lane of a packet, or `None` if the packet must be written
+    /// after all packets sent before it and before all packets sent after it.
+    pub(crate) fn of(pkt: &S2cPlayPacket) -> Option<Self> {
+        match pkt {
+            S2cPlayPacket::GameJoin(_) | S2cPlayPacket::PlayerRespawn(_) => None,
+            S2cPlayPacket::KeepAlive(_)
+            | S2cPlayPacket::PlayerPositionLook(_)
+            | S2cPlayPacket::Disconnect(_) => Some(Self::Urgent),
+            S2cPlayPacket::ChunkData(_)
+            | S2cPlayPacket::UnloadChunk(_)
+            | S2cPlayPacket::BlockUpdate(_)
+            | S2cPlayPacket::ChunkSectionUpdate(_)
+            | S2cPlayPacket::BlockEntityUpdate(_)
+            | S2cPlayPacket::BlockEvent(_)
+            | S2cPlayPacket::BlockBreakingProgress(_)
+            | S2cPlayPacket::ChunkRenderDistanceCenter(_)
+            | S2cPlayPacket::ChunkLoadDistance(_) => Some(Self::Chunks),
+            _ => Some(Self::Normal),
+        }
+    }
+}
+
+/// Receives the packets sent to a client from all [`PacketLane`]s in the
+/// order they are written.
+pub(crate) struct S2cPlayReceiver {
+    lanes: [Receiver<S2cPlayMessage>; 3],
+    /// The message at the front of each lane, if it was received but not
+    /// handled yet.
+    heads: [Option<S2cPlayMessage>; 3],
 }
 
-impl<P: Into<S2cPlayPacket>> From<P> for S2cPlayMessage {
-    fn from(pkt: P) -> Self {
-        Self::Queue(pkt.into())
+impl S2cPlayReceiver {
+    fn new(lanes: [Receiver<S2cPlayMessage>; 3]) -> Self {
+        Self {
+            lanes,
+            heads: [None, None, None],
+        }
+    }
+
+    /// Takes the next packet to write and the lane it was sent on, or returns
+    /// `None` if no packet can be written until more messages are sent.
+    pub(crate) fn try_next(&mut self) -> Option<(S2cPlayPacket, PacketLane)> {
+        loop {
+            for (head, lane) in self.heads.iter_mut().zip(&self.lanes) {
+                if head.is_none() {
+                    *head = lane.try_recv().ok();
+                }
+            }
+
+            if self
+                .heads
+                .iter()
+                .all(|head| matches!(head, Some(S2cPlayMessage::Barrier)))
+            {
+                self.heads = [None, None, None];
+                continue;
+            }
+
+            for (head, lane) in self.heads.iter_mut().zip(PacketLane::ALL) {
+                if let Some(S2cPlayMessage::Packet(_)) = head {
+                    if let Some(S2cPlayMessage::Packet(pkt)) = head.take() {
+                        return Some((pkt, lane));
+                    }
+                }
+            }
+
+            return None;
+        }
+    }
+
+    /// Waits for a message on a lane which is not held at a barrier. Returns
+    /// `false` if no more packets can be written because the sender is gone.
+    pub(crate) async fn wait(&mut self) -> bool {
+        let (idxs, recvs): (Vec<_>, Vec<_>) = self
+            .heads
+            .iter()
+            .zip(&self.lanes)
+            .enumerate()
+            .filter(|(_, (head, _))| head.is_none())
+            .map(|(i, (_, lane))| (i, lane.recv_async()))
+            .unzip();
+
+        if recvs.is_empty() {
+            return true;
+        }
+
+        match future::select_all(recvs).await {
+            (Ok(msg), i, _) => {
+                self.heads[idxs[i]] = Some(msg);
+                true
+            }
+            // The lanes are disconnected at the same time, but the other lanes
+            // may still hold messages.
+            (Err(_), _, _) => self
+                .heads
+                .iter()
+                .zip(&self.lanes)
+                .any(|(head, lane)| head.is_none() && !lane.is_empty()),
+        }
+    }
+
+    /// Returns `true` if the sender is gone and all packets have been taken.
+    pub(crate) fn is_disconnected(&self) -> bool {
+        self.lanes
+            .iter()
+            .all(|lane| lane.is_disconnected() && lane.is_empty())
+            && self
+                .heads
+                .iter()
+                .all(|head| !matches!(head, Some(S2cPlayMessage::Packet(_))))
     }
 }
 
@@ -162,9 +400,15 @@ impl<C: Config> SharedServer<C> {
         &self.0.cfg
     }
 
-    /// Gets the socket address this server is bound to.
-    pub fn address(&self) -> SocketAddr {
-        self.0.address
+    /// Gets the first address this server is listening on.
+    pub fn address(&self) -> &ListenAddress {
+        &self.0.addresses[0]
+    }
+
+    /// Gets all addresses this server is listening on. See
+    /// [`Config::addresses`].
+    pub fn addresses(&self) -> &[ListenAddress] {
+        &self.0.addresses
     }
 
     /// Gets the configured tick rate of this server.
@@ -182,6 +426,43 @@ impl<C: Config> SharedServer<C> {
         self.0.max_connections
     }
 
+    /// Gets the limits on packets sent by clients.
+    pub fn packet_limits(&self) -> &PacketLimits {
+        &self.0.packet_limits
+    }
+
+    /// Gets the filter of the packets which are
+    /// [logged](crate::packet_log), if packet logging is enabled.
+    pub fn packet_log(&self) -> Option<&PacketLogFilter> {
+        self.0.packet_log.as_ref()
+    }
+
+    /// Gets the number of block changes in a chunk section per tick above
+    /// which the whole chunk is resent to clients.
+    pub fn chunk_resend_threshold(&self) -> usize {
+        self.0.chunk_resend_threshold
+    }
+
+    /// Gets the number of ticks between autosaves, if autosaving is enabled.
+    pub fn autosave_interval(&self) -> Option<Ticks> {
+        self.0.autosave_interval
+    }
+
+    /// Gets the settings for lowering view distances under load, if enabled.
+    pub fn view_distance_scaling(&self) -> Option<&ViewDistanceScaling> {
+        self.0.view_distance_scaling.as_ref()
+    }
+
+    /// Gets the largest view distance clients currently use, or `None` if
+    /// their view distance is not limited. The limit is set by
+    /// [`Config::view_distance_scaling`] while the server is overloaded.
+    pub fn view_distance_limit(&self) -> Option<u8> {
+        match self.0.view_distance_limit.load(Ordering::Relaxed) {
+            MAX_VIEW_DISTANCE => None,
+            limit => Some(limit),
+        }
+    }
+
     /// Gets the configured incoming packet capacity.
     pub fn incoming_packet_capacity(&self) -> usize {
         self.0.incoming_packet_capacity
@@ -246,6 +527,18 @@ impl<C: Config> SharedServer<C> {
         self.0.start_instant
     }
 
+    /// Gets what the server does when ticks fall behind schedule.
+    pub fn tick_catch_up(&self) -> TickCatchUp {
+        self.0.tick_catch_up
+    }
+
+    /// Returns the number of ticks the server was behind schedule after the
+    /// last tick. This is zero while ticks take less time than the
+    /// [tick rate](Self::tick_rate) allows.
+    pub fn tick_debt(&self) -> Ticks {
+        self.0.tick_debt.load(Ordering::Relaxed)
+    }
+
     /// Returns the number of ticks that have elapsed since the server began.
     pub fn current_tick(&self) -> Ticks {
         self.0.tick_counter.load(Ordering::SeqCst)
@@ -254,8 +547,9 @@ impl<C: Config> SharedServer<C> {
     /// Immediately stops new connections to the server and initiates server
     /// shutdown. The given result is returned through [`start_server`].
     ///
-    /// You may want to disconnect all players with a message prior to calling
-    /// this function.
+    /// At the start of the next tick, [`Config::shutdown`] is called and all
+    /// clients are disconnected. The server waits for the remaining packets to
+    /// be sent to clients before [`start_server`] returns.
     pub fn shutdown<R, E>(&self, res: R)
     where
         R: Into<Result<(), E>>,
@@ -263,6 +557,124 @@ impl<C: Config> SharedServer<C> {
     {
         self.0.connection_sema.close();
         *self.0.shutdown_result.lock().unwrap() = Some(res.into().map_err(|e| e.into()));
+        self.0.shutting_down.send_replace(true);
+    }
+
+    /// Completes once the player data of `uuid` passed to
+    /// [`Config::save_player_data`] so far has been saved.
+    pub(crate) async fn wait_for_player_save(&self, uuid: Uuid) {
+        let pending = self.0.pending_saves.lock().unwrap().get(&uuid).cloned();
+        if let Some(pending) = pending {
+            let _ = pending.await;
+        }
+    }
+
+    /// Completes once [`Self::shutdown`] has been called.
+    pub(crate) async fn shutdown_signal(&self) {
+        // The sender lives as long as `self`, so this cannot fail.
+        let _ = self.0.shutting_down.subscribe().wait_for(|&s| s).await;
+    }
+
+    /// Returns the metrics of this server.
+    pub fn metrics(&self) -> &Metrics {
+        &self.0.metrics
+    }
+
+    /// Accepts connections from the given transport, in addition to the
+    /// connections on [`Config::addresses`], until the server shuts down.
+    pub fn add_transport(&self, transport: impl Transport) {
+        self.0
+            .tokio_handle
+            .spawn(accept_connections(self.clone(), transport));
+    }
+
+    /// Returns the client used for the Mojang API and session server, set with
+    /// [`Config::mojang_api`].
+    pub fn mojang_api(&self) -> &dyn MojangApi {
+        &*self.0.mojang_api
+    }
+
+    /// Returns the storage holding bans and kick records, set with
+    /// [`Config::storage`].
+    pub fn storage(&self) -> &Storage {
+        &self.0.storage
+    }
+
+    /// Bans a player or IP address until `expires_at`, or permanently if
+    /// `expires_at` is `None`. Any existing ban of the target is replaced.
+    ///
+    /// Banned players are disconnected during login. Players who are already
+    /// online are not disconnected by this; the built-in `ban` and `ban-ip`
+    /// [commands](crate::command) disconnect them as well.
+    pub fn ban(
+        &self,
+        target: impl Into<BanTarget>,
+        reason: impl Into<String>,
+        expires_at: Option<SystemTime>,
+    ) -> anyhow::Result<()> {
+        self.0.storage.ban(
+            target,
+            &Ban {
+                name: None,
+                reason: reason.into(),
+                source: "Server".into(),
+                created_at: SystemTime::now(),
+                expires_at,
+            },
+        )
+    }
+
+    /// Lifts the ban of a player or IP address. Returns `true` if the target
+    /// was banned.
+    pub fn unban(&self, target: impl Into<BanTarget>) -> anyhow::Result<bool> {
+        self.0.storage.unban(target)
+    }
+
+    /// Sets the response to server list pings. While a status is set,
+    /// [`Config::server_list_ping`] is not called. Passing `None` goes back to
+    /// calling [`Config::server_list_ping`].
+    ///
+    /// This can be called at any time to change the description, favicon, or
+    /// player sample without recomputing them for every ping.
+    pub fn set_status(&self, status: impl Into<Option<ServerStatus>>) {
+        *self.0.status.write().unwrap() = status.into();
+    }
+
+    /// Gets the server list ping response set with [`Self::set_status`].
+    pub fn status(&self) -> Option<ServerStatus> {
+        self.0.status.read().unwrap().clone()
+    }
+
+    /// Restricts who can join the server to the players on `whitelist`.
+    /// Passing `None` lets everyone who is not banned join again, which is the
+    /// default.
+    ///
+    /// Players who are not on the whitelist are disconnected during login.
+    /// Players who are already online are not disconnected by this.
+    pub fn set_whitelist(&self, whitelist: impl Into<Option<Whitelist>>) {
+        *self.0.whitelist.write().unwrap() = whitelist.into();
+    }
+
+    /// Gets the whitelist set with [`Self::set_whitelist`].
+    pub fn whitelist(&self) -> Option<Whitelist> {
+        self.0.whitelist.read().unwrap().clone()
+    }
+
+    /// Returns `true` if the player with the given UUID may join the server
+    /// according to the [whitelist](Self::set_whitelist). This is `true` for
+    /// everyone while there is no whitelist.
+    pub fn is_whitelisted(&self, uuid: Uuid) -> bool {
+        match &*self.0.whitelist.read().unwrap() {
+            Some(whitelist) => whitelist.contains(uuid),
+            None => true,
+        }
+    }
+
+    /// Like [`Self::shutdown`] with a successful result, but clients are
+    /// disconnected with the given message instead of the default one.
+    pub fn shutdown_with_reason(&self, reason: impl Into<Text>) {
+        *self.0.shutdown_reason.lock().unwrap() = Some(reason.into());
+        self.shutdown::<_, Box<dyn Error + Send + Sync>>(Ok(()));
     }
 }
 
@@ -271,35 +683,124 @@ impl<C: Config> SharedServer<C> {
 /// The function returns once the server has shut down, a runtime error
 /// occurs, or the configuration is found to be invalid.
 pub fn start_server<C: Config>(config: C, data: C::ServerState) -> ShutdownResult {
-    let shared = setup_server(config).map_err(Box::<dyn Error + Send + Sync + 'static>::from)?;
+    let mut server =
+        create_server(config, data).map_err(Box::<dyn Error + Send + Sync + 'static>::from)?;
+    let shared = server.shared.clone();
 
     let _guard = shared.tokio_handle().enter();
 
-    let mut server = Server {
-        state: data,
-        shared: shared.clone(),
-        clients: Clients::new(),
-        entities: Entities::new(),
-        worlds: Worlds::new(shared.clone()),
-        player_lists: PlayerLists::new(),
-    };
-
     shared.config().init(&mut server);
 
+    if let Some(address) = shared.config().metrics_address() {
+        tokio::spawn(serve_metrics(shared.clone(), address));
+    }
+
+    if let Some(motd) = shared.config().lan_motd() {
+        match shared.addresses().iter().find_map(ListenAddress::port) {
+            Some(port) => {
+                tokio::spawn(announce_lan(shared.clone(), port, motd));
+            }
+            None => tracing::warn!("not announcing on LAN without a TCP address"),
+        }
+    }
+
     tokio::spawn(do_accept_loop(shared));
 
     do_update_loop(&mut server)
 }
 
+/// Creates the server without initializing it or accepting connections.
+pub(crate) fn create_server<C: Config>(
+    config: C,
+    data: C::ServerState,
+) -> anyhow::Result<Server<C>> {
+    let shared = setup_server(config)?;
+
+    Ok(Server {
+        state: data,
+        shared: shared.clone(),
+        clients: Clients::new(),
+        entities: Entities::new(),
+        worlds: Worlds::new(shared),
+        player_lists: PlayerLists::new(),
+        commands: Commands::new(),
+        functions: Functions::new(),
+        permissions: Permissions::new(),
+        systems: Systems::new(),
+        event_bus: EventBus::new(),
+    })
+}
+
 fn setup_server<C: Config>(cfg: C) -> anyhow::Result<SharedServer<C>> {
     let max_connections = cfg.max_connections();
-    let address = cfg.address();
+    let addresses = cfg.addresses();
+
+    ensure!(!addresses.is_empty(), "at least one address must be given");
+
     let tick_rate = cfg.tick_rate();
 
     ensure!(tick_rate > 0, "tick rate must be greater than zero");
 
+    let tick_catch_up = cfg.tick_catch_up();
+
+    if let TickCatchUp::CatchUp { max_ticks } = tick_catch_up {
+        ensure!(
+            max_ticks >= 0,
+            "the maximum number of catch-up ticks must not be negative"
+        );
+    }
+
+    // The protocol crate does not know about blocks, so their names are
+    // interned here.
+    for kind in BlockKind::ALL {
+        ident!("minecraft:{}", kind.to_str()).intern();
+    }
+
     let online_mode = cfg.online_mode();
 
+    let packet_limits = cfg.packet_limits();
+
+    for (name, limit) in [
+        ("chat", &packet_limits.chat),
+        ("interaction", &packet_limits.interaction),
+        ("movement", &packet_limits.movement),
+    ] {
+        if let Some(limit) = limit {
+            ensure!(
+                limit.window > 0,
+                "{name} rate limit window must be greater than zero"
+            );
+        }
+    }
+
+    let packet_log = cfg.packet_log();
+
+    let chunk_resend_threshold = cfg.chunk_resend_threshold();
+
+    let autosave_interval = cfg.autosave_interval();
+
+    ensure!(
+        autosave_interval.is_none_or(|interval| interval > 0),
+        "autosave interval must be greater than zero"
+    );
+
+    let view_distance_scaling = cfg.view_distance_scaling();
+
+    if let Some(scaling) = &view_distance_scaling {
+        ensure!(
+            scaling.fast_tick < scaling.slow_tick,
+            "the fast tick duration of view distance scaling must be less than the slow one"
+        );
+        ensure!(
+            (2..=MAX_VIEW_DISTANCE).contains(&scaling.min_view_distance),
+            "the minimum view distance of view distance scaling must be in 2..=32"
+        );
+        ensure!(
+            scaling.interval > 0,
+            "the interval of view distance scaling must be greater than zero"
+        );
+    }
+
     let incoming_packet_capacity = cfg.incoming_packet_capacity();
 
     ensure!(
@@ -315,6 +816,8 @@ fn setup_server<C: Config>(cfg: C) -> anyhow::Result<SharedServer<C>> {
     );
 
     let tokio_handle = cfg.tokio_handle();
+    let mojang_api = cfg.mojang_api();
+    let storage = cfg.storage();
     let dimensions = cfg.dimensions();
 
     ensure!(
@@ -328,29 +831,8 @@ fn setup_server<C: Config>(cfg: C) -> anyhow::Result<SharedServer<C>> {
     );
 
     for (i, dim) in dimensions.iter().enumerate() {
-        ensure!(
-            dim.min_y % 16 == 0 && (-2032..=2016).contains(&dim.min_y),
-            "invalid min_y in dimension #{i}",
-        );
-
-        ensure!(
-            dim.height % 16 == 0
-                && (0..=4064).contains(&dim.height)
-                && dim.min_y.saturating_add(dim.height) <= 2032,
-            "invalid height in dimension #{i}",
-        );
-
-        ensure!(
-            (0.0..=1.0).contains(&dim.ambient_light),
-            "ambient_light is out of range in dimension #{i}",
-        );
-
-        if let Some(fixed_time) = dim.fixed_time {
-            assert!(
-                (0..=24_000).contains(&fixed_time),
-                "fixed_time is out of range in dimension #{i}",
-            );
-        }
+        dim.validate()
+            .with_context(|| format!("invalid dimension #{i}"))?;
     }
 
     let biomes = cfg.biomes();
@@ -380,6 +862,14 @@ fn setup_server<C: Config>(cfg: C) -> anyhow::Result<SharedServer<C>> {
 
     let (new_clients_tx, new_clients_rx) = flume::bounded(1);
 
+    let (console_tx, console_rx) = flume::unbounded();
+
+    if cfg.console_input() {
+        thread::Builder::new()
+            .name("console".into())
+            .spawn(move || read_console(console_tx))?;
+    }
+
     let runtime = if tokio_handle.is_none() {
         Some(Runtime::new()?)
     } else {
@@ -393,10 +883,19 @@ fn setup_server<C: Config>(cfg: C) -> anyhow::Result<SharedServer<C>> {
 
     let server = SharedServerInner {
         cfg,
-        address,
+        addresses,
         tick_rate,
+        tick_catch_up,
+        tick_debt: AtomicI64::new(0),
         online_mode,
         max_connections,
+        packet_limits,
+        packet_log,
+        chunk_resend_threshold,
+        autosave_interval,
+        view_distance_scaling,
+        view_distance_limit: AtomicU8::new(MAX_VIEW_DISTANCE),
+        tick_load: Mutex::new((Duration::ZERO, 0)),
         incoming_packet_capacity,
         outgoing_packet_capacity,
         tokio_handle,
@@ -406,216 +905,728 @@ fn setup_server<C: Config>(cfg: C) -> anyhow::Result<SharedServer<C>> {
         start_instant: Instant::now(),
         new_clients_rx,
         new_clients_tx,
+        console_rx,
         tick_counter: AtomicI64::new(0),
         connection_sema: Arc::new(Semaphore::new(max_connections)),
         shutdown_result: Mutex::new(None),
+        shutting_down: watch::channel(false).0,
+        status: RwLock::new(None),
+        whitelist: RwLock::new(None),
+        shutdown_reason: Mutex::new(None),
+        metrics: Metrics::new(),
+        encoder_tasks: AtomicUsize::new(0),
+        save_tasks: AtomicUsize::new(0),
+        pending_saves: Mutex::new(HashMap::new()),
         rsa_key,
         public_key_der,
-        http_client: HttpClient::new(),
+        mojang_api,
+        storage,
     };
 
     Ok(SharedServer(Arc::new(server)))
 }
 
-fn do_update_loop<C: Config>(server: &mut Server<C>) -> ShutdownResult {
-    let mut tick_start = Instant::now();
+/// The longest time the server waits for outgoing packets to be sent, and for
+/// saves to finish, when shutting down.
+const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
 
+pub(crate) fn shutdown_server<C: Config>(server: &mut Server<C>) {
     let shared = server.shared.clone();
-    loop {
-        if let Some(res) = shared.0.shutdown_result.lock().unwrap().take() {
-            return res;
-        }
 
-        while let Ok(msg) = shared.0.new_clients_rx.try_recv() {
-            join_player(server, msg);
-        }
+    system::shutdown(server);
+    shared.config().shutdown(server);
 
-        // Get serverbound packets first so they are not dealt with a tick late.
-        server.clients.par_iter_mut().for_each(|(_, client)| {
-            client.handle_serverbound_packets(&server.entities);
-        });
+    let reason = shared
+        .0
+        .shutdown_reason
+        .lock()
+        .unwrap()
+        .take()
+        .unwrap_or_else(|| Text::translate("multiplayer.disconnect.server_shutdown"));
+
+    // Disconnecting closes the packet channels of the clients. The encoder tasks
+    // then write what is left and exit.
+    for (_, client) in server.clients.iter_mut() {
+        client.disconnect(reason.clone());
+    }
 
-        shared.config().update(server);
+    save_dirty_chunks(server);
+    server.clients.retain(|_, _| false);
+    save_removed_clients(server);
 
-        server.worlds.par_iter_mut().for_each(|(id, world)| {
-            // Chunks created this tick can have their changes applied immediately because
-            // they have not been observed by clients yet. Clients will not have to be sent
-            // the block change packet in this case, since the changes are applied before we
-            // update clients.
-            world.chunks.update_created_this_tick();
+    if !wait_for_tasks(&shared.0.encoder_tasks) {
+        tracing::warn!("timed out while sending remaining packets to clients");
+    }
 
-            world.spatial_index.update(&server.entities, id);
-        });
+    if !wait_for_tasks(&shared.0.save_tasks) {
+        tracing::warn!("timed out while waiting for saves to finish");
+    }
+}
 
-        server.clients.par_iter_mut().for_each(|(_, client)| {
-            client.update(
-                &shared,
-                &server.entities,
-                &server.worlds,
-                &server.player_lists,
-            );
-        });
+/// Waits up to [`SHUTDOWN_FLUSH_TIMEOUT`] for the number of `tasks` to reach
+/// zero. Returns `false` if it timed out.
+fn wait_for_tasks(tasks: &AtomicUsize) -> bool {
+    let start = Instant::now();
+    while tasks.load(Ordering::SeqCst) > 0 {
+        if start.elapsed() >= SHUTDOWN_FLUSH_TIMEOUT {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
 
-        server.entities.update();
+/// Counts a running save in `save_tasks` until it is dropped, which also
+/// happens if the save panics.
+struct SaveTask<C: Config>(SharedServer<C>);
 
-        server.worlds.par_iter_mut().for_each(|(_, world)| {
-            world.chunks.update();
-        });
+impl<C: Config> SaveTask<C> {
+    fn start(shared: &SharedServer<C>) -> Self {
+        shared.0.save_tasks.fetch_add(1, Ordering::SeqCst);
+        Self(shared.clone())
+    }
+}
 
-        server.player_lists.update();
+impl<C: Config> Drop for SaveTask<C> {
+    fn drop(&mut self) {
+        self.0 .0.save_tasks.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
-        // Sleep for the remainder of the tick.
-        let tick_duration = Duration::from_secs_f64((shared.0.tick_rate as f64).recip());
-        thread::sleep(tick_duration.saturating_sub(tick_start.elapsed()));
+impl<C: Config> Server<C> {
+    /// Starts saving the [dirty](crate::chunk::Chunk::is_dirty) chunks of all
+    /// worlds with [`Config::save_chunk`] and the state of all clients with
+    /// [`Config::save_player_data`], as copied by
+    /// [`Config::snapshot_player_data`].
+    ///
+    /// Only the copies are made here. The saves run on the tokio runtime, so
+    /// the update loop is not blocked while they are written.
+    pub fn save_all(&mut self) {
+        save_dirty_chunks(self);
+
+        for (_, client) in self.clients.iter() {
+            if let Some(state) = self.shared.config().snapshot_player_data(client) {
+                spawn_save_player_data(&self.shared, client.uuid(), state);
+            }
+        }
+    }
+}
 
-        tick_start = Instant::now();
-        shared.0.tick_counter.fetch_add(1, Ordering::SeqCst);
+/// Passes the states of the clients removed since the last call to
+/// [`Config::save_player_data`].
+fn save_removed_clients<C: Config>(server: &mut Server<C>) {
+    for (uuid, state) in server.clients.take_removed() {
+        spawn_save_player_data(&server.shared, uuid, state);
     }
 }
 
-fn join_player<C: Config>(server: &mut Server<C>, msg: NewClientMessage) {
-    let (clientbound_tx, clientbound_rx) = flume::bounded(server.shared.0.outgoing_packet_capacity);
-    let (serverbound_tx, serverbound_rx) = flume::bounded(server.shared.0.incoming_packet_capacity);
+fn spawn_save_player_data<C: Config>(shared: &SharedServer<C>, uuid: Uuid, state: C::ClientState) {
+    let task = SaveTask::start(shared);
+    let (done_tx, done_rx) = oneshot::channel();
+    let done_rx = done_rx.shared();
+    let previous = shared
+        .0
+        .pending_saves
+        .lock()
+        .unwrap()
+        .insert(uuid, done_rx.clone());
 
-    let s2c_packet_channels: S2cPacketChannels = (serverbound_tx, clientbound_rx);
-    let c2s_packet_channels: C2sPacketChannels = (clientbound_tx, serverbound_rx);
+    shared.tokio_handle().spawn(async move {
+        let shared = &task.0;
 
-    let _ = msg.reply.send(s2c_packet_channels);
+        // Saves of the same player are written in the order they were made.
+        if let Some(previous) = previous {
+            let _ = previous.await;
+        }
 
-    let client = Client::new(c2s_packet_channels, msg.ncd, C::ClientState::default());
+        shared.config().save_player_data(shared, uuid, state).await;
 
-    server.clients.insert(client);
-}
+        let mut pending = shared.0.pending_saves.lock().unwrap();
+        if pending.get(&uuid).is_some_and(|p| p.ptr_eq(&done_rx)) {
+            pending.remove(&uuid);
+        }
+        drop(pending);
 
-struct Codec {
-    enc: Encoder<OwnedWriteHalf>,
-    dec: Decoder<OwnedReadHalf>,
+        // Dropping the sender wakes the loads and saves waiting for this
+        // save. If the save panics, it is dropped while unwinding instead.
+        drop(done_tx);
+    });
 }
 
-async fn do_accept_loop<C: Config>(server: SharedServer<C>) {
-    log::trace!("entering accept loop");
-
-    let listener = match TcpListener::bind(server.0.address).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            server.shutdown(Err(e).context("failed to start TCP listener"));
-            return;
+/// Passes a copy of every dirty chunk to [`Config::save_chunk`].
+fn save_dirty_chunks<C: Config>(server: &mut Server<C>) {
+    for (world_id, world) in server.worlds.iter_mut() {
+        for (pos, chunk) in world.chunks.take_dirty() {
+            let task = SaveTask::start(&server.shared);
+
+            server.shared.tokio_handle().spawn(async move {
+                let shared = &task.0;
+                shared
+                    .config()
+                    .save_chunk(shared, world_id, pos, chunk)
+                    .await;
+            });
         }
-    };
-
-    loop {
-        match server.0.connection_sema.clone().acquire_owned().await {
-            Ok(permit) => match listener.accept().await {
-                Ok((stream, remote_addr)) => {
-                    let server = server.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = stream.set_nodelay(true) {
-                            log::error!("failed to set TCP_NODELAY: {e}");
-                        }
+    }
+}
 
-                        if let Err(e) = handle_connection(server, stream, remote_addr).await {
-                            if let Some(e) = e.downcast_ref::<io::Error>() {
-                                if e.kind() == io::ErrorKind::UnexpectedEof {
-                                    return;
-                                }
-                            }
-                            log::error!("connection to {remote_addr} ended: {e:#}");
-                        }
-                        drop(permit);
-                    });
-                }
-                Err(e) => {
-                    log::error!("failed to accept incoming connection: {e}");
+/// Sends lines from the standard input to the update loop until the input is
+/// closed or the server stops.
+fn read_console(console_tx: Sender<String>) {
+    for line in io::stdin().lines() {
+        match line {
+            Ok(line) => {
+                if !line.trim().is_empty() && console_tx.send(line).is_err() {
+                    break;
                 }
-            },
-            // Closed semaphore indicates server shutdown.
-            Err(_) => return,
+            }
+            Err(e) => {
+                tracing::error!("failed to read console input: {e}");
+                break;
+            }
         }
     }
 }
 
-async fn handle_connection<C: Config>(
-    server: SharedServer<C>,
-    stream: TcpStream,
-    remote_addr: SocketAddr,
-) -> anyhow::Result<()> {
-    let timeout = Duration::from_secs(10);
+fn do_update_loop<C: Config>(server: &mut Server<C>) -> ShutdownResult {
+    let shared = server.shared.clone();
+    let tick_duration = Duration::from_secs_f64((shared.0.tick_rate as f64).recip());
+    let mut tick_start = Instant::now();
 
-    let (read, write) = stream.into_split();
-    let mut c = Codec {
-        enc: Encoder::new(write, timeout),
-        dec: Decoder::new(read, timeout),
-    };
+    loop {
+        let shutdown_result = shared.0.shutdown_result.lock().unwrap().take();
+        if let Some(res) = shutdown_result {
+            shutdown_server(server);
+            return res;
+        }
 
-    // TODO: peek stream for 0xFE legacy ping
+        while let Ok(msg) = shared.0.new_clients_rx.try_recv() {
+            let (_, channels) = join_player(server, msg.ncd, msg.state);
+            let _ = msg.reply.send(channels);
+        }
 
-    let handshake = c.dec.read_packet::<Handshake>().await?;
+        do_tick(server);
 
-    match handshake.next_state {
-        HandshakeNextState::Status => handle_status(server, &mut c, remote_addr, handshake)
-            .await
-            .context("error during status"),
-        HandshakeNextState::Login => match handle_login(&server, &mut c, remote_addr, handshake)
-            .await
-            .context("error during login")?
-        {
-            Some(npd) => handle_play(&server, c, npd)
-                .await
-                .context("error during play"),
-            None => Ok(()),
-        },
+        // Sleep until the next tick is due.
+        let now = Instant::now();
+        let (next_tick_start, debt) = schedule_next_tick(
+            shared.0.tick_catch_up,
+            tick_start + tick_duration,
+            now,
+            tick_duration,
+        );
+
+        shared.0.tick_debt.store(debt, Ordering::Relaxed);
+        thread::sleep(next_tick_start.saturating_duration_since(now));
+        tick_start = next_tick_start;
     }
 }
 
-async fn handle_status<C: Config>(
-    server: SharedServer<C>,
-    c: &mut Codec,
-    remote_addr: SocketAddr,
-    handshake: Handshake,
-) -> anyhow::Result<()> {
-    c.dec.read_packet::<QueryRequest>().await?;
+/// Decides when the next tick starts according to `catch_up`, given the time
+/// it was due and the current time. Returns the start of the next tick and
+/// the number of ticks the server is behind schedule.
+fn schedule_next_tick(
+    catch_up: TickCatchUp,
+    due: Instant,
+    now: Instant,
+    tick_duration: Duration,
+) -> (Instant, Ticks) {
+    if now <= due {
+        return (due, 0);
+    }
 
-    match server
-        .0
-        .cfg
-        .server_list_ping(&server, remote_addr, handshake.protocol_version.0)
-        .await
-    {
-        ServerListPing::Respond {
-            online_players,
-            max_players,
-            description,
-            favicon_png,
-        } => {
-            let mut json = json!({
-                "version": {
-                    "name": VERSION_NAME,
-                    "protocol": PROTOCOL_VERSION
-                },
-                "players": {
-                    "online": online_players,
-                    "max": max_players,
-                    // TODO: player sample?
-                },
-                "description": description,
-            });
+    let late = now - due;
+    let debt = Ticks::try_from(late.as_nanos() / tick_duration.as_nanos()).unwrap_or(Ticks::MAX);
+
+    match catch_up {
+        TickCatchUp::Stretch => (now, debt),
+        TickCatchUp::Skip => {
+            // The time since the start of the tick that should be running now.
+            let into_tick = late.as_nanos() % tick_duration.as_nanos();
+            (
+                now + (tick_duration - Duration::from_nanos(into_tick as u64)),
+                debt,
+            )
+        }
+        TickCatchUp::CatchUp { max_ticks } => {
+            let max_ticks = max_ticks.max(0);
+            if debt > max_ticks {
+                // Forget the ticks past the maximum. The owed ticks take less
+                // time than `late`, so this neither overflows nor goes back
+                // past `due`.
+                let owed = max_ticks as u128 * tick_duration.as_nanos();
+                let owed =
+                    Duration::new((owed / 1_000_000_000) as u64, (owed % 1_000_000_000) as u32);
+                (now - owed, max_ticks)
+            } else {
+                (due, debt)
+            }
+        }
+    }
+}
+
+/// Calls [`Config::update_world`] for every world in parallel, along with the
+/// clients currently in that world.
+fn update_worlds<C: Config>(server: &mut Server<C>) {
+    let shared = &server.shared;
+
+    let mut clients_by_world: HashMap<_, Vec<_>> = HashMap::new();
+    for (id, client) in server.clients.iter_mut() {
+        clients_by_world
+            .entry(client.world())
+            .or_default()
+            .push((id, client));
+    }
+
+    let shards: Vec<_> = server
+        .worlds
+        .iter_mut()
+        .map(|(id, world)| {
+            let clients = clients_by_world.remove(&id).unwrap_or_default();
+            (id, world, clients)
+        })
+        .collect();
+
+    shards.into_par_iter().for_each(|(id, world, mut clients)| {
+        let _span = tracing::trace_span!("update_world", world = ?id).entered();
+
+        shared
+            .config()
+            .update_world(shared, id, world, &mut clients, &server.entities);
+    });
+}
+
+/// Runs a single game update and advances the tick counter. This does not
+/// sleep or accept new clients.
+pub(crate) fn do_tick<C: Config>(server: &mut Server<C>) {
+    let tick_start = Instant::now();
+
+    let shared = server.shared.clone();
+    let tick_span = tracing::debug_span!("tick", tick = shared.current_tick()).entered();
+
+    // Get serverbound packets first so they are not dealt with a tick late.
+    server.clients.par_iter_mut().for_each(|(_, client)| {
+        client.handle_serverbound_packets(&shared, &server.entities, &server.worlds);
+    });
+
+    // Execute commands sent by clients.
+    let mut pending_commands = Vec::new();
+    for (id, client) in server.clients.iter_mut() {
+        for cmd in client.take_commands() {
+            pending_commands.push((id, cmd));
+        }
+
+        client.remove_disconnected_player_entity(&mut server.entities);
+    }
 
-            if let Some(data) = favicon_png {
-                let mut buf = "data:image/png;base64,".to_owned();
-                base64::encode_config_buf(data, base64::STANDARD, &mut buf);
-                json.as_object_mut()
-                    .unwrap()
-                    .insert("favicon".to_owned(), Value::String(buf));
+    for (id, cmd) in pending_commands {
+        command::execute(server, CommandSender::Client(id), &cmd);
+    }
+
+    while let Ok(line) = shared.0.console_rx.try_recv() {
+        command::execute(server, CommandSender::Console, &line);
+    }
+
+    event_bus::post_client_events(server);
+    event_bus::post_chunk_events(server);
+
+    function::update(server);
+
+    shared.config().update(server);
+
+    system::update(server);
+
+    update_worlds(server);
+
+    server.entities.despawn();
+
+    if let Some(interval) = shared.0.autosave_interval {
+        let tick = shared.current_tick();
+        if tick > 0 && tick % interval == 0 {
+            if shared.0.save_tasks.load(Ordering::SeqCst) == 0 {
+                server.save_all();
+            } else {
+                tracing::warn!("skipping autosave because the previous save is still running");
             }
+        }
+    }
+
+    save_removed_clients(server);
+
+    for (_, client) in server.clients.iter_mut() {
+        client.sync_player_entity(&mut server.entities, &mut server.player_lists);
+    }
+
+    server.entities.update_worlds(&mut server.worlds);
+
+    let view_distance_limit = shared.0.view_distance_limit.load(Ordering::Relaxed);
+    for (_, client) in server.clients.iter_mut() {
+        client.set_view_distance_limit(view_distance_limit);
+    }
+
+    let viewers: Vec<_> = server
+        .clients
+        .iter()
+        .filter(|(_, client)| !client.is_disconnected())
+        .map(|(_, client)| {
+            let pos = client.position();
+            (
+                client.world(),
+                ChunkPos::at(pos.x, pos.z),
+                client.effective_view_distance(),
+            )
+        })
+        .collect();
+
+    server.worlds.par_iter_mut().for_each(|(id, world)| {
+        let _span = tracing::trace_span!("update_world", world = ?id).entered();
 
-            c.enc
-                .write_packet(&QueryResponse {
-                    json_response: json.to_string(),
-                })
-                .await?;
+        world.chunks.update_random_ticks(&shared, id);
+        world.chunks.update_physics();
+
+        world.chunks.update_tickets(
+            viewers
+                .iter()
+                .filter(|&&(world, _, _)| world == id)
+                .map(|&(_, pos, dist)| (pos, dist)),
+        );
+
+        // Chunks created this tick can have their changes applied immediately because
+        // they have not been observed by clients yet. Clients will not have to be sent
+        // the block change packet in this case, since the changes are applied before we
+        // update clients. The same goes for chunks with too many changes, which are
+        // sent to clients in full.
+        world.chunks.update_created_this_tick();
+
+        world
+            .spatial_index
+            .update(&server.entities, &world.entities);
+    });
+
+    server.clients.par_iter_mut().for_each(|(_, client)| {
+        let _span = tracing::trace_span!("update_client", username = client.username()).entered();
+
+        client.update(
+            &shared,
+            &server.entities,
+            &server.worlds,
+            &server.player_lists,
+            &server.commands,
+            &server.permissions,
+        );
+    });
+
+    for (_, client) in server.clients.iter_mut() {
+        client.remove_disconnected_player_entity(&mut server.entities);
+    }
+
+    let viewed: HashSet<_> = server
+        .clients
+        .iter()
+        .flat_map(|(_, client)| client.loaded_entities().iter().copied())
+        .collect();
+
+    server.entities.update(&viewed);
+
+    server.worlds.par_iter_mut().for_each(|(id, world)| {
+        let _span = tracing::trace_span!("update_world", world = ?id).entered();
+
+        world.chunks.update();
+        world.meta.update();
+    });
+
+    server.player_lists.update();
+    server.commands.update();
+    server.permissions.update();
+
+    if let Some(scaling) = &shared.0.view_distance_scaling {
+        scale_view_distance(server, scaling, tick_start.elapsed());
+    }
+
+    shared.0.metrics.record_tick(
+        tick_start.elapsed(),
+        server.clients.len(),
+        server.worlds.iter().map(|(_, w)| w.chunks.len()).sum(),
+        server.worlds.iter().map(|(_, w)| w.memory_usage()).sum(),
+        server.entities.len(),
+    );
+
+    drop(tick_span);
+
+    shared.0.tick_counter.fetch_add(1, Ordering::SeqCst);
+}
+
+/// The largest view distance of clients.
+pub(crate) const MAX_VIEW_DISTANCE: u8 = 32;
+
+/// Adds the duration of the current tick to the load of the interval and
+/// adjusts the view distance limit at the end of the interval.
+pub(crate) fn scale_view_distance<C: Config>(
+    server: &Server<C>,
+    scaling: &ViewDistanceScaling,
+    tick_duration: Duration,
+) {
+    let shared = &server.shared.0;
+
+    let average = {
+        let mut load = shared.tick_load.lock().unwrap();
+        load.0 += tick_duration;
+        load.1 += 1;
+
+        if load.1 < scaling.interval {
+            return;
         }
-        ServerListPing::Ignore => return Ok(()),
+
+        let (total, ticks) = std::mem::take(&mut *load);
+        total / ticks as u32
+    };
+
+    let limit = shared.view_distance_limit.load(Ordering::Relaxed);
+
+    let new_limit = if average > scaling.slow_tick {
+        let largest = server
+            .clients
+            .iter()
+            .map(|(_, c)| c.view_distance())
+            .max()
+            .unwrap_or(MAX_VIEW_DISTANCE);
+
+        limit
+            .min(largest)
+            .saturating_sub(1)
+            .max(scaling.min_view_distance)
+    } else if average < scaling.fast_tick {
+        (limit + 1).min(MAX_VIEW_DISTANCE)
+    } else {
+        limit
+    };
+
+    if new_limit != limit {
+        if new_limit == MAX_VIEW_DISTANCE {
+            tracing::info!("average tick took {average:?}, no longer limiting view distance");
+        } else {
+            tracing::info!(
+                "average tick took {average:?}, limiting view distance to {new_limit} chunks"
+            );
+        }
+        shared
+            .view_distance_limit
+            .store(new_limit, Ordering::Relaxed);
     }
+}
+
+/// Adds a new client to the server and returns its ID along with the channels
+/// for the other end of the connection.
+pub(crate) fn join_player<C: Config>(
+    server: &mut Server<C>,
+    ncd: NewClientData,
+    state: C::ClientState,
+) -> (ClientId, S2cPacketChannels) {
+    let [(urgent_tx, urgent_rx), (normal_tx, normal_rx), (chunks_tx, chunks_rx)] =
+        [(); 3].map(|_| flume::bounded(server.shared.0.outgoing_packet_capacity));
+    let (serverbound_tx, serverbound_rx) = flume::bounded(server.shared.0.incoming_packet_capacity);
+    let network = Arc::new(NetworkCounters::default());
+
+    let s2c_packet_channels: S2cPacketChannels = (
+        serverbound_tx,
+        S2cPlayReceiver::new([urgent_rx, normal_rx, chunks_rx]),
+        network.clone(),
+    );
+    let c2s_packet_channels: C2sPacketChannels =
+        ([urgent_tx, normal_tx, chunks_tx], serverbound_rx, network);
+
+    let client = Client::new(c2s_packet_channels, ncd, state);
+
+    let (id, _) = server.clients.insert(client);
+
+    (id, s2c_packet_channels)
+}
+
+struct Codec {
+    enc: Encoder<ConnectionWriter>,
+    dec: Decoder<ConnectionReader>,
+}
+
+async fn do_accept_loop<C: Config>(server: SharedServer<C>) {
+    tracing::trace!("entering accept loop");
+
+    let mut transports: Vec<Box<dyn Transport>> = Vec::new();
+
+    for address in server.addresses() {
+        match bind_transport(address).await {
+            Ok(transport) => transports.push(transport),
+            Err(e) => {
+                server.shutdown(
+                    Err(e).with_context(|| format!("failed to start listener on {address}")),
+                );
+                return;
+            }
+        }
+    }
+
+    future::join_all(
+        transports
+            .into_iter()
+            .map(|transport| accept_connections(server.clone(), transport)),
+    )
+    .await;
+}
+
+async fn bind_transport(address: &ListenAddress) -> io::Result<Box<dyn Transport>> {
+    Ok(match address {
+        ListenAddress::Tcp(addr) => Box::new(TcpTransport::bind(*addr).await?),
+        #[cfg(unix)]
+        ListenAddress::Unix(path) => Box::new(UnixTransport::bind(path)?),
+    })
+}
+
+/// Accepts connections from a transport until the server shuts down.
+async fn accept_connections<C: Config>(server: SharedServer<C>, transport: impl Transport) {
+    let local_addr = transport.local_addr();
+
+    loop {
+        match server.0.connection_sema.clone().acquire_owned().await {
+            Ok(permit) => match transport.accept().await {
+                Ok(conn) => {
+                    let server = server.clone();
+                    let local_addr = local_addr.clone();
+                    let remote_addr = conn.remote_addr;
+                    let span = tracing::info_span!("connection", %remote_addr);
+                    tokio::spawn(
+                        async move {
+                            if let Err(e) = handle_connection(server, conn, local_addr).await {
+                                if let Some(e) = e.downcast_ref::<io::Error>() {
+                                    if e.kind() == io::ErrorKind::UnexpectedEof {
+                                        return;
+                                    }
+                                }
+                                tracing::error!("connection to {remote_addr} ended: {e:#}");
+                            }
+                            drop(permit);
+                        }
+                        .instrument(span),
+                    );
+                }
+                Err(e) => {
+                    tracing::error!("failed to accept incoming connection: {e}");
+                }
+            },
+            // Closed semaphore indicates server shutdown.
+            Err(_) => return,
+        }
+    }
+}
+
+async fn handle_connection<C: Config>(
+    server: SharedServer<C>,
+    conn: Connection,
+    local_addr: ListenAddress,
+) -> anyhow::Result<()> {
+    let timeout = Duration::from_secs(10);
+
+    let remote_addr = conn.remote_addr;
+    let mut c = Codec {
+        enc: Encoder::new(conn.write, timeout),
+        dec: Decoder::new(conn.read, timeout),
+    };
+
+    c.dec.set_limits(server.0.packet_limits.decode);
+
+    // TODO: peek stream for 0xFE legacy ping
+
+    let handshake = c.dec.read_packet::<Handshake>().await?;
+
+    match handshake.next_state {
+        HandshakeNextState::Status => {
+            handle_status(server, &mut c, remote_addr, &local_addr, handshake)
+                .await
+                .context("error during status")
+        }
+        HandshakeNextState::Login => {
+            let info = ConnectionInfo {
+                remote_addr,
+                local_addr,
+                protocol_version: handshake.protocol_version.0,
+                server_address: handshake.server_adddress.0.clone(),
+                server_port: handshake.server_port,
+            };
+
+            server.0.cfg.on_connect(&server, &info);
+
+            match handle_login(&server, &mut c, remote_addr, handshake).await {
+                Ok(Some(ncd)) => {
+                    server.0.cfg.on_login_success(&server, &info, &ncd);
+
+                    let res = handle_play(&server, c, ncd.clone())
+                        .await
+                        .context("error during play");
+
+                    server.0.cfg.on_disconnect(&server, &info, Some(&ncd));
+                    res
+                }
+                Ok(None) => {
+                    server.0.cfg.on_disconnect(&server, &info, None);
+                    Ok(())
+                }
+                Err(e) => {
+                    server.0.cfg.on_disconnect(&server, &info, None);
+                    Err(e.context("error during login"))
+                }
+            }
+        }
+    }
+}
+
+async fn handle_status<C: Config>(
+    server: SharedServer<C>,
+    c: &mut Codec,
+    remote_addr: SocketAddr,
+    local_addr: &ListenAddress,
+    handshake: Handshake,
+) -> anyhow::Result<()> {
+    c.dec.read_packet::<QueryRequest>().await?;
+
+    let json = match server.status() {
+        Some(status) => status_json(
+            status.online_players,
+            status.max_players,
+            &status.player_sample,
+            &status.description,
+            status.favicon.as_ref(),
+        ),
+        None => match server
+            .0
+            .cfg
+            .server_list_ping(
+                &server,
+                remote_addr,
+                local_addr,
+                handshake.protocol_version.0,
+            )
+            .await
+        {
+            ServerListPing::Respond {
+                online_players,
+                max_players,
+                player_sample,
+                description,
+                favicon,
+            } => status_json(
+                online_players,
+                max_players,
+                &player_sample,
+                &description,
+                favicon,
+            ),
+            ServerListPing::Ignore => return Ok(()),
+        },
+    };
+
+    c.enc
+        .write_packet(&QueryResponse {
+            json_response: json.to_string(),
+        })
+        .await?;
 
     let QueryPing { payload } = c.dec.read_packet().await?;
 
@@ -624,6 +1635,45 @@ async fn handle_status<C: Config>(
     Ok(())
 }
 
+fn status_json(
+    online_players: i32,
+    max_players: i32,
+    player_sample: &[PlayerSampleEntry],
+    description: &Text,
+    favicon: Option<&Favicon>,
+) -> Value {
+    let sample: Vec<_> = player_sample
+        .iter()
+        .map(|p| {
+            json!({
+                "name": p.name,
+                "id": p.id.to_string(),
+            })
+        })
+        .collect();
+
+    let mut json = json!({
+        "version": {
+            "name": VERSION_NAME,
+            "protocol": PROTOCOL_VERSION
+        },
+        "players": {
+            "online": online_players,
+            "max": max_players,
+            "sample": sample,
+        },
+        "description": description,
+    });
+
+    if let Some(favicon) = favicon {
+        json.as_object_mut()
+            .unwrap()
+            .insert("favicon".to_owned(), Value::String(favicon.to_data_uri()));
+    }
+
+    json
+}
+
 /// Handle the login process and return the new player's data if successful.
 async fn handle_login<C: Config>(
     server: &SharedServer<C>,
@@ -636,6 +1686,13 @@ async fn handle_login<C: Config>(
         return Ok(None);
     }
 
+    let Handshake {
+        protocol_version: VarInt(protocol_version),
+        server_adddress: BoundedString(server_address),
+        server_port,
+        ..
+    } = handshake;
+
     let LoginStart {
         username: BoundedString(username),
         sig_data: _,   // TODO
@@ -691,13 +1748,6 @@ async fn handle_login<C: Config>(
         c.enc.enable_encryption(&crypt_key);
         c.dec.enable_encryption(&crypt_key);
 
-        #[derive(Debug, Deserialize)]
-        struct AuthResponse {
-            id: String,
-            name: String,
-            properties: Vec<Property>,
-        }
-
         let hash = Sha1::new()
             .chain(&shared_secret)
             .chain(&server.0.public_key_der)
@@ -705,37 +1755,40 @@ async fn handle_login<C: Config>(
 
         let hex_hash = weird_hex_encoding(&hash);
 
-        let url = format!("https://sessionserver.mojang.com/session/minecraft/hasJoined?username={username}&serverId={hex_hash}&ip={}", remote_addr.ip());
-        let resp = server.0.http_client.get(url).send().await?;
-
-        let status = resp.status();
-        ensure!(
-            status.is_success(),
-            "session server GET request failed: {status}"
-        );
-
-        let data: AuthResponse = resp.json().await?;
-
-        ensure!(data.name == username, "usernames do not match");
-
-        let uuid = Uuid::parse_str(&data.id).context("failed to parse player's UUID")?;
-
-        let textures = match data.properties.into_iter().find(|p| p.name == "textures") {
-            Some(p) => SignedPlayerTextures::from_base64(
-                p.value,
-                p.signature.context("missing signature for textures")?,
-            )?,
-            None => bail!("failed to find textures in auth response"),
+        let profile = match server
+            .0
+            .cfg
+            .authenticate(server, &username, &hex_hash, remote_addr)
+            .await
+        {
+            Ok(profile) => profile,
+            Err(reason) => {
+                tracing::info!("Disconnect at authentication: \"{reason}\"");
+                c.enc.write_packet(&LoginDisconnect { reason }).await?;
+                return Ok(None);
+            }
         };
 
-        (uuid, Some(textures))
-    } else {
-        // Derive the player's UUID from a hash of their username.
-        let uuid = Uuid::from_slice(&Sha256::digest(&username)[..16]).unwrap();
+        ensure!(profile.name == username, "usernames do not match");
 
-        (uuid, None)
+        (profile.id, profile.textures()?)
+    } else {
+        (offline_uuid(&username), None)
     };
 
+    if let Some(reason) = active_ban_message(&server.0.storage, uuid, remote_addr)? {
+        tracing::info!("Disconnect at login: \"{reason}\"");
+        c.enc.write_packet(&LoginDisconnect { reason }).await?;
+        return Ok(None);
+    }
+
+    if !server.is_whitelisted(uuid) {
+        let reason = Text::from("You are not whitelisted on this server.");
+        tracing::info!("Disconnect at login: \"{reason}\"");
+        c.enc.write_packet(&LoginDisconnect { reason }).await?;
+        return Ok(None);
+    }
+
     let compression_threshold = 256;
     c.enc
         .write_packet(&LoginCompression {
@@ -746,15 +1799,21 @@ async fn handle_login<C: Config>(
     c.enc.enable_compression(compression_threshold);
     c.dec.enable_compression(compression_threshold);
 
+    let (server_address, forge_marker) = parse_server_address(&server_address);
+
     let ncd = NewClientData {
         uuid,
         username,
         textures,
         remote_addr,
+        server_address,
+        server_port,
+        forge_marker,
+        protocol_version,
     };
 
     if let Err(reason) = server.0.cfg.login(server, &ncd).await {
-        log::info!("Disconnect at login: \"{reason}\"");
+        tracing::info!("Disconnect at login: \"{reason}\"");
         c.enc.write_packet(&LoginDisconnect { reason }).await?;
         return Ok(None);
     }
@@ -770,11 +1829,49 @@ async fn handle_login<C: Config>(
     Ok(Some(ncd))
 }
 
+/// Returns the message a player is disconnected with if the player or the IP
+/// address the player connects from is banned.
+fn active_ban_message(
+    storage: &Storage,
+    uuid: Uuid,
+    remote_addr: SocketAddr,
+) -> anyhow::Result<Option<Text>> {
+    let target = BanTarget::Player(uuid);
+    if let Some(ban) = storage.active_ban(target)? {
+        return Ok(Some(ban_message(target, &ban)));
+    }
+
+    let ip = remote_addr.ip();
+    // Connections without a socket address all have the unspecified address.
+    if !ip.is_unspecified() {
+        let target = BanTarget::Ip(ip);
+        if let Some(ban) = storage.active_ban(target)? {
+            return Ok(Some(ban_message(target, &ban)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The message a player is disconnected with because of `ban`.
+pub(crate) fn ban_message(target: BanTarget, ban: &Ban) -> Text {
+    let what = match target {
+        BanTarget::Player(_) => "You are",
+        BanTarget::Ip(_) => "Your IP address is",
+    };
+
+    format!("{what} banned from this server.\nReason: {}", ban.reason).into()
+}
+
 async fn handle_play<C: Config>(
     server: &SharedServer<C>,
     c: Codec,
     ncd: NewClientData,
 ) -> anyhow::Result<()> {
+    server.wait_for_player_save(ncd.uuid).await;
+    let state = server.0.cfg.load_player_data(server, ncd.uuid).await;
+    let username = ncd.username.clone();
+
     let (reply_tx, reply_rx) = oneshot::channel();
 
     server
@@ -782,38 +1879,71 @@ async fn handle_play<C: Config>(
         .new_clients_tx
         .send_async(NewClientMessage {
             ncd,
+            state,
             reply: reply_tx,
         })
         .await?;
 
-    let (packet_tx, packet_rx) = match reply_rx.await {
+    let (packet_tx, mut packet_rx, network) = match reply_rx.await {
         Ok(res) => res,
         Err(_) => return Ok(()), // Server closed
     };
 
     let Codec { mut enc, mut dec } = c;
 
+    let shared = server.clone();
+    let sent = network.clone();
+    let sent_username = username.clone();
+    shared.0.encoder_tasks.fetch_add(1, Ordering::SeqCst);
+
     tokio::spawn(async move {
-        while let Ok(msg) = packet_rx.recv_async().await {
-            match msg {
-                S2cPlayMessage::Queue(pkt) => {
-                    if let Err(e) = enc.queue_packet(&pkt) {
-                        log::debug!("error while queueing play packet: {e:#}");
-                        break;
-                    }
+        'write: loop {
+            while let Some((pkt, lane)) = packet_rx.try_next() {
+                shared.0.metrics.inc_packets_sent();
+                let queued = enc.queued_len();
+                if let Err(e) = enc.queue_packet(&pkt) {
+                    tracing::debug!("error while queueing play packet: {e:#}");
+                    break 'write;
                 }
-                S2cPlayMessage::Flush => {
+                let len = enc.queued_len() - queued;
+                sent.record_sent(len);
+                if let Some(log) = shared.packet_log() {
+                    log.log_clientbound(&sent_username, pkt.packet_name(), len);
+                }
+
+                // Write chunk data one packet at a time so that packets in more
+                // urgent lanes sent in the meantime can go first.
+                if lane == PacketLane::Chunks {
                     if let Err(e) = enc.flush().await {
-                        log::debug!("error while flushing packet queue: {e:#}");
-                        break;
+                        tracing::debug!("error while flushing packet queue: {e:#}");
+                        break 'write;
                     }
                 }
             }
+
+            if let Err(e) = enc.flush().await {
+                tracing::debug!("error while flushing packet queue: {e:#}");
+                break;
+            }
+
+            if !packet_rx.wait().await {
+                break;
+            }
         }
+
+        shared.0.encoder_tasks.fetch_sub(1, Ordering::SeqCst);
     });
 
     loop {
-        let pkt = dec.read_packet().await?;
+        let pkt: C2sPlayPacket = dec.read_packet().await?;
+        server.0.metrics.inc_packets_received();
+
+        let len = dec.frame_len();
+        network.record_received(len);
+        if let Some(log) = server.packet_log() {
+            log.log_serverbound(&username, pkt.packet_name(), len);
+        }
+
         if packet_tx.send_async(pkt).await.is_err() {
             break;
         }
@@ -822,13 +1952,122 @@ async fn handle_play<C: Config>(
     Ok(())
 }
 
+/// Splits the server address from a handshake into the normalized hostname and
+/// the Forge marker, if any.
+fn parse_server_address(raw: &str) -> (String, Option<String>) {
+    let mut parts = raw.split('\0');
+
+    let hostname = parts
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+
+    let forge_marker = parts
+        .find(|part| part.starts_with("FML"))
+        .map(|part| part.to_owned());
+
+    (hostname, forge_marker)
+}
+
+/// Derives the UUID of a player in offline mode from a hash of their username.
+pub(crate) fn offline_uuid(username: &str) -> Uuid {
+    Uuid::from_slice(&Sha256::digest(username)[..16]).unwrap()
+}
+
 fn weird_hex_encoding(bytes: &[u8]) -> String {
     BigInt::from_signed_bytes_be(bytes).to_str_radix(16)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::future::Future;
+    use std::net::{Ipv4Addr, SocketAddrV4, TcpListener};
+    use std::sync::atomic::AtomicBool;
+
+    use async_trait::async_trait;
+
     use super::*;
+    use crate::testing::{messages, test_server, Game, TestClient, TestServer};
+    use crate::world::{World, WorldId};
+
+    #[test]
+    fn tick_catch_up_schedules() {
+        let tick = Duration::from_millis(50);
+        let due = Instant::now();
+
+        for catch_up in [
+            TickCatchUp::Stretch,
+            TickCatchUp::Skip,
+            TickCatchUp::CatchUp { max_ticks: 2 },
+        ] {
+            // Ticks on time keep the schedule.
+            let now = due - Duration::from_millis(10);
+            assert_eq!(schedule_next_tick(catch_up, due, now, tick), (due, 0));
+        }
+
+        // The last tick overran by three and a half ticks.
+        let now = due + tick * 3 + tick / 2;
+
+        assert_eq!(
+            schedule_next_tick(TickCatchUp::Stretch, due, now, tick),
+            (now, 3)
+        );
+        assert_eq!(
+            schedule_next_tick(TickCatchUp::Skip, due, now, tick),
+            (due + tick * 4, 3)
+        );
+        assert_eq!(
+            schedule_next_tick(TickCatchUp::CatchUp { max_ticks: 5 }, due, now, tick),
+            (due, 3)
+        );
+        assert_eq!(
+            schedule_next_tick(TickCatchUp::CatchUp { max_ticks: 2 }, due, now, tick),
+            (now - tick * 2, 2)
+        );
+
+        // Huge delays and limits neither overflow nor panic.
+        let now = due + Duration::from_secs(3600 * 24 * 365 * 100);
+        assert_eq!(
+            schedule_next_tick(TickCatchUp::Skip, due, now, tick),
+            (now + tick, 63_072_000_000)
+        );
+        assert_eq!(
+            schedule_next_tick(
+                TickCatchUp::CatchUp {
+                    max_ticks: Ticks::MAX
+                },
+                due,
+                now,
+                tick
+            ),
+            (due, 63_072_000_000)
+        );
+        assert_eq!(
+            schedule_next_tick(
+                TickCatchUp::CatchUp {
+                    max_ticks: 10_000_000_000
+                },
+                due,
+                now,
+                tick
+            ),
+            (now - tick * 1_000_000_000 * 10, 10_000_000_000)
+        );
+    }
+
+    #[test]
+    fn parse_server_address_correct() {
+        assert_eq!(
+            parse_server_address("Lobby.Example.com"),
+            ("lobby.example.com".to_owned(), None)
+        );
+        assert_eq!(
+            parse_server_address("example.com.\0FML3\0"),
+            ("example.com".to_owned(), Some("FML3".to_owned()))
+        );
+        assert_eq!(parse_server_address(""), (String::new(), None));
+    }
 
     #[test]
     fn weird_hex_encoding_correct() {
@@ -845,4 +2084,254 @@ mod tests {
             "88e16a1019277b15d58faf0541e11910eb756f6"
         );
     }
+
+    #[test]
+    fn view_distance_scaled_under_load() {
+        let mut server = test_server();
+
+        let client = server.connect("tester");
+        server.tick();
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        c.set_view_distance(8);
+        server.tick();
+        client.take_packets();
+
+        let scaling = ViewDistanceScaling {
+            interval: 2,
+            ..ViewDistanceScaling::default()
+        };
+        let scale = |server: &TestServer<Game>, millis| {
+            for _ in 0..scaling.interval {
+                scale_view_distance(server.server(), &scaling, Duration::from_millis(millis));
+            }
+        };
+
+        scale(&server, 100);
+        assert_eq!(server.server().shared.view_distance_limit(), Some(7));
+        scale(&server, 100);
+        assert_eq!(server.server().shared.view_distance_limit(), Some(6));
+        // Ticks between the thresholds keep the limit.
+        scale(&server, 40);
+        assert_eq!(server.server().shared.view_distance_limit(), Some(6));
+
+        server.tick();
+        let c = server.server().clients.get(client.id()).unwrap();
+        assert_eq!(c.view_distance(), 8);
+        assert_eq!(c.effective_view_distance(), 6);
+        assert!(client.take_packets().iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::ChunkLoadDistance(p) if p.view_distance.0 .0 == 6
+        )));
+
+        for _ in 0..26 {
+            scale(&server, 1);
+        }
+        assert_eq!(server.server().shared.view_distance_limit(), None);
+
+        server.tick();
+        let c = server.server().clients.get(client.id()).unwrap();
+        assert_eq!(c.effective_view_distance(), 8);
+    }
+
+    /// Refuses players banned in `storage` or missing from `whitelist`.
+    struct BanGame {
+        address: SocketAddr,
+        storage: Storage,
+        whitelist: Option<Whitelist>,
+    }
+
+    #[async_trait]
+    impl Config for BanGame {
+        type ServerState = Arc<AtomicBool>;
+        type ClientState = ();
+        type EntityState = ();
+        type WorldState = ();
+        type ChunkState = ();
+        type PlayerListState = ();
+
+        fn max_connections(&self) -> usize {
+            10
+        }
+
+        fn address(&self) -> ListenAddress {
+            self.address.into()
+        }
+
+        fn online_mode(&self) -> bool {
+            false
+        }
+
+        fn storage(&self) -> Storage {
+            self.storage.clone()
+        }
+
+        fn init(&self, server: &mut Server<Self>) {
+            server.shared.set_whitelist(self.whitelist.clone());
+        }
+
+        fn update(&self, server: &mut Server<Self>) {
+            if server.state.load(Ordering::SeqCst) {
+                server
+                    .shared
+                    .shutdown::<_, Box<dyn std::error::Error + Send + Sync>>(Ok(()));
+            }
+        }
+    }
+
+    /// Runs a [`BanGame`] while `f` connects to it.
+    fn with_ban_game<F: Future<Output = ()>>(
+        storage: Storage,
+        whitelist: Option<Whitelist>,
+        f: impl FnOnce(SocketAddr) -> F,
+    ) {
+        let address = {
+            let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+            listener.local_addr().unwrap()
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let server = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                crate::start_server(
+                    BanGame {
+                        address,
+                        storage,
+                        whitelist,
+                    },
+                    stop,
+                )
+            })
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(f(address));
+        drop(runtime);
+
+        stop.store(true, Ordering::SeqCst);
+        server.join().unwrap().unwrap();
+    }
+
+    /// Connects `username` until the server is up and returns why the player
+    /// was disconnected during login.
+    async fn login_error(address: SocketAddr, username: &str) -> String {
+        loop {
+            match TestClient::connect(address, username).await {
+                Ok(_) => panic!("{username} logged in"),
+                Err(e) if e.to_string().starts_with("disconnected") => break e.to_string(),
+                Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        }
+    }
+
+    #[test]
+    fn banned_player_refused_at_login() {
+        let storage = Storage::in_memory();
+        storage
+            .ban(
+                offline_uuid("griefer"),
+                &Ban {
+                    name: Some("griefer".into()),
+                    reason: "griefing".into(),
+                    source: "Server".into(),
+                    created_at: SystemTime::now(),
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+
+        with_ban_game(storage, None, |address| async move {
+            assert_eq!(
+                login_error(address, "griefer").await,
+                "disconnected during login: You are banned from this server.\nReason: griefing"
+            );
+
+            TestClient::connect(address, "tester").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn unlisted_player_refused_at_login() {
+        let mut whitelist = Whitelist::new();
+        whitelist.add(offline_uuid("tester"));
+
+        with_ban_game(
+            Storage::in_memory(),
+            Some(whitelist),
+            |address| async move {
+                assert_eq!(
+                    login_error(address, "stranger").await,
+                    "disconnected during login: You are not whitelisted on this server."
+                );
+
+                TestClient::connect(address, "tester").await.unwrap();
+            },
+        );
+    }
+
+    /// Puts "alice" in the first world and everyone else in the second, then
+    /// greets players from [`Config::update_world`].
+    struct ShardedGame;
+
+    impl Config for ShardedGame {
+        type ServerState = ();
+        type ClientState = ();
+        type EntityState = ();
+        type WorldState = &'static str;
+        type ChunkState = ();
+        type PlayerListState = ();
+
+        fn max_connections(&self) -> usize {
+            10
+        }
+
+        fn init(&self, server: &mut Server<Self>) {
+            server.worlds.insert(DimensionId::default(), "lobby");
+            server.worlds.insert(DimensionId::default(), "arena");
+        }
+
+        fn update(&self, server: &mut Server<Self>) {
+            let ids: Vec<_> = server.worlds.iter().map(|(id, _)| id).collect();
+
+            for (_, client) in server.clients.iter_mut() {
+                if client.created_this_tick() {
+                    let world = if client.username() == "alice" {
+                        ids[0]
+                    } else {
+                        ids[1]
+                    };
+                    client.respawn(world, [0.5, 100.0, 0.5], 0.0, 0.0);
+                }
+            }
+        }
+
+        fn update_world(
+            &self,
+            _shared: &SharedServer<Self>,
+            id: WorldId,
+            world: &mut World<Self>,
+            clients: &mut [(ClientId, &mut Client<Self>)],
+            _entities: &Entities<Self>,
+        ) {
+            let count = clients.len();
+            for (_, client) in clients.iter_mut() {
+                assert_eq!(client.world(), id);
+                client.send_message(format!("{} has {count} players", world.state));
+            }
+        }
+    }
+
+    #[test]
+    fn update_world_gets_clients_in_world() {
+        let mut server = TestServer::new(ShardedGame, ()).unwrap();
+
+        let alice = server.connect("alice");
+        let bob = server.connect("bob");
+        let carol = server.connect("carol");
+        server.tick();
+
+        assert_eq!(messages(&alice), ["lobby has 1 players"]);
+        assert_eq!(messages(&bob), ["arena has 2 players"]);
+        assert_eq!(messages(&carol), ["arena has 2 players"]);
+    }
 }
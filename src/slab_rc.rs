@@ -40,13 +40,13 @@ impl PartialEq for Key {
 
 impl PartialOrd for Key {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Arc::as_ptr(&self.0).partial_cmp(&Arc::as_ptr(&other.0))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Key {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        Arc::as_ptr(&self.0).cmp(&Arc::as_ptr(&other.0))
     }
 }
 
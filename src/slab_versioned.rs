@@ -87,7 +87,7 @@ impl<T> VersionedSlab<T> {
     pub fn insert_with(&mut self, f: impl FnOnce(Key) -> T) -> (Key, &mut T) {
         let version = self.version;
         self.version = NonZeroU32::new(version.get().wrapping_add(1)).unwrap_or_else(|| {
-            log::warn!("slab version overflow");
+            tracing::warn!("slab version overflow");
             ONE
         });
 
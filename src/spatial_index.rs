@@ -1,5 +1,6 @@
 //! Efficient spatial entity queries.
 
+use std::collections::BTreeSet;
 use std::iter::FusedIterator;
 
 use rayon::iter::{IndexedParallelIterator, ParallelIterator};
@@ -9,7 +10,6 @@ use crate::bvh::{Bvh, Node};
 use crate::config::Config;
 use crate::entity::{Entities, EntityId};
 use crate::util::ray_box_intersect;
-use crate::world::WorldId;
 
 /// A data structure for fast spatial queries on entity [hitboxes]. This is used
 /// to accelerate tasks such as collision detection and ray tracing.
@@ -224,13 +224,16 @@ impl SpatialIndex {
         self.bvh.par_iter().map(|(&id, bb)| (id, bb))
     }
 
-    pub(crate) fn update<C: Config>(&mut self, entities: &Entities<C>, id: WorldId) {
-        self.bvh.build(
-            entities
-                .iter()
-                .filter(|(_, e)| e.world() == id)
-                .map(|(id, e)| (id, e.hitbox())),
-        )
+    /// `world_entities` contains the entities in the world of this index.
+    pub(crate) fn update<C: Config>(
+        &mut self,
+        entities: &Entities<C>,
+        world_entities: &BTreeSet<EntityId>,
+    ) {
+        self.bvh.build(world_entities.iter().map(|&id| {
+            let e = entities.get(id).expect("world entity should exist");
+            (id, e.hitbox())
+        }))
     }
 }
 
@@ -0,0 +1,579 @@
+//! Key-value storage for player data, bans, kicks and statistics.
+//!
+//! [`Storage`] stores these in a [`StorageBackend`], which is a set of named
+//! trees mapping byte keys to byte values. [`MemoryStorage`] keeps everything
+//! in memory and is useful for tests. With the `sled-storage` feature,
+//! `SledStorage` keeps everything in a [sled] database on disk.
+//!
+//! The operations of the backends included here are fast enough to be called
+//! from [`Config::load_player_data`] and [`Config::save_player_data`] directly.
+//!
+//! [sled]: https://docs.rs/sled
+//! [`Config::load_player_data`]: crate::config::Config::load_player_data
+//! [`Config::save_player_data`]: crate::config::Config::save_player_data
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::IpAddr;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The name of the tree holding player data.
+const PLAYER_DATA_TREE: &str = "player_data";
+/// The name of the tree holding bans of players.
+const BANS_TREE: &str = "bans";
+/// The name of the tree holding bans of IP addresses.
+const IP_BANS_TREE: &str = "ip_bans";
+/// The name of the tree holding the kick history.
+const KICKS_TREE: &str = "kicks";
+/// The name of the tree holding statistics.
+const STATISTICS_TREE: &str = "statistics";
+
+/// The function passed to [`StorageBackend::update`], mapping the current
+/// value of a key to its new value.
+pub type UpdateFn<'a> = dyn FnMut(Option<&[u8]>) -> Option<Vec<u8>> + 'a;
+
+/// A key-value store with named trees, used by [`Storage`].
+///
+/// Implement this trait to keep data in a different database.
+pub trait StorageBackend: Send + Sync + 'static {
+    /// Gets the value of a key.
+    fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Sets the value of a key, replacing the previous value.
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<()>;
+
+    /// Removes a key. Returns `true` if the key was present.
+    fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<bool>;
+
+    /// Atomically replaces the value of a key with the result of `f`, which is
+    /// called with the current value. Returning `None` from `f` removes the
+    /// key. The new value is returned.
+    ///
+    /// `f` may be called more than once if the key is modified concurrently.
+    fn update(&self, tree: &str, key: &[u8], f: &mut UpdateFn) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Gets all keys starting with `prefix` and their values, ordered by key.
+    fn scan_prefix(&self, tree: &str, prefix: &[u8]) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Writes all changes to durable storage, if the backend has any.
+    fn flush(&self) -> anyhow::Result<()>;
+}
+
+/// What a [`Ban`] applies to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BanTarget {
+    /// The player with the given UUID.
+    Player(Uuid),
+    /// Every player connecting from the given IP address.
+    Ip(IpAddr),
+}
+
+impl BanTarget {
+    fn tree_and_key(self) -> (&'static str, Vec<u8>) {
+        match self {
+            BanTarget::Player(uuid) => (BANS_TREE, uuid.as_bytes().to_vec()),
+            BanTarget::Ip(ip) => (IP_BANS_TREE, ip.to_string().into_bytes()),
+        }
+    }
+}
+
+impl From<Uuid> for BanTarget {
+    fn from(uuid: Uuid) -> Self {
+        Self::Player(uuid)
+    }
+}
+
+impl From<IpAddr> for BanTarget {
+    fn from(ip: IpAddr) -> Self {
+        Self::Ip(ip)
+    }
+}
+
+impl fmt::Display for BanTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BanTarget::Player(uuid) => uuid.fmt(f),
+            BanTarget::Ip(ip) => ip.fmt(f),
+        }
+    }
+}
+
+/// A ban of a player or IP address from the server.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Ban {
+    /// The username of the banned player at the time of the ban, if known.
+    pub name: Option<String>,
+    /// The reason shown to the player.
+    pub reason: String,
+    /// Who issued the ban, such as the username of an operator or `Server`.
+    pub source: String,
+    /// When the ban was issued.
+    pub created_at: SystemTime,
+    /// When the ban ends, or `None` if it is permanent.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl Ban {
+    /// Returns `true` if the ban is no longer in effect at the given time.
+    pub fn is_expired_at(&self, time: SystemTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= time)
+    }
+}
+
+/// A record of a player being kicked from the server.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Kick {
+    /// The reason shown to the player.
+    pub reason: String,
+    /// Who kicked the player, such as the username of an operator or `Server`.
+    pub source: String,
+    /// When the player was kicked.
+    pub time: SystemTime,
+}
+
+/// Player data, bans, kicks and statistics kept in a [`StorageBackend`].
+///
+/// Player data, bans and kicks are stored as JSON. `Storage` is cheap to clone, and
+/// clones share the same backend.
+#[derive(Clone)]
+pub struct Storage {
+    backend: Arc<dyn StorageBackend>,
+}
+
+impl Storage {
+    /// Creates storage on top of the given backend.
+    pub fn new(backend: impl StorageBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Creates storage which keeps everything in memory.
+    pub fn in_memory() -> Self {
+        Self::new(MemoryStorage::default())
+    }
+
+    /// Opens or creates a sled database at the given path and creates storage
+    /// on top of it.
+    #[cfg(feature = "sled-storage")]
+    pub fn open_sled(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        Ok(Self::new(SledStorage::open(path)?))
+    }
+
+    /// Gets the backend of this storage.
+    pub fn backend(&self) -> &dyn StorageBackend {
+        &*self.backend
+    }
+
+    /// Loads the data of the player with the given UUID. Returns `None` if
+    /// nothing was saved for the player.
+    pub fn load_player_data<T: DeserializeOwned>(&self, uuid: Uuid) -> anyhow::Result<Option<T>> {
+        self.get_json(PLAYER_DATA_TREE, uuid.as_bytes())
+    }
+
+    /// Saves the data of the player with the given UUID, replacing what was
+    /// saved before.
+    pub fn save_player_data<T: Serialize>(&self, uuid: Uuid, data: &T) -> anyhow::Result<()> {
+        self.backend
+            .insert(PLAYER_DATA_TREE, uuid.as_bytes(), serde_json::to_vec(data)?)
+    }
+
+    /// Removes the data saved for the player with the given UUID. Returns
+    /// `true` if there was any.
+    pub fn remove_player_data(&self, uuid: Uuid) -> anyhow::Result<bool> {
+        self.backend.remove(PLAYER_DATA_TREE, uuid.as_bytes())
+    }
+
+    /// Bans a player or IP address, replacing any existing ban of it.
+    pub fn ban(&self, target: impl Into<BanTarget>, ban: &Ban) -> anyhow::Result<()> {
+        let (tree, key) = target.into().tree_and_key();
+        self.backend.insert(tree, &key, serde_json::to_vec(ban)?)
+    }
+
+    /// Lifts the ban of a player or IP address. Returns `true` if it was
+    /// banned.
+    pub fn unban(&self, target: impl Into<BanTarget>) -> anyhow::Result<bool> {
+        let (tree, key) = target.into().tree_and_key();
+        self.backend.remove(tree, &key)
+    }
+
+    /// Gets the ban of a player or IP address, including expired bans.
+    pub fn ban_of(&self, target: impl Into<BanTarget>) -> anyhow::Result<Option<Ban>> {
+        let (tree, key) = target.into().tree_and_key();
+        self.get_json(tree, &key)
+    }
+
+    /// Gets the ban of a player or IP address if it is in effect now.
+    pub fn active_ban(&self, target: impl Into<BanTarget>) -> anyhow::Result<Option<Ban>> {
+        Ok(self
+            .ban_of(target)?
+            .filter(|ban| !ban.is_expired_at(SystemTime::now())))
+    }
+
+    /// Gets all bans of players followed by all bans of IP addresses,
+    /// including expired bans.
+    pub fn bans(&self) -> anyhow::Result<Vec<(BanTarget, Ban)>> {
+        let players = self.backend.scan_prefix(BANS_TREE, &[])?;
+        let ips = self.backend.scan_prefix(IP_BANS_TREE, &[])?;
+
+        let players = players.into_iter().map(|(key, value)| {
+            let uuid = Uuid::from_slice(&key).context("invalid UUID in bans")?;
+            Ok((BanTarget::Player(uuid), serde_json::from_slice(&value)?))
+        });
+
+        let ips = ips.into_iter().map(|(key, value)| {
+            let ip = String::from_utf8(key)?
+                .parse()
+                .context("invalid IP address in bans")?;
+            Ok((BanTarget::Ip(ip), serde_json::from_slice(&value)?))
+        });
+
+        players.chain(ips).collect()
+    }
+
+    /// Adds a kick to the kick history of the player with the given UUID.
+    pub fn record_kick(&self, uuid: Uuid, kick: &Kick) -> anyhow::Result<()> {
+        let nanos = kick
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+
+        let mut key = uuid.as_bytes().to_vec();
+        key.extend_from_slice(&nanos.to_be_bytes());
+
+        self.backend
+            .insert(KICKS_TREE, &key, serde_json::to_vec(kick)?)
+    }
+
+    /// Gets the kick history of the player with the given UUID, oldest kick
+    /// first.
+    pub fn kicks(&self, uuid: Uuid) -> anyhow::Result<Vec<Kick>> {
+        self.backend
+            .scan_prefix(KICKS_TREE, uuid.as_bytes())?
+            .into_iter()
+            .map(|(_, value)| Ok(serde_json::from_slice(&value)?))
+            .collect()
+    }
+
+    /// Removes the kick history of the player with the given UUID. Returns
+    /// the number of kicks removed.
+    pub fn clear_kicks(&self, uuid: Uuid) -> anyhow::Result<usize> {
+        let kicks = self.backend.scan_prefix(KICKS_TREE, uuid.as_bytes())?;
+
+        for (key, _) in &kicks {
+            self.backend.remove(KICKS_TREE, key)?;
+        }
+
+        Ok(kicks.len())
+    }
+
+    /// Gets the value of a statistic of the player with the given UUID.
+    /// Statistics which were never set are zero.
+    pub fn statistic(&self, uuid: Uuid, name: &str) -> anyhow::Result<i64> {
+        Ok(self
+            .backend
+            .get(STATISTICS_TREE, &statistic_key(uuid, name))?
+            .map_or(0, |value| decode_statistic(&value)))
+    }
+
+    /// Adds `amount` to a statistic of the player with the given UUID and
+    /// returns the new value.
+    pub fn add_to_statistic(&self, uuid: Uuid, name: &str, amount: i64) -> anyhow::Result<i64> {
+        let value =
+            self.backend
+                .update(STATISTICS_TREE, &statistic_key(uuid, name), &mut |old| {
+                    let old = old.map_or(0, decode_statistic);
+                    Some(old.saturating_add(amount).to_be_bytes().to_vec())
+                })?;
+
+        Ok(value.map_or(0, |value| decode_statistic(&value)))
+    }
+
+    /// Sets a statistic of the player with the given UUID.
+    pub fn set_statistic(&self, uuid: Uuid, name: &str, value: i64) -> anyhow::Result<()> {
+        self.backend.insert(
+            STATISTICS_TREE,
+            &statistic_key(uuid, name),
+            value.to_be_bytes().to_vec(),
+        )
+    }
+
+    /// Gets all statistics of the player with the given UUID by name.
+    pub fn statistics(&self, uuid: Uuid) -> anyhow::Result<BTreeMap<String, i64>> {
+        self.backend
+            .scan_prefix(STATISTICS_TREE, uuid.as_bytes())?
+            .into_iter()
+            .map(|(key, value)| {
+                let name =
+                    String::from_utf8(key[16..].to_vec()).context("invalid statistic name")?;
+                Ok((name, decode_statistic(&value)))
+            })
+            .collect()
+    }
+
+    /// Writes all changes to durable storage.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        self.backend.flush()
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<T>> {
+        match self.backend.get(tree, key)? {
+            Some(value) => {
+                Ok(Some(serde_json::from_slice(&value).with_context(|| {
+                    format!("failed to parse value in {tree}")
+                })?))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+// Backends apply each operation atomically, so a panic cannot leave the data
+// in a state other threads should not see. This lets `Storage` be kept in a
+// `Config`.
+impl UnwindSafe for Storage {}
+impl RefUnwindSafe for Storage {}
+
+impl fmt::Debug for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Storage").finish_non_exhaustive()
+    }
+}
+
+/// The key of a statistic, which is the UUID of the player followed by the
+/// name of the statistic.
+fn statistic_key(uuid: Uuid, name: &str) -> Vec<u8> {
+    let mut key = uuid.as_bytes().to_vec();
+    key.extend_from_slice(name.as_bytes());
+    key
+}
+
+/// Statistics are stored as big-endian `i64`s. Malformed values are zero.
+fn decode_statistic(value: &[u8]) -> i64 {
+    value.try_into().map_or(0, i64::from_be_bytes)
+}
+
+type Tree = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// A [`StorageBackend`] keeping everything in memory.
+#[derive(Default, Debug)]
+pub struct MemoryStorage {
+    trees: Mutex<HashMap<String, Tree>>,
+}
+
+impl StorageBackend for MemoryStorage {
+    fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let trees = self.trees.lock().unwrap();
+        Ok(trees.get(tree).and_then(|t| t.get(key)).cloned())
+    }
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<()> {
+        let mut trees = self.trees.lock().unwrap();
+        trees
+            .entry(tree.to_owned())
+            .or_default()
+            .insert(key.to_vec(), value);
+        Ok(())
+    }
+
+    fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<bool> {
+        let mut trees = self.trees.lock().unwrap();
+        Ok(trees.get_mut(tree).and_then(|t| t.remove(key)).is_some())
+    }
+
+    fn update(&self, tree: &str, key: &[u8], f: &mut UpdateFn) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut trees = self.trees.lock().unwrap();
+        let tree = trees.entry(tree.to_owned()).or_default();
+
+        match f(tree.get(key).map(|v| v.as_slice())) {
+            Some(value) => {
+                tree.insert(key.to_vec(), value.clone());
+                Ok(Some(value))
+            }
+            None => {
+                tree.remove(key);
+                Ok(None)
+            }
+        }
+    }
+
+    fn scan_prefix(&self, tree: &str, prefix: &[u8]) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let trees = self.trees.lock().unwrap();
+        Ok(trees
+            .get(tree)
+            .into_iter()
+            .flat_map(|t| t.range(prefix.to_vec()..))
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect())
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A [`StorageBackend`] keeping everything in a [sled](https://docs.rs/sled)
+/// database. Each tree is a sled tree.
+#[cfg(feature = "sled-storage")]
+#[derive(Clone, Debug)]
+pub struct SledStorage {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled-storage")]
+impl SledStorage {
+    /// Opens or creates a database at the given path.
+    pub fn open(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let db = sled::open(path)
+            .with_context(|| format!("failed to open database at {}", path.display()))?;
+
+        Ok(Self { db })
+    }
+
+    /// Creates a backend from an already opened database.
+    pub fn from_db(db: sled::Db) -> Self {
+        Self { db }
+    }
+
+    /// Gets the database of this backend.
+    pub fn db(&self) -> &sled::Db {
+        &self.db
+    }
+}
+
+#[cfg(feature = "sled-storage")]
+impl StorageBackend for SledStorage {
+    fn get(&self, tree: &str, key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.db.open_tree(tree)?.get(key)?.map(|v| v.to_vec()))
+    }
+
+    fn insert(&self, tree: &str, key: &[u8], value: Vec<u8>) -> anyhow::Result<()> {
+        self.db.open_tree(tree)?.insert(key, value)?;
+        Ok(())
+    }
+
+    fn remove(&self, tree: &str, key: &[u8]) -> anyhow::Result<bool> {
+        Ok(self.db.open_tree(tree)?.remove(key)?.is_some())
+    }
+
+    fn update(&self, tree: &str, key: &[u8], f: &mut UpdateFn) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self
+            .db
+            .open_tree(tree)?
+            .update_and_fetch(key, f)?
+            .map(|v| v.to_vec()))
+    }
+
+    fn scan_prefix(&self, tree: &str, prefix: &[u8]) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.db
+            .open_tree(tree)?
+            .scan_prefix(prefix)
+            .map(|res| {
+                let (k, v) = res?;
+                Ok((k.to_vec(), v.to_vec()))
+            })
+            .collect()
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn check_storage(storage: &Storage) {
+        let uuid = Uuid::from_u128(1);
+        let other = Uuid::from_u128(2);
+
+        assert_eq!(storage.load_player_data::<i32>(uuid).unwrap(), None);
+        storage.save_player_data(uuid, &5).unwrap();
+        assert_eq!(storage.load_player_data(uuid).unwrap(), Some(5));
+        assert!(storage.remove_player_data(uuid).unwrap());
+        assert_eq!(storage.load_player_data::<i32>(uuid).unwrap(), None);
+
+        let now = SystemTime::now();
+        let ban = |reason: &str, expires_at| Ban {
+            name: Some("tester".into()),
+            reason: reason.into(),
+            source: "Server".into(),
+            created_at: now,
+            expires_at,
+        };
+
+        let expired = ban("griefing", Some(now - Duration::from_secs(1)));
+        storage.ban(uuid, &expired).unwrap();
+        assert_eq!(storage.ban_of(uuid).unwrap(), Some(expired.clone()));
+        assert_eq!(storage.active_ban(uuid).unwrap(), None);
+
+        let permanent = ban("cheating", None);
+        storage.ban(other, &permanent).unwrap();
+        assert_eq!(storage.active_ban(other).unwrap(), Some(permanent.clone()));
+
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        storage.ban(ip, &permanent).unwrap();
+        assert_eq!(storage.active_ban(ip).unwrap(), Some(permanent.clone()));
+        assert_eq!(storage.ban_of(other).unwrap(), Some(permanent.clone()));
+
+        assert_eq!(
+            storage.bans().unwrap(),
+            [
+                (BanTarget::Player(uuid), expired),
+                (BanTarget::Player(other), permanent.clone()),
+                (BanTarget::Ip(ip), permanent),
+            ]
+        );
+        assert!(storage.unban(other).unwrap());
+        assert!(!storage.unban(other).unwrap());
+        assert!(storage.unban(ip).unwrap());
+
+        let kick = |secs| Kick {
+            reason: "spam".into(),
+            source: "Server".into(),
+            time: UNIX_EPOCH + Duration::from_secs(secs),
+        };
+        storage.record_kick(uuid, &kick(20)).unwrap();
+        storage.record_kick(uuid, &kick(10)).unwrap();
+        storage.record_kick(other, &kick(15)).unwrap();
+        assert_eq!(storage.kicks(uuid).unwrap(), [kick(10), kick(20)]);
+        assert_eq!(storage.clear_kicks(uuid).unwrap(), 2);
+        assert_eq!(storage.kicks(uuid).unwrap(), []);
+        assert_eq!(storage.kicks(other).unwrap(), [kick(15)]);
+
+        assert_eq!(storage.statistic(uuid, "jumps").unwrap(), 0);
+        assert_eq!(storage.add_to_statistic(uuid, "jumps", 2).unwrap(), 2);
+        assert_eq!(storage.add_to_statistic(uuid, "jumps", 3).unwrap(), 5);
+        storage.set_statistic(uuid, "deaths", 1).unwrap();
+        storage.set_statistic(other, "deaths", 9).unwrap();
+        assert_eq!(
+            storage.statistics(uuid).unwrap(),
+            BTreeMap::from([("deaths".into(), 1), ("jumps".into(), 5)])
+        );
+    }
+
+    #[test]
+    fn memory_storage() {
+        check_storage(&Storage::in_memory());
+    }
+
+    #[cfg(feature = "sled-storage")]
+    #[test]
+    fn sled_storage() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        check_storage(&Storage::new(SledStorage::from_db(db)));
+    }
+}
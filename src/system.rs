@@ -0,0 +1,261 @@
+//! Systems: self-contained parts of the game logic added at runtime.
+//!
+//! Instead of putting all game logic into [`Config::update`], a server can be
+//! split into [`System`]s, such as one per game mode or minigame. Systems are
+//! stored by name in [`Server::systems`] and can be added, replaced and
+//! removed while the server is running, for example from a command.
+//!
+//! Every tick, the systems are updated in the order they were added, right
+//! after [`Config::update`].
+//!
+//! A system added to the server is [initialized](System::init) at the
+//! beginning of the next tick, before it is first updated. A system which is
+//! removed or replaced is [shut down](System::shutdown) before the next tick's
+//! updates, or right away if it removes itself during its update. All systems
+//! are shut down when the server shuts down.
+
+use std::mem;
+
+use crate::config::Config;
+use crate::server::Server;
+
+/// A part of the game logic which runs every tick.
+#[allow(unused_variables)]
+pub trait System<C: Config>: Send + 'static {
+    /// Called once before the first [update](Self::update) of the system.
+    ///
+    /// # Default Implementation
+    ///
+    /// The default implementation does nothing.
+    fn init(&mut self, server: &mut Server<C>) {}
+
+    /// Called once every tick after [`Config::update`].
+    fn update(&mut self, server: &mut Server<C>);
+
+    /// Called once after the system is removed or replaced, or when the
+    /// server shuts down. This is not called if the system was never
+    /// [initialized](Self::init).
+    ///
+    /// # Default Implementation
+    ///
+    /// The default implementation does nothing.
+    fn shutdown(&mut self, server: &mut Server<C>) {}
+}
+
+/// Contains the systems of a server.
+pub struct Systems<C: Config> {
+    entries: Vec<Entry<C>>,
+    /// Removed systems which have not been shut down yet.
+    removed: Vec<Box<dyn System<C>>>,
+}
+
+struct Entry<C: Config> {
+    name: String,
+    /// `None` while the system is being updated.
+    system: Option<Box<dyn System<C>>>,
+    initialized: bool,
+}
+
+impl<C: Config> Systems<C> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    /// Adds a system with the given name. If a system with the same name
+    /// exists, it is replaced while keeping its position in the update order,
+    /// and `true` is returned.
+    pub fn add(&mut self, name: impl Into<String>, system: impl System<C>) -> bool {
+        let name = name.into();
+        let system: Box<dyn System<C>> = Box::new(system);
+
+        match self.entries.iter_mut().find(|e| e.name == name) {
+            Some(entry) => {
+                let old = entry.system.replace(system);
+                if entry.initialized {
+                    self.removed.extend(old);
+                }
+                entry.initialized = false;
+                true
+            }
+            None => {
+                self.entries.push(Entry {
+                    name,
+                    system: Some(system),
+                    initialized: false,
+                });
+                false
+            }
+        }
+    }
+
+    /// Removes the system with the given name. Returns `true` if it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let Some(idx) = self.entries.iter().position(|e| e.name == name) else {
+            return false;
+        };
+
+        let entry = self.entries.remove(idx);
+        if entry.initialized {
+            self.removed.extend(entry.system);
+        }
+        true
+    }
+
+    /// Returns `true` if a system with the given name exists.
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e.name == name)
+    }
+
+    /// Returns an iterator over the names of all systems in update order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+
+    /// Returns the number of systems.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no systems.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Shuts down the removed systems, then initializes new systems and updates
+/// all systems.
+pub(crate) fn update<C: Config>(server: &mut Server<C>) {
+    shutdown_removed(server);
+
+    let names: Vec<_> = server.systems.names().map(str::to_owned).collect();
+
+    for name in names {
+        let Some(entry) = server.systems.entries.iter_mut().find(|e| e.name == name) else {
+            continue;
+        };
+        let Some(mut system) = entry.system.take() else {
+            continue;
+        };
+        let init = !mem::replace(&mut entry.initialized, true);
+
+        if init {
+            system.init(server);
+        }
+        system.update(server);
+
+        // The system may have removed or replaced itself.
+        match server.systems.entries.iter_mut().find(|e| e.name == name) {
+            Some(Entry {
+                system: slot @ None,
+                ..
+            }) => *slot = Some(system),
+            _ => system.shutdown(server),
+        }
+    }
+}
+
+/// Shuts down all systems. Called when the server shuts down.
+pub(crate) fn shutdown<C: Config>(server: &mut Server<C>) {
+    shutdown_removed(server);
+
+    for entry in mem::take(&mut server.systems.entries) {
+        if let (Some(mut system), true) = (entry.system, entry.initialized) {
+            system.shutdown(server);
+        }
+    }
+}
+
+fn shutdown_removed<C: Config>(server: &mut Server<C>) {
+    while !server.systems.removed.is_empty() {
+        for mut system in mem::take(&mut server.systems.removed) {
+            system.shutdown(server);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::testing::{test_server, Game};
+
+    /// Records the calls made to it and removes itself after `lifetime`
+    /// updates.
+    struct Recorder {
+        name: &'static str,
+        lifetime: u32,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl System<Game> for Recorder {
+        fn init(&mut self, _: &mut Server<Game>) {
+            self.log.lock().unwrap().push(format!("{} init", self.name));
+        }
+
+        fn update(&mut self, server: &mut Server<Game>) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} update", self.name));
+            self.lifetime -= 1;
+            if self.lifetime == 0 {
+                server.systems.remove(self.name);
+            }
+        }
+
+        fn shutdown(&mut self, _: &mut Server<Game>) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} shutdown", self.name));
+        }
+    }
+
+    #[test]
+    fn systems_added_and_removed() {
+        let mut server = test_server();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let recorder = |name, lifetime| Recorder {
+            name,
+            lifetime,
+            log: log.clone(),
+        };
+        let take_log = || mem::take(&mut *log.lock().unwrap());
+
+        let systems = &mut server.server_mut().systems;
+        assert!(!systems.add("lobby", recorder("lobby", 10)));
+        assert!(!systems.add("arena", recorder("arena", 2)));
+        server.tick();
+        assert_eq!(
+            take_log(),
+            ["lobby init", "lobby update", "arena init", "arena update"]
+        );
+
+        // Replacing keeps the update order.
+        let systems = &mut server.server_mut().systems;
+        assert!(systems.add("lobby", recorder("lobby v2", 10)));
+        assert_eq!(systems.names().collect::<Vec<_>>(), ["lobby", "arena"]);
+        server.tick();
+        assert_eq!(
+            take_log(),
+            [
+                "lobby shutdown",
+                "lobby v2 init",
+                "lobby v2 update",
+                "arena update",
+                "arena shutdown"
+            ]
+        );
+
+        let s = server.server_mut();
+        assert!(!s.systems.contains("arena"));
+        assert!(!s.systems.remove("arena"));
+        shutdown(s);
+        assert_eq!(take_log(), ["lobby v2 shutdown"]);
+        assert!(s.systems.is_empty());
+    }
+}
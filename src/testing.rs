@@ -0,0 +1,550 @@
+//! Utilities for testing servers.
+//!
+//! [`TestClient`] is a headless Minecraft client which connects to a running
+//! server, goes through the handshake and login, and then sends and receives
+//! play packets. It can be used to write integration tests which join a
+//! server, move around, chat, and make assertions about the packets received.
+//!
+//! The client does not support online mode or encryption. Servers under test
+//! should return `false` from [`Config::online_mode`].
+//!
+//! [`TestServer`] runs a server without any sockets or background update loop.
+//! Ticks only happen when [`TestServer::tick`] is called, so the tick counter
+//! is fully deterministic. Clients are added with [`TestServer::connect`] and
+//! exchange packets with the server through in-memory [`MockClient`]s. This
+//! makes it possible to unit test [`Config::update`] logic and assert on the
+//! exact packets queued for each client.
+//!
+//! [`Config::online_mode`]: crate::config::Config::online_mode
+//! [`Config::update`]: crate::config::Config::update
+
+use std::iter;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use flume::Sender;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use uuid::Uuid;
+use vek::Vec3;
+
+use crate::client::{ClientId, NetworkCounters};
+use crate::config::Config;
+use crate::protocol::codec::{Decoder, Encoder};
+use crate::protocol::packets::c2s::handshake::{Handshake, HandshakeNextState};
+use crate::protocol::packets::c2s::login::LoginStart;
+use crate::protocol::packets::c2s::play::{
+    C2sPlayPacket, ChatMessage, CommandExecution, KeepAlive, MessageAcknowledgment,
+    MessageAcknowledgmentList, MovePlayerPosition, MovePlayerPositionAndRotation, TeleportConfirm,
+};
+use crate::protocol::packets::s2c::login::S2cLoginPacket;
+use crate::protocol::packets::s2c::play::S2cPlayPacket;
+use crate::protocol::packets::EncodePacket;
+use crate::protocol::{BoundedString, VarInt};
+use crate::server::{self, NewClientData, S2cPlayReceiver, Server};
+use crate::PROTOCOL_VERSION;
+
+#[cfg(test)]
+pub(crate) use fixtures::{chat_message, connected_client, messages, test_server, Game};
+
+#[cfg(test)]
+mod fixtures;
+
+/// The default amount of time [`TestClient`] waits for a packet before giving
+/// up.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A programmatic Minecraft client in the play state.
+///
+/// Keep alives and teleports are answered automatically while receiving
+/// packets, so the server does not disconnect the client as long as packets
+/// are being received.
+pub struct TestClient {
+    enc: Encoder<OwnedWriteHalf>,
+    dec: Decoder<OwnedReadHalf>,
+    uuid: Uuid,
+    username: String,
+    position: Vec3<f64>,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl TestClient {
+    /// Connects to the server at `addr` and logs in with `username`.
+    ///
+    /// Returns once the login succeeded and the client is in the play state.
+    pub async fn connect(addr: SocketAddr, username: impl Into<String>) -> anyhow::Result<Self> {
+        Self::connect_with_timeout(addr, username, DEFAULT_TIMEOUT).await
+    }
+
+    /// Like [`Self::connect`], but with a custom timeout for reading and
+    /// writing packets.
+    pub async fn connect_with_timeout(
+        addr: SocketAddr,
+        username: impl Into<String>,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let username = username.into();
+
+        let stream = TcpStream::connect(addr)
+            .await
+            .with_context(|| format!("failed to connect to {addr}"))?;
+        stream.set_nodelay(true)?;
+
+        let (read, write) = stream.into_split();
+        let mut enc = Encoder::new(write, timeout);
+        let mut dec = Decoder::new(read, timeout);
+
+        enc.write_packet(&Handshake {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            server_adddress: BoundedString(addr.ip().to_string()),
+            server_port: addr.port(),
+            next_state: HandshakeNextState::Login,
+        })
+        .await?;
+
+        enc.write_packet(&LoginStart {
+            username: BoundedString(username.clone()),
+            sig_data: None,
+            profile_id: None,
+        })
+        .await?;
+
+        let uuid = loop {
+            match dec.read_packet().await? {
+                S2cLoginPacket::LoginDisconnect(p) => {
+                    bail!("disconnected during login: {}", p.reason.to_plain())
+                }
+                S2cLoginPacket::EncryptionRequest(_) => {
+                    bail!("the test client does not support online mode")
+                }
+                S2cLoginPacket::LoginSuccess(p) => break p.uuid,
+                S2cLoginPacket::LoginCompression(p) => {
+                    let threshold = p.threshold.0 as u32;
+                    enc.enable_compression(threshold);
+                    dec.enable_compression(threshold);
+                }
+                S2cLoginPacket::LoginPluginRequest(_) => {
+                    bail!("the test client does not support login plugin requests")
+                }
+            }
+        };
+
+        Ok(Self {
+            enc,
+            dec,
+            uuid,
+            username,
+            position: Vec3::zero(),
+            yaw: 0.0,
+            pitch: 0.0,
+        })
+    }
+
+    /// Gets the UUID the server assigned to this client.
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    /// Gets the username this client logged in with.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Gets the position of this client, as last set by the server or
+    /// [`Self::move_to`].
+    pub fn position(&self) -> Vec3<f64> {
+        self.position
+    }
+
+    /// Gets the yaw of this client in degrees.
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    /// Gets the pitch of this client in degrees.
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Sends a play packet to the server.
+    pub async fn send(&mut self, pkt: &(impl EncodePacket + ?Sized)) -> anyhow::Result<()> {
+        self.enc.write_packet(pkt).await
+    }
+
+    /// Receives the next play packet from the server.
+    ///
+    /// An error is returned if the server disconnects the client or no packet
+    /// arrives within the timeout.
+    pub async fn recv(&mut self) -> anyhow::Result<S2cPlayPacket> {
+        let pkt = self.dec.read_packet().await?;
+
+        match &pkt {
+            S2cPlayPacket::KeepAlive(p) => self.send(&KeepAlive { id: p.id }).await?,
+            S2cPlayPacket::PlayerPositionLook(p) => {
+                let pos = &mut self.position;
+                pos.x = if p.flags.x() {
+                    pos.x + p.position.x
+                } else {
+                    p.position.x
+                };
+                pos.y = if p.flags.y() {
+                    pos.y + p.position.y
+                } else {
+                    p.position.y
+                };
+                pos.z = if p.flags.z() {
+                    pos.z + p.position.z
+                } else {
+                    p.position.z
+                };
+                self.yaw = if p.flags.y_rot() {
+                    self.yaw + p.yaw
+                } else {
+                    p.yaw
+                };
+                self.pitch = if p.flags.x_rot() {
+                    self.pitch + p.pitch
+                } else {
+                    p.pitch
+                };
+
+                self.send(&TeleportConfirm {
+                    teleport_id: p.teleport_id,
+                })
+                .await?;
+            }
+            S2cPlayPacket::Disconnect(p) => {
+                bail!("disconnected by the server: {}", p.reason.to_plain())
+            }
+            _ => {}
+        }
+
+        Ok(pkt)
+    }
+
+    /// Receives packets until `f` returns `Some`, and returns the value.
+    ///
+    /// Packets for which `f` returns `None` are discarded.
+    pub async fn recv_until<T>(
+        &mut self,
+        mut f: impl FnMut(S2cPlayPacket) -> Option<T>,
+    ) -> anyhow::Result<T> {
+        loop {
+            if let Some(t) = f(self.recv().await?) {
+                return Ok(t);
+            }
+        }
+    }
+
+    /// Receives packets until a system chat message arrives, and returns its
+    /// contents as plain text.
+    pub async fn recv_message(&mut self) -> anyhow::Result<String> {
+        self.recv_until(|pkt| match pkt {
+            S2cPlayPacket::GameMessage(p) => Some(p.chat.to_plain()),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Moves this client to `pos`.
+    pub async fn move_to(&mut self, pos: impl Into<Vec3<f64>>) -> anyhow::Result<()> {
+        self.position = pos.into();
+
+        self.send(&MovePlayerPosition {
+            position: self.position,
+            on_ground: true,
+        })
+        .await
+    }
+
+    /// Moves this client to `pos` and changes its rotation.
+    pub async fn look(
+        &mut self,
+        pos: impl Into<Vec3<f64>>,
+        yaw: f32,
+        pitch: f32,
+    ) -> anyhow::Result<()> {
+        self.position = pos.into();
+        self.yaw = yaw;
+        self.pitch = pitch;
+
+        self.send(&MovePlayerPositionAndRotation {
+            position: self.position,
+            yaw,
+            pitch,
+            on_ground: true,
+        })
+        .await
+    }
+
+    /// Sends an unsigned chat message. Messages starting with `/` are sent as
+    /// commands.
+    pub async fn chat(&mut self, msg: impl Into<String>) -> anyhow::Result<()> {
+        let msg = msg.into();
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+
+        let acknowledgement = MessageAcknowledgment {
+            last_seen: MessageAcknowledgmentList {
+                entries: Vec::new(),
+            },
+            last_received: None,
+        };
+
+        match msg.strip_prefix('/') {
+            Some(command) => {
+                self.send(&CommandExecution {
+                    command: BoundedString(command.to_owned()),
+                    timestamp,
+                    salt: 0,
+                    arg_sig: Vec::new(),
+                    signed_preview: false,
+                    acknowledgement,
+                })
+                .await
+            }
+            None => {
+                self.send(&ChatMessage {
+                    message: BoundedString(msg),
+                    timestamp,
+                    salt: 0,
+                    signature: Vec::new(),
+                    signed_preview: false,
+                    acknowledgement,
+                })
+                .await
+            }
+        }
+    }
+}
+
+/// A server which is driven manually instead of running its own update loop.
+///
+/// No sockets are opened, so [`Config::addresses`] and
+/// [`Config::metrics_address`] are ignored. [`Config::init`] is called when
+/// the server is created.
+///
+/// [`Config::addresses`]: crate::config::Config::addresses
+/// [`Config::metrics_address`]: crate::config::Config::metrics_address
+/// [`Config::init`]: crate::config::Config::init
+pub struct TestServer<C: Config> {
+    server: Server<C>,
+}
+
+impl<C: Config> TestServer<C> {
+    /// Creates and initializes a server from the given configuration and
+    /// server state.
+    pub fn new(config: C, data: C::ServerState) -> anyhow::Result<Self> {
+        let mut server = server::create_server(config, data)?;
+        let shared = server.shared.clone();
+
+        let _guard = shared.tokio_handle().enter();
+        shared.config().init(&mut server);
+
+        Ok(Self { server })
+    }
+
+    /// Gets a reference to the server.
+    pub fn server(&self) -> &Server<C> {
+        &self.server
+    }
+
+    /// Gets a mutable reference to the server.
+    pub fn server_mut(&mut self) -> &mut Server<C> {
+        &mut self.server
+    }
+
+    /// Runs a single game update. The tick counter is advanced by one
+    /// afterwards.
+    ///
+    /// Unlike a running server, this does not sleep for the remainder of the
+    /// tick and does not check for [`SharedServer::shutdown`].
+    ///
+    /// [`SharedServer::shutdown`]: crate::server::SharedServer::shutdown
+    pub fn tick(&mut self) {
+        let shared = self.server.shared.clone();
+        let _guard = shared.tokio_handle().enter();
+
+        server::do_tick(&mut self.server);
+    }
+
+    /// Adds a client with the given username to the server, as if it had just
+    /// logged in with online mode disabled. The client's state is loaded with
+    /// [`Config::load_player_data`].
+    ///
+    /// The client is added immediately, so it is visible in [`Server::clients`]
+    /// before the next call to [`Self::tick`].
+    pub fn connect(&mut self, username: impl Into<String>) -> MockClient {
+        let username = username.into();
+
+        let ncd = NewClientData {
+            uuid: server::offline_uuid(&username),
+            username,
+            textures: None,
+            remote_addr: SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0).into(),
+            server_address: "localhost".into(),
+            server_port: self.server.shared.address().port().unwrap_or(25565),
+            forge_marker: None,
+            protocol_version: PROTOCOL_VERSION,
+        };
+
+        let shared = self.server.shared.clone();
+        let state = {
+            let _guard = shared.tokio_handle().enter();
+            futures::executor::block_on(async {
+                shared.wait_for_player_save(ncd.uuid).await;
+                shared.config().load_player_data(&shared, ncd.uuid).await
+            })
+        };
+
+        let (id, (send, recv, network)) = server::join_player(&mut self.server, ncd, state);
+
+        MockClient {
+            id,
+            send,
+            recv: Mutex::new(recv),
+            network,
+        }
+    }
+}
+
+/// The other end of a client added with [`TestServer::connect`].
+///
+/// Packets sent by the server are buffered until they are taken with
+/// [`Self::take_packets`]. The client is disconnected by the server if the
+/// buffer reaches [`Config::outgoing_packet_capacity`], so packets should be
+/// taken regularly.
+///
+/// Dropping the `MockClient` disconnects the client.
+///
+/// [`Config::outgoing_packet_capacity`]: crate::config::Config::outgoing_packet_capacity
+pub struct MockClient {
+    id: ClientId,
+    send: Sender<C2sPlayPacket>,
+    recv: Mutex<S2cPlayReceiver>,
+    /// Counts packets without their size, since they are not encoded.
+    network: Arc<NetworkCounters>,
+}
+
+impl MockClient {
+    /// Gets the ID of the client on the server.
+    pub fn id(&self) -> ClientId {
+        self.id
+    }
+
+    /// Sends a play packet to the server. It is handled during the next tick.
+    ///
+    /// Returns `false` if the server has disconnected the client.
+    pub fn send(&self, pkt: impl Into<C2sPlayPacket>) -> bool {
+        let sent = self.send.try_send(pkt.into()).is_ok();
+        if sent {
+            self.network.record_received(0);
+        }
+        sent
+    }
+
+    /// Takes all packets the server has queued for this client so far, in
+    /// the order they would be written to the connection.
+    pub fn take_packets(&self) -> Vec<S2cPlayPacket> {
+        let mut recv = self.recv.lock().unwrap();
+        iter::from_fn(|| recv.try_next().map(|(pkt, _)| pkt))
+            .inspect(|_| self.network.record_sent(0))
+            .collect()
+    }
+
+    /// Returns `true` if the server has disconnected the client and no
+    /// packets are left to take.
+    pub fn is_disconnected(&self) -> bool {
+        self.recv.lock().unwrap().is_disconnected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn join_move_and_chat() {
+        let address = {
+            let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+            listener.local_addr().unwrap()
+        };
+
+        let server = std::thread::spawn(move || {
+            crate::start_server(
+                Game {
+                    address,
+                    ..Game::default()
+                },
+                (),
+            )
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut client = loop {
+                match TestClient::connect(address, "tester").await {
+                    Ok(client) => break client,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+                }
+            };
+
+            assert_eq!(client.username(), "tester");
+
+            let mut welcomed = false;
+            while !welcomed || client.position() != Vec3::new(0.5, 100.0, 0.5) {
+                if let S2cPlayPacket::GameMessage(p) = client.recv().await.unwrap() {
+                    assert_eq!(p.chat.to_plain(), "welcome");
+                    welcomed = true;
+                }
+            }
+
+            client.move_to([3.0, 100.0, 0.5]).await.unwrap();
+            client.chat("hello").await.unwrap();
+            assert_eq!(client.recv_message().await.unwrap(), "hello at 3");
+        });
+        drop(runtime);
+
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_server_ticks_deterministically() {
+        let mut server = test_server();
+
+        let client = server.connect("tester");
+        assert!(server.server().clients.get(client.id()).is_some());
+        assert_eq!(server.server().shared.current_tick(), 0);
+
+        server.tick();
+        assert_eq!(server.server().shared.current_tick(), 1);
+
+        let pkts = client.take_packets();
+        assert!(matches!(pkts.first(), Some(S2cPlayPacket::GameJoin(_))));
+        assert!(pkts.iter().any(
+            |pkt| matches!(pkt, S2cPlayPacket::GameMessage(p) if p.chat.to_plain() == "welcome")
+        ));
+        assert!(client.take_packets().is_empty());
+
+        assert!(client.send(chat_message("hello")));
+
+        server.tick();
+
+        let messages: Vec<_> = client
+            .take_packets()
+            .into_iter()
+            .filter_map(|pkt| match pkt {
+                S2cPlayPacket::GameMessage(p) => Some(p.chat.to_plain()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(messages, ["hello at 0.5"]);
+    }
+}
@@ -0,0 +1,185 @@
+//! The server configuration and helpers shared by the unit tests of the crate.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{MockClient, TestServer};
+use crate::client::{ClientEvent, PacketLimits};
+use crate::config::Config;
+use crate::dimension::DimensionId;
+use crate::mojang::{GameProfile, MojangApi};
+use crate::protocol::packets::c2s::play::{
+    ChatMessage, MessageAcknowledgment, MessageAcknowledgmentList,
+};
+use crate::protocol::packets::s2c::play::S2cPlayPacket;
+use crate::protocol::BoundedString;
+use crate::server::Server;
+use crate::transport::ListenAddress;
+
+/// A server with one world of 4x4 chunks around the origin.
+///
+/// Clients are spawned at (0.5, 100, 0.5) and greeted with "welcome". Chat
+/// messages are echoed back along with the x coordinate of the sender.
+pub(crate) struct Game {
+    pub address: SocketAddr,
+    pub packet_limits: PacketLimits,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self {
+            address: SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0).into(),
+            packet_limits: PacketLimits::default(),
+        }
+    }
+}
+
+/// A session server on which only "tester" has joined.
+pub(crate) struct MockMojangApi;
+
+#[async_trait]
+impl MojangApi for MockMojangApi {
+    async fn has_joined(
+        &self,
+        username: &str,
+        _server_hash: &str,
+        _ip: Option<IpAddr>,
+    ) -> anyhow::Result<Option<GameProfile>> {
+        Ok((username == "tester").then(|| GameProfile {
+            id: Uuid::from_u128(1),
+            name: username.into(),
+            properties: Vec::new(),
+        }))
+    }
+
+    async fn profile(&self, _uuid: Uuid) -> anyhow::Result<Option<GameProfile>> {
+        Ok(None)
+    }
+
+    async fn uuid_of(&self, _username: &str) -> anyhow::Result<Option<Uuid>> {
+        Ok(None)
+    }
+}
+
+impl Config for Game {
+    type ServerState = ();
+    type ClientState = ();
+    type EntityState = ();
+    type WorldState = ();
+    type ChunkState = ();
+    type PlayerListState = ();
+
+    fn max_connections(&self) -> usize {
+        10
+    }
+
+    fn address(&self) -> ListenAddress {
+        self.address.into()
+    }
+
+    fn online_mode(&self) -> bool {
+        false
+    }
+
+    fn packet_limits(&self) -> PacketLimits {
+        self.packet_limits.clone()
+    }
+
+    fn chunk_resend_threshold(&self) -> usize {
+        8
+    }
+
+    fn mojang_api(&self) -> Box<dyn MojangApi> {
+        Box::new(MockMojangApi)
+    }
+
+    fn init(&self, server: &mut Server<Self>) {
+        let (_, world) = server.worlds.insert(DimensionId::default(), ());
+        for z in -2..2 {
+            for x in -2..2 {
+                world.chunks.insert([x, z], ());
+            }
+        }
+    }
+
+    fn update(&self, server: &mut Server<Self>) {
+        let (world_id, _) = server.worlds.iter_mut().next().unwrap();
+
+        for (_, client) in server.clients.iter_mut() {
+            if client.created_this_tick() {
+                client.respawn(world_id, [0.5, 100.0, 0.5], 0.0, 0.0);
+                client.send_message("welcome");
+            }
+
+            while let Some(event) = client.pop_event() {
+                match event {
+                    ClientEvent::ChatMessage { message, .. } => {
+                        let x = client.position().x;
+                        client.send_message(format!("{message} at {x}"));
+                    }
+                    ClientEvent::RespawnRequest => {
+                        client.change_dimension(world_id, [0.5, 100.0, 0.5], 0.0, 0.0);
+                    }
+                    ClientEvent::ProtectionDenied { action, region } => {
+                        client.send_message(format!("{action:?} denied by {region}"));
+                    }
+                    _ => {}
+                }
+            }
+
+            if client.is_disconnected() {
+                server
+                    .shared
+                    .shutdown::<_, Box<dyn std::error::Error + Send + Sync>>(Ok(()));
+            }
+        }
+    }
+}
+
+/// Creates a [`TestServer`] running the default [`Game`].
+pub(crate) fn test_server() -> TestServer<Game> {
+    TestServer::new(Game::default(), ()).unwrap()
+}
+
+/// Creates a [`TestServer`] running the default [`Game`] and connects
+/// "tester" to it. The packets sent to the client while joining are already
+/// taken.
+pub(crate) fn connected_client() -> (TestServer<Game>, MockClient) {
+    let mut server = test_server();
+    let client = server.connect("tester");
+    server.tick();
+    client.take_packets();
+    (server, client)
+}
+
+/// Creates an unsigned chat message packet.
+pub(crate) fn chat_message(msg: &str) -> ChatMessage {
+    ChatMessage {
+        message: BoundedString(msg.into()),
+        timestamp: 0,
+        salt: 0,
+        signature: Vec::new(),
+        signed_preview: false,
+        acknowledgement: MessageAcknowledgment {
+            last_seen: MessageAcknowledgmentList {
+                entries: Vec::new(),
+            },
+            last_received: None,
+        },
+    }
+}
+
+/// Takes the packets sent to `client` and returns the plain text of the chat
+/// messages among them.
+pub(crate) fn messages(client: &MockClient) -> Vec<String> {
+    client
+        .take_packets()
+        .into_iter()
+        .filter_map(|pkt| match pkt {
+            S2cPlayPacket::GameMessage(p) => Some(p.chat.to_plain()),
+            _ => None,
+        })
+        .collect()
+}
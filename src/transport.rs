@@ -0,0 +1,444 @@
+//! Network transports carrying the protocol.
+//!
+//! The server accepts connections over TCP or Unix domain sockets on the
+//! addresses returned by [`Config::addresses`]. Other transports, such as WebSocket tunnels or
+//! in-memory pipes for tests, can carry the protocol by implementing
+//! [`Transport`] and passing it to [`SharedServer::add_transport`].
+//!
+//! [`Config::addresses`]: crate::config::Config::addresses
+//! [`SharedServer::add_transport`]: crate::server::SharedServer::add_transport
+
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+/// An address the server listens on for connections.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ListenAddress {
+    /// A TCP socket address.
+    Tcp(SocketAddr),
+    /// The path of a Unix domain socket, such as one shared with a proxy
+    /// running alongside the server.
+    ///
+    /// The socket file is created when the server starts and removed when the
+    /// listener is dropped. Starting the server fails if the file already
+    /// exists.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl ListenAddress {
+    /// Returns the TCP socket address, or `None` if this is not a TCP
+    /// address.
+    pub fn as_tcp(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Tcp(addr) => Some(*addr),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    /// Returns the TCP port, or `None` if this is not a TCP address.
+    pub fn port(&self) -> Option<u16> {
+        self.as_tcp().map(|addr| addr.port())
+    }
+}
+
+impl From<SocketAddr> for ListenAddress {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Tcp(addr)
+    }
+}
+
+impl From<SocketAddrV4> for ListenAddress {
+    fn from(addr: SocketAddrV4) -> Self {
+        Self::Tcp(addr.into())
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => addr.fmt(f),
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// The reading half of a [`Connection`].
+pub type ConnectionReader = Box<dyn AsyncRead + Send + Unpin>;
+/// The writing half of a [`Connection`].
+pub type ConnectionWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// A connection accepted by a [`Transport`].
+pub struct Connection {
+    /// Reads the bytes sent by the client.
+    pub read: ConnectionReader,
+    /// Writes bytes to the client.
+    pub write: ConnectionWriter,
+    /// The address of the remote end of the connection.
+    ///
+    /// Transports without socket addresses for their peers, such as Unix
+    /// domain sockets, use [`UNSPECIFIED_REMOTE_ADDR`].
+    pub remote_addr: SocketAddr,
+}
+
+impl Connection {
+    /// Creates a connection from a bidirectional stream.
+    ///
+    /// Prefer constructing the connection from separate halves directly if
+    /// the stream can be split without locking.
+    pub fn new(
+        stream: impl AsyncRead + AsyncWrite + Send + 'static,
+        remote_addr: SocketAddr,
+    ) -> Self {
+        let (read, write) = tokio::io::split(stream);
+
+        Self {
+            read: Box::new(read),
+            write: Box::new(write),
+            remote_addr,
+        }
+    }
+}
+
+/// The remote address of connections from peers without a socket address.
+pub const UNSPECIFIED_REMOTE_ADDR: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+
+/// Accepts connections carrying the protocol.
+///
+/// This trait uses the [async_trait](crate::async_trait) attribute macro.
+#[async_trait]
+pub trait Transport: Send + Sync + 'static {
+    /// Waits for the next connection.
+    ///
+    /// Errors are logged and the transport is asked for another connection,
+    /// so errors that only affect a single connection should be returned
+    /// here.
+    async fn accept(&self) -> io::Result<Connection>;
+
+    /// Returns the local address of the transport. Connections accepted by
+    /// this transport are reported with this address in
+    /// [`ConnectionInfo::local_addr`] and to
+    /// [`Config::server_list_ping`].
+    ///
+    /// [`ConnectionInfo::local_addr`]: crate::server::ConnectionInfo::local_addr
+    /// [`Config::server_list_ping`]: crate::config::Config::server_list_ping
+    fn local_addr(&self) -> ListenAddress;
+}
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    async fn accept(&self) -> io::Result<Connection> {
+        (**self).accept().await
+    }
+
+    fn local_addr(&self) -> ListenAddress {
+        (**self).local_addr()
+    }
+}
+
+/// The default [`Transport`], accepting TCP connections.
+#[derive(Debug)]
+pub struct TcpTransport {
+    listener: TcpListener,
+    address: SocketAddr,
+}
+
+impl TcpTransport {
+    /// Binds a TCP listener to the given address.
+    pub async fn bind(address: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address).await?,
+            address,
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for TcpTransport {
+    async fn accept(&self) -> io::Result<Connection> {
+        let (stream, remote_addr) = self.listener.accept().await?;
+
+        if let Err(e) = stream.set_nodelay(true) {
+            tracing::error!("failed to set TCP_NODELAY: {e}");
+        }
+
+        let (read, write) = stream.into_split();
+
+        Ok(Connection {
+            read: Box::new(read),
+            write: Box::new(write),
+            remote_addr,
+        })
+    }
+
+    fn local_addr(&self) -> ListenAddress {
+        self.address.into()
+    }
+}
+
+/// A [`Transport`] accepting connections on a Unix domain socket.
+///
+/// The peers of these connections have no socket address, so their
+/// [`Connection::remote_addr`] is [`UNSPECIFIED_REMOTE_ADDR`].
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixTransport {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixTransport {
+    /// Creates a Unix domain socket at the given path and listens on it.
+    ///
+    /// The socket file is removed when the transport is dropped.
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+
+        Ok(Self {
+            listener: UnixListener::bind(&path)?,
+            path,
+        })
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl Transport for UnixTransport {
+    async fn accept(&self) -> io::Result<Connection> {
+        let (stream, _) = self.listener.accept().await?;
+        let (read, write) = stream.into_split();
+
+        Ok(Connection {
+            read: Box::new(read),
+            write: Box::new(write),
+            remote_addr: UNSPECIFIED_REMOTE_ADDR,
+        })
+    }
+
+    fn local_addr(&self) -> ListenAddress {
+        ListenAddress::Unix(self.path.clone())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixTransport {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use anyhow::Context;
+    use flume::Receiver;
+    use futures::future;
+    use tokio::io::DuplexStream;
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::config::{Config, ServerListPing};
+    use crate::protocol::codec::{Decoder, Encoder};
+    use crate::protocol::packets::c2s::handshake::{Handshake, HandshakeNextState};
+    use crate::protocol::packets::c2s::status::QueryRequest;
+    use crate::protocol::packets::s2c::status::QueryResponse;
+    use crate::protocol::{BoundedString, VarInt};
+    use crate::server::{Server, SharedServer};
+    use crate::testing::DEFAULT_TIMEOUT;
+    use crate::PROTOCOL_VERSION;
+
+    /// Responds to status queries with the address a query was received on.
+    struct MultiAddressGame {
+        addresses: Vec<ListenAddress>,
+        pipes: Receiver<DuplexStream>,
+    }
+
+    /// Accepts in-memory connections, reported on port 1.
+    struct PipeTransport {
+        pipes: Receiver<DuplexStream>,
+    }
+
+    #[async_trait]
+    impl Transport for PipeTransport {
+        async fn accept(&self) -> io::Result<Connection> {
+            match self.pipes.recv_async().await {
+                Ok(pipe) => Ok(Connection::new(
+                    pipe,
+                    SocketAddrV4::new(Ipv4Addr::LOCALHOST, 1).into(),
+                )),
+                Err(_) => future::pending().await,
+            }
+        }
+
+        fn local_addr(&self) -> ListenAddress {
+            SocketAddrV4::new(Ipv4Addr::LOCALHOST, 1).into()
+        }
+    }
+
+    #[async_trait]
+    impl Config for MultiAddressGame {
+        type ServerState = Arc<AtomicBool>;
+        type ClientState = ();
+        type EntityState = ();
+        type WorldState = ();
+        type ChunkState = ();
+        type PlayerListState = ();
+
+        fn max_connections(&self) -> usize {
+            10
+        }
+
+        fn addresses(&self) -> Vec<ListenAddress> {
+            self.addresses.clone()
+        }
+
+        fn online_mode(&self) -> bool {
+            false
+        }
+
+        async fn server_list_ping(
+            &self,
+            _shared: &SharedServer<Self>,
+            _remote_addr: SocketAddr,
+            local_addr: &ListenAddress,
+            _protocol_version: i32,
+        ) -> ServerListPing {
+            ServerListPing::Respond {
+                online_players: 0,
+                max_players: 10,
+                player_sample: Vec::new(),
+                description: local_addr.to_string().into(),
+                favicon: None,
+            }
+        }
+
+        fn init(&self, server: &mut Server<Self>) {
+            server.shared.add_transport(PipeTransport {
+                pipes: self.pipes.clone(),
+            });
+        }
+
+        fn update(&self, server: &mut Server<Self>) {
+            if server.state.load(Ordering::SeqCst) {
+                server
+                    .shared
+                    .shutdown::<_, Box<dyn std::error::Error + Send + Sync>>(Ok(()));
+            }
+        }
+    }
+
+    async fn query_status(
+        read: impl AsyncRead + Unpin,
+        write: impl AsyncWrite + Unpin,
+    ) -> anyhow::Result<String> {
+        let mut enc = Encoder::new(write, DEFAULT_TIMEOUT);
+        let mut dec = Decoder::new(read, DEFAULT_TIMEOUT);
+
+        enc.write_packet(&Handshake {
+            protocol_version: VarInt(PROTOCOL_VERSION),
+            server_adddress: BoundedString("localhost".into()),
+            server_port: 25565,
+            next_state: HandshakeNextState::Status,
+        })
+        .await?;
+        enc.write_packet(&QueryRequest {}).await?;
+
+        let QueryResponse { json_response } = dec.read_packet().await?;
+        let json: serde_json::Value = serde_json::from_str(&json_response)?;
+
+        Ok(json["description"]["text"]
+            .as_str()
+            .context("missing description")?
+            .to_owned())
+    }
+
+    #[test]
+    fn status_per_listener() {
+        let tcp_addresses: Vec<SocketAddr> = (0..2)
+            .map(|_| {
+                let listener =
+                    TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+                listener.local_addr().unwrap()
+            })
+            .collect();
+
+        let mut addresses: Vec<ListenAddress> =
+            tcp_addresses.iter().map(|&addr| addr.into()).collect();
+
+        #[cfg(unix)]
+        let socket_path =
+            std::env::temp_dir().join(format!("valence-test-{}.sock", std::process::id()));
+        #[cfg(unix)]
+        addresses.push(ListenAddress::Unix(socket_path.clone()));
+
+        let (pipes_tx, pipes) = flume::unbounded();
+        let stop = Arc::new(AtomicBool::new(false));
+        let server = {
+            let (addresses, stop) = (addresses.clone(), stop.clone());
+            std::thread::spawn(move || {
+                crate::start_server(MultiAddressGame { addresses, pipes }, stop)
+            })
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            for &addr in &tcp_addresses {
+                let description = loop {
+                    let res = match TcpStream::connect(addr).await {
+                        Ok(stream) => {
+                            let (read, write) = stream.into_split();
+                            query_status(read, write).await
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+
+                    match res {
+                        Ok(description) => break description,
+                        Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+                    }
+                };
+
+                assert_eq!(description, addr.to_string());
+            }
+
+            #[cfg(unix)]
+            {
+                let stream = tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+                let (read, write) = stream.into_split();
+                assert_eq!(
+                    query_status(read, write).await.unwrap(),
+                    format!("unix:{}", socket_path.display())
+                );
+            }
+
+            let (client, server) = tokio::io::duplex(1024);
+            pipes_tx.send(server).unwrap();
+
+            let (read, write) = tokio::io::split(client);
+            assert_eq!(query_status(read, write).await.unwrap(), "127.0.0.1:1");
+        });
+
+        stop.store(true, Ordering::SeqCst);
+        server.join().unwrap().unwrap();
+
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(socket_path);
+    }
+}
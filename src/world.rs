@@ -1,15 +1,26 @@
 //! A space on a server for objects to occupy.
 
+use std::collections::BTreeSet;
 use std::iter::FusedIterator;
+use std::mem;
 
 use rayon::iter::ParallelIterator;
+use uuid::Uuid;
+use vek::Vec3;
 
-use crate::chunk::Chunks;
+use crate::block_pos::BlockPos;
+use crate::chunk::{ChunkPos, ChunkTicketId, Chunks};
 use crate::config::Config;
 use crate::dimension::DimensionId;
-use crate::server::SharedServer;
+use crate::entity::EntityId;
+use crate::firework::Firework;
+use crate::generator::Generator;
+use crate::nbt::Compound;
+use crate::protection::Protection;
+use crate::server::{SharedServer, MAX_VIEW_DISTANCE};
 use crate::slab_versioned::{Key, VersionedSlab};
 use crate::spatial_index::SpatialIndex;
+use crate::Ticks;
 
 /// A container for all [`World`]s on a [`Server`](crate::server::Server).
 pub struct Worlds<C: Config> {
@@ -48,8 +59,22 @@ impl<C: Config> Worlds<C> {
         let (id, world) = self.slab.insert(World {
             state,
             spatial_index: SpatialIndex::new(),
+            entities: BTreeSet::new(),
             chunks: Chunks::new(self.shared.clone(), dim),
-            meta: WorldMeta { dimension: dim },
+            meta: WorldMeta {
+                dimension: dim,
+                rain_level: 0.0,
+                rain_level_modified: false,
+                thunder_level: 0.0,
+                thunder_level_modified: false,
+                lightning_strikes: Vec::new(),
+                fireworks: Vec::new(),
+                next_network_id: -1,
+                level_events: Vec::new(),
+                flat: false,
+                hardcore: false,
+            },
+            protection: Protection::new(),
         });
 
         (WorldId(id), world)
@@ -57,7 +82,8 @@ impl<C: Config> Worlds<C> {
 
     /// Deletes a world from the server.
     ///
-    /// Note that any entities located in the world are not deleted.
+    /// Note that any entities located in the world are not deleted. They are
+    /// moved to [`WorldId::NULL`] instead.
     /// Additionally, clients that are still in the deleted world at the end
     /// of the tick are disconnected.
     ///
@@ -131,15 +157,363 @@ pub struct World<C: Config> {
     pub state: C::WorldState,
     /// Contains all of the entities in this world.
     pub spatial_index: SpatialIndex,
+    /// The entities in this world, see [`World::entities`].
+    pub(crate) entities: BTreeSet<EntityId>,
     /// All of the chunks in this world.
     pub chunks: Chunks<C>,
     /// This world's metadata.
     pub meta: WorldMeta,
+    /// The protected regions of this world.
+    pub protection: Protection,
+}
+
+impl<C: Config> World<C> {
+    /// Sets the generator used to fill chunks created with
+    /// [`Chunks::insert`] from now on. Existing chunks are not changed.
+    ///
+    /// This also determines if the world is shown as superflat, see
+    /// [`WorldMeta::is_flat`]. Passing `None` makes new chunks empty.
+    pub fn set_generator(&mut self, generator: Option<Generator>) {
+        self.meta.flat = generator.as_ref().is_some_and(Generator::is_flat);
+        self.chunks.set_generator(generator);
+    }
+
+    /// Returns an iterator over the IDs of the entities in this world, ordered
+    /// by [`EntityId`]. Only these entities are part of the world's
+    /// [`SpatialIndex`].
+    ///
+    /// Like the spatial index, the entities of the world are only updated once
+    /// per tick, after [`Config::update`]. Entities added to or moved out of
+    /// the world in the meantime are not reflected until the next tick.
+    ///
+    /// [`Config::update`]: crate::config::Config::update
+    pub fn entities(&self) -> impl ExactSizeIterator<Item = EntityId> + FusedIterator + Clone + '_ {
+        self.entities.iter().copied()
+    }
+
+    /// Returns `true` if the entity with the given ID is in this world. See
+    /// [`Self::entities`].
+    pub fn contains_entity(&self, entity: EntityId) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    /// Returns an estimate of the memory used by this world in bytes. See
+    /// [`Chunks::memory_usage`].
+    pub fn memory_usage(&self) -> usize {
+        mem::size_of::<C::WorldState>()
+            + mem::size_of::<SpatialIndex>()
+            + mem::size_of::<WorldMeta>()
+            + self.chunks.memory_usage()
+    }
+
+    /// Adds a ticket keeping the chunks within `radius` chunks of `pos`
+    /// loaded, and returns its ID.
+    ///
+    /// Missing chunks covered by a ticket are created with the world's
+    /// [generator](Self::set_generator) and the default chunk state before
+    /// clients are updated. Once no ticket covers them anymore, they are
+    /// removed again, along with any changes made to them. Chunks inserted
+    /// with [`Chunks::insert`] are never removed automatically.
+    ///
+    /// The radius is clamped to the maximum view distance of 32 chunks. If
+    /// `ttl` is `Some`, the ticket is removed after that many ticks.
+    pub fn add_ticket(
+        &mut self,
+        pos: impl Into<ChunkPos>,
+        radius: u32,
+        ttl: Option<Ticks>,
+    ) -> ChunkTicketId {
+        let radius = radius.min(MAX_VIEW_DISTANCE.into());
+        self.chunks.add_ticket(pos.into(), radius, ttl)
+    }
+
+    /// Removes a ticket added with [`Self::add_ticket`]. Returns `true` if the
+    /// ticket existed.
+    pub fn remove_ticket(&mut self, id: ChunkTicketId) -> bool {
+        self.chunks.remove_ticket(id)
+    }
+
+    /// Gets if clients in this world hold a ticket for the chunks in their
+    /// view distance. This is `false` by default.
+    pub fn has_player_tickets(&self) -> bool {
+        self.chunks.player_tickets()
+    }
+
+    /// Sets if clients in this world hold a ticket for the chunks in their
+    /// view distance, so that the chunks around them are loaded and unloaded
+    /// automatically. See [`Self::add_ticket`].
+    pub fn set_player_tickets(&mut self, enabled: bool) {
+        self.chunks.set_player_tickets(enabled);
+    }
+
+    /// Strikes a bolt of lightning at the given position.
+    ///
+    /// The lightning bolt is sent to every client in this world regardless of
+    /// its distance to the strike, like in vanilla. Clients play the thunder
+    /// and impact sounds and remove the bolt on their own, so the bolt is not
+    /// added to [`Entities`](crate::entity::Entities).
+    pub fn strike_lightning(&mut self, pos: impl Into<Vec3<f64>>) {
+        let network_id = self.meta.next_network_id();
+
+        self.meta.lightning_strikes.push(LightningStrike {
+            network_id,
+            uuid: Uuid::from_u128(rand::random()),
+            position: pos.into(),
+        });
+    }
+
+    /// Launches a firework rocket from the given position, which detonates
+    /// after its [lifetime](Firework::lifetime).
+    ///
+    /// Like lightning bolts, rockets are sent to every client in this world
+    /// and are not added to [`Entities`](crate::entity::Entities). Clients
+    /// simulate the flight of the rocket themselves, so it explodes wherever
+    /// it is on each client when its lifetime is over.
+    pub fn launch_firework(&mut self, pos: impl Into<Vec3<f64>>, firework: &Firework) {
+        self.launch_firework_with_lifetime(pos, firework, firework.lifetime());
+    }
+
+    /// Like [`Self::launch_firework`], but the rocket detonates after exactly
+    /// `lifetime` ticks.
+    pub fn launch_firework_with_lifetime(
+        &mut self,
+        pos: impl Into<Vec3<f64>>,
+        firework: &Firework,
+        lifetime: Ticks,
+    ) {
+        let position = pos.into();
+        let network_id = self.meta.next_network_id();
+
+        self.meta.fireworks.push(LaunchedFirework {
+            network_id,
+            uuid: Uuid::from_u128(rand::random()),
+            position,
+            nbt: firework.to_nbt(),
+            launched: true,
+            ticks_left: lifetime.max(0),
+        });
+
+        self.play_level_event(BlockPos::at(position), LevelEvent::FireworkShot, 0);
+    }
+
+    /// Plays a sound or particle effect at the given block position.
+    ///
+    /// `data` is interpreted depending on the event. For instance, it is the
+    /// [block state](crate::block::BlockState) ID for
+    /// [`LevelEvent::BlockBreak`] and the direction for
+    /// [`LevelEvent::Smoke`]. Most events ignore it.
+    ///
+    /// [Global](LevelEvent::is_global) events are heard by every client in
+    /// this world. Other events are sent to clients with the position in
+    /// view.
+    pub fn play_level_event(&mut self, pos: impl Into<BlockPos>, event: LevelEvent, data: i32) {
+        self.meta.level_events.push(QueuedLevelEvent {
+            event,
+            position: pos.into(),
+            data,
+        });
+    }
 }
 
 /// Contains miscellaneous data about the world.
 pub struct WorldMeta {
     dimension: DimensionId,
+    rain_level: f32,
+    rain_level_modified: bool,
+    thunder_level: f32,
+    thunder_level_modified: bool,
+    lightning_strikes: Vec<LightningStrike>,
+    fireworks: Vec<LaunchedFirework>,
+    /// The network ID of the next lightning bolt or firework rocket. These
+    /// use negative network IDs so they never collide with regular entities.
+    next_network_id: i32,
+    level_events: Vec<QueuedLevelEvent>,
+    flat: bool,
+    hardcore: bool,
+}
+
+/// A lightning bolt queued by [`World::strike_lightning`] during the current
+/// tick.
+pub(crate) struct LightningStrike {
+    pub network_id: i32,
+    pub uuid: Uuid,
+    pub position: Vec3<f64>,
+}
+
+/// A firework rocket launched with [`World::launch_firework`] which has not
+/// detonated yet.
+pub(crate) struct LaunchedFirework {
+    pub network_id: i32,
+    pub uuid: Uuid,
+    pub position: Vec3<f64>,
+    /// The `Fireworks` compound of the rocket.
+    pub nbt: Compound,
+    /// If the rocket was launched this tick.
+    pub launched: bool,
+    /// The rocket detonates when this reaches zero.
+    pub ticks_left: Ticks,
+}
+
+/// A level event queued by [`World::play_level_event`] during the current tick.
+pub(crate) struct QueuedLevelEvent {
+    pub event: LevelEvent,
+    pub position: BlockPos,
+    pub data: i32,
+}
+
+/// A sound or particle effect played with [`World::play_level_event`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LevelEvent {
+    DispenserDispense,
+    DispenserFail,
+    DispenserShoot,
+    EnderEyeLaunch,
+    FireworkShot,
+    IronDoorOpen,
+    WoodenDoorOpen,
+    WoodenTrapdoorOpen,
+    FenceGateOpen,
+    FireExtinguish,
+    /// Plays a music disc. `data` is the item ID of the disc, or 0 to stop
+    /// playing.
+    PlayRecord,
+    IronDoorClose,
+    WoodenDoorClose,
+    WoodenTrapdoorClose,
+    FenceGateClose,
+    GhastWarn,
+    GhastShoot,
+    EnderDragonShoot,
+    BlazeShoot,
+    ZombieAttackWoodenDoor,
+    ZombieAttackIronDoor,
+    ZombieBreakWoodenDoor,
+    WitherBreakBlock,
+    WitherSpawn,
+    WitherShoot,
+    BatTakeoff,
+    ZombieInfect,
+    ZombieVillagerConverted,
+    EnderDragonDeath,
+    AnvilDestroy,
+    AnvilUse,
+    AnvilLand,
+    PortalTravel,
+    ChorusFlowerGrow,
+    ChorusFlowerDeath,
+    BrewingStandBrew,
+    IronTrapdoorClose,
+    IronTrapdoorOpen,
+    EndPortalCreated,
+    PhantomBite,
+    ZombieConvertedToDrowned,
+    HuskConvertedToZombie,
+    GrindstoneUse,
+    BookPageTurn,
+    SmithingTableUse,
+    /// Smoke particles. `data` is the direction: 0 down, 1 up, 2 north, 3
+    /// south, 4 west and 5 east.
+    Smoke,
+    /// Block break particles and sound. `data` is the block state ID.
+    BlockBreak,
+    /// Splash potion particles. `data` is the RGB color.
+    SplashPotion,
+    EyeOfEnderBreak,
+    SpawnerParticles,
+    /// Bone meal particles. `data` is the number of particles.
+    BoneMeal,
+    DragonBreath,
+    /// Instant splash potion particles. `data` is the RGB color.
+    InstantSplashPotion,
+    EnderDragonDestroyBlock,
+    WetSpongeVaporize,
+    EndGatewaySpawn,
+    EnderDragonGrowl,
+    ElectricSpark,
+    WaxOn,
+    WaxOff,
+    Scrape,
+    /// An event by its protocol ID, for events without a variant.
+    Other(i32),
+}
+
+impl LevelEvent {
+    /// Returns the protocol ID of this event.
+    pub fn id(self) -> i32 {
+        match self {
+            LevelEvent::DispenserDispense => 1000,
+            LevelEvent::DispenserFail => 1001,
+            LevelEvent::DispenserShoot => 1002,
+            LevelEvent::EnderEyeLaunch => 1003,
+            LevelEvent::FireworkShot => 1004,
+            LevelEvent::IronDoorOpen => 1005,
+            LevelEvent::WoodenDoorOpen => 1006,
+            LevelEvent::WoodenTrapdoorOpen => 1007,
+            LevelEvent::FenceGateOpen => 1008,
+            LevelEvent::FireExtinguish => 1009,
+            LevelEvent::PlayRecord => 1010,
+            LevelEvent::IronDoorClose => 1011,
+            LevelEvent::WoodenDoorClose => 1012,
+            LevelEvent::WoodenTrapdoorClose => 1013,
+            LevelEvent::FenceGateClose => 1014,
+            LevelEvent::GhastWarn => 1015,
+            LevelEvent::GhastShoot => 1016,
+            LevelEvent::EnderDragonShoot => 1017,
+            LevelEvent::BlazeShoot => 1018,
+            LevelEvent::ZombieAttackWoodenDoor => 1019,
+            LevelEvent::ZombieAttackIronDoor => 1020,
+            LevelEvent::ZombieBreakWoodenDoor => 1021,
+            LevelEvent::WitherBreakBlock => 1022,
+            LevelEvent::WitherSpawn => 1023,
+            LevelEvent::WitherShoot => 1024,
+            LevelEvent::BatTakeoff => 1025,
+            LevelEvent::ZombieInfect => 1026,
+            LevelEvent::ZombieVillagerConverted => 1027,
+            LevelEvent::EnderDragonDeath => 1028,
+            LevelEvent::AnvilDestroy => 1029,
+            LevelEvent::AnvilUse => 1030,
+            LevelEvent::AnvilLand => 1031,
+            LevelEvent::PortalTravel => 1032,
+            LevelEvent::ChorusFlowerGrow => 1033,
+            LevelEvent::ChorusFlowerDeath => 1034,
+            LevelEvent::BrewingStandBrew => 1035,
+            LevelEvent::IronTrapdoorClose => 1036,
+            LevelEvent::IronTrapdoorOpen => 1037,
+            LevelEvent::EndPortalCreated => 1038,
+            LevelEvent::PhantomBite => 1039,
+            LevelEvent::ZombieConvertedToDrowned => 1040,
+            LevelEvent::HuskConvertedToZombie => 1041,
+            LevelEvent::GrindstoneUse => 1042,
+            LevelEvent::BookPageTurn => 1043,
+            LevelEvent::SmithingTableUse => 1044,
+            LevelEvent::Smoke => 2000,
+            LevelEvent::BlockBreak => 2001,
+            LevelEvent::SplashPotion => 2002,
+            LevelEvent::EyeOfEnderBreak => 2003,
+            LevelEvent::SpawnerParticles => 2004,
+            LevelEvent::BoneMeal => 2005,
+            LevelEvent::DragonBreath => 2006,
+            LevelEvent::InstantSplashPotion => 2007,
+            LevelEvent::EnderDragonDestroyBlock => 2008,
+            LevelEvent::WetSpongeVaporize => 2009,
+            LevelEvent::EndGatewaySpawn => 3000,
+            LevelEvent::EnderDragonGrowl => 3001,
+            LevelEvent::ElectricSpark => 3002,
+            LevelEvent::WaxOn => 3003,
+            LevelEvent::WaxOff => 3004,
+            LevelEvent::Scrape => 3005,
+            LevelEvent::Other(id) => id,
+        }
+    }
+
+    /// Returns `true` if the sound of this event is heard at the same volume
+    /// everywhere in the world, like the wither spawning.
+    pub fn is_global(self) -> bool {
+        matches!(
+            self,
+            LevelEvent::WitherSpawn | LevelEvent::EnderDragonDeath | LevelEvent::EndPortalCreated
+        )
+    }
 }
 
 impl WorldMeta {
@@ -147,4 +521,285 @@ impl WorldMeta {
     pub fn dimension(&self) -> DimensionId {
         self.dimension
     }
+
+    /// Gets if clients see the world as superflat. Superflat worlds have a
+    /// horizon line lower than normal worlds and no void fog.
+    ///
+    /// This is set by [`World::set_generator`] and defaults to `false`.
+    pub fn is_flat(&self) -> bool {
+        self.flat
+    }
+
+    /// Sets if clients see the world as superflat, overriding the value set
+    /// by [`World::set_generator`].
+    ///
+    /// Clients must be (re)spawned in the world for changes to take effect.
+    pub fn set_flat(&mut self, flat: bool) {
+        self.flat = flat;
+    }
+
+    /// Gets if the world is played in hardcore mode. Clients joining the
+    /// server in a hardcore world are in hardcore mode, see
+    /// [`Client::set_hardcore`]. The default is `false`.
+    ///
+    /// [`Client::set_hardcore`]: crate::client::Client::set_hardcore
+    pub fn is_hardcore(&self) -> bool {
+        self.hardcore
+    }
+
+    /// Sets if the world is played in hardcore mode.
+    ///
+    /// Clients keep the mode of the world they joined in until they
+    /// reconnect, so this only affects clients joining from now on. Hardcore
+    /// and regular worlds should not be mixed on a server where clients move
+    /// between worlds.
+    pub fn set_hardcore(&mut self, hardcore: bool) {
+        self.hardcore = hardcore;
+    }
+
+    /// Gets the rain level of the world. This is in `0.0..=1.0`.
+    pub fn rain_level(&self) -> f32 {
+        self.rain_level
+    }
+
+    /// Sets the rain level of the world. The value is clamped to `0.0..=1.0`.
+    ///
+    /// Clients in the world see rain or snow, depending on the
+    /// [precipitation](crate::biome::Biome::precipitation) of the biome, while
+    /// the rain level is above zero. Clients entering the world are sent the
+    /// current rain and thunder levels.
+    pub fn set_rain_level(&mut self, rain_level: f32) {
+        let rain_level = rain_level.clamp(0.0, 1.0);
+
+        if self.rain_level != rain_level {
+            self.rain_level = rain_level;
+            self.rain_level_modified = true;
+        }
+    }
+
+    pub(crate) fn rain_level_modified(&self) -> bool {
+        self.rain_level_modified
+    }
+
+    /// Gets the thunder level of the world. This is in `0.0..=1.0`.
+    pub fn thunder_level(&self) -> f32 {
+        self.thunder_level
+    }
+
+    /// Sets the thunder level of the world. The value is clamped to
+    /// `0.0..=1.0`.
+    ///
+    /// The thunder level darkens the sky of clients in the world, but only
+    /// while it is raining.
+    pub fn set_thunder_level(&mut self, thunder_level: f32) {
+        let thunder_level = thunder_level.clamp(0.0, 1.0);
+
+        if self.thunder_level != thunder_level {
+            self.thunder_level = thunder_level;
+            self.thunder_level_modified = true;
+        }
+    }
+
+    pub(crate) fn thunder_level_modified(&self) -> bool {
+        self.thunder_level_modified
+    }
+
+    pub(crate) fn lightning_strikes(&self) -> &[LightningStrike] {
+        &self.lightning_strikes
+    }
+
+    pub(crate) fn level_events(&self) -> &[QueuedLevelEvent] {
+        &self.level_events
+    }
+
+    pub(crate) fn fireworks(&self) -> &[LaunchedFirework] {
+        &self.fireworks
+    }
+
+    fn next_network_id(&mut self) -> i32 {
+        let network_id = self.next_network_id;
+        self.next_network_id = network_id.checked_sub(1).unwrap_or(-1);
+        network_id
+    }
+
+    /// Clears the changes made to the world's metadata this tick.
+    pub(crate) fn update(&mut self) {
+        self.rain_level_modified = false;
+        self.thunder_level_modified = false;
+        self.lightning_strikes.clear();
+        self.level_events.clear();
+        self.fireworks.retain_mut(|fw| {
+            fw.launched = false;
+            fw.ticks_left -= 1;
+            fw.ticks_left >= 0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::packets::s2c::play::{GameStateChangeReason, S2cPlayPacket};
+    use crate::testing::{test_server, MockClient};
+
+    #[test]
+    fn weather_sent_on_entering_world() {
+        let mut server = test_server();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.meta.set_rain_level(0.5);
+        world.meta.set_thunder_level(1.0);
+        server.tick();
+
+        let weather = |client: &MockClient| -> Vec<_> {
+            client
+                .take_packets()
+                .into_iter()
+                .filter_map(|pkt| match pkt {
+                    S2cPlayPacket::GameStateChange(p) => Some((p.reason as u8, p.value)),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let client = server.connect("tester");
+        server.tick();
+        assert_eq!(
+            weather(&client),
+            [
+                (GameStateChangeReason::BeginRaining as u8, 0.0),
+                (GameStateChangeReason::RainLevelChange as u8, 0.5),
+                (GameStateChangeReason::ThunderLevelChange as u8, 1.0),
+            ]
+        );
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.meta.set_rain_level(0.0);
+        server.tick();
+        assert_eq!(
+            weather(&client),
+            [
+                (GameStateChangeReason::EndRaining as u8, 0.0),
+                (GameStateChangeReason::RainLevelChange as u8, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn hardcore_world_and_respawn_screen() {
+        let mut server = test_server();
+
+        let (world_id, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.meta.set_hardcore(true);
+
+        let client = server.connect("tester");
+        server.tick();
+
+        let join = client
+            .take_packets()
+            .into_iter()
+            .find_map(|pkt| match pkt {
+                S2cPlayPacket::GameJoin(p) => Some(p),
+                _ => None,
+            })
+            .unwrap();
+        assert!(join.is_hardcore);
+        assert!(!join.enable_respawn_screen);
+
+        let c = server.server_mut().clients.get_mut(client.id()).unwrap();
+        assert!(c.is_hardcore());
+        // The hardcore mode can't change after joining.
+        c.set_hardcore(false);
+        assert!(c.is_hardcore());
+        c.set_respawn_screen(true);
+        c.spawn(world_id);
+        server.tick();
+
+        let pkts = client.take_packets();
+        assert!(pkts.iter().any(|pkt| matches!(
+            pkt,
+            S2cPlayPacket::GameStateChange(p)
+                if matches!(p.reason, GameStateChangeReason::EnableRespawnScreen) && p.value == 0.0
+        )));
+
+        let c = server.server().clients.get(client.id()).unwrap();
+        assert!(c.is_hardcore());
+        assert!(c.has_respawn_screen());
+    }
+
+    #[test]
+    fn chunk_tickets_load_and_unload() {
+        let mut server = test_server();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.add_ticket([10, 10], 1, Some(2));
+        server.tick();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        assert_eq!(world.chunks.len(), 16 + 5);
+        assert!(world.chunks.get([11, 10]).is_some());
+        assert!(world.chunks.get([11, 11]).is_none());
+
+        server.tick();
+        server.tick();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        assert_eq!(world.chunks.len(), 16);
+        assert!(world.chunks.get([10, 10]).is_none());
+
+        let ticket = world.add_ticket([0, 0], 3, None);
+        server.tick();
+
+        // Chunks inserted manually are kept when the ticket is removed.
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        assert!(world.chunks.get([3, 0]).is_some());
+        assert!(world.remove_ticket(ticket));
+        assert!(!world.remove_ticket(ticket));
+        server.tick();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        assert_eq!(world.chunks.len(), 16);
+    }
+
+    #[test]
+    fn world_memory_usage_grows_with_chunks() {
+        let mut server = test_server();
+        server.tick();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        let before = world.memory_usage();
+        assert!(before > 16 * 4096);
+
+        for x in 10..20 {
+            world.chunks.insert([x, 0], ());
+        }
+        assert!(world.memory_usage() > before);
+
+        server.tick();
+        let metrics = server.server().shared.metrics();
+        assert_eq!(metrics.chunks(), 26);
+        assert!(metrics.world_memory_usage() > before as u64);
+    }
+
+    #[test]
+    fn player_tickets_load_chunks_in_view() {
+        let mut server = test_server();
+
+        let (_, world) = server.server_mut().worlds.iter_mut().next().unwrap();
+        world.set_player_tickets(true);
+
+        let _client = server.connect("tester");
+        server.tick();
+
+        let (_, client) = server.server_mut().clients.iter_mut().next().unwrap();
+        let dist = client.view_distance() as i32;
+        client.teleport([1000.0, 100.0, 0.0], 0.0, 0.0);
+        server.tick();
+
+        let (_, world) = server.server().worlds.iter().next().unwrap();
+        assert!(world.chunks.get([62 + dist, 0]).is_some());
+        assert!(world.chunks.get([62, dist]).is_some());
+        assert!(world.chunks.get([62 - dist, 0]).is_some());
+        assert!(world.chunks.get([dist, 0]).is_none());
+        assert!(world.chunks.get([0, 0]).is_some());
+    }
 }
@@ -0,0 +1,490 @@
+//! Noise-based terrain generation.
+//!
+//! This module is available with the `worldgen` feature. It contains the
+//! building blocks of simple procedural terrain:
+//!
+//! - [`LayeredNoise`] sums several octaves of Perlin or simplex noise.
+//! - [`Spline`] shapes noise values into terrain heights.
+//! - [`BiomePlacement`] picks biomes from temperature and humidity noise.
+//! - [`TerrainGenerator`] combines the above to generate [`UnloadedChunk`]s,
+//!   which are added to a world with [`Chunks::insert_with`].
+//!
+//! [`Chunks::insert_with`]: crate::chunk::Chunks::insert_with
+
+use noise::{NoiseFn, Seedable, SuperSimplex};
+
+use crate::biome::BiomeId;
+use crate::block::BlockState;
+use crate::chunk::{ChunkPos, UnloadedChunk};
+
+/// Fractal noise made by summing octaves of a noise function with increasing
+/// frequency and decreasing amplitude.
+///
+/// The output is normalized to roughly `-1.0..=1.0`.
+#[derive(Clone, Debug)]
+pub struct LayeredNoise {
+    source: NoiseSource,
+    octaves: u32,
+    frequency: f64,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+#[derive(Clone, Debug)]
+enum NoiseSource {
+    Perlin(Perlin),
+    Simplex(Box<SuperSimplex>),
+}
+
+impl LayeredNoise {
+    /// Creates layered Perlin noise with the given seed.
+    pub fn perlin(seed: u32) -> Self {
+        Self::with_source(NoiseSource::Perlin(Perlin::new(seed)))
+    }
+
+    /// Creates layered simplex noise with the given seed.
+    pub fn simplex(seed: u32) -> Self {
+        Self::with_source(NoiseSource::Simplex(Box::new(
+            SuperSimplex::new().set_seed(seed),
+        )))
+    }
+
+    fn with_source(source: NoiseSource) -> Self {
+        Self {
+            source,
+            octaves: 4,
+            frequency: 1.0 / 128.0,
+            lacunarity: 2.0,
+            persistence: 0.5,
+        }
+    }
+
+    /// Sets the number of octaves. More octaves add finer detail. The default
+    /// is 4.
+    #[must_use]
+    pub fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    /// Sets the frequency of the first octave in cycles per block. The
+    /// default is `1.0 / 128.0`.
+    #[must_use]
+    pub fn frequency(mut self, frequency: f64) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Sets the factor the frequency is multiplied by for each octave. The
+    /// default is 2.
+    #[must_use]
+    pub fn lacunarity(mut self, lacunarity: f64) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    /// Sets the factor the amplitude is multiplied by for each octave. The
+    /// default is 0.5.
+    #[must_use]
+    pub fn persistence(mut self, persistence: f64) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    /// Samples the noise at a horizontal position.
+    pub fn get_2d(&self, x: f64, z: f64) -> f64 {
+        self.fractal(|freq| match &self.source {
+            NoiseSource::Perlin(n) => n.get([x * freq, 0.0, z * freq]),
+            NoiseSource::Simplex(n) => n.get([x * freq, z * freq]),
+        })
+    }
+
+    /// Samples the noise at a position.
+    pub fn get_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+        self.fractal(|freq| match &self.source {
+            NoiseSource::Perlin(n) => n.get([x * freq, y * freq, z * freq]),
+            NoiseSource::Simplex(n) => n.get([x * freq, y * freq, z * freq]),
+        })
+    }
+
+    fn fractal(&self, mut sample: impl FnMut(f64) -> f64) -> f64 {
+        let mut freq = self.frequency;
+        let mut amp = 1.0;
+        let mut amp_sum = 0.0;
+        let mut sum = 0.0;
+
+        for _ in 0..self.octaves {
+            sum += sample(freq) * amp;
+            amp_sum += amp;
+            freq *= self.lacunarity;
+            amp *= self.persistence;
+        }
+
+        sum / amp_sum
+    }
+}
+
+/// Improved Perlin noise.
+///
+/// This is implemented here because the `Perlin` type of the `noise` crate
+/// cannot be named unambiguously.
+#[derive(Clone, Debug)]
+struct Perlin {
+    /// A permutation of `0..256`, repeated twice to avoid wrapping indices.
+    perm: Box<[u8; 512]>,
+}
+
+impl Perlin {
+    fn new(seed: u32) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+
+        // Fisher-Yates shuffle driven by a xorshift generator so the
+        // permutation only depends on the seed.
+        let mut state = u64::from(seed) ^ 0x9e37_79b9_7f4a_7c15;
+        for i in (1..table.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            table.swap(i, (state % (i as u64 + 1)) as usize);
+        }
+
+        Self {
+            perm: Box::new(std::array::from_fn(|i| table[i % 256])),
+        }
+    }
+
+    fn get(&self, [x, y, z]: [f64; 3]) -> f64 {
+        let p = &self.perm;
+
+        let (xf, yf, zf) = (x.floor(), y.floor(), z.floor());
+        let (xi, yi, zi) = (
+            xf.rem_euclid(256.0) as usize,
+            yf.rem_euclid(256.0) as usize,
+            zf.rem_euclid(256.0) as usize,
+        );
+        let (x, y, z) = (x - xf, y - yf, z - zf);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a = p[xi] as usize + yi;
+        let aa = p[a] as usize + zi;
+        let ab = p[a + 1] as usize + zi;
+        let b = p[xi + 1] as usize + yi;
+        let ba = p[b] as usize + zi;
+        let bb = p[b + 1] as usize + zi;
+
+        let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// A piecewise linear curve through a list of control points, used to map
+/// noise values to terrain heights.
+///
+/// Inputs outside the range of the control points are clamped to the first
+/// or last point.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Spline {
+    points: Vec<(f64, f64)>,
+}
+
+impl Spline {
+    /// Creates a spline from `(input, output)` control points. The points are
+    /// sorted by their input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `points` is empty.
+    pub fn new(points: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        let mut points: Vec<_> = points.into_iter().collect();
+        assert!(!points.is_empty(), "spline must have a control point");
+
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { points }
+    }
+
+    /// Gets the output of the spline for an input.
+    pub fn eval(&self, x: f64) -> f64 {
+        let idx = self.points.partition_point(|&(px, _)| px < x);
+
+        if idx == 0 {
+            return self.points[0].1;
+        }
+
+        let (x0, y0) = self.points[idx - 1];
+
+        match self.points.get(idx) {
+            Some(&(x1, y1)) => y0 + (y1 - y0) * (x - x0) / (x1 - x0),
+            None => y0,
+        }
+    }
+}
+
+/// Describes how and under which climate a biome is placed by a
+/// [`BiomePlacement`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BiomeSettings {
+    /// The biome to place.
+    pub biome: BiomeId,
+    /// The preferred temperature of the biome in `-1.0..=1.0`.
+    pub temperature: f64,
+    /// The preferred humidity of the biome in `-1.0..=1.0`.
+    pub humidity: f64,
+    /// The top block of the terrain above water, such as grass or sand.
+    pub surface: BlockState,
+    /// The blocks below the surface and the top of underwater terrain.
+    pub subsurface: BlockState,
+}
+
+/// Places biomes by comparing temperature and humidity noise against the
+/// preferred climate of each biome.
+#[derive(Clone, Debug)]
+pub struct BiomePlacement {
+    temperature: LayeredNoise,
+    humidity: LayeredNoise,
+    biomes: Vec<BiomeSettings>,
+}
+
+impl BiomePlacement {
+    /// Creates a biome placement helper for the given biomes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `biomes` is empty.
+    pub fn new(seed: u32, biomes: Vec<BiomeSettings>) -> Self {
+        assert!(!biomes.is_empty(), "biome placement must have a biome");
+
+        let climate = |seed| {
+            LayeredNoise::simplex(seed)
+                .octaves(2)
+                .frequency(1.0 / 512.0)
+        };
+
+        Self {
+            temperature: climate(seed),
+            humidity: climate(seed.wrapping_add(1)),
+            biomes,
+        }
+    }
+
+    /// Gets the biome whose preferred climate is closest to the climate at a
+    /// horizontal block position.
+    pub fn get(&self, x: f64, z: f64) -> &BiomeSettings {
+        let temperature = self.temperature.get_2d(x, z);
+        let humidity = self.humidity.get_2d(x, z);
+
+        let distance = |b: &BiomeSettings| {
+            (b.temperature - temperature).powi(2) + (b.humidity - humidity).powi(2)
+        };
+
+        self.biomes
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .expect("biome placement must have a biome")
+    }
+}
+
+/// Generates terrain from a height map shaped by a [`Spline`], with biomes
+/// from a [`BiomePlacement`].
+///
+/// Terrain below the sea level is covered with water.
+///
+/// ```ignore
+/// let chunk = generator.generate(pos, dimension.min_y, dimension.section_count());
+/// world.chunks.insert_with(pos, &chunk, ());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TerrainGenerator {
+    /// The noise sampled at each column to determine its height.
+    pub height_noise: LayeredNoise,
+    /// Maps values of the height noise to the y coordinate of the surface.
+    pub height_spline: Spline,
+    /// Picks the biome and surface blocks of each column.
+    pub biomes: BiomePlacement,
+    /// The y coordinate of the top of the water.
+    pub sea_level: i32,
+    /// The number of subsurface blocks between the surface and stone.
+    pub subsurface_depth: i32,
+}
+
+impl TerrainGenerator {
+    /// Creates a terrain generator with a default height map, sea level and
+    /// the given biomes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `biomes` is empty.
+    pub fn new(seed: u32, biomes: Vec<BiomeSettings>) -> Self {
+        Self {
+            height_noise: LayeredNoise::simplex(seed)
+                .octaves(5)
+                .frequency(1.0 / 256.0),
+            height_spline: Spline::new([
+                (-1.0, 30.0),
+                (-0.3, 55.0),
+                (0.0, 64.0),
+                (0.4, 80.0),
+                (1.0, 140.0),
+            ]),
+            biomes: BiomePlacement::new(seed.wrapping_add(2), biomes),
+            sea_level: 62,
+            subsurface_depth: 3,
+        }
+    }
+
+    /// Gets the y coordinate of the surface at a horizontal block position.
+    pub fn height_at(&self, x: i32, z: i32) -> i32 {
+        self.height_spline
+            .eval(self.height_noise.get_2d(x as f64, z as f64))
+            .round() as i32
+    }
+
+    /// Generates the chunk at `pos` in a dimension whose lowest block is at
+    /// `min_y` and which is `section_count` chunk sections tall.
+    pub fn generate(&self, pos: ChunkPos, min_y: i32, section_count: usize) -> UnloadedChunk {
+        let mut columns = [(0, BlockState::AIR, BlockState::AIR); 16 * 16];
+
+        for (i, col) in columns.iter_mut().enumerate() {
+            let x = pos.x * 16 + (i % 16) as i32;
+            let z = pos.z * 16 + (i / 16) as i32;
+            let biome = self.biomes.get(x as f64, z as f64);
+
+            *col = (self.height_at(x, z), biome.surface, biome.subsurface);
+        }
+
+        let mut chunk = UnloadedChunk::from_fn(section_count, |x, y, z| {
+            let (height, surface, subsurface) = columns[x + z * 16];
+            let y = y as i32 + min_y;
+
+            if y > height {
+                if y <= self.sea_level {
+                    BlockState::WATER
+                } else {
+                    BlockState::AIR
+                }
+            } else if y == height && height >= self.sea_level {
+                surface
+            } else if y > height - self.subsurface_depth - 1 {
+                subsurface
+            } else {
+                BlockState::STONE
+            }
+        });
+
+        for bz in 0..4 {
+            for bx in 0..4 {
+                let x = pos.x * 16 + bx as i32 * 4 + 2;
+                let z = pos.z * 16 + bz as i32 * 4 + 2;
+                let biome = self.biomes.get(x as f64, z as f64).biome;
+
+                for by in 0..chunk.height() / 4 {
+                    chunk.set_biome(bx, by, bz, biome);
+                }
+            }
+        }
+
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spline_eval() {
+        let spline = Spline::new([(1.0, 20.0), (-1.0, 0.0), (0.0, 10.0)]);
+
+        assert_eq!(spline.eval(-5.0), 0.0);
+        assert_eq!(spline.eval(-0.5), 5.0);
+        assert_eq!(spline.eval(0.0), 10.0);
+        assert_eq!(spline.eval(0.25), 12.5);
+        assert_eq!(spline.eval(5.0), 20.0);
+    }
+
+    #[test]
+    fn layered_noise_is_deterministic() {
+        for noise in [LayeredNoise::perlin(7), LayeredNoise::simplex(7)] {
+            let other = noise.clone();
+
+            for i in 0..100 {
+                let (x, z) = (i as f64 * 13.7, i as f64 * -5.3);
+                let n = noise.get_2d(x, z);
+
+                assert_eq!(n, other.get_2d(x, z));
+                assert!((-1.5..=1.5).contains(&n));
+                assert!((-1.5..=1.5).contains(&noise.get_3d(x, 64.0, z)));
+            }
+        }
+    }
+
+    #[test]
+    fn generated_columns() {
+        let biome = BiomeSettings {
+            biome: BiomeId::default(),
+            temperature: 0.0,
+            humidity: 0.0,
+            surface: BlockState::GRASS_BLOCK,
+            subsurface: BlockState::DIRT,
+        };
+
+        let mut generator = TerrainGenerator::new(0, vec![biome]);
+        generator.height_spline = Spline::new([(0.0, 70.0)]);
+
+        let chunk = generator.generate(ChunkPos::new(3, -2), -64, 24);
+        let y = |y: i32| (y + 64) as usize;
+
+        assert_eq!(chunk.get_block_state(5, y(71), 5), BlockState::AIR);
+        assert_eq!(chunk.get_block_state(5, y(70), 5), BlockState::GRASS_BLOCK);
+        assert_eq!(chunk.get_block_state(5, y(67), 5), BlockState::DIRT);
+        assert_eq!(chunk.get_block_state(5, y(66), 5), BlockState::STONE);
+
+        generator.height_spline = Spline::new([(0.0, 50.0)]);
+        let chunk = generator.generate(ChunkPos::new(3, -2), -64, 24);
+
+        assert_eq!(chunk.get_block_state(5, y(62), 5), BlockState::WATER);
+        assert_eq!(chunk.get_block_state(5, y(50), 5), BlockState::DIRT);
+    }
+}
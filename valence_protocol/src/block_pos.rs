@@ -0,0 +1,247 @@
+use std::io::{Read, Write};
+
+use anyhow::bail;
+use vek::Vec3;
+
+use crate::packets::c2s::play::BlockFace;
+use crate::{Decode, Encode};
+
+/// Represents an absolute block position in a world.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPos {
+    /// Constructs a new block position.
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Returns the block position a point is contained within.
+    pub fn at(pos: impl Into<Vec3<f64>>) -> Self {
+        pos.into().floor().as_::<i32>().into()
+    }
+
+    /// Returns this position with the given offset added to each component.
+    pub const fn offset(self, x: i32, y: i32, z: i32) -> Self {
+        Self::new(self.x + x, self.y + y, self.z + z)
+    }
+
+    /// Returns the position adjacent to this one in the direction of `face`.
+    pub fn get_in_direction(self, face: BlockFace) -> Self {
+        self.offset_in_direction(face, 1)
+    }
+
+    /// Returns the position `distance` blocks away from this one in the
+    /// direction of `face`. Negative distances go in the opposite direction.
+    pub fn offset_in_direction(self, face: BlockFace, distance: i32) -> Self {
+        let d = distance;
+        match face {
+            BlockFace::Bottom => self.offset(0, -d, 0),
+            BlockFace::Top => self.offset(0, d, 0),
+            BlockFace::North => self.offset(0, 0, -d),
+            BlockFace::South => self.offset(0, 0, d),
+            BlockFace::West => self.offset(-d, 0, 0),
+            BlockFace::East => self.offset(d, 0, 0),
+        }
+    }
+
+    /// Returns the sum of the absolute differences between the components of
+    /// two positions. This is the number of steps between adjacent blocks
+    /// needed to get from one position to the other.
+    pub fn manhattan_distance(self, other: Self) -> u32 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y) + self.z.abs_diff(other.z)
+    }
+
+    /// Returns the largest absolute difference between the components of two
+    /// positions.
+    pub fn chebyshev_distance(self, other: Self) -> u32 {
+        self.x
+            .abs_diff(other.x)
+            .max(self.y.abs_diff(other.y))
+            .max(self.z.abs_diff(other.z))
+    }
+
+    /// Returns an iterator over all positions in the box with corners `a`
+    /// and `b`, inclusive. The corners can be given in any order.
+    ///
+    /// Positions are visited in x, z, y order, the same order blocks are
+    /// stored in chunk sections.
+    pub fn iter_between(a: Self, b: Self) -> impl Iterator<Item = Self> + Clone {
+        let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+        let (min_y, max_y) = (a.y.min(b.y), a.y.max(b.y));
+        let (min_z, max_z) = (a.z.min(b.z), a.z.max(b.z));
+
+        (min_y..=max_y).flat_map(move |y| {
+            (min_z..=max_z).flat_map(move |z| (min_x..=max_x).map(move |x| Self::new(x, y, z)))
+        })
+    }
+
+    /// Returns the coordinates of the 16x16x16 chunk section containing this
+    /// position.
+    pub fn section(self) -> Vec3<i32> {
+        Vec3::new(
+            self.x.div_euclid(16),
+            self.y.div_euclid(16),
+            self.z.div_euclid(16),
+        )
+    }
+
+    /// Returns the offsets of this position within its chunk section. Each
+    /// component is in `0..16`.
+    pub fn section_offset(self) -> Vec3<usize> {
+        Vec3::new(
+            self.x.rem_euclid(16) as usize,
+            self.y.rem_euclid(16) as usize,
+            self.z.rem_euclid(16) as usize,
+        )
+    }
+
+    /// Constructs a position from the coordinates of a chunk section and the
+    /// offsets within it. This is the inverse of [`Self::section`] and
+    /// [`Self::section_offset`].
+    pub fn from_section(section: Vec3<i32>, offset: Vec3<usize>) -> Self {
+        Self::new(
+            section.x * 16 + offset.x as i32,
+            section.y * 16 + offset.y as i32,
+            section.z * 16 + offset.z as i32,
+        )
+    }
+}
+
+impl Encode for BlockPos {
+    fn encode(&self, w: &mut impl Write) -> anyhow::Result<()> {
+        match (self.x, self.y, self.z) {
+            (-0x2000000..=0x1ffffff, -0x800..=0x7ff, -0x2000000..=0x1ffffff) => {
+                let (x, y, z) = (self.x as u64, self.y as u64, self.z as u64);
+                (x << 38 | z << 38 >> 26 | y & 0xfff).encode(w)
+            }
+            _ => bail!("out of range: {self:?}"),
+        }
+    }
+}
+
+impl Decode for BlockPos {
+    fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
+        // Use arithmetic right shift to determine sign.
+        let val = i64::decode(r)?;
+        let x = val >> 38;
+        let z = val << 26 >> 38;
+        let y = val << 52 >> 52;
+        Ok(Self {
+            x: x as i32,
+            y: y as i32,
+            z: z as i32,
+        })
+    }
+}
+
+impl From<(i32, i32, i32)> for BlockPos {
+    fn from((x, y, z): (i32, i32, i32)) -> Self {
+        BlockPos::new(x, y, z)
+    }
+}
+
+impl From<BlockPos> for (i32, i32, i32) {
+    fn from(pos: BlockPos) -> Self {
+        (pos.x, pos.y, pos.z)
+    }
+}
+
+impl From<[i32; 3]> for BlockPos {
+    fn from([x, y, z]: [i32; 3]) -> Self {
+        BlockPos::new(x, y, z)
+    }
+}
+
+impl From<BlockPos> for [i32; 3] {
+    fn from(pos: BlockPos) -> Self {
+        [pos.x, pos.y, pos.z]
+    }
+}
+
+impl From<Vec3<i32>> for BlockPos {
+    fn from(pos: Vec3<i32>) -> Self {
+        Self::new(pos.x, pos.y, pos.z)
+    }
+}
+
+impl From<BlockPos> for Vec3<i32> {
+    fn from(pos: BlockPos) -> Self {
+        Vec3::new(pos.x, pos.y, pos.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn math() {
+        let a = BlockPos::new(1, -2, 3);
+        let b = BlockPos::new(-1, 5, 3);
+
+        assert_eq!(a.manhattan_distance(b), 9);
+        assert_eq!(a.chebyshev_distance(b), 7);
+        assert_eq!(
+            a.offset_in_direction(BlockFace::West, 3),
+            BlockPos::new(-2, -2, 3)
+        );
+        assert_eq!(a.get_in_direction(BlockFace::Top), BlockPos::new(1, -1, 3));
+
+        let positions: Vec<_> = BlockPos::iter_between(b, a).collect();
+        assert_eq!(positions.len(), 3 * 8);
+        assert_eq!(positions.first(), Some(&BlockPos::new(-1, -2, 3)));
+        assert_eq!(positions[1], BlockPos::new(0, -2, 3));
+        assert_eq!(positions.last(), Some(&BlockPos::new(1, 5, 3)));
+
+        let pos = BlockPos::new(-17, 40, 15);
+        assert_eq!(pos.section(), Vec3::new(-2, 2, 0));
+        assert_eq!(pos.section_offset(), Vec3::new(15, 8, 15));
+        assert_eq!(
+            BlockPos::from_section(pos.section(), pos.section_offset()),
+            pos
+        );
+    }
+
+    #[test]
+    fn position() {
+        let xzs = [
+            (-33554432, true),
+            (-33554433, false),
+            (33554431, true),
+            (33554432, false),
+            (0, true),
+            (1, true),
+            (-1, true),
+        ];
+        let ys = [
+            (-2048, true),
+            (-2049, false),
+            (2047, true),
+            (2048, false),
+            (0, true),
+            (1, true),
+            (-1, true),
+        ];
+
+        let mut buf = [0; 8];
+
+        for (x, x_valid) in xzs {
+            for (y, y_valid) in ys {
+                for (z, z_valid) in xzs {
+                    let pos = BlockPos::new(x, y, z);
+                    if x_valid && y_valid && z_valid {
+                        pos.encode(&mut &mut buf[..]).unwrap();
+                        assert_eq!(BlockPos::decode(&mut &buf[..]).unwrap(), pos);
+                    } else {
+                        assert!(pos.encode(&mut &mut buf[..]).is_err());
+                    }
+                }
+            }
+        }
+    }
+}
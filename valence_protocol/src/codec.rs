@@ -9,12 +9,11 @@ use cfb8::cipher::{AsyncStreamCipher, NewCipher};
 use cfb8::Cfb8;
 use flate2::bufread::{ZlibDecoder, ZlibEncoder};
 use flate2::Compression;
-use log::{log_enabled, Level};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::time::timeout;
 
 use super::packets::{DecodePacket, EncodePacket};
-use crate::protocol::{Decode, Encode, VarInt, MAX_PACKET_SIZE};
+use crate::{Decode, DecodeLimits, Encode, VarInt, MAX_PACKET_SIZE};
 
 pub struct Encoder<W> {
     write: W,
@@ -91,6 +90,11 @@ impl<W: AsyncWrite + Unpin> Encoder<W> {
         Ok(())
     }
 
+    /// Gets the number of bytes queued to be written by the next flush.
+    pub fn queued_len(&self) -> usize {
+        self.buf.len()
+    }
+
     /// Writes all queued packets to the writer.
     pub async fn flush(&mut self) -> anyhow::Result<()> {
         if !self.buf.is_empty() {
@@ -134,6 +138,7 @@ pub struct Decoder<R> {
     compression_threshold: Option<u32>,
     cipher: Option<Cipher>,
     timeout: Duration,
+    limits: DecodeLimits,
 }
 
 impl<R: AsyncRead + Unpin> Decoder<R> {
@@ -145,6 +150,7 @@ impl<R: AsyncRead + Unpin> Decoder<R> {
             compression_threshold: None,
             cipher: None,
             timeout,
+            limits: DecodeLimits::DEFAULT,
         }
     }
 
@@ -158,12 +164,9 @@ impl<R: AsyncRead + Unpin> Decoder<R> {
             .await
             .context("reading packet length")?;
 
-        ensure!(
-            (0..=MAX_PACKET_SIZE).contains(&packet_len),
-            "invalid packet length of {packet_len}."
-        );
+        let packet_len = check_packet_len(packet_len, &self.limits)?;
 
-        self.buf.resize(packet_len as usize, 0);
+        self.buf.resize(packet_len, 0);
 
         self.read
             .read_exact(&mut self.buf)
@@ -174,57 +177,12 @@ impl<R: AsyncRead + Unpin> Decoder<R> {
             cipher.decrypt(&mut self.buf);
         }
 
-        let mut packet_contents = self.buf.as_slice();
-
-        // Compression enabled?
-        let packet = if self.compression_threshold.is_some() {
-            // The length of the packet data once uncompressed (zero indicates no
-            // compression).
-            let data_len = VarInt::decode(&mut packet_contents)
-                .context("reading data length (once decompressed)")?
-                .0;
-
-            ensure!(
-                (0..=MAX_PACKET_SIZE).contains(&data_len),
-                "invalid packet data length of {data_len}."
-            );
-
-            if data_len != 0 {
-                let mut z = ZlibDecoder::new(&mut packet_contents);
-                self.decompress_buf.resize(data_len as usize, 0);
-                z.read_exact(&mut self.decompress_buf)
-                    .context("decompressing packet body")?;
-
-                let mut decompressed = self.decompress_buf.as_slice();
-                let packet = P::decode_packet(&mut decompressed)
-                    .context("decoding packet after decompressing")?;
-                ensure!(
-                    decompressed.is_empty(),
-                    format!(
-                        "packet contents were not read completely, {} remaining bytes",
-                        decompressed.len()
-                    )
-                );
-                packet
-            } else {
-                P::decode_packet(&mut packet_contents).context("decoding packet")?
-            }
-        } else {
-            P::decode_packet(&mut packet_contents).context("decoding packet")?
-        };
-
-        if !packet_contents.is_empty() {
-            if log_enabled!(Level::Debug) {
-                log::debug!("complete packet after partial decode: {packet:?}");
-            }
-
-            bail!(
-                "packet contents were not decoded completely ({} bytes remaining)",
-                packet_contents.len()
-            );
-        }
-
-        Ok(packet)
+        decode_packet_body(
+            &self.buf,
+            self.compression_threshold.is_some(),
+            self.limits,
+            &mut self.decompress_buf,
+        )
     }
 
     async fn read_var_int_async(&mut self) -> anyhow::Result<i32> {
@@ -236,6 +194,10 @@ impl<R: AsyncRead + Unpin> Decoder<R> {
             }
             let [byte] = *array;
 
+            if i == VarInt::MAX_SIZE - 1 && byte & 0b11110000 != 0 {
+                break;
+            }
+
             val |= (byte as i32 & 0b01111111) << (i * 7);
             if byte & 0b10000000 == 0 {
                 return Ok(val);
@@ -252,6 +214,23 @@ impl<R: AsyncRead + Unpin> Decoder<R> {
         self.compression_threshold = Some(threshold);
     }
 
+    /// Gets the limits packets are decoded with.
+    pub fn limits(&self) -> DecodeLimits {
+        self.limits
+    }
+
+    /// Sets the limits packets are decoded with. Packets exceeding the limits
+    /// result in an error from [`Self::read_packet`].
+    pub fn set_limits(&mut self, limits: DecodeLimits) {
+        self.limits = limits;
+    }
+
+    /// Gets the number of bytes the last packet read took up on the wire,
+    /// including its length prefix.
+    pub fn frame_len(&self) -> usize {
+        VarInt(self.buf.len() as i32).written_size() + self.buf.len()
+    }
+
     pub fn packet_buf(&self) -> &[u8] {
         &self.buf
     }
@@ -261,6 +240,82 @@ impl<R: AsyncRead + Unpin> Decoder<R> {
     }
 }
 
+/// Decodes a single packet frame as it appears in the protocol, starting with
+/// the packet length.
+///
+/// `compression` determines if the frame is in the compressed format. `bytes`
+/// must contain exactly one frame. An error is returned if the frame is
+/// malformed or exceeds `limits`. Unlike [`Decoder`], no I/O or encryption is
+/// involved, which makes this suitable as a fuzzing entry point.
+pub fn decode_frame<P: DecodePacket>(
+    mut bytes: &[u8],
+    compression: bool,
+    limits: DecodeLimits,
+) -> anyhow::Result<P> {
+    let packet_len = VarInt::decode(&mut bytes)
+        .context("reading packet length")?
+        .0;
+    let packet_len = check_packet_len(packet_len, &limits)?;
+
+    ensure!(
+        bytes.len() == packet_len,
+        "packet length of {packet_len} does not match the {} remaining bytes",
+        bytes.len()
+    );
+
+    decode_packet_body(bytes, compression, limits, &mut Vec::new())
+}
+
+fn check_packet_len(packet_len: i32, limits: &DecodeLimits) -> anyhow::Result<usize> {
+    ensure!(
+        packet_len >= 0 && packet_len as usize <= limits.max_packet_size,
+        "invalid packet length of {packet_len}."
+    );
+
+    Ok(packet_len as usize)
+}
+
+/// Decodes the contents of a packet frame following the packet length.
+fn decode_packet_body<P: DecodePacket>(
+    mut packet_contents: &[u8],
+    compression: bool,
+    limits: DecodeLimits,
+    decompress_buf: &mut Vec<u8>,
+) -> anyhow::Result<P> {
+    if !compression {
+        return P::decode_packet_from_slice(packet_contents, limits).context("decoding packet");
+    }
+
+    // The length of the packet data once uncompressed (zero indicates no
+    // compression).
+    let data_len = VarInt::decode(&mut packet_contents)
+        .context("reading data length (once decompressed)")?
+        .0;
+
+    ensure!(
+        data_len >= 0 && data_len as usize <= limits.max_packet_size,
+        "invalid packet data length of {data_len}."
+    );
+
+    if data_len == 0 {
+        return P::decode_packet_from_slice(packet_contents, limits).context("decoding packet");
+    }
+
+    let mut z = ZlibDecoder::new(packet_contents);
+    decompress_buf.resize(data_len as usize, 0);
+    z.read_exact(decompress_buf)
+        .context("decompressing packet body")?;
+
+    ensure!(
+        z.get_ref().is_empty(),
+        "packet contents were not read completely, {} remaining bytes",
+        z.get_ref().len()
+    );
+
+    P::decode_packet_from_slice(decompress_buf, limits)
+        .context("decoding packet after decompressing")
+}
+
 /// The AES block cipher with a 128 bit key, using the CFB-8 mode of
 /// operation.
 type Cipher = Cfb8<Aes128>;
@@ -270,11 +325,12 @@ mod tests {
     use std::net::SocketAddr;
     use std::time::Duration;
 
+    use rand::{thread_rng, Rng};
     use tokio::net::{TcpListener, TcpStream};
     use tokio::sync::oneshot;
 
     use super::*;
-    use crate::protocol::packets::test::TestPacket;
+    use crate::packets::test::{TestPacket, TestPacketGroup};
 
     #[tokio::test]
     async fn encode_decode() {
@@ -340,4 +396,75 @@ mod tests {
         assert_eq!(&second, &[0x1234, 0xabcd]);
         assert_eq!(third, 0x1122334455667788);
     }
+
+    fn test_frame(compression_threshold: Option<u32>) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new(), TIMEOUT);
+        if let Some(threshold) = compression_threshold {
+            encoder.enable_compression(threshold);
+        }
+
+        encoder
+            .queue_packet(&TestPacket {
+                first: "abcdefghijklmnopqrstuvwxyz".into(),
+                second: vec![0x1234, 0xabcd],
+                third: 0x1122334455667788,
+            })
+            .unwrap();
+
+        encoder.buf
+    }
+
+    #[test]
+    fn decode_frame_correct() {
+        for (threshold, compression) in [(None, false), (Some(10), true), (Some(1000), true)] {
+            let frame = test_frame(threshold);
+            let pkt: TestPacket = decode_frame(&frame, compression, DecodeLimits::DEFAULT).unwrap();
+            assert_eq!(pkt.first, "abcdefghijklmnopqrstuvwxyz");
+
+            // Trailing and missing bytes are rejected.
+            let mut longer = frame.clone();
+            longer.push(0);
+            assert!(
+                decode_frame::<TestPacket>(&longer, compression, DecodeLimits::DEFAULT).is_err()
+            );
+            assert!(decode_frame::<TestPacket>(
+                &frame[..frame.len() - 1],
+                compression,
+                DecodeLimits::DEFAULT
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn decode_frame_limits() {
+        let limits = DecodeLimits {
+            max_packet_size: 16,
+            ..DecodeLimits::DEFAULT
+        };
+
+        // Too large before decompression.
+        assert!(decode_frame::<TestPacket>(&test_frame(None), false, limits).is_err());
+        // Too large after decompression.
+        assert!(decode_frame::<TestPacket>(&test_frame(Some(10)), true, limits).is_err());
+
+        let limits = DecodeLimits {
+            max_string_len: 8,
+            ..DecodeLimits::DEFAULT
+        };
+        assert!(decode_frame::<TestPacket>(&test_frame(None), false, limits).is_err());
+    }
+
+    #[test]
+    fn decode_garbage() {
+        let mut rng = thread_rng();
+        let mut buf = [0; 64];
+
+        for _ in 0..10_000 {
+            rng.fill(&mut buf[..]);
+            let len = rng.gen_range(0..buf.len());
+            let _ = decode_frame::<TestPacketGroup>(&buf[..len], rng.gen(), DecodeLimits::DEFAULT);
+            let _ = TestPacketGroup::decode_packet_from_slice(&buf[..len], DecodeLimits::DEFAULT);
+        }
+    }
 }
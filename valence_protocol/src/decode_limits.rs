@@ -0,0 +1,94 @@
+use std::cell::Cell;
+
+use crate::MAX_PACKET_SIZE;
+
+/// Maximum sizes enforced while decoding data from an untrusted source, such
+/// as a malicious client.
+///
+/// The limits in effect are set per thread with [`Self::scope`]. Packets read
+/// by a [`Decoder`] are always decoded with the decoder's limits, so servers
+/// only need to call [`Decoder::set_limits`].
+///
+/// [`Decoder`]: crate::codec::Decoder
+/// [`Decoder::set_limits`]: crate::codec::Decoder::set_limits
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct DecodeLimits {
+    /// The maximum length of a packet in bytes. This applies both before and
+    /// after decompression.
+    pub max_packet_size: usize,
+    /// The maximum number of characters in a string. Strings with a smaller
+    /// bound of their own use that bound instead.
+    pub max_string_len: usize,
+    /// The maximum number of elements in a length-prefixed array. Arrays with
+    /// a smaller bound of their own use that bound instead.
+    pub max_array_len: usize,
+    /// The maximum number of bytes in a single NBT value.
+    pub max_nbt_size: usize,
+    /// The maximum depth of nested lists and compounds in an NBT value.
+    pub max_nbt_depth: usize,
+}
+
+impl DecodeLimits {
+    /// The limits used when no others are in effect. These match the limits
+    /// of the vanilla server.
+    pub const DEFAULT: Self = Self {
+        max_packet_size: MAX_PACKET_SIZE as usize,
+        max_string_len: 32767,
+        max_array_len: MAX_PACKET_SIZE as usize,
+        max_nbt_size: MAX_PACKET_SIZE as usize,
+        max_nbt_depth: 512,
+    };
+
+    /// Gets the limits in effect on the current thread.
+    pub fn current() -> Self {
+        CURRENT.with(|c| c.get())
+    }
+
+    /// Calls `f` with these limits in effect on the current thread. The
+    /// previous limits are restored afterwards, even if `f` panics.
+    pub fn scope<T>(self, f: impl FnOnce() -> T) -> T {
+        struct Restore(DecodeLimits);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|c| c.set(self.0));
+            }
+        }
+
+        let _restore = Restore(CURRENT.with(|c| c.replace(self)));
+        f()
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<DecodeLimits> = const { Cell::new(DecodeLimits::DEFAULT) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_restores_previous_limits() {
+        let limits = DecodeLimits {
+            max_string_len: 16,
+            ..DecodeLimits::DEFAULT
+        };
+
+        limits.scope(|| {
+            assert_eq!(DecodeLimits::current(), limits);
+            DecodeLimits::DEFAULT.scope(|| {
+                assert_eq!(DecodeLimits::current(), DecodeLimits::DEFAULT);
+            });
+            assert_eq!(DecodeLimits::current(), limits);
+        });
+
+        assert_eq!(DecodeLimits::current(), DecodeLimits::DEFAULT);
+    }
+}
@@ -1,15 +1,19 @@
 //! Namespaced identifiers.
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::io::{Read, Write};
 use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
 
 use ascii::{AsAsciiStr, AsciiChar, AsciiStr, IntoAsciiString};
 use serde::de::Visitor;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::protocol::{encode_string_bounded, BoundedString, Decode, Encode};
+use crate::{encode_string_bounded, BoundedString, Decode, Encode};
+
+mod vanilla;
 
 /// An identifier is a string split into a "namespace" part and a "path" part.
 /// For instance `minecraft:apple` and `apple` are both valid identifiers.
@@ -19,6 +23,12 @@ use crate::protocol::{encode_string_bounded, BoundedString, Decode, Encode};
 ///
 /// A string must match the regex `^([a-z0-9_-]+:)?[a-z0-9_\/.-]+$` to be a
 /// valid identifier.
+///
+/// Identifiers can be [interned](Ident::intern), which makes comparing them
+/// with other interned identifiers a pointer comparison. Vanilla particles
+/// and common items and sounds are interned ahead of time (Valence adds block
+/// names when the server starts), and identifiers decoded from packets reuse
+/// the interned string when there is one instead of allocating.
 #[derive(Clone, Eq)]
 pub struct Ident {
     ident: Cow<'static, AsciiStr>,
@@ -28,6 +38,9 @@ pub struct Ident {
     /// Since the string only contains ASCII characters, we can slice it
     /// in O(1) time.
     colon_idx: usize,
+    /// If the string is owned by the interner. Interned strings always have a
+    /// namespace, so equal interned identifiers share the same string.
+    interned: bool,
 }
 
 /// The error type which is created when an [`Ident`] cannot be parsed from a
@@ -54,42 +67,94 @@ impl Ident {
             })?),
         };
 
-        let s = cow.as_ref();
+        if !is_valid(cow.as_str()) {
+            return Err(ParseError {
+                src: ascii_cow_to_str_cow(cow),
+            });
+        }
+
+        let colon_idx = cow
+            .chars()
+            .position(|c| c == AsciiChar::Colon)
+            .unwrap_or(usize::MAX);
+
+        Ok(Self {
+            ident: cow,
+            colon_idx,
+            interned: false,
+        })
+    }
+
+    /// Returns an interned copy of this identifier. Interned identifiers do
+    /// not allocate when cloned and are compared with other interned
+    /// identifiers in constant time.
+    ///
+    /// The interned string always includes the namespace, so interning
+    /// `apple` returns `minecraft:apple`. Interned strings are never freed,
+    /// so identifiers from untrusted sources should not be interned.
+    pub fn intern(&self) -> Ident {
+        if self.interned {
+            return self.clone();
+        }
+
+        if let Some(interned) = self.get_interned() {
+            return interned;
+        }
 
-        let check_namespace = |s: &AsciiStr| {
-            !s.is_empty()
-                && s.chars()
-                    .all(|c| matches!(c.as_char(), 'a'..='z' | '0'..='9' | '_' | '-'))
+        let full = self.to_full_string();
+        let mut set = interner().write().unwrap_or_else(|e| e.into_inner());
+
+        let s = match set.get(full.as_str()) {
+            Some(&s) => s,
+            None => {
+                let s: &'static str = Box::leak(full.into_boxed_str());
+                set.insert(s);
+                s
+            }
         };
-        let check_name = |s: &AsciiStr| {
-            !s.is_empty()
-                && s.chars()
-                    .all(|c| matches!(c.as_char(), 'a'..='z' | '0'..='9' | '_' | '/' | '.' | '-'))
+
+        Self::from_interned(s)
+    }
+
+    /// Returns the interned copy of this identifier if it has been interned
+    /// before, without interning it otherwise.
+    pub fn get_interned(&self) -> Option<Ident> {
+        if self.interned {
+            return Some(self.clone());
+        }
+
+        let set = interner().read().unwrap_or_else(|e| e.into_inner());
+
+        let found = match self.namespace() {
+            Some(_) => set.get(self.as_str()),
+            None => set.get(self.to_full_string().as_str()),
         };
 
-        if let Some(colon_idx) = s.chars().position(|c| c == AsciiChar::Colon) {
-            if check_namespace(&s[..colon_idx]) && check_name(&s[colon_idx + 1..]) {
-                Ok(Self {
-                    ident: cow,
-                    colon_idx,
-                })
-            } else {
-                Err(ParseError {
-                    src: ascii_cow_to_str_cow(cow),
-                })
-            }
-        } else if check_name(s) {
-            Ok(Self {
-                ident: cow,
-                colon_idx: usize::MAX,
-            })
-        } else {
-            Err(ParseError {
-                src: ascii_cow_to_str_cow(cow),
-            })
+        found.map(|&s| Self::from_interned(s))
+    }
+
+    /// Returns `true` if this identifier is interned. See [`Self::intern`].
+    pub fn is_interned(&self) -> bool {
+        self.interned
+    }
+
+    fn from_interned(s: &'static str) -> Self {
+        Self {
+            ident: Cow::Borrowed(s.as_ascii_str().expect("interned identifiers are ASCII")),
+            colon_idx: s.find(':').expect("interned identifiers have a namespace"),
+            interned: true,
         }
     }
 
+    /// Returns the identifier as a string including the namespace.
+    fn to_full_string(&self) -> String {
+        format!(
+            "{}:{}",
+            self.namespace().unwrap_or("minecraft"),
+            self.path()
+        )
+    }
+
     /// Returns the namespace part of this namespaced identifier.
     ///
     /// If this identifier was constructed from a string without a namespace,
@@ -117,6 +182,81 @@ impl Ident {
     }
 }
 
+/// Checks if a string is a valid identifier.
+const fn is_valid(s: &str) -> bool {
+    let b = s.as_bytes();
+
+    let mut colon_idx = None;
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b':' {
+            colon_idx = Some(i);
+            break;
+        }
+        i += 1;
+    }
+
+    match colon_idx {
+        Some(idx) => is_valid_part(b, 0, idx, false) && is_valid_part(b, idx + 1, b.len(), true),
+        None => is_valid_part(b, 0, b.len(), true),
+    }
+}
+
+/// Checks if `b[start..end]` is a valid namespace, or a valid path if `path`
+/// is `true`.
+const fn is_valid_part(b: &[u8], start: usize, end: usize, path: bool) -> bool {
+    if start >= end {
+        return false;
+    }
+
+    let mut i = start;
+    while i < end {
+        match b[i] {
+            b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' => {}
+            b'/' | b'.' if path => {}
+            _ => return false,
+        }
+        i += 1;
+    }
+
+    true
+}
+
+/// Used by the [`ident`](crate::ident) macro to validate string literals at
+/// compile time. Format strings are validated when the identifier is created.
+#[doc(hidden)]
+pub const fn __is_valid_literal(s: &str) -> bool {
+    let b = s.as_bytes();
+
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'{' {
+            return true;
+        }
+        i += 1;
+    }
+
+    is_valid(s)
+}
+
+fn interner() -> &'static RwLock<HashSet<&'static str>> {
+    static INTERNER: OnceLock<RwLock<HashSet<&'static str>>> = OnceLock::new();
+
+    INTERNER.get_or_init(|| {
+        let names = vanilla::PARTICLES
+            .iter()
+            .copied()
+            .chain(vanilla::SOUNDS.iter().copied())
+            .chain(vanilla::ITEMS.iter().copied());
+
+        let set = names
+            .map(|name| &*Box::leak(format!("minecraft:{name}").into_boxed_str()))
+            .collect();
+
+        RwLock::new(set)
+    })
+}
+
 fn ascii_cow_to_str_cow(cow: Cow<AsciiStr>) -> Cow<str> {
     match cow {
         Cow::Borrowed(s) => Cow::Borrowed(s.as_str()),
@@ -189,6 +329,10 @@ impl std::fmt::Display for Ident {
 /// "apple" have the same meaning.
 impl PartialEq for Ident {
     fn eq(&self, other: &Self) -> bool {
+        if self.interned && other.interned {
+            return std::ptr::eq(self.as_str(), other.as_str());
+        }
+
         self.namespace().unwrap_or("minecraft") == other.namespace().unwrap_or("minecraft")
             && self.path() == other.path()
     }
@@ -210,7 +354,8 @@ impl Encode for Ident {
 impl Decode for Ident {
     fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
         let string = BoundedString::<0, 32767>::decode(r)?.0;
-        Ok(Ident::new(string)?)
+        let ident = Ident::new(string)?;
+        Ok(ident.get_interned().unwrap_or(ident))
     }
 }
 
@@ -252,12 +397,19 @@ impl<'de> Visitor<'de> for IdentifierVisitor {
 /// # Panics
 ///
 /// The macro will cause a panic if the formatted string is not a valid
-/// identifier.
+/// identifier. String literals without format arguments are validated at
+/// compile time instead:
+///
+/// ```compile_fail
+/// use valence_protocol::ident;
+///
+/// let invalid = ident!("Not Valid");
+/// ```
 ///
 /// # Examples
 ///
 /// ```
-/// use valence::ident;
+/// use valence_protocol::ident;
 ///
 /// let namespace = "my_namespace";
 /// let apple = ident!("{namespace}:apple");
@@ -267,18 +419,30 @@ impl<'de> Visitor<'de> for IdentifierVisitor {
 /// ```
 #[macro_export]
 macro_rules! ident {
-    ($($arg:tt)*) => {{
+    ($lit:literal $(,)?) => {{
+        const _: () = assert!(
+            $crate::ident::__is_valid_literal($lit),
+            "invalid identifier in `ident` macro"
+        );
+        $crate::ident!(@format $lit)
+    }};
+    (@format $($arg:tt)*) => {{
         let errmsg = "invalid identifier in `ident` macro";
         #[allow(clippy::redundant_closure_call)]
         (|args: ::std::fmt::Arguments| match args.as_str() {
             Some(s) => $crate::ident::Ident::new(s).expect(errmsg),
             None => $crate::ident::Ident::new(args.to_string()).expect(errmsg),
         })(format_args!($($arg)*))
-    }}
+    }};
+    ($($arg:tt)*) => {
+        $crate::ident!(@format $($arg)*)
+    };
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn parse_valid() {
         ident!("minecraft:whatever");
@@ -288,23 +452,48 @@ mod tests {
     #[test]
     #[should_panic]
     fn parse_invalid_0() {
-        ident!("");
+        Ident::new("").unwrap();
     }
 
     #[test]
     #[should_panic]
     fn parse_invalid_1() {
-        ident!(":");
+        Ident::new(":").unwrap();
     }
 
     #[test]
     #[should_panic]
     fn parse_invalid_2() {
-        ident!("foo:bar:baz");
+        Ident::new("foo:bar:baz").unwrap();
     }
 
     #[test]
     fn equality() {
         assert_eq!(ident!("minecraft:my.identifier"), ident!("my.identifier"));
     }
+
+    #[test]
+    fn interning() {
+        let diamond = ident!("diamond").get_interned().unwrap();
+        assert_eq!(diamond.as_str(), "minecraft:diamond");
+        assert!(std::ptr::eq(
+            diamond.as_str(),
+            ident!("minecraft:diamond").intern().as_str()
+        ));
+
+        assert!(ident!("flame").get_interned().is_some());
+        assert!(ident!("valence:not_interned_yet").get_interned().is_none());
+
+        let custom = ident!("valence:custom").intern();
+        assert!(custom.is_interned());
+        assert_eq!(custom, ident!("valence:custom").intern());
+        assert_eq!(custom, ident!("valence:custom"));
+        assert_ne!(custom, diamond);
+
+        let mut buf = Vec::new();
+        ident!("minecraft:ui.button.click")
+            .encode(&mut buf)
+            .unwrap();
+        assert!(Ident::decode(&mut buf.as_slice()).unwrap().is_interned());
+    }
 }
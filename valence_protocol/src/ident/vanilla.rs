@@ -0,0 +1,209 @@
+//! Identifiers registered with the interner when it is first used.
+//!
+//! Block names are not listed here since this crate does not know about
+//! blocks. Valence interns them when the server starts. Since most blocks have
+//! an item with the same name, only items without a block are listed here.
+
+/// All particle types.
+pub(super) const PARTICLES: &[&str] = &[
+    "ambient_entity_effect",
+    "angry_villager",
+    "block",
+    "block_marker",
+    "bubble",
+    "cloud",
+    "crit",
+    "damage_indicator",
+    "dragon_breath",
+    "dripping_lava",
+    "falling_lava",
+    "landing_lava",
+    "dripping_water",
+    "falling_water",
+    "dust",
+    "dust_color_transition",
+    "effect",
+    "elder_guardian",
+    "enchanted_hit",
+    "enchant",
+    "end_rod",
+    "entity_effect",
+    "explosion_emitter",
+    "explosion",
+    "sonic_boom",
+    "falling_dust",
+    "firework",
+    "fishing",
+    "flame",
+    "sculk_soul",
+    "sculk_charge",
+    "sculk_charge_pop",
+    "soul_fire_flame",
+    "soul",
+    "flash",
+    "happy_villager",
+    "composter",
+    "heart",
+    "instant_effect",
+    "item",
+    "vibration",
+    "item_slime",
+    "item_snowball",
+    "large_smoke",
+    "lava",
+    "mycelium",
+    "note",
+    "poof",
+    "portal",
+    "rain",
+    "smoke",
+    "sneeze",
+    "spit",
+    "squid_ink",
+    "sweep_attack",
+    "totem_of_undying",
+    "underwater",
+    "splash",
+    "witch",
+    "bubble_pop",
+    "current_down",
+    "bubble_column_up",
+    "nautilus",
+    "dolphin",
+    "campfire_cosy_smoke",
+    "campfire_signal_smoke",
+    "dripping_honey",
+    "falling_honey",
+    "landing_honey",
+    "falling_nectar",
+    "falling_spore_blossom",
+    "ash",
+    "crimson_spore",
+    "warped_spore",
+    "spore_blossom_air",
+    "dripping_obsidian_tear",
+    "falling_obsidian_tear",
+    "landing_obsidian_tear",
+    "reverse_portal",
+    "white_ash",
+    "small_flame",
+    "snowflake",
+    "dripping_dripstone_lava",
+    "falling_dripstone_lava",
+    "dripping_dripstone_water",
+    "falling_dripstone_water",
+    "glow_squid_ink",
+    "glow",
+    "wax_on",
+    "wax_off",
+    "electric_spark",
+    "scrape",
+    "shriek",
+];
+
+/// Commonly played sound events.
+pub(super) const SOUNDS: &[&str] = &[
+    "block.anvil.land",
+    "block.anvil.use",
+    "block.bell.use",
+    "block.chest.close",
+    "block.chest.open",
+    "block.glass.break",
+    "block.grass.break",
+    "block.grass.place",
+    "block.lever.click",
+    "block.note_block.banjo",
+    "block.note_block.basedrum",
+    "block.note_block.bass",
+    "block.note_block.bell",
+    "block.note_block.bit",
+    "block.note_block.chime",
+    "block.note_block.cow_bell",
+    "block.note_block.didgeridoo",
+    "block.note_block.flute",
+    "block.note_block.guitar",
+    "block.note_block.harp",
+    "block.note_block.hat",
+    "block.note_block.iron_xylophone",
+    "block.note_block.pling",
+    "block.note_block.snare",
+    "block.note_block.xylophone",
+    "block.stone.break",
+    "block.stone.place",
+    "block.wood.break",
+    "block.wood.place",
+    "entity.arrow.hit",
+    "entity.arrow.hit_player",
+    "entity.arrow.shoot",
+    "entity.ender_dragon.growl",
+    "entity.enderman.teleport",
+    "entity.experience_orb.pickup",
+    "entity.firework_rocket.blast",
+    "entity.firework_rocket.launch",
+    "entity.generic.explode",
+    "entity.item.pickup",
+    "entity.lightning_bolt.impact",
+    "entity.lightning_bolt.thunder",
+    "entity.player.attack.crit",
+    "entity.player.attack.knockback",
+    "entity.player.attack.nodamage",
+    "entity.player.attack.strong",
+    "entity.player.attack.sweep",
+    "entity.player.attack.weak",
+    "entity.player.death",
+    "entity.player.hurt",
+    "entity.player.levelup",
+    "entity.villager.no",
+    "entity.villager.yes",
+    "entity.wither.spawn",
+    "ui.button.click",
+    "ui.toast.challenge_complete",
+    "ui.toast.in",
+    "ui.toast.out",
+];
+
+/// Common items which have no block of the same name.
+pub(super) const ITEMS: &[&str] = &[
+    "apple",
+    "arrow",
+    "bow",
+    "bread",
+    "bucket",
+    "compass",
+    "cooked_beef",
+    "crossbow",
+    "diamond",
+    "diamond_axe",
+    "diamond_boots",
+    "diamond_chestplate",
+    "diamond_helmet",
+    "diamond_leggings",
+    "diamond_pickaxe",
+    "diamond_shovel",
+    "diamond_sword",
+    "egg",
+    "elytra",
+    "ender_pearl",
+    "fishing_rod",
+    "flint_and_steel",
+    "golden_apple",
+    "iron_axe",
+    "iron_boots",
+    "iron_chestplate",
+    "iron_helmet",
+    "iron_ingot",
+    "iron_leggings",
+    "iron_pickaxe",
+    "iron_shovel",
+    "iron_sword",
+    "lava_bucket",
+    "netherite_sword",
+    "shield",
+    "snowball",
+    "stick",
+    "stone_sword",
+    "totem_of_undying",
+    "trident",
+    "water_bucket",
+    "wooden_sword",
+];
@@ -1,15 +1,38 @@
-//! Provides low-level access to the Minecraft protocol.
+//! An implementation of the Minecraft protocol, targeting the same version as
+//! Valence.
 //!
-//! You should avoid this module if possible.
+//! This crate can be used on its own to build clients, bots and proxies. It
+//! contains:
+//!
+//! - The [`Encode`] and [`Decode`] traits and implementations for the primitive
+//!   types of the protocol, such as [`VarInt`], [`VarLong`] and [`ByteAngle`].
+//! - Definitions of every packet in [`packets`].
+//! - A packet [`codec`] with support for compression and encryption.
+//! - [`DecodeLimits`], which bound the sizes accepted while decoding data from
+//!   untrusted sources.
+//! - [`Ident`](ident::Ident)ifiers, [`Text`](text::Text) components, [`nbt`]
+//!   values and [`BlockPos`](block_pos::BlockPos).
+//!
+//! Valence re-exports this crate as `valence::protocol` when its `protocol`
+//! feature is enabled.
+
+#![forbid(unsafe_code)]
+#![warn(
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_lifetimes,
+    unused_import_braces
+)]
 
 use std::io::{Read, Write};
 use std::mem;
 
-use anyhow::{anyhow, ensure, Context};
+use anyhow::{anyhow, ensure};
 use arrayvec::ArrayVec;
 use bitvec::prelude::*;
 pub use byte_angle::ByteAngle;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+pub use decode_limits::DecodeLimits;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use uuid::Uuid;
@@ -17,15 +40,22 @@ pub use var_int::VarInt;
 pub use var_long::VarLong;
 use vek::{Vec2, Vec3, Vec4};
 
-use crate::entity::EntityId;
-use crate::nbt;
-
+pub mod block_pos;
 mod byte_angle;
 pub mod codec;
+mod decode_limits;
+pub mod ident;
+pub mod nbt;
 pub mod packets;
+pub mod text;
 mod var_int;
 mod var_long;
 
+/// The Minecraft protocol version this crate targets.
+pub const PROTOCOL_VERSION: i32 = 760;
+/// The name of the Minecraft version this crate targets.
+pub const VERSION_NAME: &str = "1.19.2";
+
 /// Types that can be written to the Minecraft protocol.
 pub trait Encode {
     /// This function must be pure. In other words, consecutive calls to
@@ -516,7 +546,8 @@ impl Encode for nbt::Compound {
 
 impl Decode for nbt::Compound {
     fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
-        Ok(nbt::binary::from_reader(r)?)
+        let buf = nbt::read_binary_limited(r)?;
+        Ok(nbt::binary::from_reader(buf.as_slice())?)
     }
 }
 
@@ -533,7 +564,8 @@ impl<T: Serialize> Encode for NbtBridge<T> {
 
 impl<T: DeserializeOwned> Decode for NbtBridge<T> {
     fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
-        Ok(Self(nbt::binary::from_reader(r)?))
+        let buf = nbt::read_binary_limited(r)?;
+        Ok(Self(nbt::binary::from_reader(buf.as_slice())?))
     }
 }
 
@@ -582,20 +614,6 @@ impl Encode for RawBytes {
     }
 }
 
-impl Encode for Option<EntityId> {
-    fn encode(&self, w: &mut impl Write) -> anyhow::Result<()> {
-        match self {
-            Some(id) => VarInt(
-                id.to_network_id()
-                    .checked_add(1)
-                    .context("i32::MAX is unrepresentable as an optional VarInt")?,
-            ),
-            None => VarInt(0),
-        }
-        .encode(w)
-    }
-}
-
 fn encode_array_bounded<T: Encode>(
     s: &[T],
     min: usize,
@@ -649,7 +667,10 @@ pub(crate) fn decode_string_bounded(
 ) -> anyhow::Result<String> {
     assert!(min <= max);
 
-    let bytes = decode_array_bounded(min, max.saturating_mul(4), r)?;
+    let max = max.min(DecodeLimits::current().max_string_len);
+
+    let len = decode_len_bounded(min, max.saturating_mul(4), r)?;
+    let bytes = decode_elems(len, r)?;
     let string = String::from_utf8(bytes)?;
 
     let char_count = string.chars().count();
@@ -669,15 +690,27 @@ pub(crate) fn decode_array_bounded<T: Decode>(
 ) -> anyhow::Result<Vec<T>> {
     assert!(min <= max);
 
+    let max = max.min(DecodeLimits::current().max_array_len);
+
+    let len = decode_len_bounded(min, max, r)?;
+    decode_elems(len, r)
+}
+
+/// Reads a `VarInt` array length and checks that it is in `min..=max`.
+fn decode_len_bounded(min: usize, max: usize, r: &mut impl Read) -> anyhow::Result<usize> {
     let len = VarInt::decode(r)?.0;
     ensure!(
         len >= 0 && (min..=max).contains(&(len as usize)),
         "Length of array is out of bounds while decoding (got {len}, needed {min}..={max})",
     );
 
+    Ok(len as usize)
+}
+
+fn decode_elems<T: Decode>(len: usize, r: &mut impl Read) -> anyhow::Result<Vec<T>> {
     // Don't allocate more than what would roughly fit in a single packet in case we
     // get a malicious array length.
-    let cap = (MAX_PACKET_SIZE as usize / mem::size_of::<T>().max(1)).min(len as usize);
+    let cap = (MAX_PACKET_SIZE as usize / mem::size_of::<T>().max(1)).min(len);
 
     let mut res = Vec::with_capacity(cap);
     for _ in 0..len {
@@ -686,3 +719,45 @@ pub(crate) fn decode_array_bounded<T: Decode>(
 
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_limits() {
+        let mut buf = Vec::new();
+        "abcdefgh".to_owned().encode(&mut buf).unwrap();
+
+        assert_eq!(String::decode(&mut buf.as_slice()).unwrap(), "abcdefgh");
+
+        let limits = DecodeLimits {
+            max_string_len: 4,
+            ..DecodeLimits::DEFAULT
+        };
+
+        limits.scope(|| {
+            assert!(String::decode(&mut buf.as_slice()).is_err());
+            assert!(BoundedString::<0, 100>::decode(&mut buf.as_slice()).is_err());
+        });
+    }
+
+    #[test]
+    fn array_limits() {
+        let mut buf = Vec::new();
+        vec![1_u8, 2, 3].encode(&mut buf).unwrap();
+
+        let limits = DecodeLimits {
+            max_array_len: 2,
+            ..DecodeLimits::DEFAULT
+        };
+
+        assert_eq!(Vec::<u8>::decode(&mut buf.as_slice()).unwrap(), [1, 2, 3]);
+        limits.scope(|| assert!(Vec::<u8>::decode(&mut buf.as_slice()).is_err()));
+
+        // Zero-sized elements cannot be used to spin on a huge length.
+        let mut buf = Vec::new();
+        VarInt(i32::MAX).encode(&mut buf).unwrap();
+        assert!(Vec::<()>::decode(&mut buf.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+//! Minecraft's [Named Binary Tag] (NBT) format.
+//!
+//! This re-exports the [serde_nbt] crate, which (de)serializes owned values
+//! and user-defined types, and adds:
+//!
+//! - [`borrowed`], which decodes binary NBT without copying strings and arrays
+//!   out of the input.
+//! - [`snbt`], which parses and prints the stringified NBT format used in
+//!   commands.
+//! - [`to_compound`] and [`from_compound`], which convert between [`Compound`]s
+//!   and types implementing `Serialize` and `Deserialize`, for storing custom
+//!   structures in item and block entity data.
+//!
+//! [Named Binary Tag]: https://minecraft.fandom.com/wiki/NBT_format
+//! [serde_nbt]: https://docs.rs/serde_nbt
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+pub use serde_nbt::*;
+
+pub mod borrowed;
+mod limited;
+pub mod snbt;
+
+pub(crate) use limited::read_binary_limited;
+
+/// Converts a value to an NBT compound.
+///
+/// An error is returned if the value does not serialize as a compound (a map
+/// or struct).
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use valence_protocol::nbt::{from_compound, to_compound, Value};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Display {
+///     color: i32,
+///     name: String,
+/// }
+///
+/// let display = Display {
+///     color: 0xff0000,
+///     name: "Apple".into(),
+/// };
+///
+/// let compound = to_compound(&display).unwrap();
+/// assert_eq!(compound["color"], Value::Int(0xff0000));
+/// assert_eq!(from_compound::<Display>(&compound).unwrap(), display);
+/// ```
+pub fn to_compound<T: Serialize + ?Sized>(value: &T) -> Result<Compound> {
+    let mut buf = Vec::new();
+    binary::to_writer(&mut buf, value)?;
+    binary::from_reader(buf.as_slice())
+}
+
+/// Converts an NBT compound to a value of a type implementing `Deserialize`.
+pub fn from_compound<T: DeserializeOwned>(compound: &Compound) -> Result<T> {
+    let mut buf = Vec::new();
+    binary::to_writer(&mut buf, compound)?;
+    binary::from_reader(buf.as_slice())
+}
@@ -0,0 +1,360 @@
+//! Decoding binary NBT without copying.
+//!
+//! [`from_slice`] decodes NBT into [`ValueRef`]s, which borrow strings and
+//! arrays from the input. This avoids most allocations when NBT from packets
+//! only needs to be inspected, such as when looking up a few fields of item
+//! data. Borrowed values can be converted to owned [`Value`]s with
+//! [`ValueRef::to_value`].
+
+use std::borrow::Cow;
+
+use anyhow::{bail, ensure, Context};
+use byteorder::{BigEndian, ReadBytesExt};
+use cesu8::from_java_cesu8;
+
+use crate::nbt::{Compound, List, Value};
+
+/// The maximum depth of nested lists and compounds, matching the vanilla
+/// limit. Deeper input is rejected instead of overflowing the stack.
+const MAX_DEPTH: usize = 512;
+
+pub(super) const TAG_END: u8 = 0;
+pub(super) const TAG_BYTE: u8 = 1;
+pub(super) const TAG_SHORT: u8 = 2;
+pub(super) const TAG_INT: u8 = 3;
+pub(super) const TAG_LONG: u8 = 4;
+pub(super) const TAG_FLOAT: u8 = 5;
+pub(super) const TAG_DOUBLE: u8 = 6;
+pub(super) const TAG_BYTE_ARRAY: u8 = 7;
+pub(super) const TAG_STRING: u8 = 8;
+pub(super) const TAG_LIST: u8 = 9;
+pub(super) const TAG_COMPOUND: u8 = 10;
+pub(super) const TAG_INT_ARRAY: u8 = 11;
+pub(super) const TAG_LONG_ARRAY: u8 = 12;
+
+/// An NBT value borrowing from the data it was decoded from.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ValueRef<'a> {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(&'a [u8]),
+    /// Strings are only copied if they contain characters which are encoded
+    /// differently in Java's modified UTF-8.
+    String(Cow<'a, str>),
+    List(Vec<ValueRef<'a>>),
+    Compound(CompoundRef<'a>),
+    IntArray(IntArrayRef<'a>),
+    LongArray(LongArrayRef<'a>),
+}
+
+/// An NBT compound borrowing from the data it was decoded from.
+///
+/// Entries are stored in the order they were decoded in.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct CompoundRef<'a> {
+    entries: Vec<(Cow<'a, str>, ValueRef<'a>)>,
+}
+
+impl<'a> CompoundRef<'a> {
+    /// Gets the value of the entry with the given name.
+    pub fn get(&self, name: &str) -> Option<&ValueRef<'a>> {
+        self.entries.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// Returns an iterator over the names and values of all entries.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&str, &ValueRef<'a>)> + '_ {
+        self.entries.iter().map(|(n, v)| (n.as_ref(), v))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Copies this compound into an owned [`Compound`].
+    pub fn to_compound(&self) -> Compound {
+        self.entries
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_value()))
+            .collect()
+    }
+}
+
+macro_rules! def_array_ref {
+    ($name:ident, $ty:ty, $read:ident, $doc:literal) => {
+        #[doc = $doc]
+        ///
+        /// The elements are decoded from the borrowed big-endian bytes
+        /// when iterated.
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        pub struct $name<'a>(&'a [u8]);
+
+        impl<'a> $name<'a> {
+            /// Returns the number of elements.
+            pub fn len(&self) -> usize {
+                self.0.len() / std::mem::size_of::<$ty>()
+            }
+
+            /// Returns `true` if there are no elements.
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            /// Returns an iterator over the elements.
+            pub fn iter(&self) -> impl ExactSizeIterator<Item = $ty> + 'a {
+                self.0
+                    .chunks_exact(std::mem::size_of::<$ty>())
+                    .map(|mut b| b.$read::<BigEndian>().unwrap())
+            }
+        }
+    };
+}
+
+def_array_ref!(IntArrayRef, i32, read_i32, "A borrowed NBT int array.");
+def_array_ref!(LongArrayRef, i64, read_i64, "A borrowed NBT long array.");
+
+impl<'a> ValueRef<'a> {
+    /// Returns the string if this is a string value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the compound if this is a compound value.
+    pub fn as_compound(&self) -> Option<&CompoundRef<'a>> {
+        match self {
+            Self::Compound(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an `i64` if this is a byte, short, int or long.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Self::Byte(v) => Some(v.into()),
+            Self::Short(v) => Some(v.into()),
+            Self::Int(v) => Some(v.into()),
+            Self::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Copies this value into an owned [`Value`].
+    pub fn to_value(&self) -> Value {
+        match self {
+            Self::Byte(v) => Value::Byte(*v),
+            Self::Short(v) => Value::Short(*v),
+            Self::Int(v) => Value::Int(*v),
+            Self::Long(v) => Value::Long(*v),
+            Self::Float(v) => Value::Float(*v),
+            Self::Double(v) => Value::Double(*v),
+            Self::ByteArray(v) => Value::ByteArray(v.iter().map(|&b| b as i8).collect()),
+            Self::String(v) => Value::String(v.to_string()),
+            Self::List(v) => Value::List(to_list(v)),
+            Self::Compound(v) => Value::Compound(v.to_compound()),
+            Self::IntArray(v) => Value::IntArray(v.iter().collect()),
+            Self::LongArray(v) => Value::LongArray(v.iter().collect()),
+        }
+    }
+}
+
+/// Converts borrowed list elements to an owned list. All elements have the
+/// same type since they were decoded from a single list.
+fn to_list(elems: &[ValueRef]) -> List {
+    macro_rules! collect {
+        ($variant:ident, $pat:pat => $val:expr) => {
+            List::$variant(
+                elems
+                    .iter()
+                    .filter_map(|e| match e {
+                        $pat => Some($val),
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        };
+    }
+
+    match elems.first() {
+        None | Some(ValueRef::Byte(_)) => collect!(Byte, ValueRef::Byte(v) => *v),
+        Some(ValueRef::Short(_)) => collect!(Short, ValueRef::Short(v) => *v),
+        Some(ValueRef::Int(_)) => collect!(Int, ValueRef::Int(v) => *v),
+        Some(ValueRef::Long(_)) => collect!(Long, ValueRef::Long(v) => *v),
+        Some(ValueRef::Float(_)) => collect!(Float, ValueRef::Float(v) => *v),
+        Some(ValueRef::Double(_)) => collect!(Double, ValueRef::Double(v) => *v),
+        Some(ValueRef::ByteArray(_)) => collect!(
+            ByteArray,
+            ValueRef::ByteArray(v) => v.iter().map(|&b| b as i8).collect()
+        ),
+        Some(ValueRef::String(_)) => collect!(String, ValueRef::String(v) => v.to_string()),
+        Some(ValueRef::List(_)) => collect!(List, ValueRef::List(v) => to_list(v)),
+        Some(ValueRef::Compound(_)) => {
+            collect!(Compound, ValueRef::Compound(v) => v.to_compound())
+        }
+        Some(ValueRef::IntArray(_)) => {
+            collect!(IntArray, ValueRef::IntArray(v) => v.iter().collect())
+        }
+        Some(ValueRef::LongArray(_)) => {
+            collect!(LongArray, ValueRef::LongArray(v) => v.iter().collect())
+        }
+    }
+}
+
+/// Decodes a root NBT compound from the front of `r`, advancing `r` past the
+/// decoded data. The name of the root compound is discarded.
+pub fn from_slice<'a>(r: &mut &'a [u8]) -> anyhow::Result<CompoundRef<'a>> {
+    let tag = r.read_u8()?;
+    ensure!(
+        tag == TAG_COMPOUND,
+        "expected root compound tag, got tag {tag}"
+    );
+
+    read_string(r).context("reading root name")?;
+    read_compound(r, 0)
+}
+
+fn take<'a>(r: &mut &'a [u8], len: usize) -> anyhow::Result<&'a [u8]> {
+    ensure!(r.len() >= len, "unexpected end of NBT data");
+    let (taken, rest) = r.split_at(len);
+    *r = rest;
+    Ok(taken)
+}
+
+fn read_len(r: &mut &[u8]) -> anyhow::Result<usize> {
+    let len = r.read_i32::<BigEndian>()?;
+    ensure!(len >= 0, "negative NBT length {len}");
+    Ok(len as usize)
+}
+
+fn read_string<'a>(r: &mut &'a [u8]) -> anyhow::Result<Cow<'a, str>> {
+    let len = r.read_u16::<BigEndian>()? as usize;
+    from_java_cesu8(take(r, len)?).context("invalid modified UTF-8 in NBT string")
+}
+
+fn read_compound<'a>(r: &mut &'a [u8], depth: usize) -> anyhow::Result<CompoundRef<'a>> {
+    let mut entries = Vec::new();
+
+    loop {
+        let tag = r.read_u8()?;
+        if tag == TAG_END {
+            return Ok(CompoundRef { entries });
+        }
+
+        let name = read_string(r)?;
+        let value =
+            read_payload(r, tag, depth + 1).with_context(|| format!("in field `{name}`"))?;
+        entries.push((name, value));
+    }
+}
+
+fn read_payload<'a>(r: &mut &'a [u8], tag: u8, depth: usize) -> anyhow::Result<ValueRef<'a>> {
+    ensure!(depth <= MAX_DEPTH, "NBT data is nested too deeply");
+
+    Ok(match tag {
+        TAG_BYTE => ValueRef::Byte(r.read_i8()?),
+        TAG_SHORT => ValueRef::Short(r.read_i16::<BigEndian>()?),
+        TAG_INT => ValueRef::Int(r.read_i32::<BigEndian>()?),
+        TAG_LONG => ValueRef::Long(r.read_i64::<BigEndian>()?),
+        TAG_FLOAT => ValueRef::Float(r.read_f32::<BigEndian>()?),
+        TAG_DOUBLE => ValueRef::Double(r.read_f64::<BigEndian>()?),
+        TAG_BYTE_ARRAY => {
+            let len = read_len(r)?;
+            ValueRef::ByteArray(take(r, len)?)
+        }
+        TAG_STRING => ValueRef::String(read_string(r)?),
+        TAG_LIST => {
+            let elem_tag = r.read_u8()?;
+            let len = read_len(r)?;
+
+            if elem_tag == TAG_END {
+                ensure!(len == 0, "list of end tags must be empty");
+            }
+
+            // Every element takes at least one byte, so the length can be
+            // checked before allocating.
+            ensure!(len <= r.len(), "unexpected end of NBT data");
+
+            let mut elems = Vec::with_capacity(len);
+            for _ in 0..len {
+                elems.push(read_payload(r, elem_tag, depth + 1)?);
+            }
+            ValueRef::List(elems)
+        }
+        TAG_COMPOUND => ValueRef::Compound(read_compound(r, depth)?),
+        TAG_INT_ARRAY => {
+            let len = read_len(r)?;
+            ValueRef::IntArray(IntArrayRef(take(r, len.saturating_mul(4))?))
+        }
+        TAG_LONG_ARRAY => {
+            let len = read_len(r)?;
+            ValueRef::LongArray(LongArrayRef(take(r, len.saturating_mul(8))?))
+        }
+        _ => bail!("invalid NBT tag {tag}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::binary::to_writer;
+
+    #[test]
+    fn matches_owned_decoding() {
+        let mut compound = Compound::new();
+        compound.insert("byte".into(), Value::Byte(-5));
+        compound.insert("string".into(), Value::String("hello 🌎".into()));
+        compound.insert("ints".into(), Value::IntArray(vec![1, -2, 3]));
+        compound.insert(
+            "list".into(),
+            Value::List(List::String(vec!["a".into(), "b".into()])),
+        );
+        compound.insert(
+            "nested".into(),
+            Value::Compound(Compound::from_iter([("long".into(), Value::Long(7))])),
+        );
+
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &compound).unwrap();
+        buf.push(0xff);
+
+        let mut r = buf.as_slice();
+        let borrowed = from_slice(&mut r).unwrap();
+
+        assert_eq!(r, &[0xff]);
+        assert_eq!(borrowed.to_compound(), compound);
+        assert!(matches!(
+            borrowed.get("list"),
+            Some(ValueRef::List(l)) if matches!(l[0], ValueRef::String(Cow::Borrowed("a")))
+        ));
+        assert_eq!(
+            borrowed
+                .get("nested")
+                .and_then(|n| n.as_compound())
+                .and_then(|n| n.get("long"))
+                .and_then(|n| n.as_i64()),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        // Truncated string.
+        let mut r: &[u8] = &[10, 0, 0, 8, 0, 1, b'a', 0, 5];
+        assert!(from_slice(&mut r).is_err());
+
+        // List claiming more elements than there is data.
+        let mut r: &[u8] = &[10, 0, 0, 9, 0, 1, b'a', 1, 0x7f, 0, 0, 0];
+        assert!(from_slice(&mut r).is_err());
+    }
+}
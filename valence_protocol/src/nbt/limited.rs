@@ -0,0 +1,198 @@
+//! Copying binary NBT out of a reader while enforcing [`DecodeLimits`].
+
+use std::io::{self, Read};
+
+use anyhow::{bail, ensure, Context};
+use byteorder::{BigEndian, ReadBytesExt};
+
+use super::borrowed::{
+    TAG_BYTE, TAG_BYTE_ARRAY, TAG_COMPOUND, TAG_DOUBLE, TAG_END, TAG_FLOAT, TAG_INT, TAG_INT_ARRAY,
+    TAG_LIST, TAG_LONG, TAG_LONG_ARRAY, TAG_SHORT, TAG_STRING,
+};
+use crate::DecodeLimits;
+
+/// Reads the bytes of a root NBT compound from `r` without interpreting them.
+///
+/// Only as many bytes as the compound occupies are read. An error is returned
+/// if the data is malformed or exceeds the [`DecodeLimits`] in effect, so the
+/// result can be passed to a decoder without risk of unbounded allocation or
+/// recursion.
+pub(crate) fn read_binary_limited(r: &mut impl Read) -> anyhow::Result<Vec<u8>> {
+    let limits = DecodeLimits::current();
+
+    let mut c = Copier {
+        r,
+        buf: Vec::new(),
+        max_size: limits.max_nbt_size,
+        max_depth: limits.max_nbt_depth,
+    };
+
+    let tag = c.read_u8()?;
+    ensure!(
+        tag == TAG_COMPOUND,
+        "expected root compound tag, got tag {tag}"
+    );
+
+    c.string().context("reading root name")?;
+    c.compound(0)?;
+
+    Ok(c.buf)
+}
+
+struct Copier<'a, R> {
+    r: &'a mut R,
+    buf: Vec<u8>,
+    max_size: usize,
+    max_depth: usize,
+}
+
+impl<R: Read> Copier<'_, R> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&[u8]> {
+        ensure!(
+            len <= self.max_size.saturating_sub(self.buf.len()),
+            "NBT data exceeds the maximum size of {} bytes",
+            self.max_size
+        );
+
+        let start = self.buf.len();
+        let read = self.r.take(len as u64).read_to_end(&mut self.buf)?;
+        if read < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(&self.buf[start..])
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_len(&mut self) -> anyhow::Result<usize> {
+        let len = self.take(4)?.read_i32::<BigEndian>()?;
+        ensure!(len >= 0, "negative NBT length {len}");
+        Ok(len as usize)
+    }
+
+    fn string(&mut self) -> anyhow::Result<()> {
+        let len = self.take(2)?.read_u16::<BigEndian>()?;
+        self.take(len as usize)?;
+        Ok(())
+    }
+
+    fn compound(&mut self, depth: usize) -> anyhow::Result<()> {
+        loop {
+            let tag = self.read_u8()?;
+            if tag == TAG_END {
+                return Ok(());
+            }
+
+            self.string()?;
+            self.payload(tag, depth + 1)?;
+        }
+    }
+
+    fn payload(&mut self, tag: u8, depth: usize) -> anyhow::Result<()> {
+        ensure!(depth <= self.max_depth, "NBT data is nested too deeply");
+
+        match tag {
+            TAG_BYTE => self.take(1).map(drop),
+            TAG_SHORT => self.take(2).map(drop),
+            TAG_INT | TAG_FLOAT => self.take(4).map(drop),
+            TAG_LONG | TAG_DOUBLE => self.take(8).map(drop),
+            TAG_BYTE_ARRAY => {
+                let len = self.read_len()?;
+                self.take(len).map(drop)
+            }
+            TAG_STRING => self.string(),
+            TAG_LIST => {
+                let elem_tag = self.read_u8()?;
+                let len = self.read_len()?;
+
+                if elem_tag == TAG_END {
+                    ensure!(len == 0, "list of end tags must be empty");
+                }
+
+                for _ in 0..len {
+                    self.payload(elem_tag, depth + 1)?;
+                }
+                Ok(())
+            }
+            TAG_COMPOUND => self.compound(depth),
+            TAG_INT_ARRAY => {
+                let len = self.read_len()?;
+                self.take(len.saturating_mul(4)).map(drop)
+            }
+            TAG_LONG_ARRAY => {
+                let len = self.read_len()?;
+                self.take(len.saturating_mul(8)).map(drop)
+            }
+            _ => bail!("invalid NBT tag {tag}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::binary::to_writer;
+    use crate::nbt::{Compound, List, Value};
+
+    #[test]
+    fn copies_exactly_one_compound() {
+        let mut compound = Compound::new();
+        compound.insert("name".into(), Value::String("abc".into()));
+        compound.insert("longs".into(), Value::LongArray(vec![1, 2, 3]));
+        compound.insert(
+            "list".into(),
+            Value::List(List::Compound(vec![Compound::new(), Compound::new()])),
+        );
+
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &compound).unwrap();
+        let len = buf.len();
+        buf.push(0xff);
+
+        let mut r = buf.as_slice();
+        assert_eq!(read_binary_limited(&mut r).unwrap(), buf[..len]);
+        assert_eq!(r, &[0xff]);
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let mut buf = Vec::new();
+        to_writer(
+            &mut buf,
+            &Compound::from_iter([("bytes".into(), Value::ByteArray(vec![0; 100]))]),
+        )
+        .unwrap();
+
+        let limits = DecodeLimits {
+            max_nbt_size: 50,
+            ..DecodeLimits::DEFAULT
+        };
+        limits.scope(|| assert!(read_binary_limited(&mut buf.as_slice()).is_err()));
+
+        // A list of lists nested deeper than the limit.
+        let mut deep = vec![TAG_COMPOUND, 0, 0, TAG_LIST, 0, 1, b'a'];
+        for _ in 0..1000 {
+            deep.extend([TAG_LIST, 0, 0, 0, 1]);
+        }
+        deep.extend([TAG_END, 0, 0, 0, 0, TAG_END]);
+        assert!(read_binary_limited(&mut deep.as_slice()).is_err());
+
+        // Lengths larger than the remaining data.
+        let mut r: &[u8] = &[
+            TAG_COMPOUND,
+            0,
+            0,
+            TAG_BYTE_ARRAY,
+            0,
+            1,
+            b'a',
+            0x7f,
+            0xff,
+            0xff,
+        ];
+        assert!(read_binary_limited(&mut r).is_err());
+    }
+}
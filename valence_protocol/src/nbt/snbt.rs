@@ -0,0 +1,488 @@
+//! Stringified NBT (SNBT), the text format of NBT used in commands such as
+//! `/give @p diamond_sword{Unbreakable:1b}`.
+
+use std::fmt::Write as _;
+
+use thiserror::Error;
+
+use crate::nbt::{Compound, List, Value};
+
+/// The maximum depth of nested lists and compounds accepted by the parser.
+const MAX_DEPTH: usize = 512;
+
+/// The error type which is created when SNBT cannot be parsed.
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+#[error("{message} at byte {position} of SNBT")]
+pub struct SnbtError {
+    message: String,
+    position: usize,
+}
+
+impl SnbtError {
+    /// Gets the offset of the byte in the input where the error occurred.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+/// Parses a compound from an SNBT string such as `{name:"Steve",age:20}`.
+pub fn compound_from_snbt(s: &str) -> Result<Compound, SnbtError> {
+    match from_snbt(s)? {
+        Value::Compound(c) => Ok(c),
+        _ => Err(SnbtError {
+            message: "expected compound".into(),
+            position: 0,
+        }),
+    }
+}
+
+/// Parses a value from an SNBT string.
+///
+/// Like in vanilla, numbers without a suffix are ints if they have no
+/// decimal point and doubles otherwise, `true` and `false` are bytes, and
+/// other unquoted words are strings.
+pub fn from_snbt(s: &str) -> Result<Value, SnbtError> {
+    let mut parser = Parser { s, pos: 0 };
+
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+
+    if parser.pos != s.len() {
+        return Err(parser.error("trailing data"));
+    }
+
+    Ok(value)
+}
+
+/// Prints a compound as SNBT.
+pub fn compound_to_snbt(compound: &Compound) -> String {
+    let mut out = String::new();
+    write_compound(&mut out, compound);
+    out
+}
+
+/// Prints a value as SNBT.
+///
+/// ```
+/// use valence_protocol::nbt::snbt::{from_snbt, to_snbt};
+/// use valence_protocol::nbt::Value;
+///
+/// let value = from_snbt("{Count:3b,id:\"minecraft:apple\"}").unwrap();
+/// assert_eq!(to_snbt(&value), "{Count:3b,id:\"minecraft:apple\"}");
+/// ```
+pub fn to_snbt(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Byte(v) => _ = write!(out, "{v}b"),
+        Value::Short(v) => _ = write!(out, "{v}s"),
+        Value::Int(v) => _ = write!(out, "{v}"),
+        Value::Long(v) => _ = write!(out, "{v}L"),
+        Value::Float(v) => _ = write!(out, "{v}f"),
+        Value::Double(v) => _ = write!(out, "{v}d"),
+        Value::ByteArray(v) => write_seq(out, "B;", v, |out, v| _ = write!(out, "{v}b")),
+        Value::String(v) => write_string(out, v),
+        Value::List(v) => write_list(out, v),
+        Value::Compound(v) => write_compound(out, v),
+        Value::IntArray(v) => write_seq(out, "I;", v, |out, v| _ = write!(out, "{v}")),
+        Value::LongArray(v) => write_seq(out, "L;", v, |out, v| _ = write!(out, "{v}L")),
+    }
+}
+
+fn write_seq<T>(out: &mut String, prefix: &str, elems: &[T], mut f: impl FnMut(&mut String, &T)) {
+    out.push('[');
+    out.push_str(prefix);
+    for (i, elem) in elems.iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        f(out, elem);
+    }
+    out.push(']');
+}
+
+fn write_list(out: &mut String, list: &List) {
+    match list {
+        List::Byte(l) => write_seq(out, "", l, |out, v| write_value(out, &Value::Byte(*v))),
+        List::Short(l) => write_seq(out, "", l, |out, v| write_value(out, &Value::Short(*v))),
+        List::Int(l) => write_seq(out, "", l, |out, v| write_value(out, &Value::Int(*v))),
+        List::Long(l) => write_seq(out, "", l, |out, v| write_value(out, &Value::Long(*v))),
+        List::Float(l) => write_seq(out, "", l, |out, v| write_value(out, &Value::Float(*v))),
+        List::Double(l) => write_seq(out, "", l, |out, v| write_value(out, &Value::Double(*v))),
+        List::ByteArray(l) => write_seq(out, "", l, |out, v| {
+            write_seq(out, "B;", v, |out, v| _ = write!(out, "{v}b"))
+        }),
+        List::String(l) => write_seq(out, "", l, |out, v| write_string(out, v)),
+        List::List(l) => write_seq(out, "", l, write_list),
+        List::Compound(l) => write_seq(out, "", l, write_compound),
+        List::IntArray(l) => write_seq(out, "", l, |out, v| {
+            write_seq(out, "I;", v, |out, v| _ = write!(out, "{v}"))
+        }),
+        List::LongArray(l) => write_seq(out, "", l, |out, v| {
+            write_seq(out, "L;", v, |out, v| _ = write!(out, "{v}L"))
+        }),
+    }
+}
+
+fn write_compound(out: &mut String, compound: &Compound) {
+    out.push('{');
+    for (i, (name, value)) in compound.iter().enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+
+        if !name.is_empty() && name.chars().all(is_unquoted_char) {
+            out.push_str(name);
+        } else {
+            write_string(out, name);
+        }
+
+        out.push(':');
+        write_value(out, value);
+    }
+    out.push('}');
+}
+
+/// Writes a quoted string, preferring double quotes unless the string
+/// contains them.
+fn write_string(out: &mut String, s: &str) {
+    let quote = if s.contains('"') && !s.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+
+    out.push(quote);
+    for c in s.chars() {
+        if c == quote || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push(quote);
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: impl Into<String>) -> SnbtError {
+        SnbtError {
+            message: message.into(),
+            position: self.pos,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    /// Consumes `c` if it is the next non-whitespace character.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, SnbtError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("SNBT is nested too deeply"));
+        }
+
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('{') => self.compound(depth).map(Value::Compound),
+            Some('[') => self.list_or_array(depth),
+            Some('"' | '\'') => self.quoted_string().map(Value::String),
+            Some(_) => {
+                let word = self.unquoted_string()?;
+                Ok(parse_word(word))
+            }
+            None => Err(self.error("expected value")),
+        }
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<Compound, SnbtError> {
+        self.expect('{')?;
+        let mut compound = Compound::new();
+
+        if self.eat('}') {
+            return Ok(compound);
+        }
+
+        loop {
+            self.skip_whitespace();
+            let name = match self.peek() {
+                Some('"' | '\'') => self.quoted_string()?,
+                _ => self.unquoted_string()?.to_owned(),
+            };
+
+            self.expect(':')?;
+            let value = self.value(depth + 1)?;
+            compound.insert(name, value);
+
+            if self.eat('}') {
+                return Ok(compound);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn list_or_array(&mut self, depth: usize) -> Result<Value, SnbtError> {
+        self.expect('[')?;
+
+        for (prefix, kind) in [("B;", 'B'), ("I;", 'I'), ("L;", 'L')] {
+            if self.rest().starts_with(prefix) {
+                self.pos += prefix.len();
+                return self.array(kind);
+            }
+        }
+
+        let start = self.pos;
+        let mut elems = Vec::new();
+
+        if !self.eat(']') {
+            loop {
+                elems.push(self.value(depth + 1)?);
+                if self.eat(']') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+
+        match to_list(elems) {
+            Some(list) => Ok(Value::List(list)),
+            None => {
+                self.pos = start;
+                Err(self.error("list elements must all have the same type"))
+            }
+        }
+    }
+
+    fn array(&mut self, kind: char) -> Result<Value, SnbtError> {
+        let mut elems = Vec::new();
+
+        if !self.eat(']') {
+            loop {
+                self.skip_whitespace();
+                let start = self.pos;
+                let value = match parse_word(self.unquoted_string()?) {
+                    Value::Byte(v) if kind == 'B' => v.into(),
+                    Value::Int(v) if kind == 'I' => v.into(),
+                    Value::Long(v) if kind == 'L' => v,
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error(format!("invalid element in {kind} array")));
+                    }
+                };
+                elems.push(value);
+
+                if self.eat(']') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+
+        Ok(match kind {
+            'B' => Value::ByteArray(elems.into_iter().map(|v| v as i8).collect()),
+            'I' => Value::IntArray(elems.into_iter().map(|v| v as i32).collect()),
+            _ => Value::LongArray(elems),
+        })
+    }
+
+    fn quoted_string(&mut self) -> Result<String, SnbtError> {
+        let quote = self.peek().expect("string must start with a quote");
+        self.pos += 1;
+
+        let mut s = String::new();
+        let mut chars = self.rest().char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, c @ ('\\' | '"' | '\''))) => s.push(c),
+                    _ => {
+                        self.pos += i;
+                        return Err(self.error("invalid escape sequence"));
+                    }
+                },
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(s);
+                }
+                c => s.push(c),
+            }
+        }
+
+        Err(self.error("unterminated string"))
+    }
+
+    fn unquoted_string(&mut self) -> Result<&'a str, SnbtError> {
+        let rest = self.rest();
+        let len = rest.find(|c| !is_unquoted_char(c)).unwrap_or(rest.len());
+
+        if len == 0 {
+            return Err(self.error("expected value"));
+        }
+
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+}
+
+/// Interprets an unquoted word as a number, boolean or string.
+fn parse_word(word: &str) -> Value {
+    match word {
+        "true" => return Value::Byte(1),
+        "false" => return Value::Byte(0),
+        _ => {}
+    }
+
+    let (num, suffix) = match word.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&word[..i], Some(c.to_ascii_lowercase())),
+        _ => (word, None),
+    };
+
+    let is_integer = |s: &str| {
+        let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    };
+    let is_decimal = |s: &str| {
+        s.chars()
+            .any(|c| c.is_ascii_digit())
+            .then(|| s.parse::<f64>().ok())
+            .flatten()
+    };
+
+    let parsed = match suffix {
+        Some('b') if is_integer(num) => num.parse().ok().map(Value::Byte),
+        Some('s') if is_integer(num) => num.parse().ok().map(Value::Short),
+        Some('l') if is_integer(num) => num.parse().ok().map(Value::Long),
+        Some('f') => is_decimal(num).map(|v| Value::Float(v as f32)),
+        Some('d') => is_decimal(num).map(Value::Double),
+        None if is_integer(num) => num.parse().ok().map(Value::Int),
+        None if num.contains(['.', 'e', 'E']) => is_decimal(num).map(Value::Double),
+        _ => None,
+    };
+
+    parsed.unwrap_or_else(|| Value::String(word.to_owned()))
+}
+
+/// Converts parsed values to a list, or returns `None` if they do not all have
+/// the same type.
+fn to_list(elems: Vec<Value>) -> Option<List> {
+    macro_rules! collect {
+        ($variant:ident) => {
+            elems
+                .into_iter()
+                .map(|e| match e {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                })
+                .collect::<Option<_>>()
+                .map(List::$variant)
+        };
+    }
+
+    match elems.first() {
+        None | Some(Value::Byte(_)) => collect!(Byte),
+        Some(Value::Short(_)) => collect!(Short),
+        Some(Value::Int(_)) => collect!(Int),
+        Some(Value::Long(_)) => collect!(Long),
+        Some(Value::Float(_)) => collect!(Float),
+        Some(Value::Double(_)) => collect!(Double),
+        Some(Value::ByteArray(_)) => collect!(ByteArray),
+        Some(Value::String(_)) => collect!(String),
+        Some(Value::List(_)) => collect!(List),
+        Some(Value::Compound(_)) => collect!(Compound),
+        Some(Value::IntArray(_)) => collect!(IntArray),
+        Some(Value::LongArray(_)) => collect!(LongArray),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_print() {
+        let snbt = r#"{
+            Count: 3b,
+            id: "minecraft:diamond_sword",
+            tag: {
+                Damage: 10,
+                Enchantments: [{id: sharpness, lvl: 5s}],
+                "display name": 'say "hi"',
+                Scale: 1.5f,
+                Speed: 0.25,
+                Big: 123L,
+                Ints: [I; 1, -2, 3],
+                Unbreakable: true
+            }
+        }"#;
+
+        let compound = compound_from_snbt(snbt).unwrap();
+        let tag = match &compound["tag"] {
+            Value::Compound(tag) => tag,
+            other => panic!("unexpected tag {other:?}"),
+        };
+
+        assert_eq!(compound["Count"], Value::Byte(3));
+        assert_eq!(tag["Damage"], Value::Int(10));
+        assert_eq!(tag["display name"], Value::String("say \"hi\"".into()));
+        assert_eq!(tag["Scale"], Value::Float(1.5));
+        assert_eq!(tag["Speed"], Value::Double(0.25));
+        assert_eq!(tag["Big"], Value::Long(123));
+        assert_eq!(tag["Ints"], Value::IntArray(vec![1, -2, 3]));
+        assert_eq!(tag["Unbreakable"], Value::Byte(1));
+        assert!(matches!(&tag["Enchantments"], Value::List(List::Compound(l)) if l.len() == 1));
+
+        let printed = compound_to_snbt(&compound);
+        assert_eq!(compound_from_snbt(&printed).unwrap(), compound);
+    }
+
+    #[test]
+    fn invalid_snbt() {
+        assert!(from_snbt("{a:1,}").is_err());
+        assert!(from_snbt("[1, 2b]").is_err());
+        assert!(from_snbt("\"unterminated").is_err());
+        assert!(from_snbt("[B; 1, 2]").is_err());
+        assert_eq!(from_snbt("{a:1} x").unwrap_err().position(), 6);
+    }
+}
@@ -12,6 +12,7 @@ use bitvec::prelude::BitVec;
 use num::{One, Zero};
 use paste::paste;
 use serde::{Deserialize, Serialize};
+use tracing::Level;
 use uuid::Uuid;
 use vek::Vec3;
 
@@ -19,11 +20,11 @@ use vek::Vec3;
 use crate::block_pos::BlockPos;
 use crate::ident::Ident;
 use crate::nbt::Compound;
-use crate::protocol::{
-    BoundedArray, BoundedInt, BoundedString, ByteAngle, Decode, Encode, NbtBridge, RawBytes,
-    VarInt, VarLong,
-};
 use crate::text::Text;
+use crate::{
+    encode_string_bounded, BoundedArray, BoundedInt, BoundedString, ByteAngle, Decode,
+    DecodeLimits, Encode, NbtBridge, RawBytes, VarInt, VarLong,
+};
 
 /// Trait for types that can be written to the Minecraft protocol as a complete
 /// packet.
@@ -43,6 +44,36 @@ pub trait EncodePacket: fmt::Debug {
 pub trait DecodePacket: Sized + fmt::Debug {
     /// Reads a packet from the Minecraft protocol, including its packet ID.
     fn decode_packet(r: &mut impl Read) -> anyhow::Result<Self>;
+
+    /// Reads a packet from a slice containing exactly one packet, including
+    /// its packet ID but not its length prefix.
+    ///
+    /// The packet is decoded with `limits` in effect. An error is returned if
+    /// the slice is longer than the maximum packet size or not all of it is
+    /// decoded. This never panics on malformed input, which makes it suitable
+    /// as a fuzzing entry point.
+    fn decode_packet_from_slice(mut bytes: &[u8], limits: DecodeLimits) -> anyhow::Result<Self> {
+        ensure!(
+            bytes.len() <= limits.max_packet_size,
+            "packet of {} bytes exceeds the maximum packet size",
+            bytes.len()
+        );
+
+        let packet = limits.scope(|| Self::decode_packet(&mut bytes))?;
+
+        if !bytes.is_empty() {
+            if tracing::enabled!(Level::DEBUG) {
+                tracing::debug!("complete packet after partial decode: {packet:?}");
+            }
+
+            bail!(
+                "packet contents were not decoded completely ({} bytes remaining)",
+                bytes.len()
+            );
+        }
+
+        Ok(packet)
+    }
 }
 
 /// Defines a struct which implements [`Encode`] and [`Decode`].
@@ -285,6 +316,17 @@ macro_rules! def_packet_group {
             $($packet($packet)),*
         }
 
+        impl $group_name {
+            /// Gets the name of the packet, such as `"KeepAlive"`.
+            pub fn packet_name(&self) -> &'static str {
+                match self {
+                    $(
+                        Self::$packet(_) => stringify!($packet),
+                    )*
+                }
+            }
+        }
+
         $(
             impl From<$packet> for $group_name {
                 fn from(p: $packet) -> Self {
@@ -387,6 +429,7 @@ def_struct! {
 }
 
 #[cfg(test)]
+#[allow(dead_code)]
 pub(crate) mod test {
     use super::*;
 
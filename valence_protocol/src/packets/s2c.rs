@@ -227,6 +227,148 @@ pub mod play {
         }
     }
 
+    def_struct! {
+        Commands {
+            nodes: Vec<CommandNode>,
+            root_index: VarInt,
+        }
+    }
+
+    /// A node in the command graph of a [`Commands`] packet.
+    #[derive(Clone, Debug)]
+    pub struct CommandNode {
+        pub children: Vec<VarInt>,
+        pub redirect_node: Option<VarInt>,
+        pub executable: bool,
+        pub data: CommandNodeData,
+    }
+
+    #[derive(Clone, Debug)]
+    pub enum CommandNodeData {
+        Root,
+        Literal {
+            name: String,
+        },
+        Argument {
+            name: String,
+            parser: CommandParser,
+            suggestion: Option<Ident>,
+        },
+    }
+
+    impl Encode for CommandNode {
+        fn encode(&self, w: &mut impl Write) -> anyhow::Result<()> {
+            let node_type = match &self.data {
+                CommandNodeData::Root => 0,
+                CommandNodeData::Literal { .. } => 1,
+                CommandNodeData::Argument { .. } => 2,
+            };
+
+            let has_suggestion = matches!(
+                &self.data,
+                CommandNodeData::Argument {
+                    suggestion: Some(_),
+                    ..
+                }
+            );
+
+            let flags: u8 = node_type
+                | (self.executable as u8) << 2
+                | (self.redirect_node.is_some() as u8) << 3
+                | (has_suggestion as u8) << 4;
+
+            flags.encode(w)?;
+            self.children.encode(w)?;
+
+            if let Some(redirect_node) = self.redirect_node {
+                redirect_node.encode(w)?;
+            }
+
+            match &self.data {
+                CommandNodeData::Root => {}
+                CommandNodeData::Literal { name } => name.encode(w)?,
+                CommandNodeData::Argument {
+                    name,
+                    parser,
+                    suggestion,
+                } => {
+                    name.encode(w)?;
+                    parser.encode(w)?;
+                    if let Some(suggestion) = suggestion {
+                        suggestion.encode(w)?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Decode for CommandNode {
+        fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
+            let flags = u8::decode(r)?;
+            let children = Vec::decode(r)?;
+
+            let redirect_node = if flags & 0x08 != 0 {
+                Some(VarInt::decode(r)?)
+            } else {
+                None
+            };
+
+            let data = match flags & 0x03 {
+                0 => CommandNodeData::Root,
+                1 => CommandNodeData::Literal {
+                    name: String::decode(r)?,
+                },
+                2 => CommandNodeData::Argument {
+                    name: String::decode(r)?,
+                    parser: CommandParser::decode(r)?,
+                    suggestion: if flags & 0x10 != 0 {
+                        Some(Ident::decode(r)?)
+                    } else {
+                        None
+                    },
+                },
+                n => bail!("invalid command node type of {n}"),
+            };
+
+            Ok(Self {
+                children,
+                redirect_node,
+                executable: flags & 0x04 != 0,
+                data,
+            })
+        }
+    }
+
+    def_enum! {
+        CommandParser: VarInt {
+            String: StringArg = 5,
+            Entity: EntityArgFlags = 6,
+            BlockPos = 8,
+            ItemStack = 14,
+            Color = 16,
+        }
+    }
+
+    def_bitfield! {
+        EntityArgFlags: u8 {
+            /// Only one entity can be selected.
+            single = 0,
+            /// Only players can be selected.
+            players_only = 1,
+        }
+    }
+
+    def_enum! {
+        #[derive(Copy, PartialEq, Eq)]
+        StringArg: VarInt {
+            SingleWord = 0,
+            QuotablePhrase = 1,
+            GreedyPhrase = 2,
+        }
+    }
+
     def_enum! {
         SoundCategory: VarInt {
             Master = 0,
@@ -348,6 +490,17 @@ pub mod play {
         }
     }
 
+    def_struct! {
+        WorldEvent {
+            event: i32,
+            location: BlockPos,
+            data: i32,
+            /// If the sound is played at the same volume regardless of the
+            /// distance to the event.
+            disable_relative_volume: bool,
+        }
+    }
+
     def_struct! {
         GameJoin {
             /// Entity ID of the joining player
@@ -449,7 +602,6 @@ pub mod play {
         pub category: String,
         pub temperature_modifier: Option<String>,
         pub effects: BiomeEffects,
-        pub particle: Option<BiomeParticle>,
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -465,6 +617,7 @@ pub mod play {
         pub ambient_sound: Option<Ident>,
         pub additions_sound: Option<BiomeAdditionsSound>,
         pub mood_sound: Option<BiomeMoodSound>,
+        pub particle: Option<BiomeParticle>,
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -492,13 +645,8 @@ pub mod play {
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct BiomeParticle {
         pub probability: f32,
-        pub options: BiomeParticleOptions,
-    }
-
-    #[derive(Clone, Debug, Serialize, Deserialize)]
-    pub struct BiomeParticleOptions {
-        #[serde(rename = "type")]
-        pub kind: Ident,
+        /// The particle type in the `type` tag and the particle's data.
+        pub options: Compound,
     }
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -530,7 +678,7 @@ pub mod play {
     }
 
     def_enum! {
-        #[derive(Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+        #[derive(Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
         GameMode: u8 {
             #[default]
             Survival = 0,
@@ -610,6 +758,23 @@ pub mod play {
         }
     }
 
+    def_struct! {
+        LookAt {
+            anchor: EntityAnchor,
+            target: Vec3<f64>,
+            /// The entity to look at and its part to look at. If present, this
+            /// takes precedence over `target`.
+            entity: Option<(VarInt, EntityAnchor)>,
+        }
+    }
+
+    def_enum! {
+        EntityAnchor: VarInt {
+            Feet = 0,
+            Eyes = 1,
+        }
+    }
+
     def_bitfield! {
         PlayerPositionLookFlags: u8 {
             x = 0,
@@ -687,6 +852,22 @@ pub mod play {
         }
     }
 
+    def_struct! {
+        ScoreboardDisplay {
+            position: ScoreboardPosition,
+            score_name: BoundedString<0, 16>,
+        }
+    }
+
+    def_enum! {
+        #[derive(Copy, PartialEq, Eq)]
+        ScoreboardPosition: i8 {
+            List = 0,
+            Sidebar = 1,
+            BelowName = 2,
+        }
+    }
+
     def_struct! {
         EntityTrackerUpdate {
             entity_id: VarInt,
@@ -701,6 +882,212 @@ pub mod play {
         }
     }
 
+    def_struct! {
+        ScoreboardObjectiveUpdate {
+            objective_name: BoundedString<0, 16>,
+            mode: ScoreboardObjectiveUpdateMode,
+        }
+    }
+
+    def_enum! {
+        ScoreboardObjectiveUpdateMode: i8 {
+            Create: ScoreboardObjective = 0,
+            Remove = 1,
+            Update: ScoreboardObjective = 2,
+        }
+    }
+
+    def_struct! {
+        ScoreboardObjective {
+            display_name: Text,
+            render_type: ScoreboardRenderType,
+        }
+    }
+
+    def_enum! {
+        #[derive(Copy, PartialEq, Eq)]
+        ScoreboardRenderType: VarInt {
+            Integer = 0,
+            Hearts = 1,
+        }
+    }
+
+    def_struct! {
+        Team {
+            team_name: BoundedString<0, 16>,
+            mode: TeamMode,
+        }
+    }
+
+    def_enum! {
+        TeamMode: i8 {
+            Create: Box<TeamCreate> = 0,
+            Remove = 1,
+            UpdateInfo: Box<TeamInfo> = 2,
+            AddEntities: Vec<BoundedString<0, 40>> = 3,
+            RemoveEntities: Vec<BoundedString<0, 40>> = 4,
+        }
+    }
+
+    def_struct! {
+        TeamCreate {
+            info: TeamInfo,
+            /// Usernames of players and UUIDs of other entities in the team.
+            entities: Vec<BoundedString<0, 40>>,
+        }
+    }
+
+    def_struct! {
+        TeamInfo {
+            display_name: Text,
+            flags: TeamFlags,
+            name_tag_visibility: TeamVisibility,
+            collision_rule: TeamCollisionRule,
+            color: TeamColor,
+            prefix: Text,
+            suffix: Text,
+        }
+    }
+
+    def_bitfield! {
+        TeamFlags: u8 {
+            friendly_fire = 0,
+            see_invisible_teammates = 1,
+        }
+    }
+
+    /// Controls who can see the name tags of a team's members.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum TeamVisibility {
+        Always,
+        HideForOtherTeams,
+        HideForOwnTeam,
+        Never,
+    }
+
+    impl Encode for TeamVisibility {
+        fn encode(&self, w: &mut impl Write) -> anyhow::Result<()> {
+            let s = match self {
+                TeamVisibility::Always => "always",
+                TeamVisibility::HideForOtherTeams => "hideForOtherTeams",
+                TeamVisibility::HideForOwnTeam => "hideForOwnTeam",
+                TeamVisibility::Never => "never",
+            };
+
+            encode_string_bounded(s, 0, 40, w)
+        }
+    }
+
+    impl Decode for TeamVisibility {
+        fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
+            Ok(match BoundedString::<0, 40>::decode(r)?.0.as_str() {
+                "always" => TeamVisibility::Always,
+                "hideForOtherTeams" => TeamVisibility::HideForOtherTeams,
+                "hideForOwnTeam" => TeamVisibility::HideForOwnTeam,
+                "never" => TeamVisibility::Never,
+                other => bail!("unknown team visibility \"{other}\""),
+            })
+        }
+    }
+
+    /// Controls which entities a team's members are pushed by.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum TeamCollisionRule {
+        Always,
+        PushOtherTeams,
+        PushOwnTeam,
+        Never,
+    }
+
+    impl Encode for TeamCollisionRule {
+        fn encode(&self, w: &mut impl Write) -> anyhow::Result<()> {
+            let s = match self {
+                TeamCollisionRule::Always => "always",
+                TeamCollisionRule::PushOtherTeams => "pushOtherTeams",
+                TeamCollisionRule::PushOwnTeam => "pushOwnTeam",
+                TeamCollisionRule::Never => "never",
+            };
+
+            encode_string_bounded(s, 0, 40, w)
+        }
+    }
+
+    impl Decode for TeamCollisionRule {
+        fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
+            Ok(match BoundedString::<0, 40>::decode(r)?.0.as_str() {
+                "always" => TeamCollisionRule::Always,
+                "pushOtherTeams" => TeamCollisionRule::PushOtherTeams,
+                "pushOwnTeam" => TeamCollisionRule::PushOwnTeam,
+                "never" => TeamCollisionRule::Never,
+                other => bail!("unknown team collision rule \"{other}\""),
+            })
+        }
+    }
+
+    def_enum! {
+        #[derive(Copy, PartialEq, Eq)]
+        TeamColor: VarInt {
+            Black = 0,
+            DarkBlue = 1,
+            DarkGreen = 2,
+            DarkAqua = 3,
+            DarkRed = 4,
+            DarkPurple = 5,
+            Gold = 6,
+            Gray = 7,
+            DarkGray = 8,
+            Blue = 9,
+            Green = 10,
+            Aqua = 11,
+            Red = 12,
+            LightPurple = 13,
+            Yellow = 14,
+            White = 15,
+            Reset = 21,
+        }
+    }
+
+    /// Sets or removes the score of an entity in an objective.
+    #[derive(Clone, Debug)]
+    pub struct ScoreboardPlayerUpdate {
+        /// The username of a player or UUID of another entity.
+        pub entity_name: BoundedString<0, 40>,
+        pub objective_name: BoundedString<0, 16>,
+        /// The new score, or `None` to remove the score.
+        pub value: Option<VarInt>,
+    }
+
+    impl Encode for ScoreboardPlayerUpdate {
+        fn encode(&self, w: &mut impl Write) -> anyhow::Result<()> {
+            self.entity_name.encode(w)?;
+            VarInt(self.value.is_none() as i32).encode(w)?;
+            self.objective_name.encode(w)?;
+            if let Some(value) = self.value {
+                value.encode(w)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Decode for ScoreboardPlayerUpdate {
+        fn decode(r: &mut impl Read) -> anyhow::Result<Self> {
+            let entity_name = BoundedString::decode(r)?;
+            let action = VarInt::decode(r)?;
+            let objective_name = BoundedString::decode(r)?;
+            let value = match action.0 {
+                0 => Some(VarInt::decode(r)?),
+                1 => None,
+                n => bail!("bad scoreboard player update action {n}"),
+            };
+
+            Ok(Self {
+                entity_name,
+                objective_name,
+                value,
+            })
+        }
+    }
+
     def_struct! {
         UpdateSubtitle {
             subtitle_text: Text,
@@ -818,6 +1205,7 @@ pub mod play {
             BlockUpdate = 9,
             BossBar = 10,
             ClearTitles = 13,
+            Commands = 15,
             PlaySoundId = 23,
             Disconnect = 25,
             EntityStatus = 26,
@@ -825,12 +1213,14 @@ pub mod play {
             GameStateChange = 29,
             KeepAlive = 32,
             ChunkData = 33,
+            WorldEvent = 34,
             GameJoin = 37,
             MoveRelative = 40,
             RotateAndMoveRelative = 41,
             Rotate = 42,
             ChatMessage = 51,
             UpdatePlayerList = 55,
+            LookAt = 56,
             PlayerPositionLook = 57,
             EntitiesDestroy = 59,
             PlayerRespawn = 62,
@@ -841,8 +1231,12 @@ pub mod play {
             ChunkRenderDistanceCenter = 75,
             ChunkLoadDistance = 76,
             PlayerSpawnPosition = 77,
+            ScoreboardDisplay = 79,
             EntityTrackerUpdate = 80,
             EntityVelocityUpdate = 82,
+            ScoreboardObjectiveUpdate = 86,
+            Team = 88,
+            ScoreboardPlayerUpdate = 89,
             UpdateSubtitle = 91,
             WorldTimeUpdate = 92,
             UpdateTitle = 93,
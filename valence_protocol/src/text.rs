@@ -8,7 +8,7 @@ use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::ident::Ident;
-use crate::protocol::{BoundedString, Decode, Encode};
+use crate::{BoundedString, Decode, Encode};
 
 /// Represents formatted text in Minecraft's JSON text format.
 ///
@@ -26,7 +26,7 @@ use crate::protocol::{BoundedString, Decode, Encode};
 ///
 /// With [`TextFormat`] in scope, you can write the following:
 /// ```
-/// use valence::text::{Color, Text, TextFormat};
+/// use valence_protocol::text::{Color, Text, TextFormat};
 ///
 /// let txt = "The text is ".into_text()
 ///     + "Red".color(Color::RED)
@@ -125,6 +125,15 @@ impl Text {
         Ok(())
     }
 
+    /// Removes the color from this text object and all of its children.
+    pub fn strip_colors(&mut self) {
+        self.color = None;
+
+        for child in &mut self.extra {
+            child.strip_colors();
+        }
+    }
+
     /// Returns `true` if the text contains no characters. Returns `false`
     /// otherwise.
     pub fn is_empty(&self) -> bool {
@@ -490,6 +499,30 @@ impl Color {
     pub const fn new(r: u8, g: u8, b: u8) -> Self {
         Self { r, g, b }
     }
+
+    /// Gets the named color with the given name, such as `dark_red`. Returns
+    /// `None` if no named color has the name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "aqua" => Some(Color::AQUA),
+            "black" => Some(Color::BLACK),
+            "blue" => Some(Color::BLUE),
+            "dark_aqua" => Some(Color::DARK_AQUA),
+            "dark_blue" => Some(Color::DARK_BLUE),
+            "dark_gray" => Some(Color::DARK_GRAY),
+            "dark_green" => Some(Color::DARK_GREEN),
+            "dark_purple" => Some(Color::DARK_PURPLE),
+            "dark_red" => Some(Color::DARK_RED),
+            "gold" => Some(Color::GOLD),
+            "gray" => Some(Color::GRAY),
+            "green" => Some(Color::GREEN),
+            "light_purple" => Some(Color::LIGHT_PURPLE),
+            "red" => Some(Color::RED),
+            "white" => Some(Color::WHITE),
+            "yellow" => Some(Color::YELLOW),
+            _ => None,
+        }
+    }
 }
 
 impl Serialize for Color {
@@ -532,25 +565,7 @@ fn color_from_str(s: &str) -> Option<Color> {
             g: to_num(*g0)? << 4 | to_num(*g1)?,
             b: to_num(*b0)? << 4 | to_num(*b1)?,
         }),
-        _ => match s {
-            "aqua" => Some(Color::AQUA),
-            "black" => Some(Color::BLACK),
-            "blue" => Some(Color::BLUE),
-            "dark_aqua" => Some(Color::DARK_AQUA),
-            "dark_blue" => Some(Color::DARK_BLUE),
-            "dark_gray" => Some(Color::DARK_GRAY),
-            "dark_green" => Some(Color::DARK_GREEN),
-            "dark_purple" => Some(Color::DARK_PURPLE),
-            "dark_red" => Some(Color::DARK_RED),
-            "gold" => Some(Color::GOLD),
-            "gray" => Some(Color::GRAY),
-            "green" => Some(Color::GREEN),
-            "light_purple" => Some(Color::LIGHT_PURPLE),
-            "red" => Some(Color::RED),
-            "white" => Some(Color::WHITE),
-            "yellow" => Some(Color::YELLOW),
-            _ => None,
-        },
+        _ => Color::from_name(s),
     }
 }
 
@@ -593,6 +608,14 @@ mod tests {
         assert_eq!(color_from_str("blue"), Some(Color::BLUE));
     }
 
+    #[test]
+    fn strip_colors() {
+        let mut txt = "foo".color(Color::RED).bold() + ("bar".color(Color::BLUE) + "baz");
+        txt.strip_colors();
+
+        assert_eq!(txt, "foo".bold() + ("bar".into_text() + "baz"));
+    }
+
     #[test]
     fn empty() {
         assert!("".into_text().is_empty());
@@ -3,7 +3,7 @@ use std::io::{Read, Write};
 use anyhow::bail;
 use byteorder::{ReadBytesExt, WriteBytesExt};
 
-use crate::protocol::{Decode, Encode};
+use crate::{Decode, Encode};
 
 /// An `i32` encoded with variable length.
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -51,6 +51,10 @@ impl Decode for VarInt {
         let mut val = 0;
         for i in 0..Self::MAX_SIZE {
             let byte = r.read_u8()?;
+            // The last byte only has room for the four most significant bits.
+            if i == Self::MAX_SIZE - 1 && byte & 0b11110000 != 0 {
+                bail!("VarInt is too large");
+            }
             val |= (byte as i32 & 0b01111111) << (i * 7);
             if byte & 0b10000000 == 0 {
                 return Ok(VarInt(val));
@@ -120,4 +124,16 @@ mod tests {
             buf.clear();
         }
     }
+
+    #[test]
+    fn decode_rejects_overlong() {
+        let mut r: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0x0f];
+        assert_eq!(VarInt::decode(&mut r).unwrap().0, -1);
+
+        let mut r: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0x1f];
+        assert!(VarInt::decode(&mut r).is_err());
+
+        let mut r: &[u8] = &[0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+        assert!(VarInt::decode(&mut r).is_err());
+    }
 }
@@ -3,11 +3,11 @@ use std::io::{Read, Write};
 use anyhow::bail;
 use byteorder::{ReadBytesExt, WriteBytesExt};
 
-use crate::protocol::{Decode, Encode};
+use crate::{Decode, Encode};
 
 /// An `i64` encoded with variable length.
 #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
-pub struct VarLong(pub(crate) i64);
+pub struct VarLong(pub i64);
 
 impl VarLong {
     /// The maximum number of bytes a `VarLong` can occupy when read from and
@@ -34,6 +34,10 @@ impl Decode for VarLong {
         let mut val = 0;
         for i in 0..Self::MAX_SIZE {
             let byte = r.read_u8()?;
+            // The last byte only has room for the most significant bit.
+            if i == Self::MAX_SIZE - 1 && byte & 0b11111110 != 0 {
+                bail!("VarLong is too large");
+            }
             val |= (byte as i64 & 0b01111111) << (i * 7);
             if byte & 0b10000000 == 0 {
                 return Ok(VarLong(val));